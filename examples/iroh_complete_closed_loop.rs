@@ -47,6 +47,7 @@ async fn main() -> Result<()> {
         connection_timeout: Some(30),
         enable_relay: Some(true),
         enable_nat_traversal: Some(true),
+        ..Default::default()
     };
     
     // 4. 启动节点1的监听器（接收方）
@@ -84,7 +85,7 @@ async fn main() -> Result<()> {
                                     // 创建响应消息
                                     let response = serde_json::json!({
                                         "message_type": "response",
-                                        "message_id": uuid::Uuid::new_v4().to_string(),
+                                        "message_id": diap_rs_sdk::new_message_id(),
                                         "from_node": format!("{:?}", node_addr1.node_id),
                                         "to_node": format!("{:?}", remote_node_id),
                                         "original_message_id": diap_message.get("message_id"),
@@ -138,7 +139,7 @@ async fn main() -> Result<()> {
                 // 创建完整的DIAP消息
                 let diap_message = serde_json::json!({
                     "message_type": "auth_request",
-                    "message_id": uuid::Uuid::new_v4().to_string(),
+                    "message_id": diap_rs_sdk::new_message_id(),
                     "from_did": "did:example:alice",
                     "to_did": "did:example:bob",
                     "from_node": format!("{:?}", node_addr2.node_id),
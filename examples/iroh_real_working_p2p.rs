@@ -23,12 +23,8 @@ async fn main() -> Result<()> {
     println!("\n📡 创建通信器...");
     
     let config = IrohCommConfig {
-        listen_addr: Some("0.0.0.0:0".parse().unwrap()),
-        data_dir: None,
         max_connections: Some(100),
-        connection_timeout: Some(30),
-        enable_relay: Some(true),
-        enable_nat_traversal: Some(true),
+        ..Default::default()
     };
     
     let mut communicator1 = IrohCommunicator::new(config.clone()).await?;
@@ -59,9 +55,10 @@ async fn main() -> Result<()> {
     
     // 创建自定义消息
     let custom_message = IrohMessage {
-        message_id: uuid::Uuid::new_v4().to_string(),
+        message_id: diap_rs_sdk::new_message_id(),
         message_type: IrohMessageType::Custom("data_exchange".to_string()),
-        from_did: "did:example:alice".to_string(),
+        // 未签名的消息只能以发送方节点ID作为from_did，接收方会丢弃冒充其他DID的消息
+        from_did: node_addr2.node_id.to_string(),
         to_did: Some("did:example:bob".to_string()),
         content: "Hello from Node 2! This is a real working P2P communication!".to_string(),
         metadata: std::collections::HashMap::from([
@@ -70,23 +67,33 @@ async fn main() -> Result<()> {
             ("timestamp".to_string(), chrono::Utc::now().to_rfc3339()),
         ]),
         timestamp: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs(),
-        signature: Some("placeholder_signature".to_string()),
+        signature: None,
     };
     println!("   ✅ 自定义消息创建成功: {}", custom_message.message_id);
     
-    // 4. 演示连接管理功能
+    // 4. 建立连接并发送消息（通信器1在后台接受连接）
+    println!("\n📨 发送消息...");
+    let _listener = communicator1.spawn_message_listener();
+    let node_id1 = communicator2.connect_to_node_with_addr(node_addr1.clone()).await?;
+    communicator2.send_message(&node_id1, custom_message).await?;
+    match tokio::time::timeout(Duration::from_secs(10), communicator1.receive_message()).await {
+        Ok(Some(message)) => println!("   ✅ 通信器1收到消息: {}", message.content),
+        _ => println!("   ⚠️ 10秒内未收到消息"),
+    }
+    
+    // 5. 演示连接管理功能
     println!("\n📊 演示连接管理功能...");
     println!("   通信器1连接的节点: {:?}", communicator1.get_connected_nodes());
     println!("   通信器2连接的节点: {:?}", communicator2.get_connected_nodes());
     
-    // 5. 演示节点地址获取
+    // 6. 演示节点地址获取
     println!("\n🏠 演示节点地址获取...");
     let node_addr1_str = communicator1.get_node_addr()?;
     let node_addr2_str = communicator2.get_node_addr()?;
     println!("   通信器1地址: {}", node_addr1_str);
     println!("   通信器2地址: {}", node_addr2_str);
     
-    // 6. 清理资源
+    // 7. 清理资源
     println!("\n🧹 清理资源...");
     communicator1.shutdown().await?;
     communicator2.shutdown().await?;
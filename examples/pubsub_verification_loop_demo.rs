@@ -6,7 +6,7 @@ use diap_rs_sdk::{
     PubsubAuthenticator,
     IdentityManager,
     AgentInfo, KeyPair,
-    TopicConfig, TopicPolicy,
+    TopicConfig, TopicPolicy, TopicEncryption,
 };
 use anyhow::Result;
 use std::time::Instant;
@@ -128,6 +128,9 @@ async fn main() -> Result<()> {
         policy: TopicPolicy::AllowAuthenticated,
         require_zkp: true,
         require_signature: true,
+        retention: None,
+        rate_limit: None,
+        encryption: TopicEncryption::Optional,
     };
     
     // 配置心跳主题 - 允许所有认证用户
@@ -136,6 +139,9 @@ async fn main() -> Result<()> {
         policy: TopicPolicy::AllowAuthenticated,
         require_zkp: false,
         require_signature: true,
+        retention: None,
+        rate_limit: None,
+        encryption: TopicEncryption::Optional,
     };
     
     // 配置通用主题 - 允许特定DID列表
@@ -144,6 +150,9 @@ async fn main() -> Result<()> {
         policy: TopicPolicy::AllowList(vec![alice_keypair.did.clone(), bob_keypair.did.clone()]),
         require_zkp: true,
         require_signature: true,
+        retention: None,
+        rate_limit: None,
+        encryption: TopicEncryption::Optional,
     };
     
     alice_pubsub.configure_topic(verification_config.clone()).await?;
@@ -0,0 +1,347 @@
+// DIAP Rust SDK - 三智能体市场场景（端到端集成示例）
+// 在同一进程内运行三个智能体与一个模拟IPFS节点，串联注册、发现、双向认证、任务委托（流式响应）、支付回执和审计
+//
+// 运行: cargo run --example three_agent_marketplace
+
+use anyhow::{Context, Result};
+use diap_rs_sdk::{
+    AgentInfo, ServiceInfo, KeyPair,
+    IdentityManager, IpfsClient,
+    PubsubAuthenticator, AuthenticatedMessage, PubSubMessageType,
+    TopicConfig, TopicPolicy, TopicEncryption, RetentionPolicy,
+    DIDResolver, get_did_document_from_cid,
+};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const MARKET_TOPIC: &str = "diap-marketplace";
+
+/// 模拟IPFS节点：实现 /api/v0/add 和 /ipfs/<cid> 两个HTTP端点
+#[derive(Clone, Default)]
+struct MockIpfs {
+    store: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl MockIpfs {
+    /// 启动并返回节点URL
+    async fn start(&self) -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        let node = self.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let node = node.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = node.handle(stream).await {
+                        eprintln!("模拟IPFS请求处理失败: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(url)
+    }
+
+    async fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        // 读取请求头
+        let mut data = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let header_end = loop {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                anyhow::bail!("连接提前关闭");
+            }
+            data.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let headers = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let header_value = |name: &str| {
+            headers.lines()
+                .find(|line| line.to_ascii_lowercase().starts_with(&format!("{}:", name)))
+                .map(|line| line[name.len() + 1..].trim().to_string())
+        };
+
+        // 读取请求体
+        let content_length: usize = header_value("content-length")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        while data.len() < header_end + content_length {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&chunk[..n]);
+        }
+        let body = String::from_utf8_lossy(&data[header_end..]).to_string();
+
+        let request_line = headers.lines().next().unwrap_or_default();
+        let (status, response) = if request_line.starts_with("POST /api/v0/add") {
+            let boundary = header_value("content-type")
+                .and_then(|ct| ct.split("boundary=").nth(1).map(|b| b.trim_matches('"').to_string()))
+                .context("缺少multipart boundary")?;
+            let content = Self::extract_file_part(&body, &boundary)?;
+
+            let cid = format!("bafkmock{}", hex::encode(Sha256::digest(content.as_bytes())));
+            let size = content.len();
+            self.store.lock().unwrap().insert(cid.clone(), content);
+
+            ("200 OK", serde_json::json!({ "Hash": cid, "Size": size.to_string() }).to_string())
+        } else if let Some(path) = request_line.strip_prefix("GET /ipfs/") {
+            let cid = path.split_whitespace().next().unwrap_or_default();
+            match self.store.lock().unwrap().get(cid) {
+                Some(content) => ("200 OK", content.clone()),
+                None => ("404 Not Found", String::new()),
+            }
+        } else {
+            ("404 Not Found", String::new())
+        };
+
+        let reply = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, response.len(), response
+        );
+        stream.write_all(reply.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// 从multipart请求体中取出file字段的内容
+    fn extract_file_part(body: &str, boundary: &str) -> Result<String> {
+        let delimiter = format!("--{}", boundary);
+        for part in body.split(&delimiter) {
+            if !part.contains("name=\"file\"") {
+                continue;
+            }
+            let (_, content) = part.split_once("\r\n\r\n").context("multipart格式错误")?;
+            return Ok(content.trim_end_matches("\r\n").to_string());
+        }
+        anyhow::bail!("multipart中缺少file字段")
+    }
+}
+
+/// 市场中的智能体
+struct MarketAgent {
+    name: String,
+    keypair: KeyPair,
+    cid: String,
+    pubsub: PubsubAuthenticator,
+}
+
+impl MarketAgent {
+    /// 注册身份并初始化Pubsub认证器
+    async fn register(name: &str, service_type: &str, ipfs_url: &str) -> Result<Self> {
+        let ipfs_client = || IpfsClient::new_with_remote_node(ipfs_url.to_string(), ipfs_url.to_string(), 10);
+
+        let keypair = KeyPair::generate()?;
+        let peer_id = PeerId::random();
+        let agent_info = AgentInfo {
+            name: name.to_string(),
+            services: vec![ServiceInfo {
+                service_type: service_type.to_string(),
+                endpoint: serde_json::json!(format!("diap://{}", name.to_lowercase())),
+            }],
+            description: Some(format!("{}（市场示例）", name)),
+            tags: Some(vec!["marketplace".to_string()]),
+        };
+
+        let registration = IdentityManager::new(ipfs_client())
+            .register_identity(&agent_info, &keypair, &peer_id)
+            .await?;
+
+        let pubsub = PubsubAuthenticator::new(IdentityManager::new(ipfs_client()), None, None);
+        pubsub.set_local_identity(keypair.clone(), peer_id, registration.cid.clone()).await?;
+
+        println!("✅ {} 注册成功", name);
+        println!("   DID: {}", keypair.did);
+        println!("   CID: {}", registration.cid);
+
+        Ok(Self {
+            name: name.to_string(),
+            keypair,
+            cid: registration.cid,
+            pubsub,
+        })
+    }
+
+    /// 创建、归档并序列化一条消息（模拟发布到gossipsub）
+    async fn send(
+        &self,
+        message_type: PubSubMessageType,
+        content: &[u8],
+        to: Option<&MarketAgent>,
+    ) -> Result<Vec<u8>> {
+        let message = self.pubsub.create_authenticated_message(
+            MARKET_TOPIC,
+            message_type,
+            content,
+            to.map(|agent| agent.keypair.did.clone()),
+        ).await?;
+
+        self.pubsub.archive_message(&message, true);
+        PubsubAuthenticator::serialize_message(&message)
+    }
+
+    /// 接收、验证并归档一条消息
+    async fn receive(&self, data: &[u8]) -> Result<AuthenticatedMessage> {
+        let message = PubsubAuthenticator::deserialize_message(data)?;
+        let verification = self.pubsub.verify_message(&message).await?;
+
+        if !verification.verified {
+            anyhow::bail!("{} 拒绝消息 {}: {:?}", self.name, message.message_id, verification.details);
+        }
+
+        self.pubsub.archive_message(&message, false);
+        Ok(message)
+    }
+}
+
+/// 任务结果分片
+#[derive(Debug, Serialize, Deserialize)]
+struct TaskChunk {
+    seq: u32,
+    data: String,
+    last: bool,
+}
+
+/// 支付回执
+#[derive(Debug, Serialize, Deserialize)]
+struct PaymentReceipt {
+    task_id: String,
+    payer: String,
+    payee: String,
+    amount: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    println!("🏪 三智能体市场场景");
+    println!("==========================================");
+
+    // 1. 启动模拟IPFS
+    let ipfs = MockIpfs::default();
+    let ipfs_url = ipfs.start().await?;
+    println!("\n🧪 模拟IPFS节点: {}", ipfs_url);
+
+    // 2. 注册三个智能体
+    println!("\n📝 注册智能体");
+    let alice = MarketAgent::register("Alice", "Buyer", &ipfs_url).await?;
+    let bob = MarketAgent::register("Bob", "Translation", &ipfs_url).await?;
+    let carol = MarketAgent::register("Carol", "Escrow", &ipfs_url).await?;
+
+    let members: Vec<String> = [&alice, &bob, &carol].iter().map(|a| a.keypair.did.clone()).collect();
+    for agent in [&alice, &bob, &carol] {
+        agent.pubsub.configure_topic(TopicConfig {
+            name: MARKET_TOPIC.to_string(),
+            policy: TopicPolicy::AllowList(members.clone()),
+            require_zkp: true,
+            require_signature: true,
+            retention: Some(RetentionPolicy {
+                retention_hours: 24,
+                require_deletion_ack: true,
+            }),
+            rate_limit: None,
+            encryption: TopicEncryption::Optional,
+        }).await?;
+        agent.pubsub.subscribe_topic(MARKET_TOPIC).await?;
+    }
+
+    // 3. 服务发现：Alice从IPFS获取Bob的DID文档，并与did:key推导结果比对
+    println!("\n🔍 服务发现");
+    let discovery_client = IpfsClient::new_with_remote_node(ipfs_url.clone(), ipfs_url.clone(), 10);
+    let bob_document = get_did_document_from_cid(&discovery_client, &bob.cid).await?;
+    let offers_translation = bob_document.service.as_ref()
+        .is_some_and(|services| services.iter().any(|s| s.service_type == "Translation"));
+    anyhow::ensure!(offers_translation, "Bob的DID文档中缺少翻译服务");
+
+    let derived = DIDResolver::new().resolve(&bob.keypair.did)?;
+    anyhow::ensure!(
+        derived.verification_method[0].public_key_multibase == bob_document.verification_method[0].public_key_multibase,
+        "Bob的DID文档公钥与did:key不一致"
+    );
+    println!("✅ 发现翻译服务提供方: {}", bob.keypair.did);
+
+    // 4. 双向认证
+    println!("\n🤝 双向认证");
+    let request = alice.pubsub.create_auth_request(MARKET_TOPIC, &bob.keypair.did, "challenge-42").await?;
+    alice.pubsub.archive_message(&request, true);
+    bob.receive(&PubsubAuthenticator::serialize_message(&request)?).await?;
+
+    let response = bob.pubsub.create_auth_response(MARKET_TOPIC, &alice.keypair.did, "challenge-42").await?;
+    bob.pubsub.archive_message(&response, true);
+    alice.receive(&PubsubAuthenticator::serialize_message(&response)?).await?;
+    println!("✅ Alice与Bob互相验证通过");
+
+    // 5. 任务委托与流式响应
+    println!("\n📦 任务委托");
+    let task = alice.send(
+        PubSubMessageType::Custom("task_request".to_string()),
+        "translate: 你好，世界".as_bytes(),
+        Some(&bob),
+    ).await?;
+    let task_message = bob.receive(&task).await?;
+    println!("✅ Bob收到任务: {}", String::from_utf8_lossy(&task_message.content));
+
+    let parts = ["Hello", ", ", "world"];
+    let mut assembled = String::new();
+    for (seq, data) in parts.iter().enumerate() {
+        let chunk = TaskChunk {
+            seq: seq as u32,
+            data: data.to_string(),
+            last: seq + 1 == parts.len(),
+        };
+        let bytes = bob.send(
+            PubSubMessageType::Custom("task_chunk".to_string()),
+            &serde_json::to_vec(&chunk)?,
+            Some(&alice),
+        ).await?;
+
+        let received: TaskChunk = serde_json::from_slice(&alice.receive(&bytes).await?.content)?;
+        anyhow::ensure!(received.seq == seq as u32, "分片乱序");
+        assembled.push_str(&received.data);
+        if received.last {
+            break;
+        }
+    }
+    anyhow::ensure!(assembled == "Hello, world", "流式结果不完整");
+    println!("✅ Alice收到完整结果: {}", assembled);
+
+    // 6. 支付回执（由托管方Carol见证）
+    println!("\n💰 支付回执");
+    let receipt = PaymentReceipt {
+        task_id: task_message.message_id.clone(),
+        payer: alice.keypair.did.clone(),
+        payee: bob.keypair.did.clone(),
+        amount: 100,
+    };
+    let receipt_bytes = serde_json::to_vec(&receipt)?;
+    let to_bob = alice.send(PubSubMessageType::Custom("payment_receipt".to_string()), &receipt_bytes, Some(&bob)).await?;
+    let to_carol = alice.send(PubSubMessageType::Custom("payment_receipt".to_string()), &receipt_bytes, Some(&carol)).await?;
+    bob.receive(&to_bob).await?;
+    let witnessed: PaymentReceipt = serde_json::from_slice(&carol.receive(&to_carol).await?.content)?;
+    anyhow::ensure!(witnessed.payee == bob.keypair.did, "回执收款方错误");
+    println!("✅ Carol见证支付: {} -> {} ({})", witnessed.payer, witnessed.payee, witnessed.amount);
+
+    // 7. 审计：核对各方归档的消息
+    println!("\n📋 审计");
+    let expected = [(&alice, 8), (&bob, 7), (&carol, 1)];
+    for (agent, count) in expected {
+        let archived = agent.pubsub.message_archive().len();
+        let report = agent.pubsub.compliance_report();
+        anyhow::ensure!(archived == count, "{} 归档消息数为 {}，应为 {}", agent.name, archived, count);
+        anyhow::ensure!(report.is_compliant(), "{} 存在逾期消息", agent.name);
+        println!("✅ {}: {} 条归档消息，保留策略合规", agent.name, archived);
+    }
+
+    println!("\n🎉 市场场景完成");
+    Ok(())
+}
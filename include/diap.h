@@ -0,0 +1,130 @@
+/* DIAP Rust SDK - C ABI（由cbindgen生成，请勿手动修改） */
+
+#ifndef DIAP_H
+#define DIAP_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// 调用结果
+typedef enum DiapStatus {
+  // 成功
+  DIAP_STATUS_OK = 0,
+  // 必需的指针参数为空
+  DIAP_STATUS_NULL_POINTER = 1,
+  // 参数无效（长度不对、非UTF-8、JSON无法解析等）
+  DIAP_STATUS_INVALID_ARGUMENT = 2,
+  // 操作失败（网络、IPFS、证明等）
+  DIAP_STATUS_FAILED = 3,
+  // SDK内部panic
+  DIAP_STATUS_PANIC = 4,
+} DiapStatus;
+
+// 客户端句柄：IPFS客户端、身份管理器和执行异步调用的tokio运行时
+typedef struct DiapClient DiapClient;
+
+// 密钥对句柄
+typedef struct DiapKeyPair DiapKeyPair;
+
+// SDK分配的字节缓冲（用 diap_bytes_free 释放）
+typedef struct DiapBytes {
+  uint8_t *data;
+  size_t len;
+} DiapBytes;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// 当前线程上一次失败调用的错误信息（没有错误时为NULL）
+// 返回的指针在本线程下一次调用SDK前有效，不要释放
+const char *diap_last_error(void);
+
+// SDK版本（静态字符串，不要释放）
+const char *diap_version(void);
+
+// 释放SDK返回的字符串
+void diap_string_free(char *value);
+
+// 释放SDK返回的字节缓冲
+void diap_bytes_free(struct DiapBytes bytes);
+
+// 生成新的Ed25519密钥对
+enum DiapStatus diap_keypair_generate(struct DiapKeyPair **out_keypair);
+
+// 从32字节私钥恢复密钥对
+enum DiapStatus diap_keypair_from_private_key(const uint8_t *private_key,
+                                              size_t private_key_len,
+                                              struct DiapKeyPair **out_keypair);
+
+// 释放密钥对
+void diap_keypair_free(struct DiapKeyPair *keypair);
+
+// 密钥对的DID（did:key，用 diap_string_free 释放）
+enum DiapStatus diap_keypair_did(const struct DiapKeyPair *keypair, char **out_did);
+
+// 写出32字节公钥
+enum DiapStatus diap_keypair_public_key(const struct DiapKeyPair *keypair,
+                                        uint8_t (*out_public_key)[32]);
+
+// 写出32字节私钥（用于调用方自行持久化）
+enum DiapStatus diap_keypair_private_key(const struct DiapKeyPair *keypair,
+                                         uint8_t (*out_private_key)[32]);
+
+// 用密钥对签名数据，写出64字节签名（用 diap_bytes_free 释放）
+enum DiapStatus diap_sign(const struct DiapKeyPair *keypair,
+                          const uint8_t *data,
+                          size_t data_len,
+                          struct DiapBytes *out_signature);
+
+// 用did:key中的公钥验证签名，结果写入 out_valid
+enum DiapStatus diap_verify_signature(const char *did,
+                                      const uint8_t *data,
+                                      size_t data_len,
+                                      const uint8_t *signature,
+                                      size_t signature_len,
+                                      bool *out_valid);
+
+// 创建客户端
+// api_url/gateway_url 均非空时使用远程IPFS节点，否则只用公共网关
+enum DiapStatus diap_client_new(const char *api_url,
+                                const char *gateway_url,
+                                uint64_t timeout_seconds,
+                                struct DiapClient **out_client);
+
+// 释放客户端（会等待运行时中的任务结束）
+void diap_client_free(struct DiapClient *client);
+
+// 发布DID文档到IPFS
+// agent_info_json 为 AgentInfo 的JSON（为NULL时以DID为名称、不含服务端点）；
+// 结果为 IdentityRegistration 的JSON（用 diap_string_free 释放）
+enum DiapStatus diap_publish_did(const struct DiapClient *client,
+                                 const struct DiapKeyPair *keypair,
+                                 const char *agent_info_json,
+                                 char **out_registration_json);
+
+// 为已发布的DID文档生成DID-CID绑定证明（用 diap_bytes_free 释放）
+enum DiapStatus diap_generate_proof(const struct DiapClient *client,
+                                    const struct DiapKeyPair *keypair,
+                                    const char *cid,
+                                    const uint8_t *nonce,
+                                    size_t nonce_len,
+                                    struct DiapBytes *out_proof);
+
+// 通过CID和证明验证身份
+// 结果为 IdentityVerification 的JSON（用 diap_string_free 释放），out_verified 为是否通过
+enum DiapStatus diap_verify_proof(const struct DiapClient *client,
+                                  const char *cid,
+                                  const uint8_t *proof,
+                                  size_t proof_len,
+                                  const uint8_t *nonce,
+                                  size_t nonce_len,
+                                  bool *out_verified,
+                                  char **out_verification_json);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DIAP_H */
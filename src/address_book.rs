@@ -0,0 +1,330 @@
+// DIAP Rust SDK - 对端地址簿
+// 按DID记录对端的PeerID、多地址、最后在线时间和可用传输，来源包括DHT智能体记录、identify和mDNS；
+// 可持久化到JSON文件，重启后直接按记录重连，无需重新发现整个网络
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::agent_discovery::AgentRecord;
+use crate::clock::{SharedClock, system_clock};
+use crate::config_manager::Libp2pProtocol;
+
+/// 每个对端最多保存的地址数（新地址在前，超出时丢弃最旧的）
+pub const MAX_ADDRESSES_PER_PEER: usize = 16;
+
+/// 地址来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressSource {
+    /// DHT上签名的智能体记录（唯一能建立DID与PeerID对应关系的来源）
+    Dht,
+    /// identify协议报告的监听地址
+    Identify,
+    /// 局域网mDNS发现
+    Mdns,
+    /// 应用手动添加
+    Manual,
+}
+
+/// 地址簿条目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerEntry {
+    /// 对端DID
+    pub did: String,
+
+    /// libp2p PeerID
+    pub peer_id: String,
+
+    /// 已知多地址（新的在前）
+    pub addresses: Vec<String>,
+
+    /// 最后在线时间（秒）
+    pub last_seen: u64,
+
+    /// 地址中出现的传输协议（按地址顺序去重）
+    pub transports: Vec<Libp2pProtocol>,
+
+    /// 是否只能经中继到达（所有地址都是/p2p-circuit）
+    #[serde(default)]
+    pub relayed_only: bool,
+
+    /// 最近一次更新的来源
+    pub source: AddressSource,
+}
+
+impl PeerEntry {
+    /// 可直接拨号的地址（带/p2p/后缀）
+    pub fn dial_addresses(&self) -> Vec<String> {
+        let suffix = format!("/p2p/{}", self.peer_id);
+        self.addresses.iter()
+            .map(|addr| if addr.ends_with(&suffix) { addr.clone() } else { format!("{}{}", addr, suffix) })
+            .collect()
+    }
+
+    /// 合并新地址（新地址排在前面），返回无法解析为Multiaddr而被丢弃的地址
+    fn merge_addresses(&mut self, addresses: &[String]) -> Vec<String> {
+        let mut rejected = Vec::new();
+        for addr in addresses.iter().rev() {
+            if addr.parse::<libp2p::Multiaddr>().is_err() {
+                log::warn!("📒 丢弃{}的无效地址: {}", self.did, addr);
+                rejected.insert(0, addr.clone());
+                continue;
+            }
+            self.addresses.retain(|existing| existing != addr);
+            self.addresses.insert(0, addr.clone());
+        }
+        self.addresses.truncate(MAX_ADDRESSES_PER_PEER);
+
+        let parsed: Vec<libp2p::Multiaddr> = self.addresses.iter().filter_map(|addr| addr.parse().ok()).collect();
+        self.transports.clear();
+        for addr in &parsed {
+            let protocol = transport_hint(addr);
+            if !self.transports.contains(&protocol) {
+                self.transports.push(protocol);
+            }
+        }
+        self.relayed_only = !parsed.is_empty() && parsed.iter().all(is_relayed);
+        rejected
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct AddressBookSnapshot {
+    peers: Vec<PeerEntry>,
+}
+
+/// 对端地址簿（可克隆，克隆体共享状态）
+#[derive(Clone)]
+pub struct AddressBook {
+    peers: Arc<Mutex<HashMap<String, PeerEntry>>>,
+    path: Option<PathBuf>,
+    clock: SharedClock,
+}
+
+impl AddressBook {
+    /// 创建内存中的地址簿
+    pub fn new() -> Self {
+        Self {
+            peers: Arc::new(Mutex::new(HashMap::new())),
+            path: None,
+            clock: system_clock(),
+        }
+    }
+
+    /// 打开持久化的地址簿（文件不存在时新建）
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建地址簿目录: {:?}", parent))?;
+        }
+
+        let mut book = Self::new();
+        if path.exists() {
+            let content = std::fs::read(&path).with_context(|| format!("无法读取地址簿: {:?}", path))?;
+            let snapshot: AddressBookSnapshot = serde_json::from_slice(&content).context("解析地址簿失败")?;
+            *book.peers.lock().unwrap() = snapshot.peers.into_iter()
+                .map(|entry| (entry.did.clone(), entry))
+                .collect();
+        }
+        log::info!("📒 地址簿: {:?} ({}个对端)", path, book.len());
+        book.path = Some(path);
+        Ok(book)
+    }
+
+    /// 使用指定时间源
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 记录DID对应的PeerID和地址；PeerID变化时（对端换了libp2p身份）丢弃旧地址
+    /// 返回无法解析而未记录的地址
+    pub fn upsert(&self, did: &str, peer_id: &str, addresses: &[String], source: AddressSource) -> Vec<String> {
+        let now = self.clock.now_secs();
+        let mut peers = self.peers.lock().unwrap();
+        let entry = peers.entry(did.to_string()).or_insert_with(|| PeerEntry {
+            did: did.to_string(),
+            peer_id: peer_id.to_string(),
+            addresses: Vec::new(),
+            last_seen: now,
+            transports: Vec::new(),
+            relayed_only: false,
+            source,
+        });
+        if entry.peer_id != peer_id {
+            log::info!("📒 {}的PeerID已变化: {} -> {}", did, entry.peer_id, peer_id);
+            entry.peer_id = peer_id.to_string();
+            entry.addresses.clear();
+        }
+        let rejected = entry.merge_addresses(addresses);
+        entry.last_seen = now;
+        entry.source = source;
+        rejected
+    }
+
+    /// 按PeerID更新已知对端（identify、mDNS事件只带PeerID），返回对应的DID；未知PeerID忽略
+    pub fn observe_peer(&self, peer_id: &str, addresses: &[String], source: AddressSource) -> Option<String> {
+        let now = self.clock.now_secs();
+        let mut peers = self.peers.lock().unwrap();
+        let entry = peers.values_mut().find(|entry| entry.peer_id == peer_id)?;
+        entry.merge_addresses(addresses);
+        entry.last_seen = now;
+        entry.source = source;
+        Some(entry.did.clone())
+    }
+
+    /// 记录DHT上发现的智能体（调用方应已校验签名和有效期）
+    pub fn record_agent(&self, record: &AgentRecord) {
+        self.upsert(&record.did, &record.peer_id, &record.addresses, AddressSource::Dht);
+    }
+
+    /// 查询DID的条目
+    pub fn get(&self, did: &str) -> Option<PeerEntry> {
+        self.peers.lock().unwrap().get(did).cloned()
+    }
+
+    /// PeerID对应的DID
+    pub fn did_for_peer(&self, peer_id: &str) -> Option<String> {
+        self.peers.lock().unwrap().values()
+            .find(|entry| entry.peer_id == peer_id)
+            .map(|entry| entry.did.clone())
+    }
+
+    /// 所有条目（最近在线的在前）
+    pub fn entries(&self) -> Vec<PeerEntry> {
+        let mut entries: Vec<PeerEntry> = self.peers.lock().unwrap().values().cloned().collect();
+        entries.sort_by(|a, b| b.last_seen.cmp(&a.last_seen).then_with(|| a.did.cmp(&b.did)));
+        entries
+    }
+
+    /// 删除DID的条目
+    pub fn remove(&self, did: &str) -> bool {
+        self.peers.lock().unwrap().remove(did).is_some()
+    }
+
+    /// 删除超过max_age未在线的条目，返回删除数量
+    pub fn prune(&self, max_age: Duration) -> usize {
+        let cutoff = self.clock.now_secs().saturating_sub(max_age.as_secs());
+        let mut peers = self.peers.lock().unwrap();
+        let before = peers.len();
+        peers.retain(|_, entry| entry.last_seen >= cutoff);
+        before - peers.len()
+    }
+
+    /// 条目数
+    pub fn len(&self) -> usize {
+        self.peers.lock().unwrap().len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.peers.lock().unwrap().is_empty()
+    }
+
+    /// 写入持久化文件（内存地址簿时什么也不做）
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let snapshot = AddressBookSnapshot { peers: self.entries() };
+        let content = serde_json::to_vec_pretty(&snapshot).context("序列化地址簿失败")?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content).with_context(|| format!("无法写入地址簿: {:?}", tmp))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("无法替换地址簿文件: {:?}", path))?;
+        Ok(())
+    }
+
+    /// 定期保存（identify每次连接都会更新地址，不逐条写盘）
+    pub fn spawn_autosave(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let book = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = book.save() {
+                    log::warn!("⚠️ 保存地址簿失败: {}", e);
+                }
+            }
+        })
+    }
+}
+
+impl Default for AddressBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 地址使用的传输协议（中继地址按到中继的那一段判断）
+pub fn transport_hint(addr: &libp2p::Multiaddr) -> Libp2pProtocol {
+    use libp2p::multiaddr::Protocol;
+    for protocol in addr.iter() {
+        match protocol {
+            Protocol::QuicV1 => return Libp2pProtocol::Quic,
+            Protocol::Ws(_) | Protocol::Wss(_) => return Libp2pProtocol::Websocket,
+            Protocol::P2pCircuit => break,
+            _ => {}
+        }
+    }
+    Libp2pProtocol::Tcp
+}
+
+/// 是否为中继电路地址
+pub fn is_relayed(addr: &libp2p::Multiaddr) -> bool {
+    addr.iter().any(|protocol| protocol == libp2p::multiaddr::Protocol::P2pCircuit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_address_book_updates_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("peers.json");
+        let clock = MockClock::new(1_000);
+        let book = AddressBook::open(&path).unwrap().with_clock(Arc::new(clock.clone()));
+
+        let tcp = "/ip4/203.0.113.7/tcp/4001".to_string();
+        let quic = "/ip4/203.0.113.7/udp/4001/quic-v1".to_string();
+        book.upsert("did:key:alice", "12D3KooWAlice", std::slice::from_ref(&tcp), AddressSource::Dht);
+        assert!(book.observe_peer("12D3KooWUnknown", std::slice::from_ref(&quic), AddressSource::Mdns).is_none());
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(book.observe_peer("12D3KooWAlice", std::slice::from_ref(&quic), AddressSource::Identify).as_deref(), Some("did:key:alice"));
+        let entry = book.get("did:key:alice").unwrap();
+        assert_eq!(entry.addresses, vec![quic.clone(), tcp.clone()]);
+        assert_eq!(entry.transports, vec![Libp2pProtocol::Quic, Libp2pProtocol::Tcp]);
+        assert_eq!((entry.last_seen, entry.source), (1_060, AddressSource::Identify));
+        assert_eq!(entry.dial_addresses()[0], format!("{}/p2p/12D3KooWAlice", quic));
+
+        clock.advance(Duration::from_secs(10));
+        let relayed = format!("/ip4/198.51.100.1/tcp/4001/p2p/{}/p2p-circuit", libp2p::PeerId::random());
+        let rejected = book.upsert("did:key:bob", "12D3KooWBob", &[relayed.clone(), "not-a-multiaddr".to_string()], AddressSource::Manual);
+        assert_eq!(rejected, vec!["not-a-multiaddr".to_string()]);
+        let bob = book.get("did:key:bob").unwrap();
+        assert_eq!(bob.addresses, vec![relayed]);
+        assert!(bob.relayed_only);
+
+        book.save().unwrap();
+        let reopened = AddressBook::open(&path).unwrap().with_clock(Arc::new(clock.clone()));
+        assert_eq!(reopened.entries().iter().map(|e| e.did.as_str()).collect::<Vec<_>>(), vec!["did:key:bob", "did:key:alice"]);
+        assert_eq!(reopened.did_for_peer("12D3KooWAlice").as_deref(), Some("did:key:alice"));
+
+        // PeerID变化时旧地址作废
+        reopened.upsert("did:key:alice", "12D3KooWAlice2", &[], AddressSource::Dht);
+        assert!(reopened.get("did:key:alice").unwrap().addresses.is_empty());
+
+        clock.advance(Duration::from_secs(3600));
+        reopened.upsert("did:key:alice", "12D3KooWAlice2", &[], AddressSource::Dht);
+        assert_eq!(reopened.prune(Duration::from_secs(1800)), 1);
+        assert!(reopened.get("did:key:bob").is_none());
+    }
+}
@@ -0,0 +1,910 @@
+// DIAP Rust SDK - 本地管理接口
+// 在本机回环地址上以HTTP+JSON提供智能体运行状态（对端、主题、消息速率、验证失败、资源占用），
+// 供 `diap top` 等运维工具读取，适合无界面的边缘智能体；
+// /dashboard 提供内置的只读网页仪表盘，通过WebSocket事件流实时刷新；
+// 挂载任务调度器后 /v1/jobs 列出定时任务，并可暂停、恢复或立即运行。
+// 数据接口始终需要令牌（未配置时启动时随机生成）；浏览器无法为WebSocket设置请求头，
+// 仪表盘从URL片段（#token=，不会发给服务端或写入访问日志）读取令牌并放在子协议中，跨站升级请求一律拒绝
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use futures::{SinkExt, StreamExt};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::{handshake::derive_accept_key, protocol::Role, Message};
+use tokio_tungstenite::WebSocketStream;
+
+use crate::circuit_breaker::{CircuitEvent, CircuitStatus};
+use crate::clock::{SharedClock, system_clock};
+use crate::connection_manager::{ConnectionManager, PeerState};
+use crate::http_server::{HttpRequest, connection_limiter, constant_time_eq, read_request, write_response};
+use crate::job_scheduler::JobScheduler;
+use crate::pubsub_authenticator::{PubsubAuthenticator, VerificationFailure};
+
+/// 默认管理接口地址（仅本机）
+pub const DEFAULT_ADMIN_ADDR: &str = "127.0.0.1:7878";
+
+/// 状态接口路径
+pub const STATUS_PATH: &str = "/v1/status";
+
+/// 事件流的WebSocket子协议
+pub const EVENTS_SUBPROTOCOL: &str = "diap-admin";
+
+/// 携带令牌的子协议前缀（`diap-token.<token>`）
+const TOKEN_SUBPROTOCOL_PREFIX: &str = "diap-token.";
+
+/// 事件流（WebSocket）路径
+pub const EVENTS_PATH: &str = "/v1/events";
+
+/// 熔断状态接口路径
+pub const CIRCUITS_PATH: &str = "/v1/circuits";
+
+/// 定时任务接口路径（GET列出任务，POST {JOBS_PATH}/<name>/pause|resume|trigger 控制任务）
+pub const JOBS_PATH: &str = "/v1/jobs";
+
+/// 网页仪表盘路径
+pub const DASHBOARD_PATH: &str = "/dashboard";
+
+/// 事件流推送状态快照的默认间隔
+pub const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 消息速率统计窗口
+pub const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// 事件通道容量（落后的订阅者会丢弃旧事件）
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+const JSON: &str = "application/json";
+const HTML: &str = "text/html; charset=utf-8";
+
+/// 内置的只读网页仪表盘
+const DASHBOARD_HTML: &str = include_str!("admin_dashboard.html");
+
+/// 智能体运行状态快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStatus {
+    /// 本地DID
+    pub did: Option<String>,
+
+    /// 本地PeerID
+    pub peer_id: Option<String>,
+
+    /// SDK版本
+    pub version: String,
+
+    /// 运行时长（秒）
+    pub uptime_secs: u64,
+
+    /// 采集时间（毫秒）
+    pub collected_at: u64,
+
+    /// 已知对端
+    pub peers: Vec<PeerStatus>,
+
+    /// 主题
+    pub topics: Vec<TopicStatus>,
+
+    /// 验证失败总数
+    pub verification_failures: u64,
+
+    /// 最近的验证失败
+    pub recent_failures: Vec<VerificationFailure>,
+
+    /// 有失败记录的对端熔断状态
+    #[serde(default)]
+    pub circuits: Vec<CircuitStatus>,
+
+    /// 资源占用
+    pub resources: ResourceUsage,
+}
+
+/// 对端状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerStatus {
+    /// PeerID
+    pub peer_id: String,
+
+    /// 对端DID
+    pub did: Option<String>,
+
+    /// 连接状态
+    pub state: PeerState,
+
+    /// 往返延迟（毫秒）
+    pub latency_ms: Option<u64>,
+
+    /// 最后活跃时间（毫秒）
+    pub last_seen: Option<u64>,
+}
+
+/// 主题状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicStatus {
+    /// 主题名
+    pub topic: String,
+
+    /// 是否已订阅
+    pub subscribed: bool,
+
+    /// 累计消息数
+    pub total_messages: u64,
+
+    /// 统计窗口内的每分钟消息数
+    pub messages_per_minute: f64,
+}
+
+/// 进程资源占用（不支持的平台为None）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// 常驻内存（字节）
+    pub rss_bytes: Option<u64>,
+
+    /// 累计CPU时间（秒）
+    pub cpu_seconds: Option<f64>,
+
+    /// 线程数
+    pub threads: Option<u64>,
+}
+
+impl ResourceUsage {
+    /// 读取当前进程的资源占用
+    #[cfg(target_os = "linux")]
+    pub fn current() -> Self {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+        let field = |name: &str| status.lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|value| value.parse::<u64>().ok());
+
+        // /proc/self/stat 第14、15列为用户态/内核态时钟滴答（进程名可能含空格，从右括号之后解析）
+        let cpu_seconds = std::fs::read_to_string("/proc/self/stat").ok().and_then(|stat| {
+            let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+            let utime: u64 = fields.get(11)?.parse().ok()?;
+            let stime: u64 = fields.get(12)?.parse().ok()?;
+            Some((utime + stime) as f64 / 100.0)
+        });
+
+        Self {
+            rss_bytes: field("VmRSS:").map(|kb| kb * 1024),
+            cpu_seconds,
+            threads: field("Threads:"),
+        }
+    }
+
+    /// 读取当前进程的资源占用
+    #[cfg(not(target_os = "linux"))]
+    pub fn current() -> Self {
+        Self::default()
+    }
+}
+
+/// 状态采集器（从认证器和连接管理器汇总运行状态）
+pub struct StatusCollector {
+    authenticator: Arc<PubsubAuthenticator>,
+    connection_manager: Option<ConnectionManager>,
+    clock: SharedClock,
+    started_at: u64,
+    samples: Mutex<VecDeque<(u64, HashMap<String, u64>)>>,
+}
+
+impl StatusCollector {
+    /// 创建状态采集器
+    pub fn new(authenticator: Arc<PubsubAuthenticator>) -> Self {
+        Self::new_with_clock(authenticator, system_clock())
+    }
+
+    /// 使用指定时间源创建
+    pub fn new_with_clock(authenticator: Arc<PubsubAuthenticator>, clock: SharedClock) -> Self {
+        Self {
+            authenticator,
+            connection_manager: None,
+            started_at: clock.now_millis(),
+            clock,
+            samples: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// 附加连接管理器（提供对端列表）
+    pub fn with_connection_manager(mut self, connection_manager: ConnectionManager) -> Self {
+        self.connection_manager = Some(connection_manager);
+        self
+    }
+
+    /// 采集当前状态
+    pub async fn snapshot(&self) -> AgentStatus {
+        let now = self.clock.now_millis();
+        let stats = self.authenticator.get_message_stats().await;
+        let subscribed = self.authenticator.get_subscribed_topics().await;
+        let rates = self.message_rates(now, &stats);
+
+        let mut topics = Vec::new();
+        for topic in subscribed.iter().chain(stats.keys().filter(|topic| !subscribed.contains(topic))) {
+            topics.push(TopicStatus {
+                topic: topic.clone(),
+                subscribed: subscribed.contains(topic) || self.authenticator.is_subscribed(topic).await,
+                total_messages: stats.get(topic).copied().unwrap_or(0),
+                messages_per_minute: rates.get(topic).copied().unwrap_or(0.0),
+            });
+        }
+        topics.sort_by(|a, b| a.topic.cmp(&b.topic));
+
+        let peers = self.connection_manager.iter()
+            .flat_map(|manager| manager.known_peers())
+            .map(|peer| PeerStatus {
+                peer_id: peer.peer_id.to_base58(),
+                did: peer.did,
+                state: peer.state,
+                latency_ms: peer.latency.map(|latency| latency.as_millis() as u64),
+                last_seen: peer.last_seen,
+            })
+            .collect();
+
+        AgentStatus {
+            did: self.authenticator.local_did().await,
+            peer_id: self.authenticator.local_peer_id().await.map(|id| id.to_base58()),
+            version: crate::VERSION.to_string(),
+            uptime_secs: now.saturating_sub(self.started_at) / 1000,
+            collected_at: now,
+            peers,
+            topics,
+            verification_failures: self.authenticator.verification_failure_count(),
+            recent_failures: self.authenticator.recent_verification_failures(),
+            circuits: self.authenticator.circuit_breakers().snapshot(),
+            resources: ResourceUsage::current(),
+        }
+    }
+
+    /// 与窗口内最早的采样比较计算每分钟消息数
+    fn message_rates(&self, now: u64, stats: &HashMap<String, u64>) -> HashMap<String, f64> {
+        let mut samples = self.samples.lock().unwrap();
+        let window = RATE_WINDOW.as_millis() as u64;
+        while samples.front().is_some_and(|(at, _)| now.saturating_sub(*at) > window) {
+            samples.pop_front();
+        }
+
+        let rates = match samples.front() {
+            Some((at, previous)) if now > *at => {
+                let minutes = (now - at) as f64 / 60_000.0;
+                stats.iter()
+                    .map(|(topic, count)| {
+                        let delta = count.saturating_sub(previous.get(topic).copied().unwrap_or(0));
+                        (topic.clone(), delta as f64 / minutes)
+                    })
+                    .collect()
+            }
+            _ => HashMap::new(),
+        };
+
+        samples.push_back((now, stats.clone()));
+        rates
+    }
+}
+
+/// 管理事件（通过WebSocket推送给仪表盘）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AdminEvent {
+    /// 周期性状态快照
+    Status(AgentStatus),
+
+    /// 对端连接
+    PeerConnected {
+        peer_id: String,
+        did: Option<String>,
+    },
+
+    /// 对端断开
+    PeerDisconnected {
+        peer_id: String,
+    },
+
+    /// 消息验证失败
+    VerificationFailed(VerificationFailure),
+
+    /// 注册表/发现查询
+    RegistryLookup {
+        query: String,
+        results: usize,
+        at: u64,
+    },
+
+    /// 对端熔断状态变化
+    Circuit(CircuitEvent),
+}
+
+/// 管理事件通道（可克隆，应用可发布自定义事件）
+#[derive(Clone)]
+pub struct AdminEvents {
+    sender: broadcast::Sender<AdminEvent>,
+}
+
+impl AdminEvents {
+    /// 创建事件通道
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// 发布事件（没有订阅者时丢弃）
+    pub fn publish(&self, event: AdminEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// 订阅事件
+    pub fn subscribe(&self) -> broadcast::Receiver<AdminEvent> {
+        self.sender.subscribe()
+    }
+
+    /// 当前订阅者数
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for AdminEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 本地管理接口服务
+pub struct AdminServer {
+    collector: Arc<StatusCollector>,
+    events: AdminEvents,
+    token: String,
+    generated_token: bool,
+    snapshot_interval: Duration,
+    scheduler: Option<JobScheduler>,
+}
+
+impl AdminServer {
+    /// 创建管理接口服务（随机生成访问令牌，见 `token`）
+    pub fn new(collector: StatusCollector) -> Self {
+        Self {
+            collector: Arc::new(collector),
+            events: AdminEvents::new(),
+            token: hex::encode(rand::random::<[u8; 32]>()),
+            generated_token: true,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            scheduler: None,
+        }
+    }
+
+    /// 使用指定的访问令牌（请求头 `Authorization: Bearer <token>`，事件流也可放在子协议中，
+    /// 因此令牌只能包含字母、数字和 `-._~` 等HTTP token字符）
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = token.into();
+        self.generated_token = false;
+        self
+    }
+
+    /// 访问令牌
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// 设置事件流推送状态快照的间隔
+    pub fn with_snapshot_interval(mut self, interval: Duration) -> Self {
+        self.snapshot_interval = interval;
+        self
+    }
+
+    /// 通过 `JOBS_PATH` 暴露任务调度器
+    pub fn with_job_scheduler(mut self, scheduler: JobScheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 事件通道（用于发布注册表查询等应用事件）
+    pub fn events(&self) -> AdminEvents {
+        self.events.clone()
+    }
+
+    /// 绑定地址并在后台处理请求，返回实际监听地址
+    /// 未设置令牌时输出生成的令牌（仪表盘地址带 `#token=` 片段）
+    pub async fn bind(self, addr: &str) -> Result<(SocketAddr, tokio::task::JoinHandle<()>)> {
+        let listener = TcpListener::bind(addr).await
+            .with_context(|| format!("无法绑定管理接口地址: {}", addr))?;
+        let local_addr = listener.local_addr()?;
+
+        log::info!("🛠️ 管理接口已启动: http://{}{}", local_addr, DASHBOARD_PATH);
+        if self.generated_token {
+            log::warn!("🔑 管理接口未配置令牌，已生成: http://{}{}#token={}", local_addr, DASHBOARD_PATH, self.token);
+        }
+        let server = Arc::new(self);
+        let watcher = tokio::spawn(server.clone().watch_status());
+        let circuit_forwarder = tokio::spawn(server.clone().forward_circuit_events());
+        let limiter = connection_limiter();
+        let handle = tokio::spawn(async move {
+            loop {
+                let Ok(permit) = limiter.clone().acquire_owned().await else {
+                    break;
+                };
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        log::warn!("管理接口接受连接失败: {}", e);
+                        continue;
+                    }
+                };
+                let server = server.clone();
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    if let Err(e) = server.handle_connection(stream).await {
+                        log::debug!("管理接口请求处理失败 {}: {}", peer, e);
+                    }
+                });
+            }
+        });
+
+        // 服务任务被取消时一并停止状态推送
+        let handle = tokio::spawn(async move {
+            let _ = handle.await;
+            watcher.abort();
+            circuit_forwarder.abort();
+        });
+        Ok((local_addr, handle))
+    }
+
+    /// 有订阅者时定期采集状态，推送快照以及与上次相比的连接变化和新的验证失败
+    async fn watch_status(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(self.snapshot_interval);
+        let mut previous: Option<AgentStatus> = None;
+        loop {
+            interval.tick().await;
+            if self.events.subscriber_count() == 0 {
+                previous = None;
+                continue;
+            }
+
+            let status = self.collector.snapshot().await;
+            if let Some(previous) = &previous {
+                for event in status_changes(previous, &status) {
+                    self.events.publish(event);
+                }
+            }
+            self.events.publish(AdminEvent::Status(status.clone()));
+            previous = Some(status);
+        }
+    }
+
+    /// 把认证器的熔断事件转发到事件流
+    async fn forward_circuit_events(self: Arc<Self>) {
+        let mut receiver = self.collector.authenticator.circuit_breakers().subscribe();
+        loop {
+            match receiver.recv().await {
+                Ok(event) => self.events.publish(AdminEvent::Circuit(event)),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::debug!("熔断事件转发落后，丢弃{}个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> Result<()> {
+        let request = match read_request(&mut stream, 0).await {
+            Ok(request) => request,
+            Err(e) => match e.status() {
+                Some(status) => return write_response(&mut stream, status, JSON, b"{}").await,
+                None => return Err(e.into()),
+            },
+        };
+
+        if let Some(rest) = request.path.strip_prefix(JOBS_PATH) {
+            if rest.is_empty() || rest.starts_with('/') {
+                return self.handle_jobs(&mut stream, &request, rest).await;
+            }
+        }
+        if request.method != "GET" {
+            return write_response(&mut stream, 405, JSON, br#"{"error":"method not allowed"}"#).await;
+        }
+
+        match request.path.as_str() {
+            DASHBOARD_PATH => write_response(&mut stream, 200, HTML, DASHBOARD_HTML.as_bytes()).await,
+            EVENTS_PATH if !same_origin(&request) => {
+                log::warn!("🚫 拒绝跨站的事件流连接: {:?}", request.header("origin"));
+                write_response(&mut stream, 403, JSON, br#"{"error":"cross-origin request"}"#).await
+            }
+            STATUS_PATH | EVENTS_PATH | CIRCUITS_PATH if !self.authorized(&request) => {
+                write_response(&mut stream, 401, JSON, br#"{"error":"unauthorized"}"#).await
+            }
+            STATUS_PATH => {
+                let body = serde_json::to_vec(&self.collector.snapshot().await)?;
+                write_response(&mut stream, 200, JSON, &body).await
+            }
+            CIRCUITS_PATH => {
+                let body = serde_json::to_vec(&self.collector.authenticator.circuit_breakers().snapshot())?;
+                write_response(&mut stream, 200, JSON, &body).await
+            }
+            EVENTS_PATH => self.stream_events(stream, &request).await,
+            _ => write_response(&mut stream, 404, JSON, br#"{"error":"not found"}"#).await,
+        }
+    }
+
+    /// 定时任务接口：GET列出任务，POST /<name>/pause|resume|trigger 控制任务并返回任务信息
+    async fn handle_jobs(&self, stream: &mut TcpStream, request: &HttpRequest, rest: &str) -> Result<()> {
+        if !self.authorized(request) {
+            return write_response(stream, 401, JSON, br#"{"error":"unauthorized"}"#).await;
+        }
+        let Some(scheduler) = &self.scheduler else {
+            return write_response(stream, 404, JSON, br#"{"error":"not found"}"#).await;
+        };
+
+        let (name, action) = match (request.method.as_str(), rest.trim_matches('/').rsplit_once('/')) {
+            ("GET", _) if rest.trim_matches('/').is_empty() => {
+                let body = serde_json::to_vec(&scheduler.list())?;
+                return write_response(stream, 200, JSON, &body).await;
+            }
+            ("POST", Some((name, action))) if !name.is_empty() => (name, action),
+            ("GET", _) | ("POST", _) => return write_response(stream, 404, JSON, br#"{"error":"not found"}"#).await,
+            _ => return write_response(stream, 405, JSON, br#"{"error":"method not allowed"}"#).await,
+        };
+
+        if !scheduler.list().iter().any(|job| job.name == name) {
+            return write_response(stream, 404, JSON, br#"{"error":"job not found"}"#).await;
+        }
+        let result = match action {
+            "pause" => scheduler.pause(name),
+            "resume" => scheduler.resume(name),
+            "trigger" => scheduler.trigger(name).await,
+            _ => return write_response(stream, 404, JSON, br#"{"error":"not found"}"#).await,
+        };
+        log::info!("🛠️ 管理接口{}定时任务: {}", action, name);
+
+        if let Err(e) = result {
+            let body = serde_json::to_vec(&serde_json::json!({ "error": e.to_string() }))?;
+            return write_response(stream, 500, JSON, &body).await;
+        }
+        let job = scheduler.list().into_iter().find(|job| job.name == name);
+        write_response(stream, 200, JSON, &serde_json::to_vec(&job)?).await
+    }
+
+    /// Bearer令牌，或WebSocket子协议中的令牌（不接受查询参数，避免令牌进入访问日志）
+    fn authorized(&self, request: &HttpRequest) -> bool {
+        let provided = request.header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| subprotocols(request).find_map(|protocol| protocol.strip_prefix(TOKEN_SUBPROTOCOL_PREFIX)));
+        provided.is_some_and(|token| constant_time_eq(token.as_bytes(), self.token.as_bytes()))
+    }
+
+    /// WebSocket事件流：先发送当前状态，之后转发事件通道上的所有事件
+    async fn stream_events(&self, mut stream: TcpStream, request: &HttpRequest) -> Result<()> {
+        let key = match request.header("sec-websocket-key") {
+            Some(key) if request.header("upgrade").is_some_and(|u| u.eq_ignore_ascii_case("websocket")) => key,
+            _ => return write_response(&mut stream, 426, JSON, br#"{"error":"websocket required"}"#).await,
+        };
+
+        // 浏览器要求服务端选定一个客户端提供的子协议
+        let protocol = match subprotocols(request).any(|protocol| protocol == EVENTS_SUBPROTOCOL) {
+            true => format!("Sec-WebSocket-Protocol: {}\r\n", EVENTS_SUBPROTOCOL),
+            false => String::new(),
+        };
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n{}\r\n",
+            derive_accept_key(key.as_bytes()),
+            protocol
+        );
+        stream.write_all(response.as_bytes()).await?;
+
+        let mut receiver = self.events.subscribe();
+        let websocket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+        let (mut sink, mut incoming) = websocket.split();
+
+        let initial = AdminEvent::Status(self.collector.snapshot().await);
+        sink.send(Message::Text(serde_json::to_string(&initial)?)).await?;
+
+        loop {
+            tokio::select! {
+                event = receiver.recv() => match event {
+                    Ok(event) => sink.send(Message::Text(serde_json::to_string(&event)?)).await?,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::debug!("事件流订阅者落后，丢弃{}个事件", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                message = incoming.next() => match message {
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    // 只读事件流，忽略客户端发来的其他消息
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 请求提供的WebSocket子协议
+fn subprotocols(request: &HttpRequest) -> impl Iterator<Item = &str> {
+    request.header("sec-websocket-protocol")
+        .into_iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+}
+
+/// 没有Origin（非浏览器客户端）或Origin与Host一致（本机仪表盘页面）
+fn same_origin(request: &HttpRequest) -> bool {
+    let Some(origin) = request.header("origin") else {
+        return true;
+    };
+    let origin_host = origin.split_once("://").map(|(_, host)| host).unwrap_or(origin);
+    request.header("host").is_some_and(|host| host.eq_ignore_ascii_case(origin_host))
+}
+
+/// 比较两次快照，生成连接变化和新验证失败事件
+fn status_changes(previous: &AgentStatus, current: &AgentStatus) -> Vec<AdminEvent> {
+    let connected = |status: &AgentStatus| -> HashMap<String, Option<String>> {
+        status.peers.iter()
+            .filter(|peer| peer.state == PeerState::Connected)
+            .map(|peer| (peer.peer_id.clone(), peer.did.clone()))
+            .collect()
+    };
+    let (before, after) = (connected(previous), connected(current));
+
+    let mut events: Vec<AdminEvent> = after.iter()
+        .filter(|(peer_id, _)| !before.contains_key(*peer_id))
+        .map(|(peer_id, did)| AdminEvent::PeerConnected { peer_id: peer_id.clone(), did: did.clone() })
+        .collect();
+    events.extend(before.keys()
+        .filter(|peer_id| !after.contains_key(*peer_id))
+        .map(|peer_id| AdminEvent::PeerDisconnected { peer_id: peer_id.clone() }));
+
+    let new_failures = current.verification_failures.saturating_sub(previous.verification_failures) as usize;
+    let skip = current.recent_failures.len().saturating_sub(new_failures);
+    events.extend(current.recent_failures.iter().skip(skip).cloned().map(AdminEvent::VerificationFailed));
+    events
+}
+
+/// 从本地管理接口读取状态
+pub async fn fetch_status(addr: &str, token: Option<&str>) -> Result<AgentStatus> {
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let mut request = client.get(format!("http://{}{}", addr, STATUS_PATH));
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send()
+        .await
+        .with_context(|| format!("无法连接管理接口: {}", addr))?;
+    if !response.status().is_success() {
+        anyhow::bail!("管理接口返回错误: {}", response.status());
+    }
+    response.json().await.context("解析状态失败")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::identity_manager::IdentityManager;
+    use crate::ipfs_client::IpfsClient;
+    use crate::key_manager::{CallbackSigner, KeyPair};
+    use crate::job_scheduler::JobInfo;
+    use libp2p::PeerId;
+
+    fn authenticator(clock: &MockClock) -> Arc<PubsubAuthenticator> {
+        let identity_manager = IdentityManager::new(IpfsClient::new_public_only(5));
+        Arc::new(PubsubAuthenticator::new(identity_manager, None, None).with_clock(Arc::new(clock.clone())))
+    }
+
+    #[tokio::test]
+    async fn test_status_snapshot() {
+        let clock = MockClock::new(1_000);
+        let auth = authenticator(&clock);
+        auth.set_local_identity(KeyPair::generate().unwrap(), PeerId::random(), "cid".to_string()).await.unwrap();
+        auth.subscribe_topic("tasks").await.unwrap();
+
+        let manager = ConnectionManager::new_with_clock(Arc::new(clock.clone()));
+        let peer = PeerId::random();
+        manager.on_connected(peer, None);
+
+        let collector = StatusCollector::new_with_clock(auth.clone(), Arc::new(clock.clone()))
+            .with_connection_manager(manager);
+        collector.snapshot().await;
+
+        for _ in 0..30 {
+            auth.update_message_stats("tasks").await;
+        }
+        clock.advance(Duration::from_secs(30));
+        let status = collector.snapshot().await;
+
+        assert!(status.did.is_some());
+        assert_eq!(status.uptime_secs, 30);
+        assert_eq!(status.peers.len(), 1);
+        assert_eq!(status.peers[0].state, PeerState::Connected);
+        assert_eq!(status.topics.len(), 1);
+        assert_eq!(status.topics[0].total_messages, 30);
+        assert!((status.topics[0].messages_per_minute - 60.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_verification_failures_and_http() {
+        let clock = MockClock::new(1_000);
+        let auth = authenticator(&clock);
+        // 回调签名器不生成ZKP证明，创建消息不需要访问IPFS
+        let keypair = KeyPair::generate().unwrap();
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+        let sender = authenticator(&clock);
+        let peer = PeerId::random();
+        sender.set_local_signer(Arc::new(signer), peer, "cid".to_string()).await.unwrap();
+
+        // 尚未到投递时间的定时消息验证失败（发送者身份未证明，失败记在传输层对端名下）
+        let message = sender.create_scheduled_message(
+            "tasks",
+            crate::pubsub_authenticator::PubSubMessageType::Heartbeat,
+            b"later",
+            None,
+            2_000,
+        ).await.unwrap();
+        assert!(!auth.verify_message_from_peer(&message, &peer).await.unwrap().verified);
+        assert_eq!(auth.verification_failure_count(), 1);
+
+        let server = AdminServer::new(StatusCollector::new(auth)).with_token("secret");
+        let (addr, handle) = server.bind("127.0.0.1:0").await.unwrap();
+        let addr = addr.to_string();
+        assert!(fetch_status(&addr, None).await.is_err());
+        assert!(fetch_status(&addr, Some("wrong")).await.is_err());
+
+        let status = fetch_status(&addr, Some("secret")).await.unwrap();
+        assert_eq!(status.verification_failures, 1);
+        assert_eq!(status.recent_failures[0].message_id, message.message_id);
+        assert!(!status.recent_failures[0].reasons.is_empty());
+        assert_eq!(status.circuits.len(), 1);
+        assert_eq!(status.circuits[0].did, peer.to_base58());
+        assert_eq!(status.circuits[0].verification_failures, 1);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_job_routes() {
+        let clock = MockClock::new(1_000);
+        let scheduler = JobScheduler::new_with_clock(Arc::new(clock.clone()));
+        scheduler.register("republish", crate::job_scheduler::Schedule::every(Duration::from_secs(60)), || async {
+            Ok(())
+        }).unwrap();
+
+        let server = AdminServer::new(StatusCollector::new(authenticator(&clock)))
+            .with_token("secret")
+            .with_job_scheduler(scheduler.clone());
+        let (addr, handle) = server.bind("127.0.0.1:0").await.unwrap();
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        let url = |path: &str| format!("http://{}{}{}", addr, JOBS_PATH, path);
+
+        assert_eq!(client.get(url("")).send().await.unwrap().status(), 401);
+        let jobs: Vec<JobInfo> = client.get(url("")).bearer_auth("secret").send().await.unwrap().json().await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert!(jobs[0].enabled);
+
+        let paused: JobInfo = client.post(url("/republish/pause")).bearer_auth("secret")
+            .send().await.unwrap().json().await.unwrap();
+        assert!(!paused.enabled);
+        assert!(!scheduler.list()[0].enabled);
+
+        let triggered: JobInfo = client.post(url("/republish/trigger")).bearer_auth("secret")
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(triggered.run_count, 1);
+
+        let missing = client.post(url("/missing/pause")).bearer_auth("secret").send().await.unwrap();
+        assert_eq!(missing.status(), 404);
+        let wrong_method = client.delete(url("/republish/pause")).bearer_auth("secret").send().await.unwrap();
+        assert_eq!(wrong_method.status(), 405);
+        handle.abort();
+    }
+
+    async fn next_event(websocket: &mut WebSocketStream<TcpStream>) -> AdminEvent {
+        let message = websocket.next().await.unwrap().unwrap();
+        serde_json::from_str(message.to_text().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dashboard_and_event_stream() {
+        let clock = MockClock::new(1_000);
+        let server = AdminServer::new(StatusCollector::new(authenticator(&clock)))
+            .with_token("secret")
+            .with_snapshot_interval(Duration::from_millis(50));
+        let events = server.events();
+        let (addr, handle) = server.bind("127.0.0.1:0").await.unwrap();
+
+        // 页面本身不含数据，无需令牌
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        let page = client.get(format!("http://{}{}", addr, DASHBOARD_PATH)).send().await.unwrap();
+        assert_eq!(page.status(), 200);
+        assert!(page.text().await.unwrap().contains(EVENTS_PATH));
+
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        let events_request = |token: Option<&str>, origin: Option<&str>| {
+            let mut request = format!("ws://{}{}", addr, EVENTS_PATH).into_client_request().unwrap();
+            let mut protocols = EVENTS_SUBPROTOCOL.to_string();
+            if let Some(token) = token {
+                protocols.push_str(&format!(", {}{}", TOKEN_SUBPROTOCOL_PREFIX, token));
+            }
+            request.headers_mut().insert("sec-websocket-protocol", protocols.parse().unwrap());
+            if let Some(origin) = origin {
+                request.headers_mut().insert("origin", origin.parse().unwrap());
+            }
+            request
+        };
+
+        // 缺少令牌、查询参数中的令牌、跨站页面发起的连接都被拒绝
+        let stream = TcpStream::connect(addr).await.unwrap();
+        assert!(tokio_tungstenite::client_async(events_request(None, None), stream).await.is_err());
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let query = format!("ws://{}{}?token=secret", addr, EVENTS_PATH);
+        assert!(tokio_tungstenite::client_async(query, stream).await.is_err());
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let cross_site = events_request(Some("secret"), Some("https://evil.example"));
+        assert!(tokio_tungstenite::client_async(cross_site, stream).await.is_err());
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let same_site = events_request(Some("secret"), Some(&format!("http://{}", addr)));
+        let (mut websocket, _) = tokio_tungstenite::client_async(same_site, stream).await.unwrap();
+
+        assert!(matches!(next_event(&mut websocket).await, AdminEvent::Status(_)));
+
+        events.publish(AdminEvent::RegistryLookup { query: "capability:translation".to_string(), results: 2, at: 1_000 });
+        let mut lookup = None;
+        for _ in 0..20 {
+            if let AdminEvent::RegistryLookup { query, results, .. } = next_event(&mut websocket).await {
+                lookup = Some((query, results));
+                break;
+            }
+        }
+        assert_eq!(lookup, Some(("capability:translation".to_string(), 2)));
+        handle.abort();
+
+        // 未配置令牌时生成随机令牌，而不是放开访问
+        let server = AdminServer::new(StatusCollector::new(authenticator(&clock)));
+        let token = server.token().to_string();
+        assert_eq!(token.len(), 64);
+        let (addr, handle) = server.bind("127.0.0.1:0").await.unwrap();
+        let addr = addr.to_string();
+        assert!(fetch_status(&addr, None).await.is_err());
+        assert!(fetch_status(&addr, Some(&token)).await.is_ok());
+        handle.abort();
+    }
+
+    #[test]
+    fn test_status_changes() {
+        let peer = |id: &str, state| PeerStatus { peer_id: id.to_string(), did: None, state, latency_ms: None, last_seen: None };
+        let failure = |id: &str| VerificationFailure {
+            message_id: id.to_string(),
+            from_did: "did:key:x".to_string(),
+            topic: "t".to_string(),
+            reasons: vec![],
+            at: 0,
+        };
+        let status = |peers, failures: Vec<VerificationFailure>, total| AgentStatus {
+            did: None,
+            peer_id: None,
+            version: String::new(),
+            uptime_secs: 0,
+            collected_at: 0,
+            peers,
+            topics: vec![],
+            verification_failures: total,
+            recent_failures: failures,
+            circuits: vec![],
+            resources: ResourceUsage::default(),
+        };
+
+        let before = status(vec![peer("a", PeerState::Connected), peer("b", PeerState::Connected)], vec![failure("m1")], 1);
+        let after = status(
+            vec![peer("a", PeerState::Connected), peer("b", PeerState::Disconnected), peer("c", PeerState::Connected)],
+            vec![failure("m1"), failure("m2")],
+            2,
+        );
+
+        let events = status_changes(&before, &after);
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().any(|e| matches!(e, AdminEvent::PeerConnected { peer_id, .. } if peer_id == "c")));
+        assert!(events.iter().any(|e| matches!(e, AdminEvent::PeerDisconnected { peer_id } if peer_id == "b")));
+        assert!(events.iter().any(|e| matches!(e, AdminEvent::VerificationFailed(f) if f.message_id == "m2")));
+    }
+}
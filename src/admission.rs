@@ -0,0 +1,186 @@
+// DIAP Rust SDK - 消息准入控制
+// 熔断、限流、信誉和自定义主题策略不属于消息验证本身：验证只回答消息是否可信，
+// 准入控制决定是否值得验证、配额记在谁名下，以及失败由谁承担
+// 发送者身份未被证明时，from_did只是发送者随意填写的字段，失败记在传输层PeerID名下
+
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::circuit_breaker::{CircuitBreakers, FailureKind};
+use crate::pubsub_authenticator::RateLimit;
+use crate::rate_limiter::{RateLimitScope, RateLimiter};
+use crate::trust::{ReputationEvent, ReputationStore, TopicPolicyHook};
+
+/// 准入拒绝原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdmissionRejection {
+    /// 发送者或传输层对端处于熔断冷却期
+    CircuitOpen,
+    /// 超出速率限制
+    RateLimited { scope: RateLimitScope, limit: RateLimit },
+}
+
+/// 准入控制：熔断器、速率限制、信誉和TopicPolicy::Custom主题的策略钩子
+#[derive(Clone, Default)]
+pub struct AdmissionControl {
+    /// 熔断器（按身份已证明的DID或传输层PeerID计数）
+    circuit_breakers: CircuitBreakers,
+
+    /// 按发送者/主题的速率限制
+    rate_limiter: Arc<RateLimiter>,
+
+    /// 按DID的信誉评分（身份未证明的验证失败记在传输层PeerID名下）
+    reputation: ReputationStore,
+
+    /// TopicPolicy::Custom主题的策略钩子
+    topic_policy_hook: Arc<RwLock<Option<TopicPolicyHook>>>,
+}
+
+impl AdmissionControl {
+    /// 使用默认配置创建
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用指定的熔断器（例如与发送侧共享，投递失败也计入同一组熔断器）
+    pub fn with_circuit_breakers(mut self, breakers: CircuitBreakers) -> Self {
+        self.circuit_breakers = breakers;
+        self
+    }
+
+    /// 使用指定的速率限制器
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = limiter;
+        self
+    }
+
+    /// 使用指定的信誉存储
+    pub fn with_reputation(mut self, reputation: ReputationStore) -> Self {
+        self.reputation = reputation;
+        self
+    }
+
+    /// 熔断器
+    pub fn circuit_breakers(&self) -> &CircuitBreakers {
+        &self.circuit_breakers
+    }
+
+    /// 速率限制器
+    pub fn rate_limiter(&self) -> &Arc<RateLimiter> {
+        &self.rate_limiter
+    }
+
+    /// 信誉存储
+    pub fn reputation(&self) -> &ReputationStore {
+        &self.reputation
+    }
+
+    /// 设置TopicPolicy::Custom主题的策略钩子
+    pub async fn set_topic_policy_hook(&self, hook: TopicPolicyHook) {
+        *self.topic_policy_hook.write().await = Some(hook);
+    }
+
+    /// 自定义主题策略是否接受该发送者（未设置钩子时接受）
+    pub async fn custom_policy_allows(&self, topic: &str, did: &str) -> bool {
+        self.topic_policy_hook.read().await.as_ref().is_none_or(|hook| hook(topic, did))
+    }
+
+    /// 验证前的准入检查：熔断中的发送者或对端、已耗尽配额的发送者直接拒绝，不消耗配额
+    pub fn screen(
+        &self,
+        did: &str,
+        peer: Option<&str>,
+        topic: &str,
+        topic_limit: Option<&RateLimit>,
+        now_ms: u64,
+    ) -> Result<(), AdmissionRejection> {
+        let did_allowed = self.circuit_breakers.allow(did);
+        let peer_allowed = peer.is_none_or(|peer| self.circuit_breakers.allow(peer));
+        if !(did_allowed && peer_allowed) {
+            // 另一方拿到的半开试探没有被使用，归还
+            if did_allowed {
+                self.circuit_breakers.release_probe(did);
+            } else if let Some(peer) = peer.filter(|_| peer_allowed) {
+                self.circuit_breakers.release_probe(peer);
+            }
+            return Err(AdmissionRejection::CircuitOpen);
+        }
+        self.rate_limiter.peek(did, topic, topic_limit, now_ms)
+            .map_err(|(scope, limit)| AdmissionRejection::RateLimited { scope, limit })
+    }
+
+    /// 验证通过：消耗配额并记录成功，超出配额时拒绝
+    pub fn accept(
+        &self,
+        did: &str,
+        peer: Option<&str>,
+        topic: &str,
+        topic_limit: Option<&RateLimit>,
+        now_ms: u64,
+    ) -> Result<(), AdmissionRejection> {
+        if let Err((scope, limit)) = self.rate_limiter.acquire(did, topic, topic_limit, now_ms) {
+            self.reputation.record(did, ReputationEvent::RateLimited);
+            return Err(AdmissionRejection::RateLimited { scope, limit });
+        }
+        self.circuit_breakers.record_success(did);
+        if let Some(peer) = peer {
+            self.circuit_breakers.record_success(peer);
+        }
+        self.reputation.record(did, ReputationEvent::Verified);
+        Ok(())
+    }
+
+    /// 验证失败：身份已证明时记在DID名下，否则记在传输层对端名下（没有对端时不记录）
+    pub fn reject(&self, did: &str, peer: Option<&str>, sender_proven: bool) {
+        if sender_proven {
+            self.reputation.record(did, ReputationEvent::VerificationFailed);
+            self.circuit_breakers.record_failure(did, FailureKind::Verification);
+            return;
+        }
+        // 冒用者不能占用该DID的半开试探
+        self.circuit_breakers.release_probe(did);
+        if let Some(peer) = peer {
+            self.reputation.record(peer, ReputationEvent::VerificationFailed);
+            self.circuit_breakers.record_failure(peer, FailureKind::Verification);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit_breaker::{CircuitBreakerConfig, CircuitState};
+
+    #[tokio::test]
+    async fn test_unproven_failures_charged_to_peer() {
+        let config = CircuitBreakerConfig { verification_threshold: 2, ..Default::default() };
+        let admission = AdmissionControl::new().with_circuit_breakers(CircuitBreakers::new(config));
+        let initial_score = admission.reputation().weights().initial_score;
+
+        for _ in 0..2 {
+            assert!(admission.screen("did:key:alice", Some("peer-mallory"), "tasks", None, 0).is_ok());
+            admission.reject("did:key:alice", Some("peer-mallory"), false);
+        }
+        admission.reject("did:key:alice", None, false);
+        assert_eq!(admission.circuit_breakers().state("did:key:alice"), CircuitState::Closed);
+        assert!(admission.reputation().record_of("did:key:alice").is_none());
+        assert!(admission.reputation().trust_score("peer-mallory") < initial_score);
+        assert_eq!(admission.screen("did:key:alice", Some("peer-mallory"), "tasks", None, 0), Err(AdmissionRejection::CircuitOpen));
+        assert!(admission.screen("did:key:alice", Some("peer-alice"), "tasks", None, 0).is_ok());
+
+        // 身份已证明的失败记在DID名下
+        for _ in 0..2 {
+            admission.reject("did:key:alice", None, true);
+        }
+        assert!(matches!(admission.circuit_breakers().state("did:key:alice"), CircuitState::Open { .. }));
+        assert!(admission.reputation().trust_score("did:key:alice") < initial_score);
+    }
+
+    #[tokio::test]
+    async fn test_custom_policy_hook() {
+        let admission = AdmissionControl::new();
+        assert!(admission.custom_policy_allows("tasks", "did:key:bob").await);
+        admission.set_topic_policy_hook(admission.reputation().min_score_policy(60.0)).await;
+        assert!(!admission.custom_policy_allows("tasks", "did:key:bob").await);
+    }
+}
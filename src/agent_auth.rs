@@ -1,14 +1,27 @@
 use crate::{
-    IdentityManager, AgentInfo, ServiceInfo, KeyPair, IdentityRegistration
+    IdentityManager, AgentInfo, ServiceInfo, KeyPair, IdentityRegistration, TrustLevel
 };
+use crate::credentials::{self, PresentationRequest, VerifiedPresentation};
+use crate::secure_session::{self, SecureSession, DEFAULT_SESSION_TTL};
+use crate::transport::Transport;
 use libp2p_identity::PeerId;
+use futures::future::BoxFuture;
+use std::sync::Arc;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Serialize, Deserialize};
 
 /// 智能体认证管理器 - 统一的API接口（轻量级版本）
 pub struct AgentAuthManager {
     identity_manager: IdentityManager,
+    transport: Option<Arc<dyn Transport>>,
+}
+
+/// 经传输层发送的远程验证请求
+#[derive(Debug, Serialize, Deserialize)]
+struct VerificationRequest {
+    cid: String,
+    proof: Vec<u8>,
 }
 
 /// 认证结果
@@ -20,6 +33,16 @@ pub struct AuthResult {
     pub verification_details: Vec<String>,
     pub timestamp: u64,
     pub processing_time_ms: u64,
+    /// 达到的信任等级（仅验证结果有值）
+    #[serde(default)]
+    pub trust_level: Option<TrustLevel>,
+}
+
+impl AuthResult {
+    /// 是否认证成功且达到指定信任等级
+    pub fn meets(&self, required: TrustLevel) -> bool {
+        self.success && self.trust_level.is_some_and(|level| level.satisfies(required))
+    }
 }
 
 /// 批量认证结果
@@ -57,6 +80,7 @@ impl AgentAuthManager {
         
         Ok(Self {
             identity_manager,
+            transport: None,
         })
     }
     
@@ -89,9 +113,47 @@ impl AgentAuthManager {
         
         Ok(Self {
             identity_manager,
+            transport: None,
         })
     }
     
+    /// 使用传输层收发远程验证请求
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// 当前传输层
+    pub fn transport(&self) -> Option<&Arc<dyn Transport>> {
+        self.transport.as_ref()
+    }
+
+    /// 请求远程节点验证身份（对方需已调用serve_verification）
+    pub async fn request_verification(&self, peer: &str, cid: &str, proof: &[u8]) -> Result<AuthResult> {
+        let transport = self.transport.as_ref().ok_or_else(|| anyhow::anyhow!("未配置传输层"))?;
+        let request = serde_json::to_vec(&VerificationRequest { cid: cid.to_string(), proof: proof.to_vec() })
+            .context("序列化验证请求失败")?;
+        let response = transport.send_request(peer, request).await?;
+        serde_json::from_slice(&response).context("解析验证结果失败")
+    }
+
+    /// 在传输层上应答远程验证请求
+    pub fn serve_verification(self: &Arc<Self>) -> Result<()> {
+        let transport = self.transport.as_ref().ok_or_else(|| anyhow::anyhow!("未配置传输层"))?;
+        let manager = Arc::downgrade(self);
+        transport.set_request_handler(Arc::new(move |from: String, data: Vec<u8>| -> BoxFuture<'static, Result<Vec<u8>>> {
+            let manager = manager.clone();
+            Box::pin(async move {
+                let manager = manager.upgrade().ok_or_else(|| anyhow::anyhow!("认证管理器已关闭"))?;
+                let request: VerificationRequest = serde_json::from_slice(&data).context("解析验证请求失败")?;
+                log::info!("📨 收到来自{}的验证请求: {}", from, request.cid);
+                let result = manager.verify_identity(&request.cid, &request.proof).await?;
+                serde_json::to_vec(&result).context("序列化验证结果失败")
+            })
+        }));
+        Ok(())
+    }
+
     /// 创建智能体
     pub fn create_agent(&self, name: &str, _email: Option<&str>) -> Result<(AgentInfo, KeyPair, PeerId)> {
         log::info!("🤖 创建智能体: {}", name);
@@ -165,6 +227,7 @@ impl AgentAuthManager {
             ],
             timestamp,
             processing_time_ms: processing_time.as_millis() as u64,
+            trust_level: None,
         };
         
         log::info!("✅ 身份证明生成成功");
@@ -199,6 +262,8 @@ impl AgentAuthManager {
             verification_details: verification.verification_details,
             timestamp,
             processing_time_ms: processing_time.as_millis() as u64,
+            // 身份验证总是重新获取DID文档并验证ZKP
+            trust_level: verification.zkp_verified.then_some(TrustLevel::FullZkpFreshDoc),
         };
         
         log::info!("✅ 身份验证完成");
@@ -208,6 +273,45 @@ impl AgentAuthManager {
         Ok(result)
     }
     
+    /// 验证凭证出示（替代ZKP的信任输入）
+    /// 签发者签名和持有者key binding都验证通过后，返回的agent_id为凭证主体DID；
+    /// 凭证只证明持有者控制该DID的密钥，信任等级为仅签名
+    pub fn verify_credential_presentation(
+        &self,
+        presentation: &str,
+        request: &PresentationRequest,
+    ) -> Result<(AuthResult, Option<VerifiedPresentation>)> {
+        log::info!("🔍 验证凭证出示");
+        
+        let start_time = Instant::now();
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        
+        let (agent_id, verified, verification_details) = match credentials::verify_presentation(presentation, request, timestamp) {
+            Ok(verified) => {
+                let details = vec![
+                    format!("✓ 凭证签发者: {}", verified.issuer),
+                    format!("✓ 凭证类型: {}", verified.credential_type),
+                    format!("✓ 已披露声明: {}", verified.claims.keys().cloned().collect::<Vec<_>>().join(", ")),
+                ];
+                (verified.subject.clone(), Some(verified), details)
+            }
+            Err(e) => (String::new(), None, vec![format!("✗ 凭证出示验证失败: {}", e)]),
+        };
+        
+        let result = AuthResult {
+            success: verified.is_some(),
+            agent_id,
+            proof: None,
+            verification_details,
+            timestamp,
+            processing_time_ms: start_time.elapsed().as_millis() as u64,
+            trust_level: verified.as_ref().map(|_| TrustLevel::SignatureOnly),
+        };
+        
+        log::info!("   验证结果: {}", if result.success { "通过" } else { "失败" });
+        Ok((result, verified))
+    }
+    
     /// 双向认证
     pub async fn mutual_authentication(&self, 
         _alice_info: &AgentInfo, alice_keypair: &KeyPair, _alice_peer_id: &PeerId, alice_cid: &str,
@@ -234,6 +338,26 @@ impl AgentAuthManager {
         Ok((alice_proof, bob_verify_alice, bob_proof, alice_verify_bob))
     }
     
+    /// 双向认证成功后建立安全会话（X25519密钥协商），返回(Alice会话, Bob会话)
+    /// 后续流量用会话的encrypt/decrypt/sign处理，不必对每条消息都做完整的ZKP验证
+    pub fn establish_session(&self,
+        alice_keypair: &KeyPair, bob_keypair: &KeyPair,
+        bob_verify_alice: &AuthResult, alice_verify_bob: &AuthResult
+    ) -> Result<(SecureSession, SecureSession)> {
+        if !bob_verify_alice.success || !alice_verify_bob.success {
+            anyhow::bail!("双向认证未通过，无法建立安全会话");
+        }
+        
+        let (alice_session, bob_session) = secure_session::establish_pair(
+            alice_keypair,
+            bob_keypair,
+            DEFAULT_SESSION_TTL,
+            crate::clock::system_clock(),
+        )?;
+        log::info!("🔐 安全会话已建立: {}", alice_session.session_id());
+        Ok((alice_session, bob_session))
+    }
+    
     /// 批量认证测试
     pub async fn batch_authentication_test(&self, 
         _agent_info: &AgentInfo, keypair: &KeyPair, _peer_id: &PeerId, cid: &str, count: usize
@@ -0,0 +1,309 @@
+// DIAP Rust SDK - 智能体检查点模块
+// 捕获运行中智能体的会话密钥、订阅、待发消息和连接意图，用于在主机之间热迁移；对端通过签名的恢复通知切换到新地址
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::key_manager::{KeyBackup, KeyPair, SignedEnvelope};
+use crate::pubsub_authenticator::{AuthenticatedMessage, SignedMessage, TopicConfig};
+
+/// 会话恢复通知消息类型标识（PubSubMessageType::Custom）
+pub const SESSION_RESUME_MESSAGE_TYPE: &str = "session_resume";
+
+/// 检查点格式版本
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// 连接意图（迁移后需要在新主机上重建的连接）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionIntent {
+    /// 对端PeerID
+    pub peer_id: String,
+
+    /// 对端DID（已知时）
+    pub did: Option<String>,
+
+    /// 对端多地址
+    pub addresses: Vec<String>,
+}
+
+/// 智能体检查点
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgentCheckpoint {
+    /// 格式版本
+    pub version: u32,
+
+    /// 检查点ID
+    pub checkpoint_id: String,
+
+    /// 智能体DID
+    pub did: String,
+
+    /// DID文档CID
+    pub cid: String,
+
+    /// 迁移前的PeerID
+    pub peer_id: String,
+
+    /// 会话密钥（口令加密）
+    pub session_key: KeyBackup,
+
+    /// 订阅的主题
+    pub subscribed_topics: Vec<String>,
+
+    /// 主题配置
+    pub topic_configs: Vec<TopicConfig>,
+
+    /// 尚未发出的消息
+    pub outbox: Vec<AuthenticatedMessage>,
+
+    /// 连接意图
+    pub connection_intents: Vec<ConnectionIntent>,
+
+    /// 创建时间
+    pub created_at: u64,
+}
+
+impl AgentCheckpoint {
+    /// 序列化检查点
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化检查点失败")
+    }
+
+    /// 反序列化检查点
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let checkpoint: Self = serde_json::from_slice(data).context("解析检查点失败")?;
+        if checkpoint.version > CHECKPOINT_VERSION {
+            anyhow::bail!("不支持的检查点版本: {}", checkpoint.version);
+        }
+        Ok(checkpoint)
+    }
+
+    /// 用口令解密会话密钥
+    pub fn session_keypair(&self, passphrase: &str) -> Result<KeyPair> {
+        let keypair = KeyPair::import_from_backup(&self.session_key, Some(passphrase))
+            .context("无法解密会话密钥（口令可能错误）")?;
+
+        if keypair.did != self.did {
+            anyhow::bail!("会话密钥与检查点中的DID不一致");
+        }
+        Ok(keypair)
+    }
+}
+
+/// 会话恢复通知（迁移后广播给对端）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResumption {
+    /// 智能体DID
+    pub did: String,
+
+    /// 恢复所用的检查点ID
+    pub checkpoint_id: String,
+
+    /// 迁移前的PeerID
+    pub previous_peer_id: String,
+
+    /// 迁移后的PeerID
+    pub peer_id: String,
+
+    /// 迁移后的监听地址
+    pub addresses: Vec<String>,
+
+    /// 时间戳
+    pub timestamp: u64,
+
+    /// 签名（base64）
+    pub signature: String,
+}
+
+impl SessionResumption {
+    /// 创建并签名恢复通知
+    pub fn new(
+        keypair: &KeyPair,
+        checkpoint: &AgentCheckpoint,
+        peer_id: String,
+        addresses: Vec<String>,
+    ) -> Result<Self> {
+        Self {
+            did: keypair.did.clone(),
+            checkpoint_id: checkpoint.checkpoint_id.clone(),
+            previous_peer_id: checkpoint.peer_id.clone(),
+            peer_id,
+            addresses,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            signature: String::new(),
+        }
+        .signed_by(keypair)
+    }
+}
+
+impl SignedEnvelope for SessionResumption {
+    const KIND: &'static str = "恢复通知";
+
+    fn signer_did(&self) -> &str {
+        &self.did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+impl SignedMessage for SessionResumption {
+    const MESSAGE_TYPE: &'static str = SESSION_RESUME_MESSAGE_TYPE;
+}
+
+/// 从检查点恢复的结果
+#[derive(Debug)]
+pub struct RestoredAgent {
+    /// 会话密钥
+    pub keypair: KeyPair,
+
+    /// 需要重新发送的消息
+    pub outbox: Vec<AuthenticatedMessage>,
+
+    /// 需要重建的连接
+    pub connection_intents: Vec<ConnectionIntent>,
+
+    /// 待广播的恢复通知
+    pub resumption: SessionResumption,
+}
+
+/// 对端会话表（对端侧：记录各DID当前所在的PeerID和地址）
+#[derive(Clone, Default)]
+pub struct PeerSessionTable {
+    /// DID -> 最近一次接受的恢复通知
+    sessions: Arc<DashMap<String, SessionResumption>>,
+}
+
+impl PeerSessionTable {
+    /// 创建空的会话表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 接受恢复通知
+    /// 签名无效时报错；比已知通知更旧的通知会被忽略并返回false
+    pub fn accept(&self, resumption: SessionResumption) -> Result<bool> {
+        if !resumption.verify()? {
+            anyhow::bail!("恢复通知签名无效: {}", resumption.did);
+        }
+
+        if let Some(known) = self.sessions.get(&resumption.did) {
+            if known.timestamp > resumption.timestamp {
+                log::debug!("忽略过期的恢复通知: {}", resumption.did);
+                return Ok(false);
+            }
+        }
+
+        log::info!("🔁 {} 已迁移: {} -> {}", resumption.did, resumption.previous_peer_id, resumption.peer_id);
+        self.sessions.insert(resumption.did.clone(), resumption);
+        Ok(true)
+    }
+
+    /// 获取DID当前的PeerID
+    pub fn current_peer(&self, did: &str) -> Option<String> {
+        self.sessions.get(did).map(|s| s.peer_id.clone())
+    }
+
+    /// 获取DID当前的地址
+    pub fn current_addresses(&self, did: &str) -> Vec<String> {
+        self.sessions.get(did).map(|s| s.addresses.clone()).unwrap_or_default()
+    }
+
+    /// 更新连接意图中已迁移对端的PeerID和地址
+    pub fn refresh_intents(&self, intents: &mut [ConnectionIntent]) {
+        for intent in intents.iter_mut() {
+            let Some(did) = &intent.did else { continue };
+            if let Some(session) = self.sessions.get(did) {
+                intent.peer_id = session.peer_id.clone();
+                intent.addresses = session.addresses.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(keypair: &KeyPair) -> AgentCheckpoint {
+        AgentCheckpoint {
+            version: CHECKPOINT_VERSION,
+            checkpoint_id: "cp1".to_string(),
+            did: keypair.did.clone(),
+            cid: "bafy".to_string(),
+            peer_id: "old-peer".to_string(),
+            session_key: keypair.export_backup(Some("secret")).unwrap(),
+            subscribed_topics: vec!["general".to_string()],
+            topic_configs: Vec::new(),
+            outbox: Vec::new(),
+            connection_intents: Vec::new(),
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let keypair = KeyPair::generate().unwrap();
+        let data = checkpoint(&keypair).to_bytes().unwrap();
+
+        let restored = AgentCheckpoint::from_bytes(&data).unwrap();
+        assert_eq!(restored.subscribed_topics, vec!["general".to_string()]);
+        assert_eq!(restored.session_keypair("secret").unwrap().did, keypair.did);
+        assert!(restored.session_keypair("wrong").is_err());
+    }
+
+    #[test]
+    fn test_peer_session_table() {
+        let keypair = KeyPair::generate().unwrap();
+        let checkpoint = checkpoint(&keypair);
+        let table = PeerSessionTable::new();
+
+        let resumption = SessionResumption::new(&keypair, &checkpoint, "new-peer".to_string(), vec!["/ip4/10.0.0.2/tcp/4001".to_string()]).unwrap();
+        assert!(table.accept(resumption.clone()).unwrap());
+        assert_eq!(table.current_peer(&keypair.did).as_deref(), Some("new-peer"));
+
+        let mut intents = vec![ConnectionIntent {
+            peer_id: "old-peer".to_string(),
+            did: Some(keypair.did.clone()),
+            addresses: Vec::new(),
+        }];
+        table.refresh_intents(&mut intents);
+        assert_eq!(intents[0].peer_id, "new-peer");
+
+        let mut forged = resumption;
+        forged.peer_id = "attacker".to_string();
+        assert!(table.accept(forged).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_migrate_between_authenticators() {
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+        use libp2p::PeerId;
+
+        let authenticator = || PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None);
+        let keypair = KeyPair::generate().unwrap();
+
+        let source = authenticator();
+        source.set_local_identity(keypair.clone(), PeerId::random(), "bafy".to_string()).await.unwrap();
+        source.subscribe_topic("general").await.unwrap();
+        let data = source.checkpoint("secret", Vec::new(), Vec::new()).await.unwrap().to_bytes().unwrap();
+
+        let target = authenticator();
+        let new_peer = PeerId::random();
+        let restored = target.restore(AgentCheckpoint::from_bytes(&data).unwrap(), "secret", new_peer, Vec::new()).await.unwrap();
+
+        assert_eq!(restored.keypair.did, keypair.did);
+        assert_eq!(target.get_subscribed_topics().await, vec!["general".to_string()]);
+        assert_eq!(restored.resumption.peer_id, new_peer.to_string());
+        assert!(restored.resumption.verify().unwrap());
+    }
+}
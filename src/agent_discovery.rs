@@ -0,0 +1,684 @@
+// DIAP Rust SDK - 基于Kademlia的智能体发现模块
+// 智能体把签名的智能体记录（DID、CID、能力标签、地址）存为DHT值记录，
+// 并为每个能力标签发布provider记录；查询方按能力找到provider后取回记录并验签，
+// 无需依赖中心化的发现服务
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use libp2p::{
+    kad::{self, store::MemoryStore, QueryId, Quorum, Record, RecordKey},
+    noise, swarm::SwarmEvent, tcp, yamux, Multiaddr, PeerId, StreamProtocol, Swarm,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::address_book::AddressBook;
+use crate::admin_api::{AdminEvent, AdminEvents};
+use crate::clock::{SharedClock, system_clock};
+use crate::constants::network_params;
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::libp2p_identity::LibP2PIdentity;
+
+/// DIAP专用的Kademlia协议名（与IPFS公共DHT隔离）
+pub use crate::constants::DIAP_KAD_PROTOCOL;
+
+/// 默认智能体记录有效期
+pub const DEFAULT_AGENT_RECORD_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// 默认DHT查询超时
+pub const DEFAULT_DHT_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 启动时等待监听地址就绪的超时
+pub const LISTEN_READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 等待Swarm报告第一个监听地址（NewListenAddr），超时报错
+pub(crate) async fn wait_for_listen_addr<B: libp2p::swarm::NetworkBehaviour>(swarm: &mut Swarm<B>) -> Result<Multiaddr> {
+    tokio::time::timeout(LISTEN_READY_TIMEOUT, async {
+        loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
+                return address;
+            }
+        }
+    })
+    .await
+    .context("等待监听地址超时")
+}
+
+/// 签名的智能体记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentRecord {
+    /// 智能体DID
+    pub did: String,
+
+    /// DID文档CID
+    pub cid: String,
+
+    /// libp2p PeerID
+    pub peer_id: String,
+
+    /// 能力标签（已规范化）
+    pub capabilities: Vec<String>,
+
+    /// 可拨号地址
+    pub addresses: Vec<String>,
+
+    /// 发布时间（秒）
+    pub published_at: u64,
+
+    /// 过期时间（秒）
+    pub expires_at: u64,
+
+    /// DID签名（base64）
+    pub signature: String,
+}
+
+impl AgentRecord {
+    /// 创建并签名智能体记录
+    pub fn new(
+        signer: &dyn Signer,
+        cid: &str,
+        peer_id: &PeerId,
+        capabilities: &[String],
+        addresses: Vec<String>,
+        published_at: u64,
+        ttl: Duration,
+    ) -> Result<Self> {
+        let mut capabilities: Vec<String> = capabilities.iter()
+            .map(|c| normalize_capability(c))
+            .filter(|c| !c.is_empty())
+            .collect();
+        capabilities.sort();
+        capabilities.dedup();
+
+        Self {
+            did: signer.did(),
+            cid: cid.to_string(),
+            peer_id: peer_id.to_base58(),
+            capabilities,
+            addresses,
+            published_at,
+            expires_at: published_at + ttl.as_secs(),
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// 是否声明了该能力
+    pub fn has_capability(&self, capability: &str) -> bool {
+        let capability = normalize_capability(capability);
+        self.capabilities.contains(&capability)
+    }
+
+    /// 是否已过期
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+impl SignedEnvelope for AgentRecord {
+    const KIND: &'static str = "智能体记录";
+
+    fn signer_did(&self) -> &str {
+        &self.did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 能力标签规范化（去空白、小写）
+pub fn normalize_capability(capability: &str) -> String {
+    capability.trim().to_lowercase()
+}
+
+/// 能力标签对应的provider记录键
+pub fn capability_key(capability: &str) -> RecordKey {
+    hashed_key(&network_params().capability_key_prefix(), &normalize_capability(capability))
+}
+
+/// 智能体记录的值记录键
+pub fn agent_record_key(peer_id: &PeerId) -> RecordKey {
+    hashed_key(&network_params().agent_record_key_prefix(), &peer_id.to_base58())
+}
+
+pub(crate) fn hashed_key(namespace: &str, value: &str) -> RecordKey {
+    let mut hasher = Sha256::new();
+    hasher.update(namespace.as_bytes());
+    hasher.update(value.as_bytes());
+    RecordKey::new(&hasher.finalize().to_vec())
+}
+
+/// DHT后端（provider记录 + 值记录）
+#[async_trait]
+pub trait DhtBackend: Send + Sync {
+    /// 本地PeerID
+    fn local_peer_id(&self) -> PeerId;
+
+    /// 宣告本节点提供该键
+    async fn start_providing(&self, key: RecordKey) -> Result<()>;
+
+    /// 查找键的provider
+    async fn get_providers(&self, key: RecordKey) -> Result<HashSet<PeerId>>;
+
+    /// 存储值记录
+    async fn put_record(&self, key: RecordKey, value: Vec<u8>) -> Result<()>;
+
+    /// 获取值记录（可能有多个副本）
+    async fn get_records(&self, key: RecordKey) -> Result<Vec<Vec<u8>>>;
+}
+
+enum DhtCommand {
+    AddPeer(PeerId, Multiaddr),
+    Bootstrap,
+    ListenAddrs(oneshot::Sender<Vec<Multiaddr>>),
+    StartProviding(RecordKey, oneshot::Sender<Result<()>>),
+    GetProviders(RecordKey, oneshot::Sender<Result<HashSet<PeerId>>>),
+    PutRecord(RecordKey, Vec<u8>, oneshot::Sender<Result<()>>),
+    GetRecords(RecordKey, oneshot::Sender<Result<Vec<Vec<u8>>>>),
+}
+
+enum PendingQuery {
+    Provide(oneshot::Sender<Result<()>>),
+    Providers(HashSet<PeerId>, oneshot::Sender<Result<HashSet<PeerId>>>),
+    Put(oneshot::Sender<Result<()>>),
+    Records(Vec<Vec<u8>>, oneshot::Sender<Result<Vec<Vec<u8>>>>),
+}
+
+/// 基于libp2p Kademlia的DHT节点（后台任务驱动Swarm，通过命令通道交互）
+pub struct KademliaDht {
+    peer_id: PeerId,
+    commands: mpsc::UnboundedSender<DhtCommand>,
+}
+
+impl KademliaDht {
+    /// 启动DHT节点并监听地址（如 /ip4/0.0.0.0/tcp/0）
+    pub async fn start(identity: &LibP2PIdentity, listen_addr: Multiaddr) -> Result<Self> {
+        Self::start_with_timeout(identity, listen_addr, DEFAULT_DHT_QUERY_TIMEOUT).await
+    }
+
+    /// 启动DHT节点并指定查询超时
+    pub async fn start_with_timeout(
+        identity: &LibP2PIdentity,
+        listen_addr: Multiaddr,
+        query_timeout: Duration,
+    ) -> Result<Self> {
+        let kad_protocol = StreamProtocol::try_from_owned(network_params().kad_protocol())
+            .context("无效的Kademlia协议名")?;
+        let mut swarm = libp2p::SwarmBuilder::with_existing_identity(identity.keypair().clone())
+            .with_tokio()
+            .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)
+            .context("创建TCP传输失败")?
+            .with_behaviour(|key| {
+                let peer_id = key.public().to_peer_id();
+                let mut config = kad::Config::default();
+                config.set_protocol_names(vec![kad_protocol]);
+                config.set_query_timeout(query_timeout);
+                let mut behaviour = kad::Behaviour::with_config(peer_id, MemoryStore::new(peer_id), config);
+                // 私有网络中没有外部地址确认，直接以服务端模式应答查询
+                behaviour.set_mode(Some(kad::Mode::Server));
+                behaviour
+            })
+            .context("创建Kademlia行为失败")?
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build();
+
+        swarm.listen_on(listen_addr.clone())
+            .with_context(|| format!("无法监听地址: {}", listen_addr))?;
+        // 监听地址就绪后再返回，listen_addrs() 不会返回空列表
+        let address = wait_for_listen_addr(&mut swarm).await?;
+
+        let peer_id = *swarm.local_peer_id();
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run_dht(swarm, receiver));
+
+        log::info!("🌐 Kademlia DHT已启动: {} ({})", peer_id, address);
+        Ok(Self { peer_id, commands })
+    }
+
+    /// 添加已知节点（引导节点）
+    pub fn add_peer(&self, peer_id: PeerId, address: Multiaddr) -> Result<()> {
+        self.send(DhtCommand::AddPeer(peer_id, address))
+    }
+
+    /// 发起路由表引导
+    pub fn bootstrap(&self) -> Result<()> {
+        self.send(DhtCommand::Bootstrap)
+    }
+
+    /// 当前监听地址（start返回时至少有一个）
+    pub async fn listen_addrs(&self) -> Result<Vec<Multiaddr>> {
+        let (tx, rx) = oneshot::channel();
+        self.send(DhtCommand::ListenAddrs(tx))?;
+        rx.await.context("DHT任务已停止")
+    }
+
+    fn send(&self, command: DhtCommand) -> Result<()> {
+        self.commands.send(command).map_err(|_| anyhow::anyhow!("DHT任务已停止"))
+    }
+
+    async fn request<T>(&self, command: DhtCommand, rx: oneshot::Receiver<Result<T>>) -> Result<T> {
+        self.send(command)?;
+        rx.await.context("DHT任务已停止")?
+    }
+}
+
+#[async_trait]
+impl DhtBackend for KademliaDht {
+    fn local_peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    async fn start_providing(&self, key: RecordKey) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.request(DhtCommand::StartProviding(key, tx), rx).await
+    }
+
+    async fn get_providers(&self, key: RecordKey) -> Result<HashSet<PeerId>> {
+        let (tx, rx) = oneshot::channel();
+        self.request(DhtCommand::GetProviders(key, tx), rx).await
+    }
+
+    async fn put_record(&self, key: RecordKey, value: Vec<u8>) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.request(DhtCommand::PutRecord(key, value, tx), rx).await
+    }
+
+    async fn get_records(&self, key: RecordKey) -> Result<Vec<Vec<u8>>> {
+        let (tx, rx) = oneshot::channel();
+        self.request(DhtCommand::GetRecords(key, tx), rx).await
+    }
+}
+
+/// DHT事件循环：命令通道关闭（KademliaDht被丢弃）时退出
+async fn run_dht(
+    mut swarm: Swarm<kad::Behaviour<MemoryStore>>,
+    mut commands: mpsc::UnboundedReceiver<DhtCommand>,
+) {
+    let mut pending: HashMap<QueryId, PendingQuery> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(command) => handle_command(&mut swarm, &mut pending, command),
+                None => break,
+            },
+            event = swarm.select_next_some() => match event {
+                SwarmEvent::Behaviour(kad::Event::OutboundQueryProgressed { id, result, step, .. }) => {
+                    handle_query_result(&mut pending, id, result, step.last);
+                }
+                SwarmEvent::NewListenAddr { address, .. } => {
+                    log::debug!("DHT监听地址: {}", address);
+                }
+                _ => {}
+            },
+        }
+    }
+
+    log::info!("Kademlia DHT已停止: {}", swarm.local_peer_id());
+}
+
+fn handle_command(
+    swarm: &mut Swarm<kad::Behaviour<MemoryStore>>,
+    pending: &mut HashMap<QueryId, PendingQuery>,
+    command: DhtCommand,
+) {
+    let kad = swarm.behaviour_mut();
+    match command {
+        DhtCommand::AddPeer(peer_id, address) => {
+            kad.add_address(&peer_id, address);
+        }
+        DhtCommand::Bootstrap => {
+            if let Err(e) = kad.bootstrap() {
+                log::warn!("DHT引导失败: {}", e);
+            }
+        }
+        DhtCommand::ListenAddrs(tx) => {
+            let _ = tx.send(swarm.listeners().cloned().collect());
+        }
+        DhtCommand::StartProviding(key, tx) => match kad.start_providing(key) {
+            Ok(id) => {
+                pending.insert(id, PendingQuery::Provide(tx));
+            }
+            Err(e) => {
+                let _ = tx.send(Err(anyhow::anyhow!("本地存储provider记录失败: {}", e)));
+            }
+        },
+        DhtCommand::GetProviders(key, tx) => {
+            let id = kad.get_providers(key);
+            pending.insert(id, PendingQuery::Providers(HashSet::new(), tx));
+        }
+        DhtCommand::PutRecord(key, value, tx) => match kad.put_record(Record::new(key, value), Quorum::One) {
+            Ok(id) => {
+                pending.insert(id, PendingQuery::Put(tx));
+            }
+            Err(e) => {
+                let _ = tx.send(Err(anyhow::anyhow!("本地存储记录失败: {}", e)));
+            }
+        },
+        DhtCommand::GetRecords(key, tx) => {
+            let id = kad.get_record(key);
+            pending.insert(id, PendingQuery::Records(Vec::new(), tx));
+        }
+    }
+}
+
+fn handle_query_result(
+    pending: &mut HashMap<QueryId, PendingQuery>,
+    id: QueryId,
+    result: kad::QueryResult,
+    last: bool,
+) {
+    let Some(query) = pending.remove(&id) else {
+        return;
+    };
+
+    // 记录已保存在本地存储中，复制到远端失败不影响本节点应答查询
+    let query = match (query, result) {
+        (PendingQuery::Provide(tx), kad::QueryResult::StartProviding(result)) => {
+            if let Err(e) = result {
+                log::warn!("provider记录未能复制到远端节点: {}", e);
+            }
+            let _ = tx.send(Ok(()));
+            return;
+        }
+        (PendingQuery::Put(tx), kad::QueryResult::PutRecord(result)) => {
+            if let Err(e) = result {
+                log::warn!("DHT记录未能复制到远端节点: {}", e);
+            }
+            let _ = tx.send(Ok(()));
+            return;
+        }
+        (PendingQuery::Providers(mut found, tx), kad::QueryResult::GetProviders(result)) => {
+            if let Ok(kad::GetProvidersOk::FoundProviders { providers, .. }) = result {
+                found.extend(providers);
+            }
+            PendingQuery::Providers(found, tx)
+        }
+        (PendingQuery::Records(mut found, tx), kad::QueryResult::GetRecord(result)) => {
+            if let Ok(kad::GetRecordOk::FoundRecord(peer_record)) = result {
+                found.push(peer_record.record.value);
+            }
+            PendingQuery::Records(found, tx)
+        }
+        (query, _) => query,
+    };
+
+    if !last {
+        pending.insert(id, query);
+        return;
+    }
+
+    match query {
+        PendingQuery::Providers(found, tx) => {
+            let _ = tx.send(Ok(found));
+        }
+        PendingQuery::Records(found, tx) => {
+            let _ = tx.send(Ok(found));
+        }
+        PendingQuery::Provide(tx) | PendingQuery::Put(tx) => {
+            let _ = tx.send(Err(anyhow::anyhow!("DHT查询意外结束")));
+        }
+    }
+}
+
+/// 智能体发现（发布能力、按能力查找已验证的候选智能体）
+pub struct AgentDiscovery {
+    dht: Arc<dyn DhtBackend>,
+    clock: SharedClock,
+    record_ttl: Duration,
+    events: Option<AdminEvents>,
+    address_book: Option<AddressBook>,
+}
+
+impl AgentDiscovery {
+    /// 创建智能体发现
+    pub fn new(dht: Arc<dyn DhtBackend>) -> Self {
+        Self::new_with_clock(dht, system_clock())
+    }
+
+    /// 使用指定时间源创建
+    pub fn new_with_clock(dht: Arc<dyn DhtBackend>, clock: SharedClock) -> Self {
+        Self {
+            dht,
+            clock,
+            record_ttl: DEFAULT_AGENT_RECORD_TTL,
+            events: None,
+            address_book: None,
+        }
+    }
+
+    /// 设置发布记录的有效期
+    pub fn with_record_ttl(mut self, ttl: Duration) -> Self {
+        self.record_ttl = ttl;
+        self
+    }
+
+    /// 将查询记录为管理事件（显示在仪表盘的注册表查询中）
+    pub fn with_admin_events(mut self, events: AdminEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// 将查到的有效记录写入地址簿（重启后可直接重连）
+    pub fn with_address_book(mut self, address_book: AddressBook) -> Self {
+        self.address_book = Some(address_book);
+        self
+    }
+
+    /// 发布本智能体的记录，并为每个能力标签宣告provider
+    pub async fn publish(
+        &self,
+        signer: &dyn Signer,
+        cid: &str,
+        capabilities: &[String],
+        addresses: Vec<String>,
+    ) -> Result<AgentRecord> {
+        let peer_id = self.dht.local_peer_id();
+        let record = AgentRecord::new(
+            signer,
+            cid,
+            &peer_id,
+            capabilities,
+            addresses,
+            self.clock.now_secs(),
+            self.record_ttl,
+        )?;
+
+        self.dht.put_record(agent_record_key(&peer_id), record.to_bytes()?).await?;
+        for capability in &record.capabilities {
+            self.dht.start_providing(capability_key(capability)).await?;
+        }
+
+        log::info!("📣 已发布智能体记录: {} 能力: {:?}", record.did, record.capabilities);
+        Ok(record)
+    }
+
+    /// 按能力查找智能体，只返回签名有效、未过期、PeerID与provider一致的记录（最新发布的在前）
+    pub async fn find_agents_by_capability(&self, capability: &str) -> Result<Vec<AgentRecord>> {
+        let providers = self.dht.get_providers(capability_key(capability)).await?;
+        let now = self.clock.now_secs();
+
+        let mut candidates = Vec::new();
+        for provider in providers {
+            let values = match self.dht.get_records(agent_record_key(&provider)).await {
+                Ok(values) => values,
+                Err(e) => {
+                    log::warn!("获取智能体记录失败 {}: {}", provider, e);
+                    continue;
+                }
+            };
+
+            let newest = values.iter()
+                .filter_map(|value| AgentRecord::from_bytes(value).ok())
+                .filter(|record| self.accept(record, &provider, capability, now))
+                .max_by_key(|record| record.published_at);
+
+            match newest {
+                Some(record) => candidates.push(record),
+                None => log::debug!("provider没有有效的智能体记录: {}", provider),
+            }
+        }
+
+        candidates.sort_by_key(|record| std::cmp::Reverse(record.published_at));
+        if let Some(book) = &self.address_book {
+            for record in &candidates {
+                book.record_agent(record);
+            }
+        }
+        log::info!("🔍 能力 {} 找到 {} 个智能体", normalize_capability(capability), candidates.len());
+        if let Some(events) = &self.events {
+            events.publish(AdminEvent::RegistryLookup {
+                query: format!("capability:{}", normalize_capability(capability)),
+                results: candidates.len(),
+                at: now,
+            });
+        }
+        Ok(candidates)
+    }
+
+    fn accept(&self, record: &AgentRecord, provider: &PeerId, capability: &str, now: u64) -> bool {
+        if record.peer_id != provider.to_base58() {
+            log::warn!("智能体记录PeerID与provider不一致: {}", record.did);
+            return false;
+        }
+        if !record.has_capability(capability) || record.is_expired(now) {
+            return false;
+        }
+        match record.verify() {
+            Ok(true) => true,
+            _ => {
+                log::warn!("智能体记录签名无效: {}", record.did);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::clock::MockClock;
+
+    /// 多个节点共享的内存DHT
+    #[derive(Default)]
+    struct SharedDht {
+        providers: std::sync::Mutex<HashMap<RecordKey, HashSet<PeerId>>>,
+        records: std::sync::Mutex<HashMap<RecordKey, Vec<Vec<u8>>>>,
+    }
+
+    struct MemoryDht {
+        peer_id: PeerId,
+        shared: Arc<SharedDht>,
+    }
+
+    #[async_trait]
+    impl DhtBackend for MemoryDht {
+        fn local_peer_id(&self) -> PeerId {
+            self.peer_id
+        }
+
+        async fn start_providing(&self, key: RecordKey) -> Result<()> {
+            self.shared.providers.lock().unwrap().entry(key).or_default().insert(self.peer_id);
+            Ok(())
+        }
+
+        async fn get_providers(&self, key: RecordKey) -> Result<HashSet<PeerId>> {
+            Ok(self.shared.providers.lock().unwrap().get(&key).cloned().unwrap_or_default())
+        }
+
+        async fn put_record(&self, key: RecordKey, value: Vec<u8>) -> Result<()> {
+            self.shared.records.lock().unwrap().insert(key, vec![value]);
+            Ok(())
+        }
+
+        async fn get_records(&self, key: RecordKey) -> Result<Vec<Vec<u8>>> {
+            Ok(self.shared.records.lock().unwrap().get(&key).cloned().unwrap_or_default())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_agents_by_capability() {
+        let shared = Arc::new(SharedDht::default());
+        let clock = MockClock::new(1_000);
+        let node = |shared: &Arc<SharedDht>| AgentDiscovery::new_with_clock(
+            Arc::new(MemoryDht { peer_id: PeerId::random(), shared: shared.clone() }),
+            Arc::new(clock.clone()),
+        );
+
+        let translator = KeyPair::generate().unwrap();
+        let coder = KeyPair::generate().unwrap();
+        node(&shared).publish(&translator, "cid-t", &["Translation ".to_string()], vec![]).await.unwrap();
+        node(&shared).publish(&coder, "cid-c", &["code".to_string()], vec![]).await.unwrap();
+
+        let searcher = node(&shared);
+        let found = searcher.find_agents_by_capability("translation").await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].did, translator.did);
+        assert_eq!(found[0].cid, "cid-t");
+
+        // 篡改的记录被丢弃
+        let key = agent_record_key(&found[0].peer_id.parse().unwrap());
+        let mut forged = found[0].clone();
+        forged.cid = "cid-evil".to_string();
+        shared.records.lock().unwrap().insert(key, vec![forged.to_bytes().unwrap()]);
+        assert!(searcher.find_agents_by_capability("translation").await.unwrap().is_empty());
+
+        // 过期的记录被丢弃
+        clock.advance(DEFAULT_AGENT_RECORD_TTL);
+        assert!(searcher.find_agents_by_capability("code").await.unwrap().is_empty());
+    }
+
+    async fn wait_listen_addr(dht: &KademliaDht) -> Multiaddr {
+        for _ in 0..50 {
+            if let Some(addr) = dht.listen_addrs().await.unwrap().into_iter().next() {
+                return addr;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("DHT未能开始监听");
+    }
+
+    #[tokio::test]
+    async fn test_kademlia_discovery_between_nodes() {
+        let listen: Multiaddr = "/ip4/127.0.0.1/tcp/0".parse().unwrap();
+        let alice_id = LibP2PIdentity::generate().unwrap();
+        let bob_id = LibP2PIdentity::generate().unwrap();
+        let alice_dht = Arc::new(KademliaDht::start(&alice_id, listen.clone()).await.unwrap());
+        let bob_dht = Arc::new(KademliaDht::start(&bob_id, listen).await.unwrap());
+
+        // 没有identify协议时入站连接的地址不可路由，双方互相登记地址
+        bob_dht.add_peer(*alice_id.peer_id(), wait_listen_addr(&alice_dht).await).unwrap();
+        alice_dht.add_peer(*bob_id.peer_id(), wait_listen_addr(&bob_dht).await).unwrap();
+
+        let bob = KeyPair::generate().unwrap();
+        AgentDiscovery::new(bob_dht.clone())
+            .publish(&bob, "cid-bob", &["translation".to_string()], vec![])
+            .await
+            .unwrap();
+
+        // AddProvider请求不等待应答，给对端处理的时间
+        let discovery = AgentDiscovery::new(alice_dht);
+        let mut found = Vec::new();
+        for _ in 0..50 {
+            found = discovery.find_agents_by_capability("translation").await.unwrap();
+            if !found.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].did, bob.did);
+        assert_eq!(found[0].peer_id, bob_id.peer_id_string());
+    }
+}
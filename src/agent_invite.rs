@@ -0,0 +1,316 @@
+// DIAP Rust SDK - 智能体邀请模块
+// 已有智能体签发带有效期的邀请（引导节点、主题、注册表CID），新智能体凭邀请自动配置并广播入网通知，邀请人记为其引荐人
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::SignedMessage;
+use crate::trust_graph::Introduction;
+
+/// 入网通知消息类型标识（PubSubMessageType::Custom）
+pub const INVITE_ANNOUNCE_MESSAGE_TYPE: &str = "invite_announce";
+
+/// 邀请格式版本
+pub const INVITE_VERSION: u32 = 1;
+
+/// 允许的签发时间偏差（秒）
+const INVITE_CLOCK_SKEW: u64 = 300;
+
+/// 邀请中携带的引导配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InviteBootstrap {
+    /// 引导节点多地址
+    pub bootstrap_peers: Vec<String>,
+
+    /// 需要订阅的主题
+    pub topics: Vec<String>,
+
+    /// 注册表CID
+    pub registry_cids: Vec<String>,
+}
+
+/// 签名的邀请
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentInvite {
+    /// 格式版本
+    pub version: u32,
+
+    /// 邀请ID
+    pub invite_id: String,
+
+    /// 邀请人DID
+    pub inviter_did: String,
+
+    /// 邀请人DID文档CID
+    pub inviter_cid: String,
+
+    /// 引导配置
+    pub bootstrap: InviteBootstrap,
+
+    /// 签发时间
+    pub issued_at: u64,
+
+    /// 过期时间
+    pub expires_at: u64,
+
+    /// 邀请人签名（base64）
+    pub signature: String,
+}
+
+impl AgentInvite {
+    /// 签发邀请
+    pub fn new(
+        signer: &dyn Signer,
+        inviter_cid: String,
+        bootstrap: InviteBootstrap,
+        issued_at: u64,
+        ttl_secs: u64,
+    ) -> Result<Self> {
+        Self {
+            version: INVITE_VERSION,
+            invite_id: crate::message_id::new_message_id(),
+            inviter_did: signer.did(),
+            inviter_cid,
+            bootstrap,
+            issued_at,
+            expires_at: issued_at + ttl_secs,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// 校验邀请：版本、签名和有效期
+    pub fn validate(&self, now: u64) -> Result<()> {
+        if self.version > INVITE_VERSION {
+            anyhow::bail!("不支持的邀请版本: {}", self.version);
+        }
+        if !self.verify()? {
+            anyhow::bail!("邀请签名无效: {}", self.invite_id);
+        }
+        if now >= self.expires_at {
+            anyhow::bail!("邀请已过期: {}", self.invite_id);
+        }
+        if self.issued_at > now + INVITE_CLOCK_SKEW {
+            anyhow::bail!("邀请签发时间在未来: {}", self.invite_id);
+        }
+        Ok(())
+    }
+
+    /// 编码为可分享的令牌（URL安全base64）
+    pub fn to_token(&self) -> Result<String> {
+        let json = serde_json::to_vec(self).context("序列化邀请失败")?;
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(json))
+    }
+
+    /// 从令牌解码（不校验签名，请调用validate）
+    pub fn from_token(token: &str) -> Result<Self> {
+        let json = general_purpose::URL_SAFE_NO_PAD.decode(token.trim())
+            .context("解码邀请令牌失败")?;
+        serde_json::from_slice(&json).context("解析邀请失败")
+    }
+}
+
+impl SignedEnvelope for AgentInvite {
+    const KIND: &'static str = "邀请";
+
+    fn signer_did(&self) -> &str {
+        &self.inviter_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 入网通知（新智能体接受邀请后广播）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteAnnouncement {
+    /// 所接受的邀请
+    pub invite: AgentInvite,
+
+    /// 新智能体DID
+    pub did: String,
+
+    /// 新智能体DID文档CID
+    pub cid: String,
+
+    /// 新智能体PeerID
+    pub peer_id: String,
+
+    /// 时间戳
+    pub timestamp: u64,
+
+    /// 新智能体签名（base64）
+    pub signature: String,
+}
+
+impl InviteAnnouncement {
+    /// 创建并签名入网通知
+    pub fn new(
+        signer: &dyn Signer,
+        invite: AgentInvite,
+        cid: String,
+        peer_id: String,
+        timestamp: u64,
+    ) -> Result<Self> {
+        Self {
+            invite,
+            did: signer.did(),
+            cid,
+            peer_id,
+            timestamp,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// 验证通知：新智能体签名、邀请人签名，且在邀请有效期内接受
+    pub fn verify(&self) -> Result<bool> {
+        if !self.invite.verify()? {
+            return Ok(false);
+        }
+        if self.timestamp >= self.invite.expires_at {
+            return Ok(false);
+        }
+        SignedEnvelope::verify(self)
+    }
+
+    /// 对应的引荐关系
+    pub fn introduction(&self) -> Introduction {
+        Introduction {
+            did: self.did.clone(),
+            introducer_did: self.invite.inviter_did.clone(),
+            invite_id: self.invite.invite_id.clone(),
+            introduced_at: self.timestamp,
+        }
+    }
+}
+
+impl SignedEnvelope for InviteAnnouncement {
+    const KIND: &'static str = "入网通知";
+
+    fn signer_did(&self) -> &str {
+        &self.did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+impl SignedMessage for InviteAnnouncement {
+    const MESSAGE_TYPE: &'static str = INVITE_ANNOUNCE_MESSAGE_TYPE;
+}
+
+/// 接受邀请的结果
+#[derive(Debug, Clone)]
+pub struct AcceptedInvite {
+    /// 需要连接的引导节点
+    pub bootstrap_peers: Vec<String>,
+
+    /// 注册表CID
+    pub registry_cids: Vec<String>,
+
+    /// 待广播的入网通知
+    pub announcement: InviteAnnouncement,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::pubsub_authenticator::{AuthenticatedMessage, PubSubMessageType};
+
+    fn bootstrap() -> InviteBootstrap {
+        InviteBootstrap {
+            bootstrap_peers: vec!["/ip4/10.0.0.1/tcp/4001".to_string()],
+            topics: vec!["general".to_string()],
+            registry_cids: vec!["bafyregistry".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_invite_token_roundtrip_and_expiry() {
+        let inviter = KeyPair::generate().unwrap();
+        let invite = AgentInvite::new(&inviter, "bafyinviter".to_string(), bootstrap(), 1_000, 600).unwrap();
+
+        let decoded = AgentInvite::from_token(&invite.to_token().unwrap()).unwrap();
+        assert_eq!(decoded.bootstrap, bootstrap());
+        assert!(decoded.validate(1_100).is_ok());
+        assert!(decoded.validate(1_600).is_err());
+
+        let mut tampered = decoded;
+        tampered.bootstrap.topics.push("admin".to_string());
+        assert!(tampered.validate(1_100).is_err());
+    }
+
+    #[test]
+    fn test_announcement_verification() {
+        let inviter = KeyPair::generate().unwrap();
+        let invitee = KeyPair::generate().unwrap();
+        let invite = AgentInvite::new(&inviter, "bafyinviter".to_string(), bootstrap(), 1_000, 600).unwrap();
+
+        let announcement = InviteAnnouncement::new(&invitee, invite.clone(), "bafyinvitee".to_string(), "peer".to_string(), 1_100).unwrap();
+        assert!(announcement.verify().unwrap());
+        assert_eq!(announcement.introduction().introducer_did, inviter.did);
+
+        // 过期后才接受的邀请不被承认
+        let late = InviteAnnouncement::new(&invitee, invite, "bafyinvitee".to_string(), "peer".to_string(), 1_700).unwrap();
+        assert!(!late.verify().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_onboard_with_invite() {
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+        use libp2p::PeerId;
+
+        let authenticator = || PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None);
+        let inviter_key = KeyPair::generate().unwrap();
+        let invitee_key = KeyPair::generate().unwrap();
+
+        let inviter = authenticator();
+        inviter.set_local_identity(inviter_key.clone(), PeerId::random(), "bafyinviter".to_string()).await.unwrap();
+        let token = inviter.create_invite(bootstrap(), std::time::Duration::from_secs(600)).await.unwrap().to_token().unwrap();
+
+        let invitee = authenticator();
+        invitee.set_local_identity(invitee_key.clone(), PeerId::random(), "bafyinvitee".to_string()).await.unwrap();
+        let accepted = invitee.accept_invite(&AgentInvite::from_token(&token).unwrap()).await.unwrap();
+
+        assert_eq!(accepted.bootstrap_peers, bootstrap().bootstrap_peers);
+        assert_eq!(invitee.get_subscribed_topics().await, vec!["general".to_string()]);
+        assert_eq!(invitee.trust_graph().introducer_of(&invitee_key.did), Some(inviter_key.did.clone()));
+
+        // 其他节点收到入网通知后记录引荐关系
+        let message = AuthenticatedMessage {
+            message_id: "m1".to_string(),
+            message_type: PubSubMessageType::Custom(INVITE_ANNOUNCE_MESSAGE_TYPE.to_string()),
+            from_did: invitee_key.did.clone(),
+            to_did: None,
+            from_peer_id: accepted.announcement.peer_id.clone(),
+            did_cid: "bafyinvitee".to_string(),
+            topic: "general".to_string(),
+            content: accepted.announcement.to_bytes().unwrap(),
+            nonce: String::new(),
+            zkp_proof: Vec::new(),
+            signature: Vec::new(),
+            timestamp: 0,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        };
+        assert!(inviter.handle_invite_announcement(&message).unwrap());
+        assert_eq!(inviter.trust_graph().introduced_by(&inviter_key.did), vec![invitee_key.did]);
+    }
+}
@@ -3,7 +3,12 @@
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::did_revocation::RevocationRegistry;
+use crate::did_update::DidUpdatedEvent;
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::session_token::{SessionToken, SessionTokenClaims, DEFAULT_SESSION_TOKEN_TTL};
 
 /// 智能体验证状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +23,8 @@ pub enum AgentVerificationStatus {
     Failed,
     /// 已过期
     Expired,
+    /// DID已被吊销
+    Revoked,
 }
 
 /// 智能体验证请求
@@ -50,6 +57,9 @@ pub struct AgentVerificationResponse {
     pub verification_timestamp: u64,
     /// 错误信息
     pub error_message: Option<String>,
+    /// 会话令牌（配置了令牌签发者且验证成功时签发，见 session_token 模块）
+    #[serde(default)]
+    pub session_token: Option<String>,
 }
 
 /// 智能体验证管理器
@@ -58,6 +68,12 @@ pub struct AgentVerificationManager {
     noir_circuits_path: String,
     /// 验证记录缓存
     verification_cache: std::collections::HashMap<String, AgentVerificationResponse>,
+    /// 吊销注册表（可选）
+    revocation_registry: Option<RevocationRegistry>,
+    /// 会话令牌签发者（可选）
+    token_issuer: Option<Arc<dyn Signer>>,
+    /// 会话令牌有效期
+    token_ttl: Duration,
 }
 
 impl AgentVerificationManager {
@@ -66,9 +82,53 @@ impl AgentVerificationManager {
         Self {
             noir_circuits_path,
             verification_cache: std::collections::HashMap::new(),
+            revocation_registry: None,
+            token_issuer: None,
+            token_ttl: DEFAULT_SESSION_TOKEN_TTL,
         }
     }
 
+    /// 设置吊销注册表，验证前检查DID是否已被吊销
+    pub fn set_revocation_registry(&mut self, registry: RevocationRegistry) {
+        self.revocation_registry = Some(registry);
+    }
+
+    /// 设置会话令牌签发者：验证成功后签发短期会话令牌，之后的消息可凭令牌跳过ZKP验证
+    pub fn set_token_issuer(&mut self, signer: Arc<dyn Signer>, ttl: Duration) {
+        self.token_issuer = Some(signer);
+        self.token_ttl = ttl;
+    }
+
+    /// 验证本管理器签发的会话令牌：签名有效、未过期，且主体未被吊销
+    pub fn verify_session_token(&self, token: &str, subject: &str, did_cid: &str) -> Result<SessionTokenClaims> {
+        let issuer = self.token_issuer.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("未设置会话令牌签发者"))?;
+        let token = SessionToken::decode(token)?;
+        token.check(subject, did_cid, &[issuer.did()], self.get_current_timestamp())?;
+        if self.revocation_registry.as_ref().is_some_and(|registry| registry.is_revoked(subject)) {
+            anyhow::bail!("DID已被吊销: {}", subject);
+        }
+        Ok(token.claims)
+    }
+
+    /// 处理DID更新事件：移除该DID和旧文档CID相关的验证结果，返回移除数量
+    pub fn apply_did_update(&mut self, event: &DidUpdatedEvent) -> Result<usize> {
+        event.require_valid()?;
+
+        // 缓存键为 agent_id:resource_cid:nonce，agent_id本身是含':'的DID
+        let agent_prefix = format!("{}:", event.did);
+        let stale_cid = event.previous_cid.as_ref().map(|cid| format!(":{}:", cid));
+        let before = self.verification_cache.len();
+        self.verification_cache.retain(|key, _| {
+            !key.starts_with(&agent_prefix) && !stale_cid.as_ref().is_some_and(|cid| key.contains(cid.as_str()))
+        });
+        let removed = before - self.verification_cache.len();
+        if removed > 0 {
+            log::info!("♻️ DID文档已更新，移除验证缓存: {} ({} 条)", event.did, removed);
+        }
+        Ok(removed)
+    }
+
     /// 验证智能体访问权限
     pub async fn verify_agent_access(
         &mut self,
@@ -87,14 +147,31 @@ impl AgentVerificationManager {
                 circuit_output: None,
                 verification_timestamp: self.get_current_timestamp(),
                 error_message: Some("验证请求已过期".to_string()),
+                session_token: None,
             });
         }
 
-        // 检查缓存
+        // 检查吊销状态（先于缓存，已吊销的DID不能沿用旧的验证结果）
         let cache_key = self.generate_cache_key(request);
+        if let Some(did) = self.find_revoked_did(request, agent_private_key) {
+            log::warn!("⛔ DID已被吊销: {}", did);
+            self.verification_cache.remove(&cache_key);
+            return Ok(AgentVerificationResponse {
+                status: AgentVerificationStatus::Revoked,
+                proof: None,
+                public_inputs: None,
+                circuit_output: None,
+                verification_timestamp: self.get_current_timestamp(),
+                error_message: Some(format!("DID已被吊销: {}", did)),
+                session_token: None,
+            });
+        }
+
+        // 检查缓存（缓存中不保存会话令牌，每次返回时重新签发）
         if let Some(cached_response) = self.verification_cache.get(&cache_key) {
             log::info!("📦 使用缓存的验证结果");
-            return Ok(cached_response.clone());
+            let response = cached_response.clone();
+            return self.attach_session_token(response, request, agent_private_key);
         }
 
         // 生成ZKP证明
@@ -107,13 +184,14 @@ impl AgentVerificationManager {
                     circuit_output: Some(proof_data.circuit_output),
                     verification_timestamp: self.get_current_timestamp(),
                     error_message: None,
+                    session_token: None,
                 };
 
                 // 缓存结果
                 self.verification_cache.insert(cache_key, response.clone());
                 
                 log::info!("✅ 智能体验证成功");
-                Ok(response)
+                self.attach_session_token(response, request, agent_private_key)
             }
             Err(e) => {
                 log::error!("❌ 智能体验证失败: {}", e);
@@ -124,6 +202,7 @@ impl AgentVerificationManager {
                     circuit_output: None,
                     verification_timestamp: self.get_current_timestamp(),
                     error_message: Some(e.to_string()),
+                    session_token: None,
                 })
             }
         }
@@ -185,6 +264,7 @@ impl AgentVerificationManager {
                             circuit_output: None,
                             verification_timestamp: self.get_current_timestamp(),
                             error_message: Some(e.to_string()),
+                            session_token: None,
                         });
                     }
                 }
@@ -197,6 +277,7 @@ impl AgentVerificationManager {
                     circuit_output: None,
                     verification_timestamp: self.get_current_timestamp(),
                     error_message: Some("未找到智能体数据".to_string()),
+                    session_token: None,
                 });
             }
         }
@@ -245,6 +326,35 @@ impl AgentVerificationManager {
 
     // 私有方法
 
+    /// 为验证成功的响应签发会话令牌（主体为由私钥派生的DID，绑定资源CID）
+    fn attach_session_token(
+        &self,
+        mut response: AgentVerificationResponse,
+        request: &AgentVerificationRequest,
+        agent_private_key: &[u8],
+    ) -> Result<AgentVerificationResponse> {
+        let Some(issuer) = &self.token_issuer else {
+            return Ok(response);
+        };
+        if !matches!(response.status, AgentVerificationStatus::Verified) {
+            return Ok(response);
+        }
+
+        let private_key: [u8; 32] = agent_private_key.try_into()
+            .map_err(|_| anyhow::anyhow!("私钥长度必须是32字节"))?;
+        let subject = crate::KeyPair::from_private_key(private_key)?.did;
+        let token = SessionToken::issue(
+            issuer.as_ref(),
+            &subject,
+            Some(&request.resource_cid),
+            self.token_ttl,
+            self.get_current_timestamp(),
+        )?;
+        log::debug!("🎫 签发会话令牌: {} (有效期{}秒)", subject, self.token_ttl.as_secs());
+        response.session_token = Some(token.encode());
+        Ok(response)
+    }
+
     /// 生成ZKP证明
     async fn generate_zkp_proof(
         &self,
@@ -301,8 +411,11 @@ impl AgentVerificationManager {
             id: did.to_string(),
             verification_method: vec![],
             authentication: vec![],
+            key_agreement: vec![],
             service: None,
             created: chrono::Utc::now().to_rfc3339(),
+            key_rotation: None,
+            previous_version_cid: None,
         }
     }
 
@@ -318,6 +431,23 @@ impl AgentVerificationManager {
         hash.to_le_bytes().to_vec()
     }
 
+    /// 查找请求中已被吊销的DID（智能体ID或由私钥派生的DID）
+    fn find_revoked_did(&self, request: &AgentVerificationRequest, agent_private_key: &[u8]) -> Option<String> {
+        let registry = self.revocation_registry.as_ref()?;
+
+        if registry.is_revoked(&request.agent_id) {
+            return Some(request.agent_id.clone());
+        }
+
+        let private_key: [u8; 32] = agent_private_key.try_into().ok()?;
+        let keypair = crate::KeyPair::from_private_key(private_key).ok()?;
+        if registry.is_revoked(&keypair.did) {
+            Some(keypair.did)
+        } else {
+            None
+        }
+    }
+
     /// 检查请求是否过期
     fn is_request_expired(&self, request: &AgentVerificationRequest) -> bool {
         let current_time = self.get_current_timestamp();
@@ -378,4 +508,55 @@ mod tests {
         let manager = AgentVerificationManager::new("./noir_circuits".to_string());
         assert_eq!(manager.verification_cache.len(), 0);
     }
+
+    #[tokio::test]
+    async fn test_revoked_agent_rejected() {
+        let keypair = crate::KeyPair::generate().unwrap();
+        let registry = RevocationRegistry::new();
+        registry.revoke(&keypair, "compromised").unwrap();
+
+        let mut manager = AgentVerificationManager::new("./noir_circuits".to_string());
+        manager.set_revocation_registry(registry);
+
+        let request = AgentVerificationRequest {
+            agent_id: "agent_001".to_string(),
+            resource_cid: "QmTestResource".to_string(),
+            challenge_nonce: "challenge_123".to_string(),
+            timestamp: manager.get_current_timestamp(),
+            expiry_seconds: 3600,
+        };
+
+        let response = manager.verify_agent_access(&request, &keypair.private_key, "").await.unwrap();
+        assert!(matches!(response.status, AgentVerificationStatus::Revoked));
+    }
+
+    #[test]
+    fn test_did_update_evicts_cached_results() {
+        let keypair = crate::KeyPair::generate().unwrap();
+        let mut manager = AgentVerificationManager::new("./noir_circuits".to_string());
+        let response = AgentVerificationResponse {
+            status: AgentVerificationStatus::Verified,
+            proof: None,
+            public_inputs: None,
+            circuit_output: None,
+            verification_timestamp: 0,
+            error_message: None,
+            session_token: None,
+        };
+        for key in [
+            format!("{}:bafyold:n1", keypair.did),
+            "agent_002:bafyold:n2".to_string(),
+            "agent_003:bafykeep:n3".to_string(),
+        ] {
+            manager.verification_cache.insert(key, response.clone());
+        }
+
+        let event = DidUpdatedEvent::sign(&keypair, Some("bafyold"), "bafynew", 0).unwrap();
+        assert_eq!(manager.apply_did_update(&event).unwrap(), 2);
+        assert!(manager.verification_cache.contains_key("agent_003:bafykeep:n3"));
+
+        let mut forged = event.clone();
+        forged.previous_cid = Some("bafykeep".to_string());
+        assert!(manager.apply_did_update(&forged).is_err());
+    }
 }
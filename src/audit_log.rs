@@ -0,0 +1,492 @@
+// DIAP Rust SDK - 认证审计日志
+// 仅追加的哈希链日志：记录身份注册、每次消息验证、nonce拒绝和DID吊销（签名者DID与结果），
+// 提供按DID/事件类型/时间的查询，并可定期把日志头锚定到IPFS作为防篡改证据
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::clock::{SharedClock, system_clock};
+use crate::ipfs_client::IpfsClient;
+
+/// 链首记录的前一哈希
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// 审计事件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// 身份注册（DID文档已发布）
+    Registration {
+        cid: String,
+    },
+
+    /// 消息验证尝试
+    Verification {
+        message_id: String,
+        topic: String,
+        verified: bool,
+        provisional: bool,
+        /// 失败原因（验证通过时为空）
+        reasons: Vec<String>,
+    },
+
+    /// nonce被拒绝（重放或过期）
+    NonceRejected {
+        message_id: String,
+        nonce: String,
+        reason: String,
+    },
+
+    /// DID吊销
+    Revocation {
+        reason: String,
+    },
+}
+
+impl AuditEvent {
+    /// 事件类型名（与序列化的type字段一致）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuditEvent::Registration { .. } => "registration",
+            AuditEvent::Verification { .. } => "verification",
+            AuditEvent::NonceRejected { .. } => "nonce_rejected",
+            AuditEvent::Revocation { .. } => "revocation",
+        }
+    }
+
+    /// 事件结果是否为成功
+    pub fn is_success(&self) -> bool {
+        match self {
+            AuditEvent::Registration { .. } | AuditEvent::Revocation { .. } => true,
+            AuditEvent::Verification { verified, .. } => *verified,
+            AuditEvent::NonceRejected { .. } => false,
+        }
+    }
+}
+
+/// 审计记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// 序号（从0开始）
+    pub seq: u64,
+
+    /// 记录时间
+    pub timestamp: u64,
+
+    /// 事件涉及的DID（消息发送者、注册者或被吊销者）
+    pub did: String,
+
+    /// 事件
+    pub event: AuditEvent,
+
+    /// 前一条记录的哈希（hex）
+    pub prev_hash: String,
+
+    /// 本条记录的哈希（hex）：SHA256(prev_hash || 不含hash字段的记录)
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// 重新计算本条记录的哈希
+    pub fn compute_hash(&self) -> Result<String> {
+        let unhashed = AuditRecord {
+            hash: String::new(),
+            ..self.clone()
+        };
+        let data = serde_json::to_vec(&unhashed).context("序列化审计记录失败")?;
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(&data);
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+/// 审计日志查询条件（字段为空表示不限）
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    /// 涉及的DID
+    pub did: Option<String>,
+
+    /// 事件类型（AuditEvent::kind）
+    pub kind: Option<String>,
+
+    /// 只返回失败的事件
+    pub failures_only: bool,
+
+    /// 起始时间（含）
+    pub since: Option<u64>,
+
+    /// 结束时间（含）
+    pub until: Option<u64>,
+
+    /// 最多返回条数（取最新的）
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.did.as_deref().is_none_or(|did| record.did == did)
+            && self.kind.as_deref().is_none_or(|kind| record.event.kind() == kind)
+            && (!self.failures_only || !record.event.is_success())
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp <= until)
+    }
+}
+
+/// 日志头锚定记录（上传到IPFS的内容）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditAnchor {
+    /// 锚定时的记录数
+    pub length: u64,
+
+    /// 锚定时的日志头哈希
+    pub head_hash: String,
+
+    /// 锚定时间
+    pub anchored_at: u64,
+
+    /// 锚定内容在IPFS上的CID（上传后填写，不参与上传内容）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+}
+
+/// 审计日志（可克隆，克隆体共享状态）
+///
+/// 仅追加：记录只能通过record追加，落盘文件为逐行JSON，打开时校验整条哈希链；
+/// 旁路的`.head`文件保存最新日志头，打开时据此发现尾部被截断
+#[derive(Clone)]
+pub struct AuditLog {
+    records: Arc<Mutex<Vec<AuditRecord>>>,
+    anchors: Arc<Mutex<Vec<AuditAnchor>>>,
+    path: Option<PathBuf>,
+    file: Option<Arc<Mutex<std::fs::File>>>,
+    clock: SharedClock,
+}
+
+impl AuditLog {
+    /// 创建内存中的审计日志
+    pub fn new() -> Self {
+        Self {
+            records: Arc::new(Mutex::new(Vec::new())),
+            anchors: Arc::new(Mutex::new(Vec::new())),
+            path: None,
+            file: None,
+            clock: system_clock(),
+        }
+    }
+
+    /// 打开持久化的审计日志（逐行JSON，文件不存在时新建）；哈希链断裂时报错
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建审计日志目录: {:?}", parent))?;
+        }
+
+        let mut records = Vec::new();
+        if path.exists() {
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("无法读取审计日志: {:?}", path))?;
+            for (line_no, line) in std::io::BufReader::new(file).lines().enumerate() {
+                let line = line.with_context(|| format!("无法读取审计日志: {:?}", path))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record: AuditRecord = serde_json::from_str(&line)
+                    .with_context(|| format!("解析审计记录失败（第{}行）", line_no + 1))?;
+                records.push(record);
+            }
+            verify_chain(&records)?;
+        }
+
+        let audit = Self::new();
+        if let Some(anchor) = read_head(&path)? {
+            let length = anchor.length as usize;
+            let head_hash = if length == 0 {
+                Some(GENESIS_HASH)
+            } else {
+                records.get(length - 1).map(|r| r.hash.as_str())
+            };
+            if head_hash != Some(anchor.head_hash.as_str()) {
+                anyhow::bail!("审计日志被截断或改写: 期望至少{}条记录，实际{}条", anchor.length, records.len());
+            }
+            if anchor.cid.is_some() {
+                audit.anchors.lock().unwrap().push(anchor);
+            }
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("无法打开审计日志: {:?}", path))?;
+        *audit.records.lock().unwrap() = records;
+        log::info!("💾 审计日志: {:?} ({}条记录)", path, audit.len());
+        Ok(Self { path: Some(path), file: Some(Arc::new(Mutex::new(file))), ..audit })
+    }
+
+    /// 使用指定时间源
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 追加一条记录；持久化日志先写盘并同步、更新日志头文件，再追加到内存
+    pub fn record(&self, did: &str, event: AuditEvent) -> Result<AuditRecord> {
+        let mut records = self.records.lock().unwrap();
+        let mut record = AuditRecord {
+            seq: records.len() as u64,
+            timestamp: self.clock.now_secs(),
+            did: did.to_string(),
+            event,
+            prev_hash: records.last().map(|r| r.hash.clone()).unwrap_or_else(|| GENESIS_HASH.to_string()),
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash()?;
+
+        if let (Some(path), Some(file)) = (&self.path, &self.file) {
+            let mut line = serde_json::to_vec(&record).context("序列化审计记录失败")?;
+            line.push(b'\n');
+            let mut file = file.lock().unwrap();
+            file.write_all(&line).with_context(|| format!("无法写入审计日志: {:?}", path))?;
+            file.sync_data().with_context(|| format!("同步审计日志失败: {:?}", path))?;
+            write_head(path, &AuditAnchor {
+                length: record.seq + 1,
+                head_hash: record.hash.clone(),
+                anchored_at: record.timestamp,
+                cid: None,
+            })?;
+        }
+
+        records.push(record.clone());
+        Ok(record)
+    }
+
+    /// 追加记录，失败时只记录警告（用于验证流程中，审计失败不影响验证结果）
+    pub(crate) fn record_or_warn(&self, did: &str, event: AuditEvent) {
+        if let Err(e) = self.record(did, event) {
+            log::warn!("⚠️ 写入审计日志失败: {}", e);
+        }
+    }
+
+    /// 记录数
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 日志头哈希（空日志为GENESIS_HASH）
+    pub fn head(&self) -> String {
+        self.records.lock().unwrap()
+            .last()
+            .map(|r| r.hash.clone())
+            .unwrap_or_else(|| GENESIS_HASH.to_string())
+    }
+
+    /// 按序号获取记录
+    pub fn get(&self, seq: u64) -> Option<AuditRecord> {
+        self.records.lock().unwrap().get(seq as usize).cloned()
+    }
+
+    /// 查询记录（按时间顺序，limit时取最新的若干条）
+    pub fn query(&self, query: &AuditQuery) -> Vec<AuditRecord> {
+        let records = self.records.lock().unwrap();
+        let mut matched: Vec<AuditRecord> = records.iter().filter(|r| query.matches(r)).cloned().collect();
+        if let Some(limit) = query.limit {
+            let skip = matched.len().saturating_sub(limit);
+            matched.drain(..skip);
+        }
+        matched
+    }
+
+    /// 与某DID相关的全部记录
+    pub fn records_for(&self, did: &str) -> Vec<AuditRecord> {
+        self.query(&AuditQuery { did: Some(did.to_string()), ..Default::default() })
+    }
+
+    /// 校验整条哈希链
+    pub fn verify(&self) -> Result<()> {
+        verify_chain(&self.records.lock().unwrap())
+    }
+
+    /// 把当前日志头锚定到IPFS，返回锚定记录（含CID）
+    pub async fn anchor(&self, ipfs_client: &IpfsClient) -> Result<AuditAnchor> {
+        let mut anchor = {
+            let records = self.records.lock().unwrap();
+            AuditAnchor {
+                length: records.len() as u64,
+                head_hash: records.last().map(|r| r.hash.clone()).unwrap_or_else(|| GENESIS_HASH.to_string()),
+                anchored_at: self.clock.now_secs(),
+                cid: None,
+            }
+        };
+        let json = serde_json::to_string_pretty(&anchor).context("序列化审计锚定失败")?;
+        let result = ipfs_client.upload(&json, "audit-anchor.json").await
+            .context("上传审计锚定到IPFS失败")?;
+
+        log::info!("⚓ 审计日志头已锚定: #{} {} -> {}", anchor.length, anchor.head_hash, result.cid);
+        anchor.cid = Some(result.cid);
+        if let Some(path) = &self.path {
+            // 锚定期间有新记录时保留更新的日志头
+            let records = self.records.lock().unwrap();
+            if records.len() as u64 == anchor.length {
+                write_head(path, &anchor)?;
+            }
+        }
+        self.anchors.lock().unwrap().push(anchor.clone());
+        Ok(anchor)
+    }
+
+    /// 已完成的锚定（最新的在后）
+    pub fn anchors(&self) -> Vec<AuditAnchor> {
+        self.anchors.lock().unwrap().clone()
+    }
+
+    /// 检查锚定记录与当前日志一致（锚定之后的记录被改写时失败）
+    pub fn check_anchor(&self, anchor: &AuditAnchor) -> Result<bool> {
+        self.verify()?;
+        let records = self.records.lock().unwrap();
+        Ok(match anchor.length {
+            0 => anchor.head_hash == GENESIS_HASH,
+            length => records.get(length as usize - 1).is_some_and(|r| r.hash == anchor.head_hash),
+        })
+    }
+
+    /// 定期锚定日志头（日志头未变化时跳过）
+    pub fn spawn_anchoring(&self, ipfs_client: IpfsClient, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let audit = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let last = audit.anchors.lock().unwrap().last().map(|a| a.head_hash.clone());
+                if last.as_deref() == Some(audit.head().as_str()) {
+                    continue;
+                }
+                if let Err(e) = audit.anchor(&ipfs_client).await {
+                    log::warn!("⚠️ 锚定审计日志失败: {}", e);
+                }
+            }
+        })
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 日志头文件路径（audit.jsonl -> audit.jsonl.head）
+fn head_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".head");
+    PathBuf::from(name)
+}
+
+/// 读取持久化的日志头（文件不存在时为None）
+fn read_head(path: &Path) -> Result<Option<AuditAnchor>> {
+    let head_path = head_path(path);
+    if !head_path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read(&head_path)
+        .with_context(|| format!("无法读取审计日志头: {:?}", head_path))?;
+    let anchor = serde_json::from_slice(&content).context("解析审计日志头失败")?;
+    Ok(Some(anchor))
+}
+
+/// 原子写入日志头（先写临时文件并同步，再替换）
+fn write_head(path: &Path, anchor: &AuditAnchor) -> Result<()> {
+    let head_path = head_path(path);
+    let temp_path = head_path.with_extension("head.tmp");
+    let content = serde_json::to_vec(anchor).context("序列化审计日志头失败")?;
+    {
+        let mut temp = std::fs::File::create(&temp_path)
+            .with_context(|| format!("无法写入审计日志头: {:?}", temp_path))?;
+        temp.write_all(&content)?;
+        temp.sync_all()?;
+    }
+    std::fs::rename(&temp_path, &head_path)
+        .with_context(|| format!("无法替换审计日志头: {:?}", head_path))?;
+    Ok(())
+}
+
+/// 校验哈希链：序号连续、前一哈希衔接、每条记录哈希正确
+pub fn verify_chain(records: &[AuditRecord]) -> Result<()> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (index, record) in records.iter().enumerate() {
+        if record.seq != index as u64 {
+            anyhow::bail!("审计记录序号不连续: 期望{}，实际{}", index, record.seq);
+        }
+        if record.prev_hash != prev_hash {
+            anyhow::bail!("审计日志哈希链断裂: 第{}条", record.seq);
+        }
+        if record.compute_hash()? != record.hash {
+            anyhow::bail!("审计记录被篡改: 第{}条", record.seq);
+        }
+        prev_hash = record.hash.clone();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_chain_persists_and_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let clock = MockClock::new(1_000);
+        let log = AuditLog::open(&path).unwrap().with_clock(Arc::new(clock.clone()));
+
+        log.record("did:key:alice", AuditEvent::Registration { cid: "bafyalice".to_string() }).unwrap();
+        clock.advance(Duration::from_secs(10));
+        log.record("did:key:mallory", AuditEvent::NonceRejected {
+            message_id: "m1".to_string(),
+            nonce: "n1".to_string(),
+            reason: "重放".to_string(),
+        }).unwrap();
+        log.record("did:key:alice", AuditEvent::Revocation { reason: "密钥泄露".to_string() }).unwrap();
+        assert!(log.verify().is_ok());
+
+        let anchor = AuditAnchor { length: 2, head_hash: log.get(1).unwrap().hash, anchored_at: 1_010, cid: None };
+        assert!(log.check_anchor(&anchor).unwrap());
+
+        let alice = log.records_for("did:key:alice");
+        assert_eq!(alice.len(), 2);
+        let failures = log.query(&AuditQuery { failures_only: true, ..Default::default() });
+        assert_eq!(failures[0].did, "did:key:mallory");
+        let recent = log.query(&AuditQuery { since: Some(1_005), limit: Some(1), ..Default::default() });
+        assert_eq!(recent[0].event.kind(), "revocation");
+
+        // 重新打开后链完整
+        let reopened = AuditLog::open(&path).unwrap();
+        assert_eq!(reopened.len(), 3);
+        assert_eq!(reopened.head(), log.head());
+
+        // 截掉最后一条记录后打开失败
+        let content = std::fs::read_to_string(&path).unwrap();
+        let truncated: String = content.lines().take(2).map(|line| format!("{}\n", line)).collect();
+        std::fs::write(&path, &truncated).unwrap();
+        assert!(AuditLog::open(&path).is_err());
+        std::fs::write(&path, &content).unwrap();
+
+        // 改写中间记录后打开失败
+        let content = std::fs::read_to_string(&path).unwrap().replace("did:key:mallory", "did:key:nobody");
+        std::fs::write(&path, content).unwrap();
+        assert!(AuditLog::open(&path).is_err());
+    }
+}
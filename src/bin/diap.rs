@@ -0,0 +1,234 @@
+// DIAP 命令行工具
+// 用法: diap new <name> [--path <dir>] | diap top [--addr <host:port>] [--token <token>] | diap self-test | diap relay | diap zkp-report <file>
+
+use anyhow::Result;
+use diap_rs_sdk::{
+    ConfigLoader, run_self_test, scaffold_project, AuthenticatedMessage, MigrationReport, RelayConfig, RelayNode, SelfTestOptions,
+    DEFAULT_ADMIN_ADDR, VERSION,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const USAGE: &str = "用法:
+  diap new <name> [--path <dir>]   生成智能体项目（配置文件、处理骨架、Dockerfile）
+  diap top [--addr <host:port>] [--token <token>] [--interval <ms>]
+                                   终端仪表盘，读取本地管理接口（需启用tui特性）
+  diap self-test                   上线前自检（密钥、DID文档、ZKP证明、消息签名闭环）
+  diap relay [--config <file>] [--data-dir <dir>] [--role <role>]... [--listen <addr>]
+             [--service-listen <addr>] [--bootstrap <addr>]... [--mirror <capability>]...
+                                   运行中继/基础设施节点（角色: mailbox, bootstrap, registry-mirror）
+  diap zkp-report <messages.json|messages.jsonl> [--json]
+                                   扫描归档消息，列出仍在发送旧版Arkworks证明的智能体
+  diap config [--config <file>] [--set <section.key=value>]... [--json]
+                                   显示合并默认值、配置文件、DIAP_*环境变量和命令行覆盖后的配置
+  diap --version                   显示SDK版本";
+
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("new") => {
+            let (name, parent_dir) = parse_new_args(&args[1..])?;
+            let root = scaffold_project(&name, &parent_dir)?;
+            println!("已生成 {}", root.display());
+            println!("  cd {} && cargo run", root.display());
+            Ok(())
+        }
+        Some("top") => {
+            let options = parse_top_args(&args[1..])?;
+            run_top(&options)
+        }
+        Some("self-test") => run_self_test_command(),
+        Some("relay") => {
+            let config = parse_relay_args(&args[1..])?;
+            run_relay(config)
+        }
+        Some("zkp-report") => run_zkp_report(&args[1..]),
+        Some("config") => run_config(&args[1..]),
+        Some("--version") | Some("-V") => {
+            println!("diap {}", VERSION);
+            Ok(())
+        }
+        Some("help") | Some("--help") | Some("-h") | None => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(other) => anyhow::bail!("未知命令: {}\n{}", other, USAGE),
+    }
+}
+
+fn parse_new_args(args: &[String]) -> Result<(String, PathBuf)> {
+    let mut name = None;
+    let mut parent_dir = PathBuf::from(".");
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--path" => {
+                parent_dir = iter.next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| anyhow::anyhow!("--path 需要目录参数"))?;
+            }
+            _ if name.is_none() => name = Some(arg.clone()),
+            _ => anyhow::bail!("多余的参数: {}\n{}", arg, USAGE),
+        }
+    }
+    let name = name.ok_or_else(|| anyhow::anyhow!("缺少项目名\n{}", USAGE))?;
+    Ok((name, parent_dir))
+}
+
+/// diap top 参数
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+struct TopOptions {
+    addr: String,
+    token: Option<String>,
+    interval: Duration,
+}
+
+fn parse_top_args(args: &[String]) -> Result<TopOptions> {
+    let mut options = TopOptions {
+        addr: DEFAULT_ADMIN_ADDR.to_string(),
+        token: std::env::var("DIAP_ADMIN_TOKEN").ok(),
+        interval: Duration::from_secs(1),
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let value = iter.next().ok_or_else(|| anyhow::anyhow!("{} 需要参数值", arg));
+        match arg.as_str() {
+            "--addr" => options.addr = value?.clone(),
+            "--token" => options.token = Some(value?.clone()),
+            "--interval" => {
+                let millis: u64 = value?.parse().map_err(|_| anyhow::anyhow!("--interval 需要毫秒数"))?;
+                options.interval = Duration::from_millis(millis.max(100));
+            }
+            _ => anyhow::bail!("未知参数: {}\n{}", arg, USAGE),
+        }
+    }
+    Ok(options)
+}
+
+fn run_config(args: &[String]) -> Result<()> {
+    let (loader, rest) = ConfigLoader::new().with_cli_args(args)?;
+    let json = match rest.as_slice() {
+        [] => false,
+        [flag] if flag == "--json" => true,
+        _ => anyhow::bail!("未知参数: {}\n{}", rest.join(" "), USAGE),
+    };
+    let config = loader.load()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+    } else {
+        println!("{}", toml::to_string_pretty(&config)?);
+    }
+    Ok(())
+}
+
+fn parse_relay_args(args: &[String]) -> Result<RelayConfig> {
+    // 先读配置文件，命令行参数覆盖文件中的值
+    let mut config = match args.iter().position(|arg| arg == "--config") {
+        Some(index) => {
+            let path = args.get(index + 1).ok_or_else(|| anyhow::anyhow!("--config 需要文件参数"))?;
+            RelayConfig::from_file(path)?
+        }
+        None => RelayConfig::default(),
+    };
+    let mut roles = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let value = iter.next().ok_or_else(|| anyhow::anyhow!("{} 需要参数值", arg));
+        match arg.as_str() {
+            "--config" => {}
+            "--data-dir" => config.data_dir = PathBuf::from(value?),
+            "--role" => roles.push(value?.parse()?),
+            "--listen" => config.dht_listen_addr = value?.clone(),
+            "--service-listen" => config.service_listen_addr = value?.clone(),
+            "--bootstrap" => config.bootstrap_peers.push(value?.clone()),
+            "--mirror" => config.mirror_capabilities.push(value?.clone()),
+            _ => anyhow::bail!("未知参数: {}\n{}", arg, USAGE),
+        }
+    }
+    if !roles.is_empty() {
+        config.roles = roles;
+    }
+    Ok(config)
+}
+
+fn run_relay(config: RelayConfig) -> Result<()> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            let node = RelayNode::start(config).await?;
+            println!("中继DID: {}", node.did());
+            println!("PeerID:  {}", node.peer_id());
+            for addr in node.dht_addrs().await? {
+                println!("DHT:     {}/p2p/{}", addr, node.peer_id());
+            }
+            for addr in node.service_addrs() {
+                println!("服务:    {}", addr);
+            }
+            tokio::signal::ctrl_c().await?;
+            println!("{}", serde_json::to_string_pretty(&node.metrics())?);
+            node.shutdown();
+            Ok(())
+        })
+}
+
+fn run_self_test_command() -> Result<()> {
+    let report = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run_self_test(SelfTestOptions::default()));
+    println!("{}", report.summary());
+    if !report.passed() {
+        anyhow::bail!("自检失败: {} 个步骤未通过", report.failures().len());
+    }
+    Ok(())
+}
+
+fn run_zkp_report(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut json = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => anyhow::bail!("多余的参数: {}\n{}", arg, USAGE),
+        }
+    }
+    let path = path.ok_or_else(|| anyhow::anyhow!("缺少消息文件\n{}", USAGE))?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("读取 {} 失败: {}", path.display(), e))?;
+
+    // 支持JSON数组或每行一条消息（JSONL）
+    let messages: Vec<AuthenticatedMessage> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(&content)?
+    } else {
+        content.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?
+    };
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    let report = MigrationReport::from_messages(&messages, now);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report.summary());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+fn run_top(options: &TopOptions) -> Result<()> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(diap_rs_sdk::tui_dashboard::run(&options.addr, options.token.as_deref(), options.interval))
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_top(_options: &TopOptions) -> Result<()> {
+    anyhow::bail!("diap top 需要启用tui特性: cargo install diap-rs-sdk --features tui")
+}
@@ -0,0 +1,161 @@
+// DIAP Rust SDK - 本地块存储
+// 基于文件系统的持久化 CID -> 内容 存储，IpfsClient获取内容时优先查询，成功获取后回填，支持离线或弱网环境下解析已见过的DID文档
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 块存储统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockStoreStats {
+    /// 块数量
+    pub blocks: usize,
+
+    /// 总字节数
+    pub total_bytes: u64,
+}
+
+/// 本地内容寻址块存储
+/// 每个CID对应根目录下的一个文件，写入时先写临时文件再原子重命名
+#[derive(Debug, Clone)]
+pub struct BlockStore {
+    /// 存储根目录
+    root: PathBuf,
+}
+
+impl BlockStore {
+    /// 打开（必要时创建）块存储目录
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("无法创建块存储目录: {:?}", root))?;
+
+        log::info!("📦 块存储已打开: {:?}", root);
+        Ok(Self { root })
+    }
+
+    /// 存储根目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 写入块（已存在时覆盖）
+    pub async fn put(&self, cid: &str, data: &[u8]) -> Result<()> {
+        let path = self.block_path(cid)?;
+        let temp_path = self.root.join(format!(".{}.{}.tmp", cid, uuid::Uuid::new_v4()));
+
+        tokio::fs::write(&temp_path, data).await
+            .with_context(|| format!("写入块失败: {}", cid))?;
+        if let Err(e) = tokio::fs::rename(&temp_path, &path).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e).with_context(|| format!("保存块失败: {}", cid));
+        }
+
+        log::debug!("📦 块已存储: {} ({} 字节)", cid, data.len());
+        Ok(())
+    }
+
+    /// 读取块，不存在时返回None
+    pub async fn get(&self, cid: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.block_path(cid)?;
+        match tokio::fs::read(&path).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("读取块失败: {}", cid)),
+        }
+    }
+
+    /// 是否包含块
+    pub async fn contains(&self, cid: &str) -> bool {
+        match self.block_path(cid) {
+            Ok(path) => tokio::fs::try_exists(&path).await.unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    /// 删除块，返回是否存在
+    pub async fn remove(&self, cid: &str) -> Result<bool> {
+        let path = self.block_path(cid)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("删除块失败: {}", cid)),
+        }
+    }
+
+    /// 列出全部CID
+    pub async fn list(&self) -> Result<Vec<String>> {
+        let mut cids = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.root).await
+            .with_context(|| format!("无法读取块存储目录: {:?}", self.root))?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if Self::validate_cid(&name).is_ok() && entry.file_type().await?.is_file() {
+                cids.push(name);
+            }
+        }
+
+        cids.sort();
+        Ok(cids)
+    }
+
+    /// 获取统计信息
+    pub async fn stats(&self) -> Result<BlockStoreStats> {
+        let mut stats = BlockStoreStats::default();
+        for cid in self.list().await? {
+            let metadata = tokio::fs::metadata(self.root.join(&cid)).await?;
+            stats.blocks += 1;
+            stats.total_bytes += metadata.len();
+        }
+        Ok(stats)
+    }
+
+    fn block_path(&self, cid: &str) -> Result<PathBuf> {
+        Self::validate_cid(cid)?;
+        Ok(self.root.join(cid))
+    }
+
+    /// CID只允许字母数字（同时防止路径穿越）
+    fn validate_cid(cid: &str) -> Result<()> {
+        if cid.is_empty() || cid.len() > 256 || !cid.chars().all(|c| c.is_ascii_alphanumeric()) {
+            anyhow::bail!("无效的CID: {}", cid);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_put_get_remove() {
+        let dir = TempDir::new().unwrap();
+        let store = BlockStore::open(dir.path().join("blocks")).unwrap();
+
+        assert!(store.get("bafytest").await.unwrap().is_none());
+        store.put("bafytest", b"{\"id\":\"did:key:z\"}").await.unwrap();
+        assert!(store.contains("bafytest").await);
+        assert_eq!(store.get("bafytest").await.unwrap().unwrap(), b"{\"id\":\"did:key:z\"}");
+
+        // 重新打开后仍然存在
+        let reopened = BlockStore::open(dir.path().join("blocks")).unwrap();
+        assert_eq!(reopened.list().await.unwrap(), vec!["bafytest".to_string()]);
+        assert_eq!(reopened.stats().await.unwrap().total_bytes, 18);
+
+        assert!(reopened.remove("bafytest").await.unwrap());
+        assert!(!reopened.remove("bafytest").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_cid() {
+        let dir = TempDir::new().unwrap();
+        let store = BlockStore::open(dir.path()).unwrap();
+
+        assert!(store.put("../escape", b"x").await.is_err());
+        assert!(store.get("").await.is_err());
+        assert!(!store.contains("a/b").await);
+    }
+}
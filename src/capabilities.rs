@@ -0,0 +1,430 @@
+// DIAP Rust SDK - 基于能力的授权令牌
+// 智能体A签发能力令牌，授权智能体B在限定时间内调用某个服务端点（主题+消息类型）；
+// B可以把令牌的子集继续委托给C（资源、动作、有效期只能收窄），每一级都由上一级的受众签名，
+// 令牌随认证消息一起发送，接收方在分发请求前验证整条委托链
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::topic_pattern::TopicPattern;
+
+/// 委托链最大深度（根令牌深度为0）
+pub const MAX_DELEGATION_DEPTH: usize = 8;
+
+/// 匹配所有动作
+pub const ANY_ACTION: &str = "*";
+
+/// 签名的能力令牌
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityToken {
+    /// 令牌ID
+    pub id: String,
+
+    /// 签发者DID
+    pub issuer: String,
+
+    /// 受众DID（被授权方）
+    pub audience: String,
+
+    /// 资源（主题名称或通配模式）
+    pub resource: String,
+
+    /// 允许的动作（消息类型，见 message_type_key；"*"表示全部）
+    pub actions: Vec<String>,
+
+    /// 生效时间（Unix秒）
+    pub not_before: u64,
+
+    /// 过期时间（Unix秒）
+    pub expires_at: u64,
+
+    /// 上一级令牌（委托时携带，根令牌为None）
+    #[serde(default)]
+    pub proof: Option<Box<CapabilityToken>>,
+
+    /// 签发者签名（base64）
+    pub signature: String,
+}
+
+impl CapabilityToken {
+    /// 签发根令牌
+    pub fn grant(
+        signer: &dyn Signer,
+        audience: &str,
+        resource: &str,
+        actions: &[&str],
+        not_before: u64,
+        expires_at: u64,
+    ) -> Result<Self> {
+        Self::sign(signer, audience, resource, actions, not_before, expires_at, None)
+    }
+
+    /// 把令牌的子集委托给其他智能体（签名者必须是当前令牌的受众）
+    pub fn delegate(
+        &self,
+        signer: &dyn Signer,
+        audience: &str,
+        resource: &str,
+        actions: &[&str],
+        expires_at: u64,
+    ) -> Result<Self> {
+        if signer.did() != self.audience {
+            anyhow::bail!("只有令牌受众可以继续委托: {}", self.audience);
+        }
+        if self.depth() + 1 > MAX_DELEGATION_DEPTH {
+            anyhow::bail!("委托链超过最大深度: {}", MAX_DELEGATION_DEPTH);
+        }
+        let child = Self::sign(
+            signer,
+            audience,
+            resource,
+            actions,
+            self.not_before,
+            expires_at,
+            Some(Box::new(self.clone())),
+        )?;
+        child.check_attenuation(self)?;
+        Ok(child)
+    }
+
+    fn sign(
+        signer: &dyn Signer,
+        audience: &str,
+        resource: &str,
+        actions: &[&str],
+        not_before: u64,
+        expires_at: u64,
+        proof: Option<Box<CapabilityToken>>,
+    ) -> Result<Self> {
+        TopicPattern::parse(resource)?;
+        if actions.is_empty() {
+            anyhow::bail!("能力令牌至少需要一个动作");
+        }
+        if expires_at <= not_before {
+            anyhow::bail!("能力令牌的过期时间必须晚于生效时间");
+        }
+        Self {
+            id: hex::encode(rand::random::<[u8; 16]>()),
+            issuer: signer.did(),
+            audience: audience.to_string(),
+            resource: resource.to_string(),
+            actions: actions.iter().map(|action| action.to_string()).collect(),
+            not_before,
+            expires_at,
+            proof,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// 委托深度（根令牌为0）
+    pub fn depth(&self) -> usize {
+        self.proof.as_ref().map_or(0, |parent| parent.depth() + 1)
+    }
+
+    /// 委托链的根签发者
+    pub fn root_issuer(&self) -> &str {
+        match &self.proof {
+            Some(parent) => parent.root_issuer(),
+            None => &self.issuer,
+        }
+    }
+
+    /// 令牌本身是否允许对资源执行动作（不检查签名和有效期）
+    pub fn allows(&self, resource: &str, action: &str) -> bool {
+        TopicPattern::parse(&self.resource).is_ok_and(|pattern| pattern.matches(resource))
+            && self.actions.iter().any(|allowed| allowed == ANY_ACTION || allowed == action)
+    }
+
+    /// 验证整条委托链：每一级的签名、有效期，以及相对上一级只做了收窄
+    pub fn verify_chain(&self, now: u64) -> Result<()> {
+        if self.depth() > MAX_DELEGATION_DEPTH {
+            anyhow::bail!("委托链超过最大深度: {}", MAX_DELEGATION_DEPTH);
+        }
+
+        let mut current = self;
+        loop {
+            if !current.verify()? {
+                anyhow::bail!("能力令牌签名无效: {}", current.id);
+            }
+            if now < current.not_before {
+                anyhow::bail!("能力令牌尚未生效: {}", current.id);
+            }
+            if now >= current.expires_at {
+                anyhow::bail!("能力令牌已过期: {}", current.id);
+            }
+
+            match &current.proof {
+                Some(parent) => {
+                    current.check_attenuation(parent)?;
+                    current = parent;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// 检查调用者是否可以凭此令牌对资源执行动作
+    /// trusted_issuers为接收方认可的根签发者（通常是服务所有者自己的DID）
+    pub fn check_invocation(
+        &self,
+        invoker: &str,
+        resource: &str,
+        action: &str,
+        trusted_issuers: &[String],
+        now: u64,
+    ) -> Result<()> {
+        self.verify_chain(now)?;
+        if self.audience != invoker {
+            anyhow::bail!("能力令牌的受众不是调用者: {}", self.audience);
+        }
+        if !trusted_issuers.iter().any(|issuer| issuer == self.root_issuer()) {
+            anyhow::bail!("不信任的根签发者: {}", self.root_issuer());
+        }
+        if !self.allows(resource, action) {
+            anyhow::bail!("能力令牌不允许对{}执行{}", resource, action);
+        }
+        Ok(())
+    }
+
+    /// 编码为可随消息携带的紧凑字符串（base64url JSON）
+    pub fn encode(&self) -> Result<String> {
+        let json = serde_json::to_vec(self).context("序列化能力令牌失败")?;
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(json))
+    }
+
+    /// 从紧凑字符串解码
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let json = general_purpose::URL_SAFE_NO_PAD.decode(encoded)
+            .context("解码能力令牌失败")?;
+        serde_json::from_slice(&json).context("解析能力令牌失败")
+    }
+
+    /// 相对上一级令牌只做了收窄：签发者是上一级的受众，资源、动作、有效期都不超出上一级
+    fn check_attenuation(&self, parent: &CapabilityToken) -> Result<()> {
+        if self.issuer != parent.audience {
+            anyhow::bail!("委托签发者不是上一级令牌的受众: {}", self.issuer);
+        }
+        if !resource_covers(&parent.resource, &self.resource) {
+            anyhow::bail!("委托的资源超出上一级令牌: {} ⊄ {}", self.resource, parent.resource);
+        }
+        let parent_any = parent.actions.iter().any(|action| action == ANY_ACTION);
+        if let Some(extra) = self.actions.iter().find(|action| !parent_any && !parent.actions.contains(action)) {
+            anyhow::bail!("委托的动作超出上一级令牌: {}", extra);
+        }
+        if self.not_before < parent.not_before || self.expires_at > parent.expires_at {
+            anyhow::bail!("委托的有效期超出上一级令牌");
+        }
+        Ok(())
+    }
+}
+
+impl SignedEnvelope for CapabilityToken {
+    const KIND: &'static str = "能力令牌";
+
+    fn signer_did(&self) -> &str {
+        &self.issuer
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 父资源模式是否覆盖子资源模式（子模式匹配的每个主题父模式都匹配）
+fn resource_covers(parent: &str, child: &str) -> bool {
+    let parent: Vec<&str> = parent.split(crate::topic_pattern::TOPIC_SEPARATOR).collect();
+    let child: Vec<&str> = child.split(crate::topic_pattern::TOPIC_SEPARATOR).collect();
+    covers_segments(&parent, &child)
+}
+
+fn covers_segments(parent: &[&str], child: &[&str]) -> bool {
+    match parent.split_first() {
+        None => child.is_empty(),
+        Some((&"**", rest)) => (0..=child.len()).any(|skip| covers_segments(rest, &child[skip..])),
+        Some((&segment, rest)) => match child.split_first() {
+            // 子模式的 ** 可以匹配多段，只有父模式的 ** 能覆盖
+            Some((&first, child_rest)) if first != "**" => {
+                (segment == "*" || segment == first) && covers_segments(rest, child_rest)
+            }
+            _ => false,
+        },
+    }
+}
+
+/// 按主题要求能力令牌：主题名称或通配模式 -> 认可的根签发者
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityPolicy {
+    /// 主题名称或通配模式 -> 认可的根签发者DID
+    #[serde(default)]
+    pub topics: HashMap<String, Vec<String>>,
+}
+
+impl CapabilityPolicy {
+    /// 创建空策略（不要求能力令牌）
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 要求主题（或通配模式）上的消息携带由指定根签发者授权的能力令牌
+    pub fn require_for_topic(mut self, topic: &str, trusted_issuers: &[&str]) -> Result<Self> {
+        TopicPattern::parse(topic)?;
+        self.topics.insert(
+            topic.to_string(),
+            trusted_issuers.iter().map(|issuer| issuer.to_string()).collect(),
+        );
+        Ok(self)
+    }
+
+    /// 主题的能力要求（同时匹配多条时取最具体的模式），None表示不要求令牌
+    pub fn trusted_issuers(&self, topic: &str) -> Option<&[String]> {
+        self.topics.iter()
+            .filter_map(|(pattern, issuers)| {
+                TopicPattern::parse(pattern).ok()
+                    .filter(|pattern| pattern.matches(topic))
+                    .map(|pattern| (pattern.specificity(), issuers))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, issuers)| issuers.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_delegation_chain() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let carol = KeyPair::generate().unwrap();
+        let trusted = vec![alice.did.clone()];
+
+        let root = CapabilityToken::grant(&alice, &bob.did, "services/alice/**", &["translate", "summarize"], NOW, NOW + 3600).unwrap();
+        assert!(root.check_invocation(&bob.did, "services/alice/text", "translate", &trusted, NOW + 10).is_ok());
+        assert!(root.check_invocation(&bob.did, "services/alice/text", "delete", &trusted, NOW + 10).is_err());
+        assert!(root.check_invocation(&carol.did, "services/alice/text", "translate", &trusted, NOW + 10).is_err());
+        assert!(root.check_invocation(&bob.did, "services/alice/text", "translate", &trusted, NOW + 3600).is_err());
+        assert!(root.check_invocation(&bob.did, "services/alice/text", "translate", std::slice::from_ref(&bob.did), NOW).is_err());
+
+        // Bob把翻译能力收窄后委托给Carol
+        let delegated = root.delegate(&bob, &carol.did, "services/alice/text", &["translate"], NOW + 600).unwrap();
+        assert_eq!(delegated.depth(), 1);
+        assert_eq!(delegated.root_issuer(), alice.did);
+        let decoded = CapabilityToken::decode(&delegated.encode().unwrap()).unwrap();
+        assert!(decoded.check_invocation(&carol.did, "services/alice/text", "translate", &trusted, NOW + 10).is_ok());
+        assert!(decoded.check_invocation(&carol.did, "services/alice/text", "summarize", &trusted, NOW + 10).is_err());
+        assert!(decoded.check_invocation(&carol.did, "services/alice/text", "translate", &trusted, NOW + 601).is_err());
+
+        // 不能扩大资源、动作或有效期，也不能由非受众委托
+        assert!(root.delegate(&bob, &carol.did, "services/**", &["translate"], NOW + 600).is_err());
+        assert!(root.delegate(&bob, &carol.did, "services/alice/text", &["delete"], NOW + 600).is_err());
+        assert!(root.delegate(&bob, &carol.did, "services/alice/text", &["translate"], NOW + 7200).is_err());
+        assert!(root.delegate(&carol, &carol.did, "services/alice/text", &["translate"], NOW + 600).is_err());
+
+        // 篡改链中任一级都会使验证失败
+        let mut tampered = delegated.clone();
+        tampered.proof.as_mut().unwrap().actions.push("delete".to_string());
+        assert!(tampered.verify_chain(NOW + 10).is_err());
+        let mut widened = delegated;
+        widened.resource = "services/**".to_string();
+        assert!(widened.verify_chain(NOW + 10).is_err());
+    }
+
+    #[test]
+    fn test_resource_covers() {
+        assert!(resource_covers("a/**", "a/b/c"));
+        assert!(resource_covers("a/**", "a/*/c"));
+        assert!(resource_covers("a/*", "a/b"));
+        assert!(resource_covers("a/*", "a/*"));
+        assert!(!resource_covers("a/*", "a/**"));
+        assert!(!resource_covers("a/b", "a/*"));
+        assert!(!resource_covers("a/*", "a/b/c"));
+    }
+
+    #[test]
+    fn test_policy_most_specific() {
+        let policy = CapabilityPolicy::new()
+            .require_for_topic("services/**", &["did:key:root"]).unwrap()
+            .require_for_topic("services/billing/*", &["did:key:billing"]).unwrap();
+        assert_eq!(policy.trusted_issuers("services/billing/pay"), Some(&["did:key:billing".to_string()][..]));
+        assert_eq!(policy.trusted_issuers("services/text"), Some(&["did:key:root".to_string()][..]));
+        assert_eq!(policy.trusted_issuers("chat"), None);
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_requires_capability() {
+        use crate::did_resolver::DIDResolver;
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::CallbackSigner;
+        use crate::pubsub_authenticator::{PubSubMessageType, PubsubAuthenticator};
+        use crate::verification_hint::{HintSource, VerificationHint, DEFAULT_HINT_TTL};
+        use std::sync::Arc;
+
+        struct StaticHint(VerificationHint);
+
+        #[async_trait::async_trait]
+        impl HintSource for StaticHint {
+            async fn lookup(&self, _did: &str) -> Result<Option<VerificationHint>> {
+                Ok(Some(self.0.clone()))
+            }
+        }
+
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+
+        // Alice的服务只接受持有她签发的能力令牌的请求
+        let document = DIDResolver::resolve_did_key(&bob.did).unwrap();
+        let hint = VerificationHint::sign(&bob, &document, "QmBob", now, DEFAULT_HINT_TTL).unwrap();
+        let client = IpfsClient::new_public_only(1);
+        for gateway in client.public_gateways() {
+            client.remove_gateway(&gateway);
+        }
+        let service = PubsubAuthenticator::new(IdentityManager::new(client), None, None)
+            .with_verification_hints(Arc::new(StaticHint(hint)));
+        service.set_capability_policy(
+            CapabilityPolicy::new().require_for_topic("services/alice/**", &[&alice.did]).unwrap()
+        ).await;
+
+        let token = CapabilityToken::grant(&alice, &bob.did, "services/alice/**", &["ResourceRequest"], now - 10, now + 600).unwrap();
+        let bob_did = bob.did.clone();
+        let signer = CallbackSigner::new(bob.public_key, Arc::new(move |data| bob.sign(data))).unwrap();
+        let client = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None);
+        client.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmBob".to_string()).await.unwrap();
+
+        let request = client.create_invocation("services/alice/translate", PubSubMessageType::ResourceRequest, b"hola", None, &token).await.unwrap();
+        let verification = service.verify_message(&request).await.unwrap();
+        assert!(verification.verified, "{:?}", verification.details);
+
+        // 令牌不允许的动作无法创建，缺少令牌的请求被拒绝
+        assert!(client.create_invocation("services/alice/translate", PubSubMessageType::Heartbeat, b"", None, &token).await.is_err());
+        let bare = client.create_authenticated_message("services/alice/translate", PubSubMessageType::ResourceRequest, b"hola", None).await.unwrap();
+        let verification = service.verify_message(&bare).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d.contains("主题要求能力令牌")));
+
+        // 替换为自签令牌会使消息签名失效，且根签发者不受信任
+        let mut forged = request.clone();
+        let self_signed = KeyPair::generate().unwrap();
+        forged.capability = Some(
+            CapabilityToken::grant(&self_signed, &bob_did, "services/**", &["*"], now - 10, now + 600).unwrap().encode().unwrap()
+        );
+        forged.nonce = bare.nonce.clone() + "x";
+        let verification = service.verify_message(&forged).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d.contains("不信任的根签发者")));
+        assert!(verification.details.iter().any(|d| d.contains("消息签名验证失败")));
+    }
+}
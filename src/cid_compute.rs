@@ -0,0 +1,461 @@
+// DIAP Rust SDK - 确定性CID计算
+// 按Kubo `ipfs add` 的默认规则（UnixFS、256KiB定长分块、平衡布局、sha2-256）在本地计算内容CID，
+// 智能体可以在上传前得知并签署自己的CID，验证方可以离线重新计算；
+// 同时支持CIDv1（raw leaves）、整块raw和dag-cbor编码
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Kubo默认分块大小（size-262144）
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// 平衡布局每个节点的最大链接数（与Kubo一致）
+pub const DEFAULT_LINKS_PER_NODE: usize = 174;
+
+/// multicodec: raw
+const CODEC_RAW: u64 = 0x55;
+/// multicodec: dag-pb
+const CODEC_DAG_PB: u64 = 0x70;
+/// multicodec: dag-cbor
+const CODEC_DAG_CBOR: u64 = 0x71;
+/// multihash: sha2-256
+const MULTIHASH_SHA2_256: u8 = 0x12;
+
+/// CID版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CidVersion {
+    /// base58btc、dag-pb（Qm...）
+    V0,
+    /// base32、带codec前缀（bafy.../bafk...）
+    V1,
+}
+
+/// 内容编码方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CidCodec {
+    /// UnixFS文件（ipfs add）
+    UnixFs,
+    /// 整个内容作为一个raw块（ipfs block put）
+    Raw,
+    /// 按DAG-CBOR编码JSON（ipfs dag put）
+    DagCbor,
+}
+
+/// CID计算参数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CidOptions {
+    /// 编码方式
+    pub codec: CidCodec,
+
+    /// CID版本（Raw和DagCbor只能是V1）
+    pub version: CidVersion,
+
+    /// UnixFS叶子是否使用raw块（Kubo在CIDv1下默认开启）
+    pub raw_leaves: bool,
+
+    /// UnixFS分块大小
+    pub chunk_size: usize,
+}
+
+impl Default for CidOptions {
+    /// 与 `ipfs add` 默认参数一致
+    fn default() -> Self {
+        Self {
+            codec: CidCodec::UnixFs,
+            version: CidVersion::V0,
+            raw_leaves: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
+
+impl CidOptions {
+    /// 与 `ipfs add --cid-version=1` 一致
+    pub fn v1() -> Self {
+        Self {
+            version: CidVersion::V1,
+            raw_leaves: true,
+            ..Self::default()
+        }
+    }
+
+    /// 整块raw（`ipfs block put`）
+    pub fn raw() -> Self {
+        Self {
+            codec: CidCodec::Raw,
+            ..Self::v1()
+        }
+    }
+
+    /// DAG-CBOR（`ipfs dag put`）
+    pub fn dag_cbor() -> Self {
+        Self {
+            codec: CidCodec::DagCbor,
+            ..Self::v1()
+        }
+    }
+
+    /// 校验参数组合
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_size == 0 {
+            anyhow::bail!("分块大小不能为0");
+        }
+        if self.version == CidVersion::V0 && (self.codec != CidCodec::UnixFs || self.raw_leaves) {
+            anyhow::bail!("CIDv0只支持不带raw leaves的UnixFS");
+        }
+        Ok(())
+    }
+}
+
+/// DID文档上传到IPFS时的字节内容（DIDBuilder上传和CID计算共用）
+pub fn document_bytes<T: Serialize>(document: &T) -> Result<Vec<u8>> {
+    serde_json::to_vec_pretty(document).context("序列化DID文档失败")
+}
+
+/// 计算DID文档按默认参数上传后的CID
+pub fn compute_cid<T: Serialize>(document: &T) -> Result<String> {
+    compute_cid_with(document, &CidOptions::default())
+}
+
+/// 按指定参数计算DID文档的CID
+pub fn compute_cid_with<T: Serialize>(document: &T, options: &CidOptions) -> Result<String> {
+    match options.codec {
+        CidCodec::DagCbor => {
+            options.validate()?;
+            let value = serde_json::to_value(document).context("序列化DID文档失败")?;
+            let mut encoded = Vec::new();
+            encode_dag_cbor(&value, &mut encoded)?;
+            Ok(format_cid(options.version, CODEC_DAG_CBOR, &encoded))
+        }
+        _ => compute_content_cid(&document_bytes(document)?, options),
+    }
+}
+
+/// 计算任意内容的CID（UnixFS或raw）
+pub fn compute_content_cid(content: &[u8], options: &CidOptions) -> Result<String> {
+    options.validate()?;
+    match options.codec {
+        CidCodec::Raw => Ok(format_cid(CidVersion::V1, CODEC_RAW, content)),
+        CidCodec::UnixFs => {
+            let root = build_unixfs(content, options);
+            Ok(encode_cid_string(options.version, &root.cid))
+        }
+        CidCodec::DagCbor => anyhow::bail!("DAG-CBOR需要结构化数据，请使用compute_cid_with"),
+    }
+}
+
+/// 检查DID文档是否与CID匹配（依次尝试Kubo常见的上传参数）
+pub fn document_matches_cid<T: Serialize>(document: &T, expected_cid: &str) -> Result<bool> {
+    let candidates = [
+        CidOptions::default(),
+        CidOptions::v1(),
+        CidOptions { raw_leaves: false, ..CidOptions::v1() },
+        CidOptions::dag_cbor(),
+    ];
+    for options in &candidates {
+        if compute_cid_with(document, options)? == expected_cid {
+            log::debug!("CID匹配: {} ({:?})", expected_cid, options);
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// 检查从网关取回的原始字节是否与CID匹配（UnixFS常见参数或raw块；不经过反序列化，字段顺序不受影响）
+pub fn content_matches_cid(content: &[u8], expected_cid: &str) -> Result<bool> {
+    let candidates = [
+        CidOptions::default(),
+        CidOptions::v1(),
+        CidOptions { raw_leaves: false, ..CidOptions::v1() },
+        CidOptions::raw(),
+    ];
+    for options in &candidates {
+        if compute_content_cid(content, options)? == expected_cid {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// ============ UnixFS ============
+
+/// 已编码的DAG节点
+struct DagNode {
+    /// 二进制CID（v0为multihash）
+    cid: Vec<u8>,
+
+    /// 节点及其所有子节点的序列化大小（链接中的Tsize）
+    tsize: u64,
+
+    /// 节点覆盖的文件字节数
+    filesize: u64,
+}
+
+/// 定长分块，按平衡布局自底向上构建DAG，返回根节点
+fn build_unixfs(content: &[u8], options: &CidOptions) -> DagNode {
+    let mut level: Vec<DagNode> = if content.is_empty() {
+        vec![leaf_node(&[], options)]
+    } else {
+        content.chunks(options.chunk_size).map(|chunk| leaf_node(chunk, options)).collect()
+    };
+
+    while level.len() > 1 {
+        level = level
+            .chunks(DEFAULT_LINKS_PER_NODE)
+            .map(|children| parent_node(children, options.version))
+            .collect();
+    }
+    level.remove(0)
+}
+
+fn leaf_node(chunk: &[u8], options: &CidOptions) -> DagNode {
+    if options.raw_leaves {
+        return DagNode {
+            cid: binary_cid(CidVersion::V1, CODEC_RAW, chunk),
+            tsize: chunk.len() as u64,
+            filesize: chunk.len() as u64,
+        };
+    }
+
+    let data = unixfs_file_data(chunk, chunk.len() as u64, &[]);
+    let block = pb_node(&[], &data);
+    DagNode {
+        cid: binary_cid(options.version, CODEC_DAG_PB, &block),
+        tsize: block.len() as u64,
+        filesize: chunk.len() as u64,
+    }
+}
+
+fn parent_node(children: &[DagNode], version: CidVersion) -> DagNode {
+    let filesize = children.iter().map(|child| child.filesize).sum();
+    let blocksizes: Vec<u64> = children.iter().map(|child| child.filesize).collect();
+    let data = unixfs_file_data(&[], filesize, &blocksizes);
+    let block = pb_node(children, &data);
+    DagNode {
+        cid: binary_cid(version, CODEC_DAG_PB, &block),
+        tsize: block.len() as u64 + children.iter().map(|child| child.tsize).sum::<u64>(),
+        filesize,
+    }
+}
+
+/// UnixFS Data消息（Type=File）
+fn unixfs_file_data(data: &[u8], filesize: u64, blocksizes: &[u64]) -> Vec<u8> {
+    let mut out = vec![0x08, 0x02];
+    if !data.is_empty() {
+        out.push(0x12);
+        write_varint(&mut out, data.len() as u64);
+        out.extend_from_slice(data);
+    }
+    out.push(0x18);
+    write_varint(&mut out, filesize);
+    for size in blocksizes {
+        out.push(0x20);
+        write_varint(&mut out, *size);
+    }
+    out
+}
+
+/// dag-pb PBNode（链接在前、Data在后，与Kubo的编码顺序一致）
+fn pb_node(links: &[DagNode], data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for link in links {
+        let mut encoded = vec![0x0a];
+        write_varint(&mut encoded, link.cid.len() as u64);
+        encoded.extend_from_slice(&link.cid);
+        // 文件分块的链接名为空字符串，Kubo仍会编码该字段
+        encoded.extend_from_slice(&[0x12, 0x00, 0x18]);
+        write_varint(&mut encoded, link.tsize);
+
+        out.push(0x12);
+        write_varint(&mut out, encoded.len() as u64);
+        out.extend_from_slice(&encoded);
+    }
+    out.push(0x0a);
+    write_varint(&mut out, data.len() as u64);
+    out.extend_from_slice(data);
+    out
+}
+
+// ============ DAG-CBOR ============
+
+fn encode_dag_cbor(value: &serde_json::Value, out: &mut Vec<u8>) -> Result<()> {
+    use serde_json::Value;
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(false) => out.push(0xf4),
+        Value::Bool(true) => out.push(0xf5),
+        Value::Number(number) => {
+            if let Some(n) = number.as_u64() {
+                cbor_header(out, 0, n);
+            } else if let Some(n) = number.as_i64() {
+                cbor_header(out, 1, (-1 - n) as u64);
+            } else {
+                // DAG-CBOR的浮点数统一使用64位编码
+                let f = number.as_f64().context("无效的数字")?;
+                out.push(0xfb);
+                out.extend_from_slice(&f.to_be_bytes());
+            }
+        }
+        Value::String(s) => {
+            cbor_header(out, 3, s.len() as u64);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            cbor_header(out, 4, items.len() as u64);
+            for item in items {
+                encode_dag_cbor(item, out)?;
+            }
+        }
+        Value::Object(map) => {
+            // 规范顺序：先按键长度，再按字节序
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| a.as_bytes().cmp(b.as_bytes())));
+            cbor_header(out, 5, entries.len() as u64);
+            for (key, item) in entries {
+                cbor_header(out, 3, key.len() as u64);
+                out.extend_from_slice(key.as_bytes());
+                encode_dag_cbor(item, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 写入CBOR头部（主类型 + 参数，最短编码）
+pub(crate) fn cbor_header(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+// ============ CID编码 ============
+
+fn sha256_multihash(block: &[u8]) -> Vec<u8> {
+    let mut multihash = vec![MULTIHASH_SHA2_256, 0x20];
+    multihash.extend_from_slice(&Sha256::digest(block));
+    multihash
+}
+
+fn binary_cid(version: CidVersion, codec: u64, block: &[u8]) -> Vec<u8> {
+    let multihash = sha256_multihash(block);
+    match version {
+        CidVersion::V0 => multihash,
+        CidVersion::V1 => {
+            let mut cid = Vec::with_capacity(multihash.len() + 4);
+            write_varint(&mut cid, 1);
+            write_varint(&mut cid, codec);
+            cid.extend_from_slice(&multihash);
+            cid
+        }
+    }
+}
+
+fn format_cid(version: CidVersion, codec: u64, block: &[u8]) -> String {
+    encode_cid_string(version, &binary_cid(version, codec, block))
+}
+
+fn encode_cid_string(version: CidVersion, cid: &[u8]) -> String {
+    match version {
+        CidVersion::V0 => bs58::encode(cid).into_string(),
+        CidVersion::V1 => format!("b{}", base32_lower(cid)),
+    }
+}
+
+/// RFC 4648 base32（小写、无填充），multibase前缀'b'
+fn base32_lower(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut out = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// 写入无符号LEB128 varint（multiformats / protobuf通用）
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_kubo_vectors() {
+        let defaults = CidOptions::default();
+        assert_eq!(compute_content_cid(b"", &defaults).unwrap(), "QmbFMke1KXqnYyBBWxB74N4c5SBnJMVAiMNRcGu6x1AwQH");
+        assert_eq!(compute_content_cid(b"hello world\n", &defaults).unwrap(), "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o");
+        assert_eq!(compute_content_cid(b"", &CidOptions::v1()).unwrap(), "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku");
+        assert_eq!(compute_content_cid(b"", &CidOptions::raw()).unwrap(), "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku");
+
+        let mut encoded = Vec::new();
+        encode_dag_cbor(&serde_json::json!({}), &mut encoded).unwrap();
+        assert_eq!(format_cid(CidVersion::V1, CODEC_DAG_CBOR, &encoded), "bafyreigbtj4x7ip5legnfznufuopl4sg4knzc2cof6duas4b3q2fy6swua");
+
+        assert!(CidOptions { raw_leaves: true, ..CidOptions::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_multi_chunk_layout() {
+        let content: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let options = CidOptions { chunk_size: 16, ..CidOptions::default() };
+        let root = build_unixfs(&content, &options);
+        assert_eq!(root.filesize, content.len() as u64);
+
+        // 625个叶子 -> 4个中间节点 -> 根节点
+        let single = compute_content_cid(&content, &CidOptions::default()).unwrap();
+        let chunked = compute_content_cid(&content, &options).unwrap();
+        assert_ne!(single, chunked);
+        assert_eq!(chunked, compute_content_cid(&content, &options).unwrap());
+        assert!(compute_content_cid(&content, &CidOptions { chunk_size: 16, ..CidOptions::v1() }).unwrap().starts_with("bafybei"));
+    }
+
+    #[test]
+    fn test_document_cid_roundtrip() {
+        let keypair = crate::key_manager::KeyPair::generate().unwrap();
+        let document = crate::did_resolver::DIDResolver::resolve_did_key(&keypair.did).unwrap();
+        let cid = compute_cid(&document).unwrap();
+        assert!(cid.starts_with("Qm"));
+        assert!(document_matches_cid(&document, &cid).unwrap());
+        assert!(document_matches_cid(&document, &compute_cid_with(&document, &CidOptions::dag_cbor()).unwrap()).unwrap());
+
+        // 网关返回的原始字节直接按CID校验
+        let bytes = document_bytes(&document).unwrap();
+        assert!(content_matches_cid(&bytes, &cid).unwrap());
+        assert!(content_matches_cid(&bytes, &compute_cid_with(&document, &CidOptions::v1()).unwrap()).unwrap());
+        assert!(!content_matches_cid(b"{}", &cid).unwrap());
+
+        let mut tampered = document.clone();
+        tampered.created = "2020-01-01T00:00:00Z".to_string();
+        assert!(!document_matches_cid(&tampered, &cid).unwrap());
+    }
+}
@@ -0,0 +1,327 @@
+// DIAP Rust SDK - 按DID的熔断器
+// 统计每个对端DID连续的验证失败和投递失败，超过阈值后打开熔断，冷却期内直接拒绝，
+// 不再为已失效或恶意的对端反复解析DID文档、验证证明或重试投递；
+// 冷却期结束后进入半开状态放行一次试探，成功则关闭，失败则重新打开
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+use crate::clock::{SharedClock, system_clock};
+
+/// 熔断事件广播通道容量
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// 失败类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// 消息验证失败
+    Verification,
+    /// 消息投递失败
+    Delivery,
+}
+
+/// 熔断配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// 连续验证失败多少次后熔断
+    pub verification_threshold: u32,
+
+    /// 连续投递失败多少次后熔断
+    pub delivery_threshold: u32,
+
+    /// 熔断冷却时间（秒）
+    pub cool_down_secs: u64,
+
+    /// 半开试探再次失败时冷却时间的倍数上限（每次重新打开翻倍）
+    pub max_backoff_factor: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            verification_threshold: 5,
+            delivery_threshold: 3,
+            cool_down_secs: 60,
+            max_backoff_factor: 16,
+        }
+    }
+}
+
+/// 熔断状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum CircuitState {
+    /// 正常放行
+    Closed,
+    /// 熔断中，until之前直接拒绝
+    Open { until: u64 },
+    /// 冷却结束，已放行一次试探，等待结果
+    HalfOpen,
+}
+
+/// 单个DID的熔断状态（供管理接口展示）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitStatus {
+    /// 对端DID
+    pub did: String,
+
+    /// 当前状态
+    pub state: CircuitState,
+
+    /// 连续验证失败次数
+    pub verification_failures: u32,
+
+    /// 连续投递失败次数
+    pub delivery_failures: u32,
+
+    /// 累计打开次数
+    pub times_opened: u32,
+
+    /// 最近一次打开的原因
+    pub last_trip: Option<FailureKind>,
+}
+
+/// 熔断事件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CircuitEvent {
+    /// 熔断打开
+    Opened { did: String, reason: FailureKind, until: u64 },
+    /// 进入半开状态
+    HalfOpened { did: String },
+    /// 恢复正常
+    Closed { did: String },
+}
+
+/// 按DID的熔断器（可克隆，克隆体共享状态）
+#[derive(Clone)]
+pub struct CircuitBreakers {
+    config: CircuitBreakerConfig,
+    circuits: Arc<Mutex<HashMap<String, CircuitStatus>>>,
+    events: broadcast::Sender<CircuitEvent>,
+    clock: SharedClock,
+}
+
+impl CircuitBreakers {
+    /// 创建熔断器
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self::new_with_clock(config, system_clock())
+    }
+
+    /// 使用指定时间源创建
+    pub fn new_with_clock(config: CircuitBreakerConfig, clock: SharedClock) -> Self {
+        Self {
+            config,
+            circuits: Arc::new(Mutex::new(HashMap::new())),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            clock,
+        }
+    }
+
+    /// 熔断配置
+    pub fn config(&self) -> &CircuitBreakerConfig {
+        &self.config
+    }
+
+    /// 订阅熔断事件
+    pub fn subscribe(&self) -> broadcast::Receiver<CircuitEvent> {
+        self.events.subscribe()
+    }
+
+    /// 是否允许与该DID交互；冷却结束时转为半开并放行这一次
+    pub fn allow(&self, did: &str) -> bool {
+        let now = self.clock.now_secs();
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(did) else {
+            return true;
+        };
+        match circuit.state {
+            CircuitState::Closed => true,
+            CircuitState::HalfOpen => false,
+            CircuitState::Open { until } if now < until => false,
+            CircuitState::Open { .. } => {
+                circuit.state = CircuitState::HalfOpen;
+                drop(circuits);
+                log::info!("🔌 熔断半开，放行一次试探: {}", did);
+                self.publish(CircuitEvent::HalfOpened { did: did.to_string() });
+                true
+            }
+        }
+    }
+
+    /// 记录一次成功：清零失败计数，半开状态下关闭熔断
+    pub fn record_success(&self, did: &str) {
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(did) else {
+            return;
+        };
+        let was_tripped = circuit.state != CircuitState::Closed;
+        circuit.state = CircuitState::Closed;
+        circuit.verification_failures = 0;
+        circuit.delivery_failures = 0;
+        drop(circuits);
+        if was_tripped {
+            log::info!("✅ 熔断恢复: {}", did);
+            self.publish(CircuitEvent::Closed { did: did.to_string() });
+        }
+    }
+
+    /// 记录一次失败，返回熔断是否因此打开
+    pub fn record_failure(&self, did: &str, kind: FailureKind) -> bool {
+        let now = self.clock.now_secs();
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(did.to_string()).or_insert_with(|| CircuitStatus {
+            did: did.to_string(),
+            state: CircuitState::Closed,
+            verification_failures: 0,
+            delivery_failures: 0,
+            times_opened: 0,
+            last_trip: None,
+        });
+
+        let (failures, threshold) = match kind {
+            FailureKind::Verification => (&mut circuit.verification_failures, self.config.verification_threshold),
+            FailureKind::Delivery => (&mut circuit.delivery_failures, self.config.delivery_threshold),
+        };
+        *failures = failures.saturating_add(1);
+
+        // 半开试探失败立即重新打开；关闭状态下达到阈值才打开
+        let trip = match circuit.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Closed => *failures >= threshold.max(1),
+            CircuitState::Open { .. } => false,
+        };
+        if !trip {
+            return false;
+        }
+
+        // 连续重新打开时冷却时间翻倍
+        let factor = 2u64.saturating_pow(circuit.times_opened.min(31))
+            .min(u64::from(self.config.max_backoff_factor.max(1)));
+        let until = now + self.config.cool_down_secs.saturating_mul(
+            if circuit.state == CircuitState::HalfOpen { factor } else { 1 }
+        );
+        circuit.state = CircuitState::Open { until };
+        circuit.times_opened += 1;
+        circuit.last_trip = Some(kind);
+        drop(circuits);
+
+        log::warn!("⛔ 熔断打开: {} ({:?}失败过多，至 {})", did, kind, until);
+        self.publish(CircuitEvent::Opened { did: did.to_string(), reason: kind, until });
+        true
+    }
+
+    /// 归还半开试探而不计成败（试探结果不能归到该DID名下时），下次allow可再次试探
+    pub fn release_probe(&self, did: &str) {
+        let now = self.clock.now_secs();
+        if let Some(circuit) = self.circuits.lock().unwrap().get_mut(did) {
+            if circuit.state == CircuitState::HalfOpen {
+                circuit.state = CircuitState::Open { until: now };
+            }
+        }
+    }
+
+    /// 当前状态（从未失败过的DID为Closed）
+    pub fn state(&self, did: &str) -> CircuitState {
+        self.circuits.lock().unwrap().get(did)
+            .map(|circuit| circuit.state.clone())
+            .unwrap_or(CircuitState::Closed)
+    }
+
+    /// 所有有失败记录的DID（打开的在前）
+    pub fn snapshot(&self) -> Vec<CircuitStatus> {
+        let mut circuits: Vec<CircuitStatus> = self.circuits.lock().unwrap().values().cloned().collect();
+        circuits.sort_by_key(|circuit| (circuit.state == CircuitState::Closed, circuit.did.clone()));
+        circuits
+    }
+
+    /// 手动重置某个DID（例如运维确认对端已恢复）
+    pub fn reset(&self, did: &str) -> bool {
+        let removed = self.circuits.lock().unwrap().remove(did);
+        let was_tripped = removed.as_ref().is_some_and(|circuit| circuit.state != CircuitState::Closed);
+        if was_tripped {
+            self.publish(CircuitEvent::Closed { did: did.to_string() });
+        }
+        removed.is_some()
+    }
+
+    fn publish(&self, event: CircuitEvent) {
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.events.send(event);
+    }
+}
+
+impl Default for CircuitBreakers {
+    fn default() -> Self {
+        Self::new(CircuitBreakerConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use std::time::Duration;
+
+    #[test]
+    fn test_open_half_open_and_close() {
+        let clock = MockClock::new(1_000);
+        let config = CircuitBreakerConfig { delivery_threshold: 2, cool_down_secs: 30, ..Default::default() };
+        let breakers = CircuitBreakers::new_with_clock(config, Arc::new(clock.clone()));
+        let mut events = breakers.subscribe();
+
+        assert!(!breakers.record_failure("did:key:bob", FailureKind::Delivery));
+        breakers.record_success("did:key:bob");
+        assert!(!breakers.record_failure("did:key:bob", FailureKind::Delivery), "成功后计数清零");
+        assert!(breakers.record_failure("did:key:bob", FailureKind::Delivery));
+        assert_eq!(breakers.state("did:key:bob"), CircuitState::Open { until: 1_030 });
+        assert!(!breakers.allow("did:key:bob"));
+        assert!(breakers.allow("did:key:alice"));
+
+        // 冷却结束后只放行一次试探，试探失败时冷却时间翻倍
+        clock.advance(Duration::from_secs(30));
+        assert!(breakers.allow("did:key:bob"));
+        assert!(!breakers.allow("did:key:bob"));
+        assert!(breakers.record_failure("did:key:bob", FailureKind::Verification));
+        assert_eq!(breakers.state("did:key:bob"), CircuitState::Open { until: 1_090 });
+
+        clock.advance(Duration::from_secs(60));
+        assert!(breakers.allow("did:key:bob"));
+        breakers.record_success("did:key:bob");
+        assert_eq!(breakers.state("did:key:bob"), CircuitState::Closed);
+
+        let received: Vec<CircuitEvent> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        assert_eq!(received, vec![
+            CircuitEvent::Opened { did: "did:key:bob".to_string(), reason: FailureKind::Delivery, until: 1_030 },
+            CircuitEvent::HalfOpened { did: "did:key:bob".to_string() },
+            CircuitEvent::Opened { did: "did:key:bob".to_string(), reason: FailureKind::Verification, until: 1_090 },
+            CircuitEvent::HalfOpened { did: "did:key:bob".to_string() },
+            CircuitEvent::Closed { did: "did:key:bob".to_string() },
+        ]);
+
+        let snapshot = breakers.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].times_opened, 2);
+        assert!(breakers.reset("did:key:bob"));
+        assert!(breakers.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_release_probe() {
+        let clock = MockClock::new(1_000);
+        let config = CircuitBreakerConfig { verification_threshold: 1, cool_down_secs: 30, ..Default::default() };
+        let breakers = CircuitBreakers::new_with_clock(config, Arc::new(clock.clone()));
+        breakers.record_failure("did:key:bob", FailureKind::Verification);
+
+        // 归还的试探不计成败，冷却时间不翻倍
+        clock.advance(Duration::from_secs(30));
+        assert!(breakers.allow("did:key:bob"));
+        breakers.release_probe("did:key:bob");
+        assert!(breakers.allow("did:key:bob"));
+        assert!(breakers.record_failure("did:key:bob", FailureKind::Verification));
+        assert_eq!(breakers.state("did:key:bob"), CircuitState::Open { until: 1_090 });
+    }
+}
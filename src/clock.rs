@@ -0,0 +1,133 @@
+// DIAP Rust SDK - 时间源抽象
+// 统一提供时间戳，便于在nonce、消息和缓存中注入确定性时间进行测试，或使用不回退的单调时钟
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 时间源
+pub trait Clock: Send + Sync {
+    /// 当前Unix时间（毫秒）
+    fn now_millis(&self) -> u64;
+
+    /// 当前Unix时间（秒）
+    fn now_secs(&self) -> u64 {
+        self.now_millis() / 1000
+    }
+}
+
+/// 共享时间源
+pub type SharedClock = Arc<dyn Clock>;
+
+/// 默认时间源（系统时钟）
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// 系统时钟（可能因NTP校时而回退）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+/// 单调时钟
+/// 创建时以系统时间为起点，之后按单调计时器前进，保证时间戳不回退
+#[derive(Debug, Clone)]
+pub struct MonotonicClock {
+    /// 起点的Unix时间（毫秒）
+    base_millis: u64,
+
+    /// 起点的单调时刻
+    started: Instant,
+}
+
+impl MonotonicClock {
+    /// 以当前系统时间为起点创建单调时钟
+    pub fn new() -> Self {
+        Self {
+            base_millis: SystemClock.now_millis(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_millis(&self) -> u64 {
+        self.base_millis + self.started.elapsed().as_millis() as u64
+    }
+}
+
+/// 测试时钟（手动设置和推进时间）
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    /// 当前Unix时间（毫秒）
+    millis: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    /// 创建测试时钟，初始时间为指定的Unix秒
+    pub fn new(start_secs: u64) -> Self {
+        Self {
+            millis: Arc::new(AtomicU64::new(start_secs * 1000)),
+        }
+    }
+
+    /// 设置当前时间（Unix秒）
+    pub fn set(&self, secs: u64) {
+        self.millis.store(secs * 1000, Ordering::SeqCst);
+    }
+
+    /// 推进时间
+    pub fn advance(&self, duration: Duration) {
+        self.millis.fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_millis(&self) -> u64 {
+        self.millis.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new(1_000);
+        assert_eq!(clock.now_secs(), 1_000);
+
+        clock.advance(Duration::from_millis(1_500));
+        assert_eq!(clock.now_millis(), 1_001_500);
+        assert_eq!(clock.now_secs(), 1_001);
+
+        // 克隆共享同一时间
+        let shared = clock.clone();
+        shared.set(5);
+        assert_eq!(clock.now_secs(), 5);
+    }
+
+    #[test]
+    fn test_monotonic_clock() {
+        let clock = MonotonicClock::new();
+        let first = clock.now_millis();
+        let second = clock.now_millis();
+
+        assert!(second >= first);
+        assert!(first.abs_diff(SystemClock.now_millis()) < 1_000);
+    }
+}
@@ -0,0 +1,291 @@
+// DIAP Rust SDK - 跨智能体时钟同步估计
+// 类似NTP的轻量交换：发起方记录发送时间t1，对方记录接收时间t2和回复时间t3，发起方在t4收到回复，
+// 偏移 = ((t2 - t1) + (t3 - t4)) / 2，往返时延 = (t4 - t1) - (t3 - t2)；
+// 每个对等方保留最近几次样本，取往返时延最小的一次作为估计，在验证其时间戳和nonce时校正本地时间
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::pubsub_authenticator::{AuthenticatedMessage, PubSubMessageType};
+
+/// 时钟同步消息类型标识（PubSubMessageType::Custom）
+pub const TIME_SYNC_MESSAGE_TYPE: &str = "time_sync";
+
+/// 每个对等方保留的样本数
+pub const MAX_SAMPLES_PER_PEER: usize = 8;
+
+/// 默认允许的最大校正量（对方声称的偏移超过该值时截断，避免单个对等方借此放宽窗口）
+pub const DEFAULT_MAX_CORRECTION: Duration = Duration::from_secs(600);
+
+/// 默认样本有效期
+pub const DEFAULT_SAMPLE_TTL: Duration = Duration::from_secs(3600);
+
+/// 未回复请求的最长等待时间
+const PENDING_REQUEST_TTL_MS: u64 = 30_000;
+
+/// 时钟同步报文
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeSyncPacket {
+    /// 请求（t1：发起方发送时间，毫秒）
+    Request {
+        request_id: String,
+        origin_ms: u64,
+    },
+
+    /// 回复（t2：对方接收时间，t3：对方回复时间，毫秒）
+    Response {
+        request_id: String,
+        origin_ms: u64,
+        receive_ms: u64,
+        transmit_ms: u64,
+    },
+}
+
+impl TimeSyncPacket {
+    /// 序列化为消息内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化时钟同步报文失败")
+    }
+
+    /// 从认证消息中解析时钟同步报文
+    pub fn from_message(message: &AuthenticatedMessage) -> Result<Self> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == TIME_SYNC_MESSAGE_TYPE => {}
+            _ => anyhow::bail!("不是时钟同步消息: {}", message.message_id),
+        }
+        serde_json::from_slice(&message.content).context("解析时钟同步报文失败")
+    }
+}
+
+/// 单次偏移估计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffsetSample {
+    /// 对方时钟相对本地时钟的偏移（毫秒，对方超前为正）
+    pub offset_ms: i64,
+
+    /// 往返时延（毫秒）
+    pub rtt_ms: u64,
+
+    /// 采样时间（本地毫秒）
+    pub sampled_at: u64,
+}
+
+/// 各对等方的时钟偏移估计
+pub struct PeerClockOffsets {
+    samples: Mutex<HashMap<String, VecDeque<OffsetSample>>>,
+    /// request_id -> (对方DID, 发送时间)
+    pending: Mutex<HashMap<String, (String, u64)>>,
+    max_correction: Duration,
+    sample_ttl: Duration,
+}
+
+impl Default for PeerClockOffsets {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CORRECTION, DEFAULT_SAMPLE_TTL)
+    }
+}
+
+impl PeerClockOffsets {
+    /// 创建偏移估计表
+    pub fn new(max_correction: Duration, sample_ttl: Duration) -> Self {
+        Self {
+            samples: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            max_correction,
+            sample_ttl,
+        }
+    }
+
+    /// 发起同步请求
+    pub fn start_request(&self, peer_did: &str, now_ms: u64) -> TimeSyncPacket {
+        let request_id = hex::encode(rand::random::<[u8; 8]>());
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, (_, sent)| now_ms.saturating_sub(*sent) < PENDING_REQUEST_TTL_MS);
+        pending.insert(request_id.clone(), (peer_did.to_string(), now_ms));
+        TimeSyncPacket::Request { request_id, origin_ms: now_ms }
+    }
+
+    /// 回复对方的同步请求（receive_ms为收到请求的时间，transmit_ms为回复时间）
+    pub fn respond(request: &TimeSyncPacket, receive_ms: u64, transmit_ms: u64) -> Result<TimeSyncPacket> {
+        match request {
+            TimeSyncPacket::Request { request_id, origin_ms } => Ok(TimeSyncPacket::Response {
+                request_id: request_id.clone(),
+                origin_ms: *origin_ms,
+                receive_ms,
+                transmit_ms,
+            }),
+            TimeSyncPacket::Response { .. } => anyhow::bail!("只能回复时钟同步请求"),
+        }
+    }
+
+    /// 处理对方的回复（arrival_ms为收到回复的本地时间），返回本次样本
+    /// 只接受本地发出且尚未处理的请求的回复，回复方必须是请求的对象
+    pub fn record_response(&self, peer_did: &str, response: &TimeSyncPacket, arrival_ms: u64) -> Result<OffsetSample> {
+        let TimeSyncPacket::Response { request_id, origin_ms, receive_ms, transmit_ms } = response else {
+            anyhow::bail!("不是时钟同步回复");
+        };
+
+        let (expected_peer, sent_ms) = self.pending.lock().unwrap().remove(request_id)
+            .ok_or_else(|| anyhow::anyhow!("未知或已处理的时钟同步请求: {}", request_id))?;
+        if expected_peer != peer_did || sent_ms != *origin_ms {
+            anyhow::bail!("时钟同步回复与请求不匹配");
+        }
+        if arrival_ms < sent_ms || transmit_ms < receive_ms {
+            anyhow::bail!("时钟同步回复的时间戳无效");
+        }
+
+        let (t1, t2, t3, t4) = (sent_ms as i64, *receive_ms as i64, *transmit_ms as i64, arrival_ms as i64);
+        let sample = OffsetSample {
+            offset_ms: ((t2 - t1) + (t3 - t4)) / 2,
+            rtt_ms: ((t4 - t1) - (t3 - t2)).max(0) as u64,
+            sampled_at: arrival_ms,
+        };
+
+        let mut samples = self.samples.lock().unwrap();
+        let peer_samples = samples.entry(peer_did.to_string()).or_default();
+        if peer_samples.len() >= MAX_SAMPLES_PER_PEER {
+            peer_samples.pop_front();
+        }
+        peer_samples.push_back(sample);
+
+        log::debug!("🕐 {} 的时钟偏移 {}ms（往返 {}ms）", peer_did, sample.offset_ms, sample.rtt_ms);
+        Ok(sample)
+    }
+
+    /// 对方时钟偏移的最佳估计（未过期样本中往返时延最小的一次，未截断）
+    pub fn estimate(&self, peer_did: &str, now_ms: u64) -> Option<OffsetSample> {
+        let ttl_ms = self.sample_ttl.as_millis() as u64;
+        self.samples.lock().unwrap()
+            .get(peer_did)?
+            .iter()
+            .filter(|sample| now_ms.saturating_sub(sample.sampled_at) < ttl_ms)
+            .min_by_key(|sample| sample.rtt_ms)
+            .copied()
+    }
+
+    /// 用于校正的偏移（毫秒，截断到最大校正量；没有估计时为0）
+    /// 时间戳以秒为单位，校正必须在毫秒上进行后再取整，否则会多出最多1秒的误差
+    pub fn correction_ms(&self, peer_did: &str, now_ms: u64) -> i64 {
+        let max = self.max_correction.as_millis() as i64;
+        self.estimate(peer_did, now_ms)
+            .map_or(0, |sample| sample.offset_ms.clamp(-max, max))
+    }
+
+    /// 以对方时钟表示的当前时间（秒）
+    pub fn peer_now_secs(&self, peer_did: &str, now_ms: u64) -> u64 {
+        now_ms.saturating_add_signed(self.correction_ms(peer_did, now_ms)) / 1000
+    }
+
+    /// 移除对等方的所有样本
+    pub fn forget(&self, peer_did: &str) {
+        self.samples.lock().unwrap().remove(peer_did);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_estimate() {
+        let offsets = PeerClockOffsets::default();
+        let peer = "did:key:peer";
+        let local = 1_700_000_000_000u64;
+        // 对方时钟超前120秒，单程时延分别为50ms和5ms
+        let skew = 120_000u64;
+
+        let slow = offsets.start_request(peer, local);
+        let response = PeerClockOffsets::respond(&slow, local + 50 + skew, local + 60 + skew).unwrap();
+        let sample = offsets.record_response(peer, &response, local + 110).unwrap();
+        assert_eq!(sample.rtt_ms, 100);
+        assert_eq!(sample.offset_ms, 120_000);
+        assert!(offsets.record_response(peer, &response, local + 110).is_err(), "重复的回复应被拒绝");
+
+        let fast = offsets.start_request(peer, local + 1_000);
+        let response = PeerClockOffsets::respond(&fast, local + 1_005 + skew, local + 1_006 + skew + 2).unwrap();
+        offsets.record_response(peer, &response, local + 1_011).unwrap();
+        let best = offsets.estimate(peer, local + 2_000).unwrap();
+        assert_eq!(best.rtt_ms, 8);
+        assert_eq!(offsets.correction_ms(peer, local + 2_000), 120_001);
+        assert_eq!(offsets.peer_now_secs(peer, local + 2_000), (local + 2_000) / 1000 + 120);
+
+        // 回复方不是请求对象
+        let request = offsets.start_request(peer, local);
+        let response = PeerClockOffsets::respond(&request, local, local).unwrap();
+        assert!(offsets.record_response("did:key:other", &response, local).is_err());
+
+        // 校正量有上限，样本过期后不再使用
+        let capped = PeerClockOffsets::new(Duration::from_secs(60), Duration::from_secs(10));
+        let request = capped.start_request(peer, local);
+        let response = PeerClockOffsets::respond(&request, local + skew, local + skew).unwrap();
+        capped.record_response(peer, &response, local).unwrap();
+        assert_eq!(capped.correction_ms(peer, local), 60_000);
+        assert_eq!(capped.correction_ms(peer, local + 10_000), 0);
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_accepts_skewed_peer_after_sync() {
+        use crate::clock::MockClock;
+        use crate::did_resolver::DIDResolver;
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::{CallbackSigner, KeyPair};
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+        use crate::verification_hint::{HintSource, VerificationHint, DEFAULT_HINT_TTL};
+        use std::sync::Arc;
+
+        struct StaticHint(VerificationHint);
+
+        #[async_trait::async_trait]
+        impl HintSource for StaticHint {
+            async fn lookup(&self, _did: &str) -> Result<Option<VerificationHint>> {
+                Ok(Some(self.0.clone()))
+            }
+        }
+
+        fn offline_client() -> IpfsClient {
+            let client = IpfsClient::new_public_only(1);
+            for gateway in client.public_gateways() {
+                client.remove_gateway(&gateway);
+            }
+            client
+        }
+
+        async fn with_signer(authenticator: PubsubAuthenticator, keypair: KeyPair) -> PubsubAuthenticator {
+            let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+            authenticator.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmCurrent".to_string()).await.unwrap();
+            authenticator
+        }
+
+        // 发送者时钟超前两分钟，超出默认30秒的未来容差
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let sender_key = KeyPair::generate().unwrap();
+        let sender_did = sender_key.did.clone();
+        let document = DIDResolver::resolve_did_key(&sender_did).unwrap();
+        let hint = VerificationHint::sign(&sender_key, &document, "QmCurrent", now, DEFAULT_HINT_TTL).unwrap();
+
+        let sender = PubsubAuthenticator::new(IdentityManager::new(offline_client()), None, None)
+            .with_clock(Arc::new(MockClock::new(now + 120)));
+        let sender = with_signer(sender, sender_key).await;
+        let receiver = PubsubAuthenticator::new(IdentityManager::new(offline_client()), None, None)
+            .with_verification_hints(Arc::new(StaticHint(hint)));
+        let receiver = with_signer(receiver, KeyPair::generate().unwrap()).await;
+
+        let message = sender.create_heartbeat("chat").await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(!verification.verified);
+
+        let request = receiver.create_time_sync_request("chat", &sender_did).await.unwrap();
+        let response = sender.handle_time_sync(&request).await.unwrap().expect("请求应得到回复");
+        assert!(receiver.handle_time_sync(&response).await.unwrap().is_none());
+        let correction = receiver.clock_offsets().correction_ms(&sender_did, now * 1000);
+        assert!((119_000..=121_000).contains(&correction), "{}", correction);
+
+        let message = sender.create_heartbeat("chat").await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(verification.verified, "{:?}", verification.details);
+    }
+}
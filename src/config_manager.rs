@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
+use crate::message_id::{IdStrategy, set_id_strategy};
 
 /// SDK配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +25,18 @@ pub struct DIAPConfig {
     
     /// 日志配置
     pub logging: LoggingConfig,
+
+    /// 消息配置
+    #[serde(default)]
+    pub messaging: MessagingConfig,
+
+    /// P2P网络配置
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    /// 传输层配置
+    #[serde(default)]
+    pub transport: TransportConfig,
 }
 
 /// 智能体配置
@@ -38,6 +51,81 @@ pub struct AgentConfig {
     /// 是否自动生成密钥（如果文件不存在）
     #[serde(default = "default_true")]
     pub auto_generate_key: bool,
+
+    /// 内嵌HTTP服务（REST接口、did:web/did:wba文档）
+    #[serde(default)]
+    pub http: HttpServerConfig,
+}
+
+impl AgentConfig {
+    /// 对外公布的服务根地址（启用TLS时为https）
+    pub fn base_url(&self) -> String {
+        let scheme = if self.http.tls.is_some() { "https" } else { "http" };
+        let host = self.http.public_host.as_deref().unwrap_or(&self.http.bind_addr);
+        format!("{}://{}", scheme, host.trim_end_matches('/'))
+    }
+
+    /// 对外公布的端点地址，如 `endpoint_url("/diap/api")`
+    pub fn endpoint_url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url(), path.trim_start_matches('/'))
+    }
+}
+
+/// 内嵌HTTP服务配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpServerConfig {
+    /// 监听地址
+    #[serde(default = "default_http_bind_addr")]
+    pub bind_addr: String,
+
+    /// 对外公布的主机名（含端口），缺省使用监听地址
+    #[serde(default)]
+    pub public_host: Option<String>,
+
+    /// TLS配置（缺省为明文HTTP）
+    #[serde(default)]
+    pub tls: Option<TlsSettings>,
+}
+
+impl Default for HttpServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: default_http_bind_addr(),
+            public_host: None,
+            tls: None,
+        }
+    }
+}
+
+/// TLS配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsSettings {
+    /// 证书链文件（PEM），可指向ACME客户端签发的证书
+    #[serde(default)]
+    pub cert_path: Option<PathBuf>,
+
+    /// 私钥文件（PEM）
+    #[serde(default)]
+    pub key_path: Option<PathBuf>,
+
+    /// 证书文件不存在时生成自签名证书
+    #[serde(default = "default_true")]
+    pub self_signed: bool,
+
+    /// 自签名证书的主机名/IP
+    #[serde(default = "default_subject_alt_names")]
+    pub subject_alt_names: Vec<String>,
+}
+
+impl Default for TlsSettings {
+    fn default() -> Self {
+        Self {
+            cert_path: None,
+            key_path: None,
+            self_signed: true,
+            subject_alt_names: default_subject_alt_names(),
+        }
+    }
 }
 
 /// IPFS配置
@@ -58,6 +146,182 @@ pub struct IpfsConfig {
     /// 超时时间（秒）
     #[serde(default = "default_ipfs_timeout")]
     pub timeout_seconds: u64,
+
+    /// 本地IPFS节点（Kubo）
+    #[serde(default)]
+    pub local_node: LocalIpfsNodeConfig,
+}
+
+/// 本地IPFS节点配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalIpfsNodeConfig {
+    /// 是否自动启动本地节点
+    #[serde(default)]
+    pub auto_start: bool,
+
+    /// 数据目录（缺省为 ~/.diap/ipfs）
+    #[serde(default)]
+    pub data_dir: Option<PathBuf>,
+
+    /// API端口
+    #[serde(default = "default_ipfs_api_port")]
+    pub api_port: u16,
+
+    /// 网关端口
+    #[serde(default = "default_ipfs_gateway_port")]
+    pub gateway_port: u16,
+
+    /// Swarm端口
+    #[serde(default = "default_ipfs_swarm_port")]
+    pub swarm_port: u16,
+
+    /// 是否连接Bootstrap节点
+    #[serde(default = "default_true")]
+    pub enable_bootstrap: bool,
+
+    /// 找不到ipfs可执行文件时是否自动下载Kubo
+    #[serde(default = "default_true")]
+    pub auto_download: bool,
+
+    /// 自动下载的Kubo版本（缺省为SDK固定的版本）
+    #[serde(default)]
+    pub kubo_version: Option<String>,
+
+    /// 健康检查间隔（秒）
+    #[serde(default = "default_ipfs_health_check_interval")]
+    pub health_check_interval_secs: u64,
+
+    /// 崩溃后最多连续重启次数
+    #[serde(default = "default_ipfs_max_restarts")]
+    pub max_restarts: u32,
+}
+
+impl Default for LocalIpfsNodeConfig {
+    fn default() -> Self {
+        Self {
+            auto_start: false,
+            data_dir: None,
+            api_port: default_ipfs_api_port(),
+            gateway_port: default_ipfs_gateway_port(),
+            swarm_port: default_ipfs_swarm_port(),
+            enable_bootstrap: true,
+            auto_download: true,
+            kubo_version: None,
+            health_check_interval_secs: default_ipfs_health_check_interval(),
+            max_restarts: default_ipfs_max_restarts(),
+        }
+    }
+}
+
+/// P2P网络配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// libp2p监听地址（多地址）
+    #[serde(default = "default_listen_addrs")]
+    pub listen_addrs: Vec<String>,
+
+    /// 引导节点（带/p2p/后缀的多地址）
+    #[serde(default)]
+    pub bootstrap_peers: Vec<String>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            listen_addrs: default_listen_addrs(),
+            bootstrap_peers: Vec::new(),
+        }
+    }
+}
+
+/// 传输方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportKind {
+    /// libp2p（请求-响应 + gossipsub + mDNS）
+    #[default]
+    Libp2p,
+
+    /// Iroh（QUIC，需要iroh特性）
+    Iroh,
+
+    /// HTTP（无P2P依赖，适合服务端部署）
+    Http,
+}
+
+/// libp2p底层传输协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Libp2pProtocol {
+    /// TCP（始终启用，中继客户端也基于它）
+    Tcp,
+
+    /// QUIC v1（UDP，握手更快，打洞成功率更高）
+    Quic,
+
+    /// WebSocket（/ws，配置wss_tls后可监听/wss，供浏览器中的WASM智能体拨入）
+    Websocket,
+}
+
+/// 传输层配置：同一套应用代码通过配置切换libp2p、Iroh或HTTP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransportConfig {
+    /// 传输方式: libp2p（默认）, iroh, http
+    #[serde(default)]
+    pub kind: TransportKind,
+
+    /// 监听地址（libp2p为multiaddr，http为host:port，iroh忽略）
+    #[serde(default)]
+    pub listen_addr: Option<String>,
+
+    /// 已知节点（libp2p为带/p2p/的multiaddr，iroh为节点ID，http为服务根地址）
+    #[serde(default)]
+    pub peers: Vec<String>,
+
+    /// libp2p是否启用mDNS局域网发现
+    #[serde(default = "default_true")]
+    pub enable_mdns: bool,
+
+    /// libp2p是否启用NAT穿透（AutoNAT探测、中继客户端、DCUtR打洞）
+    #[serde(default = "default_true")]
+    pub enable_nat_traversal: bool,
+
+    /// 是否作为电路中继v2服务端，为NAT后的节点转发连接
+    #[serde(default)]
+    pub relay_server: bool,
+
+    /// 中继节点（带/p2p/的multiaddr），探测到处于NAT后时在其上预约电路
+    #[serde(default)]
+    pub relays: Vec<String>,
+
+    /// libp2p启用的底层协议，按优先级从高到低（拨号时按此顺序尝试地址，未列出的协议不拨号）
+    #[serde(default = "default_libp2p_protocols")]
+    pub protocols: Vec<Libp2pProtocol>,
+
+    /// libp2p额外监听地址（如 /ip4/0.0.0.0/udp/4001/quic-v1、/ip4/0.0.0.0/tcp/4443/wss）
+    #[serde(default)]
+    pub extra_listen_addrs: Vec<String>,
+
+    /// WSS监听使用的证书（需要tls特性）
+    #[serde(default)]
+    pub wss_tls: Option<TlsSettings>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            kind: TransportKind::default(),
+            listen_addr: None,
+            peers: Vec::new(),
+            enable_mdns: true,
+            enable_nat_traversal: true,
+            relay_server: false,
+            relays: Vec::new(),
+            protocols: default_libp2p_protocols(),
+            extra_listen_addrs: Vec::new(),
+            wss_tls: None,
+        }
+    }
 }
 
 /// IPNS配置
@@ -71,6 +335,10 @@ pub struct IpnsConfig {
     #[serde(default = "default_true")]
     pub use_ipfs_node: bool,
     
+    /// 是否在本地签名IPNS记录并直接发布到DHT（IpnsPublisher，无需远程节点的key/gen和name/publish）
+    #[serde(default)]
+    pub use_dht: bool,
+    
     /// IPNS记录有效期（天）
     #[serde(default = "default_ipns_validity_days")]
     pub validity_days: u64,
@@ -101,6 +369,22 @@ pub struct LoggingConfig {
     /// 日志级别: trace, debug, info, warn, error
     #[serde(default = "default_log_level")]
     pub level: String,
+    
+    /// OTLP gRPC端点（例如 http://localhost:4317），设置后导出追踪span（需要otel特性）
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    
+    /// span结束时输出耗时（验证流水线各步骤的耗时分解）
+    #[serde(default)]
+    pub span_timings: bool,
+}
+
+/// 消息配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessagingConfig {
+    /// 消息/请求ID生成策略: uuid_v7（默认，按时间排序）, legacy_v4
+    #[serde(default)]
+    pub id_strategy: IdStrategy,
 }
 
 // 默认值函数
@@ -110,6 +394,15 @@ fn default_ipns_validity_days() -> u64 { 365 }
 fn default_cache_ttl() -> u64 { 21600 } // 6小时
 fn default_cache_max_entries() -> usize { 1000 }
 fn default_log_level() -> String { "info".to_string() }
+fn default_ipfs_api_port() -> u16 { 5001 }
+fn default_ipfs_gateway_port() -> u16 { 8080 }
+fn default_ipfs_swarm_port() -> u16 { 4001 }
+fn default_ipfs_health_check_interval() -> u64 { 10 }
+fn default_ipfs_max_restarts() -> u32 { 5 }
+fn default_listen_addrs() -> Vec<String> { vec!["/ip4/0.0.0.0/tcp/4001".to_string()] }
+fn default_http_bind_addr() -> String { "127.0.0.1:8787".to_string() }
+fn default_subject_alt_names() -> Vec<String> { vec!["localhost".to_string(), "127.0.0.1".to_string()] }
+fn default_libp2p_protocols() -> Vec<Libp2pProtocol> { vec![Libp2pProtocol::Quic, Libp2pProtocol::Tcp, Libp2pProtocol::Websocket] }
 
 impl Default for DIAPConfig {
     fn default() -> Self {
@@ -121,6 +414,7 @@ impl Default for DIAPConfig {
                 name: "DIAP Agent".to_string(),
                 private_key_path: dirs.data_dir().join("keys/agent.key"),
                 auto_generate_key: true,
+                http: HttpServerConfig::default(),
             },
             ipfs: IpfsConfig {
                 aws_api_url: None,
@@ -128,10 +422,12 @@ impl Default for DIAPConfig {
                 pinata_api_key: None,
                 pinata_api_secret: None,
                 timeout_seconds: 30,
+                local_node: LocalIpfsNodeConfig::default(),
             },
             ipns: IpnsConfig {
                 use_w3name: true,
                 use_ipfs_node: true,
+                use_dht: false,
                 validity_days: 365,
             },
             cache: CacheConfig {
@@ -142,7 +438,12 @@ impl Default for DIAPConfig {
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
+                otlp_endpoint: None,
+                span_timings: false,
             },
+            messaging: MessagingConfig::default(),
+            network: NetworkConfig::default(),
+            transport: TransportConfig::default(),
         }
     }
 }
@@ -205,29 +506,384 @@ impl DIAPConfig {
         }
     }
     
+    /// 应用进程级设置（消息ID生成策略）
+    pub fn apply_runtime_settings(&self) {
+        set_id_strategy(self.messaging.id_strategy);
+    }
+    
     /// 验证配置
     pub fn validate(&self) -> Result<()> {
         // 验证IPFS配置
         if self.ipfs.aws_api_url.is_none() && 
-           self.ipfs.pinata_api_key.is_none() {
-            anyhow::bail!("必须配置AWS IPFS节点或Pinata");
+           self.ipfs.pinata_api_key.is_none() &&
+           !self.ipfs.local_node.auto_start {
+            anyhow::bail!("必须配置AWS IPFS节点、Pinata或自动启动本地IPFS节点");
         }
         
+        self.validate_values()
+    }
+    
+    /// 验证各配置项的取值（不要求已配置IPFS服务，分层加载时使用）
+    pub fn validate_values(&self) -> Result<()> {
         // 验证IPNS配置
-        if !self.ipns.use_w3name && !self.ipns.use_ipfs_node {
+        if !self.ipns.use_w3name && !self.ipns.use_ipfs_node && !self.ipns.use_dht {
             anyhow::bail!("必须至少启用一种IPNS发布方式");
         }
         
+        // 验证TLS配置
+        for tls in [&self.agent.http.tls, &self.transport.wss_tls].into_iter().flatten() {
+            if tls.cert_path.is_some() != tls.key_path.is_some() {
+                anyhow::bail!("TLS证书和私钥路径必须同时配置");
+            }
+        }
+        
         // 验证日志级别
         let valid_levels = ["trace", "debug", "info", "warn", "error"];
         if !valid_levels.contains(&self.logging.level.as_str()) {
-            anyhow::bail!("无效的日志级别: {}", self.logging.level);
+            anyhow::bail!("无效的日志级别: {}（可选: {}）", self.logging.level, valid_levels.join(", "));
+        }
+        
+        // 验证网络地址
+        for (key, addrs) in [
+            ("network.listen_addrs", &self.network.listen_addrs),
+            ("network.bootstrap_peers", &self.network.bootstrap_peers),
+            ("transport.extra_listen_addrs", &self.transport.extra_listen_addrs),
+        ] {
+            for addr in addrs {
+                addr.parse::<libp2p::Multiaddr>()
+                    .with_context(|| format!("{} 中的多地址无效: {}", key, addr))?;
+            }
         }
+        self.agent.http.bind_addr.parse::<std::net::SocketAddr>()
+            .with_context(|| format!("agent.http.bind_addr 不是有效的 host:port: {}", self.agent.http.bind_addr))?;
         
         Ok(())
     }
 }
 
+/// 配置来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// 内置默认值
+    Default,
+    
+    /// 配置文件
+    File(PathBuf),
+    
+    /// 环境变量
+    Env(String),
+    
+    /// 命令行覆盖
+    Cli(String),
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "默认值"),
+            ConfigSource::File(path) => write!(f, "配置文件 {:?}", path),
+            ConfigSource::Env(name) => write!(f, "环境变量 {}", name),
+            ConfigSource::Cli(arg) => write!(f, "命令行 --set {}", arg),
+        }
+    }
+}
+
+/// 默认环境变量前缀
+pub const DEFAULT_ENV_PREFIX: &str = "DIAP";
+
+/// 分层配置加载器：默认值 → 配置文件（TOML/YAML）→ 环境变量 → 命令行覆盖，后面的层覆盖前面的层
+///
+/// 环境变量 `DIAP_IPFS__TIMEOUT_SECONDS=10` 对应配置项 `ipfs.timeout_seconds`（段之间用双下划线），
+/// 命令行覆盖写作 `ipfs.timeout_seconds=10`。未知配置项会报错并给出相近的配置项名。
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    file: Option<PathBuf>,
+    env_prefix: Option<String>,
+    env_vars: Option<Vec<(String, String)>>,
+    overrides: Vec<String>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self {
+            file: None,
+            env_prefix: Some(DEFAULT_ENV_PREFIX.to_string()),
+            env_vars: None,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl ConfigLoader {
+    /// 创建加载器（读取 `DIAP_` 前缀的环境变量，不读配置文件）
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 配置文件（扩展名为 .yaml/.yml 时按YAML解析，否则按TOML解析）
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file = Some(path.into());
+        self
+    }
+    
+    /// 环境变量前缀
+    pub fn with_env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+    
+    /// 不读取环境变量
+    pub fn without_env(mut self) -> Self {
+        self.env_prefix = None;
+        self
+    }
+    
+    /// 用给定的变量代替进程环境变量
+    pub fn with_env_vars<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env_vars = Some(vars.into_iter().map(|(key, value)| (key.into(), value.into())).collect());
+        self
+    }
+    
+    /// 命令行覆盖（`section.key=value`）
+    pub fn with_override(mut self, assignment: impl Into<String>) -> Self {
+        self.overrides.push(assignment.into());
+        self
+    }
+    
+    /// 从命令行参数读取 `--config <file>` 和 `--set <key=value>`，返回未识别的参数
+    pub fn with_cli_args(mut self, args: &[String]) -> Result<(Self, Vec<String>)> {
+        let mut rest = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let path = iter.next().ok_or_else(|| anyhow::anyhow!("--config 需要文件参数"))?;
+                    self.file = Some(PathBuf::from(path));
+                }
+                "--set" => {
+                    let assignment = iter.next().ok_or_else(|| anyhow::anyhow!("--set 需要 key=value 参数"))?;
+                    self.overrides.push(assignment.clone());
+                }
+                _ => rest.push(arg.clone()),
+            }
+        }
+        Ok((self, rest))
+    }
+    
+    /// 按层合并并验证
+    pub fn load(&self) -> Result<DIAPConfig> {
+        let defaults = serde_json::to_value(DIAPConfig::default()).context("无法序列化默认配置")?;
+        let mut merged = defaults.clone();
+        let mut sources: Vec<(String, ConfigSource)> = Vec::new();
+        
+        if let Some(path) = &self.file {
+            let layer = Self::read_file(path)?;
+            let source = ConfigSource::File(path.clone());
+            check_known_keys(&defaults, &layer, "", &source)?;
+            merge_layer(&mut merged, layer, "", &source, &mut sources);
+        }
+        
+        for (name, key, value) in self.env_assignments() {
+            let source = ConfigSource::Env(name);
+            self.apply_assignment(&defaults, &mut merged, &key, &value, source, &mut sources)?;
+        }
+        
+        for assignment in &self.overrides {
+            let (key, value) = assignment.split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("命令行覆盖格式应为 key=value: {}", assignment))?;
+            let source = ConfigSource::Cli(assignment.clone());
+            self.apply_assignment(&defaults, &mut merged, key.trim(), value.trim(), source, &mut sources)?;
+        }
+        
+        let config = deserialize_config(merged, &sources)?;
+        config.validate_values()?;
+        Ok(config)
+    }
+    
+    fn read_file(path: &PathBuf) -> Result<serde_json::Value> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取配置文件: {:?}", path))?;
+        let yaml = path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"));
+        let value: serde_json::Value = if yaml {
+            serde_yaml::from_str(&content).with_context(|| format!("无法解析YAML配置文件: {:?}", path))?
+        } else {
+            toml::from_str(&content).with_context(|| format!("无法解析TOML配置文件: {:?}", path))?
+        };
+        match value {
+            serde_json::Value::Object(_) => Ok(value),
+            serde_json::Value::Null => Ok(serde_json::json!({})),
+            _ => anyhow::bail!("配置文件顶层必须是表: {:?}", path),
+        }
+    }
+    
+    /// 前缀匹配的环境变量：（变量名, 配置项路径, 值）
+    fn env_assignments(&self) -> Vec<(String, String, String)> {
+        let Some(prefix) = &self.env_prefix else {
+            return Vec::new();
+        };
+        let prefix = format!("{}_", prefix);
+        let vars = match &self.env_vars {
+            Some(vars) => vars.clone(),
+            None => std::env::vars().collect(),
+        };
+        let mut assignments: Vec<(String, String, String)> = vars.into_iter()
+            .filter_map(|(name, value)| {
+                let rest = name.strip_prefix(&prefix)?;
+                // 只处理带段分隔符的变量，DIAP_ADMIN_TOKEN 这类单独使用的变量不属于配置文件
+                rest.contains("__").then(|| (name.clone(), rest.to_ascii_lowercase().replace("__", "."), value))
+            })
+            .collect();
+        assignments.sort();
+        assignments
+    }
+    
+    fn apply_assignment(
+        &self,
+        defaults: &serde_json::Value,
+        merged: &mut serde_json::Value,
+        key: &str,
+        raw: &str,
+        source: ConfigSource,
+        sources: &mut Vec<(String, ConfigSource)>,
+    ) -> Result<()> {
+        let segments: Vec<&str> = key.split('.').filter(|segment| !segment.is_empty()).collect();
+        if segments.is_empty() {
+            anyhow::bail!("配置项名为空（{}）", source);
+        }
+        let mut layer = parse_value(defaults.pointer(&json_pointer(&segments)), raw);
+        for segment in segments.iter().rev() {
+            layer = serde_json::json!({ *segment: layer });
+        }
+        check_known_keys(defaults, &layer, "", &source)?;
+        merge_layer(merged, layer, "", &source, sources);
+        Ok(())
+    }
+}
+
+fn json_pointer(segments: &[&str]) -> String {
+    segments.iter().map(|segment| format!("/{}", segment)).collect()
+}
+
+/// 按默认值的类型解释字符串：字符串项原样保留，列表项可用逗号分隔，其余按JSON解析
+fn parse_value(default: Option<&serde_json::Value>, raw: &str) -> serde_json::Value {
+    match default {
+        Some(serde_json::Value::String(_)) => serde_json::Value::String(raw.to_string()),
+        Some(serde_json::Value::Array(_)) if !raw.trim_start().starts_with('[') => serde_json::Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| serde_json::Value::String(item.to_string()))
+                .collect(),
+        ),
+        _ => serde_json::from_str(raw).unwrap_or_else(|_| serde_json::Value::String(raw.to_string())),
+    }
+}
+
+/// 检查层中的配置项是否都存在于默认配置中（默认值为空的可选项不检查其子项）
+fn check_known_keys(defaults: &serde_json::Value, layer: &serde_json::Value, path: &str, source: &ConfigSource) -> Result<()> {
+    let (serde_json::Value::Object(known), serde_json::Value::Object(given)) = (defaults, layer) else {
+        return Ok(());
+    };
+    for (key, value) in given {
+        let full = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+        match known.get(key) {
+            Some(default) => check_known_keys(default, value, &full, source)?,
+            None => {
+                let hint = known.keys()
+                    .map(|candidate| (edit_distance(candidate, key), candidate))
+                    .filter(|(distance, _)| *distance <= 2)
+                    .min()
+                    .map(|(_, candidate)| format!("，是否为 {}？", candidate))
+                    .unwrap_or_else(|| format!("（可用: {}）", known.keys().cloned().collect::<Vec<_>>().join(", ")));
+                anyhow::bail!("未知配置项 {}（{}）{}", full, source, hint);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge_layer(
+    base: &mut serde_json::Value,
+    layer: serde_json::Value,
+    path: &str,
+    source: &ConfigSource,
+    sources: &mut Vec<(String, ConfigSource)>,
+) {
+    match (base, layer) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(layer)) => {
+            for (key, value) in layer {
+                let full = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                let slot = base.entry(key).or_insert(serde_json::Value::Null);
+                if slot.is_object() && value.is_object() {
+                    merge_layer(slot, value, &full, source, sources);
+                } else {
+                    sources.retain(|(existing, _)| existing != &full);
+                    sources.push((full, source.clone()));
+                    *slot = value;
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// 反序列化合并后的配置，出错时指出出错的配置段及其设置来源
+fn deserialize_config(merged: serde_json::Value, sources: &[(String, ConfigSource)]) -> Result<DIAPConfig> {
+    fn section<T: serde::de::DeserializeOwned>(value: &serde_json::Value, name: &str) -> Option<String> {
+        serde_json::from_value::<T>(value.get(name).cloned().unwrap_or_default()).err().map(|e| e.to_string())
+    }
+    
+    match serde_json::from_value::<DIAPConfig>(merged.clone()) {
+        Ok(config) => Ok(config),
+        Err(e) => {
+            let failure = [
+                ("agent", section::<AgentConfig>(&merged, "agent")),
+                ("ipfs", section::<IpfsConfig>(&merged, "ipfs")),
+                ("ipns", section::<IpnsConfig>(&merged, "ipns")),
+                ("cache", section::<CacheConfig>(&merged, "cache")),
+                ("logging", section::<LoggingConfig>(&merged, "logging")),
+                ("messaging", section::<MessagingConfig>(&merged, "messaging")),
+                ("network", section::<NetworkConfig>(&merged, "network")),
+                ("transport", section::<TransportConfig>(&merged, "transport")),
+            ].into_iter().find_map(|(name, error)| error.map(|error| (name, error)));
+            
+            let Some((name, error)) = failure else {
+                anyhow::bail!("配置无效: {}", e);
+            };
+            let origins: Vec<String> = sources.iter()
+                .filter(|(key, _)| key == name || key.starts_with(&format!("{}.", name)))
+                .map(|(key, source)| format!("{} ← {}", key, source))
+                .collect();
+            if origins.is_empty() {
+                anyhow::bail!("配置段 [{}] 无效: {}", name, error);
+            }
+            anyhow::bail!("配置段 [{}] 无效: {}（相关设置: {}）", name, error, origins.join("; "))
+        }
+    }
+}
+
+/// 编辑距离（用于提示拼写错误的配置项）
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current.push((previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -247,4 +903,90 @@ mod tests {
         let deserialized: DIAPConfig = toml::from_str(&toml_str).unwrap();
         assert_eq!(config.agent.name, deserialized.agent.name);
     }
+    
+    #[test]
+    fn test_endpoint_urls_follow_tls() {
+        let mut agent = DIAPConfig::default().agent;
+        assert_eq!(agent.endpoint_url("/diap/api"), "http://127.0.0.1:8787/diap/api");
+        
+        agent.http.public_host = Some("agent.example.com".to_string());
+        agent.http.tls = Some(TlsSettings::default());
+        assert_eq!(agent.endpoint_url("diap/api"), "https://agent.example.com/diap/api");
+        
+        // 旧配置文件没有http段时为明文
+        let agent: AgentConfig = toml::from_str("name = \"a\"\nprivate_key_path = \"k\"").unwrap();
+        assert!(agent.http.tls.is_none());
+        assert_eq!(agent.http.bind_addr, "127.0.0.1:8787");
+    }
+    
+    #[test]
+    fn test_layered_loading() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("diap.yaml");
+        std::fs::write(&file, "agent:\n  name: yaml-agent\nipfs:\n  timeout_seconds: 12\nnetwork:\n  bootstrap_peers: []\n").unwrap();
+        
+        let config = ConfigLoader::new()
+            .with_file(&file)
+            .with_env_vars([
+                ("DIAP_IPFS__TIMEOUT_SECONDS", "20"),
+                ("DIAP_AGENT__NAME", "42"),
+                ("DIAP_NETWORK__LISTEN_ADDRS", "/ip4/127.0.0.1/tcp/1, /ip4/127.0.0.1/tcp/2"),
+                ("DIAP_ADMIN_TOKEN", "不属于配置文件"),
+                ("OTHER__VAR", "x"),
+            ])
+            .with_override("ipfs.timeout_seconds=30")
+            .with_override("agent.http.tls={\"self_signed\": true}")
+            .load()
+            .unwrap();
+        // 文件覆盖默认值，环境变量覆盖文件，命令行覆盖环境变量
+        assert_eq!(config.ipfs.timeout_seconds, 30);
+        assert_eq!(config.agent.name, "42");
+        assert_eq!(config.network.listen_addrs.len(), 2);
+        assert_eq!(config.cache.max_entries, 1000);
+        assert!(config.agent.http.tls.as_ref().unwrap().self_signed);
+        assert_eq!(config.agent.endpoint_url("/"), "https://127.0.0.1:8787/");
+        
+        let toml_file = dir.path().join("diap.toml");
+        std::fs::write(&toml_file, "[logging]\nlevel = \"debug\"\n").unwrap();
+        let (loader, rest) = ConfigLoader::new().without_env()
+            .with_cli_args(&["--config".into(), toml_file.to_string_lossy().into_owned(), "--set".into(), "cache.enabled=false".into(), "--json".into()])
+            .unwrap();
+        let config = loader.load().unwrap();
+        assert_eq!(config.logging.level, "debug");
+        assert!(!config.cache.enabled);
+        assert_eq!(rest, vec!["--json".to_string()]);
+    }
+    
+    #[test]
+    fn test_layered_loading_errors() {
+        let loader = ConfigLoader::new().without_env();
+        
+        // 拼写错误给出相近的配置项
+        let error = loader.clone().with_override("ipfs.timout_seconds=5").load().unwrap_err().to_string();
+        assert!(error.contains("ipfs.timout_seconds") && error.contains("timeout_seconds"), "{}", error);
+        
+        // 类型错误指出配置段和来源
+        let error = ConfigLoader::new()
+            .with_env_vars([("DIAP_IPFS__TIMEOUT_SECONDS", "soon")])
+            .load().unwrap_err().to_string();
+        assert!(error.contains("[ipfs]") && error.contains("DIAP_IPFS__TIMEOUT_SECONDS"), "{}", error);
+        
+        // 取值验证
+        let error = loader.clone().with_override("logging.level=loud").load().unwrap_err().to_string();
+        assert!(error.contains("loud"), "{}", error);
+        assert!(loader.clone().with_override("network.listen_addrs=not-a-multiaddr").load().is_err());
+        assert!(loader.with_override("no_equals_sign").load().is_err());
+    }
+    
+    #[test]
+    fn test_messaging_config_default() {
+        // 旧配置文件没有messaging段时使用UUIDv7
+        let mut value: toml::Value = toml::from_str(&toml::to_string(&DIAPConfig::default()).unwrap()).unwrap();
+        value.as_table_mut().unwrap().remove("messaging");
+        let config: DIAPConfig = value.try_into().unwrap();
+        assert_eq!(config.messaging.id_strategy, IdStrategy::UuidV7);
+        
+        let legacy: MessagingConfig = toml::from_str("id_strategy = \"legacy_v4\"").unwrap();
+        assert_eq!(legacy.id_strategy, IdStrategy::LegacyV4);
+    }
 }
@@ -0,0 +1,451 @@
+// DIAP Rust SDK - 连接管理模块
+// 跟踪已连接的对端（DID、延迟、最后活跃时间），检测断开，
+// 对已知多地址按指数退避重新拨号；由网络层的连接事件驱动，拨号由调用方提供
+
+use anyhow::Result;
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use libp2p::{Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::{SharedClock, system_clock};
+use crate::ipfs_client::RetryPolicy;
+use crate::trust::{ReputationEvent, ReputationStore};
+
+/// 拨号函数
+pub type DialFn = Arc<dyn Fn(PeerId, Vec<Multiaddr>) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// 对端连接状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub enum PeerState {
+    /// 已连接
+    Connected,
+
+    /// 正在拨号
+    Dialing,
+
+    /// 已断开，等待重连
+    Disconnected,
+
+    /// 重连次数耗尽
+    GaveUp,
+}
+
+/// 对端信息
+#[derive(Debug, Clone)]
+pub struct PeerInfo {
+    /// PeerID
+    pub peer_id: PeerId,
+
+    /// 对端DID（身份交换后已知）
+    pub did: Option<String>,
+
+    /// 已知多地址
+    pub addrs: Vec<Multiaddr>,
+
+    /// 连接状态
+    pub state: PeerState,
+
+    /// 最近一次测得的往返延迟
+    pub latency: Option<Duration>,
+
+    /// 最后活跃时间（毫秒）
+    pub last_seen: Option<u64>,
+
+    /// 建立连接的时间（毫秒）
+    pub connected_since: Option<u64>,
+
+    /// 连续重连失败次数
+    pub failed_dials: u32,
+
+    /// 下一次重连时间（毫秒）
+    pub next_dial_at: Option<u64>,
+}
+
+impl PeerInfo {
+    fn new(peer_id: PeerId) -> Self {
+        Self {
+            peer_id,
+            did: None,
+            addrs: Vec::new(),
+            state: PeerState::Disconnected,
+            latency: None,
+            last_seen: None,
+            connected_since: None,
+            failed_dials: 0,
+            next_dial_at: None,
+        }
+    }
+}
+
+/// 重连请求
+#[derive(Debug, Clone)]
+pub struct DialRequest {
+    /// 目标PeerID
+    pub peer_id: PeerId,
+
+    /// 拨号地址
+    pub addrs: Vec<Multiaddr>,
+}
+
+/// 连接管理器
+#[derive(Clone)]
+pub struct ConnectionManager {
+    peers: Arc<DashMap<PeerId, PeerInfo>>,
+    /// 重连退避策略（max_attempts为连续失败上限）
+    retry_policy: RetryPolicy,
+    /// 超过该时长无活动的连接视为已断开
+    idle_timeout: Duration,
+    /// 信誉存储（断开时计入已知DID的在线时长）
+    reputation: Option<ReputationStore>,
+    clock: SharedClock,
+}
+
+impl ConnectionManager {
+    /// 创建连接管理器（系统时钟）
+    pub fn new() -> Self {
+        Self::new_with_clock(system_clock())
+    }
+
+    /// 使用指定时间源创建连接管理器
+    pub fn new_with_clock(clock: SharedClock) -> Self {
+        Self {
+            peers: Arc::new(DashMap::new()),
+            retry_policy: RetryPolicy {
+                max_attempts: 10,
+                initial_backoff: Duration::from_secs(1),
+                max_backoff: Duration::from_secs(300),
+                multiplier: 2.0,
+                jitter: 0.2,
+            },
+            idle_timeout: Duration::from_secs(120),
+            reputation: None,
+            clock,
+        }
+    }
+
+    /// 设置重连退避策略
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// 设置空闲超时
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// 登记已知对端（用于断开后重连）；未连接时立即安排拨号
+    pub fn add_known_peer(&self, peer_id: PeerId, addrs: Vec<Multiaddr>, did: Option<String>) {
+        let now = self.clock.now_millis();
+        let mut peer = self.peers.entry(peer_id).or_insert_with(|| PeerInfo::new(peer_id));
+        for addr in addrs {
+            if !peer.addrs.contains(&addr) {
+                peer.addrs.push(addr);
+            }
+        }
+        if did.is_some() {
+            peer.did = did;
+        }
+        if matches!(peer.state, PeerState::Disconnected | PeerState::GaveUp) {
+            peer.state = PeerState::Disconnected;
+            peer.failed_dials = 0;
+            peer.next_dial_at = Some(now);
+        }
+        drop(peer);
+        self.report_peer_counts();
+    }
+
+    /// 移除对端（不再重连）
+    pub fn forget(&self, peer_id: &PeerId) -> bool {
+        let removed = self.peers.remove(peer_id).is_some();
+        self.report_peer_counts();
+        removed
+    }
+
+    /// 更新对端数指标
+    fn report_peer_counts(&self) {
+        let metrics = crate::metrics::global();
+        metrics.peers_known.set(self.peers.len() as i64);
+        metrics.peers_connected.set(self.peers.iter().filter(|p| p.state == PeerState::Connected).count() as i64);
+    }
+
+    /// 连接断开时把在线时长计入信誉，并可按信任分选择对端
+    pub fn with_reputation(mut self, reputation: ReputationStore) -> Self {
+        self.reputation = Some(reputation);
+        self
+    }
+    
+    /// 连接建立
+    pub fn on_connected(&self, peer_id: PeerId, addr: Option<Multiaddr>) {
+        let now = self.clock.now_millis();
+        let mut peer = self.peers.entry(peer_id).or_insert_with(|| PeerInfo::new(peer_id));
+        if let Some(addr) = addr {
+            if !peer.addrs.contains(&addr) {
+                peer.addrs.push(addr);
+            }
+        }
+        peer.state = PeerState::Connected;
+        peer.connected_since = Some(now);
+        peer.last_seen = Some(now);
+        peer.failed_dials = 0;
+        peer.next_dial_at = None;
+        drop(peer);
+        log::info!("🔗 对端已连接: {}", peer_id);
+        self.report_peer_counts();
+    }
+
+    /// 连接断开，安排重连
+    pub fn on_disconnected(&self, peer_id: &PeerId) {
+        let now = self.clock.now_millis();
+        if let Some(mut peer) = self.peers.get_mut(peer_id) {
+            if peer.state == PeerState::Connected {
+                log::info!("🔌 对端已断开: {}", peer_id);
+            }
+            if let (Some(reputation), Some(did), Some(since)) = (&self.reputation, &peer.did, peer.connected_since) {
+                reputation.record(did, ReputationEvent::Uptime { secs: now.saturating_sub(since) / 1000 });
+            }
+            peer.state = PeerState::Disconnected;
+            peer.connected_since = None;
+            peer.next_dial_at = Some(now);
+        }
+        self.report_peer_counts();
+    }
+
+    /// 身份交换后记录对端DID
+    pub fn on_identified(&self, peer_id: &PeerId, did: &str) {
+        if let Some(mut peer) = self.peers.get_mut(peer_id) {
+            peer.did = Some(did.to_string());
+        }
+    }
+
+    /// 记录ping往返延迟
+    pub fn on_ping(&self, peer_id: &PeerId, rtt: Duration) {
+        let now = self.clock.now_millis();
+        if let Some(mut peer) = self.peers.get_mut(peer_id) {
+            peer.latency = Some(rtt);
+            peer.last_seen = Some(now);
+        }
+    }
+
+    /// 记录收到对端的任何流量
+    pub fn on_activity(&self, peer_id: &PeerId) {
+        let now = self.clock.now_millis();
+        if let Some(mut peer) = self.peers.get_mut(peer_id) {
+            peer.last_seen = Some(now);
+        }
+    }
+
+    /// 拨号失败，按退避策略安排下一次重连
+    pub fn on_dial_failed(&self, peer_id: &PeerId) {
+        let now = self.clock.now_millis();
+        if let Some(mut peer) = self.peers.get_mut(peer_id) {
+            peer.failed_dials += 1;
+            if peer.failed_dials >= self.retry_policy.max_attempts {
+                peer.state = PeerState::GaveUp;
+                peer.next_dial_at = None;
+                log::warn!("放弃重连对端 {}（连续失败 {} 次）", peer_id, peer.failed_dials);
+            } else {
+                let backoff = self.retry_policy.backoff_for(peer.failed_dials);
+                peer.state = PeerState::Disconnected;
+                peer.next_dial_at = Some(now + backoff.as_millis() as u64);
+                log::debug!("重连 {} 失败，{:?} 后重试", peer_id, backoff);
+            }
+        }
+    }
+
+    /// 将超过空闲超时无活动的连接标记为断开，返回被标记的对端
+    pub fn detect_stale(&self) -> Vec<PeerId> {
+        let now = self.clock.now_millis();
+        let idle = self.idle_timeout.as_millis() as u64;
+        let stale: Vec<PeerId> = self.peers.iter()
+            .filter(|p| p.state == PeerState::Connected && p.last_seen.is_some_and(|t| now.saturating_sub(t) > idle))
+            .map(|p| p.peer_id)
+            .collect();
+        for peer_id in &stale {
+            self.on_disconnected(peer_id);
+        }
+        stale
+    }
+
+    /// 取出到期的重连请求（对应对端标记为拨号中）
+    pub fn take_due_dials(&self) -> Vec<DialRequest> {
+        let now = self.clock.now_millis();
+        let mut requests = Vec::new();
+        for mut peer in self.peers.iter_mut() {
+            let due = peer.state == PeerState::Disconnected
+                && !peer.addrs.is_empty()
+                && peer.next_dial_at.is_some_and(|t| t <= now);
+            if due {
+                peer.state = PeerState::Dialing;
+                requests.push(DialRequest { peer_id: peer.peer_id, addrs: peer.addrs.clone() });
+            }
+        }
+        requests
+    }
+
+    /// 已连接的对端
+    pub fn connected_peers(&self) -> Vec<PeerInfo> {
+        let mut peers: Vec<PeerInfo> = self.peers.iter()
+            .filter(|p| p.state == PeerState::Connected)
+            .map(|p| p.clone())
+            .collect();
+        peers.sort_by_key(|p| p.peer_id.to_base58());
+        peers
+    }
+
+    /// 已识别DID的已连接对端，按信任分从高到低排序并过滤掉低于min_score的（未设置信誉存储时均为初始分）
+    pub fn trusted_peers(&self, min_score: f64) -> Vec<(PeerInfo, f64)> {
+        let reputation = self.reputation.clone().unwrap_or_default();
+        let mut peers: Vec<(PeerInfo, f64)> = self.connected_peers().into_iter()
+            .filter_map(|peer| {
+                let score = reputation.trust_score(peer.did.as_deref()?);
+                (score >= min_score).then_some((peer, score))
+            })
+            .collect();
+        peers.sort_by(|a, b| b.1.total_cmp(&a.1));
+        peers
+    }
+
+    /// 所有已知对端（含未连接的）
+    pub fn known_peers(&self) -> Vec<PeerInfo> {
+        let mut peers: Vec<PeerInfo> = self.peers.iter().map(|p| p.clone()).collect();
+        peers.sort_by_key(|p| p.peer_id.to_base58());
+        peers
+    }
+
+    /// 对端信息
+    pub fn peer(&self, peer_id: &PeerId) -> Option<PeerInfo> {
+        self.peers.get(peer_id).map(|p| p.clone())
+    }
+
+    /// 按DID查找对端
+    pub fn peer_by_did(&self, did: &str) -> Option<PeerInfo> {
+        self.peers.iter()
+            .find(|p| p.did.as_deref() == Some(did))
+            .map(|p| p.clone())
+    }
+
+    /// 是否已连接
+    pub fn is_connected(&self, peer_id: &PeerId) -> bool {
+        self.peers.get(peer_id).is_some_and(|p| p.state == PeerState::Connected)
+    }
+
+    /// 启动后台重连任务：每隔interval检测空闲连接并拨号到期的对端
+    pub fn start_reconnect_loop(&self, interval: Duration, dial: DialFn) -> tokio::task::JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(interval);
+            loop {
+                interval_timer.tick().await;
+                manager.detect_stale();
+
+                for request in manager.take_due_dials() {
+                    log::debug!("🔄 重连对端: {}", request.peer_id);
+                    match dial(request.peer_id, request.addrs).await {
+                        Ok(()) => manager.on_connected(request.peer_id, None),
+                        Err(e) => {
+                            log::debug!("重连 {} 失败: {}", request.peer_id, e);
+                            manager.on_dial_failed(&request.peer_id);
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl Default for ConnectionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_reconnect_with_backoff() {
+        let clock = MockClock::new(1_000);
+        let manager = ConnectionManager::new_with_clock(Arc::new(clock.clone()))
+            .with_retry_policy(RetryPolicy { max_attempts: 3, jitter: 0.0, ..RetryPolicy::default() });
+        let peer = PeerId::random();
+        let addr: Multiaddr = "/ip4/10.0.0.1/tcp/4001".parse().unwrap();
+
+        manager.on_connected(peer, Some(addr.clone()));
+        manager.on_identified(&peer, "did:key:bob");
+        manager.on_ping(&peer, Duration::from_millis(42));
+        let connected = manager.connected_peers();
+        assert_eq!(connected[0].did.as_deref(), Some("did:key:bob"));
+        assert_eq!(connected[0].latency, Some(Duration::from_millis(42)));
+        assert!(manager.take_due_dials().is_empty());
+
+        manager.on_disconnected(&peer);
+        let dials = manager.take_due_dials();
+        assert_eq!(dials[0].addrs, vec![addr]);
+        assert!(manager.take_due_dials().is_empty());
+
+        // 第一次失败后等待200ms
+        manager.on_dial_failed(&peer);
+        assert!(manager.take_due_dials().is_empty());
+        clock.advance(Duration::from_millis(200));
+        assert_eq!(manager.take_due_dials().len(), 1);
+
+        manager.on_dial_failed(&peer);
+        clock.advance(Duration::from_millis(400));
+        assert_eq!(manager.take_due_dials().len(), 1);
+        manager.on_dial_failed(&peer);
+        assert_eq!(manager.peer(&peer).unwrap().state, PeerState::GaveUp);
+
+        // 重新登记后恢复重连
+        manager.add_known_peer(peer, Vec::new(), None);
+        assert_eq!(manager.take_due_dials().len(), 1);
+    }
+
+    #[test]
+    fn test_detect_stale_connections() {
+        let clock = MockClock::new(1_000);
+        let manager = ConnectionManager::new_with_clock(Arc::new(clock.clone()))
+            .with_idle_timeout(Duration::from_secs(30));
+        let quiet = PeerId::random();
+        let chatty = PeerId::random();
+        manager.on_connected(quiet, Some("/ip4/10.0.0.2/tcp/4001".parse().unwrap()));
+        manager.on_connected(chatty, None);
+
+        clock.advance(Duration::from_secs(20));
+        manager.on_activity(&chatty);
+        clock.advance(Duration::from_secs(20));
+
+        assert_eq!(manager.detect_stale(), vec![quiet]);
+        assert!(!manager.is_connected(&quiet));
+        assert_eq!(manager.connected_peers().len(), 1);
+        assert_eq!(manager.peer_by_did("did:key:none").map(|p| p.peer_id), None);
+    }
+
+    #[test]
+    fn test_uptime_feeds_reputation() {
+        let clock = MockClock::new(1_000);
+        let reputation = ReputationStore::default().with_clock(Arc::new(clock.clone()));
+        let manager = ConnectionManager::new_with_clock(Arc::new(clock.clone())).with_reputation(reputation.clone());
+        let (steady, flaky) = (PeerId::random(), PeerId::random());
+        for (peer, did) in [(steady, "did:key:steady"), (flaky, "did:key:flaky")] {
+            manager.on_connected(peer, None);
+            manager.on_identified(&peer, did);
+        }
+        reputation.record("did:key:flaky", ReputationEvent::InvalidMessage);
+
+        clock.advance(Duration::from_secs(3600));
+        manager.on_disconnected(&steady);
+        assert_eq!(reputation.record_of("did:key:steady").unwrap().uptime_secs, 3600);
+
+        manager.on_connected(steady, None);
+        let ranked: Vec<String> = manager.trusted_peers(0.0).into_iter().filter_map(|(peer, _)| peer.did).collect();
+        assert_eq!(ranked, vec!["did:key:steady".to_string(), "did:key:flaky".to_string()]);
+        assert_eq!(manager.trusted_peers(48.0).len(), 1);
+    }
+}
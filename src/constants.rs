@@ -0,0 +1,287 @@
+// DIAP Rust SDK - 协议常量模块
+// 协议标识、主题前缀、DHT键命名空间和DID上下文集中定义在这里；
+// 私有网络通过 NetworkParams 一次性替换命名空间，所有派生的协议名和主题保持一致
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// 协议版本
+pub const PROTOCOL_VERSION: &str = "1.0.0";
+
+/// 默认命名空间
+pub const DEFAULT_NAMESPACE: &str = "diap";
+
+/// libp2p identify 中声明的协议
+pub const DIAP_PROTOCOL: &str = "/diap/1.0.0";
+
+/// 请求-响应协议
+pub const DIAP_REQUEST_PROTOCOL_NAME: &str = "/diap/req/1.0.0";
+
+/// Kademlia协议（与公共IPFS DHT隔离）
+pub const DIAP_KAD_PROTOCOL: &str = "/diap/kad/1.0.0";
+
+/// 中继服务协议（邮箱存取、注册表镜像查询）
+pub const DIAP_RELAY_PROTOCOL_NAME: &str = "/diap/relay/1.0.0";
+
+/// 块交换协议（内嵌IPFS后端按CID取块）
+pub const DIAP_BLOCK_PROTOCOL_NAME: &str = "/diap/block/1.0.0";
+
+/// Iroh ALPN
+pub const IROH_ALPN: &str = "diap-iroh/communication/1";
+
+/// 智能体主题前缀（diap/agents/<did>/...）
+pub const AGENT_TOPIC_PREFIX: &str = "diap/agents";
+
+/// 分片网络主题前缀
+pub const SHARD_TOPIC_PREFIX: &str = "diap/shard";
+
+/// 吊销记录广播主题
+pub const REVOCATION_TOPIC: &str = "diap-revocations";
+
+/// DID文档更新通知主题
+pub const DID_UPDATE_TOPIC: &str = "diap-did-updates";
+
+/// 能力provider记录的DHT键命名空间
+pub const CAPABILITY_KEY_PREFIX: &str = "diap/capability/";
+
+/// 智能体值记录的DHT键命名空间
+pub const AGENT_RECORD_KEY_PREFIX: &str = "diap/agent/";
+
+/// 验证提示记录的DHT键命名空间
+pub const VERIFICATION_HINT_KEY_PREFIX: &str = "diap/hint/";
+
+/// 块provider记录的DHT键命名空间
+pub const BLOCK_KEY_PREFIX: &str = "diap/block/";
+
+/// 块交换地址记录的DHT键命名空间
+pub const BLOCK_PEER_KEY_PREFIX: &str = "diap/block-peer/";
+
+/// REST接口根路径
+pub const API_BASE_PATH: &str = "/diap/api";
+
+/// OpenAPI文档路径
+pub const OPENAPI_PATH: &str = "/diap/api/openapi.json";
+
+/// HTTP传输的请求路径
+pub const HTTP_REQUEST_PATH: &str = "/diap/transport/request";
+
+/// HTTP传输的发布路径
+pub const HTTP_PUBLISH_PATH: &str = "/diap/transport/publish";
+
+/// W3C DID v1 上下文
+pub const DID_CONTEXT_V1: &str = "https://www.w3.org/ns/did/v1";
+
+/// Ed25519 2020 签名套件上下文
+pub const ED25519_2020_CONTEXT: &str = "https://w3id.org/security/suites/ed25519-2020/v1";
+
+/// 网络参数（默认值与上面的常量一致）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkParams {
+    /// 命名空间，用于主题前缀、DHT键和Iroh ALPN
+    pub namespace: String,
+
+    /// libp2p协议前缀（例如 /diap）
+    pub protocol_prefix: String,
+
+    /// 协议版本
+    pub protocol_version: String,
+
+    /// DID文档的 @context
+    pub did_contexts: Vec<String>,
+}
+
+impl Default for NetworkParams {
+    fn default() -> Self {
+        Self::with_namespace(DEFAULT_NAMESPACE)
+    }
+}
+
+impl NetworkParams {
+    /// 以指定命名空间派生全部参数（私有网络使用，例如 "acme" 得到 /acme/kad/1.0.0、acme/agents）
+    pub fn with_namespace(namespace: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            protocol_prefix: format!("/{}", namespace),
+            protocol_version: PROTOCOL_VERSION.to_string(),
+            did_contexts: vec![DID_CONTEXT_V1.to_string(), ED25519_2020_CONTEXT.to_string()],
+        }
+    }
+
+    /// 校验参数
+    pub fn validate(&self) -> Result<()> {
+        if self.namespace.is_empty() || self.namespace.contains(['/', '*', ' ']) {
+            anyhow::bail!("无效的网络命名空间: {:?}", self.namespace);
+        }
+        if !self.protocol_prefix.starts_with('/') || self.protocol_prefix.ends_with('/') {
+            anyhow::bail!("协议前缀必须以'/'开头且不以'/'结尾: {:?}", self.protocol_prefix);
+        }
+        if self.protocol_version.is_empty() {
+            anyhow::bail!("协议版本不能为空");
+        }
+        if self.did_contexts.is_empty() {
+            anyhow::bail!("DID上下文不能为空");
+        }
+        Ok(())
+    }
+
+    /// libp2p identify 中声明的协议
+    pub fn protocol(&self) -> String {
+        format!("{}/{}", self.protocol_prefix, self.protocol_version)
+    }
+
+    /// 请求-响应协议
+    pub fn request_protocol(&self) -> String {
+        format!("{}/req/{}", self.protocol_prefix, self.protocol_version)
+    }
+
+    /// Kademlia协议
+    pub fn kad_protocol(&self) -> String {
+        format!("{}/kad/{}", self.protocol_prefix, self.protocol_version)
+    }
+
+    /// 中继服务协议
+    pub fn relay_protocol(&self) -> String {
+        format!("{}/relay/{}", self.protocol_prefix, self.protocol_version)
+    }
+
+    /// 块交换协议
+    pub fn block_protocol(&self) -> String {
+        format!("{}/block/{}", self.protocol_prefix, self.protocol_version)
+    }
+
+    /// Iroh ALPN
+    pub fn iroh_alpn(&self) -> String {
+        format!("{}-iroh/communication/1", self.namespace)
+    }
+
+    /// 智能体主题前缀
+    pub fn agent_topic_prefix(&self) -> String {
+        format!("{}/agents", self.namespace)
+    }
+
+    /// 分片网络主题前缀
+    pub fn shard_topic_prefix(&self) -> String {
+        format!("{}/shard", self.namespace)
+    }
+
+    /// 吊销记录广播主题
+    pub fn revocation_topic(&self) -> String {
+        format!("{}-revocations", self.namespace)
+    }
+
+    /// DID文档更新通知主题
+    pub fn did_update_topic(&self) -> String {
+        format!("{}-did-updates", self.namespace)
+    }
+
+    /// 能力provider记录的DHT键命名空间
+    pub fn capability_key_prefix(&self) -> String {
+        format!("{}/capability/", self.namespace)
+    }
+
+    /// 智能体值记录的DHT键命名空间
+    pub fn agent_record_key_prefix(&self) -> String {
+        format!("{}/agent/", self.namespace)
+    }
+
+    /// 验证提示记录的DHT键命名空间
+    pub fn verification_hint_key_prefix(&self) -> String {
+        format!("{}/hint/", self.namespace)
+    }
+
+    /// 块provider记录的DHT键命名空间
+    pub fn block_key_prefix(&self) -> String {
+        format!("{}/block/", self.namespace)
+    }
+
+    /// 块交换地址记录的DHT键命名空间
+    pub fn block_peer_key_prefix(&self) -> String {
+        format!("{}/block-peer/", self.namespace)
+    }
+
+    /// REST接口根路径
+    pub fn api_base_path(&self) -> String {
+        format!("{}/api", self.protocol_prefix)
+    }
+
+    /// OpenAPI文档路径
+    pub fn openapi_path(&self) -> String {
+        format!("{}/openapi.json", self.api_base_path())
+    }
+
+    /// HTTP传输的请求路径
+    pub fn http_request_path(&self) -> String {
+        format!("{}/transport/request", self.protocol_prefix)
+    }
+
+    /// HTTP传输的发布路径
+    pub fn http_publish_path(&self) -> String {
+        format!("{}/transport/publish", self.protocol_prefix)
+    }
+}
+
+static NETWORK_PARAMS: OnceLock<RwLock<Arc<NetworkParams>>> = OnceLock::new();
+
+fn params_cell() -> &'static RwLock<Arc<NetworkParams>> {
+    NETWORK_PARAMS.get_or_init(|| RwLock::new(Arc::new(NetworkParams::default())))
+}
+
+/// 设置进程全局的网络参数（应在创建节点和认证器之前调用）
+pub fn set_network_params(params: NetworkParams) -> Result<()> {
+    params.validate()?;
+    log::info!("🌐 网络命名空间: {} ({})", params.namespace, params.protocol());
+    *params_cell().write().unwrap_or_else(|e| e.into_inner()) = Arc::new(params);
+    Ok(())
+}
+
+/// 当前的网络参数
+pub fn network_params() -> Arc<NetworkParams> {
+    params_cell().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_match_constants() {
+        let params = NetworkParams::default();
+        params.validate().unwrap();
+        assert_eq!(params.protocol(), DIAP_PROTOCOL);
+        assert_eq!(params.request_protocol(), DIAP_REQUEST_PROTOCOL_NAME);
+        assert_eq!(params.kad_protocol(), DIAP_KAD_PROTOCOL);
+        assert_eq!(params.relay_protocol(), DIAP_RELAY_PROTOCOL_NAME);
+        assert_eq!(params.block_protocol(), DIAP_BLOCK_PROTOCOL_NAME);
+        assert_eq!(params.iroh_alpn(), IROH_ALPN);
+        assert_eq!(params.agent_topic_prefix(), AGENT_TOPIC_PREFIX);
+        assert_eq!(params.shard_topic_prefix(), SHARD_TOPIC_PREFIX);
+        assert_eq!(params.revocation_topic(), REVOCATION_TOPIC);
+        assert_eq!(params.did_update_topic(), DID_UPDATE_TOPIC);
+        assert_eq!(params.capability_key_prefix(), CAPABILITY_KEY_PREFIX);
+        assert_eq!(params.agent_record_key_prefix(), AGENT_RECORD_KEY_PREFIX);
+        assert_eq!(params.verification_hint_key_prefix(), VERIFICATION_HINT_KEY_PREFIX);
+        assert_eq!(params.block_key_prefix(), BLOCK_KEY_PREFIX);
+        assert_eq!(params.block_peer_key_prefix(), BLOCK_PEER_KEY_PREFIX);
+        assert_eq!(params.api_base_path(), API_BASE_PATH);
+        assert_eq!(params.openapi_path(), OPENAPI_PATH);
+        assert_eq!(params.http_request_path(), HTTP_REQUEST_PATH);
+        assert_eq!(params.http_publish_path(), HTTP_PUBLISH_PATH);
+        assert_eq!(params.did_contexts, vec![DID_CONTEXT_V1, ED25519_2020_CONTEXT]);
+    }
+
+    #[test]
+    fn test_private_namespace() {
+        let params = NetworkParams::with_namespace("acme");
+        params.validate().unwrap();
+        assert_eq!(params.kad_protocol(), "/acme/kad/1.0.0");
+        assert_eq!(params.agent_topic_prefix(), "acme/agents");
+        assert_eq!(params.openapi_path(), "/acme/api/openapi.json");
+        assert_eq!(params.http_publish_path(), "/acme/transport/publish");
+        assert_eq!(params.revocation_topic(), "acme-revocations");
+
+        assert!(NetworkParams::with_namespace("a/b").validate().is_err());
+        let bad_prefix = NetworkParams { protocol_prefix: "acme".to_string(), ..params };
+        assert!(bad_prefix.validate().is_err());
+    }
+}
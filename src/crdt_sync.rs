@@ -0,0 +1,630 @@
+// DIAP Rust SDK - CRDT状态同步模块
+// 协作智能体之间无需协调者即可共享可变状态（任务看板、在线列表）：
+// LWW-Map保存键值，OR-Set保存成员集合，同步消息由发送者签名，合并满足交换律/结合律/幂等
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::clock::{SharedClock, system_clock};
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::SignedMessage;
+
+/// CRDT同步消息类型标识（PubSubMessageType::Custom）
+pub const CRDT_SYNC_MESSAGE_TYPE: &str = "crdt_sync";
+
+/// 写入戳：先比较时间（毫秒），相同时按写入者DID决胜，保证所有副本得到相同结果
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Stamp {
+    /// 写入时间（毫秒）
+    pub timestamp: u64,
+
+    /// 写入者DID
+    pub actor: String,
+}
+
+/// LWW-Map中的条目（value为None表示已删除）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LwwEntry {
+    /// 值
+    pub value: Option<serde_json::Value>,
+
+    /// 写入戳
+    pub stamp: Stamp,
+}
+
+/// 合并结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeOutcome {
+    /// 改变了本地状态的条目数
+    pub applied: usize,
+
+    /// 本地已有或已被覆盖的条目数
+    pub ignored: usize,
+}
+
+impl MergeOutcome {
+    fn add(&mut self, other: MergeOutcome) {
+        self.applied += other.applied;
+        self.ignored += other.ignored;
+    }
+}
+
+/// 后写者胜（Last-Writer-Wins）映射
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LwwMap {
+    entries: BTreeMap<String, LwwEntry>,
+}
+
+impl LwwMap {
+    /// 创建空映射
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 写入键值，返回写入是否生效（戳不比现有条目新时不生效）
+    pub fn set(&mut self, key: &str, value: serde_json::Value, stamp: Stamp) -> bool {
+        self.apply(key, LwwEntry { value: Some(value), stamp })
+    }
+
+    /// 删除键（写入墓碑），返回是否生效
+    pub fn remove(&mut self, key: &str, stamp: Stamp) -> bool {
+        self.apply(key, LwwEntry { value: None, stamp })
+    }
+
+    /// 读取键值
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.entries.get(key).and_then(|e| e.value.as_ref())
+    }
+
+    /// 读取条目（包括墓碑）
+    pub fn entry(&self, key: &str) -> Option<&LwwEntry> {
+        self.entries.get(key)
+    }
+
+    /// 未删除的键值（按键排序）
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &serde_json::Value)> {
+        self.entries.iter().filter_map(|(k, e)| e.value.as_ref().map(|v| (k, v)))
+    }
+
+    /// 未删除的键数
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// 是否没有未删除的键
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 合并另一副本的状态
+    pub fn merge(&mut self, other: &LwwMap) -> MergeOutcome {
+        let mut outcome = MergeOutcome::default();
+        for (key, entry) in &other.entries {
+            if self.apply(key, entry.clone()) {
+                outcome.applied += 1;
+            } else {
+                outcome.ignored += 1;
+            }
+        }
+        outcome
+    }
+
+    /// 最大写入戳时间（本地写入需要比它新）
+    fn max_timestamp(&self) -> u64 {
+        self.entries.values().map(|e| e.stamp.timestamp).max().unwrap_or(0)
+    }
+
+    fn apply(&mut self, key: &str, entry: LwwEntry) -> bool {
+        match self.entries.get(key) {
+            Some(existing) if existing.stamp >= entry.stamp => false,
+            _ => {
+                self.entries.insert(key.to_string(), entry);
+                true
+            }
+        }
+    }
+}
+
+/// 观察删除集合（Observed-Remove Set）：并发的添加与删除中添加胜出
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrSet {
+    /// 元素 -> 仍有效的添加标签
+    adds: BTreeMap<String, BTreeSet<String>>,
+
+    /// 已删除的添加标签
+    removed: BTreeSet<String>,
+}
+
+impl OrSet {
+    /// 创建空集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加元素，tag需全局唯一（例如"写入者DID:序号"）
+    pub fn add(&mut self, element: &str, tag: String) {
+        if !self.removed.contains(&tag) {
+            self.adds.entry(element.to_string()).or_default().insert(tag);
+        }
+    }
+
+    /// 删除元素（仅删除本副本已观察到的添加），返回元素之前是否存在
+    pub fn remove(&mut self, element: &str) -> bool {
+        match self.adds.remove(element) {
+            Some(tags) => {
+                let existed = !tags.is_empty();
+                self.removed.extend(tags);
+                existed
+            }
+            None => false,
+        }
+    }
+
+    /// 是否包含元素
+    pub fn contains(&self, element: &str) -> bool {
+        self.adds.get(element).is_some_and(|tags| !tags.is_empty())
+    }
+
+    /// 全部元素（排序）
+    pub fn elements(&self) -> Vec<String> {
+        self.adds.iter()
+            .filter(|(_, tags)| !tags.is_empty())
+            .map(|(element, _)| element.clone())
+            .collect()
+    }
+
+    /// 元素数
+    pub fn len(&self) -> usize {
+        self.adds.values().filter(|tags| !tags.is_empty()).count()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 合并另一副本的状态
+    pub fn merge(&mut self, other: &OrSet) -> MergeOutcome {
+        let mut outcome = MergeOutcome::default();
+
+        for tag in &other.removed {
+            if self.removed.insert(tag.clone()) {
+                outcome.applied += 1;
+            } else {
+                outcome.ignored += 1;
+            }
+        }
+        for (element, tags) in &other.adds {
+            for tag in tags {
+                let local = self.adds.entry(element.clone()).or_default();
+                if self.removed.contains(tag) || local.contains(tag) {
+                    outcome.ignored += 1;
+                } else {
+                    local.insert(tag.clone());
+                    outcome.applied += 1;
+                }
+            }
+        }
+
+        // 清理被删除的添加标签
+        let removed = &self.removed;
+        for tags in self.adds.values_mut() {
+            tags.retain(|tag| !removed.contains(tag));
+        }
+        self.adds.retain(|_, tags| !tags.is_empty());
+
+        outcome
+    }
+}
+
+/// 副本状态：按名称组织的LWW-Map和OR-Set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CrdtState {
+    /// LWW-Map（例如任务看板）
+    pub maps: BTreeMap<String, LwwMap>,
+
+    /// OR-Set（例如在线列表）
+    pub sets: BTreeMap<String, OrSet>,
+
+    /// 本副本已分配的OR-Set标签序号
+    #[serde(default)]
+    next_tag: u64,
+}
+
+impl CrdtState {
+    /// 合并另一副本的状态（不合并对方的标签序号）
+    pub fn merge(&mut self, other: &CrdtState) -> MergeOutcome {
+        let mut outcome = MergeOutcome::default();
+        for (name, map) in &other.maps {
+            outcome.add(self.maps.entry(name.clone()).or_default().merge(map));
+        }
+        for (name, set) in &other.sets {
+            outcome.add(self.sets.entry(name.clone()).or_default().merge(set));
+        }
+        outcome
+    }
+}
+
+/// 签名的CRDT同步消息（携带发送者的完整状态）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrdtSyncMessage {
+    /// 发送者DID
+    pub sender_did: String,
+
+    /// 发送者状态
+    pub state: CrdtState,
+
+    /// 发送时间（秒）
+    pub sent_at: u64,
+
+    /// 发送者签名（base64）
+    pub signature: String,
+}
+
+impl CrdtSyncMessage {
+    /// 签名状态快照
+    pub fn new(signer: &dyn Signer, state: CrdtState, sent_at: u64) -> Result<Self> {
+        Self {
+            sender_did: signer.did(),
+            state,
+            sent_at,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+}
+
+impl SignedEnvelope for CrdtSyncMessage {
+    const KIND: &'static str = "CRDT同步消息";
+
+    fn signer_did(&self) -> &str {
+        &self.sender_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+impl SignedMessage for CrdtSyncMessage {
+    const MESSAGE_TYPE: &'static str = CRDT_SYNC_MESSAGE_TYPE;
+}
+
+/// 合并指标
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CrdtMetrics {
+    /// 本地写入次数
+    pub local_updates: u64,
+
+    /// 已合并的同步消息数
+    pub merges: u64,
+
+    /// 合并中改变本地状态的条目数
+    pub entries_applied: u64,
+
+    /// 合并中被忽略的条目数
+    pub entries_ignored: u64,
+
+    /// 被拒绝的同步消息数（签名无效或发送者不在允许列表中）
+    pub rejected_messages: u64,
+}
+
+/// CRDT副本：本地读写、生成和合并同步消息，可选持久化到文件
+#[derive(Clone)]
+pub struct CrdtReplica {
+    /// 本副本写入者DID
+    actor: String,
+
+    /// 状态
+    state: Arc<Mutex<CrdtState>>,
+
+    /// 允许同步的对端DID（None表示接受任何签名有效的对端）
+    allowed_peers: Option<Arc<HashSet<String>>>,
+
+    /// 持久化路径
+    path: Option<PathBuf>,
+
+    /// 时间源
+    clock: SharedClock,
+
+    local_updates: Arc<AtomicU64>,
+    merges: Arc<AtomicU64>,
+    entries_applied: Arc<AtomicU64>,
+    entries_ignored: Arc<AtomicU64>,
+    rejected_messages: Arc<AtomicU64>,
+}
+
+impl CrdtReplica {
+    /// 创建内存副本（系统时钟）
+    pub fn new(actor: &str) -> Self {
+        Self::new_with_clock(actor, system_clock())
+    }
+
+    /// 使用指定时间源创建内存副本
+    pub fn new_with_clock(actor: &str, clock: SharedClock) -> Self {
+        Self {
+            actor: actor.to_string(),
+            state: Arc::new(Mutex::new(CrdtState::default())),
+            allowed_peers: None,
+            path: None,
+            clock,
+            local_updates: Arc::new(AtomicU64::new(0)),
+            merges: Arc::new(AtomicU64::new(0)),
+            entries_applied: Arc::new(AtomicU64::new(0)),
+            entries_ignored: Arc::new(AtomicU64::new(0)),
+            rejected_messages: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 打开持久化副本（文件不存在时从空状态开始），每次变更后写回
+    pub fn open(actor: &str, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut replica = Self::new(actor);
+
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("无法读取CRDT状态: {:?}", path))?;
+            let state: CrdtState = serde_json::from_str(&content)
+                .with_context(|| format!("无法解析CRDT状态: {:?}", path))?;
+            replica.state = Arc::new(Mutex::new(state));
+        }
+
+        replica.path = Some(path);
+        Ok(replica)
+    }
+
+    /// 只接受来自这些DID的同步消息
+    pub fn with_allowed_peers(mut self, peers: Vec<String>) -> Self {
+        self.allowed_peers = Some(Arc::new(peers.into_iter().collect()));
+        self
+    }
+
+    /// 使用指定时间源
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 本副本写入者DID
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    /// 写入LWW-Map键值
+    pub fn map_set(&self, map: &str, key: &str, value: serde_json::Value) -> Result<()> {
+        self.update(|state, now, actor| {
+            let map = state.maps.entry(map.to_string()).or_default();
+            let stamp = next_stamp(map, now, actor);
+            map.set(key, value, stamp);
+        })
+    }
+
+    /// 删除LWW-Map键
+    pub fn map_remove(&self, map: &str, key: &str) -> Result<()> {
+        self.update(|state, now, actor| {
+            let map = state.maps.entry(map.to_string()).or_default();
+            let stamp = next_stamp(map, now, actor);
+            map.remove(key, stamp);
+        })
+    }
+
+    /// 读取LWW-Map键值
+    pub fn map_get(&self, map: &str, key: &str) -> Option<serde_json::Value> {
+        self.state.lock().unwrap()
+            .maps.get(map)
+            .and_then(|m| m.get(key).cloned())
+    }
+
+    /// LWW-Map快照
+    pub fn map(&self, map: &str) -> LwwMap {
+        self.state.lock().unwrap().maps.get(map).cloned().unwrap_or_default()
+    }
+
+    /// 向OR-Set添加元素
+    pub fn set_add(&self, set: &str, element: &str) -> Result<()> {
+        self.update(|state, _, actor| {
+            let tag = format!("{}:{}", actor, state.next_tag);
+            state.next_tag += 1;
+            state.sets.entry(set.to_string()).or_default().add(element, tag);
+        })
+    }
+
+    /// 从OR-Set删除元素
+    pub fn set_remove(&self, set: &str, element: &str) -> Result<()> {
+        self.update(|state, _, _| {
+            if let Some(set) = state.sets.get_mut(set) {
+                set.remove(element);
+            }
+        })
+    }
+
+    /// OR-Set是否包含元素
+    pub fn set_contains(&self, set: &str, element: &str) -> bool {
+        self.state.lock().unwrap()
+            .sets.get(set)
+            .is_some_and(|s| s.contains(element))
+    }
+
+    /// OR-Set全部元素
+    pub fn set_elements(&self, set: &str) -> Vec<String> {
+        self.state.lock().unwrap()
+            .sets.get(set)
+            .map(|s| s.elements())
+            .unwrap_or_default()
+    }
+
+    /// 完整状态快照
+    pub fn state(&self) -> CrdtState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// 生成签名的同步消息（signer应为本副本的写入者）
+    pub fn sync_message(&self, signer: &dyn Signer) -> Result<CrdtSyncMessage> {
+        if signer.did() != self.actor {
+            anyhow::bail!("签名者与副本写入者不一致: {}", signer.did());
+        }
+        CrdtSyncMessage::new(signer, self.state(), self.clock.now_secs())
+    }
+
+    /// 合并对端的同步消息（验证签名和允许列表）
+    pub fn apply_sync(&self, sync: &CrdtSyncMessage) -> Result<MergeOutcome> {
+        if let Some(allowed) = &self.allowed_peers {
+            if !allowed.contains(&sync.sender_did) {
+                self.rejected_messages.fetch_add(1, Ordering::Relaxed);
+                anyhow::bail!("对端不在CRDT同步允许列表中: {}", sync.sender_did);
+            }
+        }
+        if !sync.verify()? {
+            self.rejected_messages.fetch_add(1, Ordering::Relaxed);
+            anyhow::bail!("CRDT同步消息签名无效: {}", sync.sender_did);
+        }
+
+        let outcome = {
+            let mut state = self.state.lock().unwrap();
+            state.merge(&sync.state)
+        };
+
+        self.merges.fetch_add(1, Ordering::Relaxed);
+        self.entries_applied.fetch_add(outcome.applied as u64, Ordering::Relaxed);
+        self.entries_ignored.fetch_add(outcome.ignored as u64, Ordering::Relaxed);
+        log::debug!("🔀 合并CRDT状态 来自 {}: 应用 {} 忽略 {}",
+                    sync.sender_did, outcome.applied, outcome.ignored);
+
+        if outcome.applied > 0 {
+            self.save()?;
+        }
+        Ok(outcome)
+    }
+
+    /// 合并指标
+    pub fn metrics(&self) -> CrdtMetrics {
+        CrdtMetrics {
+            local_updates: self.local_updates.load(Ordering::Relaxed),
+            merges: self.merges.load(Ordering::Relaxed),
+            entries_applied: self.entries_applied.load(Ordering::Relaxed),
+            entries_ignored: self.entries_ignored.load(Ordering::Relaxed),
+            rejected_messages: self.rejected_messages.load(Ordering::Relaxed),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut CrdtState, u64, &str)) -> Result<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            f(&mut state, self.clock.now_millis(), &self.actor);
+        }
+        self.local_updates.fetch_add(1, Ordering::Relaxed);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建CRDT状态目录: {:?}", parent))?;
+        }
+
+        let content = serde_json::to_string(&self.state()).context("序列化CRDT状态失败")?;
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, content)
+            .with_context(|| format!("无法写入CRDT状态: {:?}", temp_path))?;
+        std::fs::rename(&temp_path, path)
+            .with_context(|| format!("无法保存CRDT状态: {:?}", path))?;
+        Ok(())
+    }
+}
+
+/// 本地写入戳：不早于映射中已有的最新写入，避免时钟回拨或同一毫秒内的写入丢失
+fn next_stamp(map: &LwwMap, now: u64, actor: &str) -> Stamp {
+    Stamp {
+        timestamp: now.max(map.max_timestamp() + 1),
+        actor: actor.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::clock::MockClock;
+    use serde_json::json;
+
+    #[test]
+    fn test_concurrent_edits_converge() {
+        let clock = Arc::new(MockClock::new(1_000));
+        let alice_key = KeyPair::generate().unwrap();
+        let bob_key = KeyPair::generate().unwrap();
+        let alice = CrdtReplica::new_with_clock(&alice_key.did, clock.clone());
+        let bob = CrdtReplica::new_with_clock(&bob_key.did, clock.clone());
+
+        alice.map_set("tasks", "t1", json!("todo")).unwrap();
+        alice.set_add("online", "alice").unwrap();
+        bob.apply_sync(&alice.sync_message(&alice_key).unwrap()).unwrap();
+
+        // 并发：alice删除在线状态，bob重新添加；两者同时修改任务
+        alice.set_remove("online", "alice").unwrap();
+        bob.set_add("online", "alice").unwrap();
+        alice.map_set("tasks", "t1", json!("doing")).unwrap();
+        bob.map_set("tasks", "t1", json!("done")).unwrap();
+
+        let from_alice = alice.sync_message(&alice_key).unwrap();
+        let from_bob = bob.sync_message(&bob_key).unwrap();
+        alice.apply_sync(&from_bob).unwrap();
+        bob.apply_sync(&from_alice).unwrap();
+
+        assert_eq!(alice.state().maps, bob.state().maps);
+        assert_eq!(alice.state().sets, bob.state().sets);
+        assert!(alice.set_contains("online", "alice"));
+        assert_eq!(alice.map_get("tasks", "t1"), bob.map_get("tasks", "t1"));
+
+        // 重复合并是幂等的
+        let again = alice.apply_sync(&from_bob).unwrap();
+        assert_eq!(again.applied, 0);
+        assert_eq!(alice.metrics().merges, 2);
+    }
+
+    #[test]
+    fn test_rejects_forged_and_unknown_peers() {
+        let alice_key = KeyPair::generate().unwrap();
+        let mallory_key = KeyPair::generate().unwrap();
+        let alice = CrdtReplica::new(&alice_key.did);
+        alice.map_set("tasks", "t1", json!("todo")).unwrap();
+
+        let bob = CrdtReplica::new("did:key:bob").with_allowed_peers(vec![alice_key.did.clone()]);
+        let mut forged = alice.sync_message(&alice_key).unwrap();
+        forged.state.maps.get_mut("tasks").unwrap().set(
+            "t1", json!("hacked"), Stamp { timestamp: u64::MAX, actor: alice_key.did.clone() },
+        );
+        assert!(bob.apply_sync(&forged).is_err());
+
+        let mallory = CrdtReplica::new(&mallory_key.did);
+        assert!(bob.apply_sync(&mallory.sync_message(&mallory_key).unwrap()).is_err());
+        assert_eq!(bob.metrics().rejected_messages, 2);
+        assert!(bob.map_get("tasks", "t1").is_none());
+    }
+
+    #[test]
+    fn test_persistence() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("crdt.json");
+
+        let replica = CrdtReplica::open("did:key:alice", &path).unwrap();
+        replica.map_set("board", "owner", json!("alice")).unwrap();
+        replica.set_add("online", "alice").unwrap();
+
+        let reopened = CrdtReplica::open("did:key:alice", &path).unwrap();
+        assert_eq!(reopened.map_get("board", "owner"), Some(json!("alice")));
+        assert_eq!(reopened.set_elements("online"), vec!["alice".to_string()]);
+
+        // 重新打开后的标签不与之前的冲突
+        reopened.set_add("online", "bob").unwrap();
+        assert_eq!(reopened.state().next_tag, 2);
+    }
+}
@@ -0,0 +1,494 @@
+// DIAP Rust SDK - 选择性披露凭证
+// 智能体可以为其他DID签发SD-JWT格式的可验证凭证（EdDSA签名，签发者为did:key）；
+// 持有者出示时只披露需要的声明，并用自己的DID密钥签名key binding JWT绑定验证方和nonce
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::key_manager::{KeyPair, Signer};
+
+/// SD-JWT凭证的JWT类型
+pub const SD_JWT_TYPE: &str = "vc+sd-jwt";
+
+/// Key binding JWT的类型
+pub const KB_JWT_TYPE: &str = "kb+jwt";
+
+/// 披露摘要算法
+pub const SD_ALGORITHM: &str = "sha-256";
+
+/// 出示时key binding JWT允许的最大时间偏差（秒）
+pub const MAX_KEY_BINDING_AGE_SECS: u64 = 300;
+
+/// JWT头部
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    typ: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    kid: Option<String>,
+}
+
+/// 凭证载荷（可选择性披露的声明只以摘要形式出现在_sd中）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialPayload {
+    /// 签发者DID
+    pub iss: String,
+
+    /// 凭证主体（持有者）DID
+    pub sub: String,
+
+    /// 凭证类型
+    pub vct: String,
+
+    /// 签发时间（秒）
+    pub iat: u64,
+
+    /// 过期时间（秒）
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub exp: Option<u64>,
+
+    /// 可披露声明的摘要
+    #[serde(rename = "_sd", default)]
+    pub sd: Vec<String>,
+
+    /// 摘要算法
+    #[serde(rename = "_sd_alg")]
+    pub sd_alg: String,
+
+    /// 始终公开的声明
+    #[serde(flatten)]
+    pub public_claims: Map<String, Value>,
+}
+
+/// Key binding JWT载荷
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeyBindingPayload {
+    iat: u64,
+    aud: String,
+    nonce: String,
+    sd_hash: String,
+}
+
+/// 单个声明的披露（[salt, name, value]的base64url编码）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disclosure {
+    /// 编码后的披露
+    pub encoded: String,
+
+    /// 声明名称
+    pub name: String,
+
+    /// 声明值
+    pub value: Value,
+}
+
+impl Disclosure {
+    fn new(name: &str, value: Value) -> Result<Self> {
+        let salt = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 16]>());
+        let json = serde_json::to_vec(&serde_json::json!([salt, name, value])).context("序列化披露失败")?;
+        Ok(Self {
+            encoded: URL_SAFE_NO_PAD.encode(json),
+            name: name.to_string(),
+            value,
+        })
+    }
+
+    /// 解析编码后的披露
+    pub fn parse(encoded: &str) -> Result<Self> {
+        let json = URL_SAFE_NO_PAD.decode(encoded).context("解码披露失败")?;
+        let parts: Vec<Value> = serde_json::from_slice(&json).context("解析披露失败")?;
+        match parts.as_slice() {
+            [Value::String(_salt), Value::String(name), value] => Ok(Self {
+                encoded: encoded.to_string(),
+                name: name.clone(),
+                value: value.clone(),
+            }),
+            _ => anyhow::bail!("披露格式错误"),
+        }
+    }
+
+    /// 披露摘要（出现在凭证的_sd中）
+    pub fn digest(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.encoded.as_bytes()))
+    }
+}
+
+/// 凭证构建器
+#[derive(Debug, Clone)]
+pub struct CredentialBuilder {
+    credential_type: String,
+    subject_did: String,
+    disclosable: Map<String, Value>,
+    public_claims: Map<String, Value>,
+    valid_for: Option<Duration>,
+}
+
+impl CredentialBuilder {
+    /// 为subject_did构建指定类型的凭证
+    pub fn new(credential_type: &str, subject_did: &str) -> Self {
+        Self {
+            credential_type: credential_type.to_string(),
+            subject_did: subject_did.to_string(),
+            disclosable: Map::new(),
+            public_claims: Map::new(),
+            valid_for: None,
+        }
+    }
+
+    /// 添加可选择性披露的声明
+    pub fn claim(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.disclosable.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// 添加始终公开的声明
+    pub fn public_claim(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.public_claims.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// 设置有效期
+    pub fn valid_for(mut self, duration: Duration) -> Self {
+        self.valid_for = Some(duration);
+        self
+    }
+
+    /// 签发凭证
+    pub fn issue(self, issuer: &dyn Signer, now: u64) -> Result<SdJwtCredential> {
+        for reserved in ["iss", "sub", "vct", "iat", "exp", "_sd", "_sd_alg", "cnf"] {
+            if self.public_claims.contains_key(reserved) || self.disclosable.contains_key(reserved) {
+                anyhow::bail!("声明名称{}为保留字段", reserved);
+            }
+        }
+
+        let disclosures = self.disclosable.into_iter()
+            .map(|(name, value)| Disclosure::new(&name, value))
+            .collect::<Result<Vec<_>>>()?;
+        let mut digests: Vec<String> = disclosures.iter().map(Disclosure::digest).collect();
+        // 摘要排序，避免从顺序推断未披露声明的名称
+        digests.sort();
+
+        let payload = CredentialPayload {
+            iss: issuer.did(),
+            sub: self.subject_did,
+            vct: self.credential_type,
+            iat: now,
+            exp: self.valid_for.map(|ttl| now + ttl.as_secs()),
+            sd: digests,
+            sd_alg: SD_ALGORITHM.to_string(),
+            public_claims: self.public_claims,
+        };
+        let header = JwtHeader {
+            alg: "EdDSA".to_string(),
+            typ: SD_JWT_TYPE.to_string(),
+            kid: Some(format!("{}#key-1", issuer.did())),
+        };
+        let jwt = sign_jwt(issuer, &header, &payload)?;
+
+        log::info!("📜 签发凭证 {} → {}", payload.vct, payload.sub);
+        Ok(SdJwtCredential { jwt, disclosures })
+    }
+}
+
+/// 持有者保存的SD-JWT凭证（签发者JWT + 全部披露）
+#[derive(Debug, Clone)]
+pub struct SdJwtCredential {
+    /// 签发者签名的JWT
+    pub jwt: String,
+
+    /// 全部披露
+    pub disclosures: Vec<Disclosure>,
+}
+
+impl SdJwtCredential {
+    /// 解析"jwt~披露~...~"格式
+    pub fn parse(serialized: &str) -> Result<Self> {
+        let mut parts = serialized.split('~');
+        let jwt = parts.next().filter(|jwt| !jwt.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("SD-JWT缺少签发者JWT"))?
+            .to_string();
+        let disclosures = parts
+            .filter(|part| !part.is_empty())
+            .map(Disclosure::parse)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { jwt, disclosures })
+    }
+
+    /// 序列化为"jwt~披露~...~"格式
+    pub fn serialize(&self) -> String {
+        serialize_sd_jwt(&self.jwt, self.disclosures.iter())
+    }
+
+    /// 凭证载荷（不验证签名）
+    pub fn payload(&self) -> Result<CredentialPayload> {
+        decode_jwt::<CredentialPayload>(&self.jwt).map(|(_, payload, _, _)| payload)
+    }
+
+    /// 可披露的声明名称
+    pub fn claim_names(&self) -> Vec<&str> {
+        self.disclosures.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    /// 只披露指定声明，并由持有者签名绑定验证方和nonce
+    pub fn present(&self, disclose: &[&str], holder: &dyn Signer, audience: &str, nonce: &str, now: u64) -> Result<String> {
+        let payload = self.payload()?;
+        if payload.sub != holder.did() {
+            anyhow::bail!("凭证主体{}与持有者{}不一致", payload.sub, holder.did());
+        }
+        for name in disclose {
+            if !self.disclosures.iter().any(|d| d.name == *name) {
+                anyhow::bail!("凭证中没有可披露的声明: {}", name);
+            }
+        }
+
+        let sd_jwt = serialize_sd_jwt(&self.jwt, self.disclosures.iter().filter(|d| disclose.contains(&d.name.as_str())));
+        let header = JwtHeader {
+            alg: "EdDSA".to_string(),
+            typ: KB_JWT_TYPE.to_string(),
+            kid: None,
+        };
+        let key_binding = KeyBindingPayload {
+            iat: now,
+            aud: audience.to_string(),
+            nonce: nonce.to_string(),
+            sd_hash: sd_hash(&sd_jwt),
+        };
+        Ok(format!("{}{}", sd_jwt, sign_jwt(holder, &header, &key_binding)?))
+    }
+}
+
+/// 验证方对出示的要求
+#[derive(Debug, Clone, Default)]
+pub struct PresentationRequest {
+    /// 验证方标识（key binding的aud）
+    pub audience: String,
+
+    /// 本次挑战nonce
+    pub nonce: String,
+
+    /// 信任的签发者DID（为空时接受任意签发者）
+    pub trusted_issuers: Vec<String>,
+
+    /// 要求的凭证类型
+    pub credential_type: Option<String>,
+
+    /// 必须披露的声明
+    pub required_claims: Vec<String>,
+}
+
+impl PresentationRequest {
+    /// 创建出示要求
+    pub fn new(audience: &str, nonce: &str) -> Self {
+        Self {
+            audience: audience.to_string(),
+            nonce: nonce.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// 信任的签发者
+    pub fn trust_issuer(mut self, issuer_did: &str) -> Self {
+        self.trusted_issuers.push(issuer_did.to_string());
+        self
+    }
+
+    /// 要求的凭证类型
+    pub fn with_type(mut self, credential_type: &str) -> Self {
+        self.credential_type = Some(credential_type.to_string());
+        self
+    }
+
+    /// 必须披露的声明
+    pub fn require_claim(mut self, name: &str) -> Self {
+        self.required_claims.push(name.to_string());
+        self
+    }
+}
+
+/// 验证通过的凭证出示
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedPresentation {
+    /// 签发者DID
+    pub issuer: String,
+
+    /// 持有者DID
+    pub subject: String,
+
+    /// 凭证类型
+    pub credential_type: String,
+
+    /// 公开声明和已披露的声明
+    pub claims: Map<String, Value>,
+
+    /// 签发时间
+    pub issued_at: u64,
+
+    /// 过期时间
+    pub expires_at: Option<u64>,
+}
+
+/// 验证凭证出示：签发者签名、披露摘要、有效期、持有者key binding、验证方要求
+pub fn verify_presentation(presentation: &str, request: &PresentationRequest, now: u64) -> Result<VerifiedPresentation> {
+    let (sd_jwt, kb_jwt) = presentation.rsplit_once('~')
+        .ok_or_else(|| anyhow::anyhow!("凭证出示格式错误"))?;
+    let sd_jwt = format!("{}~", sd_jwt);
+    if kb_jwt.is_empty() {
+        anyhow::bail!("凭证出示缺少key binding");
+    }
+    let credential = SdJwtCredential::parse(&sd_jwt)?;
+
+    // 1. 签发者签名
+    let (header, payload, signing_input, signature) = decode_jwt::<CredentialPayload>(&credential.jwt)?;
+    if header.typ != SD_JWT_TYPE || header.alg != "EdDSA" || payload.sd_alg != SD_ALGORITHM {
+        anyhow::bail!("不支持的凭证格式: {} {} {}", header.typ, header.alg, payload.sd_alg);
+    }
+    if !KeyPair::verify_with_did_key(&payload.iss, signing_input.as_bytes(), &signature)? {
+        anyhow::bail!("凭证签名无效");
+    }
+    if !request.trusted_issuers.is_empty() && !request.trusted_issuers.contains(&payload.iss) {
+        anyhow::bail!("不信任的签发者: {}", payload.iss);
+    }
+    if let Some(credential_type) = &request.credential_type {
+        if &payload.vct != credential_type {
+            anyhow::bail!("凭证类型不符: 需要{}，实际{}", credential_type, payload.vct);
+        }
+    }
+    if payload.exp.is_some_and(|exp| now >= exp) {
+        anyhow::bail!("凭证已过期");
+    }
+
+    // 2. 持有者key binding（证明出示者控制凭证主体DID，且出示针对本次验证）
+    let (kb_header, key_binding, kb_input, kb_signature) = decode_jwt::<KeyBindingPayload>(kb_jwt)?;
+    if kb_header.typ != KB_JWT_TYPE {
+        anyhow::bail!("key binding类型错误: {}", kb_header.typ);
+    }
+    if !KeyPair::verify_with_did_key(&payload.sub, kb_input.as_bytes(), &kb_signature)? {
+        anyhow::bail!("key binding签名无效");
+    }
+    if key_binding.aud != request.audience || key_binding.nonce != request.nonce {
+        anyhow::bail!("key binding与本次验证不符");
+    }
+    if key_binding.iat.abs_diff(now) > MAX_KEY_BINDING_AGE_SECS {
+        anyhow::bail!("key binding已过期");
+    }
+    if key_binding.sd_hash != sd_hash(&sd_jwt) {
+        anyhow::bail!("key binding与出示的披露不符");
+    }
+
+    // 3. 披露必须是凭证中签名的摘要
+    let mut claims = payload.public_claims.clone();
+    for disclosure in &credential.disclosures {
+        if !payload.sd.contains(&disclosure.digest()) {
+            anyhow::bail!("披露不属于该凭证: {}", disclosure.name);
+        }
+        if claims.insert(disclosure.name.clone(), disclosure.value.clone()).is_some() {
+            anyhow::bail!("重复的声明: {}", disclosure.name);
+        }
+    }
+    for name in &request.required_claims {
+        if !claims.contains_key(name) {
+            anyhow::bail!("缺少必需的声明: {}", name);
+        }
+    }
+
+    log::info!("✅ 凭证出示验证通过: {} 由 {} 签发给 {}", payload.vct, payload.iss, payload.sub);
+    Ok(VerifiedPresentation {
+        issuer: payload.iss,
+        subject: payload.sub,
+        credential_type: payload.vct,
+        claims,
+        issued_at: payload.iat,
+        expires_at: payload.exp,
+    })
+}
+
+fn serialize_sd_jwt<'a>(jwt: &str, disclosures: impl Iterator<Item = &'a Disclosure>) -> String {
+    let mut serialized = format!("{}~", jwt);
+    for disclosure in disclosures {
+        serialized.push_str(&disclosure.encoded);
+        serialized.push('~');
+    }
+    serialized
+}
+
+fn sd_hash(sd_jwt: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(sd_jwt.as_bytes()))
+}
+
+fn sign_jwt<T: Serialize>(signer: &dyn Signer, header: &JwtHeader, payload: &T) -> Result<String> {
+    let header = URL_SAFE_NO_PAD.encode(serde_json::to_vec(header).context("序列化JWT头部失败")?);
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(payload).context("序列化JWT载荷失败")?);
+    let signing_input = format!("{}.{}", header, payload);
+    let signature = signer.sign(signing_input.as_bytes())?;
+    Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature)))
+}
+
+/// 解析JWT，返回头部、载荷、签名输入和签名
+fn decode_jwt<T: for<'de> Deserialize<'de>>(jwt: &str) -> Result<(JwtHeader, T, String, Vec<u8>)> {
+    let mut parts = jwt.split('.');
+    let (Some(header), Some(payload), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        anyhow::bail!("JWT格式错误");
+    };
+    let decoded_header: JwtHeader = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).context("解码JWT头部失败")?)
+        .context("解析JWT头部失败")?;
+    let decoded_payload: T = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).context("解码JWT载荷失败")?)
+        .context("解析JWT载荷失败")?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).context("解码JWT签名失败")?;
+    Ok((decoded_header, decoded_payload, format!("{}.{}", header, payload), signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_selectively_present() {
+        let issuer = KeyPair::generate().unwrap();
+        let holder = KeyPair::generate().unwrap();
+        let credential = CredentialBuilder::new("AgentCapability", &holder.did)
+            .public_claim("operator", "acme")
+            .claim("max_spend", 500)
+            .claim("region", "eu")
+            .claim("model", "planner-v2")
+            .valid_for(Duration::from_secs(3600))
+            .issue(&issuer, 1_000)
+            .unwrap();
+
+        // 持有者保存后重新解析
+        let credential = SdJwtCredential::parse(&credential.serialize()).unwrap();
+        assert_eq!(credential.claim_names().len(), 3);
+
+        let presentation = credential.present(&["region"], &holder, "did:key:verifier", "n-1", 1_010).unwrap();
+        let request = PresentationRequest::new("did:key:verifier", "n-1")
+            .trust_issuer(&issuer.did)
+            .with_type("AgentCapability")
+            .require_claim("region");
+        let verified = verify_presentation(&presentation, &request, 1_020).unwrap();
+        assert_eq!(verified.subject, holder.did);
+        assert_eq!(verified.claims.get("region"), Some(&Value::from("eu")));
+        assert_eq!(verified.claims.get("operator"), Some(&Value::from("acme")));
+        assert!(!verified.claims.contains_key("max_spend"));
+        assert!(!presentation.contains(&credential.disclosures.iter().find(|d| d.name == "max_spend").unwrap().encoded));
+
+        // 未披露的必需声明、错误的nonce、不信任的签发者、过期凭证
+        let err = verify_presentation(&presentation, &request.clone().require_claim("max_spend"), 1_020).unwrap_err();
+        assert!(err.to_string().contains("max_spend"));
+        assert!(verify_presentation(&presentation, &PresentationRequest { nonce: "n-2".to_string(), ..request.clone() }, 1_020).is_err());
+        assert!(verify_presentation(&presentation, &PresentationRequest::new("did:key:verifier", "n-1").trust_issuer(&holder.did), 1_020).is_err());
+        assert!(verify_presentation(&presentation, &request, 4_600).is_err());
+
+        // 其他人不能冒充持有者出示
+        let thief = KeyPair::generate().unwrap();
+        assert!(credential.present(&["region"], &thief, "did:key:verifier", "n-1", 1_010).is_err());
+
+        // 追加伪造的披露会破坏key binding和摘要检查
+        let forged = Disclosure::new("max_spend", Value::from(1_000_000)).unwrap();
+        let (sd_jwt, kb_jwt) = presentation.rsplit_once('~').unwrap();
+        let tampered = format!("{}~{}~{}", sd_jwt, forged.encoded, kb_jwt);
+        assert!(verify_presentation(&tampered, &request, 1_020).is_err());
+    }
+}
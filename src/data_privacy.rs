@@ -0,0 +1,289 @@
+// DIAP Rust SDK - 数据隐私模块
+// 按交互对象DID导出或删除本地保存的全部数据（类GDPR访问权与删除权），删除后生成签名报告
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use crate::did_cache::{CacheEntry, DIDCache};
+use crate::key_manager::{KeyPair, SignedEnvelope};
+use crate::message_archive::MessageArchive;
+use crate::nonce_manager::{NonceManager, NonceRecord};
+use crate::pairwise_did::PairwiseIdentityManager;
+use crate::pubsub_authenticator::AuthenticatedMessage;
+
+/// 导出的数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataExport {
+    /// 数据主体DID
+    pub subject_did: String,
+
+    /// 导出方DID
+    pub exported_by: String,
+
+    /// 导出时间
+    pub exported_at: String,
+
+    /// 缓存的DID文档
+    pub did_documents: Vec<CacheEntry>,
+
+    /// 归档的消息（发送或接收）
+    pub messages: Vec<AuthenticatedMessage>,
+
+    /// 使用过的nonce记录
+    pub nonces: Vec<NonceRecord>,
+
+    /// 与该交互对象使用的成对DID
+    pub pairwise_did: Option<String>,
+}
+
+/// 单个存储的删除结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErasedStore {
+    /// 存储名称
+    pub store: String,
+
+    /// 删除的条目数
+    pub removed: usize,
+}
+
+/// 删除报告（由执行删除的一方签名）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErasureReport {
+    /// 数据主体DID
+    pub subject_did: String,
+
+    /// 执行删除的DID
+    pub erased_by: String,
+
+    /// 删除时间
+    pub erased_at: String,
+
+    /// 各存储的删除结果
+    pub stores: Vec<ErasedStore>,
+
+    /// 出于安全原因保留的数据说明
+    pub retained: Vec<String>,
+
+    /// 签名（base64）
+    pub signature: String,
+}
+
+impl ErasureReport {
+    /// 删除条目总数
+    pub fn total_removed(&self) -> usize {
+        self.stores.iter().map(|s| s.removed).sum()
+    }
+}
+
+impl SignedEnvelope for ErasureReport {
+    const KIND: &'static str = "删除报告";
+
+    fn signer_did(&self) -> &str {
+        &self.erased_by
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 数据隐私管理器
+/// 汇总本地各个存储，按DID统一导出或删除
+pub struct DataPrivacyManager {
+    /// 本地密钥对（用于签名删除报告）
+    keypair: KeyPair,
+
+    /// DID文档缓存
+    did_cache: Option<DIDCache>,
+
+    /// 消息归档
+    message_archive: Option<MessageArchive>,
+
+    /// Nonce管理器
+    nonce_manager: Option<NonceManager>,
+
+    /// 成对身份
+    pairwise_identities: Option<PairwiseIdentityManager>,
+}
+
+impl DataPrivacyManager {
+    /// 创建数据隐私管理器
+    pub fn new(keypair: KeyPair) -> Self {
+        Self {
+            keypair,
+            did_cache: None,
+            message_archive: None,
+            nonce_manager: None,
+            pairwise_identities: None,
+        }
+    }
+
+    /// 纳入DID文档缓存
+    pub fn with_did_cache(mut self, did_cache: DIDCache) -> Self {
+        self.did_cache = Some(did_cache);
+        self
+    }
+
+    /// 纳入消息归档
+    pub fn with_message_archive(mut self, message_archive: MessageArchive) -> Self {
+        self.message_archive = Some(message_archive);
+        self
+    }
+
+    /// 纳入Nonce管理器
+    pub fn with_nonce_manager(mut self, nonce_manager: NonceManager) -> Self {
+        self.nonce_manager = Some(nonce_manager);
+        self
+    }
+
+    /// 纳入成对身份管理器
+    pub fn with_pairwise_identities(mut self, pairwise_identities: PairwiseIdentityManager) -> Self {
+        self.pairwise_identities = Some(pairwise_identities);
+        self
+    }
+
+    /// 导出与指定DID相关的全部本地数据
+    pub fn export_data_for(&self, did: &str) -> Result<DataExport> {
+        log::info!("📤 导出DID相关数据: {}", did);
+
+        let did_documents = self.did_cache
+            .as_ref()
+            .map(|cache| cache.entries_for_did(did))
+            .unwrap_or_default();
+
+        let messages = self.message_archive
+            .as_ref()
+            .map(|archive| archive.messages_for_did(did).into_iter().map(|m| m.message).collect())
+            .unwrap_or_default();
+
+        let nonces = self.nonce_manager
+            .as_ref()
+            .map(|manager| manager.records_for_did(did))
+            .unwrap_or_default();
+
+        let pairwise_did = match &self.pairwise_identities {
+            Some(pairwise) if pairwise.known_counterparties().iter().any(|c| c == did) => {
+                Some(pairwise.identity_for(did)?.did)
+            }
+            _ => None,
+        };
+
+        Ok(DataExport {
+            subject_did: did.to_string(),
+            exported_by: self.keypair.did.clone(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            did_documents,
+            messages,
+            nonces,
+            pairwise_did,
+        })
+    }
+
+    /// 删除与指定DID相关的全部本地数据，返回签名的删除报告
+    pub fn erase_data_for(&self, did: &str) -> Result<ErasureReport> {
+        log::info!("🗑️ 删除DID相关数据: {}", did);
+
+        let mut stores = Vec::new();
+        let mut retained = Vec::new();
+
+        if let Some(cache) = &self.did_cache {
+            stores.push(ErasedStore {
+                store: "did_cache".to_string(),
+                removed: cache.remove_did(did),
+            });
+        }
+
+        if let Some(archive) = &self.message_archive {
+            stores.push(ErasedStore {
+                store: "message_archive".to_string(),
+                removed: archive.erase_did(did),
+            });
+        }
+
+        if let Some(pairwise) = &self.pairwise_identities {
+            stores.push(ErasedStore {
+                store: "pairwise_identities".to_string(),
+                removed: usize::from(pairwise.forget(did).is_some()),
+            });
+        }
+
+        // nonce记录用于防重放，删除会打开重放窗口；它们会在有效期后自动清理
+        if let Some(manager) = &self.nonce_manager {
+            let count = manager.records_for_did(did).len();
+            if count > 0 {
+                retained.push(format!("nonce记录 {} 条（防重放所需，到期后自动清理）", count));
+            }
+        }
+
+        let report = ErasureReport {
+            subject_did: did.to_string(),
+            erased_by: self.keypair.did.clone(),
+            erased_at: chrono::Utc::now().to_rfc3339(),
+            stores,
+            retained,
+            signature: String::new(),
+        }
+        .signed_by(&self.keypair)?;
+
+        log::info!("✅ 已删除 {} 条与 {} 相关的数据", report.total_removed(), did);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pubsub_authenticator::PubSubMessageType;
+
+    fn message(id: &str, from_did: &str) -> AuthenticatedMessage {
+        AuthenticatedMessage {
+            message_id: id.to_string(),
+            message_type: PubSubMessageType::Custom("test".to_string()),
+            from_did: from_did.to_string(),
+            to_did: None,
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: "general".to_string(),
+            content: b"hello".to_vec(),
+            nonce: String::new(),
+            zkp_proof: Vec::new(),
+            signature: Vec::new(),
+            timestamp: 0,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_export_and_erase() {
+        let local = KeyPair::generate().unwrap();
+        let archive = MessageArchive::new();
+        archive.archive(&message("m1", "did:key:alice"), false);
+        archive.archive(&message("m2", "did:key:bob"), false);
+
+        let pairwise = PairwiseIdentityManager::new(local.clone());
+        pairwise.identity_for("did:key:alice").unwrap();
+
+        let manager = DataPrivacyManager::new(local)
+            .with_message_archive(archive.clone())
+            .with_pairwise_identities(pairwise.clone());
+
+        let export = manager.export_data_for("did:key:alice").unwrap();
+        assert_eq!(export.messages.len(), 1);
+        assert!(export.pairwise_did.is_some());
+
+        let report = manager.erase_data_for("did:key:alice").unwrap();
+        assert_eq!(report.total_removed(), 2);
+        assert!(report.verify().unwrap());
+        assert_eq!(archive.len(), 1);
+        assert!(pairwise.known_counterparties().is_empty());
+
+        let mut tampered = report.clone();
+        tampered.stores[0].removed = 0;
+        assert!(!tampered.verify().unwrap());
+    }
+}
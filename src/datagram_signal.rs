@@ -0,0 +1,179 @@
+// DIAP Rust SDK - 低延迟信号（不可靠数据报）
+// 在线状态、输入中/进度心跳等信号对延迟敏感但允许丢失：
+// 每个信号单独签名，编码后放进一个QUIC数据报发送，不重传、不排队；
+// 接收方只保留每个发送者每类信号的最新一条，过期或乱序到达的直接丢弃
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::key_manager::{KeyPair, Signer};
+
+/// 单个信号数据报的最大字节数（低于常见路径MTU下QUIC可承载的数据报大小）
+pub const MAX_SIGNAL_DATAGRAM_SIZE: usize = 1024;
+
+/// 信号默认最长存活时间（毫秒），超过后到达的信号已无意义
+pub const DEFAULT_SIGNAL_MAX_AGE_MS: u64 = 5_000;
+
+/// 信号类型
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SignalKind {
+    /// 在线状态
+    Presence,
+    /// 正在输入
+    Typing,
+    /// 任务进度
+    Progress,
+    /// 自定义信号
+    Custom(String),
+}
+
+/// 签名的信号
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signal {
+    /// 发送者DID
+    pub from_did: String,
+
+    /// 信号类型
+    pub kind: SignalKind,
+
+    /// 发送者内单调递增的序号
+    pub seq: u64,
+
+    /// 发送时间（Unix毫秒）
+    pub sent_at_ms: u64,
+
+    /// 信号内容
+    pub payload: Vec<u8>,
+
+    /// 发送者签名
+    pub signature: Vec<u8>,
+}
+
+impl Signal {
+    /// 创建并签名信号
+    pub fn sign(signer: &dyn Signer, kind: SignalKind, payload: &[u8], seq: u64, now_ms: u64) -> Result<Self> {
+        let mut signal = Self {
+            from_did: signer.did(),
+            kind,
+            seq,
+            sent_at_ms: now_ms,
+            payload: payload.to_vec(),
+            signature: Vec::new(),
+        };
+        signal.signature = signer.sign(&signal.signing_data()?)?;
+        Ok(signal)
+    }
+
+    /// 签名数据（签名字段置空）
+    pub fn signing_data(&self) -> Result<Vec<u8>> {
+        let unsigned = Self { signature: Vec::new(), ..self.clone() };
+        bincode::serialize(&unsigned).context("序列化信号失败")
+    }
+
+    /// 编码为数据报，超过单个数据报大小时报错（信号不分片）
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let bytes = bincode::serialize(self).context("序列化信号失败")?;
+        if bytes.len() > MAX_SIGNAL_DATAGRAM_SIZE {
+            anyhow::bail!("信号过大: {} 字节（上限 {} 字节）", bytes.len(), MAX_SIGNAL_DATAGRAM_SIZE);
+        }
+        Ok(bytes)
+    }
+
+    /// 从数据报解码（不验证签名）
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if data.len() > MAX_SIGNAL_DATAGRAM_SIZE {
+            anyhow::bail!("信号数据报过大: {} 字节", data.len());
+        }
+        bincode::deserialize(data).context("解析信号失败")
+    }
+
+    /// 用did:key公钥验证签名
+    pub fn verify(&self) -> Result<bool> {
+        KeyPair::verify_with_did_key(&self.from_did, &self.signing_data()?, &self.signature)
+    }
+}
+
+/// 接收端信号过滤：丢弃过期、来自未来和乱序（序号不大于已收到的最新序号）的信号
+#[derive(Debug, Clone)]
+pub struct SignalFilter {
+    /// 最长存活时间（毫秒）
+    max_age_ms: u64,
+
+    /// (发送者DID, 信号类型) -> 最新序号
+    latest: HashMap<(String, SignalKind), u64>,
+}
+
+impl Default for SignalFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIGNAL_MAX_AGE_MS)
+    }
+}
+
+impl SignalFilter {
+    /// 创建过滤器
+    pub fn new(max_age_ms: u64) -> Self {
+        Self { max_age_ms, latest: HashMap::new() }
+    }
+
+    /// 判断信号是否应交给应用层；接受时记录其序号
+    pub fn accept(&mut self, signal: &Signal, now_ms: u64) -> bool {
+        if signal.sent_at_ms.abs_diff(now_ms) > self.max_age_ms {
+            log::debug!("⏱️ 丢弃过期信号: {} {:?} #{}", signal.from_did, signal.kind, signal.seq);
+            return false;
+        }
+        let key = (signal.from_did.clone(), signal.kind.clone());
+        match self.latest.get(&key) {
+            Some(&latest) if signal.seq <= latest => {
+                log::debug!("🔀 丢弃乱序信号: {} {:?} #{} (最新 #{})", signal.from_did, signal.kind, signal.seq, latest);
+                false
+            }
+            _ => {
+                self.latest.insert(key, signal.seq);
+                true
+            }
+        }
+    }
+
+    /// 忘记某个发送者的信号状态（如断开连接后）
+    pub fn forget(&mut self, did: &str) {
+        self.latest.retain(|(from_did, _), _| from_did != did);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_encode_and_filter() {
+        let keypair = KeyPair::generate().unwrap();
+        let now = 1_700_000_000_000;
+
+        let typing = Signal::sign(&keypair, SignalKind::Typing, b"1", 2, now).unwrap();
+        let decoded = Signal::decode(&typing.encode().unwrap()).unwrap();
+        assert_eq!(decoded, typing);
+        assert!(decoded.verify().unwrap());
+
+        let mut forged = decoded.clone();
+        forged.seq = 3;
+        assert!(!forged.verify().unwrap());
+
+        assert!(Signal::sign(&keypair, SignalKind::Progress, &[0; MAX_SIGNAL_DATAGRAM_SIZE], 1, now).unwrap().encode().is_err());
+
+        let mut filter = SignalFilter::default();
+        assert!(filter.accept(&typing, now + 10));
+        let older = Signal::sign(&keypair, SignalKind::Typing, b"0", 1, now).unwrap();
+        assert!(!filter.accept(&older, now + 20), "乱序信号被丢弃");
+        assert!(!filter.accept(&typing, now + 20), "重复信号被丢弃");
+        // 不同类型的信号各自排序
+        let presence = Signal::sign(&keypair, SignalKind::Presence, b"online", 1, now).unwrap();
+        assert!(filter.accept(&presence, now + 20));
+
+        let stale = Signal::sign(&keypair, SignalKind::Typing, b"2", 9, now).unwrap();
+        assert!(!filter.accept(&stale, now + DEFAULT_SIGNAL_MAX_AGE_MS + 1), "过期信号被丢弃");
+
+        filter.forget(&keypair.did);
+        assert!(filter.accept(&older, now + 20));
+    }
+}
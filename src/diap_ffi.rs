@@ -0,0 +1,518 @@
+// DIAP Rust SDK - C ABI绑定
+// 供Python/Go/Node等语言通过FFI嵌入SDK：密钥对生成、DID发布、证明生成/验证、消息签名
+//
+// 约定：
+// - 所有函数返回 DiapStatus，结果通过 out 参数写出；失败时 diap_last_error() 给出错误信息
+// - DiapKeyPair / DiapClient 是不透明句柄，分别用 diap_keypair_free / diap_client_free 释放
+// - SDK分配的字符串用 diap_string_free 释放，字节缓冲用 diap_bytes_free 释放
+// - 传入的指针必须有效（长度与数据一致，字符串以NUL结尾且为UTF-8）；空指针返回 NullPointer
+// - panic不会越过FFI边界，被捕获后返回 Panic
+// 头文件由 cbindgen 生成：cbindgen --config cbindgen.toml --output include/diap.h src/diap_ffi.rs
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::ptr;
+
+use anyhow::Context;
+use libp2p::PeerId;
+
+use crate::did_builder::get_did_document_from_cid;
+use crate::error::DiapError;
+use crate::identity_manager::{AgentInfo, IdentityManager};
+use crate::ipfs_client::IpfsClient;
+use crate::key_manager::KeyPair;
+use crate::libp2p_identity::LibP2PIdentity;
+
+/// 调用结果
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiapStatus {
+    /// 成功
+    Ok = 0,
+
+    /// 必需的指针参数为空
+    NullPointer = 1,
+
+    /// 参数无效（长度不对、非UTF-8、JSON无法解析等）
+    InvalidArgument = 2,
+
+    /// 操作失败（网络、IPFS、证明等）
+    Failed = 3,
+
+    /// SDK内部panic
+    Panic = 4,
+}
+
+/// SDK分配的字节缓冲（用 diap_bytes_free 释放）
+#[repr(C)]
+pub struct DiapBytes {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl DiapBytes {
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let mut bytes = bytes.into_boxed_slice();
+        let len = bytes.len();
+        let data = bytes.as_mut_ptr();
+        std::mem::forget(bytes);
+        Self { data, len }
+    }
+
+    fn empty() -> Self {
+        Self { data: ptr::null_mut(), len: 0 }
+    }
+}
+
+/// 密钥对句柄
+pub struct DiapKeyPair {
+    keypair: KeyPair,
+}
+
+/// 客户端句柄：IPFS客户端、身份管理器和执行异步调用的tokio运行时
+pub struct DiapClient {
+    runtime: tokio::runtime::Runtime,
+    identity_manager: IdentityManager,
+}
+
+/// FFI调用错误（区分参数错误与操作失败）
+enum FfiError {
+    NullPointer(&'static str),
+    InvalidArgument(anyhow::Error),
+    Failed(anyhow::Error),
+}
+
+impl From<anyhow::Error> for FfiError {
+    fn from(error: anyhow::Error) -> Self {
+        FfiError::Failed(error)
+    }
+}
+
+impl From<DiapError> for FfiError {
+    fn from(error: DiapError) -> Self {
+        FfiError::Failed(error.into())
+    }
+}
+
+type FfiResult<T> = std::result::Result<T, FfiError>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// 执行FFI调用：捕获panic，记录错误信息并转换为状态码
+fn ffi_call(call: impl FnOnce() -> FfiResult<()>) -> DiapStatus {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => DiapStatus::Ok,
+        Ok(Err(FfiError::NullPointer(name))) => {
+            set_last_error(format!("参数 {} 为空指针", name));
+            DiapStatus::NullPointer
+        }
+        Ok(Err(FfiError::InvalidArgument(e))) => {
+            set_last_error(format!("{:#}", e));
+            DiapStatus::InvalidArgument
+        }
+        Ok(Err(FfiError::Failed(e))) => {
+            log::warn!("⚠️ FFI调用失败: {:#}", e);
+            set_last_error(format!("{:#}", e));
+            DiapStatus::Failed
+        }
+        Err(panic) => {
+            let message = panic.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "未知panic".to_string());
+            set_last_error(format!("SDK内部错误: {}", message));
+            DiapStatus::Panic
+        }
+    }
+}
+
+unsafe fn non_null<'a, T>(pointer: *const T, name: &'static str) -> FfiResult<&'a T> {
+    pointer.as_ref().ok_or(FfiError::NullPointer(name))
+}
+
+unsafe fn out_param<'a, T>(pointer: *mut T, name: &'static str) -> FfiResult<&'a mut T> {
+    pointer.as_mut().ok_or(FfiError::NullPointer(name))
+}
+
+unsafe fn str_arg<'a>(pointer: *const c_char, name: &'static str) -> FfiResult<&'a str> {
+    if pointer.is_null() {
+        return Err(FfiError::NullPointer(name));
+    }
+    CStr::from_ptr(pointer).to_str()
+        .map_err(|_| FfiError::InvalidArgument(anyhow::anyhow!("参数 {} 不是有效的UTF-8", name)))
+}
+
+unsafe fn optional_str_arg<'a>(pointer: *const c_char, name: &'static str) -> FfiResult<Option<&'a str>> {
+    if pointer.is_null() {
+        Ok(None)
+    } else {
+        str_arg(pointer, name).map(Some)
+    }
+}
+
+unsafe fn bytes_arg<'a>(pointer: *const u8, len: usize, name: &'static str) -> FfiResult<&'a [u8]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if pointer.is_null() {
+        return Err(FfiError::NullPointer(name));
+    }
+    Ok(std::slice::from_raw_parts(pointer, len))
+}
+
+fn into_c_string(value: String) -> FfiResult<*mut c_char> {
+    CString::new(value).map(CString::into_raw)
+        .map_err(|e| FfiError::Failed(anyhow::anyhow!("字符串包含NUL字节: {}", e)))
+}
+
+fn to_json_c_string<T: serde::Serialize>(value: &T) -> FfiResult<*mut c_char> {
+    into_c_string(serde_json::to_string(value).context("序列化结果失败")?)
+}
+
+// ============ 内存与错误 ============
+
+/// 当前线程上一次失败调用的错误信息（没有错误时为NULL）
+/// 返回的指针在本线程下一次调用SDK前有效，不要释放
+#[no_mangle]
+pub extern "C" fn diap_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// SDK版本（静态字符串，不要释放）
+#[no_mangle]
+pub extern "C" fn diap_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// 释放SDK返回的字符串
+#[no_mangle]
+pub unsafe extern "C" fn diap_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// 释放SDK返回的字节缓冲
+#[no_mangle]
+pub unsafe extern "C" fn diap_bytes_free(bytes: DiapBytes) {
+    if !bytes.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes.data, bytes.len)));
+    }
+}
+
+// ============ 密钥对 ============
+
+/// 生成新的Ed25519密钥对
+#[no_mangle]
+pub unsafe extern "C" fn diap_keypair_generate(out_keypair: *mut *mut DiapKeyPair) -> DiapStatus {
+    ffi_call(|| {
+        let out_keypair = out_param(out_keypair, "out_keypair")?;
+        let keypair = KeyPair::generate()?;
+        *out_keypair = Box::into_raw(Box::new(DiapKeyPair { keypair }));
+        Ok(())
+    })
+}
+
+/// 从32字节私钥恢复密钥对
+#[no_mangle]
+pub unsafe extern "C" fn diap_keypair_from_private_key(
+    private_key: *const u8,
+    private_key_len: usize,
+    out_keypair: *mut *mut DiapKeyPair,
+) -> DiapStatus {
+    ffi_call(|| {
+        let out_keypair = out_param(out_keypair, "out_keypair")?;
+        let private_key: [u8; 32] = bytes_arg(private_key, private_key_len, "private_key")?
+            .try_into()
+            .map_err(|_| FfiError::InvalidArgument(anyhow::anyhow!("私钥必须是32字节")))?;
+        let keypair = KeyPair::from_private_key(private_key)?;
+        *out_keypair = Box::into_raw(Box::new(DiapKeyPair { keypair }));
+        Ok(())
+    })
+}
+
+/// 释放密钥对
+#[no_mangle]
+pub unsafe extern "C" fn diap_keypair_free(keypair: *mut DiapKeyPair) {
+    if !keypair.is_null() {
+        drop(Box::from_raw(keypair));
+    }
+}
+
+/// 密钥对的DID（did:key，用 diap_string_free 释放）
+#[no_mangle]
+pub unsafe extern "C" fn diap_keypair_did(keypair: *const DiapKeyPair, out_did: *mut *mut c_char) -> DiapStatus {
+    ffi_call(|| {
+        let keypair = non_null(keypair, "keypair")?;
+        *out_param(out_did, "out_did")? = into_c_string(keypair.keypair.did.clone())?;
+        Ok(())
+    })
+}
+
+/// 写出32字节公钥
+#[no_mangle]
+pub unsafe extern "C" fn diap_keypair_public_key(keypair: *const DiapKeyPair, out_public_key: *mut [u8; 32]) -> DiapStatus {
+    ffi_call(|| {
+        let keypair = non_null(keypair, "keypair")?;
+        *out_param(out_public_key, "out_public_key")? = keypair.keypair.public_key;
+        Ok(())
+    })
+}
+
+/// 写出32字节私钥（用于调用方自行持久化）
+#[no_mangle]
+pub unsafe extern "C" fn diap_keypair_private_key(keypair: *const DiapKeyPair, out_private_key: *mut [u8; 32]) -> DiapStatus {
+    ffi_call(|| {
+        let keypair = non_null(keypair, "keypair")?;
+        *out_param(out_private_key, "out_private_key")? = keypair.keypair.private_key;
+        Ok(())
+    })
+}
+
+// ============ 消息签名 ============
+
+/// 用密钥对签名数据，写出64字节签名（用 diap_bytes_free 释放）
+#[no_mangle]
+pub unsafe extern "C" fn diap_sign(
+    keypair: *const DiapKeyPair,
+    data: *const u8,
+    data_len: usize,
+    out_signature: *mut DiapBytes,
+) -> DiapStatus {
+    ffi_call(|| {
+        let keypair = non_null(keypair, "keypair")?;
+        let out_signature = out_param(out_signature, "out_signature")?;
+        *out_signature = DiapBytes::empty();
+        let data = bytes_arg(data, data_len, "data")?;
+        *out_signature = DiapBytes::from_vec(keypair.keypair.sign(data)?);
+        Ok(())
+    })
+}
+
+/// 用did:key中的公钥验证签名，结果写入 out_valid
+#[no_mangle]
+pub unsafe extern "C" fn diap_verify_signature(
+    did: *const c_char,
+    data: *const u8,
+    data_len: usize,
+    signature: *const u8,
+    signature_len: usize,
+    out_valid: *mut bool,
+) -> DiapStatus {
+    ffi_call(|| {
+        let did = str_arg(did, "did")?;
+        let data = bytes_arg(data, data_len, "data")?;
+        let signature = bytes_arg(signature, signature_len, "signature")?;
+        let out_valid = out_param(out_valid, "out_valid")?;
+        *out_valid = crate::did_key::verify_with_did_key(did, data, signature)
+            .map_err(FfiError::InvalidArgument)?;
+        Ok(())
+    })
+}
+
+// ============ 客户端：DID发布与证明 ============
+
+/// 创建客户端
+/// api_url/gateway_url 均非空时使用远程IPFS节点，否则只用公共网关
+#[no_mangle]
+pub unsafe extern "C" fn diap_client_new(
+    api_url: *const c_char,
+    gateway_url: *const c_char,
+    timeout_seconds: u64,
+    out_client: *mut *mut DiapClient,
+) -> DiapStatus {
+    ffi_call(|| {
+        let out_client = out_param(out_client, "out_client")?;
+        let api_url = optional_str_arg(api_url, "api_url")?;
+        let gateway_url = optional_str_arg(gateway_url, "gateway_url")?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .context("创建tokio运行时失败")?;
+        let ipfs_client = IpfsClient::new(
+            api_url.map(str::to_string),
+            gateway_url.map(str::to_string),
+            None,
+            None,
+            timeout_seconds,
+        );
+        let client = DiapClient { runtime, identity_manager: IdentityManager::new(ipfs_client) };
+        *out_client = Box::into_raw(Box::new(client));
+        Ok(())
+    })
+}
+
+/// 释放客户端（会等待运行时中的任务结束）
+#[no_mangle]
+pub unsafe extern "C" fn diap_client_free(client: *mut DiapClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// 发布DID文档到IPFS
+/// agent_info_json 为 AgentInfo 的JSON（为NULL时以DID为名称、不含服务端点）；
+/// 结果为 IdentityRegistration 的JSON（用 diap_string_free 释放）
+#[no_mangle]
+pub unsafe extern "C" fn diap_publish_did(
+    client: *const DiapClient,
+    keypair: *const DiapKeyPair,
+    agent_info_json: *const c_char,
+    out_registration_json: *mut *mut c_char,
+) -> DiapStatus {
+    ffi_call(|| {
+        let client = non_null(client, "client")?;
+        let keypair = &non_null(keypair, "keypair")?.keypair;
+        let out_registration_json = out_param(out_registration_json, "out_registration_json")?;
+        let agent_info = match optional_str_arg(agent_info_json, "agent_info_json")? {
+            Some(json) => serde_json::from_str::<AgentInfo>(json)
+                .context("解析AgentInfo失败")
+                .map_err(FfiError::InvalidArgument)?,
+            None => AgentInfo { name: keypair.did.clone(), services: Vec::new(), description: None, tags: None },
+        };
+        let peer_id: PeerId = *LibP2PIdentity::from_did_keypair(keypair)?.peer_id();
+
+        let registration = client.runtime.block_on(
+            client.identity_manager.register_identity(&agent_info, keypair, &peer_id)
+        )?;
+        *out_registration_json = to_json_c_string(&registration)?;
+        Ok(())
+    })
+}
+
+/// 为已发布的DID文档生成DID-CID绑定证明（用 diap_bytes_free 释放）
+#[no_mangle]
+pub unsafe extern "C" fn diap_generate_proof(
+    client: *const DiapClient,
+    keypair: *const DiapKeyPair,
+    cid: *const c_char,
+    nonce: *const u8,
+    nonce_len: usize,
+    out_proof: *mut DiapBytes,
+) -> DiapStatus {
+    ffi_call(|| {
+        let client = non_null(client, "client")?;
+        let keypair = &non_null(keypair, "keypair")?.keypair;
+        let cid = str_arg(cid, "cid")?;
+        let nonce = bytes_arg(nonce, nonce_len, "nonce")?;
+        let out_proof = out_param(out_proof, "out_proof")?;
+        *out_proof = DiapBytes::empty();
+
+        let identity_manager = &client.identity_manager;
+        let did_document = client.runtime.block_on(get_did_document_from_cid(identity_manager.ipfs_client(), cid))?;
+        let proof = identity_manager.generate_binding_proof(keypair, &did_document, cid, nonce)?;
+        *out_proof = DiapBytes::from_vec(proof);
+        Ok(())
+    })
+}
+
+/// 通过CID和证明验证身份
+/// 结果为 IdentityVerification 的JSON（用 diap_string_free 释放），out_verified 为是否通过
+#[no_mangle]
+pub unsafe extern "C" fn diap_verify_proof(
+    client: *const DiapClient,
+    cid: *const c_char,
+    proof: *const u8,
+    proof_len: usize,
+    nonce: *const u8,
+    nonce_len: usize,
+    out_verified: *mut bool,
+    out_verification_json: *mut *mut c_char,
+) -> DiapStatus {
+    ffi_call(|| {
+        let client = non_null(client, "client")?;
+        let cid = str_arg(cid, "cid")?;
+        let proof = bytes_arg(proof, proof_len, "proof")?;
+        let nonce = bytes_arg(nonce, nonce_len, "nonce")?;
+        let out_verified = out_param(out_verified, "out_verified")?;
+        *out_verified = false;
+
+        let verification = client.runtime.block_on(
+            client.identity_manager.verify_identity_with_zkp(cid, proof, nonce)
+        )?;
+        *out_verified = verification.zkp_verified;
+        if !out_verification_json.is_null() {
+            *out_verification_json = to_json_c_string(&verification)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keypair_sign_and_verify_over_c_abi() {
+        unsafe {
+            let mut keypair = ptr::null_mut();
+            assert_eq!(diap_keypair_generate(&mut keypair), DiapStatus::Ok);
+
+            let mut did = ptr::null_mut();
+            assert_eq!(diap_keypair_did(keypair, &mut did), DiapStatus::Ok);
+            let did_str = CStr::from_ptr(did).to_str().unwrap().to_string();
+            assert!(did_str.starts_with("did:key:z"));
+
+            // 私钥导出后可恢复出同一身份
+            let mut private_key = [0u8; 32];
+            assert_eq!(diap_keypair_private_key(keypair, &mut private_key), DiapStatus::Ok);
+            let mut restored = ptr::null_mut();
+            assert_eq!(diap_keypair_from_private_key(private_key.as_ptr(), 32, &mut restored), DiapStatus::Ok);
+            assert_eq!((*restored).keypair.did, did_str);
+
+            let message = b"hello from C";
+            let mut signature = DiapBytes::empty();
+            assert_eq!(diap_sign(keypair, message.as_ptr(), message.len(), &mut signature), DiapStatus::Ok);
+            assert_eq!(signature.len, 64);
+
+            let mut valid = false;
+            let status = diap_verify_signature(did, message.as_ptr(), message.len(), signature.data, signature.len, &mut valid);
+            assert_eq!(status, DiapStatus::Ok);
+            assert!(valid);
+            let status = diap_verify_signature(did, b"tampered".as_ptr(), 8, signature.data, signature.len, &mut valid);
+            assert_eq!(status, DiapStatus::Ok);
+            assert!(!valid);
+
+            diap_bytes_free(signature);
+            diap_string_free(did);
+            diap_keypair_free(restored);
+            diap_keypair_free(keypair);
+        }
+    }
+
+    #[test]
+    fn test_errors_are_reported_through_last_error() {
+        unsafe {
+            assert!(diap_last_error().is_null());
+
+            assert_eq!(diap_keypair_generate(ptr::null_mut()), DiapStatus::NullPointer);
+            assert!(CStr::from_ptr(diap_last_error()).to_str().unwrap().contains("out_keypair"));
+
+            let mut keypair = ptr::null_mut();
+            let short_key = [1u8; 16];
+            let status = diap_keypair_from_private_key(short_key.as_ptr(), short_key.len(), &mut keypair);
+            assert_eq!(status, DiapStatus::InvalidArgument);
+            assert!(keypair.is_null());
+
+            let mut valid = true;
+            let status = diap_verify_signature(c"did:web:example.com".as_ptr(), ptr::null(), 0, ptr::null(), 0, &mut valid);
+            assert_eq!(status, DiapStatus::InvalidArgument);
+            assert!(!diap_last_error().is_null());
+
+            // 成功调用清除上一次的错误
+            assert_eq!(diap_keypair_generate(&mut keypair), DiapStatus::Ok);
+            assert!(diap_last_error().is_null());
+            diap_keypair_free(keypair);
+        }
+    }
+}
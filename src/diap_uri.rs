@@ -0,0 +1,215 @@
+// DIAP Rust SDK - diap:// URI 与二维码
+// 将DID（附CID和多地址）、CID和邀请编码为 diap:// URI，并可生成二维码，便于移动端/桌面端在带外交换智能体身份
+
+use anyhow::{Context, Result};
+use reqwest::Url;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::agent_invite::AgentInvite;
+
+/// URI scheme
+pub const DIAP_URI_SCHEME: &str = "diap";
+
+/// diap:// URI
+///
+/// * `diap://did/<did>?cid=<cid>&addr=<multiaddr>&addr=...`
+/// * `diap://cid/<cid>`
+/// * `diap://invite/<邀请令牌>`
+#[derive(Debug, Clone)]
+pub enum DiapUri {
+    /// 智能体身份
+    Did {
+        /// DID
+        did: String,
+
+        /// DID文档CID
+        cid: Option<String>,
+
+        /// 多地址
+        addresses: Vec<String>,
+    },
+
+    /// 内容CID
+    Cid(String),
+
+    /// 邀请
+    Invite(AgentInvite),
+}
+
+impl DiapUri {
+    /// 只包含DID的身份URI
+    pub fn did(did: &str) -> Self {
+        DiapUri::Did {
+            did: did.to_string(),
+            cid: None,
+            addresses: Vec::new(),
+        }
+    }
+
+    /// 编码为URI字符串
+    pub fn to_uri(&self) -> Result<String> {
+        let mut url = match self {
+            DiapUri::Did { did, cid, addresses } => {
+                let mut url = Self::base("did", did)?;
+                {
+                    let mut query = url.query_pairs_mut();
+                    if let Some(cid) = cid {
+                        query.append_pair("cid", cid);
+                    }
+                    for address in addresses {
+                        query.append_pair("addr", address);
+                    }
+                }
+                url
+            }
+            DiapUri::Cid(cid) => Self::base("cid", cid)?,
+            DiapUri::Invite(invite) => Self::base("invite", &invite.to_token()?)?,
+        };
+
+        // 没有查询参数时去掉多余的 "?"
+        if url.query() == Some("") {
+            url.set_query(None);
+        }
+        Ok(url.to_string())
+    }
+
+    /// 解析URI字符串（邀请不校验签名，请调用AgentInvite::validate）
+    pub fn parse(uri: &str) -> Result<Self> {
+        let url = Url::parse(uri.trim()).with_context(|| format!("无效的URI: {}", uri))?;
+        if url.scheme() != DIAP_URI_SCHEME {
+            anyhow::bail!("不支持的URI scheme: {}", url.scheme());
+        }
+
+        let kind = url.host_str().unwrap_or_default();
+        let value = url.path().trim_start_matches('/');
+        if value.is_empty() {
+            anyhow::bail!("URI缺少内容: {}", uri);
+        }
+
+        match kind {
+            "did" => {
+                if !value.starts_with("did:") {
+                    anyhow::bail!("无效的DID: {}", value);
+                }
+                let mut cid = None;
+                let mut addresses = Vec::new();
+                for (key, val) in url.query_pairs() {
+                    match key.as_ref() {
+                        "cid" => cid = Some(val.to_string()),
+                        "addr" => addresses.push(val.to_string()),
+                        _ => log::debug!("忽略未知URI参数: {}", key),
+                    }
+                }
+                Ok(DiapUri::Did { did: value.to_string(), cid, addresses })
+            }
+            "cid" => Ok(DiapUri::Cid(value.to_string())),
+            "invite" => Ok(DiapUri::Invite(AgentInvite::from_token(value)?)),
+            other => anyhow::bail!("未知的URI类型: {}", other),
+        }
+    }
+
+    fn base(kind: &str, value: &str) -> Result<Url> {
+        Url::parse(&format!("{}://{}/{}", DIAP_URI_SCHEME, kind, value))
+            .with_context(|| format!("无法编码URI: {}", value))
+    }
+
+    /// 生成SVG格式的二维码
+    #[cfg(feature = "qr")]
+    pub fn to_qr_svg(&self, min_size: u32) -> Result<String> {
+        use qrcode::render::svg;
+
+        let code = qrcode::QrCode::new(self.to_uri()?.as_bytes())
+            .context("生成二维码失败")?;
+        Ok(code.render::<svg::Color>().min_dimensions(min_size, min_size).build())
+    }
+
+    /// 生成可在终端显示的二维码（Unicode半块字符）
+    #[cfg(feature = "qr")]
+    pub fn to_qr_terminal(&self) -> Result<String> {
+        use qrcode::render::unicode;
+
+        let code = qrcode::QrCode::new(self.to_uri()?.as_bytes())
+            .context("生成二维码失败")?;
+        Ok(code.render::<unicode::Dense1x2>().build())
+    }
+}
+
+impl fmt::Display for DiapUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let uri = self.to_uri().map_err(|_| fmt::Error)?;
+        f.write_str(&uri)
+    }
+}
+
+impl FromStr for DiapUri {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl From<AgentInvite> for DiapUri {
+    fn from(invite: AgentInvite) -> Self {
+        DiapUri::Invite(invite)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_invite::InviteBootstrap;
+    use crate::key_manager::KeyPair;
+
+    #[test]
+    fn test_did_uri_roundtrip() {
+        let uri = DiapUri::Did {
+            did: "did:key:z6MkTest".to_string(),
+            cid: Some("bafydoc".to_string()),
+            addresses: vec![
+                "/ip4/10.0.0.1/tcp/4001".to_string(),
+                "/ip4/10.0.0.1/udp/4001/quic-v1".to_string(),
+            ],
+        };
+
+        let encoded = uri.to_uri().unwrap();
+        assert!(encoded.starts_with("diap://did/did:key:z6MkTest?cid=bafydoc&addr="));
+
+        match DiapUri::parse(&encoded).unwrap() {
+            DiapUri::Did { did, cid, addresses } => {
+                assert_eq!(did, "did:key:z6MkTest");
+                assert_eq!(cid.as_deref(), Some("bafydoc"));
+                assert_eq!(addresses.len(), 2);
+                assert_eq!(addresses[1], "/ip4/10.0.0.1/udp/4001/quic-v1");
+            }
+            other => panic!("unexpected uri: {:?}", other),
+        }
+
+        assert_eq!(DiapUri::did("did:key:z6MkTest").to_string(), "diap://did/did:key:z6MkTest");
+        assert_eq!(DiapUri::Cid("bafy".to_string()).to_string(), "diap://cid/bafy");
+        assert!(DiapUri::parse("https://did/did:key:z").is_err());
+        assert!(DiapUri::parse("diap://did/not-a-did").is_err());
+    }
+
+    #[test]
+    fn test_invite_uri_roundtrip() {
+        let inviter = KeyPair::generate().unwrap();
+        let invite = AgentInvite::new(&inviter, "bafy".to_string(), InviteBootstrap::default(), 1_000, 60).unwrap();
+
+        let uri: DiapUri = invite.into();
+        let parsed: DiapUri = uri.to_string().parse().unwrap();
+        match parsed {
+            DiapUri::Invite(invite) => assert!(invite.validate(1_010).is_ok()),
+            other => panic!("unexpected uri: {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn test_qr_rendering() {
+        let uri = DiapUri::did("did:key:z6MkTest");
+        assert!(uri.to_qr_svg(200).unwrap().contains("<svg"));
+        assert!(!uri.to_qr_terminal().unwrap().is_empty());
+    }
+}
@@ -3,9 +3,11 @@
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use crate::error::{DiapError, DiapResult};
 use crate::key_manager::KeyPair;
 use crate::ipfs_client::{IpfsClient, IpfsUploadResult};
 use crate::encrypted_peer_id::{EncryptedPeerID, encrypt_peer_id};
+use crate::did_commitment::{CidCommitment, PendingPublication};
 use libp2p::PeerId;
 use ed25519_dalek::SigningKey;
 use base64::{Engine as _, engine::general_purpose};
@@ -26,12 +28,44 @@ pub struct DIDDocument {
     /// 认证方法
     pub authentication: Vec<String>,
     
+    /// 密钥协商方法（X25519，用于端到端加密）
+    #[serde(rename = "keyAgreement", skip_serializing_if = "Vec::is_empty", default)]
+    pub key_agreement: Vec<VerificationMethod>,
+    
     /// 服务端点（包含加密的PeerID）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub service: Option<Vec<Service>>,
     
     /// 创建时间
     pub created: String,
+    
+    /// 密钥轮换证明（由上一个密钥签名，指向上一个DID）
+    #[serde(rename = "keyRotation", skip_serializing_if = "Option::is_none", default)]
+    pub key_rotation: Option<KeyRotationProof>,
+    
+    /// 上一版本DID文档的CID（首个版本为空）
+    #[serde(rename = "previousVersionCid", skip_serializing_if = "Option::is_none", default)]
+    pub previous_version_cid: Option<String>,
+}
+
+/// 密钥轮换证明
+/// 由旧密钥对新DID签名，证明新旧身份属于同一智能体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRotationProof {
+    /// 上一个DID（did:key格式）
+    #[serde(rename = "previousDid")]
+    pub previous_did: String,
+    
+    /// 新DID（did:key格式）
+    #[serde(rename = "newDid")]
+    pub new_did: String,
+    
+    /// 轮换时间
+    #[serde(rename = "rotatedAt")]
+    pub rotated_at: String,
+    
+    /// 旧密钥的签名（base64）
+    pub signature: String,
 }
 
 /// 验证方法
@@ -68,6 +102,32 @@ pub struct Service {
     pub network_addresses: Option<Vec<String>>,
 }
 
+/// DID文档更新内容
+#[derive(Debug, Clone, Default)]
+pub struct DIDDocumentUpdate {
+    /// 新增（或按ID替换）的服务端点
+    pub add_services: Vec<Service>,
+    
+    /// 要移除的服务端点ID
+    pub remove_service_ids: Vec<String>,
+    
+    /// 新增（或按ID替换）的验证方法
+    pub add_verification_methods: Vec<VerificationMethod>,
+    
+    /// 要移除的验证方法ID（主密钥#key-1不可移除）
+    pub remove_verification_method_ids: Vec<String>,
+}
+
+/// DID文档的一个历史版本
+#[derive(Debug, Clone)]
+pub struct DIDVersion {
+    /// 该版本的CID
+    pub cid: String,
+    
+    /// 该版本的DID文档
+    pub document: DIDDocument,
+}
+
 /// DID构建器
 pub struct DIDBuilder {
     /// 服务端点列表
@@ -141,13 +201,13 @@ impl DIDBuilder {
         libp2p_peer_id: &PeerId,
         pubsub_topics: Vec<String>,
         network_addresses: Vec<String>,
-    ) -> Result<DIDPublishResult> {
+    ) -> DiapResult<DIDPublishResult> {
         log::info!("🚀 开始DID发布流程（包含PubSub信息）");
         
         // 步骤1: 加密PeerID
         log::info!("步骤1: 加密libp2p PeerID");
         let signing_key = SigningKey::from_bytes(&keypair.private_key);
-        let encrypted_peer_id = encrypt_peer_id(&signing_key, libp2p_peer_id)?;
+        let encrypted_peer_id = encrypt_peer_id(&signing_key, libp2p_peer_id).map_err(DiapError::from_did)?;
         log::info!("✓ PeerID已加密");
         
         // 步骤2: 构建包含PubSub信息的DID文档
@@ -157,13 +217,13 @@ impl DIDBuilder {
             &encrypted_peer_id, 
             pubsub_topics, 
             network_addresses
-        )?;
+        ).map_err(DiapError::from_did)?;
         log::info!("✓ DID文档构建完成");
         log::info!("  DID: {}", did_doc.id);
         
         // 步骤3: 上传到IPFS
         log::info!("步骤3: 上传DID文档到IPFS");
-        let upload_result = self.upload_did_document(&did_doc).await?;
+        let upload_result = self.upload_did_document(&did_doc).await.map_err(DiapError::from_did)?;
         log::info!("✓ 上传完成");
         log::info!("  CID: {}", upload_result.cid);
         
@@ -186,24 +246,24 @@ impl DIDBuilder {
         &self,
         keypair: &KeyPair,
         libp2p_peer_id: &PeerId,
-    ) -> Result<DIDPublishResult> {
+    ) -> DiapResult<DIDPublishResult> {
         log::info!("🚀 开始DID发布流程（简化版）");
         
         // 步骤1: 加密PeerID
         log::info!("步骤1: 加密libp2p PeerID");
         let signing_key = SigningKey::from_bytes(&keypair.private_key);
-        let encrypted_peer_id = encrypt_peer_id(&signing_key, libp2p_peer_id)?;
+        let encrypted_peer_id = encrypt_peer_id(&signing_key, libp2p_peer_id).map_err(DiapError::from_did)?;
         log::info!("✓ PeerID已加密");
         
         // 步骤2: 构建DID文档
         log::info!("步骤2: 构建DID文档");
-        let did_doc = self.build_did_document(keypair, &encrypted_peer_id)?;
+        let did_doc = self.build_did_document(keypair, &encrypted_peer_id).map_err(DiapError::from_did)?;
         log::info!("✓ DID文档构建完成");
         log::info!("  DID: {}", did_doc.id);
         
         // 步骤3: 上传到IPFS（仅一次）
         log::info!("步骤3: 上传DID文档到IPFS");
-        let upload_result = self.upload_did_document(&did_doc).await?;
+        let upload_result = self.upload_did_document(&did_doc).await.map_err(DiapError::from_did)?;
         log::info!("✓ 上传完成");
         log::info!("  CID: {}", upload_result.cid);
         
@@ -220,6 +280,168 @@ impl DIDBuilder {
         })
     }
     
+    /// 两阶段发布第一步：构建DID文档并在本地计算CID，签署预提交声明（不上传）
+    /// 声明可以先广播，绑定证明也可以直接针对预先计算的CID生成
+    pub fn prepare_publish(
+        &self,
+        keypair: &KeyPair,
+        libp2p_peer_id: &PeerId,
+    ) -> DiapResult<PendingPublication> {
+        log::info!("📝 预提交DID文档CID");
+        
+        let signing_key = SigningKey::from_bytes(&keypair.private_key);
+        let encrypted_peer_id = encrypt_peer_id(&signing_key, libp2p_peer_id).map_err(DiapError::from_did)?;
+        let did_doc = self.build_did_document(keypair, &encrypted_peer_id).map_err(DiapError::from_did)?;
+        
+        let cid = crate::cid_compute::compute_cid(&did_doc).map_err(DiapError::from_did)?;
+        let committed_at = chrono::Utc::now().timestamp() as u64;
+        let commitment = CidCommitment::sign(keypair, &cid, committed_at).map_err(DiapError::from_did)?;
+        log::info!("✓ 预提交CID: {}", cid);
+        
+        Ok(PendingPublication {
+            did: keypair.did.clone(),
+            cid,
+            did_document: did_doc,
+            encrypted_peer_id,
+            commitment,
+        })
+    }
+    
+    /// 两阶段发布第二步：上传预提交的文档，存储端返回的CID必须与预提交一致
+    pub async fn complete_publish(&self, pending: &PendingPublication) -> DiapResult<DIDPublishResult> {
+        log::info!("📤 上传预提交的DID文档: {}", pending.cid);
+        
+        let upload_result = self.upload_did_document(&pending.did_document).await.map_err(DiapError::from_did)?;
+        if upload_result.cid != pending.cid {
+            return Err(DiapError::did(format!(
+                "上传后的CID与预提交不一致: 预提交 {}，实际 {}（存储端可能使用了非默认的上传参数）",
+                pending.cid, upload_result.cid
+            )));
+        }
+        log::info!("✅ 两阶段发布完成: {}", pending.cid);
+        
+        Ok(DIDPublishResult {
+            did: pending.did.clone(),
+            cid: upload_result.cid,
+            did_document: pending.did_document.clone(),
+            encrypted_peer_id: pending.encrypted_peer_id.clone(),
+        })
+    }
+    
+    /// 创建并发布轮换后的DID文档
+    /// 新文档携带由旧密钥签名的轮换证明，旧DID文档仍保留在IPFS上
+    pub async fn create_and_publish_rotated(
+        &self,
+        new_keypair: &KeyPair,
+        previous_keypair: &KeyPair,
+        libp2p_peer_id: &PeerId,
+    ) -> DiapResult<DIDPublishResult> {
+        log::info!("🔄 发布轮换后的DID文档");
+        log::info!("  旧DID: {}", previous_keypair.did);
+        log::info!("  新DID: {}", new_keypair.did);
+        
+        let signing_key = SigningKey::from_bytes(&new_keypair.private_key);
+        let encrypted_peer_id = encrypt_peer_id(&signing_key, libp2p_peer_id).map_err(DiapError::from_did)?;
+        
+        let mut did_doc = self.build_did_document(new_keypair, &encrypted_peer_id).map_err(DiapError::from_did)?;
+        did_doc.key_rotation = Some(KeyRotationProof::sign(previous_keypair, &new_keypair.did).map_err(DiapError::from_did)?);
+        
+        let upload_result = self.upload_did_document(&did_doc).await.map_err(DiapError::from_did)?;
+        log::info!("✅ 轮换DID发布成功, CID: {}", upload_result.cid);
+        
+        Ok(DIDPublishResult {
+            did: new_keypair.did.clone(),
+            cid: upload_result.cid,
+            did_document: did_doc,
+            encrypted_peer_id,
+        })
+    }
+    
+    /// 更新并发布DID文档
+    /// 从已有CID获取文档，应用服务/验证方法变更，并在新文档中记录上一版本CID
+    pub async fn update_and_publish(
+        &self,
+        keypair: &KeyPair,
+        libp2p_peer_id: &PeerId,
+        previous_cid: &str,
+        update: &DIDDocumentUpdate,
+    ) -> DiapResult<DIDPublishResult> {
+        log::info!("📝 更新DID文档");
+        log::info!("  上一版本CID: {}", previous_cid);
+        
+        let previous = get_did_document_from_cid(&self.ipfs_client, previous_cid).await?;
+        if previous.id != keypair.did {
+            return Err(DiapError::did(format!("DID不匹配: 文档为 {}，密钥为 {}", previous.id, keypair.did)));
+        }
+        
+        // 重新加密PeerID（PeerID可能已变化，且每个版本使用新的nonce）
+        let signing_key = SigningKey::from_bytes(&keypair.private_key);
+        let encrypted_peer_id = encrypt_peer_id(&signing_key, libp2p_peer_id).map_err(DiapError::from_did)?;
+        
+        let mut did_doc = Self::apply_update(previous, update).map_err(DiapError::from_did)?;
+        Self::refresh_libp2p_service(&mut did_doc, &encrypted_peer_id);
+        did_doc.previous_version_cid = Some(previous_cid.to_string());
+        
+        let upload_result = self.upload_did_document(&did_doc).await.map_err(DiapError::from_did)?;
+        log::info!("✅ DID文档更新成功, 新CID: {}", upload_result.cid);
+        
+        Ok(DIDPublishResult {
+            did: keypair.did.clone(),
+            cid: upload_result.cid,
+            did_document: did_doc,
+            encrypted_peer_id,
+        })
+    }
+    
+    /// 对DID文档应用更新内容
+    fn apply_update(mut did_doc: DIDDocument, update: &DIDDocumentUpdate) -> Result<DIDDocument> {
+        let primary_key_id = format!("{}#key-1", did_doc.id);
+        if update.remove_verification_method_ids.contains(&primary_key_id) {
+            anyhow::bail!("不能移除主验证方法: {}", primary_key_id);
+        }
+        
+        // 服务端点
+        let mut services = did_doc.service.take().unwrap_or_default();
+        services.retain(|svc| {
+            !update.remove_service_ids.contains(&svc.id)
+                && !update.add_services.iter().any(|new| new.id == svc.id)
+        });
+        services.extend(update.add_services.iter().cloned());
+        did_doc.service = if services.is_empty() { None } else { Some(services) };
+        
+        // 验证方法
+        did_doc.verification_method.retain(|vm| {
+            !update.remove_verification_method_ids.contains(&vm.id)
+                && !update.add_verification_methods.iter().any(|new| new.id == vm.id)
+        });
+        did_doc.authentication.retain(|id| !update.remove_verification_method_ids.contains(id));
+        for vm in &update.add_verification_methods {
+            if !did_doc.authentication.contains(&vm.id) {
+                did_doc.authentication.push(vm.id.clone());
+            }
+            did_doc.verification_method.push(vm.clone());
+        }
+        
+        did_doc.key_rotation = None;
+        did_doc.created = chrono::Utc::now().to_rfc3339();
+        
+        Ok(did_doc)
+    }
+    
+    /// 用新加密的PeerID替换libp2p服务端点中的密文
+    fn refresh_libp2p_service(did_doc: &mut DIDDocument, encrypted_peer_id: &EncryptedPeerID) {
+        let Some(services) = did_doc.service.as_mut() else { return };
+        
+        for svc in services.iter_mut().filter(|svc| svc.id.ends_with("#libp2p")) {
+            if let Some(endpoint) = svc.service_endpoint.as_object_mut() {
+                endpoint.insert("ciphertext".to_string(), general_purpose::STANDARD.encode(&encrypted_peer_id.ciphertext).into());
+                endpoint.insert("nonce".to_string(), general_purpose::STANDARD.encode(&encrypted_peer_id.nonce).into());
+                endpoint.insert("signature".to_string(), general_purpose::STANDARD.encode(&encrypted_peer_id.signature).into());
+                endpoint.insert("method".to_string(), encrypted_peer_id.method.clone().into());
+            }
+        }
+    }
+    
     /// 构建DID文档
     fn build_did_document(
         &self,
@@ -254,15 +476,15 @@ impl DIDBuilder {
         services.insert(0, libp2p_service);
         
         Ok(DIDDocument {
-            context: vec![
-                "https://www.w3.org/ns/did/v1".to_string(),
-                "https://w3id.org/security/suites/ed25519-2020/v1".to_string(),
-            ],
+            context: crate::constants::network_params().did_contexts.clone(),
             id: keypair.did.clone(),
             verification_method: vec![verification_method],
             authentication: vec![format!("{}#key-1", keypair.did)],
+            key_agreement: vec![crate::e2e_encryption::key_agreement_method(keypair)],
             service: if services.is_empty() { None } else { Some(services) },
             created: chrono::Utc::now().to_rfc3339(),
+            key_rotation: None,
+            previous_version_cid: None,
         })
     }
     
@@ -303,127 +525,135 @@ impl DIDBuilder {
         services.insert(0, libp2p_service);
         
         Ok(DIDDocument {
-            context: vec![
-                "https://www.w3.org/ns/did/v1".to_string(),
-                "https://w3id.org/security/suites/ed25519-2020/v1".to_string(),
-            ],
+            context: crate::constants::network_params().did_contexts.clone(),
             id: keypair.did.clone(),
             verification_method: vec![verification_method],
             authentication: vec![format!("{}#key-1", keypair.did)],
+            key_agreement: vec![crate::e2e_encryption::key_agreement_method(keypair)],
             service: if services.is_empty() { None } else { Some(services) },
             created: chrono::Utc::now().to_rfc3339(),
+            key_rotation: None,
+            previous_version_cid: None,
         })
     }
     
     /// 上传DID文档到IPFS
     async fn upload_did_document(&self, did_doc: &DIDDocument) -> Result<IpfsUploadResult> {
-        let json = serde_json::to_string_pretty(did_doc)
-            .context("序列化DID文档失败")?;
+        let bytes = crate::cid_compute::document_bytes(did_doc)?;
+        let json = String::from_utf8(bytes).context("DID文档不是有效的UTF-8")?;
         
-        self.ipfs_client
+        let result = self.ipfs_client
             .upload(&json, "did.json")
             .await
-            .context("上传DID文档到IPFS失败")
+            .context("上传DID文档到IPFS失败")?;
+        
+        // 上传前即可得知CID；不一致说明存储端使用了非默认的上传参数
+        if !crate::cid_compute::document_matches_cid(did_doc, &result.cid)? {
+            log::warn!("⚠️  存储端返回的CID与本地计算结果不一致: {}", result.cid);
+        }
+        Ok(result)
+    }
+}
+
+impl KeyRotationProof {
+    /// 使用旧密钥对新DID签名，生成轮换证明
+    pub fn sign(previous: &KeyPair, new_did: &str) -> Result<Self> {
+        let rotated_at = chrono::Utc::now().to_rfc3339();
+        let data = Self::signing_data(&previous.did, new_did, &rotated_at);
+        let signature = previous.sign(data.as_bytes())?;
+        
+        Ok(Self {
+            previous_did: previous.did.clone(),
+            new_did: new_did.to_string(),
+            rotated_at,
+            signature: general_purpose::STANDARD.encode(signature),
+        })
+    }
+    
+    /// 验证轮换证明（公钥从previous_did中解析，无需访问IPFS）
+    pub fn verify(&self) -> Result<bool> {
+        let sig_bytes = general_purpose::STANDARD.decode(&self.signature)
+            .context("解码轮换签名失败")?;
+        let data = Self::signing_data(&self.previous_did, &self.new_did, &self.rotated_at);
+        KeyPair::verify_with_did_key(&self.previous_did, data.as_bytes(), &sig_bytes)
+    }
+    
+    fn signing_data(previous_did: &str, new_did: &str, rotated_at: &str) -> String {
+        format!("DIAP_KEY_ROTATION:{}:{}:{}", previous_did, new_did, rotated_at)
     }
 }
 
 /// 从IPFS CID获取DID文档
+#[tracing::instrument(name = "diap.did_fetch", skip(ipfs_client), err)]
 pub async fn get_did_document_from_cid(
     ipfs_client: &IpfsClient,
     cid: &str,
-) -> Result<DIDDocument> {
-    log::info!("从IPFS获取DID文档: {}", cid);
-    
-    let content = ipfs_client.get(cid).await
-        .context("从IPFS获取DID文档失败")?;
+) -> DiapResult<DIDDocument> {
+    let content = ipfs_client.get(cid).await?;
     
     let did_doc: DIDDocument = serde_json::from_str(&content)
-        .context("解析DID文档失败")?;
+        .context("解析DID文档失败")
+        .map_err(DiapError::from_did)?;
     
-    log::info!("✓ DID文档获取成功: {}", did_doc.id);
+    tracing::debug!(did = %did_doc.id, "✓ DID文档获取成功");
     
     Ok(did_doc)
 }
 
-/// 验证DID文档的完整性（改进版：支持多种哈希算法）
-/// 验证DID文档的哈希是否与CID的multihash部分匹配
-pub fn verify_did_document_integrity(
-    did_doc: &DIDDocument,
-    expected_cid: &str,
-) -> Result<bool> {
-    use sha2::{Sha256, Sha512, Digest};
-    use blake2::{Blake2b512, Blake2s256};
-    use cid::Cid;
-    use std::str::FromStr;
-    
-    log::info!("验证DID文档完整性与CID绑定（支持多种哈希算法）");
-    
-    // 1. 序列化DID文档（使用确定性序列化）
-    let json = serde_json::to_string(did_doc)
-        .context("序列化DID文档失败")?;
-    
-    log::debug!("  DID文档大小: {} 字节", json.len());
-    
-    // 2. 解析CID
-    let cid = Cid::from_str(expected_cid)
-        .context("解析CID失败")?;
-    
-    log::debug!("  CID版本: {:?}", cid.version());
-    log::debug!("  CID codec: {:?}", cid.codec());
-    
-    // 3. 提取CID的multihash部分
-    let multihash = cid.hash();
-    let hash_code = multihash.code();
-    let hash_digest = multihash.digest();
-    
-    log::debug!("  Multihash code: 0x{:x}", hash_code);
-    log::debug!("  Multihash digest: {}", hex::encode(hash_digest));
-    
-    // 4. 根据哈希算法计算文档哈希
-    let computed_hash: Vec<u8> = match hash_code {
-        0x12 => {
-            // SHA-256
-            log::debug!("  使用SHA-256计算哈希");
-            Sha256::digest(json.as_bytes()).to_vec()
-        }
-        0x13 => {
-            // SHA-512
-            log::debug!("  使用SHA-512计算哈希");
-            Sha512::digest(json.as_bytes()).to_vec()
-        }
-        0xb220 => {
-            // Blake2b-512
-            log::debug!("  使用Blake2b-512计算哈希");
-            Blake2b512::digest(json.as_bytes()).to_vec()
+/// 沿previousVersionCid回溯DID文档版本链
+/// 返回从最新到最旧的版本列表，最多回溯max_versions个版本
+pub async fn get_did_document_history(
+    ipfs_client: &IpfsClient,
+    cid: &str,
+    max_versions: usize,
+) -> Result<Vec<DIDVersion>> {
+    let mut versions: Vec<DIDVersion> = Vec::new();
+    let mut next_cid = Some(cid.to_string());
+    
+    while let Some(current_cid) = next_cid.take() {
+        if versions.len() >= max_versions {
+            log::warn!("⚠️ 版本链超过 {} 个版本，停止回溯", max_versions);
+            break;
         }
-        0xb260 => {
-            // Blake2s-256
-            log::debug!("  使用Blake2s-256计算哈希");
-            Blake2s256::digest(json.as_bytes()).to_vec()
+        if versions.iter().any(|v| v.cid == current_cid) {
+            anyhow::bail!("DID文档版本链存在循环: {}", current_cid);
         }
-        _ => {
-            log::warn!("  ⚠️ 不支持的哈希算法: 0x{:x}", hash_code);
-            // 默认使用SHA-256
-            log::debug!("  回退到SHA-256");
-            Sha256::digest(json.as_bytes()).to_vec()
+        
+        let document = get_did_document_from_cid(ipfs_client, &current_cid).await?;
+        if let Some(latest) = versions.first() {
+            if latest.document.id != document.id {
+                anyhow::bail!("版本链中的DID不一致: {} != {}", document.id, latest.document.id);
+            }
         }
-    };
-    
-    log::debug!("  计算的哈希: {}", hex::encode(&computed_hash));
+        
+        next_cid = document.previous_version_cid.clone();
+        versions.push(DIDVersion {
+            cid: current_cid,
+            document,
+        });
+    }
     
-    // 5. 比较哈希值
-    let hashes_match = computed_hash.as_slice() == hash_digest;
+    log::info!("✓ DID文档版本链: {} 个版本", versions.len());
+    Ok(versions)
+}
+
+/// 验证DID文档的完整性
+/// 按Kubo的上传规则重新计算文档CID并与预期CID比较（CIDv0/CIDv1 UnixFS、DAG-CBOR）
+pub fn verify_did_document_integrity(
+    did_doc: &DIDDocument,
+    expected_cid: &str,
+) -> Result<bool> {
+    log::info!("验证DID文档完整性与CID绑定");
     
-    if hashes_match {
-        log::info!("✅ DID文档哈希与CID匹配");
+    let matches = crate::cid_compute::document_matches_cid(did_doc, expected_cid)?;
+    if matches {
+        log::info!("✅ DID文档与CID匹配");
     } else {
-        log::warn!("❌ DID文档哈希与CID不匹配");
-        log::debug!("  预期: {}", hex::encode(hash_digest));
-        log::debug!("  实际: {}", hex::encode(&computed_hash));
-        log::debug!("  哈希算法: 0x{:x}", hash_code);
+        log::warn!("❌ DID文档与CID不匹配: {}", expected_cid);
+        log::debug!("  重新计算的CID: {}", crate::cid_compute::compute_cid(did_doc)?);
     }
     
-    Ok(hashes_match)
+    Ok(matches)
 }
 
 #[cfg(test)]
@@ -452,4 +682,66 @@ mod tests {
         println!("✓ DID文档构建测试通过");
         println!("  DID: {}", did_doc.id);
     }
+    
+    #[test]
+    fn test_key_rotation_proof() {
+        let old_keypair = KeyPair::generate().unwrap();
+        let new_keypair = KeyPair::generate().unwrap();
+        
+        let proof = KeyRotationProof::sign(&old_keypair, &new_keypair.did).unwrap();
+        assert_eq!(proof.previous_did, old_keypair.did);
+        assert!(proof.verify().unwrap());
+        
+        // 篡改新DID后验证应失败
+        let mut tampered = proof.clone();
+        tampered.new_did = KeyPair::generate().unwrap().did;
+        assert!(!tampered.verify().unwrap());
+    }
+    
+    #[test]
+    fn test_apply_update() {
+        let keypair = KeyPair::generate().unwrap();
+        let peer_id = PeerId::from(LibP2PKeypair::generate_ed25519().public());
+        
+        let ipfs_client = IpfsClient::new(None, None, None, None, 30);
+        let mut builder = DIDBuilder::new(ipfs_client);
+        builder.add_service("Messaging", serde_json::json!("https://old.example"));
+        
+        let signing_key = SigningKey::from_bytes(&keypair.private_key);
+        let encrypted_peer_id = encrypt_peer_id(&signing_key, &peer_id).unwrap();
+        let did_doc = builder.build_did_document(&keypair, &encrypted_peer_id).unwrap();
+        
+        let extra_key = KeyPair::generate().unwrap();
+        let update = DIDDocumentUpdate {
+            add_services: vec![Service {
+                id: "#messaging".to_string(),
+                service_type: "Messaging".to_string(),
+                service_endpoint: serde_json::json!("https://new.example"),
+                pubsub_topics: None,
+                network_addresses: None,
+            }],
+            add_verification_methods: vec![VerificationMethod {
+                id: format!("{}#key-2", keypair.did),
+                vm_type: "Ed25519VerificationKey2020".to_string(),
+                controller: keypair.did.clone(),
+                public_key_multibase: format!("z{}", bs58::encode(&extra_key.public_key).into_string()),
+            }],
+            ..Default::default()
+        };
+        
+        let updated = DIDBuilder::apply_update(did_doc, &update).unwrap();
+        let services = updated.service.unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services.iter().find(|s| s.id == "#messaging").unwrap().service_endpoint, "https://new.example");
+        assert_eq!(updated.verification_method.len(), 2);
+        assert_eq!(updated.authentication.len(), 2);
+        
+        // 主验证方法不可移除
+        let remove_primary = DIDDocumentUpdate {
+            remove_verification_method_ids: vec![format!("{}#key-1", keypair.did)],
+            ..Default::default()
+        };
+        let did_doc = builder.build_did_document(&keypair, &encrypted_peer_id).unwrap();
+        assert!(DIDBuilder::apply_update(did_doc, &remove_primary).is_err());
+    }
 }
@@ -1,12 +1,28 @@
 // DIAP Rust SDK - DID文档缓存
 // 减少IPFS请求，提高验证性能
+// 内存层按条目TTL和最大条目数淘汰；可选的磁盘层在重启后保留文档（同样受最大条目数限制，
+// 内存中维护磁盘索引，统计和按DID移除不读取文件）；
+// 解析失败的CID进入负缓存（同样受最大条目数限制），短时间内不再重复请求IPFS
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use dashmap::DashMap;
+use futures::stream::{self, StreamExt};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH, Duration};
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
+use crate::clock::{SharedClock, system_clock};
 use crate::did_builder::DIDDocument;
+use crate::did_update::DidUpdatedEvent;
+use crate::ipfs_client::IpfsClient;
+use crate::key_manager::SignedEnvelope;
+
+/// 默认负缓存有效期（秒）
+pub const DEFAULT_NEGATIVE_TTL: u64 = 60;
+
+/// warm 预加载的并发数
+const WARM_CONCURRENCY: usize = 8;
 
 /// 缓存条目
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,17 +43,68 @@ pub struct CacheEntry {
     pub hit_count: u64,
 }
 
+/// 负缓存条目（解析失败）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegativeEntry {
+    /// 失败原因
+    pub reason: String,
+    
+    /// 过期时间
+    pub expires_at: u64,
+}
+
+/// 磁盘层索引条目
+#[derive(Debug, Clone)]
+struct DiskIndexEntry {
+    /// 文档DID
+    did: String,
+    
+    /// 过期时间
+    expires_at: u64,
+    
+    /// 最近访问时间（淘汰依据）
+    last_access: u64,
+}
+
+/// 批量预加载结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WarmReport {
+    /// 已在缓存中（内存或磁盘）
+    pub already_cached: usize,
+    
+    /// 从IPFS加载
+    pub loaded: usize,
+    
+    /// 加载失败的CID及原因（已写入负缓存）
+    pub failed: Vec<(String, String)>,
+}
+
 /// DID文档缓存管理器
 #[derive(Clone)]
 pub struct DIDCache {
     /// CID -> DIDDocument 缓存
     cache: Arc<DashMap<String, CacheEntry>>,
     
+    /// 负缓存（CID -> 失败原因）
+    negative: Arc<DashMap<String, NegativeEntry>>,
+    
+    /// 磁盘层目录（None表示只使用内存）
+    disk_dir: Option<PathBuf>,
+    
+    /// 磁盘层索引（CID -> DID、过期和访问时间）
+    disk_index: Arc<DashMap<String, DiskIndexEntry>>,
+    
     /// 缓存有效期（秒）
     ttl: u64,
     
+    /// 负缓存有效期（秒）
+    negative_ttl: u64,
+    
     /// 最大缓存条目数
     max_entries: usize,
+    
+    /// 时间源
+    clock: SharedClock,
 }
 
 impl DIDCache {
@@ -47,13 +114,23 @@ impl DIDCache {
     /// * `ttl` - 缓存有效期（秒），默认3600秒（1小时）
     /// * `max_entries` - 最大缓存条目数，默认1000
     pub fn new(ttl: Option<u64>, max_entries: Option<usize>) -> Self {
+        Self::new_with_clock(ttl, max_entries, system_clock())
+    }
+    
+    /// 使用指定时间源创建DID缓存
+    pub fn new_with_clock(ttl: Option<u64>, max_entries: Option<usize>, clock: SharedClock) -> Self {
         let ttl_seconds = ttl.unwrap_or(3600);
         let max = max_entries.unwrap_or(1000);
         
         let cache = Self {
             cache: Arc::new(DashMap::new()),
+            negative: Arc::new(DashMap::new()),
+            disk_dir: None,
+            disk_index: Arc::new(DashMap::new()),
             ttl: ttl_seconds,
+            negative_ttl: DEFAULT_NEGATIVE_TTL,
             max_entries: max,
+            clock,
         };
         
         // 启动后台清理任务
@@ -66,10 +143,69 @@ impl DIDCache {
         cache
     }
     
+    /// 启用磁盘层：文档同时写入目录，内存未命中时从磁盘读取，重启后仍然有效
+    /// 打开时扫描一次目录建立索引，丢弃过期和损坏的文件；超出最大条目数时淘汰最久未访问的文件
+    pub fn with_disk_dir(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("无法创建DID缓存目录: {:?}", dir))?;
+        log::info!("  磁盘层: {:?}", dir);
+        self.disk_dir = Some(dir.clone());
+        
+        let now = self.current_timestamp();
+        let read_dir = std::fs::read_dir(&dir)
+            .with_context(|| format!("无法读取DID缓存目录: {:?}", dir))?;
+        for file in read_dir.filter_map(|file| file.ok()) {
+            let path = file.path();
+            if path.extension().is_some_and(|ext| ext == "tmp") {
+                let _ = std::fs::remove_file(&path);
+                continue;
+            }
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let entry = std::fs::read(&path).ok()
+                .and_then(|data| serde_json::from_slice::<CacheEntry>(&data).ok());
+            match entry {
+                Some(entry) if entry.expires_at >= now && self.disk_path(&entry.cid).as_ref() == Some(&path) => {
+                    self.index_disk(&entry, entry.cached_at);
+                }
+                _ => {
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+        while self.disk_index.len() > self.max_entries {
+            self.evict_disk_lru();
+        }
+        Ok(self)
+    }
+    
+    /// 设置负缓存有效期
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = ttl.as_secs();
+        self
+    }
+    
     /// 获取DID文档
     pub fn get(&self, cid: &str) -> Option<DIDDocument> {
+        let document = self.get_from_memory(cid);
+        if document.is_some() {
+            self.touch_disk(cid);
+            return document;
+        }
+        
+        let entry = self.read_disk(cid)?;
+        self.touch_disk(cid);
+        log::debug!("✓ 磁盘缓存命中: {}", cid);
+        let document = entry.document.clone();
+        self.insert_memory(entry);
+        Some(document)
+    }
+    
+    fn get_from_memory(&self, cid: &str) -> Option<DIDDocument> {
         if let Some(mut entry) = self.cache.get_mut(cid) {
-            let now = Self::current_timestamp();
+            let now = self.current_timestamp();
             
             // 检查是否过期
             if entry.expires_at < now {
@@ -93,38 +229,166 @@ impl DIDCache {
     
     /// 存储DID文档
     pub fn put(&self, cid: String, document: DIDDocument) -> Result<()> {
-        // 检查缓存大小
-        if self.cache.len() >= self.max_entries {
-            self.evict_lru();
-        }
-        
-        let now = Self::current_timestamp();
+        self.put_with_ttl(cid, document, Duration::from_secs(self.ttl))
+    }
+    
+    /// 以指定有效期存储DID文档（例如不可变CID可以缓存更久）
+    pub fn put_with_ttl(&self, cid: String, document: DIDDocument, ttl: Duration) -> Result<()> {
+        let now = self.current_timestamp();
         let entry = CacheEntry {
             document,
             cid: cid.clone(),
             cached_at: now,
-            expires_at: now + self.ttl,
+            expires_at: now + ttl.as_secs(),
             hit_count: 0,
         };
         
-        self.cache.insert(cid.clone(), entry);
+        self.negative.remove(&cid);
+        self.write_disk(&entry)?;
+        self.insert_memory(entry);
         log::debug!("✓ 已缓存DID文档: {}", cid);
         
         Ok(())
     }
     
+    /// 记录解析失败，负缓存有效期内 negative_reason 返回失败原因
+    /// 负缓存同样受最大条目数限制，超出时淘汰最早到期的条目（远端消息引用的任意CID不会无限累积）
+    pub fn put_negative(&self, cid: &str, reason: impl Into<String>) {
+        let entry = NegativeEntry {
+            reason: reason.into(),
+            expires_at: self.current_timestamp() + self.negative_ttl,
+        };
+        if !self.negative.contains_key(cid) && self.negative.len() >= self.max_entries {
+            let now = self.current_timestamp();
+            self.negative.retain(|_, entry| entry.expires_at >= now);
+            if self.negative.len() >= self.max_entries {
+                let oldest = self.negative.iter()
+                    .min_by_key(|entry| entry.expires_at)
+                    .map(|entry| entry.key().clone());
+                if let Some(oldest) = oldest {
+                    self.negative.remove(&oldest);
+                }
+            }
+        }
+        self.negative.insert(cid.to_string(), entry);
+        log::debug!("记录DID解析失败: {}", cid);
+    }
+    
+    /// 最近解析失败的原因（负缓存命中时返回）
+    pub fn negative_reason(&self, cid: &str) -> Option<String> {
+        let entry = self.negative.get(cid)?;
+        if entry.expires_at < self.current_timestamp() {
+            drop(entry);
+            self.negative.remove(cid);
+            return None;
+        }
+        Some(entry.reason.clone())
+    }
+    
+    /// 批量预加载：已缓存的跳过，其余从IPFS并发获取，失败的写入负缓存
+    pub async fn warm(&self, ipfs_client: &IpfsClient, cids: &[String]) -> WarmReport {
+        let mut report = WarmReport::default();
+        let mut missing = Vec::new();
+        for cid in cids {
+            if self.get(cid).is_some() {
+                report.already_cached += 1;
+            } else if !missing.contains(cid) {
+                missing.push(cid.clone());
+            }
+        }
+        
+        let results: Vec<_> = stream::iter(missing)
+            .map(|cid| async move {
+                let result = crate::did_builder::get_did_document_from_cid(ipfs_client, &cid).await;
+                (cid, result)
+            })
+            .buffer_unordered(WARM_CONCURRENCY)
+            .collect()
+            .await;
+        
+        for (cid, result) in results {
+            match result.map_err(anyhow::Error::from).and_then(|document| self.put(cid.clone(), document)) {
+                Ok(()) => report.loaded += 1,
+                Err(e) => {
+                    self.put_negative(&cid, e.to_string());
+                    report.failed.push((cid, e.to_string()));
+                }
+            }
+        }
+        
+        log::info!("💾 DID缓存预加载: 已缓存 {}，加载 {}，失败 {}", report.already_cached, report.loaded, report.failed.len());
+        report
+    }
+    
     /// 移除缓存条目
     pub fn remove(&self, cid: &str) -> Option<DIDDocument> {
-        self.cache.remove(cid).map(|(_, entry)| {
+        let disk_entry = self.read_disk(cid);
+        self.remove_disk(cid);
+        self.cache.remove(cid).map(|(_, entry)| entry).or(disk_entry).map(|entry| {
             log::debug!("移除缓存: {}", cid);
             entry.document
         })
     }
     
-    /// 清空缓存
+    /// 获取指定DID的所有缓存条目
+    pub fn entries_for_did(&self, did: &str) -> Vec<CacheEntry> {
+        self.cache
+            .iter()
+            .filter(|entry| entry.document.id == did)
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+    
+    /// 移除指定DID的所有缓存条目（含磁盘层），返回移除数量
+    pub fn remove_did(&self, did: &str) -> usize {
+        let mut removed_cids = std::collections::HashSet::new();
+        self.cache.retain(|cid, entry| {
+            if entry.document.id == did {
+                removed_cids.insert(cid.clone());
+                false
+            } else {
+                true
+            }
+        });
+        for cid in self.disk_cids_for_did(did) {
+            self.remove_disk(&cid);
+            removed_cids.insert(cid);
+        }
+        let removed = removed_cids.len();
+        log::debug!("移除DID {} 的缓存: {} 个条目", did, removed);
+        removed
+    }
+    
+    /// 处理DID更新事件：移除该DID新CID以外的所有缓存条目（含磁盘层）和新CID的负缓存，返回移除数量
+    pub fn apply_update(&self, event: &DidUpdatedEvent) -> Result<usize> {
+        event.require_valid()?;
+        
+        let mut stale: std::collections::HashSet<String> = self.entries_for_did(&event.did)
+            .into_iter()
+            .map(|entry| entry.cid)
+            .chain(self.disk_cids_for_did(&event.did))
+            .collect();
+        stale.extend(event.previous_cid.clone());
+        stale.retain(|cid| event.is_stale_cid(cid));
+        
+        let removed = stale.iter().filter(|cid| self.remove(cid).is_some()).count();
+        self.negative.remove(&event.cid);
+        
+        if removed > 0 {
+            log::info!("♻️ DID文档已更新，移除旧缓存: {} ({} 个条目)", event.did, removed);
+        }
+        Ok(removed)
+    }
+    
+    /// 清空缓存（含磁盘层和负缓存）
     pub fn clear(&self) {
         let count = self.cache.len();
         self.cache.clear();
+        self.negative.clear();
+        let disk_cids: Vec<String> = self.disk_index.iter().map(|entry| entry.key().clone()).collect();
+        for cid in disk_cids {
+            self.remove_disk(&cid);
+        }
         log::info!("🧹 清空缓存: {} 个条目", count);
     }
     
@@ -132,7 +396,7 @@ impl DIDCache {
     pub fn stats(&self) -> CacheStats {
         let mut total_hits = 0u64;
         let mut expired = 0usize;
-        let now = Self::current_timestamp();
+        let now = self.current_timestamp();
         
         for entry in self.cache.iter() {
             total_hits += entry.hit_count;
@@ -147,12 +411,14 @@ impl DIDCache {
             total_hits,
             max_entries: self.max_entries,
             ttl: self.ttl,
+            negative_entries: self.negative.len(),
+            disk_entries: self.disk_index.len(),
         }
     }
     
     /// 清理过期条目
     pub fn cleanup_expired(&self) -> usize {
-        let now = Self::current_timestamp();
+        let now = self.current_timestamp();
         let mut removed = 0;
         
         self.cache.retain(|_, entry| {
@@ -164,6 +430,15 @@ impl DIDCache {
             }
         });
         
+        self.negative.retain(|_, entry| entry.expires_at >= now);
+        let expired_on_disk: Vec<String> = self.disk_index.iter()
+            .filter(|entry| entry.expires_at < now)
+            .map(|entry| entry.key().clone())
+            .collect();
+        for cid in expired_on_disk {
+            self.remove_disk(&cid);
+        }
+        
         if removed > 0 {
             log::debug!("🧹 清理了 {} 个过期缓存", removed);
         }
@@ -171,6 +446,97 @@ impl DIDCache {
         removed
     }
     
+    fn insert_memory(&self, entry: CacheEntry) {
+        // 检查缓存大小（磁盘层不受内存上限影响）
+        if !self.cache.contains_key(&entry.cid) && self.cache.len() >= self.max_entries {
+            self.evict_lru();
+        }
+        self.cache.insert(entry.cid.clone(), entry);
+    }
+    
+    /// 磁盘文件路径（按CID哈希命名，DID中的':'等字符不进入文件名）
+    fn disk_path(&self, cid: &str) -> Option<PathBuf> {
+        let dir = self.disk_dir.as_ref()?;
+        Some(dir.join(format!("{}.json", hex::encode(Sha256::digest(cid.as_bytes())))))
+    }
+    
+    fn read_disk(&self, cid: &str) -> Option<CacheEntry> {
+        if !self.disk_index.contains_key(cid) {
+            return None;
+        }
+        let path = self.disk_path(cid)?;
+        let data = std::fs::read(&path).ok()?;
+        let entry: CacheEntry = match serde_json::from_slice(&data) {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("损坏的DID缓存文件 {:?}: {}", path, e);
+                self.remove_disk(cid);
+                return None;
+            }
+        };
+        if entry.cid != cid || entry.expires_at < self.current_timestamp() {
+            self.remove_disk(cid);
+            return None;
+        }
+        Some(entry)
+    }
+    
+    fn write_disk(&self, entry: &CacheEntry) -> Result<()> {
+        let Some(path) = self.disk_path(&entry.cid) else {
+            return Ok(());
+        };
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, serde_json::to_vec(entry)?)
+            .with_context(|| format!("无法写入DID缓存: {:?}", temp_path))?;
+        std::fs::rename(&temp_path, &path)
+            .with_context(|| format!("无法写入DID缓存: {:?}", path))?;
+        
+        if !self.disk_index.contains_key(&entry.cid) && self.disk_index.len() >= self.max_entries {
+            self.evict_disk_lru();
+        }
+        self.index_disk(entry, self.current_timestamp());
+        Ok(())
+    }
+    
+    fn index_disk(&self, entry: &CacheEntry, last_access: u64) {
+        self.disk_index.insert(entry.cid.clone(), DiskIndexEntry {
+            did: entry.document.id.clone(),
+            expires_at: entry.expires_at,
+            last_access,
+        });
+    }
+    
+    fn touch_disk(&self, cid: &str) {
+        if let Some(mut entry) = self.disk_index.get_mut(cid) {
+            entry.last_access = self.current_timestamp();
+        }
+    }
+    
+    fn remove_disk(&self, cid: &str) {
+        self.disk_index.remove(cid);
+        if let Some(path) = self.disk_path(cid) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    
+    fn disk_cids_for_did(&self, did: &str) -> Vec<String> {
+        self.disk_index.iter()
+            .filter(|entry| entry.did == did)
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+    
+    /// 淘汰最久未访问的磁盘条目
+    fn evict_disk_lru(&self) {
+        let oldest = self.disk_index.iter()
+            .min_by_key(|entry| entry.last_access)
+            .map(|entry| entry.key().clone());
+        if let Some(cid) = oldest {
+            self.remove_disk(&cid);
+            log::debug!("驱逐磁盘缓存: {}", cid);
+        }
+    }
+    
     /// 驱逐最少使用的条目（LRU）
     fn evict_lru(&self) {
         // 找到命中次数最少的条目
@@ -191,17 +557,15 @@ impl DIDCache {
     }
     
     /// 获取当前时间戳
-    fn current_timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
+    fn current_timestamp(&self) -> u64 {
+        self.clock.now_secs()
     }
     
     /// 启动后台清理任务
     fn start_cleanup_task(&self) {
         let cache = self.cache.clone();
         let ttl = self.ttl;
+        let clock = self.clock.clone();
         
         tokio::spawn(async move {
             // 每隔TTL/4清理一次
@@ -211,10 +575,7 @@ impl DIDCache {
             loop {
                 interval_timer.tick().await;
                 
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
+                let now = clock.now_secs();
                 
                 let mut removed = 0;
                 cache.retain(|_, entry| {
@@ -248,6 +609,10 @@ pub struct CacheStats {
     pub total_hits: u64,
     pub max_entries: usize,
     pub ttl: u64,
+    #[serde(default)]
+    pub negative_entries: usize,
+    #[serde(default)]
+    pub disk_entries: usize,
 }
 
 #[cfg(test)]
@@ -266,8 +631,11 @@ mod tests {
                 public_key_multibase: "z6MkTest".to_string(),
             }],
             authentication: vec![format!("{}#key-1", did)],
+            key_agreement: Vec::new(),
             service: None,
             created: chrono::Utc::now().to_rfc3339(),
+            key_rotation: None,
+            previous_version_cid: None,
         }
     }
     
@@ -361,5 +729,99 @@ mod tests {
         assert!(cache.get("QmTest2").is_none());  // 被驱逐
         assert!(cache.get("QmTest3").is_some());
     }
+    
+    #[tokio::test]
+    async fn test_disk_tier_survives_restart() {
+        use crate::clock::MockClock;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new(1_000_000);
+        let open = || DIDCache::new_with_clock(Some(300), Some(2), Arc::new(clock.clone()))
+            .with_disk_dir(dir.path())
+            .unwrap();
+        
+        let cache = open();
+        cache.put("QmA".to_string(), create_test_document("did:key:a")).unwrap();
+        cache.put_with_ttl("QmB".to_string(), create_test_document("did:key:b"), Duration::from_secs(30)).unwrap();
+        assert_eq!(cache.stats().disk_entries, 2);
+        drop(cache);
+        
+        let restarted = open();
+        assert_eq!(restarted.get("QmA").unwrap().id, "did:key:a");
+        assert_eq!(restarted.get("QmB").unwrap().id, "did:key:b");
+        
+        // 单条TTL到期后磁盘文件也失效
+        clock.advance(Duration::from_secs(31));
+        assert!(open().get("QmB").is_none());
+        assert!(open().get("QmA").is_some());
+        assert_eq!(restarted.remove_did("did:key:a"), 1);
+        assert!(open().get("QmA").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_negative_and_disk_tiers_are_bounded() {
+        use crate::clock::MockClock;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new(1_000_000);
+        let open = || DIDCache::new_with_clock(Some(300), Some(2), Arc::new(clock.clone()))
+            .with_disk_dir(dir.path())
+            .unwrap();
+        
+        // 负缓存超出上限时淘汰最早到期的条目
+        let cache = open();
+        for i in 0..3 {
+            cache.put_negative(&format!("QmBogus{}", i), "not found");
+            clock.advance(Duration::from_secs(1));
+        }
+        assert_eq!(cache.stats().negative_entries, 2);
+        assert!(cache.negative_reason("QmBogus0").is_none());
+        assert!(cache.negative_reason("QmBogus2").is_some());
+        
+        // 磁盘层超出上限时淘汰最久未访问的条目
+        cache.put("QmA".to_string(), create_test_document("did:key:a")).unwrap();
+        clock.advance(Duration::from_secs(1));
+        cache.put("QmB".to_string(), create_test_document("did:key:b")).unwrap();
+        clock.advance(Duration::from_secs(1));
+        assert!(cache.get("QmA").is_some());
+        clock.advance(Duration::from_secs(1));
+        cache.put("QmC".to_string(), create_test_document("did:key:c")).unwrap();
+        assert_eq!(cache.stats().disk_entries, 2);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+        drop(cache);
+        
+        let reopened = open();
+        assert_eq!(reopened.stats().disk_entries, 2);
+        assert!(reopened.get("QmB").is_none());
+        assert_eq!(reopened.get("QmA").unwrap().id, "did:key:a");
+        assert_eq!(reopened.get("QmC").unwrap().id, "did:key:c");
+    }
+    
+    #[tokio::test]
+    async fn test_negative_cache_and_warm() {
+        use crate::clock::MockClock;
+        
+        let clock = MockClock::new(1_000_000);
+        let cache = DIDCache::new_with_clock(Some(300), Some(100), Arc::new(clock.clone()))
+            .with_negative_ttl(Duration::from_secs(60));
+        
+        cache.put_negative("QmMissing", "not found");
+        assert_eq!(cache.negative_reason("QmMissing").as_deref(), Some("not found"));
+        clock.advance(Duration::from_secs(61));
+        assert!(cache.negative_reason("QmMissing").is_none());
+        
+        // 已缓存的CID不会访问IPFS；重复的CID只计一次
+        cache.put("QmCached".to_string(), create_test_document("did:key:c")).unwrap();
+        let ipfs = crate::ipfs_client::IpfsClient::new_public_only(5);
+        let report = cache.warm(&ipfs, &["QmCached".to_string(), "QmCached".to_string()]).await;
+        assert_eq!(report.already_cached, 2);
+        assert_eq!(report.loaded, 0);
+        assert!(report.failed.is_empty());
+        
+        // 成功写入缓存会清除负缓存
+        cache.put_negative("QmCached", "stale failure");
+        cache.put("QmCached".to_string(), create_test_document("did:key:c")).unwrap();
+        assert!(cache.negative_reason("QmCached").is_none());
+    }
 }
 
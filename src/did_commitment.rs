@@ -0,0 +1,256 @@
+// DIAP Rust SDK - DID两阶段发布
+// 智能体先在本地计算DID文档的CID并签署预提交声明，随后才上传文档；
+// 验证方在文档可获取之前把身份视为"待定"，避免证明引用了尚不能获取的CID
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::did_builder::DIDDocument;
+use crate::encrypted_peer_id::EncryptedPeerID;
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::SignedMessage;
+
+/// CID预提交消息类型标识（PubSubMessageType::Custom）
+pub const CID_COMMITMENT_MESSAGE_TYPE: &str = "cid_commitment";
+
+/// 验证方最多保留的待发布预提交声明数
+pub const MAX_PENDING_COMMITMENTS: usize = 1024;
+
+/// 签名的CID预提交声明：DID持有者承诺将以该CID发布文档
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CidCommitment {
+    /// 持有者DID
+    pub did: String,
+
+    /// 预先计算的文档CID
+    pub cid: String,
+
+    /// 声明时间（秒）
+    pub committed_at: u64,
+
+    /// DID持有者签名（base64）
+    pub signature: String,
+}
+
+impl CidCommitment {
+    /// 签署预提交声明
+    pub fn sign(signer: &dyn Signer, cid: &str, committed_at: u64) -> Result<Self> {
+        Self {
+            did: signer.did(),
+            cid: cid.to_string(),
+            committed_at,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+}
+
+impl SignedEnvelope for CidCommitment {
+    const KIND: &'static str = "CID预提交";
+
+    fn signer_did(&self) -> &str {
+        &self.did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+impl SignedMessage for CidCommitment {
+    const MESSAGE_TYPE: &'static str = CID_COMMITMENT_MESSAGE_TYPE;
+}
+
+/// 已预提交、尚未上传的DID文档
+#[derive(Debug, Clone)]
+pub struct PendingPublication {
+    /// DID标识符
+    pub did: String,
+
+    /// 预先计算的CID（上传后必须一致）
+    pub cid: String,
+
+    /// 待上传的DID文档
+    pub did_document: DIDDocument,
+
+    /// 加密的PeerID
+    pub encrypted_peer_id: EncryptedPeerID,
+
+    /// 签名的预提交声明（可先广播，并用于生成绑定证明）
+    pub commitment: CidCommitment,
+}
+
+/// 预提交CID的发布状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitmentStatus {
+    /// 文档尚不能获取，身份待定
+    Pending { reason: String },
+
+    /// 文档可获取且与预提交的CID和DID一致
+    Published,
+
+    /// 签名无效或获取到的文档与声明不符
+    Invalid { reason: String },
+}
+
+impl CommitmentStatus {
+    /// 是否已发布
+    pub fn is_published(&self) -> bool {
+        matches!(self, CommitmentStatus::Published)
+    }
+
+    /// 是否待定
+    pub fn is_pending(&self) -> bool {
+        matches!(self, CommitmentStatus::Pending { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+
+    #[test]
+    fn test_commitment_signature() {
+        let keypair = KeyPair::generate().unwrap();
+        let commitment = CidCommitment::sign(&keypair, "QmCommitted", 1_000).unwrap();
+        commitment.require_valid().unwrap();
+
+        let mut forged = commitment.clone();
+        forged.cid = "QmOther".to_string();
+        assert!(forged.require_valid().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_two_phase_publish() {
+        use crate::block_store::BlockStore;
+        use crate::cid_compute::{compute_content_cid, CidOptions};
+        use crate::identity_manager::{AgentInfo, IdentityManager};
+        use crate::ipfs_client::{IpfsClient, IpfsUploadResult};
+        use crate::pinning_provider::PinningProvider;
+        use std::sync::Arc;
+
+        /// 按Kubo默认参数计算CID并写入块存储的上传提供商
+        struct KuboLikeProvider(BlockStore);
+
+        #[async_trait::async_trait]
+        impl PinningProvider for KuboLikeProvider {
+            fn name(&self) -> &str {
+                "kubo_like"
+            }
+
+            async fn upload(&self, _client: &reqwest::Client, content: &str, _name: &str) -> Result<IpfsUploadResult> {
+                let cid = compute_content_cid(content.as_bytes(), &CidOptions::default())?;
+                self.0.put(&cid, content.as_bytes()).await?;
+                Ok(IpfsUploadResult {
+                    cid,
+                    size: content.len() as u64,
+                    uploaded_at: chrono::Utc::now().to_rfc3339(),
+                    provider: self.name().to_string(),
+                })
+            }
+
+            async fn pin(&self, _client: &reqwest::Client, _cid: &str) -> Result<()> {
+                Ok(())
+            }
+
+            async fn is_pinned(&self, _client: &reqwest::Client, cid: &str) -> Result<bool> {
+                Ok(self.0.contains(cid).await)
+            }
+
+            async fn unpin(&self, _client: &reqwest::Client, _cid: &str) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlockStore::open(dir.path()).unwrap();
+        let client = IpfsClient::new_public_only(1)
+            .with_block_store(store.clone())
+            .with_pinning_provider(Arc::new(KuboLikeProvider(store)));
+        for gateway in client.public_gateways() {
+            client.remove_gateway(&gateway);
+        }
+        let manager = IdentityManager::new(client);
+
+        let keypair = KeyPair::generate().unwrap();
+        let agent = AgentInfo {
+            name: "two-phase".to_string(),
+            services: vec![],
+            description: None,
+            tags: None,
+        };
+        let pending = manager.precommit_identity(&agent, &keypair, &libp2p::PeerId::random()).unwrap();
+        pending.commitment.require_valid().unwrap();
+
+        // 上传之前：身份待定
+        let status = manager.check_commitment(&pending.commitment).await.unwrap();
+        assert!(status.is_pending());
+        let verification = manager.verify_committed_identity(&pending.commitment, b"proof", b"nonce").await.unwrap();
+        assert!(verification.pending);
+        assert!(!verification.zkp_verified);
+
+        // 上传后CID与预提交一致
+        let registration = manager.complete_registration(&pending).await.unwrap();
+        assert_eq!(registration.cid, pending.cid);
+        assert!(manager.check_commitment(&pending.commitment).await.unwrap().is_published());
+        let verification = manager.verify_committed_identity(&pending.commitment, b"proof", b"nonce").await.unwrap();
+        assert!(!verification.pending);
+        assert!(verification.zkp_verified);
+
+        // 他人不能借用该CID声明自己的身份
+        let other = KeyPair::generate().unwrap();
+        let hijack = CidCommitment::sign(&other, &pending.cid, 1_000).unwrap();
+        assert!(matches!(manager.check_commitment(&hijack).await.unwrap(), CommitmentStatus::Invalid { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_treats_committed_cid_as_pending() {
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::CallbackSigner;
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+        use std::sync::Arc;
+
+        fn offline_authenticator() -> PubsubAuthenticator {
+            let client = IpfsClient::new_public_only(1);
+            for gateway in client.public_gateways() {
+                client.remove_gateway(&gateway);
+            }
+            PubsubAuthenticator::new(IdentityManager::new(client), None, None)
+        }
+
+        let keypair = KeyPair::generate().unwrap();
+        let commitment = CidCommitment::sign(&keypair, "QmCommitted", 1_000).unwrap();
+        let signing_key = keypair.clone();
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| signing_key.sign(data))).unwrap();
+        let sender = offline_authenticator();
+        sender.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmCommitted".to_string()).await.unwrap();
+        let receiver = offline_authenticator();
+
+        // 没有预提交：文档不可获取即验证失败
+        let message = sender.create_heartbeat("chat").await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(!verification.verified && !verification.provisional);
+
+        // 收到预提交后：同一CID的消息为待定（负缓存不再生效）
+        let announcement = sender.create_commitment_announcement("chat", &commitment).await.unwrap();
+        assert!(receiver.handle_commitment_announcement(&announcement).unwrap());
+        let message = sender.create_heartbeat("chat").await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.provisional, "{:?}", verification.details);
+
+        // 他人不能声明同一CID
+        let other = KeyPair::generate().unwrap();
+        let hijack = CidCommitment::sign(&other, "QmCommitted", 1_001).unwrap();
+        let mut forged = announcement.clone();
+        forged.from_did = other.did.clone();
+        forged.content = hijack.to_bytes().unwrap();
+        assert!(receiver.handle_commitment_announcement(&forged).is_err());
+    }
+}
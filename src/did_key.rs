@@ -0,0 +1,72 @@
+// DIAP Rust SDK - did:key编解码
+// Ed25519公钥与did:key标识符之间的纯计算转换，不依赖运行时和文件系统，
+// 供KeyPair和wasm32（浏览器）构建共用
+
+use anyhow::{Context, Result};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+/// Ed25519公钥的multicodec前缀
+/// 参考: https://github.com/multiformats/multicodec/blob/master/table.csv
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+
+/// 从公钥派生 did:key 标识符
+/// 格式: did:key:z<multibase-multicodec-pubkey>
+pub fn did_key_from_public_key(public_key: &[u8; 32]) -> String {
+    let mut multicodec_pubkey = ED25519_MULTICODEC.to_vec();
+    multicodec_pubkey.extend_from_slice(public_key);
+
+    // 使用 base58btc 编码（前缀 'z'）
+    format!("did:key:z{}", bs58::encode(&multicodec_pubkey).into_string())
+}
+
+/// 从 did:key 标识符解析Ed25519公钥
+pub fn public_key_from_did_key(did: &str) -> Result<[u8; 32]> {
+    let encoded = did.strip_prefix("did:key:z")
+        .ok_or_else(|| anyhow::anyhow!("不是base58btc编码的did:key: {}", did))?;
+
+    let multicodec_pubkey = bs58::decode(encoded).into_vec()
+        .context("解码did:key失败")?;
+
+    // 2字节Ed25519 multicodec前缀 + 32字节公钥
+    if multicodec_pubkey.len() != 34 || multicodec_pubkey[..2] != ED25519_MULTICODEC {
+        anyhow::bail!("did:key不是Ed25519公钥: {}", did);
+    }
+
+    let mut public_key = [0u8; 32];
+    public_key.copy_from_slice(&multicodec_pubkey[2..]);
+    Ok(public_key)
+}
+
+/// DID文档验证方法中的publicKeyMultibase（base58btc，不带multicodec前缀）
+pub fn public_key_multibase(public_key: &[u8; 32]) -> String {
+    format!("z{}", bs58::encode(public_key).into_string())
+}
+
+/// 使用did:key中的公钥验证签名（签名长度不对时返回false）
+pub fn verify_with_did_key(did: &str, data: &[u8], signature: &[u8]) -> Result<bool> {
+    let public_key = public_key_from_did_key(did)?;
+    let verifying_key = VerifyingKey::from_bytes(&public_key)
+        .context("无效的公钥")?;
+
+    let sig = match <[u8; 64]>::try_from(signature) {
+        Ok(bytes) => Signature::from_bytes(&bytes),
+        Err(_) => return Ok(false),
+    };
+
+    Ok(verifying_key.verify(data, &sig).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_did_key_roundtrip() {
+        let public_key = [7u8; 32];
+        let did = did_key_from_public_key(&public_key);
+        assert!(did.starts_with("did:key:z6Mk"));
+        assert_eq!(public_key_from_did_key(&did).unwrap(), public_key);
+        assert!(public_key_from_did_key("did:key:zabc").is_err());
+        assert!(public_key_from_did_key("did:web:example.com").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+// DIAP Rust SDK - DID解析模块
+// did:key的DID文档可以完全由公钥推导，无需访问IPFS
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+use crate::error::{AuthErrorKind, DiapError, DiapResult};
+use crate::did_builder::{DIDDocument, VerificationMethod};
+use crate::did_cache::DIDCache;
+use crate::did_revocation::RevocationRegistry;
+use crate::e2e_encryption::key_agreement_from_did_key;
+use crate::key_manager::KeyPair;
+
+/// DID解析器
+#[derive(Debug, Clone, Default)]
+pub struct DIDResolver;
+
+impl DIDResolver {
+    /// 创建DID解析器
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 解析DID，返回DID文档
+    pub fn resolve(&self, did: &str) -> DiapResult<DIDDocument> {
+        if did.starts_with("did:key:") {
+            return Self::resolve_did_key(did);
+        }
+
+        let method = did.split(':').nth(1).unwrap_or("");
+        Err(DiapError::did(format!("不支持的DID方法: {}", method)))
+    }
+
+    /// 解析did:key：从multibase公钥推导出基础DID文档
+    /// 推导出的文档与DIDBuilder生成文档中的验证方法一致，不包含服务端点
+    pub fn resolve_did_key(did: &str) -> DiapResult<DIDDocument> {
+        let public_key = KeyPair::public_key_from_did_key(did).map_err(DiapError::from_did)?;
+        let key_id = format!("{}#key-1", did);
+
+        Ok(DIDDocument {
+            context: crate::constants::network_params().did_contexts.clone(),
+            id: did.to_string(),
+            verification_method: vec![VerificationMethod {
+                id: key_id.clone(),
+                vm_type: "Ed25519VerificationKey2020".to_string(),
+                controller: did.to_string(),
+                public_key_multibase: format!("z{}", bs58::encode(public_key).into_string()),
+            }],
+            authentication: vec![key_id],
+            key_agreement: vec![key_agreement_from_did_key(did).map_err(DiapError::from_did)?],
+            service: None,
+            // did:key文档是推导出来的，没有创建时间
+            created: String::new(),
+            key_rotation: None,
+            previous_version_cid: None,
+        })
+    }
+}
+
+/// 基于发送者DID文档的签名验证器
+/// 按DID解析并缓存文档，用文档authentication中引用的密钥验证签名，
+/// 而不是用本地密钥对的公钥
+#[derive(Clone)]
+pub struct DIDSignatureVerifier {
+    resolver: DIDResolver,
+    cache: DIDCache,
+    revocations: Option<RevocationRegistry>,
+}
+
+impl DIDSignatureVerifier {
+    /// 创建验证器（文档缓存以DID为键）
+    pub fn new(cache: DIDCache) -> Self {
+        Self {
+            resolver: DIDResolver::new(),
+            cache,
+            revocations: None,
+        }
+    }
+
+    /// 使用吊销注册表（已吊销的DID解析和验证都返回错误）
+    pub fn with_revocation_registry(mut self, registry: RevocationRegistry) -> Self {
+        self.revocations = Some(registry);
+        self
+    }
+
+    /// 解析DID文档（优先使用缓存）
+    pub fn resolve(&self, did: &str) -> DiapResult<DIDDocument> {
+        if self.revocations.as_ref().is_some_and(|registry| registry.is_revoked(did)) {
+            return Err(DiapError::auth(AuthErrorKind::Revoked, format!("DID已吊销: {}", did)));
+        }
+
+        if let Some(document) = self.cache.get(did) {
+            return Ok(document);
+        }
+
+        let document = self.resolver.resolve(did)?;
+        if let Err(e) = self.cache.put(did.to_string(), document.clone()) {
+            log::warn!("缓存DID文档失败: {}", e);
+        }
+        Ok(document)
+    }
+
+    /// 验证签名是否由did的认证密钥之一生成
+    #[tracing::instrument(name = "diap.signature_verify", skip(self, data, signature))]
+    pub fn verify(&self, did: &str, data: &[u8], signature: &[u8]) -> DiapResult<bool> {
+        let Ok(signature) = <[u8; 64]>::try_from(signature) else {
+            return Ok(false);
+        };
+        let signature = Signature::from_bytes(&signature);
+        let document = self.resolve(did)?;
+
+        Ok(authentication_keys(&document)
+            .iter()
+            .any(|key| key.verify(data, &signature).is_ok()))
+    }
+
+    /// 验证签名，失败时返回认证错误
+    pub fn require_valid(&self, did: &str, data: &[u8], signature: &[u8]) -> DiapResult<()> {
+        if self.verify(did, data, signature)? {
+            Ok(())
+        } else {
+            Err(DiapError::auth(AuthErrorKind::InvalidSignature, format!("签名与DID文档不匹配: {}", did)))
+        }
+    }
+
+    /// 移除缓存的文档（例如收到密钥轮换后）
+    pub fn invalidate(&self, did: &str) {
+        self.cache.remove(did);
+    }
+}
+
+/// DID文档中用于认证的Ed25519公钥
+fn authentication_keys(document: &DIDDocument) -> Vec<VerifyingKey> {
+    document.verification_method.iter()
+        .filter(|vm| document.authentication.contains(&vm.id))
+        .filter_map(|vm| {
+            let bytes = bs58::decode(vm.public_key_multibase.trim_start_matches('z')).into_vec().ok()?;
+            // 兼容带Ed25519 multicodec前缀（0xed01）的编码
+            let key = match bytes.as_slice() {
+                [0xed, 0x01, rest @ ..] if rest.len() == 32 => rest,
+                key => key,
+            };
+            VerifyingKey::from_bytes(key.try_into().ok()?).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_did_key() {
+        let keypair = KeyPair::generate().unwrap();
+        let document = DIDResolver::new().resolve(&keypair.did).unwrap();
+
+        assert_eq!(document.id, keypair.did);
+        assert_eq!(document.authentication, vec![format!("{}#key-1", keypair.did)]);
+        assert_eq!(
+            document.verification_method[0].public_key_multibase,
+            format!("z{}", bs58::encode(&keypair.public_key).into_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_against_did_document() {
+        let verifier = DIDSignatureVerifier::new(DIDCache::new(None, None));
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+
+        let signature = alice.sign(b"hello").unwrap();
+        assert!(verifier.verify(&alice.did, b"hello", &signature).unwrap());
+        // 使用缓存的文档
+        assert!(verifier.verify(&alice.did, b"hello", &signature).unwrap());
+
+        // 声称来自bob的消息必须由bob的密钥签名
+        let err = verifier.require_valid(&bob.did, b"hello", &signature).unwrap_err();
+        assert_eq!(err.auth_kind(), Some(AuthErrorKind::InvalidSignature));
+        assert!(!verifier.verify(&alice.did, b"tampered", &signature).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_did_is_rejected() {
+        let registry = RevocationRegistry::new();
+        let verifier = DIDSignatureVerifier::new(DIDCache::new(None, None))
+            .with_revocation_registry(registry.clone());
+        let alice = KeyPair::generate().unwrap();
+        let signature = alice.sign(b"hello").unwrap();
+
+        // 吊销前已缓存的文档也不能再用于验证
+        assert!(verifier.verify(&alice.did, b"hello", &signature).unwrap());
+        registry.revoke(&alice, "密钥泄露").unwrap();
+
+        let err = verifier.verify(&alice.did, b"hello", &signature).unwrap_err();
+        assert_eq!(err.auth_kind(), Some(AuthErrorKind::Revoked));
+        assert!(verifier.resolve(&alice.did).is_err());
+    }
+
+    #[test]
+    fn test_resolve_unsupported_method() {
+        let resolver = DIDResolver::new();
+        assert!(resolver.resolve("did:web:example.com").is_err());
+        assert!(resolver.resolve("did:key:zInvalid").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+// DIAP Rust SDK - DID吊销模块
+// 智能体用自己的密钥签署吊销记录并发布到IPFS/Pubsub，验证方在认定DID有效前检查吊销状态
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use crate::audit_log::{AuditEvent, AuditLog};
+use crate::ipfs_client::IpfsClient;
+use crate::key_manager::KeyPair;
+
+/// 吊销记录广播主题（默认命名空间，私有网络使用 NetworkParams::revocation_topic）
+pub use crate::constants::REVOCATION_TOPIC;
+
+/// 吊销记录
+/// 由被吊销DID自身的密钥签名（密钥泄露时持有者仍可吊销）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationRecord {
+    /// 被吊销的DID
+    pub did: String,
+
+    /// 吊销原因
+    pub reason: String,
+
+    /// 吊销时间
+    pub revoked_at: String,
+
+    /// 签名（base64）
+    pub signature: String,
+}
+
+impl RevocationRecord {
+    /// 签署吊销记录
+    pub fn sign(keypair: &KeyPair, reason: &str) -> Result<Self> {
+        let revoked_at = chrono::Utc::now().to_rfc3339();
+        let data = Self::signing_data(&keypair.did, reason, &revoked_at);
+        let signature = keypair.sign(data.as_bytes())?;
+
+        Ok(Self {
+            did: keypair.did.clone(),
+            reason: reason.to_string(),
+            revoked_at,
+            signature: general_purpose::STANDARD.encode(signature),
+        })
+    }
+
+    /// 验证吊销记录签名（公钥从did:key中解析）
+    pub fn verify(&self) -> Result<bool> {
+        let sig_bytes = general_purpose::STANDARD.decode(&self.signature)
+            .context("解码吊销签名失败")?;
+        let data = Self::signing_data(&self.did, &self.reason, &self.revoked_at);
+        KeyPair::verify_with_did_key(&self.did, data.as_bytes(), &sig_bytes)
+    }
+
+    /// 序列化（用于Pubsub广播）
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化吊销记录失败")
+    }
+
+    /// 反序列化
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).context("解析吊销记录失败")
+    }
+
+    fn signing_data(did: &str, reason: &str, revoked_at: &str) -> String {
+        format!("DIAP_DID_REVOCATION:{}:{}:{}", did, reason, revoked_at)
+    }
+}
+
+/// 吊销注册表
+/// 本地维护已知的吊销记录，可共享给多个验证组件
+#[derive(Clone, Default)]
+pub struct RevocationRegistry {
+    /// DID -> 吊销记录
+    revoked: Arc<DashMap<String, RevocationRecord>>,
+
+    /// DID -> 吊销记录在IPFS上的CID
+    record_cids: Arc<DashMap<String, String>>,
+
+    /// 审计日志（可选）
+    audit_log: Option<AuditLog>,
+}
+
+impl RevocationRegistry {
+    /// 创建空的吊销注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 吊销（本地或外部记录）写入审计日志
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// 吊销本地DID，返回签名后的记录
+    pub fn revoke(&self, keypair: &KeyPair, reason: &str) -> Result<RevocationRecord> {
+        let record = RevocationRecord::sign(keypair, reason)?;
+        self.revoked.insert(record.did.clone(), record.clone());
+        self.audit(&record);
+
+        log::warn!("⛔ DID已吊销: {} ({})", record.did, reason);
+        Ok(record)
+    }
+
+    /// 添加外部吊销记录（从Pubsub或IPFS收到），签名无效时拒绝
+    pub fn add_record(&self, record: RevocationRecord) -> Result<()> {
+        if !record.verify()? {
+            anyhow::bail!("吊销记录签名无效: {}", record.did);
+        }
+
+        log::warn!("⛔ 收到吊销记录: {}", record.did);
+        if !self.revoked.contains_key(&record.did) {
+            self.audit(&record);
+        }
+        self.revoked.insert(record.did.clone(), record);
+        Ok(())
+    }
+
+    fn audit(&self, record: &RevocationRecord) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record_or_warn(&record.did, AuditEvent::Revocation { reason: record.reason.clone() });
+        }
+    }
+
+    /// 发布吊销记录到IPFS，返回CID
+    pub async fn publish(&self, ipfs_client: &IpfsClient, record: &RevocationRecord) -> Result<String> {
+        let json = serde_json::to_string_pretty(record)
+            .context("序列化吊销记录失败")?;
+
+        let result = ipfs_client.upload(&json, "revocation.json").await
+            .context("上传吊销记录到IPFS失败")?;
+
+        self.record_cids.insert(record.did.clone(), result.cid.clone());
+        log::info!("✓ 吊销记录已发布, CID: {}", result.cid);
+        Ok(result.cid)
+    }
+
+    /// 从IPFS导入吊销记录
+    pub async fn import_from_cid(&self, ipfs_client: &IpfsClient, cid: &str) -> Result<RevocationRecord> {
+        let content = ipfs_client.get(cid).await
+            .context("从IPFS获取吊销记录失败")?;
+        let record: RevocationRecord = serde_json::from_str(&content)
+            .context("解析吊销记录失败")?;
+
+        self.add_record(record.clone())?;
+        self.record_cids.insert(record.did.clone(), cid.to_string());
+        Ok(record)
+    }
+
+    /// DID是否已被吊销
+    pub fn is_revoked(&self, did: &str) -> bool {
+        self.revoked.contains_key(did)
+    }
+
+    /// 获取吊销记录
+    pub fn get(&self, did: &str) -> Option<RevocationRecord> {
+        self.revoked.get(did).map(|r| r.clone())
+    }
+
+    /// 获取吊销记录的CID
+    pub fn record_cid(&self, did: &str) -> Option<String> {
+        self.record_cids.get(did).map(|c| c.clone())
+    }
+
+    /// 已吊销的DID数量
+    pub fn count(&self) -> usize {
+        self.revoked.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revocation_record() {
+        let keypair = KeyPair::generate().unwrap();
+        let record = RevocationRecord::sign(&keypair, "密钥泄露").unwrap();
+        assert!(record.verify().unwrap());
+
+        let mut tampered = record.clone();
+        tampered.reason = "其他原因".to_string();
+        assert!(!tampered.verify().unwrap());
+    }
+
+    #[test]
+    fn test_registry_rejects_forged_record() {
+        let victim = KeyPair::generate().unwrap();
+        let attacker = KeyPair::generate().unwrap();
+
+        // 攻击者用自己的密钥为他人的DID签名
+        let mut forged = RevocationRecord::sign(&attacker, "forged").unwrap();
+        forged.did = victim.did.clone();
+
+        let registry = RevocationRegistry::new();
+        assert!(registry.add_record(forged).is_err());
+        assert!(!registry.is_revoked(&victim.did));
+
+        let record = RevocationRecord::sign(&victim, "compromised").unwrap();
+        registry.add_record(record).unwrap();
+        assert!(registry.is_revoked(&victim.did));
+    }
+}
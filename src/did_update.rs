@@ -0,0 +1,132 @@
+// DIAP Rust SDK - DID文档更新通知
+// 智能体重新发布DID文档后，在约定主题上广播签名的"did-updated"事件；
+// 收到事件的节点从DID缓存和验证结果缓存中移除旧CID，避免继续使用过时的服务端点
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::SignedMessage;
+
+/// DID更新通知主题（默认命名空间，私有网络使用 NetworkParams::did_update_topic）
+pub use crate::constants::DID_UPDATE_TOPIC;
+
+/// DID更新消息类型标识（PubSubMessageType::Custom）
+pub const DID_UPDATED_MESSAGE_TYPE: &str = "did_updated";
+
+/// 签名的DID文档更新事件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DidUpdatedEvent {
+    /// 更新文档的DID
+    pub did: String,
+
+    /// 被取代的文档CID（首次发布时为None）
+    pub previous_cid: Option<String>,
+
+    /// 新文档CID
+    pub cid: String,
+
+    /// 更新时间（秒）
+    pub updated_at: u64,
+
+    /// DID持有者签名（base64）
+    pub signature: String,
+}
+
+impl DidUpdatedEvent {
+    /// 签署更新事件
+    pub fn sign(signer: &dyn Signer, previous_cid: Option<&str>, cid: &str, updated_at: u64) -> Result<Self> {
+        Self {
+            did: signer.did(),
+            previous_cid: previous_cid.map(str::to_string),
+            cid: cid.to_string(),
+            updated_at,
+            signature: String::new(),
+        }.signed_by(signer)
+    }
+
+    /// 该DID的某个文档CID是否因本次更新而过时（新CID以外的都算）
+    pub fn is_stale_cid(&self, cid: &str) -> bool {
+        cid != self.cid
+    }
+}
+
+impl SignedEnvelope for DidUpdatedEvent {
+    const KIND: &'static str = "DID更新事件";
+
+    fn signer_did(&self) -> &str {
+        &self.did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+impl SignedMessage for DidUpdatedEvent {
+    const MESSAGE_TYPE: &'static str = DID_UPDATED_MESSAGE_TYPE;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+
+    #[test]
+    fn test_event_signature() {
+        let keypair = KeyPair::generate().unwrap();
+        let event = DidUpdatedEvent::sign(&keypair, Some("bafyold"), "bafynew", 1_000).unwrap();
+        event.require_valid().unwrap();
+        assert!(event.is_stale_cid("bafyold"));
+        assert!(!event.is_stale_cid("bafynew"));
+
+        // 他人不能伪造更新事件
+        let attacker = KeyPair::generate().unwrap();
+        let mut forged = DidUpdatedEvent::sign(&attacker, Some("bafyold"), "bafyevil", 1_000).unwrap();
+        forged.did = keypair.did.clone();
+        assert!(forged.require_valid().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_evicts_stale_entries() {
+        use crate::did_cache::DIDCache;
+        use crate::did_resolver::DIDResolver;
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::CallbackSigner;
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+        use std::sync::Arc;
+
+        let keypair = KeyPair::generate().unwrap();
+        let document = DIDResolver::resolve_did_key(&keypair.did).unwrap();
+
+        // 接收方缓存了旧CID对应的文档
+        let cache = DIDCache::new(None, None);
+        cache.put("bafyold".to_string(), document.clone()).unwrap();
+        cache.put("bafyother".to_string(), DIDResolver::resolve_did_key(&KeyPair::generate().unwrap().did).unwrap()).unwrap();
+        cache.put_negative("bafynew", "not yet propagated");
+        let receiver = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(5)), None, Some(cache.clone()));
+        let mut updates = receiver.subscribe_did_updates().await.unwrap();
+        assert!(receiver.get_subscribed_topics().await.contains(&DID_UPDATE_TOPIC.to_string()));
+
+        // 发送方重新发布文档（回调签名器不生成ZKP证明，不需要访问IPFS）
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+        let sender = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(5)), None, None);
+        sender.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "bafyold".to_string()).await.unwrap();
+        let message = sender.create_did_update("bafynew").await.unwrap();
+        assert_eq!(message.topic, DID_UPDATE_TOPIC);
+        assert_eq!(message.did_cid, "bafynew");
+
+        let event = receiver.handle_did_update(&message).unwrap();
+        assert_eq!(event.previous_cid.as_deref(), Some("bafyold"));
+        assert!(cache.get("bafyold").is_none());
+        assert!(cache.get("bafyother").is_some());
+        assert!(cache.negative_reason("bafynew").is_none());
+
+        assert_eq!(updates.recv().await.unwrap(), event);
+    }
+}
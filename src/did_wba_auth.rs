@@ -0,0 +1,165 @@
+// DIAP Rust SDK - DID签名的HTTP请求认证
+// 请求头 `Authorization: DIDWba did="...", nonce="...", signature="..."`，
+// 签名覆盖服务端标识（audience）、方法、路径、DID、nonce和请求体摘要，
+// 服务端解析调用方DID后验签并检查nonce防重放；audience不同的服务端不接受同一请求头
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::did_resolver::DIDSignatureVerifier;
+use crate::error::{AuthErrorKind, DiapError, DiapResult};
+use crate::key_manager::KeyPair;
+use crate::nonce_manager::NonceManager;
+
+/// Authorization方案名
+pub const DIDWBA_SCHEME: &str = "DIDWba";
+
+/// 解析后的DIDWba请求头
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DidWbaAuthorization {
+    /// 调用方DID
+    pub did: String,
+
+    /// nonce（`timestamp:uuid:random`，见 `NonceManager::generate_nonce`）
+    pub nonce: String,
+
+    /// Ed25519签名
+    pub signature: Vec<u8>,
+}
+
+impl DidWbaAuthorization {
+    /// 解析请求头的值
+    pub fn parse(value: &str) -> DiapResult<Self> {
+        let invalid = |message: &str| DiapError::auth(AuthErrorKind::InvalidSignature, message.to_string());
+        let params = value.trim().strip_prefix(DIDWBA_SCHEME)
+            .filter(|rest| rest.starts_with(' '))
+            .ok_or_else(|| invalid("Authorization不是DIDWba方案"))?;
+
+        let (mut did, mut nonce, mut signature) = (None, None, None);
+        for param in params.split(',') {
+            let Some((key, value)) = param.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"').to_string();
+            match key.trim() {
+                "did" => did = Some(value),
+                "nonce" => nonce = Some(value),
+                "signature" => signature = Some(value),
+                _ => {}
+            }
+        }
+
+        let signature = URL_SAFE_NO_PAD.decode(signature.ok_or_else(|| invalid("DIDWba缺少signature"))?)
+            .map_err(|_| invalid("DIDWba签名不是有效的base64url"))?;
+        Ok(Self {
+            did: did.ok_or_else(|| invalid("DIDWba缺少did"))?,
+            nonce: nonce.ok_or_else(|| invalid("DIDWba缺少nonce"))?,
+            signature,
+        })
+    }
+
+    /// 格式化为请求头的值
+    pub fn to_header_value(&self) -> String {
+        format!(
+            "{} did=\"{}\", nonce=\"{}\", signature=\"{}\"",
+            DIDWBA_SCHEME, self.did, self.nonce, URL_SAFE_NO_PAD.encode(&self.signature)
+        )
+    }
+}
+
+/// 被签名的内容：服务端标识、方法、路径（未编码，含查询串）、DID、nonce和请求体SHA-256
+pub fn signing_payload(audience: &str, method: &str, path: &str, did: &str, nonce: &str, body: &[u8]) -> Vec<u8> {
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+        DIDWBA_SCHEME,
+        audience,
+        method.to_ascii_uppercase(),
+        path,
+        did,
+        nonce,
+        hex::encode(Sha256::digest(body))
+    ).into_bytes()
+}
+
+/// 客户端：为发往audience服务端的请求生成Authorization头
+pub fn sign_request(keypair: &KeyPair, audience: &str, method: &str, path: &str, body: &[u8]) -> anyhow::Result<String> {
+    let nonce = NonceManager::generate_nonce();
+    let signature = keypair.sign(&signing_payload(audience, method, path, &keypair.did, &nonce, body))?;
+    Ok(DidWbaAuthorization { did: keypair.did.clone(), nonce, signature }.to_header_value())
+}
+
+/// 认证通过的调用方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedCaller {
+    /// 调用方DID
+    pub did: String,
+
+    /// 请求使用的nonce
+    pub nonce: String,
+}
+
+/// 服务端：验证DIDWba请求
+#[derive(Clone)]
+pub struct DidRequestVerifier {
+    signatures: DIDSignatureVerifier,
+    nonces: Arc<NonceManager>,
+    audience: String,
+}
+
+impl DidRequestVerifier {
+    /// 创建验证器（DID文档解析与缓存、nonce防重放）；audience为本服务端标识（公开域名或服务端DID）
+    pub fn new(signatures: DIDSignatureVerifier, nonces: Arc<NonceManager>, audience: impl Into<String>) -> Self {
+        Self { signatures, nonces, audience: audience.into() }
+    }
+
+    /// 本服务端标识
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    /// 验证请求头：解析DID文档验签，然后检查并记录nonce
+    pub fn verify(&self, authorization: &str, method: &str, path: &str, body: &[u8]) -> DiapResult<AuthenticatedCaller> {
+        let header = DidWbaAuthorization::parse(authorization)?;
+        let payload = signing_payload(&self.audience, method, path, &header.did, &header.nonce, body);
+        self.signatures.require_valid(&header.did, &payload, &header.signature)?;
+        // 验签通过后再记录nonce，伪造的请求不会占用nonce
+        self.nonces.check_and_record(&header.nonce, &header.did)?;
+
+        log::debug!("🔏 DIDWba认证通过: {} {} {}", header.did, method, path);
+        Ok(AuthenticatedCaller { did: header.did, nonce: header.nonce })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did_cache::DIDCache;
+
+    #[tokio::test]
+    async fn test_sign_and_verify_request() {
+        let keypair = KeyPair::generate().unwrap();
+        let header = sign_request(&keypair, "api.example", "post", "/diap/api/messages", b"{}").unwrap();
+        let parsed = DidWbaAuthorization::parse(&header).unwrap();
+        assert_eq!(parsed.did, keypair.did);
+        assert_eq!(DidWbaAuthorization::parse(&parsed.to_header_value()).unwrap(), parsed);
+
+        let nonces = Arc::new(NonceManager::new(None, None));
+        let verifier = DidRequestVerifier::new(DIDSignatureVerifier::new(DIDCache::new(None, None)), nonces.clone(), "api.example");
+        let caller = verifier.verify(&header, "POST", "/diap/api/messages", b"{}").unwrap();
+        assert_eq!(caller.did, keypair.did);
+
+        // 重放、篡改请求体或路径
+        assert!(verifier.verify(&header, "POST", "/diap/api/messages", b"{}").unwrap_err().is_nonce_replay());
+        let header = sign_request(&keypair, "api.example", "POST", "/diap/api/messages", b"{}").unwrap();
+        assert!(verifier.verify(&header, "POST", "/diap/api/messages", b"{\"a\":1}").is_err());
+        assert!(verifier.verify(&header, "POST", "/diap/api/peers", b"{}").is_err());
+        assert!(DidWbaAuthorization::parse("Bearer abc").is_err());
+
+        // 发给其他服务端的请求头不能重放到本服务端
+        let other = sign_request(&keypair, "other.example", "POST", "/diap/api/messages", b"{}").unwrap();
+        assert!(verifier.verify(&other, "POST", "/diap/api/messages", b"{}").is_err());
+        let other_server = DidRequestVerifier::new(DIDSignatureVerifier::new(DIDCache::new(None, None)), nonces, "other.example");
+        assert!(other_server.verify(&other, "POST", "/diap/api/messages", b"{}").is_ok());
+    }
+}
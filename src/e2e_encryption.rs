@@ -0,0 +1,207 @@
+// DIAP Rust SDK - 端到端加密模块
+// 根据接收方DID文档的keyAgreement条目做X25519密钥协商，用ChaCha20-Poly1305加密消息内容，
+// 中继pubsub或请求-响应流量的中间节点只能看到密文（签名仍覆盖密文，可照常验证）
+
+use anyhow::{Context, Result};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use ed25519_dalek::VerifyingKey;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+
+use crate::did_builder::{DIDDocument, VerificationMethod};
+use crate::key_manager::KeyPair;
+
+/// keyAgreement验证方法类型
+pub const KEY_AGREEMENT_TYPE: &str = "X25519KeyAgreementKey2020";
+
+/// 加密内容的前缀标记（用于区分明文内容）
+const E2E_MAGIC: &[u8] = b"DIAPE2E1";
+
+/// 消息密钥派生域分隔标签
+const E2E_KEY_TAG: &[u8] = b"DIAP_E2E_MESSAGE_V1";
+
+/// 加密后的消息内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedPayload {
+    /// 发送方DID
+    pub sender_did: String,
+
+    /// 接收方DID
+    pub recipient_did: String,
+
+    /// 使用的接收方keyAgreement密钥ID
+    pub key_id: String,
+
+    /// 发送方临时X25519公钥
+    pub ephemeral_public: [u8; 32],
+
+    /// ChaCha20-Poly1305 nonce
+    pub nonce: [u8; 12],
+
+    /// 密文（含认证标签）
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedPayload {
+    /// 编码为消息内容（前缀标记 + bincode）
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = E2E_MAGIC.to_vec();
+        bytes.extend(bincode::serialize(self).context("序列化加密内容失败")?);
+        Ok(bytes)
+    }
+
+    /// 从消息内容解码
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let body = data.strip_prefix(E2E_MAGIC)
+            .ok_or_else(|| anyhow::anyhow!("消息内容未加密"))?;
+        bincode::deserialize(body).context("解析加密内容失败")
+    }
+
+    /// 消息内容是否为加密内容
+    pub fn is_encrypted(data: &[u8]) -> bool {
+        data.starts_with(E2E_MAGIC)
+    }
+
+    /// 关联数据：绑定双方DID和密钥ID，防止密文被挪用到其他会话
+    fn associated_data(&self) -> Vec<u8> {
+        let mut aad = Vec::new();
+        for part in [&self.sender_did, &self.recipient_did, &self.key_id] {
+            aad.extend_from_slice(&(part.len() as u32).to_be_bytes());
+            aad.extend_from_slice(part.as_bytes());
+        }
+        aad
+    }
+}
+
+/// 为本地密钥对生成keyAgreement验证方法（X25519公钥由Ed25519密钥转换）
+pub fn key_agreement_method(keypair: &KeyPair) -> VerificationMethod {
+    let public = X25519PublicKey::from(&x25519_secret(keypair));
+    key_agreement_entry(&keypair.did, public)
+}
+
+/// 从did:key推导keyAgreement验证方法
+pub fn key_agreement_from_did_key(did: &str) -> Result<VerificationMethod> {
+    let public_key = KeyPair::public_key_from_did_key(did)?;
+    let verifying_key = VerifyingKey::from_bytes(&public_key)
+        .context("无效的Ed25519公钥")?;
+    Ok(key_agreement_entry(did, X25519PublicKey::from(verifying_key.to_montgomery().to_bytes())))
+}
+
+/// 加密发给recipient的内容（使用其DID文档中的第一个X25519 keyAgreement密钥）
+pub fn encrypt_for(sender_did: &str, recipient: &DIDDocument, plaintext: &[u8]) -> Result<EncryptedPayload> {
+    let (key_id, recipient_public) = recipient.key_agreement.iter()
+        .filter(|vm| vm.vm_type == KEY_AGREEMENT_TYPE)
+        .find_map(|vm| decode_x25519(&vm.public_key_multibase).map(|key| (vm.id.clone(), key)))
+        .ok_or_else(|| anyhow::anyhow!("DID文档缺少可用的keyAgreement密钥: {}", recipient.id))?;
+
+    let ephemeral = StaticSecret::random_from_rng(rand::rngs::OsRng);
+    let ephemeral_public = X25519PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&recipient_public);
+
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut payload = EncryptedPayload {
+        sender_did: sender_did.to_string(),
+        recipient_did: recipient.id.clone(),
+        key_id,
+        ephemeral_public: ephemeral_public.to_bytes(),
+        nonce,
+        ciphertext: Vec::new(),
+    };
+
+    let cipher = message_cipher(shared.as_bytes(), &payload.ephemeral_public, recipient_public.as_bytes());
+    payload.ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: &payload.associated_data() })
+        .map_err(|_| anyhow::anyhow!("加密消息内容失败"))?;
+
+    Ok(payload)
+}
+
+/// 用本地密钥对解密发给自己的内容
+pub fn decrypt(keypair: &KeyPair, payload: &EncryptedPayload) -> Result<Vec<u8>> {
+    if payload.recipient_did != keypair.did {
+        anyhow::bail!("加密内容不是发给本节点的: {}", payload.recipient_did);
+    }
+
+    let secret = x25519_secret(keypair);
+    let local_public = X25519PublicKey::from(&secret);
+    let shared = secret.diffie_hellman(&X25519PublicKey::from(payload.ephemeral_public));
+
+    let cipher = message_cipher(shared.as_bytes(), &payload.ephemeral_public, local_public.as_bytes());
+    cipher
+        .decrypt(Nonce::from_slice(&payload.nonce), Payload { msg: &payload.ciphertext, aad: &payload.associated_data() })
+        .map_err(|_| anyhow::anyhow!("解密消息内容失败：密钥不匹配或内容被篡改"))
+}
+
+/// Ed25519私钥 -> X25519私钥（SHA-512前32字节，clamp由x25519完成）
+fn x25519_secret(keypair: &KeyPair) -> StaticSecret {
+    let hash = Sha512::digest(keypair.private_key);
+    let mut scalar = [0u8; 32];
+    scalar.copy_from_slice(&hash[..32]);
+    StaticSecret::from(scalar)
+}
+
+fn key_agreement_entry(did: &str, public: X25519PublicKey) -> VerificationMethod {
+    VerificationMethod {
+        id: format!("{}#key-x25519-1", did),
+        vm_type: KEY_AGREEMENT_TYPE.to_string(),
+        controller: did.to_string(),
+        public_key_multibase: format!("z{}", bs58::encode(public.as_bytes()).into_string()),
+    }
+}
+
+fn decode_x25519(multibase: &str) -> Option<X25519PublicKey> {
+    let bytes = bs58::decode(multibase.strip_prefix('z')?).into_vec().ok()?;
+    let bytes: [u8; 32] = bytes.try_into().ok()?;
+    Some(X25519PublicKey::from(bytes))
+}
+
+/// 从共享密钥派生消息密钥（绑定临时公钥和接收方公钥）
+fn message_cipher(shared_secret: &[u8], ephemeral_public: &[u8; 32], recipient_public: &[u8; 32]) -> ChaCha20Poly1305 {
+    let mut hasher = Sha256::new();
+    hasher.update(E2E_KEY_TAG);
+    hasher.update(shared_secret);
+    hasher.update(ephemeral_public);
+    hasher.update(recipient_public);
+    ChaCha20Poly1305::new(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did_resolver::DIDResolver;
+
+    #[test]
+    fn test_encrypt_between_dids() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+
+        // did:key推导出的keyAgreement与本地密钥对生成的一致
+        let bob_document = DIDResolver::new().resolve(&bob.did).unwrap();
+        assert_eq!(bob_document.key_agreement[0].public_key_multibase,
+                   key_agreement_method(&bob).public_key_multibase);
+
+        let payload = encrypt_for(&alice.did, &bob_document, b"secret plan").unwrap();
+        let content = payload.to_bytes().unwrap();
+        assert!(EncryptedPayload::is_encrypted(&content));
+        assert!(!content.windows(6).any(|w| w == b"secret"));
+
+        let received = EncryptedPayload::from_bytes(&content).unwrap();
+        assert_eq!(decrypt(&bob, &received).unwrap(), b"secret plan");
+
+        // 其他节点无法解密，伪造发送方会破坏认证
+        let mut misdirected = received.clone();
+        misdirected.recipient_did = mallory.did.clone();
+        assert!(decrypt(&mallory, &misdirected).is_err());
+        let mut forged = received;
+        forged.sender_did = mallory.did.clone();
+        assert!(decrypt(&bob, &forged).is_err());
+    }
+}
@@ -0,0 +1,423 @@
+// DIAP Rust SDK - 内嵌IPFS后端
+// 纯Rust的内容存取，无需外部IPFS守护进程：内容按Kubo默认规则在本地计算CID并保存在BlockStore，
+// 通过Kademlia provider记录宣告和查找，再用DIAP块交换协议（request-response）从提供者直接取回并校验CID。
+// 块交换协议只在DIAP节点之间使用，不与Kubo的bitswap互通
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use libp2p::{
+    kad::RecordKey,
+    noise,
+    request_response::{self, OutboundRequestId, ProtocolSupport, ResponseChannel},
+    swarm::SwarmEvent,
+    tcp, yamux, Multiaddr, PeerId, StreamProtocol, Swarm,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::agent_discovery::{hashed_key, wait_for_listen_addr, DhtBackend};
+use crate::block_store::BlockStore;
+use crate::cid_compute::{self, CidOptions};
+use crate::clock::{SharedClock, system_clock};
+use crate::constants::network_params;
+use crate::ipfs_client::IpfsBackend;
+use crate::libp2p_identity::LibP2PIdentity;
+use crate::p2p_codec::DIAPCodec;
+
+/// 默认取块超时
+pub const DEFAULT_BLOCK_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 块响应状态：找到
+const BLOCK_FOUND: u8 = 1;
+
+/// 块响应状态：不存在
+const BLOCK_NOT_FOUND: u8 = 0;
+
+/// 块的provider记录键
+pub fn block_key(cid: &str) -> RecordKey {
+    hashed_key(&network_params().block_key_prefix(), cid)
+}
+
+/// 节点块交换地址记录的键
+pub fn block_peer_key(peer_id: &PeerId) -> RecordKey {
+    hashed_key(&network_params().block_peer_key_prefix(), &peer_id.to_base58())
+}
+
+/// 块交换地址记录（provider只给出PeerID，取块前按此记录拨号）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockPeerRecord {
+    /// PeerID
+    pub peer_id: String,
+
+    /// 块交换协议的监听地址
+    pub addresses: Vec<String>,
+
+    /// 发布时间（秒）
+    pub published_at: u64,
+}
+
+impl BlockPeerRecord {
+    /// 序列化为DHT记录值
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化块交换地址记录失败")
+    }
+
+    /// 从DHT记录值解析
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).context("解析块交换地址记录失败")
+    }
+}
+
+type FetchReply = oneshot::Sender<Result<Option<Vec<u8>>>>;
+
+enum ExchangeCommand {
+    Fetch(PeerId, Vec<Multiaddr>, String, FetchReply),
+}
+
+/// 内嵌IPFS节点（BlockStore + DHT provider记录 + 块交换）
+pub struct EmbeddedIpfs {
+    peer_id: PeerId,
+    store: BlockStore,
+    dht: Arc<dyn DhtBackend>,
+    commands: mpsc::UnboundedSender<ExchangeCommand>,
+    listen_addrs: Vec<Multiaddr>,
+    fetch_timeout: Duration,
+    clock: SharedClock,
+}
+
+impl EmbeddedIpfs {
+    /// 启动块交换服务并在DHT上发布本节点的块交换地址
+    ///
+    /// identity应与dht使用同一身份，provider记录中的PeerID才能对应到块交换地址
+    pub async fn start(
+        identity: &LibP2PIdentity,
+        store: BlockStore,
+        dht: Arc<dyn DhtBackend>,
+        listen_addr: Multiaddr,
+    ) -> Result<Self> {
+        if dht.local_peer_id() != *identity.peer_id() {
+            anyhow::bail!("块交换身份与DHT节点不一致: {} != {}", identity.peer_id(), dht.local_peer_id());
+        }
+
+        let mut swarm = build_exchange_swarm(identity)?;
+        swarm.listen_on(listen_addr.clone())
+            .with_context(|| format!("无法监听地址: {}", listen_addr))?;
+        let address = wait_for_listen_addr(&mut swarm).await?;
+
+        let peer_id = *swarm.local_peer_id();
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run_exchange(swarm, store.clone(), receiver));
+
+        let node = Self {
+            peer_id,
+            store,
+            dht,
+            commands,
+            listen_addrs: vec![address],
+            fetch_timeout: DEFAULT_BLOCK_FETCH_TIMEOUT,
+            clock: system_clock(),
+        };
+        node.publish_peer_record().await?;
+
+        log::info!("📦 内嵌IPFS已启动: {} ({})", peer_id, node.listen_addrs[0]);
+        Ok(node)
+    }
+
+    /// 设置取块超时
+    pub fn with_fetch_timeout(mut self, timeout: Duration) -> Self {
+        self.fetch_timeout = timeout;
+        self
+    }
+
+    /// 使用指定时间源
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 本地PeerID
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    /// 块交换监听地址
+    pub fn listen_addrs(&self) -> &[Multiaddr] {
+        &self.listen_addrs
+    }
+
+    /// 本地块存储
+    pub fn block_store(&self) -> &BlockStore {
+        &self.store
+    }
+
+    /// 添加内容：计算CID（与Kubo `ipfs add` 默认参数一致）、保存到本地并宣告provider
+    pub async fn add_content(&self, content: &[u8]) -> Result<String> {
+        let cid = cid_compute::compute_content_cid(content, &CidOptions::default())?;
+        self.store.put(&cid, content).await?;
+        self.provide(&cid).await;
+        Ok(cid)
+    }
+
+    /// 获取内容：先查本地，再向DHT上的provider取块；取回的内容必须与CID一致
+    pub async fn get_content(&self, cid: &str) -> Result<Option<Vec<u8>>> {
+        if let Some(data) = self.store.get(cid).await? {
+            return Ok(Some(data));
+        }
+
+        let providers = self.dht.get_providers(block_key(cid)).await?;
+        for provider in providers.into_iter().filter(|p| *p != self.peer_id) {
+            let addresses = self.peer_addresses(&provider).await;
+            if addresses.is_empty() {
+                log::debug!("provider没有块交换地址: {}", provider);
+                continue;
+            }
+
+            let data = match self.fetch(provider, addresses, cid).await {
+                Ok(Some(data)) => data,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::warn!("从{}取块失败: {}", provider, e);
+                    continue;
+                }
+            };
+            if !cid_compute::content_matches_cid(&data, cid)? {
+                log::warn!("⚠️ {}返回的内容与CID不符: {}", provider, cid);
+                continue;
+            }
+
+            // 取回后本节点也成为provider
+            self.store.put(cid, &data).await?;
+            self.provide(cid).await;
+            log::info!("📦 从{}取回块: {} ({} 字节)", provider, cid, data.len());
+            return Ok(Some(data));
+        }
+
+        Ok(None)
+    }
+
+    /// 重新宣告本地全部块（provider记录会过期，应定期调用）
+    pub async fn reprovide(&self) -> Result<usize> {
+        let cids = self.store.list().await?;
+        for cid in &cids {
+            self.provide(cid).await;
+        }
+        self.publish_peer_record().await?;
+        Ok(cids.len())
+    }
+
+    /// 宣告provider（本地已保存，失败只记录日志）
+    async fn provide(&self, cid: &str) {
+        if let Err(e) = self.dht.start_providing(block_key(cid)).await {
+            log::warn!("宣告块provider失败 {}: {}", cid, e);
+        }
+    }
+
+    async fn publish_peer_record(&self) -> Result<()> {
+        let record = BlockPeerRecord {
+            peer_id: self.peer_id.to_base58(),
+            addresses: self.listen_addrs.iter().map(ToString::to_string).collect(),
+            published_at: self.clock.now_secs(),
+        };
+        self.dht.put_record(block_peer_key(&self.peer_id), record.to_bytes()?).await
+    }
+
+    /// provider最新发布的块交换地址
+    async fn peer_addresses(&self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        let values = match self.dht.get_records(block_peer_key(peer_id)).await {
+            Ok(values) => values,
+            Err(e) => {
+                log::debug!("获取块交换地址失败 {}: {}", peer_id, e);
+                return Vec::new();
+            }
+        };
+        values.iter()
+            .filter_map(|value| BlockPeerRecord::from_bytes(value).ok())
+            .filter(|record| record.peer_id == peer_id.to_base58())
+            .max_by_key(|record| record.published_at)
+            .map(|record| record.addresses.iter().filter_map(|a| a.parse().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    async fn fetch(&self, peer_id: PeerId, addresses: Vec<Multiaddr>, cid: &str) -> Result<Option<Vec<u8>>> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(ExchangeCommand::Fetch(peer_id, addresses, cid.to_string(), tx))
+            .map_err(|_| anyhow::anyhow!("块交换任务已停止"))?;
+        tokio::time::timeout(self.fetch_timeout, rx).await
+            .map_err(|_| anyhow::anyhow!("取块超时: {}", cid))?
+            .context("块交换任务已停止")?
+    }
+}
+
+#[async_trait]
+impl IpfsBackend for EmbeddedIpfs {
+    fn name(&self) -> &str {
+        "EmbeddedIpfs"
+    }
+
+    async fn add(&self, content: &[u8]) -> Result<String> {
+        self.add_content(content).await
+    }
+
+    async fn get(&self, cid: &str) -> Result<Option<Vec<u8>>> {
+        self.get_content(cid).await
+    }
+}
+
+fn block_protocol() -> Result<StreamProtocol> {
+    StreamProtocol::try_from_owned(network_params().block_protocol()).context("无效的块交换协议名")
+}
+
+fn build_exchange_swarm(identity: &LibP2PIdentity) -> Result<Swarm<request_response::Behaviour<DIAPCodec>>> {
+    let protocol = block_protocol()?;
+    Ok(libp2p::SwarmBuilder::with_existing_identity(identity.keypair().clone())
+        .with_tokio()
+        .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)
+        .context("创建TCP传输失败")?
+        .with_behaviour(|_| request_response::Behaviour::with_codec(
+            DIAPCodec::default(),
+            [(protocol, ProtocolSupport::Full)],
+            request_response::Config::default(),
+        ))
+        .context("创建块交换行为失败")?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+        .build())
+}
+
+/// 块交换事件循环：应答取块请求，并按命令向provider取块；命令通道关闭（EmbeddedIpfs被丢弃）时退出
+async fn run_exchange(
+    mut swarm: Swarm<request_response::Behaviour<DIAPCodec>>,
+    store: BlockStore,
+    mut commands: mpsc::UnboundedReceiver<ExchangeCommand>,
+) {
+    let mut in_flight: HashMap<OutboundRequestId, FetchReply> = HashMap::new();
+    // 等待连接建立后再发出的请求
+    let mut dialing: HashMap<PeerId, Vec<(String, FetchReply)>> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(ExchangeCommand::Fetch(peer_id, addresses, cid, tx)) => {
+                    if swarm.is_connected(&peer_id) {
+                        let id = swarm.behaviour_mut().send_request(&peer_id, cid.into_bytes());
+                        in_flight.insert(id, tx);
+                        continue;
+                    }
+                    let queued = dialing.entry(peer_id).or_default();
+                    queued.push((cid, tx));
+                    if queued.len() > 1 {
+                        continue;
+                    }
+                    let opts = libp2p::swarm::dial_opts::DialOpts::peer_id(peer_id).addresses(addresses).build();
+                    if let Err(e) = swarm.dial(opts) {
+                        for (_, tx) in dialing.remove(&peer_id).unwrap_or_default() {
+                            let _ = tx.send(Err(anyhow::anyhow!("无法拨号{}: {}", peer_id, e)));
+                        }
+                    }
+                }
+                None => break,
+            },
+            event = swarm.select_next_some() => match event {
+                SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                    for (cid, tx) in dialing.remove(&peer_id).unwrap_or_default() {
+                        let id = swarm.behaviour_mut().send_request(&peer_id, cid.into_bytes());
+                        in_flight.insert(id, tx);
+                    }
+                }
+                SwarmEvent::OutgoingConnectionError { peer_id: Some(peer_id), error, .. } => {
+                    for (_, tx) in dialing.remove(&peer_id).unwrap_or_default() {
+                        let _ = tx.send(Err(anyhow::anyhow!("连接{}失败: {}", peer_id, error)));
+                    }
+                }
+                SwarmEvent::Behaviour(request_response::Event::Message { message, .. }) => match message {
+                    request_response::Message::Request { request, channel, .. } => {
+                        serve_block(&mut swarm, &store, &request, channel).await;
+                    }
+                    request_response::Message::Response { request_id, response } => {
+                        if let Some(tx) = in_flight.remove(&request_id) {
+                            let _ = tx.send(decode_block_response(&response));
+                        }
+                    }
+                },
+                SwarmEvent::Behaviour(request_response::Event::OutboundFailure { request_id, error, .. }) => {
+                    if let Some(tx) = in_flight.remove(&request_id) {
+                        let _ = tx.send(Err(anyhow::anyhow!("取块请求失败: {}", error)));
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    log::info!("块交换已停止: {}", swarm.local_peer_id());
+}
+
+async fn serve_block(
+    swarm: &mut Swarm<request_response::Behaviour<DIAPCodec>>,
+    store: &BlockStore,
+    request: &[u8],
+    channel: ResponseChannel<Vec<u8>>,
+) {
+    let block = match std::str::from_utf8(request) {
+        Ok(cid) => store.get(cid).await.unwrap_or_else(|e| {
+            log::debug!("读取请求的块失败: {}", e);
+            None
+        }),
+        Err(_) => None,
+    };
+    let response = match block {
+        Some(data) => {
+            let mut response = Vec::with_capacity(data.len() + 1);
+            response.push(BLOCK_FOUND);
+            response.extend_from_slice(&data);
+            response
+        }
+        None => vec![BLOCK_NOT_FOUND],
+    };
+    if swarm.behaviour_mut().send_response(channel, response).is_err() {
+        log::debug!("取块请求方已断开");
+    }
+}
+
+fn decode_block_response(response: &[u8]) -> Result<Option<Vec<u8>>> {
+    match response.split_first() {
+        Some((&BLOCK_FOUND, data)) => Ok(Some(data.to_vec())),
+        Some((&BLOCK_NOT_FOUND, _)) => Ok(None),
+        _ => anyhow::bail!("无效的块交换响应"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_discovery::KademliaDht;
+
+    #[tokio::test]
+    async fn test_fetch_block_from_provider() {
+        let listen: Multiaddr = "/ip4/127.0.0.1/tcp/0".parse().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let alice_identity = LibP2PIdentity::generate().unwrap();
+        let alice_dht = Arc::new(KademliaDht::start(&alice_identity, listen.clone()).await.unwrap());
+        let bob_identity = LibP2PIdentity::generate().unwrap();
+        let bob_dht = Arc::new(KademliaDht::start(&bob_identity, listen.clone()).await.unwrap());
+        bob_dht.add_peer(*alice_identity.peer_id(), alice_dht.listen_addrs().await.unwrap()[0].clone()).unwrap();
+        alice_dht.add_peer(*bob_identity.peer_id(), bob_dht.listen_addrs().await.unwrap()[0].clone()).unwrap();
+
+        let alice = EmbeddedIpfs::start(&alice_identity, BlockStore::open(dir.path().join("alice")).unwrap(), alice_dht, listen.clone()).await.unwrap();
+        let bob = EmbeddedIpfs::start(&bob_identity, BlockStore::open(dir.path().join("bob")).unwrap(), bob_dht, listen).await.unwrap();
+
+        let content = br#"{"id":"did:key:z6MkEmbedded"}"#;
+        let cid = alice.add_content(content).await.unwrap();
+        assert!(cid_compute::content_matches_cid(content, &cid).unwrap());
+
+        assert_eq!(bob.get_content(&cid).await.unwrap().as_deref(), Some(&content[..]));
+        assert!(bob.block_store().contains(&cid).await);
+
+        let missing = cid_compute::compute_content_cid(b"missing", &CidOptions::default()).unwrap();
+        assert!(bob.get_content(&missing).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,316 @@
+// DIAP Rust SDK - 统一错误类型
+// 面向库使用者的结构化错误：按失败类别（IPFS、ZKP、DID、P2P、认证）区分，
+// 调用方可以通过match区分例如nonce重放和IPFS超时，底层错误作为source保留
+
+use reqwest::StatusCode;
+use std::fmt;
+
+use crate::ipfs_client::HttpStatusError;
+
+/// 底层错误
+pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// 使用DiapError的Result
+pub type DiapResult<T> = std::result::Result<T, DiapError>;
+
+/// IPFS失败原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpfsErrorKind {
+    /// 请求超时
+    Timeout,
+
+    /// 无法连接到节点或网关
+    Unreachable,
+
+    /// 内容不存在
+    NotFound,
+
+    /// 节点或网关返回的HTTP错误状态
+    Http(u16),
+
+    /// 未配置可用的IPFS节点或Pin服务
+    NotConfigured,
+
+    /// 其他错误
+    Other,
+}
+
+impl fmt::Display for IpfsErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpfsErrorKind::Timeout => f.write_str("超时"),
+            IpfsErrorKind::Unreachable => f.write_str("无法连接"),
+            IpfsErrorKind::NotFound => f.write_str("内容不存在"),
+            IpfsErrorKind::Http(status) => write!(f, "HTTP {}", status),
+            IpfsErrorKind::NotConfigured => f.write_str("未配置"),
+            IpfsErrorKind::Other => f.write_str("其他"),
+        }
+    }
+}
+
+/// 认证失败原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthErrorKind {
+    /// nonce已被使用（重放攻击）
+    NonceReplay,
+
+    /// nonce已过期或时间戳在未来
+    NonceExpired,
+
+    /// nonce格式错误
+    InvalidNonce,
+
+    /// 签名无效
+    InvalidSignature,
+
+    /// 主题策略拒绝
+    Unauthorized,
+
+    /// 未设置本地身份
+    MissingIdentity,
+
+    /// nonce无法持久化（存储不可用时拒绝消息，避免重启后被重放）
+    NonceStoreUnavailable,
+
+    /// DID已被吊销
+    Revoked,
+}
+
+impl fmt::Display for AuthErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            AuthErrorKind::NonceReplay => "nonce重放",
+            AuthErrorKind::NonceExpired => "nonce过期",
+            AuthErrorKind::InvalidNonce => "nonce格式错误",
+            AuthErrorKind::InvalidSignature => "签名无效",
+            AuthErrorKind::Unauthorized => "未授权",
+            AuthErrorKind::MissingIdentity => "缺少本地身份",
+            AuthErrorKind::NonceStoreUnavailable => "nonce存储不可用",
+            AuthErrorKind::Revoked => "DID已吊销",
+        };
+        f.write_str(text)
+    }
+}
+
+/// DIAP错误
+#[derive(Debug, thiserror::Error)]
+pub enum DiapError {
+    /// IPFS上传、获取、Pin失败
+    #[error("IPFS错误({kind}): {message}")]
+    IpfsError {
+        kind: IpfsErrorKind,
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// 零知识证明生成或验证失败
+    #[error("ZKP错误: {message}")]
+    ZkpError {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// DID解析、构建或文档校验失败
+    #[error("DID错误: {message}")]
+    DidError {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// P2P网络失败
+    #[error("P2P错误: {message}")]
+    P2pError {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// 认证失败
+    #[error("认证错误({kind}): {message}")]
+    AuthError {
+        kind: AuthErrorKind,
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+}
+
+impl DiapError {
+    /// IPFS错误
+    pub fn ipfs(kind: IpfsErrorKind, message: impl Into<String>) -> Self {
+        DiapError::IpfsError { kind, message: message.into(), source: None }
+    }
+
+    /// 包装底层错误为IPFS错误，并根据错误链判断失败原因
+    pub fn from_ipfs(error: anyhow::Error) -> Self {
+        if let Some(existing) = Self::find_in_chain(&error) {
+            return existing;
+        }
+        DiapError::IpfsError {
+            kind: classify_ipfs(&error),
+            message: error.to_string(),
+            source: Some(error.into()),
+        }
+    }
+
+    /// 包装底层错误为ZKP错误
+    pub fn from_zkp(error: anyhow::Error) -> Self {
+        if let Some(existing) = Self::find_in_chain(&error) {
+            return existing;
+        }
+        DiapError::ZkpError { message: error.to_string(), source: Some(error.into()) }
+    }
+
+    /// DID错误
+    pub fn did(message: impl Into<String>) -> Self {
+        DiapError::DidError { message: message.into(), source: None }
+    }
+
+    /// 包装底层错误为DID错误（错误链中已有DiapError时保留其类别，例如IPFS超时）
+    pub fn from_did(error: anyhow::Error) -> Self {
+        if let Some(existing) = Self::find_in_chain(&error) {
+            return existing;
+        }
+        DiapError::DidError { message: error.to_string(), source: Some(error.into()) }
+    }
+
+    /// P2P错误
+    pub fn p2p(message: impl Into<String>) -> Self {
+        DiapError::P2pError { message: message.into(), source: None }
+    }
+
+    /// 包装底层错误为P2P错误
+    pub fn from_p2p(error: anyhow::Error) -> Self {
+        if let Some(existing) = Self::find_in_chain(&error) {
+            return existing;
+        }
+        DiapError::P2pError { message: error.to_string(), source: Some(error.into()) }
+    }
+
+    /// 认证错误
+    pub fn auth(kind: AuthErrorKind, message: impl Into<String>) -> Self {
+        DiapError::AuthError { kind, message: message.into(), source: None }
+    }
+
+    /// IPFS失败原因
+    pub fn ipfs_kind(&self) -> Option<IpfsErrorKind> {
+        match self {
+            DiapError::IpfsError { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+
+    /// 认证失败原因
+    pub fn auth_kind(&self) -> Option<AuthErrorKind> {
+        match self {
+            DiapError::AuthError { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+
+    /// 是否为nonce重放
+    pub fn is_nonce_replay(&self) -> bool {
+        self.auth_kind() == Some(AuthErrorKind::NonceReplay)
+    }
+
+    /// 是否为超时
+    pub fn is_timeout(&self) -> bool {
+        self.ipfs_kind() == Some(IpfsErrorKind::Timeout)
+    }
+
+    /// 是否为暂时性错误（值得稍后重试）
+    pub fn is_transient(&self) -> bool {
+        match self.ipfs_kind() {
+            Some(IpfsErrorKind::Timeout) | Some(IpfsErrorKind::Unreachable) => true,
+            Some(IpfsErrorKind::Http(status)) => status >= 500 || status == 408 || status == 429,
+            _ => matches!(self, DiapError::P2pError { .. }),
+        }
+    }
+
+    /// 错误链中已有的DiapError（例如DID解析时底层的IPFS错误），避免重复包装后丢失类别
+    fn find_in_chain(error: &anyhow::Error) -> Option<Self> {
+        let existing = error.chain().find_map(|cause| cause.downcast_ref::<DiapError>())?;
+        // 保留原类别，外层上下文并入消息
+        let message = format!("{:#}", error);
+        Some(match existing {
+            DiapError::IpfsError { kind, .. } => DiapError::IpfsError { kind: *kind, message, source: None },
+            DiapError::ZkpError { .. } => DiapError::ZkpError { message, source: None },
+            DiapError::DidError { .. } => DiapError::DidError { message, source: None },
+            DiapError::P2pError { .. } => DiapError::P2pError { message, source: None },
+            DiapError::AuthError { kind, .. } => DiapError::AuthError { kind: *kind, message, source: None },
+        })
+    }
+}
+
+/// 根据错误链判断IPFS失败原因
+fn classify_ipfs(error: &anyhow::Error) -> IpfsErrorKind {
+    for cause in error.chain() {
+        if let Some(status_error) = cause.downcast_ref::<HttpStatusError>() {
+            return classify_status(status_error.status());
+        }
+        if let Some(reqwest_error) = cause.downcast_ref::<reqwest::Error>() {
+            if reqwest_error.is_timeout() {
+                return IpfsErrorKind::Timeout;
+            }
+            if let Some(status) = reqwest_error.status() {
+                return classify_status(status);
+            }
+            if reqwest_error.is_connect() {
+                return IpfsErrorKind::Unreachable;
+            }
+        }
+        if let Some(io_error) = cause.downcast_ref::<std::io::Error>() {
+            if io_error.kind() == std::io::ErrorKind::TimedOut {
+                return IpfsErrorKind::Timeout;
+            }
+        }
+    }
+    IpfsErrorKind::Other
+}
+
+fn classify_status(status: StatusCode) -> IpfsErrorKind {
+    match status {
+        StatusCode::NOT_FOUND => IpfsErrorKind::NotFound,
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => IpfsErrorKind::Timeout,
+        status => IpfsErrorKind::Http(status.as_u16()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_ipfs_errors() {
+        let not_found = HttpStatusError::error(StatusCode::NOT_FOUND, "网关返回错误".to_string());
+        let error = DiapError::from_ipfs(not_found.context("无法从任何网关获取内容"));
+        assert_eq!(error.ipfs_kind(), Some(IpfsErrorKind::NotFound));
+        assert!(!error.is_transient());
+        assert!(std::error::Error::source(&error).is_some());
+
+        let unavailable = HttpStatusError::error(StatusCode::SERVICE_UNAVAILABLE, "网关返回错误".to_string());
+        let error = DiapError::from_ipfs(unavailable);
+        assert_eq!(error.ipfs_kind(), Some(IpfsErrorKind::Http(503)));
+        assert!(error.is_transient());
+
+        let timeout = anyhow::Error::new(std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"));
+        assert!(DiapError::from_ipfs(timeout).is_timeout());
+    }
+
+    #[test]
+    fn test_category_preserved_through_anyhow() {
+        // 内部以anyhow传递的DiapError在重新包装时保留原类别
+        let replay: anyhow::Error = DiapError::auth(AuthErrorKind::NonceReplay, "nonce已被使用").into();
+        let wrapped = DiapError::from_did(replay.context("验证失败"));
+        assert!(wrapped.is_nonce_replay());
+        assert!(wrapped.to_string().contains("验证失败"));
+
+        let timeout: anyhow::Error = DiapError::ipfs(IpfsErrorKind::Timeout, "请求超时").into();
+        let wrapped = DiapError::from_did(timeout);
+        assert!(wrapped.is_timeout());
+        assert!(!wrapped.is_nonce_replay());
+    }
+}
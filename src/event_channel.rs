@@ -0,0 +1,290 @@
+// DIAP Rust SDK - 有界事件通道
+// 通信器收到的消息和信号经内部通道交给应用；无界通道在消息洪泛时会耗尽内存，
+// 这里改为有界队列，队列满时按配置的溢出策略处理，并统计队列深度等指标
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// 默认通道容量
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 队列满时的处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// 丢弃最旧的事件，为新事件腾出位置（默认，适合实时性优先的场景）
+    #[default]
+    DropOldest,
+    /// 发送方等待，直到接收方取走事件（向网络层传导背压）
+    Block,
+    /// 拒绝新事件，发送方收到错误
+    Error,
+}
+
+/// 通道错误
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ChannelError {
+    #[error("事件队列已满（容量 {capacity}）")]
+    Full { capacity: usize },
+
+    #[error("事件通道已关闭")]
+    Closed,
+}
+
+/// 通道指标快照
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelMetrics {
+    /// 容量
+    pub capacity: usize,
+
+    /// 当前队列深度
+    pub depth: usize,
+
+    /// 历史最大深度
+    pub high_watermark: usize,
+
+    /// 累计入队数
+    pub enqueued: u64,
+
+    /// 因DropOldest丢弃的事件数
+    pub dropped: u64,
+
+    /// 因Error策略拒绝的事件数
+    pub rejected: u64,
+}
+
+struct ChannelState<T> {
+    queue: VecDeque<T>,
+    metrics: ChannelMetrics,
+    senders: usize,
+    receiver_alive: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<ChannelState<T>>,
+    policy: OverflowPolicy,
+    /// 有新事件或发送方全部关闭
+    items: Notify,
+    /// 有空位或接收方关闭
+    space: Notify,
+}
+
+/// 创建有界事件通道（容量至少为1）
+pub fn bounded_event_channel<T>(capacity: usize, policy: OverflowPolicy) -> (EventSender<T>, EventReceiver<T>) {
+    let capacity = capacity.max(1);
+    let shared = Arc::new(Shared {
+        state: Mutex::new(ChannelState {
+            queue: VecDeque::with_capacity(capacity.min(DEFAULT_CHANNEL_CAPACITY)),
+            metrics: ChannelMetrics { capacity, ..Default::default() },
+            senders: 1,
+            receiver_alive: true,
+        }),
+        policy,
+        items: Notify::new(),
+        space: Notify::new(),
+    });
+    (EventSender { shared: shared.clone() }, EventReceiver { shared })
+}
+
+/// 事件发送端（可克隆）
+pub struct EventSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> EventSender<T> {
+    /// 发送事件；Block策略下队列满时等待，其他策略立即返回
+    pub async fn send(&self, item: T) -> Result<(), ChannelError> {
+        let mut item = item;
+        loop {
+            let space = self.shared.space.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+
+            match self.push(item) {
+                Err((returned, ChannelError::Full { .. })) if self.shared.policy == OverflowPolicy::Block => {
+                    item = returned;
+                    space.await;
+                }
+                result => return result.map_err(|(_, e)| e),
+            }
+        }
+    }
+
+    /// 不等待地发送事件；Block策略下队列满时返回Full
+    pub fn try_send(&self, item: T) -> Result<(), ChannelError> {
+        self.push(item).map_err(|(_, e)| e)
+    }
+
+    /// 通道指标
+    pub fn metrics(&self) -> ChannelMetrics {
+        self.shared.state.lock().unwrap().metrics.clone()
+    }
+
+    /// 接收方是否已关闭
+    pub fn is_closed(&self) -> bool {
+        !self.shared.state.lock().unwrap().receiver_alive
+    }
+
+    fn push(&self, item: T) -> Result<(), (T, ChannelError)> {
+        let mut state = self.shared.state.lock().unwrap();
+        if !state.receiver_alive {
+            return Err((item, ChannelError::Closed));
+        }
+        let capacity = state.metrics.capacity;
+        if state.queue.len() >= capacity {
+            match self.shared.policy {
+                OverflowPolicy::DropOldest => {
+                    state.queue.pop_front();
+                    state.metrics.dropped += 1;
+                }
+                OverflowPolicy::Block => return Err((item, ChannelError::Full { capacity })),
+                OverflowPolicy::Error => {
+                    state.metrics.rejected += 1;
+                    return Err((item, ChannelError::Full { capacity }));
+                }
+            }
+        }
+        state.queue.push_back(item);
+        state.metrics.enqueued += 1;
+        state.metrics.depth = state.queue.len();
+        state.metrics.high_watermark = state.metrics.high_watermark.max(state.metrics.depth);
+        drop(state);
+        self.shared.items.notify_one();
+        Ok(())
+    }
+}
+
+impl<T> Clone for EventSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        Self { shared: self.shared.clone() }
+    }
+}
+
+impl<T> Drop for EventSender<T> {
+    fn drop(&mut self) {
+        let last = {
+            let mut state = self.shared.state.lock().unwrap();
+            state.senders -= 1;
+            state.senders == 0
+        };
+        if last {
+            self.shared.items.notify_one();
+        }
+    }
+}
+
+/// 事件接收端
+pub struct EventReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> EventReceiver<T> {
+    /// 接收事件；队列为空且所有发送端已关闭时返回None
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            let items = self.shared.items.notified();
+            tokio::pin!(items);
+            items.as_mut().enable();
+
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                if let Some(item) = state.queue.pop_front() {
+                    state.metrics.depth = state.queue.len();
+                    drop(state);
+                    self.shared.space.notify_one();
+                    return Some(item);
+                }
+                if state.senders == 0 {
+                    return None;
+                }
+            }
+            items.await;
+        }
+    }
+
+    /// 不等待地接收事件
+    pub fn try_recv(&mut self) -> Option<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        let item = state.queue.pop_front();
+        state.metrics.depth = state.queue.len();
+        drop(state);
+        if item.is_some() {
+            self.shared.space.notify_one();
+        }
+        item
+    }
+
+    /// 当前队列深度
+    pub fn depth(&self) -> usize {
+        self.shared.state.lock().unwrap().queue.len()
+    }
+
+    /// 通道指标
+    pub fn metrics(&self) -> ChannelMetrics {
+        self.shared.state.lock().unwrap().metrics.clone()
+    }
+}
+
+impl<T> Drop for EventReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().receiver_alive = false;
+        self.shared.space.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_overflow_policies() {
+        let (sender, mut receiver) = bounded_event_channel(2, OverflowPolicy::DropOldest);
+        for i in 0..5 {
+            sender.send(i).await.unwrap();
+        }
+        let metrics = receiver.metrics();
+        assert_eq!((metrics.depth, metrics.high_watermark, metrics.dropped, metrics.enqueued), (2, 2, 3, 5));
+        assert_eq!(receiver.recv().await, Some(3));
+        assert_eq!(receiver.recv().await, Some(4));
+
+        let (sender, mut receiver) = bounded_event_channel(1, OverflowPolicy::Error);
+        sender.send(1).await.unwrap();
+        assert_eq!(sender.send(2).await, Err(ChannelError::Full { capacity: 1 }));
+        assert_eq!(receiver.metrics().rejected, 1);
+        assert_eq!(receiver.try_recv(), Some(1));
+        drop(receiver);
+        assert_eq!(sender.try_send(3), Err(ChannelError::Closed));
+
+        // Block策略下发送方等待接收方取走事件
+        let (sender, mut receiver) = bounded_event_channel(1, OverflowPolicy::Block);
+        sender.send(1).await.unwrap();
+        assert_eq!(sender.try_send(2), Err(ChannelError::Full { capacity: 1 }));
+        let blocked = tokio::spawn({
+            let sender = sender.clone();
+            async move { sender.send(2).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!blocked.is_finished());
+        assert_eq!(receiver.recv().await, Some(1));
+        blocked.await.unwrap().unwrap();
+        assert_eq!(receiver.recv().await, Some(2));
+
+        // 所有发送端关闭后接收端结束
+        drop(sender);
+        assert_eq!(receiver.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_blocked_sender_released_when_receiver_dropped() {
+        let (sender, receiver) = bounded_event_channel(1, OverflowPolicy::Block);
+        sender.send(1).await.unwrap();
+        let blocked = tokio::spawn(async move { sender.send(2).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(receiver);
+        assert_eq!(blocked.await.unwrap(), Err(ChannelError::Closed));
+    }
+}
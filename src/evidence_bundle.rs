@@ -0,0 +1,473 @@
+// DIAP Rust SDK - 验证证据包
+// 把一次智能体交互涉及的DID文档、CID、证明、签名、nonce、时间戳和验证日志打包为一个签名归档，
+// 交给审计方或第三方后，对方无需访问IPFS即可独立复核这次交互
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::cid_compute;
+use crate::did_builder::DIDDocument;
+use crate::did_cache::DIDCache;
+use crate::identity_manager::IdentityVerification;
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::nonce_manager::{NonceManager, NonceRecord};
+use crate::pubsub_authenticator::{AuthenticatedMessage, MessageVerification};
+
+/// 证据包格式版本
+pub const EVIDENCE_BUNDLE_VERSION: u32 = 1;
+
+/// 证据包中的DID文档及其CID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceDocument {
+    /// DID文档CID
+    pub cid: String,
+
+    /// DID文档
+    pub document: DIDDocument,
+}
+
+/// 证据包中的消息及本地验证结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceMessage {
+    /// 原始认证消息（包含签名、nonce、时间戳和ZKP证明）
+    pub message: AuthenticatedMessage,
+
+    /// 收集方当时的验证结果
+    pub verification: Option<MessageVerification>,
+}
+
+/// 证据包中的身份证明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityProofRecord {
+    /// 智能体DID
+    pub did: String,
+
+    /// DID文档CID
+    pub cid: String,
+
+    /// 证明使用的nonce
+    pub nonce: String,
+
+    /// ZKP证明（base64）
+    pub proof: String,
+
+    /// 收集方当时的验证结果
+    pub zkp_verified: bool,
+
+    /// 收集方当时的验证详情
+    pub verification_details: Vec<String>,
+}
+
+/// 签名的验证证据包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceBundle {
+    /// 格式版本
+    pub version: u32,
+
+    /// 交互标识（由应用指定，例如任务ID或会话ID）
+    pub interaction_id: String,
+
+    /// 导出方DID
+    pub exported_by: String,
+
+    /// 导出时间
+    pub exported_at: String,
+
+    /// 涉及的DID文档
+    pub documents: Vec<EvidenceDocument>,
+
+    /// 交互中的消息
+    pub messages: Vec<EvidenceMessage>,
+
+    /// 身份证明
+    pub identity_proofs: Vec<IdentityProofRecord>,
+
+    /// 收集方记录的nonce
+    pub nonces: Vec<NonceRecord>,
+
+    /// 验证日志
+    pub verification_log: Vec<String>,
+
+    /// 导出方签名（base64）
+    pub signature: String,
+}
+
+/// 单项复核结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvidenceCheck {
+    /// 复核对象（CID或消息ID）
+    pub subject: String,
+
+    /// 是否通过
+    pub passed: bool,
+
+    /// 说明
+    pub detail: String,
+}
+
+/// 证据包复核报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceReport {
+    /// 证据包签名是否有效
+    pub signature_valid: bool,
+
+    /// 各项复核结果
+    pub checks: Vec<EvidenceCheck>,
+}
+
+impl EvidenceReport {
+    /// 签名有效且所有复核项都通过
+    pub fn all_passed(&self) -> bool {
+        self.signature_valid && self.checks.iter().all(|check| check.passed)
+    }
+
+    /// 未通过的复核项
+    pub fn failures(&self) -> Vec<&EvidenceCheck> {
+        self.checks.iter().filter(|check| !check.passed).collect()
+    }
+
+    fn record(&mut self, subject: &str, passed: bool, detail: impl Into<String>) {
+        self.checks.push(EvidenceCheck {
+            subject: subject.to_string(),
+            passed,
+            detail: detail.into(),
+        });
+    }
+}
+
+impl EvidenceBundle {
+    /// 按CID查找DID文档
+    pub fn document(&self, cid: &str) -> Option<&DIDDocument> {
+        self.documents.iter().find(|entry| entry.cid == cid).map(|entry| &entry.document)
+    }
+
+    /// 独立复核：证据包签名、文档与CID一致、消息签名与DID文档中的公钥一致、nonce归属
+    /// ZKP证明随证据包提供，需要使用对应电路的验证器另行验证
+    pub fn reverify(&self) -> Result<EvidenceReport> {
+        let mut report = EvidenceReport {
+            signature_valid: self.verify()?,
+            checks: Vec::new(),
+        };
+
+        for entry in &self.documents {
+            match cid_compute::document_matches_cid(&entry.document, &entry.cid) {
+                Ok(true) => report.record(&entry.cid, true, "DID文档与CID一致"),
+                Ok(false) => report.record(&entry.cid, false, "DID文档与CID不一致"),
+                Err(e) => report.record(&entry.cid, false, format!("计算CID失败: {}", e)),
+            }
+        }
+
+        for entry in &self.messages {
+            let message = &entry.message;
+            let subject = &message.message_id;
+            let Some(document) = self.document(&message.did_cid) else {
+                report.record(subject, false, format!("缺少DID文档: {}", message.did_cid));
+                continue;
+            };
+            if document.id != message.from_did {
+                report.record(subject, false, format!("DID文档属于{}，与发送者不符", document.id));
+                continue;
+            }
+            match verify_message_signature(document, message) {
+                Ok(true) => report.record(subject, true, "消息签名有效"),
+                Ok(false) => report.record(subject, false, "消息签名无效"),
+                Err(e) => report.record(subject, false, format!("无法验证消息签名: {}", e)),
+            }
+            if let Some(record) = self.nonces.iter().find(|record| record.nonce == message.nonce) {
+                report.record(subject, record.did == message.from_did, format!("nonce记录于{}", record.used_at));
+            }
+        }
+
+        for proof in &self.identity_proofs {
+            let passed = self.document(&proof.cid).is_some_and(|document| document.id == proof.did);
+            report.record(
+                &proof.cid,
+                passed,
+                if passed { "身份证明对应的DID文档已包含" } else { "缺少身份证明对应的DID文档" },
+            );
+        }
+
+        log::info!(
+            "🔍 复核证据包 {}: {}项检查，{}项未通过",
+            self.interaction_id,
+            report.checks.len(),
+            report.failures().len()
+        );
+        Ok(report)
+    }
+
+    /// 序列化为归档
+    pub fn to_archive(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).context("序列化证据包失败")
+    }
+
+    /// 从归档解析
+    pub fn from_archive(data: &[u8]) -> Result<Self> {
+        let bundle: Self = serde_json::from_slice(data).context("解析证据包失败")?;
+        if bundle.version > EVIDENCE_BUNDLE_VERSION {
+            anyhow::bail!("不支持的证据包版本: {}", bundle.version);
+        }
+        Ok(bundle)
+    }
+
+    /// 保存归档到文件
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path.as_ref(), self.to_archive()?)
+            .with_context(|| format!("写入证据包失败: {}", path.as_ref().display()))
+    }
+
+    /// 从文件加载归档
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let data = std::fs::read(path.as_ref())
+            .with_context(|| format!("读取证据包失败: {}", path.as_ref().display()))?;
+        Self::from_archive(&data)
+    }
+}
+
+impl SignedEnvelope for EvidenceBundle {
+    const KIND: &'static str = "证据包";
+
+    fn signer_did(&self) -> &str {
+        &self.exported_by
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 证据收集器：逐条加入交互数据，最后签名导出
+pub struct EvidenceCollector {
+    interaction_id: String,
+    documents: BTreeMap<String, DIDDocument>,
+    messages: Vec<EvidenceMessage>,
+    identity_proofs: Vec<IdentityProofRecord>,
+    nonces: Vec<NonceRecord>,
+    verification_log: Vec<String>,
+
+    /// 从缓存补全消息引用的DID文档
+    did_cache: Option<DIDCache>,
+
+    /// 从Nonce管理器补全nonce记录
+    nonce_manager: Option<NonceManager>,
+}
+
+impl EvidenceCollector {
+    /// 为一次交互创建收集器
+    pub fn new(interaction_id: impl Into<String>) -> Self {
+        Self {
+            interaction_id: interaction_id.into(),
+            documents: BTreeMap::new(),
+            messages: Vec::new(),
+            identity_proofs: Vec::new(),
+            nonces: Vec::new(),
+            verification_log: Vec::new(),
+            did_cache: None,
+            nonce_manager: None,
+        }
+    }
+
+    /// 从DID文档缓存补全文档
+    pub fn with_did_cache(mut self, did_cache: DIDCache) -> Self {
+        self.did_cache = Some(did_cache);
+        self
+    }
+
+    /// 从Nonce管理器补全nonce记录
+    pub fn with_nonce_manager(mut self, nonce_manager: NonceManager) -> Self {
+        self.nonce_manager = Some(nonce_manager);
+        self
+    }
+
+    /// 加入DID文档
+    pub fn add_document(&mut self, cid: &str, document: DIDDocument) -> &mut Self {
+        self.documents.insert(cid.to_string(), document);
+        self
+    }
+
+    /// 加入消息及其验证结果
+    pub fn add_message(&mut self, message: &AuthenticatedMessage, verification: Option<&MessageVerification>) -> &mut Self {
+        self.fill_document(&message.did_cid);
+        self.fill_nonce(&message.nonce);
+        if let Some(verification) = verification {
+            self.verification_log.extend(
+                verification.details.iter().map(|detail| format!("[{}] {}", message.message_id, detail))
+            );
+        }
+        self.messages.push(EvidenceMessage {
+            message: message.clone(),
+            verification: verification.cloned(),
+        });
+        self
+    }
+
+    /// 加入身份证明及其验证结果
+    pub fn add_identity_proof(&mut self, verification: &IdentityVerification, proof: &[u8], nonce: &[u8]) -> &mut Self {
+        self.fill_document(&verification.cid);
+        self.verification_log.extend(
+            verification.verification_details.iter().map(|detail| format!("[{}] {}", verification.cid, detail))
+        );
+        self.identity_proofs.push(IdentityProofRecord {
+            did: verification.did.clone(),
+            cid: verification.cid.clone(),
+            nonce: String::from_utf8_lossy(nonce).into_owned(),
+            proof: general_purpose::STANDARD.encode(proof),
+            zkp_verified: verification.zkp_verified,
+            verification_details: verification.verification_details.clone(),
+        });
+        self
+    }
+
+    /// 追加一条验证日志
+    pub fn log(&mut self, entry: impl Into<String>) -> &mut Self {
+        self.verification_log.push(entry.into());
+        self
+    }
+
+    /// 签名并生成证据包
+    pub fn finish(&self, signer: &dyn Signer) -> Result<EvidenceBundle> {
+        let bundle = EvidenceBundle {
+            version: EVIDENCE_BUNDLE_VERSION,
+            interaction_id: self.interaction_id.clone(),
+            exported_by: signer.did(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            documents: self.documents.iter()
+                .map(|(cid, document)| EvidenceDocument { cid: cid.clone(), document: document.clone() })
+                .collect(),
+            messages: self.messages.clone(),
+            identity_proofs: self.identity_proofs.clone(),
+            nonces: self.nonces.clone(),
+            verification_log: self.verification_log.clone(),
+            signature: String::new(),
+        }
+        .signed_by(signer)?;
+
+        log::info!(
+            "📦 生成证据包 {}: {}个文档，{}条消息，{}个身份证明",
+            bundle.interaction_id,
+            bundle.documents.len(),
+            bundle.messages.len(),
+            bundle.identity_proofs.len()
+        );
+        Ok(bundle)
+    }
+
+    fn fill_document(&mut self, cid: &str) {
+        if self.documents.contains_key(cid) {
+            return;
+        }
+        if let Some(document) = self.did_cache.as_ref().and_then(|cache| cache.get(cid)) {
+            self.documents.insert(cid.to_string(), document);
+        }
+    }
+
+    fn fill_nonce(&mut self, nonce: &str) {
+        if self.nonces.iter().any(|record| record.nonce == nonce) {
+            return;
+        }
+        if let Some(record) = self.nonce_manager.as_ref().and_then(|manager| manager.get_record(nonce)) {
+            self.nonces.push(record);
+        }
+    }
+}
+
+/// 用DID文档中的主公钥验证消息签名
+fn verify_message_signature(document: &DIDDocument, message: &AuthenticatedMessage) -> Result<bool> {
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    let method = document.verification_method.first()
+        .ok_or_else(|| anyhow::anyhow!("DID文档缺少验证方法"))?;
+    let public_key = bs58::decode(method.public_key_multibase.trim_start_matches('z'))
+        .into_vec()
+        .context("解码公钥失败")?;
+    let key_bytes: [u8; 32] = public_key[public_key.len().saturating_sub(32)..]
+        .try_into()
+        .context("公钥长度错误")?;
+    let verifying_key = VerifyingKey::from_bytes(&key_bytes).context("无效的公钥")?;
+    let Ok(signature) = <[u8; 64]>::try_from(message.signature.as_slice()) else {
+        return Ok(false);
+    };
+    Ok(verifying_key.verify(&message.signing_data(), &Signature::from_bytes(&signature)).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::did_resolver::DIDResolver;
+    use crate::pubsub_authenticator::PubSubMessageType;
+
+    fn signed_message(keypair: &KeyPair, cid: &str, nonce: &str) -> AuthenticatedMessage {
+        let mut message = AuthenticatedMessage {
+            message_id: format!("msg-{}", nonce),
+            message_type: PubSubMessageType::Custom("task".to_string()),
+            from_did: keypair.did.clone(),
+            to_did: None,
+            from_peer_id: String::new(),
+            did_cid: cid.to_string(),
+            topic: "tasks".to_string(),
+            content: b"run job 42".to_vec(),
+            nonce: nonce.to_string(),
+            zkp_proof: vec![1, 2, 3],
+            signature: Vec::new(),
+            timestamp: 1_000,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        };
+        message.signature = keypair.sign(&message.signing_data()).unwrap();
+        message
+    }
+
+    #[tokio::test]
+    async fn test_bundle_roundtrip_and_reverify() {
+        let agent = KeyPair::generate().unwrap();
+        let collector_key = KeyPair::generate().unwrap();
+        let document = DIDResolver::resolve_did_key(&agent.did).unwrap();
+        let cid = cid_compute::compute_cid(&document).unwrap();
+
+        let cache = DIDCache::new(None, None);
+        cache.put(cid.clone(), document).unwrap();
+        let nonces = NonceManager::new(None, None);
+        let nonce = NonceManager::generate_nonce();
+        nonces.verify_and_record(&nonce, &agent.did).unwrap();
+
+        let message = signed_message(&agent, &cid, &nonce);
+        let mut collector = EvidenceCollector::new("task-42")
+            .with_did_cache(cache)
+            .with_nonce_manager(nonces);
+        collector.add_message(&message, None).log("任务完成");
+        let bundle = collector.finish(&collector_key).unwrap();
+        assert_eq!(bundle.documents.len(), 1);
+        assert_eq!(bundle.nonces.len(), 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("evidence.json");
+        bundle.save(&path).unwrap();
+        let loaded = EvidenceBundle::load(&path).unwrap();
+        let report = loaded.reverify().unwrap();
+        assert!(report.all_passed(), "{:?}", report.failures());
+        assert_eq!(report.checks.len(), 3);
+
+        // 篡改消息内容后，证据包签名和消息签名都不再成立
+        let mut tampered = loaded.clone();
+        tampered.messages[0].message.content = b"run job 43".to_vec();
+        let report = tampered.reverify().unwrap();
+        assert!(!report.signature_valid);
+        assert_eq!(report.failures().len(), 1);
+
+        // 缺少DID文档的消息无法复核
+        let mut incomplete = EvidenceCollector::new("task-43");
+        incomplete.add_message(&signed_message(&agent, "QmMissing", "n-1"), None);
+        let report = incomplete.finish(&collector_key).unwrap().reverify().unwrap();
+        assert!(report.signature_valid);
+        assert!(!report.all_passed());
+    }
+}
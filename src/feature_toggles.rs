@@ -0,0 +1,439 @@
+// DIAP Rust SDK - 远程功能开关模块
+// 运维DID通过签名的控制消息远程开启/关闭已登记智能体上开销较大的子系统
+// （例如事故期间关闭逐条消息的ZKP，改用安全会话）；智能体按本地允许策略决定是否接受，
+// 每条开关命令无论接受与否都写入审计日志
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::clock::{SharedClock, system_clock};
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::SignedMessage;
+
+/// 功能开关消息类型标识（PubSubMessageType::Custom）
+pub const FEATURE_TOGGLE_MESSAGE_TYPE: &str = "feature_toggle";
+
+/// 内存中保留的审计记录数
+pub const MAX_AUDIT_ENTRIES: usize = 1000;
+
+/// 可远程开关的子系统
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SdkFeature {
+    /// 为每条发出的消息生成ZKP证明，并验证收到消息的ZKP证明
+    ZkpPerMessage,
+
+    /// 使用DHT验证提示
+    VerificationHints,
+
+    /// 消息归档
+    MessageArchive,
+
+    /// 应用自定义的子系统
+    Custom(String),
+}
+
+impl std::fmt::Display for SdkFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SdkFeature::Custom(name) => f.write_str(name),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// 签名的开关命令
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToggleCommand {
+    /// 运维DID
+    pub operator_did: String,
+
+    /// 目标子系统
+    pub feature: SdkFeature,
+
+    /// 开启或关闭
+    pub enabled: bool,
+
+    /// 原因（写入审计日志）
+    pub reason: String,
+
+    /// 签发时间（毫秒，同一运维DID对同一子系统的命令必须递增，防止重放旧命令）
+    pub issued_at: u64,
+
+    /// 自动恢复默认状态的时间（毫秒，None表示一直生效）
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+
+    /// 运维签名（base64）
+    pub signature: String,
+}
+
+impl ToggleCommand {
+    /// 签发开关命令
+    pub fn sign(
+        signer: &dyn Signer,
+        feature: SdkFeature,
+        enabled: bool,
+        reason: &str,
+        issued_at: u64,
+        expires_at: Option<u64>,
+    ) -> Result<Self> {
+        if expires_at.is_some_and(|expires_at| expires_at <= issued_at) {
+            anyhow::bail!("开关命令的恢复时间必须晚于签发时间");
+        }
+        Self {
+            operator_did: signer.did(),
+            feature,
+            enabled,
+            reason: reason.to_string(),
+            issued_at,
+            expires_at,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+}
+
+impl SignedEnvelope for ToggleCommand {
+    const KIND: &'static str = "开关命令";
+
+    fn signer_did(&self) -> &str {
+        &self.operator_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+impl SignedMessage for ToggleCommand {
+    const MESSAGE_TYPE: &'static str = FEATURE_TOGGLE_MESSAGE_TYPE;
+}
+
+/// 本地允许策略：哪些运维DID可以开关哪些子系统
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TogglePolicy {
+    /// 运维DID -> 允许开关的子系统
+    #[serde(default)]
+    pub operators: HashMap<String, HashSet<SdkFeature>>,
+
+    /// 不允许远程关闭的子系统（仍可远程开启）
+    #[serde(default)]
+    pub pinned_on: HashSet<SdkFeature>,
+}
+
+impl TogglePolicy {
+    /// 创建空策略（拒绝所有远程命令）
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 允许运维DID开关指定子系统
+    pub fn allow(mut self, operator_did: &str, features: &[SdkFeature]) -> Self {
+        self.operators.entry(operator_did.to_string())
+            .or_default()
+            .extend(features.iter().cloned());
+        self
+    }
+
+    /// 禁止远程关闭指定子系统
+    pub fn pin_on(mut self, feature: SdkFeature) -> Self {
+        self.pinned_on.insert(feature);
+        self
+    }
+
+    /// 检查命令是否被本地策略允许
+    pub fn check(&self, command: &ToggleCommand) -> Result<()> {
+        let allowed = self.operators.get(&command.operator_did)
+            .ok_or_else(|| anyhow::anyhow!("运维DID不在本地允许策略中: {}", command.operator_did))?;
+        if !allowed.contains(&command.feature) {
+            anyhow::bail!("运维DID无权开关子系统: {}", command.feature);
+        }
+        if !command.enabled && self.pinned_on.contains(&command.feature) {
+            anyhow::bail!("子系统不允许远程关闭: {}", command.feature);
+        }
+        Ok(())
+    }
+}
+
+/// 审计记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToggleAuditEntry {
+    /// 记录时间（毫秒）
+    pub at: u64,
+
+    /// 运维DID
+    pub operator_did: String,
+
+    /// 目标子系统
+    pub feature: SdkFeature,
+
+    /// 请求的状态
+    pub enabled: bool,
+
+    /// 命令中的原因
+    pub reason: String,
+
+    /// 命令签发时间（毫秒）
+    pub issued_at: u64,
+
+    /// 是否已应用
+    pub accepted: bool,
+
+    /// 拒绝原因
+    #[serde(default)]
+    pub rejection: Option<String>,
+}
+
+struct Override {
+    enabled: bool,
+    expires_at: Option<u64>,
+}
+
+/// 本地功能开关状态
+pub struct FeatureToggles {
+    policy: Mutex<TogglePolicy>,
+    defaults: HashMap<SdkFeature, bool>,
+    overrides: Mutex<HashMap<SdkFeature, Override>>,
+    /// (运维DID, 子系统) -> 最近接受的命令签发时间
+    last_issued: Mutex<HashMap<(String, SdkFeature), u64>>,
+    audit: Mutex<VecDeque<ToggleAuditEntry>>,
+    audit_file: Option<PathBuf>,
+    clock: SharedClock,
+}
+
+impl FeatureToggles {
+    /// 创建开关状态（未列出默认值的子系统默认开启）
+    pub fn new(policy: TogglePolicy) -> Self {
+        Self {
+            policy: Mutex::new(policy),
+            defaults: HashMap::new(),
+            overrides: Mutex::new(HashMap::new()),
+            last_issued: Mutex::new(HashMap::new()),
+            audit: Mutex::new(VecDeque::new()),
+            audit_file: None,
+            clock: system_clock(),
+        }
+    }
+
+    /// 设置子系统的默认状态
+    pub fn with_default(mut self, feature: SdkFeature, enabled: bool) -> Self {
+        self.defaults.insert(feature, enabled);
+        self
+    }
+
+    /// 同时把审计记录追加到文件（每行一条JSON）
+    pub fn with_audit_file(mut self, path: &Path) -> Self {
+        self.audit_file = Some(path.to_path_buf());
+        self
+    }
+
+    /// 使用指定时间源
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 替换本地允许策略
+    pub fn set_policy(&self, policy: TogglePolicy) {
+        *self.policy.lock().unwrap() = policy;
+    }
+
+    /// 子系统当前是否开启（过期的远程覆盖恢复为默认状态）
+    pub fn is_enabled(&self, feature: &SdkFeature) -> bool {
+        let now = self.clock.now_millis();
+        let mut overrides = self.overrides.lock().unwrap();
+        if let Some(current) = overrides.get(feature) {
+            if current.expires_at.is_none_or(|expires_at| now < expires_at) {
+                return current.enabled;
+            }
+            log::info!("⏱️ 功能开关 {} 的远程覆盖已到期，恢复默认状态", feature);
+            overrides.remove(feature);
+        }
+        self.defaults.get(feature).copied().unwrap_or(true)
+    }
+
+    /// 应用开关命令：验证签名、本地策略和时效，记录审计日志
+    pub fn apply(&self, command: &ToggleCommand) -> Result<ToggleAuditEntry> {
+        let result = self.check(command);
+        let entry = ToggleAuditEntry {
+            at: self.clock.now_millis(),
+            operator_did: command.operator_did.clone(),
+            feature: command.feature.clone(),
+            enabled: command.enabled,
+            reason: command.reason.clone(),
+            issued_at: command.issued_at,
+            accepted: result.is_ok(),
+            rejection: result.as_ref().err().map(|e| e.to_string()),
+        };
+
+        match &result {
+            Ok(()) => {
+                self.overrides.lock().unwrap().insert(command.feature.clone(), Override {
+                    enabled: command.enabled,
+                    expires_at: command.expires_at,
+                });
+                self.last_issued.lock().unwrap()
+                    .insert((command.operator_did.clone(), command.feature.clone()), command.issued_at);
+                log::warn!(
+                    "🎚️ 功能开关 {} 已被 {} {}: {}",
+                    command.feature,
+                    command.operator_did,
+                    if command.enabled { "开启" } else { "关闭" },
+                    command.reason
+                );
+            }
+            Err(e) => log::warn!("⚠️ 拒绝功能开关命令（{} → {}）: {}", command.operator_did, command.feature, e),
+        }
+        self.record(entry.clone());
+
+        result.map(|_| entry)
+    }
+
+    fn check(&self, command: &ToggleCommand) -> Result<()> {
+        if !command.verify()? {
+            anyhow::bail!("开关命令签名无效");
+        }
+        self.policy.lock().unwrap().check(command)?;
+
+        let now = self.clock.now_millis();
+        if command.expires_at.is_some_and(|expires_at| now >= expires_at) {
+            anyhow::bail!("开关命令已到期");
+        }
+        let key = (command.operator_did.clone(), command.feature.clone());
+        if let Some(last) = self.last_issued.lock().unwrap().get(&key) {
+            if command.issued_at <= *last {
+                anyhow::bail!("开关命令不比已应用的命令新: {}", command.issued_at);
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&self, entry: ToggleAuditEntry) {
+        if let Some(path) = &self.audit_file {
+            let written = serde_json::to_string(&entry)
+                .map_err(anyhow::Error::from)
+                .and_then(|line| {
+                    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+                    writeln!(file, "{}", line)?;
+                    Ok(())
+                });
+            if let Err(e) = written {
+                log::error!("❌ 写入功能开关审计日志失败: {}", e);
+            }
+        }
+
+        let mut audit = self.audit.lock().unwrap();
+        if audit.len() >= MAX_AUDIT_ENTRIES {
+            audit.pop_front();
+        }
+        audit.push_back(entry);
+    }
+
+    /// 审计记录（最新的在后）
+    pub fn audit_log(&self) -> Vec<ToggleAuditEntry> {
+        self.audit.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl Default for FeatureToggles {
+    fn default() -> Self {
+        Self::new(TogglePolicy::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::pubsub_authenticator::PubSubMessageType;
+    use crate::clock::MockClock;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_policy_replay_and_expiry() {
+        let operator = KeyPair::generate().unwrap();
+        let intruder = KeyPair::generate().unwrap();
+        let clock = MockClock::new(1_700_000_000);
+        let dir = tempfile::tempdir().unwrap();
+        let audit_path = dir.path().join("toggles.jsonl");
+        let toggles = FeatureToggles::new(
+            TogglePolicy::new()
+                .allow(&operator.did, &[SdkFeature::ZkpPerMessage, SdkFeature::MessageArchive])
+                .pin_on(SdkFeature::MessageArchive),
+        )
+        .with_clock(Arc::new(clock.clone()))
+        .with_audit_file(&audit_path);
+        let now = 1_700_000_000_000;
+
+        // 事故期间关闭逐条ZKP，一小时后自动恢复
+        let off = ToggleCommand::sign(&operator, SdkFeature::ZkpPerMessage, false, "incident", now, Some(now + 3_600_000)).unwrap();
+        toggles.apply(&off).unwrap();
+        assert!(!toggles.is_enabled(&SdkFeature::ZkpPerMessage));
+        assert!(toggles.apply(&off).is_err(), "重放的命令应被拒绝");
+
+        // 未授权的运维、未授权的子系统、固定开启的子系统、篡改的命令
+        let foreign = ToggleCommand::sign(&intruder, SdkFeature::ZkpPerMessage, true, "", now + 1, None).unwrap();
+        assert!(toggles.apply(&foreign).is_err());
+        let hints = ToggleCommand::sign(&operator, SdkFeature::VerificationHints, false, "", now + 1, None).unwrap();
+        assert!(toggles.apply(&hints).is_err());
+        let archive = ToggleCommand::sign(&operator, SdkFeature::MessageArchive, false, "", now + 1, None).unwrap();
+        assert!(toggles.apply(&archive).is_err());
+        let mut tampered = ToggleCommand::sign(&operator, SdkFeature::ZkpPerMessage, false, "", now + 1, None).unwrap();
+        tampered.enabled = true;
+        assert!(toggles.apply(&tampered).is_err());
+        assert!(!toggles.is_enabled(&SdkFeature::ZkpPerMessage));
+
+        clock.advance(Duration::from_secs(3_600));
+        assert!(toggles.is_enabled(&SdkFeature::ZkpPerMessage));
+
+        // 所有命令（包括被拒绝的）都写入审计日志
+        let audit = toggles.audit_log();
+        assert_eq!(audit.len(), 6);
+        assert_eq!(audit.iter().filter(|entry| entry.accepted).count(), 1);
+        let lines = std::fs::read_to_string(&audit_path).unwrap();
+        assert_eq!(lines.lines().count(), 6);
+        let first: ToggleAuditEntry = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(first.reason, "incident");
+    }
+
+    #[tokio::test]
+    async fn test_toggle_over_messages_disables_zkp() {
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::CallbackSigner;
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+
+        let operator = KeyPair::generate().unwrap();
+        let agent_key = KeyPair::generate().unwrap();
+        let operator_node = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None);
+        let operator_signer = operator.clone();
+        let signer = CallbackSigner::new(operator.public_key, Arc::new(move |data| operator_signer.sign(data))).unwrap();
+        operator_node.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmOperator".to_string()).await.unwrap();
+
+        let toggles = Arc::new(FeatureToggles::new(TogglePolicy::new().allow(&operator.did, &[SdkFeature::ZkpPerMessage])));
+        let agent = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None)
+            .with_feature_toggles(toggles.clone());
+        agent.set_local_signer(Arc::new(agent_key), libp2p::PeerId::random(), "QmAgent".to_string()).await.unwrap();
+
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+        let command = ToggleCommand::sign(&operator, SdkFeature::ZkpPerMessage, false, "incident", now, None).unwrap();
+        let message = operator_node.create_toggle_message("ops", &command).await.unwrap();
+        let entry = agent.handle_toggle_message(&message).unwrap();
+        assert!(entry.accepted);
+        assert!(!agent.feature_toggles().is_enabled(&SdkFeature::ZkpPerMessage));
+
+        // 关闭后发出的消息不再生成ZKP证明（也就不需要获取DID文档）
+        let outgoing = agent.create_authenticated_message("chat", PubSubMessageType::Heartbeat, b"hi", None).await.unwrap();
+        assert!(outgoing.zkp_proof.is_empty());
+        assert_eq!(toggles.audit_log().len(), 1);
+    }
+}
@@ -0,0 +1,678 @@
+// DIAP Rust SDK - 集群管理模块
+// 运维方的控制器DID通过认证消息向已登记的智能体批量下发签名的策略包（主题配置、允许列表、日志级别）；
+// 按阶段灰度推送，逐个跟踪确认，某阶段失败过多时停止推送，并可把已应用的智能体回滚到上一个稳定版本
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::{AuthenticatedMessage, PubSubMessageType, TopicConfig};
+
+/// 策略下发消息类型标识（PubSubMessageType::Custom）
+pub const FLEET_POLICY_MESSAGE_TYPE: &str = "fleet_policy";
+
+/// 策略确认消息类型标识（PubSubMessageType::Custom）
+pub const FLEET_ACK_MESSAGE_TYPE: &str = "fleet_ack";
+
+/// 策略包格式版本
+pub const POLICY_BUNDLE_VERSION: u32 = 1;
+
+/// 策略内容（None/空表示不修改该项）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyContents {
+    /// 完整的主题配置集（整体替换）
+    #[serde(default)]
+    pub topics: Option<Vec<TopicConfig>>,
+
+    /// 主题 -> 允许的DID列表（在主题配置之后应用，未配置的主题会新建）
+    #[serde(default)]
+    pub allow_lists: BTreeMap<String, Vec<String>>,
+
+    /// 日志级别（off/error/warn/info/debug/trace）
+    #[serde(default)]
+    pub log_level: Option<String>,
+}
+
+impl PolicyContents {
+    /// 解析日志级别
+    pub fn log_level_filter(&self) -> Result<Option<log::LevelFilter>> {
+        self.log_level.as_deref()
+            .map(|level| log::LevelFilter::from_str(level).map_err(|_| anyhow::anyhow!("无效的日志级别: {}", level)))
+            .transpose()
+    }
+}
+
+/// 控制器签名的策略包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyBundle {
+    /// 格式版本
+    pub version: u32,
+
+    /// 控制器DID
+    pub controller_did: String,
+
+    /// 修订号（签发时间毫秒，单调递增，智能体拒绝比已应用修订旧的策略包）
+    pub revision: u64,
+
+    /// 策略内容
+    pub contents: PolicyContents,
+
+    /// 回滚时为被回滚的修订号
+    #[serde(default)]
+    pub rollback_of: Option<u64>,
+
+    /// 控制器签名（base64）
+    pub signature: String,
+}
+
+impl PolicyBundle {
+    /// 签发策略包
+    pub fn sign(signer: &dyn Signer, revision: u64, contents: PolicyContents, rollback_of: Option<u64>) -> Result<Self> {
+        contents.log_level_filter()?;
+        Self {
+            version: POLICY_BUNDLE_VERSION,
+            controller_did: signer.did(),
+            revision,
+            contents,
+            rollback_of,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// 验证签名，并确认签发者是受信控制器
+    pub fn verify(&self, controller_dids: &[String]) -> Result<()> {
+        if self.version != POLICY_BUNDLE_VERSION {
+            anyhow::bail!("不支持的策略包版本: {}", self.version);
+        }
+        if !controller_dids.contains(&self.controller_did) {
+            anyhow::bail!("策略包签发者不是受信控制器: {}", self.controller_did);
+        }
+        if !SignedEnvelope::verify(self)? {
+            anyhow::bail!("策略包签名无效");
+        }
+        Ok(())
+    }
+}
+
+impl SignedEnvelope for PolicyBundle {
+    const KIND: &'static str = "策略包";
+
+    fn signer_did(&self) -> &str {
+        &self.controller_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 下发给某一阶段智能体的策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FleetDirective {
+    /// 策略包
+    pub bundle: PolicyBundle,
+
+    /// 灰度阶段（从0开始）
+    pub stage: usize,
+
+    /// 本阶段的目标智能体DID（其他智能体忽略该消息）
+    pub targets: Vec<String>,
+}
+
+impl FleetDirective {
+    /// 序列化为消息内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化策略下发失败")
+    }
+
+    /// 从认证消息中解析策略下发（消息发送者必须是策略包的控制器）
+    pub fn from_message(message: &AuthenticatedMessage) -> Result<Self> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == FLEET_POLICY_MESSAGE_TYPE => {}
+            _ => anyhow::bail!("不是策略下发消息: {}", message.message_id),
+        }
+
+        let directive: Self = serde_json::from_slice(&message.content)
+            .context("解析策略下发失败")?;
+        if directive.bundle.controller_did != message.from_did {
+            anyhow::bail!("策略包控制器与消息发送者不一致");
+        }
+        Ok(directive)
+    }
+
+    /// 是否发给指定智能体
+    pub fn targets(&self, did: &str) -> bool {
+        self.targets.iter().any(|target| target == did)
+    }
+}
+
+/// 智能体应用策略包的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AckStatus {
+    /// 已应用
+    Applied,
+
+    /// 拒绝或应用失败（原因）
+    Rejected(String),
+}
+
+/// 智能体对策略包的确认
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FleetAck {
+    /// 智能体DID
+    pub agent_did: String,
+
+    /// 策略包修订号
+    pub revision: u64,
+
+    /// 应用结果
+    pub status: AckStatus,
+}
+
+impl FleetAck {
+    /// 序列化为消息内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化策略确认失败")
+    }
+
+    /// 从认证消息中解析策略确认
+    pub fn from_message(message: &AuthenticatedMessage) -> Result<Self> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == FLEET_ACK_MESSAGE_TYPE => {}
+            _ => anyhow::bail!("不是策略确认消息: {}", message.message_id),
+        }
+
+        let ack: Self = serde_json::from_slice(&message.content)
+            .context("解析策略确认失败")?;
+        if ack.agent_did != message.from_did {
+            anyhow::bail!("策略确认DID与消息发送者不一致");
+        }
+        Ok(ack)
+    }
+}
+
+/// 灰度推送计划
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RolloutPlan {
+    /// 各阶段累计覆盖的智能体比例（递增，最后一项应为1.0）
+    pub stages: Vec<f64>,
+
+    /// 单个推送允许的拒绝数，超过时停止推送
+    pub max_rejections: usize,
+}
+
+impl Default for RolloutPlan {
+    fn default() -> Self {
+        Self {
+            stages: vec![0.1, 0.5, 1.0],
+            max_rejections: 0,
+        }
+    }
+}
+
+impl RolloutPlan {
+    /// 一次推送到全部智能体
+    pub fn all_at_once() -> Self {
+        Self {
+            stages: vec![1.0],
+            max_rejections: 0,
+        }
+    }
+
+    /// 把智能体按阶段划分（每个非空阶段至少一个智能体）
+    fn partition(&self, agents: &[String]) -> Result<Vec<Vec<String>>> {
+        if self.stages.is_empty() || self.stages.windows(2).any(|w| w[0] > w[1]) {
+            anyhow::bail!("推送阶段比例必须非空且递增");
+        }
+        if self.stages.last() != Some(&1.0) {
+            anyhow::bail!("最后一个推送阶段必须覆盖全部智能体");
+        }
+
+        let mut stages = Vec::new();
+        let mut covered = 0;
+        for fraction in &self.stages {
+            let until = ((agents.len() as f64 * fraction).ceil() as usize).clamp(covered, agents.len());
+            if until > covered {
+                stages.push(agents[covered..until].to_vec());
+                covered = until;
+            }
+        }
+        Ok(stages)
+    }
+}
+
+/// 推送状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RolloutState {
+    /// 推送中
+    InProgress,
+
+    /// 全部阶段已确认
+    Completed,
+
+    /// 拒绝过多，已停止推送
+    Halted,
+
+    /// 已回滚
+    RolledBack,
+}
+
+/// 推送进度
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RolloutStatus {
+    /// 策略包修订号
+    pub revision: u64,
+
+    /// 推送状态
+    pub state: RolloutState,
+
+    /// 当前阶段
+    pub stage: usize,
+
+    /// 阶段总数
+    pub stage_count: usize,
+
+    /// 已应用的智能体
+    pub applied: Vec<String>,
+
+    /// 拒绝的智能体及原因
+    pub rejected: BTreeMap<String, String>,
+
+    /// 当前阶段尚未确认的智能体
+    pub pending: Vec<String>,
+}
+
+struct Rollout {
+    bundle: PolicyBundle,
+    plan: RolloutPlan,
+    stages: Vec<Vec<String>>,
+    stage: usize,
+    acks: HashMap<String, AckStatus>,
+    state: RolloutState,
+}
+
+impl Rollout {
+    fn directive(&self) -> FleetDirective {
+        FleetDirective {
+            bundle: self.bundle.clone(),
+            stage: self.stage,
+            targets: self.stages[self.stage].clone(),
+        }
+    }
+
+    fn rejections(&self) -> usize {
+        self.acks.values().filter(|status| matches!(status, AckStatus::Rejected(_))).count()
+    }
+
+    fn status(&self) -> RolloutStatus {
+        let mut applied: Vec<String> = self.acks.iter()
+            .filter(|(_, status)| **status == AckStatus::Applied)
+            .map(|(did, _)| did.clone())
+            .collect();
+        applied.sort();
+        RolloutStatus {
+            revision: self.bundle.revision,
+            state: self.state,
+            stage: self.stage,
+            stage_count: self.stages.len(),
+            applied,
+            rejected: self.acks.iter()
+                .filter_map(|(did, status)| match status {
+                    AckStatus::Rejected(reason) => Some((did.clone(), reason.clone())),
+                    AckStatus::Applied => None,
+                })
+                .collect(),
+            pending: self.stages[self.stage].iter()
+                .filter(|did| !self.acks.contains_key(*did))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// 集群控制器：登记智能体、分阶段推送策略包并跟踪确认
+pub struct FleetController {
+    controller_did: String,
+    enrolled: Mutex<BTreeSet<String>>,
+    rollouts: Mutex<BTreeMap<u64, Rollout>>,
+    /// 最近一个全部完成的策略包（回滚目标）
+    stable: Mutex<Option<PolicyBundle>>,
+}
+
+impl FleetController {
+    /// 创建控制器
+    pub fn new(controller_did: &str) -> Self {
+        Self {
+            controller_did: controller_did.to_string(),
+            enrolled: Mutex::new(BTreeSet::new()),
+            rollouts: Mutex::new(BTreeMap::new()),
+            stable: Mutex::new(None),
+        }
+    }
+
+    /// 控制器DID
+    pub fn controller_did(&self) -> &str {
+        &self.controller_did
+    }
+
+    /// 登记智能体
+    pub fn enroll(&self, did: &str) {
+        self.enrolled.lock().unwrap().insert(did.to_string());
+    }
+
+    /// 移除智能体
+    pub fn unenroll(&self, did: &str) -> bool {
+        self.enrolled.lock().unwrap().remove(did)
+    }
+
+    /// 已登记的智能体（按DID排序）
+    pub fn enrolled(&self) -> Vec<String> {
+        self.enrolled.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// 签发新的策略包并开始推送，返回第一阶段的下发内容
+    pub fn start_rollout(
+        &self,
+        signer: &dyn Signer,
+        contents: PolicyContents,
+        plan: RolloutPlan,
+        now_millis: u64,
+    ) -> Result<FleetDirective> {
+        let agents = self.enrolled();
+        if agents.is_empty() {
+            anyhow::bail!("没有已登记的智能体");
+        }
+        let stages = plan.partition(&agents)?;
+        self.launch(signer, contents, plan, stages, now_millis, None)
+    }
+
+    fn launch(
+        &self,
+        signer: &dyn Signer,
+        contents: PolicyContents,
+        plan: RolloutPlan,
+        stages: Vec<Vec<String>>,
+        now_millis: u64,
+        rollback_of: Option<u64>,
+    ) -> Result<FleetDirective> {
+        if signer.did() != self.controller_did {
+            anyhow::bail!("签名者不是控制器: {}", signer.did());
+        }
+
+        let mut rollouts = self.rollouts.lock().unwrap();
+        if rollouts.values().any(|rollout| rollout.state == RolloutState::InProgress) {
+            anyhow::bail!("已有正在进行的推送");
+        }
+        // 修订号必须严格递增，否则智能体会把新策略包当作旧版本拒绝
+        let revision = rollouts.keys().next_back().map_or(now_millis, |last| now_millis.max(last + 1));
+        let bundle = PolicyBundle::sign(signer, revision, contents, rollback_of)?;
+
+        let rollout = Rollout {
+            bundle,
+            plan,
+            stages,
+            stage: 0,
+            acks: HashMap::new(),
+            state: RolloutState::InProgress,
+        };
+        let directive = rollout.directive();
+        log::info!("📦 开始推送策略包 {}（{}个阶段）", revision, rollout.stages.len());
+        rollouts.insert(revision, rollout);
+
+        Ok(directive)
+    }
+
+    /// 记录智能体的确认；拒绝数超过计划上限时停止推送
+    pub fn record_ack(&self, ack: &FleetAck) -> Result<RolloutStatus> {
+        let mut rollouts = self.rollouts.lock().unwrap();
+        let rollout = rollouts.get_mut(&ack.revision)
+            .ok_or_else(|| anyhow::anyhow!("未知的策略包修订: {}", ack.revision))?;
+        if !rollout.stages.iter().take(rollout.stage + 1).flatten().any(|did| did == &ack.agent_did) {
+            anyhow::bail!("智能体不在已下发的阶段中: {}", ack.agent_did);
+        }
+
+        rollout.acks.insert(ack.agent_did.clone(), ack.status.clone());
+        if let AckStatus::Rejected(reason) = &ack.status {
+            log::warn!("⚠️ 智能体 {} 拒绝策略包 {}: {}", ack.agent_did, ack.revision, reason);
+            if rollout.state == RolloutState::InProgress && rollout.rejections() > rollout.plan.max_rejections {
+                log::warn!("🛑 策略包 {} 拒绝过多，停止推送", ack.revision);
+                rollout.state = RolloutState::Halted;
+            }
+        }
+        Ok(rollout.status())
+    }
+
+    /// 当前阶段全部确认后进入下一阶段，返回下一阶段的下发内容；
+    /// 最后一个阶段完成时推送结束并返回None（该策略包成为回滚目标）
+    pub fn advance(&self, revision: u64) -> Result<Option<FleetDirective>> {
+        let mut rollouts = self.rollouts.lock().unwrap();
+        let rollout = rollouts.get_mut(&revision)
+            .ok_or_else(|| anyhow::anyhow!("未知的策略包修订: {}", revision))?;
+        if rollout.state != RolloutState::InProgress {
+            anyhow::bail!("策略包 {} 不在推送中: {:?}", revision, rollout.state);
+        }
+        let status = rollout.status();
+        if !status.pending.is_empty() {
+            anyhow::bail!("当前阶段还有{}个智能体未确认", status.pending.len());
+        }
+
+        if rollout.stage + 1 < rollout.stages.len() {
+            rollout.stage += 1;
+            log::info!("📦 策略包 {} 进入第{}阶段", revision, rollout.stage);
+            return Ok(Some(rollout.directive()));
+        }
+
+        rollout.state = RolloutState::Completed;
+        *self.stable.lock().unwrap() = Some(rollout.bundle.clone());
+        log::info!("✅ 策略包 {} 推送完成", revision);
+        Ok(None)
+    }
+
+    /// 回滚：把已应用该策略包的智能体恢复到上一个稳定策略包（以新修订号重新签发）
+    pub fn rollback(&self, signer: &dyn Signer, revision: u64, now_millis: u64) -> Result<FleetDirective> {
+        let stable = self.stable.lock().unwrap().clone()
+            .filter(|stable| stable.revision != revision)
+            .ok_or_else(|| anyhow::anyhow!("没有可回滚到的稳定策略包"))?;
+
+        let applied = {
+            let mut rollouts = self.rollouts.lock().unwrap();
+            let rollout = rollouts.get_mut(&revision)
+                .ok_or_else(|| anyhow::anyhow!("未知的策略包修订: {}", revision))?;
+            rollout.state = RolloutState::RolledBack;
+            rollout.status().applied
+        };
+        if applied.is_empty() {
+            anyhow::bail!("没有智能体应用过策略包 {}", revision);
+        }
+
+        log::warn!("↩️ 回滚策略包 {} 到 {}（{}个智能体）", revision, stable.revision, applied.len());
+        self.launch(signer, stable.contents, RolloutPlan::all_at_once(), vec![applied], now_millis, Some(revision))
+    }
+
+    /// 推送进度
+    pub fn status(&self, revision: u64) -> Option<RolloutStatus> {
+        self.rollouts.lock().unwrap().get(&revision).map(Rollout::status)
+    }
+}
+
+/// 智能体侧的策略接收状态
+pub struct FleetAgent {
+    controller_dids: Vec<String>,
+    applied: Mutex<Option<PolicyBundle>>,
+}
+
+impl FleetAgent {
+    /// 只接受指定控制器签发的策略包
+    pub fn new(controller_dids: Vec<String>) -> Self {
+        Self {
+            controller_dids,
+            applied: Mutex::new(None),
+        }
+    }
+
+    /// 受信控制器
+    pub fn controller_dids(&self) -> &[String] {
+        &self.controller_dids
+    }
+
+    /// 已应用的修订号
+    pub fn applied_revision(&self) -> Option<u64> {
+        self.applied.lock().unwrap().as_ref().map(|bundle| bundle.revision)
+    }
+
+    /// 检查策略包是否可以应用：签名有效、来自受信控制器、且比已应用的修订新
+    /// 重复收到已应用的修订返回false（应再次确认，但不必重新应用）
+    pub fn accept(&self, bundle: &PolicyBundle) -> Result<bool> {
+        bundle.verify(&self.controller_dids)?;
+        match self.applied_revision() {
+            Some(applied) if bundle.revision == applied => Ok(false),
+            Some(applied) if bundle.revision < applied => {
+                anyhow::bail!("策略包修订 {} 早于已应用的 {}", bundle.revision, applied)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    /// 记录已应用的策略包
+    pub fn mark_applied(&self, bundle: &PolicyBundle) {
+        *self.applied.lock().unwrap() = Some(bundle.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+
+    #[test]
+    fn test_partition_stages() {
+        let agents: Vec<String> = (0..10).map(|i| format!("did:key:{}", i)).collect();
+        let stages = RolloutPlan::default().partition(&agents).unwrap();
+        assert_eq!(stages.iter().map(Vec::len).collect::<Vec<_>>(), vec![1, 4, 5]);
+
+        let stages = RolloutPlan::default().partition(&agents[..1]).unwrap();
+        assert_eq!(stages, vec![vec!["did:key:0".to_string()]]);
+
+        let bad = RolloutPlan { stages: vec![0.5, 0.2, 1.0], max_rejections: 0 };
+        assert!(bad.partition(&agents).is_err());
+    }
+
+    #[test]
+    fn test_staged_rollout_halt_and_rollback() {
+        let controller_key = KeyPair::generate().unwrap();
+        let controller = FleetController::new(&controller_key.did);
+        let agents: Vec<String> = (0..4).map(|i| format!("did:key:agent{}", i)).collect();
+        for agent in &agents {
+            controller.enroll(agent);
+        }
+        let ack = |agent: &str, revision: u64, status: AckStatus| FleetAck {
+            agent_did: agent.to_string(),
+            revision,
+            status,
+        };
+
+        // 第一个策略包分两阶段推送完成，成为稳定版本
+        let plan = RolloutPlan { stages: vec![0.5, 1.0], max_rejections: 0 };
+        let contents = PolicyContents { log_level: Some("info".to_string()), ..Default::default() };
+        let first = controller.start_rollout(&controller_key, contents, plan.clone(), 1_000).unwrap();
+        assert_eq!(first.targets, agents[..2].to_vec());
+        assert!(controller.advance(first.bundle.revision).is_err());
+        for agent in &first.targets {
+            controller.record_ack(&ack(agent, first.bundle.revision, AckStatus::Applied)).unwrap();
+        }
+        let second_stage = controller.advance(first.bundle.revision).unwrap().unwrap();
+        assert_eq!(second_stage.targets, agents[2..].to_vec());
+        assert!(controller.record_ack(&ack("did:key:stranger", first.bundle.revision, AckStatus::Applied)).is_err());
+        for agent in &second_stage.targets {
+            controller.record_ack(&ack(agent, first.bundle.revision, AckStatus::Applied)).unwrap();
+        }
+        assert!(controller.advance(first.bundle.revision).unwrap().is_none());
+        assert_eq!(controller.status(first.bundle.revision).unwrap().state, RolloutState::Completed);
+
+        // 第二个策略包在第一阶段被拒绝，停止推送后回滚已应用的智能体
+        let contents = PolicyContents { log_level: Some("debug".to_string()), ..Default::default() };
+        let bad = controller.start_rollout(&controller_key, contents, plan, 1_000).unwrap();
+        assert!(bad.bundle.revision > first.bundle.revision);
+        controller.record_ack(&ack(&agents[0], bad.bundle.revision, AckStatus::Applied)).unwrap();
+        let status = controller.record_ack(&ack(&agents[1], bad.bundle.revision, AckStatus::Rejected("boom".to_string()))).unwrap();
+        assert_eq!(status.state, RolloutState::Halted);
+        assert!(controller.advance(bad.bundle.revision).is_err());
+
+        let rollback = controller.rollback(&controller_key, bad.bundle.revision, 2_000).unwrap();
+        assert_eq!(rollback.targets, vec![agents[0].clone()]);
+        assert_eq!(rollback.bundle.rollback_of, Some(bad.bundle.revision));
+        assert_eq!(rollback.bundle.contents.log_level.as_deref(), Some("info"));
+        assert_eq!(controller.status(bad.bundle.revision).unwrap().state, RolloutState::RolledBack);
+
+        // 智能体只接受受信控制器签发的、更新的策略包
+        let agent = FleetAgent::new(vec![controller_key.did.clone()]);
+        assert!(agent.accept(&rollback.bundle).unwrap());
+        agent.mark_applied(&rollback.bundle);
+        assert!(!agent.accept(&rollback.bundle).unwrap());
+        assert!(agent.accept(&bad.bundle).is_err());
+        assert!(FleetAgent::new(vec!["did:key:other".to_string()]).accept(&first.bundle).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_directive_applied_over_messages() {
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::CallbackSigner;
+        use crate::pubsub_authenticator::{PubsubAuthenticator, TopicEncryption, TopicPolicy};
+        use std::sync::Arc;
+
+        async fn authenticator(keypair: KeyPair) -> PubsubAuthenticator {
+            let authenticator = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None);
+            let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+            authenticator.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmLocal".to_string()).await.unwrap();
+            authenticator
+        }
+
+        let controller_key = KeyPair::generate().unwrap();
+        let agent_key = KeyPair::generate().unwrap();
+        let agent_did = agent_key.did.clone();
+        let controller = FleetController::new(&controller_key.did);
+        controller.enroll(&agent_did);
+        let controller_node = authenticator(controller_key.clone()).await;
+        let agent_node = authenticator(agent_key).await;
+        let fleet_agent = FleetAgent::new(vec![controller_key.did.clone()]);
+
+        let contents = PolicyContents {
+            topics: Some(vec![TopicConfig {
+                name: "tasks".to_string(),
+                policy: TopicPolicy::AllowAuthenticated,
+                require_zkp: false,
+                require_signature: true,
+                retention: None,
+                rate_limit: None,
+                encryption: TopicEncryption::Required,
+            }]),
+            allow_lists: BTreeMap::from([("ops".to_string(), vec![controller_key.did.clone()])]),
+            log_level: None,
+        };
+        let directive = controller.start_rollout(&controller_key, contents, RolloutPlan::all_at_once(), 1_000).unwrap();
+        let message = controller_node.create_fleet_directive("fleet", &directive).await.unwrap();
+
+        let ack = agent_node.handle_fleet_directive(&message, &fleet_agent).await.unwrap().unwrap();
+        assert_eq!(ack.to_did.as_deref(), Some(controller_key.did.as_str()));
+        assert_eq!(fleet_agent.applied_revision(), Some(directive.bundle.revision));
+        assert_eq!(agent_node.topic_config_for("tasks").await.unwrap().encryption, TopicEncryption::Required);
+        assert!(matches!(agent_node.topic_config_for("ops").await.unwrap().policy, TopicPolicy::AllowList(_)));
+
+        let status = controller_node.handle_fleet_ack(&ack, &controller).unwrap();
+        assert_eq!(status.applied, vec![agent_did]);
+        assert!(controller.advance(directive.bundle.revision).unwrap().is_none());
+
+        // 非目标智能体忽略下发
+        let bystander = authenticator(KeyPair::generate().unwrap()).await;
+        assert!(bystander.handle_fleet_directive(&message, &fleet_agent).await.unwrap().is_none());
+    }
+}
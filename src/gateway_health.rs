@@ -0,0 +1,205 @@
+// DIAP Rust SDK - 网关健康评分
+// 记录各IPFS网关的延迟和错误率，按健康分动态排序，支持运行时增删网关
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// 延迟滑动平均的平滑系数
+const LATENCY_EWMA_ALPHA: f64 = 0.3;
+
+/// 单个网关的健康状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GatewayHealth {
+    /// 网关URL
+    pub gateway: String,
+
+    /// 成功次数
+    pub successes: u64,
+
+    /// 失败次数
+    pub failures: u64,
+
+    /// 连续失败次数
+    pub consecutive_failures: u32,
+
+    /// 平均延迟（毫秒，指数滑动平均）
+    pub avg_latency_ms: f64,
+}
+
+impl GatewayHealth {
+    fn new(gateway: &str) -> Self {
+        Self {
+            gateway: gateway.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 错误率
+    pub fn error_rate(&self) -> f64 {
+        let total = self.successes + self.failures;
+        if total == 0 {
+            0.0
+        } else {
+            self.failures as f64 / total as f64
+        }
+    }
+
+    /// 健康分（越高越优先）
+    /// 成功率（带平滑先验，新网关为0.5）除以延迟因子，连续失败时按指数降权
+    pub fn score(&self) -> f64 {
+        let success_rate = (self.successes as f64 + 1.0) / ((self.successes + self.failures) as f64 + 2.0);
+        let latency_factor = 1.0 + self.avg_latency_ms / 1000.0;
+        let failure_penalty = 0.5f64.powi(self.consecutive_failures.min(10) as i32);
+
+        success_rate / latency_factor * failure_penalty
+    }
+}
+
+/// 网关健康跟踪器
+#[derive(Clone, Default)]
+pub struct GatewayHealthTracker {
+    /// 网关列表（保持添加顺序，分数相同时按此顺序）
+    gateways: Arc<RwLock<Vec<String>>>,
+
+    /// 网关 -> 健康状态
+    health: Arc<DashMap<String, GatewayHealth>>,
+}
+
+impl GatewayHealthTracker {
+    /// 使用初始网关列表创建跟踪器
+    pub fn new(gateways: Vec<String>) -> Self {
+        let tracker = Self::default();
+        for gateway in gateways {
+            tracker.add_gateway(&gateway);
+        }
+        tracker
+    }
+
+    /// 添加网关（已存在时忽略），返回是否新增
+    pub fn add_gateway(&self, gateway: &str) -> bool {
+        let gateway = gateway.trim_end_matches('/');
+        let mut gateways = self.gateways.write().unwrap();
+        if gateways.iter().any(|g| g == gateway) {
+            return false;
+        }
+
+        gateways.push(gateway.to_string());
+        self.health.insert(gateway.to_string(), GatewayHealth::new(gateway));
+        log::info!("➕ 添加IPFS网关: {}", gateway);
+        true
+    }
+
+    /// 移除网关，返回是否存在
+    pub fn remove_gateway(&self, gateway: &str) -> bool {
+        let gateway = gateway.trim_end_matches('/');
+        let mut gateways = self.gateways.write().unwrap();
+        let before = gateways.len();
+        gateways.retain(|g| g != gateway);
+        self.health.remove(gateway);
+
+        let removed = gateways.len() != before;
+        if removed {
+            log::info!("➖ 移除IPFS网关: {}", gateway);
+        }
+        removed
+    }
+
+    /// 按健康分从高到低排序的网关列表
+    pub fn ranked(&self) -> Vec<String> {
+        let gateways = self.gateways.read().unwrap().clone();
+        let mut scored: Vec<(usize, f64, String)> = gateways
+            .into_iter()
+            .enumerate()
+            .map(|(index, gateway)| {
+                let score = self.health.get(&gateway).map(|h| h.score()).unwrap_or(0.0);
+                (index, score, gateway)
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        scored.into_iter().map(|(_, _, gateway)| gateway).collect()
+    }
+
+    /// 记录一次成功请求
+    pub fn record_success(&self, gateway: &str, latency: Duration) {
+        if let Some(mut health) = self.health.get_mut(gateway) {
+            let latency_ms = latency.as_secs_f64() * 1000.0;
+            health.avg_latency_ms = if health.successes == 0 {
+                latency_ms
+            } else {
+                LATENCY_EWMA_ALPHA * latency_ms + (1.0 - LATENCY_EWMA_ALPHA) * health.avg_latency_ms
+            };
+            health.successes += 1;
+            health.consecutive_failures = 0;
+        }
+    }
+
+    /// 记录一次失败请求
+    pub fn record_failure(&self, gateway: &str) {
+        if let Some(mut health) = self.health.get_mut(gateway) {
+            health.failures += 1;
+            health.consecutive_failures += 1;
+        }
+    }
+
+    /// 获取全部网关的健康状态（按健康分排序）
+    pub fn snapshot(&self) -> Vec<GatewayHealth> {
+        self.ranked()
+            .iter()
+            .filter_map(|gateway| self.health.get(gateway).map(|h| h.clone()))
+            .collect()
+    }
+
+    /// 网关数量
+    pub fn len(&self) -> usize {
+        self.gateways.read().unwrap().len()
+    }
+
+    /// 是否没有网关
+    pub fn is_empty(&self) -> bool {
+        self.gateways.read().unwrap().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorder_by_health() {
+        let tracker = GatewayHealthTracker::new(vec![
+            "https://a.example".to_string(),
+            "https://b.example".to_string(),
+            "https://c.example".to_string(),
+        ]);
+
+        // 初始按添加顺序
+        assert_eq!(tracker.ranked()[0], "https://a.example");
+
+        tracker.record_failure("https://a.example");
+        tracker.record_success("https://b.example", Duration::from_millis(800));
+        tracker.record_success("https://c.example", Duration::from_millis(50));
+
+        assert_eq!(tracker.ranked(), vec![
+            "https://c.example".to_string(),
+            "https://b.example".to_string(),
+            "https://a.example".to_string(),
+        ]);
+        assert_eq!(tracker.snapshot()[2].error_rate(), 1.0);
+    }
+
+    #[test]
+    fn test_add_remove_gateway() {
+        let tracker = GatewayHealthTracker::new(vec!["https://a.example".to_string()]);
+
+        assert!(tracker.add_gateway("https://b.example/"));
+        assert!(!tracker.add_gateway("https://b.example"));
+        assert_eq!(tracker.len(), 2);
+
+        assert!(tracker.remove_gateway("https://a.example"));
+        assert!(!tracker.remove_gateway("https://a.example"));
+        assert_eq!(tracker.ranked(), vec!["https://b.example".to_string()]);
+    }
+}
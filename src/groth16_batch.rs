@@ -0,0 +1,180 @@
+// DIAP Rust SDK - Groth16批量验证
+// 网关智能体每分钟需要验证数百个DID绑定证明；用随机线性组合把多个Groth16验证方程
+// 合并为一次多重配对（只做一次最终幂运算），批量失败时二分定位无效证明
+
+use anyhow::{Context, Result};
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::Zero;
+use ark_serialize::CanonicalDeserialize;
+use ark_groth16::{Proof, VerifyingKey};
+
+use crate::setup_ceremony::{KeyBundle, VersionedProof};
+
+/// 待验证的证明及其公共输入
+#[derive(Debug, Clone)]
+pub struct ProofEnvelope {
+    /// 带密钥版本的证明
+    pub proof: VersionedProof,
+
+    /// 公共输入
+    pub public_inputs: Vec<Fr>,
+}
+
+impl ProofEnvelope {
+    /// 创建证明信封
+    pub fn new(proof: VersionedProof, public_inputs: Vec<Fr>) -> Self {
+        Self { proof, public_inputs }
+    }
+}
+
+/// 已解析的单个证明（随机系数合并前的形式）
+struct ParsedProof {
+    proof: Proof<Bn254>,
+    inputs_commitment: G1Affine,
+}
+
+/// Groth16批量验证器（同一密钥包下的证明）
+pub struct Groth16BatchVerifier {
+    bundle: KeyBundle,
+    vk: VerifyingKey<Bn254>,
+}
+
+impl Groth16BatchVerifier {
+    /// 从密钥包创建批量验证器
+    pub fn new(bundle: &KeyBundle) -> Result<Self> {
+        let vk = VerifyingKey::<Bn254>::deserialize_compressed(bundle.verifying_key.as_slice())
+            .context("解析验证密钥失败")?;
+        Ok(Self {
+            bundle: bundle.clone(),
+            vk,
+        })
+    }
+
+    /// 批量验证，返回每个证明的结果（顺序与输入一致）
+    pub fn verify_batch(&self, proofs: &[ProofEnvelope]) -> Vec<bool> {
+        let start_time = std::time::Instant::now();
+        let mut results = vec![false; proofs.len()];
+
+        // 密钥版本不符、无法解析或输入个数错误的证明直接判为无效，不参与批量
+        let parsed: Vec<(usize, ParsedProof)> = proofs.iter()
+            .enumerate()
+            .filter_map(|(index, envelope)| self.parse(envelope).map(|parsed| (index, parsed)))
+            .collect();
+
+        let mut pending = vec![parsed.as_slice()];
+        while let Some(batch) = pending.pop() {
+            if batch.is_empty() {
+                continue;
+            }
+            if self.check_combined(batch) {
+                for (index, _) in batch {
+                    results[*index] = true;
+                }
+            } else if batch.len() > 1 {
+                let (left, right) = batch.split_at(batch.len() / 2);
+                pending.push(left);
+                pending.push(right);
+            }
+        }
+
+        log::info!(
+            "✅ 批量验证{}个Groth16证明: {}个通过，耗时{}ms",
+            proofs.len(),
+            results.iter().filter(|valid| **valid).count(),
+            start_time.elapsed().as_millis()
+        );
+        results
+    }
+
+    fn parse(&self, envelope: &ProofEnvelope) -> Option<ParsedProof> {
+        if self.bundle.check_proof(&envelope.proof).is_err()
+            || envelope.public_inputs.len() + 1 != self.vk.gamma_abc_g1.len()
+        {
+            return None;
+        }
+        let proof = Proof::<Bn254>::deserialize_compressed(envelope.proof.proof.as_slice()).ok()?;
+        let inputs_commitment = (self.vk.gamma_abc_g1[0]
+            + G1Projective::msm_unchecked(&self.vk.gamma_abc_g1[1..], &envelope.public_inputs))
+            .into_affine();
+        Some(ParsedProof { proof, inputs_commitment })
+    }
+
+    /// 检查随机线性组合后的验证方程：
+    /// Π e(rᵢ·Aᵢ, Bᵢ) · e(-Σrᵢ·ICᵢ, γ) · e(-Σrᵢ·Cᵢ, δ) · e(-(Σrᵢ)·α, β) = 1
+    fn check_combined(&self, batch: &[(usize, ParsedProof)]) -> bool {
+        // 128位随机系数足以让伪造的组合以可忽略的概率通过
+        let coefficients: Vec<Fr> = batch.iter()
+            .map(|_| Fr::from(rand::random::<u128>()))
+            .collect();
+        let coefficient_sum: Fr = coefficients.iter().sum();
+
+        let a: Vec<G1Affine> = batch.iter().map(|(_, parsed)| parsed.proof.a).collect();
+        let c: Vec<G1Affine> = batch.iter().map(|(_, parsed)| parsed.proof.c).collect();
+        let ic: Vec<G1Affine> = batch.iter().map(|(_, parsed)| parsed.inputs_commitment).collect();
+
+        let mut g1: Vec<G1Affine> = a.iter()
+            .zip(&coefficients)
+            .map(|(a, r)| (*a * r).into_affine())
+            .collect();
+        let mut g2: Vec<G2Affine> = batch.iter().map(|(_, parsed)| parsed.proof.b).collect();
+
+        g1.push((-G1Projective::msm_unchecked(&ic, &coefficients)).into_affine());
+        g2.push(self.vk.gamma_g2);
+        g1.push((-G1Projective::msm_unchecked(&c, &coefficients)).into_affine());
+        g2.push(self.vk.delta_g2);
+        g1.push((-(self.vk.alpha_g1.into_group() * coefficient_sum)).into_affine());
+        g2.push(self.vk.beta_g2);
+
+        Bn254::multi_pairing(g1, g2).is_zero()
+    }
+}
+
+/// 批量验证同一密钥包下的证明
+pub fn verify_batch(bundle: &KeyBundle, proofs: &[ProofEnvelope]) -> Result<Vec<bool>> {
+    Ok(Groth16BatchVerifier::new(bundle)?.verify_batch(proofs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup_ceremony::{groth16_prove, groth16_verify, SetupCeremony};
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+    use ark_relations::lc;
+
+    #[derive(Clone)]
+    struct Square(u64);
+
+    impl ConstraintSynthesizer<Fr> for Square {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let x = cs.new_witness_variable(|| Ok(Fr::from(self.0)))?;
+            let y = cs.new_input_variable(|| Ok(Fr::from(self.0 * self.0)))?;
+            cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + y)
+        }
+    }
+
+    #[test]
+    fn test_verify_batch_matches_sequential() {
+        let bundle = SetupCeremony::new("square", 1).contribute("alice", b"entropy").groth16(Square(1)).unwrap();
+        let mut envelopes: Vec<ProofEnvelope> = (1..=6u64)
+            .map(|x| ProofEnvelope::new(groth16_prove(&bundle, Square(x)).unwrap(), vec![Fr::from(x * x)]))
+            .collect();
+
+        assert_eq!(verify_batch(&bundle, &envelopes).unwrap(), vec![true; 6]);
+
+        // 错误的公共输入、损坏的证明、错误的输入个数、旧版本密钥下的证明
+        envelopes[1].public_inputs = vec![Fr::from(5u64)];
+        envelopes[3].proof.proof[0] ^= 1;
+        envelopes[4].public_inputs.push(Fr::from(1u64));
+        let rotated = SetupCeremony::new("square", 2).contribute("alice", b"entropy").groth16(Square(1)).unwrap();
+        envelopes.push(ProofEnvelope::new(groth16_prove(&rotated, Square(2)).unwrap(), vec![Fr::from(4u64)]));
+
+        let results = verify_batch(&bundle, &envelopes).unwrap();
+        assert_eq!(results, vec![true, false, true, false, false, true, false]);
+        for (envelope, valid) in envelopes.iter().zip(&results).take(4) {
+            assert_eq!(groth16_verify(&bundle, &envelope.proof, &envelope.public_inputs).unwrap_or(false), *valid);
+        }
+        assert!(verify_batch(&bundle, &[]).unwrap().is_empty());
+    }
+}
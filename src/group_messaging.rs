@@ -0,0 +1,434 @@
+// DIAP Rust SDK - 群组消息
+// 管理员维护成员DID列表，生成对称的群组密钥，并用每个成员DID文档中的X25519 keyAgreement密钥分别封装后下发；
+// 成员变更时轮换密钥（代数+1），被移除的成员拿不到新密钥。群组消息用当前代的密钥加密后通过gossipsub主题发布，
+// 保留少量旧代密钥以解密轮换前发出、轮换后才到达的消息
+
+use anyhow::{Context, Result};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::did_builder::DIDDocument;
+use crate::e2e_encryption::{self, EncryptedPayload};
+use crate::key_manager::{KeyPair, SignedEnvelope, Signer};
+use crate::pubsub_authenticator::{AuthenticatedMessage, PubSubMessageType};
+
+/// 群组密钥下发消息类型标识（PubSubMessageType::Custom）
+pub const GROUP_KEY_MESSAGE_TYPE: &str = "group_key";
+
+/// 群组消息类型标识（PubSubMessageType::Custom）
+pub const GROUP_MESSAGE_TYPE: &str = "group_message";
+
+/// 轮换后保留的旧密钥代数
+pub const RETAINED_EPOCHS: usize = 2;
+
+/// 签名的群组密钥下发（每代一份，封装给当前所有成员）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupKeyDistribution {
+    /// 群组ID
+    pub group_id: String,
+
+    /// 群组消息主题
+    pub topic: String,
+
+    /// 管理员DID
+    pub admin_did: String,
+
+    /// 密钥代数（每次轮换+1）
+    pub epoch: u64,
+
+    /// 当前成员DID（含管理员）
+    pub members: Vec<String>,
+
+    /// 成员DID -> 封装给该成员的群组密钥
+    pub wrapped_keys: BTreeMap<String, EncryptedPayload>,
+
+    /// 签发时间（Unix秒）
+    pub issued_at: u64,
+
+    /// 管理员签名（base64）
+    pub signature: String,
+}
+
+impl GroupKeyDistribution {
+    /// 是否包含该成员
+    pub fn is_member(&self, did: &str) -> bool {
+        self.members.iter().any(|member| member == did)
+    }
+
+    /// 序列化为消息内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化群组密钥失败")
+    }
+
+    /// 从认证消息中解析群组密钥下发（必须由管理员本人发送）
+    pub fn from_message(message: &AuthenticatedMessage) -> Result<Self> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == GROUP_KEY_MESSAGE_TYPE => {}
+            _ => anyhow::bail!("不是群组密钥消息: {}", message.message_id),
+        }
+        let distribution: Self = serde_json::from_slice(&message.content).context("解析群组密钥失败")?;
+        if distribution.admin_did != message.from_did {
+            anyhow::bail!("群组密钥的管理员与消息发送者不一致");
+        }
+        Ok(distribution)
+    }
+}
+
+impl SignedEnvelope for GroupKeyDistribution {
+    const KIND: &'static str = "群组密钥";
+
+    fn signer_did(&self) -> &str {
+        &self.admin_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 群组消息内容（用某一代群组密钥加密）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupCiphertext {
+    /// 群组ID
+    pub group_id: String,
+
+    /// 使用的密钥代数
+    pub epoch: u64,
+
+    /// ChaCha20-Poly1305 nonce
+    pub nonce: [u8; 12],
+
+    /// 密文（含认证标签）
+    pub ciphertext: Vec<u8>,
+}
+
+impl GroupCiphertext {
+    /// 序列化为消息内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化群组消息失败")
+    }
+
+    /// 从认证消息中解析群组消息
+    pub fn from_message(message: &AuthenticatedMessage) -> Result<Self> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == GROUP_MESSAGE_TYPE => {}
+            _ => anyhow::bail!("不是群组消息: {}", message.message_id),
+        }
+        serde_json::from_slice(&message.content).context("解析群组消息失败")
+    }
+
+    /// 关联数据：绑定群组、代数和发送者，防止密文被挪用
+    fn associated_data(group_id: &str, epoch: u64, sender_did: &str) -> Vec<u8> {
+        let mut aad = Vec::new();
+        for part in [group_id, sender_did] {
+            aad.extend_from_slice(&(part.len() as u32).to_be_bytes());
+            aad.extend_from_slice(part.as_bytes());
+        }
+        aad.extend_from_slice(&epoch.to_be_bytes());
+        aad
+    }
+}
+
+/// 成员持有的群组密钥环
+#[derive(Clone)]
+pub struct GroupKeyring {
+    group_id: String,
+    topic: String,
+    admin_did: String,
+    members: Vec<String>,
+    /// (代数, 密钥)，最新的在末尾
+    keys: VecDeque<(u64, [u8; 32])>,
+}
+
+impl GroupKeyring {
+    /// 从第一份密钥下发加入群组
+    pub fn join(keypair: &KeyPair, distribution: &GroupKeyDistribution) -> Result<Self> {
+        let mut keyring = Self {
+            group_id: distribution.group_id.clone(),
+            topic: distribution.topic.clone(),
+            admin_did: distribution.admin_did.clone(),
+            members: Vec::new(),
+            keys: VecDeque::new(),
+        };
+        keyring.apply(keypair, distribution)?;
+        Ok(keyring)
+    }
+
+    /// 应用新一代密钥下发；本地身份已被移除时清空密钥并返回错误
+    pub fn apply(&mut self, keypair: &KeyPair, distribution: &GroupKeyDistribution) -> Result<()> {
+        if distribution.group_id != self.group_id || distribution.admin_did != self.admin_did {
+            anyhow::bail!("群组密钥不属于该群组: {}", distribution.group_id);
+        }
+        if self.keys.back().is_some_and(|(epoch, _)| distribution.epoch <= *epoch) {
+            anyhow::bail!("群组密钥代数未增加: {}", distribution.epoch);
+        }
+        distribution.require_valid()?;
+
+        if !distribution.is_member(&keypair.did) {
+            self.keys.clear();
+            self.members = distribution.members.clone();
+            anyhow::bail!("本地身份已被移出群组: {}", self.group_id);
+        }
+        let wrapped = distribution.wrapped_keys.get(&keypair.did)
+            .ok_or_else(|| anyhow::anyhow!("群组密钥缺少本地成员的封装"))?;
+        if wrapped.sender_did != distribution.admin_did {
+            anyhow::bail!("群组密钥不是由管理员封装的");
+        }
+        let key: [u8; 32] = e2e_encryption::decrypt(keypair, wrapped)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("群组密钥长度错误"))?;
+
+        self.install(distribution.epoch, key, distribution.members.clone());
+        log::info!("🔑 群组 {} 密钥已更新到第{}代（{}名成员）", self.group_id, distribution.epoch, self.members.len());
+        Ok(())
+    }
+
+    fn install(&mut self, epoch: u64, key: [u8; 32], members: Vec<String>) {
+        self.keys.push_back((epoch, key));
+        while self.keys.len() > RETAINED_EPOCHS + 1 {
+            self.keys.pop_front();
+        }
+        self.members = members;
+    }
+
+    /// 群组ID
+    pub fn group_id(&self) -> &str {
+        &self.group_id
+    }
+
+    /// 群组消息主题
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// 管理员DID
+    pub fn admin_did(&self) -> &str {
+        &self.admin_did
+    }
+
+    /// 当前成员
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+
+    /// 当前密钥代数（已被移出时为None）
+    pub fn epoch(&self) -> Option<u64> {
+        self.keys.back().map(|(epoch, _)| *epoch)
+    }
+
+    /// 用当前代密钥加密
+    pub fn encrypt(&self, sender_did: &str, plaintext: &[u8]) -> Result<GroupCiphertext> {
+        let (epoch, key) = self.keys.back()
+            .ok_or_else(|| anyhow::anyhow!("没有可用的群组密钥: {}", self.group_id))?;
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let aad = GroupCiphertext::associated_data(&self.group_id, *epoch, sender_did);
+        let ciphertext = ChaCha20Poly1305::new(key.into())
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: &aad })
+            .map_err(|_| anyhow::anyhow!("加密群组消息失败"))?;
+        Ok(GroupCiphertext { group_id: self.group_id.clone(), epoch: *epoch, nonce, ciphertext })
+    }
+
+    /// 解密群组消息（发送者必须是当前成员）
+    pub fn decrypt(&self, sender_did: &str, message: &GroupCiphertext) -> Result<Vec<u8>> {
+        if message.group_id != self.group_id {
+            anyhow::bail!("群组消息不属于该群组: {}", message.group_id);
+        }
+        if !self.members.iter().any(|member| member == sender_did) {
+            anyhow::bail!("发送者不是群组成员: {}", sender_did);
+        }
+        let (_, key) = self.keys.iter()
+            .find(|(epoch, _)| *epoch == message.epoch)
+            .ok_or_else(|| anyhow::anyhow!("没有第{}代群组密钥", message.epoch))?;
+
+        let aad = GroupCiphertext::associated_data(&self.group_id, message.epoch, sender_did);
+        ChaCha20Poly1305::new(key.into())
+            .decrypt(Nonce::from_slice(&message.nonce), Payload { msg: &message.ciphertext, aad: &aad })
+            .map_err(|_| anyhow::anyhow!("解密群组消息失败：密钥不匹配或内容被篡改"))
+    }
+}
+
+/// 群组管理员：维护成员列表，成员变更时轮换密钥
+pub struct GroupAdmin {
+    members: BTreeMap<String, DIDDocument>,
+    keyring: GroupKeyring,
+    key: [u8; 32],
+}
+
+impl GroupAdmin {
+    /// 创建群组（生成第1代密钥）
+    pub fn new(group_id: &str, topic: &str, admin_did: &str, members: Vec<DIDDocument>) -> Self {
+        let mut admin = Self {
+            members: members.into_iter()
+                .filter(|document| document.id != admin_did)
+                .map(|document| (document.id.clone(), document))
+                .collect(),
+            keyring: GroupKeyring {
+                group_id: group_id.to_string(),
+                topic: topic.to_string(),
+                admin_did: admin_did.to_string(),
+                members: Vec::new(),
+                keys: VecDeque::new(),
+            },
+            key: [0u8; 32],
+        };
+        admin.rotate();
+        admin
+    }
+
+    /// 添加成员并轮换密钥，返回是否新增
+    pub fn add_member(&mut self, member: DIDDocument) -> bool {
+        if member.id == self.keyring.admin_did || self.members.contains_key(&member.id) {
+            return false;
+        }
+        self.members.insert(member.id.clone(), member);
+        self.rotate();
+        true
+    }
+
+    /// 移除成员并轮换密钥，返回是否存在
+    pub fn remove_member(&mut self, did: &str) -> bool {
+        if self.members.remove(did).is_none() {
+            return false;
+        }
+        self.rotate();
+        true
+    }
+
+    /// 生成新一代密钥
+    fn rotate(&mut self) {
+        rand::thread_rng().fill_bytes(&mut self.key);
+        let epoch = self.keyring.epoch().unwrap_or(0) + 1;
+        let mut members = vec![self.keyring.admin_did.clone()];
+        members.extend(self.members.keys().cloned());
+        self.keyring.install(epoch, self.key, members);
+    }
+
+    /// 管理员自己的密钥环
+    pub fn keyring(&self) -> &GroupKeyring {
+        &self.keyring
+    }
+
+    /// 为当前代密钥生成下发（分别封装给每个成员）
+    pub fn distribution(&self, signer: &dyn Signer, now: u64) -> Result<GroupKeyDistribution> {
+        if signer.did() != self.keyring.admin_did {
+            anyhow::bail!("只有管理员可以下发群组密钥");
+        }
+        let wrapped_keys = self.members.iter()
+            .map(|(did, document)| {
+                e2e_encryption::encrypt_for(&self.keyring.admin_did, document, &self.key)
+                    .map(|payload| (did.clone(), payload))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        GroupKeyDistribution {
+            group_id: self.keyring.group_id.clone(),
+            topic: self.keyring.topic.clone(),
+            admin_did: self.keyring.admin_did.clone(),
+            epoch: self.keyring.epoch().unwrap_or(0),
+            members: self.keyring.members.clone(),
+            wrapped_keys,
+            issued_at: now,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did_resolver::DIDResolver;
+
+    #[test]
+    fn test_rotation_excludes_removed_member() {
+        let admin_key = KeyPair::generate().unwrap();
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let document = |kp: &KeyPair| DIDResolver::resolve_did_key(&kp.did).unwrap();
+
+        let mut admin = GroupAdmin::new("team", "diap/groups/team", &admin_key.did, vec![document(&alice), document(&bob)]);
+        let first = admin.distribution(&admin_key, 0).unwrap();
+        let mut alice_ring = GroupKeyring::join(&alice, &first).unwrap();
+        let mut bob_ring = GroupKeyring::join(&bob, &first).unwrap();
+        assert_eq!(alice_ring.epoch(), Some(1));
+
+        let sealed = alice_ring.encrypt(&alice.did, b"hello team").unwrap();
+        assert_eq!(bob_ring.decrypt(&alice.did, &sealed).unwrap(), b"hello team");
+        assert_eq!(admin.keyring().decrypt(&alice.did, &sealed).unwrap(), b"hello team");
+        assert!(bob_ring.decrypt(&bob.did, &sealed).is_err(), "发送者与关联数据不符");
+        let from_bob = bob_ring.encrypt(&bob.did, b"bye").unwrap();
+
+        // 移除bob后轮换：bob拿不到新密钥，alice仍能解密轮换前的消息
+        assert!(admin.remove_member(&bob.did));
+        let second = admin.distribution(&admin_key, 1).unwrap();
+        assert!(bob_ring.apply(&bob, &second).is_err());
+        assert_eq!(bob_ring.epoch(), None);
+        alice_ring.apply(&alice, &second).unwrap();
+        assert_eq!(alice_ring.epoch(), Some(2));
+        assert!(alice_ring.apply(&alice, &second).is_err(), "重复的下发");
+
+        let after = admin.keyring().encrypt(&admin_key.did, b"without bob").unwrap();
+        assert_eq!(alice_ring.decrypt(&admin_key.did, &after).unwrap(), b"without bob");
+        assert_eq!(alice_ring.decrypt(&alice.did, &sealed).unwrap(), b"hello team");
+        assert!(alice_ring.decrypt(&bob.did, &from_bob).is_err(), "已移除成员的消息");
+
+        // 篡改下发内容后签名失效
+        let mut forged = second.clone();
+        forged.members.push(bob.did.clone());
+        forged.epoch = 3;
+        assert!(alice_ring.apply(&alice, &forged).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_group_flow() {
+        use crate::feature_toggles::{FeatureToggles, SdkFeature, TogglePolicy};
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+        use std::sync::Arc;
+
+        // 离线测试：关闭逐条消息ZKP，消息只携带签名
+        async fn agent(keypair: &KeyPair) -> PubsubAuthenticator {
+            let toggles = FeatureToggles::new(TogglePolicy::new()).with_default(SdkFeature::ZkpPerMessage, false);
+            let authenticator = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None)
+                .with_feature_toggles(Arc::new(toggles));
+            authenticator.set_local_signer(Arc::new(keypair.clone()), libp2p::PeerId::random(), "QmCurrent".to_string()).await.unwrap();
+            authenticator
+        }
+
+        let (admin_key, alice_key, bob_key) = (KeyPair::generate().unwrap(), KeyPair::generate().unwrap(), KeyPair::generate().unwrap());
+        let (admin, alice, bob) = (agent(&admin_key).await, agent(&alice_key).await, agent(&bob_key).await);
+        let document = |kp: &KeyPair| DIDResolver::resolve_did_key(&kp.did).unwrap();
+
+        let invite = admin.create_group("team", "diap/groups/team", vec![document(&alice_key), document(&bob_key)]).await.unwrap();
+        assert!(alice.on_group_message(&invite).await.unwrap().is_none());
+        assert!(bob.on_group_message(&invite).await.unwrap().is_none());
+
+        let message = alice.send_to_group("team", b"standup in 5").await.unwrap();
+        assert!(!message.content.windows(7).any(|w| w == b"standup"));
+        assert_eq!(bob.on_group_message(&message).await.unwrap().unwrap(), b"standup in 5");
+        assert_eq!(admin.on_group_message(&message).await.unwrap().unwrap(), b"standup in 5");
+
+        let rotation = admin.remove_group_member("team", &bob_key.did).await.unwrap();
+        alice.on_group_message(&rotation).await.unwrap();
+        assert!(bob.on_group_message(&rotation).await.is_err());
+        assert!(bob.group("team").await.is_none());
+        assert!(bob.send_to_group("team", b"still here?").await.is_err());
+
+        let message = admin.send_to_group("team", b"bob left").await.unwrap();
+        assert_eq!(alice.group("team").await.unwrap().epoch(), Some(2));
+        assert_eq!(alice.on_group_message(&message).await.unwrap().unwrap(), b"bob left");
+        assert!(bob.on_group_message(&message).await.is_err());
+    }
+}
@@ -0,0 +1,288 @@
+// DIAP Rust SDK - 内嵌HTTP服务的公共部分
+// 管理接口、REST接口和HTTP传输共用的HTTP/1.1请求解析与响应写出（每个连接处理一个请求）
+
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Semaphore;
+
+/// 请求头最大长度
+pub const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// 读取请求头的超时（防止慢速客户端长期占用连接）
+pub const REQUEST_HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 读取请求体的超时
+pub const REQUEST_BODY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 每个服务的最大并发连接数
+pub const MAX_CONCURRENT_CONNECTIONS: usize = 256;
+
+/// 创建并发连接限制：accept前取得许可，连接处理结束时释放
+pub fn connection_limiter() -> Arc<Semaphore> {
+    Arc::new(Semaphore::new(MAX_CONCURRENT_CONNECTIONS))
+}
+
+/// 读取请求失败的原因
+#[derive(Debug, thiserror::Error)]
+pub enum ReadRequestError {
+    #[error("请求格式错误: {0}")]
+    Malformed(String),
+
+    #[error("请求头过大")]
+    HeadTooLarge,
+
+    #[error("请求体过大")]
+    BodyTooLarge,
+
+    #[error("读取请求超时")]
+    Timeout,
+
+    #[error("连接在请求结束前关闭")]
+    Closed,
+
+    #[error("读取请求失败: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl ReadRequestError {
+    /// 应回复给客户端的状态码；连接已关闭或IO错误时为None
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Malformed(_) => Some(400),
+            Self::HeadTooLarge => Some(431),
+            Self::BodyTooLarge => Some(413),
+            Self::Timeout => Some(408),
+            Self::Closed | Self::Io(_) => None,
+        }
+    }
+}
+
+/// 解析后的HTTP请求
+#[derive(Debug, Clone, Default)]
+pub struct HttpRequest {
+    /// 请求方法（大写）
+    pub method: String,
+
+    /// 路径（不含查询串，已做百分号解码）
+    pub path: String,
+
+    /// 原始查询串
+    pub query: String,
+
+    /// 请求头（保留原始大小写）
+    pub headers: Vec<(String, String)>,
+
+    /// 请求体
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// 按名称（不区分大小写）读取请求头
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 读取查询参数（未解码）
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query.split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// 读取请求（请求头和Content-Length指定的请求体），请求头和请求体分别有读取超时
+pub async fn read_request<S: AsyncRead + Unpin>(stream: &mut S, max_body: usize) -> Result<HttpRequest, ReadRequestError> {
+    read_request_within(stream, max_body, REQUEST_HEAD_TIMEOUT, REQUEST_BODY_TIMEOUT).await
+}
+
+async fn read_request_within<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    max_body: usize,
+    head_timeout: Duration,
+    body_timeout: Duration,
+) -> Result<HttpRequest, ReadRequestError> {
+    let mut data = Vec::new();
+    let head_end = tokio::time::timeout(head_timeout, read_head(stream, &mut data))
+        .await
+        .map_err(|_| ReadRequestError::Timeout)??;
+
+    let head = String::from_utf8_lossy(&data[..head_end]).into_owned();
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let method = parts.next().unwrap_or_default().to_ascii_uppercase();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let headers: Vec<(String, String)> = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let mut request = HttpRequest {
+        method,
+        path: percent_decode(path),
+        query: query.to_string(),
+        headers,
+        body: Vec::new(),
+    };
+
+    let content_length = match request.header("content-length") {
+        Some(value) => value.parse::<usize>()
+            .map_err(|_| ReadRequestError::Malformed(format!("无效的Content-Length: {}", value)))?,
+        None => 0,
+    };
+    if content_length > max_body {
+        return Err(ReadRequestError::BodyTooLarge);
+    }
+    let mut body = data.split_off(head_end);
+    tokio::time::timeout(body_timeout, read_body(stream, &mut body, content_length))
+        .await
+        .map_err(|_| ReadRequestError::Timeout)??;
+    body.truncate(content_length);
+    request.body = body;
+    Ok(request)
+}
+
+/// 读到请求头结束，返回请求头长度（含结尾空行）
+async fn read_head<S: AsyncRead + Unpin>(stream: &mut S, data: &mut Vec<u8>) -> Result<usize, ReadRequestError> {
+    let mut buf = [0u8; 1024];
+    loop {
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            return Ok(pos + 4);
+        }
+        if data.len() > MAX_REQUEST_HEAD {
+            return Err(ReadRequestError::HeadTooLarge);
+        }
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(ReadRequestError::Closed);
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+}
+
+async fn read_body<S: AsyncRead + Unpin>(stream: &mut S, body: &mut Vec<u8>, len: usize) -> Result<(), ReadRequestError> {
+    let mut buf = [0u8; 4096];
+    while body.len() < len {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(ReadRequestError::Closed);
+        }
+        body.extend_from_slice(&buf[..n]);
+    }
+    Ok(())
+}
+
+/// 写出完整响应并关闭连接
+pub async fn write_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    code: u16,
+    content_type: &str,
+    body: &[u8],
+) -> Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        code, reason_phrase(code), content_type, body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// 状态码对应的原因短语
+pub fn reason_phrase(code: u16) -> &'static str {
+    match code {
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        426 => "Upgrade Required",
+        431 => "Request Header Fields Too Large",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ if code >= 500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+/// 百分号解码（非法编码原样保留）
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// 常量时间比较（令牌校验）
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request_with_body() {
+        let raw = b"POST /diap/api/identities/did%3Akey%3Az6Mk?x=1 HTTP/1.1\r\nHost: a\r\nContent-Length: 11\r\n\r\n{\"a\":\"b\"}\r\n";
+        let request = read_request(&mut &raw[..], 1024).await.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/diap/api/identities/did:key:z6Mk");
+        assert_eq!(request.query_param("x"), Some("1"));
+        assert_eq!(request.header("content-length"), Some("11"));
+        assert_eq!(request.body, b"{\"a\":\"b\"}\r\n");
+
+        // 请求体超过上限
+        assert_eq!(read_request(&mut &raw[..], 4).await.unwrap_err().status(), Some(413));
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%41"), "%zzA");
+    }
+
+    #[tokio::test]
+    async fn test_read_request_rejects_bad_length_and_slow_clients() {
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: abc\r\n\r\n{}";
+        assert_eq!(read_request(&mut &raw[..], 1024).await.unwrap_err().status(), Some(400));
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: -1\r\n\r\n";
+        assert_eq!(read_request(&mut &raw[..], 1024).await.unwrap_err().status(), Some(400));
+
+        // 只发送部分请求头后停住
+        let timeout = Duration::from_millis(50);
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        let err = read_request_within(&mut server, 0, timeout, timeout).await.unwrap_err();
+        assert_eq!(err.status(), Some(408));
+
+        // 请求体发送不完整
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client.write_all(b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}").await.unwrap();
+        let err = read_request_within(&mut server, 1024, timeout, timeout).await.unwrap_err();
+        assert_eq!(err.status(), Some(408));
+    }
+}
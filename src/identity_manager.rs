@@ -3,9 +3,16 @@
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use crate::key_manager::KeyPair;
-use crate::did_builder::{DIDBuilder, DIDDocument, get_did_document_from_cid};
+use crate::key_manager::{KeyPair, SignedEnvelope};
+use crate::did_builder::{DIDBuilder, DIDDocument, DIDVersion, get_did_document_from_cid, get_did_document_history};
 use crate::ipfs_client::IpfsClient;
+use crate::did_revocation::RevocationRegistry;
+use crate::audit_log::{AuditEvent, AuditLog};
+use crate::did_commitment::{CidCommitment, CommitmentStatus, PendingPublication};
+use crate::latency_budget::{self, LatencyBudget};
+use crate::transparency_log::{RotationEvidence, TransparencyPolicy};
+use dashmap::DashMap;
+use std::sync::Arc;
 // 注意：已移除对zkp_prover的依赖，改用Noir ZKP
 use crate::encrypted_peer_id::{EncryptedPeerID, decrypt_peer_id_with_secret, verify_peer_id_signature};
 use libp2p::PeerId;
@@ -74,12 +81,49 @@ pub struct IdentityVerification {
     
     /// 验证时间
     pub verified_at: String,
+    
+    /// 预提交的文档尚不能获取（两阶段发布），身份待定
+    #[serde(default)]
+    pub pending: bool,
+    
+    /// 超出延迟预算，结果只包含已完成的步骤
+    #[serde(default)]
+    pub provisional: bool,
+}
+
+/// 批量证明的单个输入
+#[derive(Debug, Clone, Copy)]
+pub struct BindingProofInput<'a> {
+    /// 密钥对
+    pub keypair: &'a KeyPair,
+    
+    /// DID文档
+    pub did_document: &'a DIDDocument,
+    
+    /// DID文档的CID
+    pub cid: &'a str,
+    
+    /// Nonce
+    pub nonce: &'a [u8],
 }
 
 /// 统一身份管理器（简化版本）
+#[derive(Clone)]
 pub struct IdentityManager {
     /// IPFS客户端
     ipfs_client: IpfsClient,
+    
+    /// 吊销注册表（可选）
+    revocation_registry: Option<RevocationRegistry>,
+    
+    /// 审计日志（可选，记录身份注册）
+    audit_log: Option<AuditLog>,
+    
+    /// 轮换信任策略（None时只验证轮换证明的签名）
+    transparency_policy: Option<TransparencyPolicy>,
+    
+    /// 新DID -> 轮换的透明性证据
+    rotation_evidence: Arc<DashMap<String, RotationEvidence>>,
 }
 
 impl IdentityManager {
@@ -89,7 +133,47 @@ impl IdentityManager {
         
         Self {
             ipfs_client,
+            revocation_registry: None,
+            audit_log: None,
+            transparency_policy: None,
+            rotation_evidence: Arc::new(DashMap::new()),
+        }
+    }
+    
+    /// 设置吊销注册表，验证身份时检查DID是否已被吊销
+    pub fn set_revocation_registry(&mut self, registry: RevocationRegistry) {
+        self.revocation_registry = Some(registry);
+    }
+    
+    /// 设置审计日志，身份注册完成后写入注册事件
+    pub fn set_audit_log(&mut self, audit_log: AuditLog) {
+        self.audit_log = Some(audit_log);
+    }
+    
+    /// 设置轮换信任策略，验证轮换后的DID文档时按策略检查透明日志证据
+    pub fn set_transparency_policy(&mut self, policy: TransparencyPolicy) {
+        self.transparency_policy = Some(policy);
+    }
+    
+    /// 记录轮换的透明性证据（按条目中的新DID索引）
+    pub fn add_rotation_evidence(&self, evidence: RotationEvidence) {
+        self.rotation_evidence.insert(evidence.entry.did.clone(), evidence);
+    }
+    
+    /// 验证DID文档携带的轮换证明；没有轮换证明时返回true
+    pub fn verify_key_rotation(&self, did_document: &DIDDocument) -> Result<bool> {
+        let Some(rotation) = &did_document.key_rotation else {
+            return Ok(true);
+        };
+        if rotation.new_did != did_document.id {
+            return Ok(false);
         }
+        
+        let evidence = self.rotation_evidence.get(&did_document.id).map(|e| e.clone());
+        self.transparency_policy
+            .clone()
+            .unwrap_or_default()
+            .verify_rotation(rotation, evidence.as_ref())
     }
     
     /// 便捷构造函数：从文件路径创建身份管理器（已废弃）
@@ -130,7 +214,44 @@ impl IdentityManager {
         log::info!("✅ 身份注册成功");
         log::info!("  DID: {}", publish_result.did);
         log::info!("  CID: {}", publish_result.cid);
+        self.audit_registration(&publish_result.did, &publish_result.cid);
+        
+        Ok(IdentityRegistration {
+            did: publish_result.did,
+            cid: publish_result.cid,
+            did_document: publish_result.did_document,
+            encrypted_peer_id_hex: hex::encode(&publish_result.encrypted_peer_id.signature),
+            registered_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+    
+    /// 📝 两阶段注册第一步：预先计算CID并签署预提交声明（不上传）
+    pub fn precommit_identity(
+        &self,
+        agent_info: &AgentInfo,
+        keypair: &KeyPair,
+        libp2p_peer_id: &PeerId,
+    ) -> Result<PendingPublication> {
+        let mut builder = DIDBuilder::new(self.ipfs_client.clone());
+        for service in &agent_info.services {
+            builder.add_service(&service.service_type, service.endpoint.clone());
+        }
+        
+        let pending = builder.prepare_publish(keypair, libp2p_peer_id)
+            .context("DID预提交失败")?;
+        log::info!("📌 身份预提交: {} -> {}", pending.did, pending.cid);
+        Ok(pending)
+    }
+    
+    /// 📤 两阶段注册第二步：上传预提交的DID文档
+    pub async fn complete_registration(&self, pending: &PendingPublication) -> Result<IdentityRegistration> {
+        let publish_result = DIDBuilder::new(self.ipfs_client.clone())
+            .complete_publish(pending)
+            .await
+            .context("DID发布失败")?;
         
+        log::info!("✅ 两阶段注册完成: {}", publish_result.cid);
+        self.audit_registration(&publish_result.did, &publish_result.cid);
         Ok(IdentityRegistration {
             did: publish_result.did,
             cid: publish_result.cid,
@@ -140,6 +261,61 @@ impl IdentityManager {
         })
     }
     
+    /// 🔍 检查预提交CID的发布状态（文档不可获取时为待定）
+    pub async fn check_commitment(&self, commitment: &CidCommitment) -> Result<CommitmentStatus> {
+        if !commitment.verify()? {
+            return Ok(CommitmentStatus::Invalid { reason: "预提交签名无效".to_string() });
+        }
+        
+        let document = match get_did_document_from_cid(&self.ipfs_client, &commitment.cid).await {
+            Ok(document) => document,
+            Err(e) => {
+                log::info!("⏳ 预提交的DID文档尚不可获取: {} ({})", commitment.cid, e);
+                return Ok(CommitmentStatus::Pending { reason: e.to_string() });
+            }
+        };
+        
+        if document.id != commitment.did {
+            return Ok(CommitmentStatus::Invalid {
+                reason: format!("文档DID不一致: 预提交 {}，文档 {}", commitment.did, document.id),
+            });
+        }
+        if !crate::cid_compute::document_matches_cid(&document, &commitment.cid)? {
+            return Ok(CommitmentStatus::Invalid { reason: "文档内容与CID不匹配".to_string() });
+        }
+        Ok(CommitmentStatus::Published)
+    }
+    
+    /// 🔍 验证基于预提交CID的身份：文档可获取之前返回待定结果
+    pub async fn verify_committed_identity(
+        &self,
+        commitment: &CidCommitment,
+        zkp_proof: &[u8],
+        nonce: &[u8],
+    ) -> Result<IdentityVerification> {
+        match self.check_commitment(commitment).await? {
+            CommitmentStatus::Published => self.verify_identity_with_zkp(&commitment.cid, zkp_proof, nonce).await,
+            CommitmentStatus::Pending { reason } => Ok(IdentityVerification {
+                did: commitment.did.clone(),
+                cid: commitment.cid.clone(),
+                zkp_verified: false,
+                verification_details: vec![format!("⏳ DID文档尚不可获取，身份待定: {}", reason)],
+                verified_at: chrono::Utc::now().to_rfc3339(),
+                pending: true,
+                provisional: false,
+            }),
+            CommitmentStatus::Invalid { reason } => Ok(IdentityVerification {
+                did: commitment.did.clone(),
+                cid: commitment.cid.clone(),
+                zkp_verified: false,
+                verification_details: vec![format!("✗ 预提交无效: {}", reason)],
+                verified_at: chrono::Utc::now().to_rfc3339(),
+                pending: false,
+                provisional: false,
+            }),
+        }
+    }
+    
     /// 🔐 生成DID-CID绑定的ZKP证明
     pub fn generate_binding_proof(
         &self,
@@ -149,6 +325,7 @@ impl IdentityManager {
         nonce: &[u8],
     ) -> Result<Vec<u8>> {
         log::warn!("⚠️  generate_zkp_proof已废弃，请使用Noir ZKP");
+        let start = std::time::Instant::now();
         
         // 返回简单的哈希作为占位符
         use blake2::{Blake2s256, Digest};
@@ -159,24 +336,83 @@ impl IdentityManager {
         hasher.update(&keypair.private_key);
         
         let proof_hash = hasher.finalize();
+        crate::metrics::global().proof_generation.observe_since(start);
         Ok(proof_hash.to_vec())
     }
     
+    /// 🔐 批量生成DID-CID绑定证明
+    /// 使用rayon线程池并行计算，结果顺序与输入一致；任一输入失败则整体返回错误
+    #[cfg(feature = "node")]
+    pub fn prove_batch(&self, inputs: &[BindingProofInput<'_>]) -> Result<Vec<Vec<u8>>> {
+        use rayon::prelude::*;
+        
+        log::info!("🔐 批量生成 {} 个绑定证明", inputs.len());
+        let start_time = std::time::Instant::now();
+        
+        let proofs = inputs
+            .par_iter()
+            .enumerate()
+            .map(|(index, input)| {
+                self.generate_binding_proof(input.keypair, input.did_document, input.cid, input.nonce)
+                    .with_context(|| format!("第 {} 个证明生成失败: {}", index, input.keypair.did))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        
+        log::info!("✅ 批量证明生成完成，耗时: {:?}", start_time.elapsed());
+        Ok(proofs)
+    }
+    
     /// 🔍 验证身份（通过CID + ZKP）
     pub async fn verify_identity_with_zkp(
+        &self,
+        cid: &str,
+        zkp_proof: &[u8],
+        nonce: &[u8],
+    ) -> Result<IdentityVerification> {
+        self.verify_identity_within(cid, zkp_proof, nonce, None).await
+    }
+    
+    /// ⏱️ 在延迟预算内验证身份；超出预算时返回临时结果（provisional）
+    pub async fn verify_identity_with_budget(
+        &self,
+        cid: &str,
+        zkp_proof: &[u8],
+        nonce: &[u8],
+        budget: &LatencyBudget,
+    ) -> Result<IdentityVerification> {
+        self.verify_identity_within(cid, zkp_proof, nonce, Some(budget)).await
+    }
+    
+    #[tracing::instrument(name = "diap.identity_verify", skip_all, fields(cid = %cid))]
+    pub(crate) async fn verify_identity_within(
         &self,
         cid: &str,
         _zkp_proof: &[u8],
         _nonce: &[u8],
+        budget: Option<&LatencyBudget>,
     ) -> Result<IdentityVerification> {
-        log::info!("🔍 开始身份验证流程（ZKP版本）");
-        log::info!("  CID: {}", cid);
-        
         let mut verification_details = Vec::new();
         
         // 步骤1: 从IPFS获取DID文档
-        let did_document = get_did_document_from_cid(&self.ipfs_client, cid).await?;
+        let fetched = latency_budget::run_within(budget, "获取DID文档", get_did_document_from_cid(&self.ipfs_client, cid)).await;
+        let did_document = match fetched {
+            Ok(document) => document?,
+            Err(exceeded) => {
+                return Ok(IdentityVerification {
+                    did: String::new(),
+                    cid: cid.to_string(),
+                    zkp_verified: false,
+                    verification_details: vec![format!("⏱ {}", exceeded)],
+                    verified_at: chrono::Utc::now().to_rfc3339(),
+                    pending: false,
+                    provisional: true,
+                });
+            }
+        };
         verification_details.push(format!("✓ DID文档获取成功: {}", did_document.id));
+        if let Some(previous_cid) = &did_document.previous_version_cid {
+            verification_details.push(format!("✓ 上一版本CID: {}", previous_cid));
+        }
         
         // 步骤2: 计算DID文档哈希
         use blake2::{Blake2s256, Digest};
@@ -198,17 +434,46 @@ impl IdentityManager {
             verification_details.push("✗ ZKP验证失败 - DID与CID绑定无效".to_string());
         }
         
+        // 步骤5: 检查轮换证明（按透明日志策略）
+        let rotation_valid = self.verify_key_rotation(&did_document)?;
+        if let Some(rotation) = &did_document.key_rotation {
+            if rotation_valid {
+                verification_details.push(format!("✓ 密钥轮换可信: {}", rotation.previous_did));
+            } else {
+                log::warn!("⛔ 密钥轮换未通过验证: {} -> {}", rotation.previous_did, did_document.id);
+                verification_details.push(format!("✗ 密钥轮换未通过验证: {}", rotation.previous_did));
+            }
+        }
+        
+        // 步骤6: 检查吊销状态（已吊销的DID即使绑定有效也不可信）
+        let revoked = self.revocation_registry
+            .as_ref()
+            .is_some_and(|registry| registry.is_revoked(&did_document.id));
+        if revoked {
+            log::warn!("⛔ DID已被吊销: {}", did_document.id);
+            verification_details.push(format!("✗ DID已被吊销: {}", did_document.id));
+        } else if self.revocation_registry.is_some() {
+            verification_details.push("✓ DID未被吊销".to_string());
+        }
+        
         log::info!("✅ 身份验证完成");
         
         Ok(IdentityVerification {
             did: did_document.id.clone(),
             cid: cid.to_string(),
-            zkp_verified: zkp_valid,
+            zkp_verified: zkp_valid && rotation_valid && !revoked,
             verification_details,
             verified_at: chrono::Utc::now().to_rfc3339(),
+            pending: false,
+            provisional: false,
         })
     }
     
+    /// 📜 获取DID文档版本链（从最新到最旧）
+    pub async fn get_version_chain(&self, cid: &str, max_versions: usize) -> Result<Vec<DIDVersion>> {
+        get_did_document_history(&self.ipfs_client, cid, max_versions).await
+    }
+    
     /// 🔓 验证PeerID签名（任何人都可以验证）
     pub fn verify_peer_id(
         &self,
@@ -244,6 +509,12 @@ impl IdentityManager {
         decrypt_peer_id_with_secret(&signing_key, encrypted)
     }
     
+    fn audit_registration(&self, did: &str, cid: &str) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record_or_warn(did, AuditEvent::Registration { cid: cid.to_string() });
+        }
+    }
+    
     /// 从DID文档提取公钥（改进版：正确解析multicodec前缀）
     fn extract_public_key(&self, did_document: &DIDDocument) -> Result<Vec<u8>> {
         let vm = did_document.verification_method.first()
@@ -328,6 +599,62 @@ impl IdentityManager {
 mod tests {
     use super::*;
     use libp2p::identity::Keypair as LibP2PKeypair;
+    use crate::did_resolver::DIDResolver;
+    
+    #[test]
+    #[cfg(feature = "node")]
+    fn test_prove_batch_preserves_order() {
+        let manager = IdentityManager::new(IpfsClient::new_public_only(30));
+        
+        let keypairs: Vec<KeyPair> = (0..8).map(|_| KeyPair::generate().unwrap()).collect();
+        let documents: Vec<DIDDocument> = keypairs.iter()
+            .map(|k| DIDResolver::resolve_did_key(&k.did).unwrap())
+            .collect();
+        let inputs: Vec<BindingProofInput> = keypairs.iter().zip(&documents)
+            .map(|(keypair, did_document)| BindingProofInput {
+                keypair,
+                did_document,
+                cid: "bafytest",
+                nonce: b"nonce",
+            })
+            .collect();
+        
+        let proofs = manager.prove_batch(&inputs).unwrap();
+        assert_eq!(proofs.len(), inputs.len());
+        for (input, proof) in inputs.iter().zip(&proofs) {
+            let single = manager.generate_binding_proof(input.keypair, input.did_document, input.cid, input.nonce).unwrap();
+            assert_eq!(&single, proof);
+        }
+    }
+    
+    #[test]
+    fn test_rotation_checked_against_transparency_policy() {
+        use crate::did_builder::KeyRotationProof;
+        use crate::transparency_log::{LogEntry, TransparencyLog};
+        
+        let log = TransparencyLog::new(Arc::new(KeyPair::generate().unwrap()));
+        let old_key = KeyPair::generate().unwrap();
+        let new_key = KeyPair::generate().unwrap();
+        let rotation = KeyRotationProof::sign(&old_key, &new_key.did).unwrap();
+        let mut document = DIDResolver::resolve_did_key(&new_key.did).unwrap();
+        document.key_rotation = Some(rotation.clone());
+        
+        let mut manager = IdentityManager::new(IpfsClient::new_public_only(30));
+        assert!(manager.verify_key_rotation(&document).unwrap());
+        
+        // 要求包含证明后，没有证据的轮换不被信任
+        manager.set_transparency_policy(TransparencyPolicy::require(vec![log.log_did()]));
+        assert!(!manager.verify_key_rotation(&document).unwrap());
+        
+        let entry = LogEntry::rotated(rotation, "bafynew", 1);
+        manager.add_rotation_evidence(log.submit(entry.clone()).unwrap().into_evidence(entry));
+        assert!(manager.verify_key_rotation(&document).unwrap());
+        
+        // 轮换证明指向其他DID
+        let mut forged = DIDResolver::resolve_did_key(&KeyPair::generate().unwrap().did).unwrap();
+        forged.key_rotation = document.key_rotation.clone();
+        assert!(!manager.verify_key_rotation(&forged).unwrap());
+    }
     
     #[tokio::test]
     #[ignore] // 需要实际的IPFS服务和ZKP keys
@@ -527,6 +527,7 @@ impl IpfsBidirectionalVerificationManager {
             id: keypair.did.clone(),
             verification_method: vec![verification_method.clone()],
             authentication: vec![verification_method.id.clone()],
+            key_agreement: Vec::new(),
             service: Some(vec![crate::Service {
                 id: format!("{}#service", keypair.did),
                 service_type: "DIAP Agent Service".to_string(),
@@ -535,6 +536,8 @@ impl IpfsBidirectionalVerificationManager {
                 network_addresses: None,
             }]),
             created: chrono::Utc::now().to_rfc3339(),
+            key_rotation: None,
+            previous_version_cid: None,
         })
     }
     
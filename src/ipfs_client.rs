@@ -3,9 +3,36 @@
 // 边缘服务器专用：仅使用HTTP客户端，无需本地IPFS守护进程
 
 use anyhow::{Context, Result};
-use reqwest::Client;
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::future::Future;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use crate::block_store::BlockStore;
+use crate::error::{DiapError, DiapResult, IpfsErrorKind};
+use crate::gateway_health::{GatewayHealth, GatewayHealthTracker};
+use crate::pin_manager::PinTracker;
+use crate::pinning_provider::{PinataProvider, PinningProvider};
+use std::sync::Arc;
+
+/// 流式上传的分块大小（256KB）
+const UPLOAD_CHUNK_SIZE: usize = 256 * 1024;
+
+/// 可插拔的IPFS内容后端（例如内嵌的纯Rust节点 EmbeddedIpfs）
+/// 配置后上传和获取都优先使用它，失败时再回退到远程API、Pin服务和网关
+#[async_trait]
+pub trait IpfsBackend: Send + Sync {
+    /// 后端名称（写入IpfsUploadResult.provider）
+    fn name(&self) -> &str;
+
+    /// 添加内容并对外提供，返回CID
+    async fn add(&self, content: &[u8]) -> Result<String>;
+
+    /// 按CID获取内容，找不到时返回None
+    async fn get(&self, cid: &str) -> Result<Option<Vec<u8>>>;
+}
 
 /// IPFS上传结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +50,111 @@ pub struct IpfsUploadResult {
     pub provider: String,
 }
 
+/// 重试策略（指数退避 + 随机抖动）
+/// 仅重试瞬时错误：5xx、408/429、连接失败和超时；其余4xx和解析错误立即返回
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 最大尝试次数（含首次，1表示不重试）
+    pub max_attempts: u32,
+    
+    /// 首次重试前的等待时间
+    pub initial_backoff: Duration,
+    
+    /// 最长等待时间
+    pub max_backoff: Duration,
+    
+    /// 退避倍数
+    pub multiplier: f64,
+    
+    /// 抖动比例（0.0-1.0），等待时间在 ±jitter 范围内随机浮动
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 不重试
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+    
+    /// 第attempt次失败后的等待时间（attempt从1开始）
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = base.min(self.max_backoff.as_secs_f64());
+        
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            1.0 + rand::random::<f64>() * 2.0 * jitter - jitter
+        } else {
+            1.0
+        };
+        
+        Duration::from_secs_f64((capped * factor).max(0.0))
+    }
+    
+    /// 判断错误是否值得重试
+    pub fn is_retryable(error: &anyhow::Error) -> bool {
+        for cause in error.chain() {
+            if let Some(status_error) = cause.downcast_ref::<HttpStatusError>() {
+                return Self::is_retryable_status(status_error.status);
+            }
+            if let Some(reqwest_error) = cause.downcast_ref::<reqwest::Error>() {
+                if let Some(status) = reqwest_error.status() {
+                    return Self::is_retryable_status(status);
+                }
+                return reqwest_error.is_timeout() || reqwest_error.is_connect() || reqwest_error.is_request();
+            }
+        }
+        false
+    }
+    
+    fn is_retryable_status(status: StatusCode) -> bool {
+        status.is_server_error()
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS
+    }
+}
+
+/// HTTP状态码错误（用于区分可重试的错误类型）
+#[derive(Debug)]
+pub(crate) struct HttpStatusError {
+    status: StatusCode,
+    message: String,
+}
+
+impl HttpStatusError {
+    pub(crate) fn status(&self) -> StatusCode {
+        self.status
+    }
+    
+    pub(crate) fn error(status: StatusCode, message: String) -> anyhow::Error {
+        anyhow::Error::new(Self { status, message })
+    }
+}
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
 /// IPFS客户端（轻量级版本）
 /// 专为边缘服务器设计，只使用HTTP客户端连接到远程IPFS节点
 #[derive(Clone)]
@@ -33,15 +165,27 @@ pub struct IpfsClient {
     /// 远程IPFS API配置
     api_config: Option<RemoteIpfsConfig>,
     
-    /// Pinata配置
-    pinata_config: Option<PinataConfig>,
+    /// Pin服务提供商（按顺序回退）
+    pinning_providers: Vec<Arc<dyn PinningProvider>>,
     
-    /// 公共网关列表
-    public_gateways: Vec<String>,
+    /// 公共网关（按健康分动态排序）
+    public_gateways: GatewayHealthTracker,
     
     /// 超时时间
     #[allow(dead_code)]
     timeout: Duration,
+    
+    /// 重试策略
+    retry_policy: RetryPolicy,
+    
+    /// 本地块存储（可选，获取内容时优先查询）
+    block_store: Option<BlockStore>,
+    
+    /// Pin跟踪表（可选，上传成功后记录CID）
+    pin_tracker: Option<PinTracker>,
+    
+    /// 内容后端（可选，优先于远程API和网关）
+    backend: Option<Arc<dyn IpfsBackend>>,
 }
 
 /// 远程IPFS节点配置
@@ -60,6 +204,26 @@ pub struct PinataConfig {
 }
 
 impl IpfsClient {
+    /// 按SDK配置创建客户端：未配置远程节点但启用了本地节点时，使用本地节点的API和网关
+    pub fn from_config(config: &crate::config_manager::IpfsConfig) -> Self {
+        let local = &config.local_node;
+        let (api_url, gateway_url) = match (&config.aws_api_url, &config.aws_gateway_url) {
+            (Some(api), Some(gateway)) => (Some(api.clone()), Some(gateway.clone())),
+            _ if local.auto_start => (
+                Some(format!("http://127.0.0.1:{}", local.api_port)),
+                Some(format!("http://127.0.0.1:{}", local.gateway_port)),
+            ),
+            _ => (None, None),
+        };
+        Self::new(
+            api_url,
+            gateway_url,
+            config.pinata_api_key.clone(),
+            config.pinata_api_secret.clone(),
+            config.timeout_seconds,
+        )
+    }
+    
     /// 创建新的IPFS客户端（轻量级版本）
     /// 仅使用HTTP客户端，无需本地守护进程
     pub fn new(
@@ -83,28 +247,28 @@ impl IpfsClient {
             None
         };
         
-        let pinata_config = if let (Some(key), Some(secret)) = (pinata_api_key, pinata_api_secret) {
-            Some(PinataConfig {
-                api_key: key,
-                api_secret: secret,
-            })
-        } else {
-            None
-        };
+        let mut pinning_providers: Vec<Arc<dyn PinningProvider>> = Vec::new();
+        if let (Some(key), Some(secret)) = (pinata_api_key, pinata_api_secret) {
+            pinning_providers.push(Arc::new(PinataProvider::new(key, secret)));
+        }
         
         // 默认公共网关列表
-        let public_gateways = vec![
+        let public_gateways = GatewayHealthTracker::new(vec![
             "https://ipfs.io".to_string(),
             "https://dweb.link".to_string(),
             "https://cloudflare-ipfs.com".to_string(),
-        ];
+        ]);
         
         Self {
             client,
             api_config,
-            pinata_config,
+            pinning_providers,
             public_gateways,
             timeout: Duration::from_secs(timeout_seconds),
+            retry_policy: RetryPolicy::default(),
+            block_store: None,
+            pin_tracker: None,
+            backend: None,
         }
     }
     
@@ -122,37 +286,169 @@ impl IpfsClient {
         Self::new(Some(api_url), Some(gateway_url), None, None, timeout_seconds)
     }
     
+    /// 设置重试策略
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+    
+    /// 获取重试策略
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+    
+    /// 设置本地块存储
+    pub fn with_block_store(mut self, block_store: BlockStore) -> Self {
+        self.block_store = Some(block_store);
+        self
+    }
+    
+    /// 获取本地块存储
+    pub fn block_store(&self) -> Option<&BlockStore> {
+        self.block_store.as_ref()
+    }
+    
+    /// 添加Pin服务提供商（远程IPFS节点不可用时按添加顺序回退）
+    pub fn with_pinning_provider(mut self, provider: Arc<dyn PinningProvider>) -> Self {
+        self.pinning_providers.push(provider);
+        self
+    }
+    
+    /// 已配置的Pin服务提供商名称
+    pub fn pinning_providers(&self) -> Vec<String> {
+        self.pinning_providers.iter().map(|p| p.name().to_string()).collect()
+    }
+    
+    /// 设置Pin跟踪表（通常由PinManager设置）
+    pub fn with_pin_tracker(mut self, pin_tracker: PinTracker) -> Self {
+        self.pin_tracker = Some(pin_tracker);
+        self
+    }
+    
+    /// 设置内容后端（例如EmbeddedIpfs），不再依赖外部IPFS守护进程
+    pub fn with_backend(mut self, backend: Arc<dyn IpfsBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+    
+    /// 运行时添加公共网关，返回是否新增
+    pub fn add_gateway(&self, gateway_url: &str) -> bool {
+        self.public_gateways.add_gateway(gateway_url)
+    }
+    
+    /// 运行时移除公共网关，返回是否存在
+    pub fn remove_gateway(&self, gateway_url: &str) -> bool {
+        self.public_gateways.remove_gateway(gateway_url)
+    }
+    
+    /// 按健康分排序的公共网关列表
+    pub fn public_gateways(&self) -> Vec<String> {
+        self.public_gateways.ranked()
+    }
+    
+    /// 公共网关健康状态
+    pub fn gateway_health(&self) -> Vec<GatewayHealth> {
+        self.public_gateways.snapshot()
+    }
+    
+    /// 按重试策略执行操作
+    async fn with_retry<T, F, Fut>(&self, operation: &str, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match f().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.retry_policy.max_attempts && RetryPolicy::is_retryable(&e) => {
+                    let delay = self.retry_policy.backoff_for(attempt);
+                    log::warn!("⚠️ {}失败（第{}次尝试），{}ms后重试: {}", operation, attempt, delay.as_millis(), e);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    
     /// 上传内容到IPFS
-    /// 优先使用远程API节点，然后回退到Pinata
-    pub async fn upload(&self, content: &str, name: &str) -> Result<IpfsUploadResult> {
-        // 优先尝试远程API节点
+    /// 优先使用远程API节点，然后按顺序回退到各Pin服务提供商
+    pub async fn upload(&self, content: &str, name: &str) -> DiapResult<IpfsUploadResult> {
+        let metrics = crate::metrics::global();
+        let start = Instant::now();
+        let result = self.upload_with_fallback(content, name).await;
+        metrics.ipfs_upload.observe_since(start);
+        if result.is_err() {
+            metrics.ipfs_upload_failures.inc();
+        }
+        result
+    }
+    
+    async fn upload_with_fallback(&self, content: &str, name: &str) -> DiapResult<IpfsUploadResult> {
+        let mut last_error = None;
+        
+        // 优先使用内容后端
+        if let Some(ref backend) = self.backend {
+            match backend.add(content.as_bytes()).await {
+                Ok(cid) => {
+                    log::info!("成功添加到{}: {}", backend.name(), cid);
+                    let result = IpfsUploadResult {
+                        cid,
+                        size: content.len() as u64,
+                        uploaded_at: chrono::Utc::now().to_rfc3339(),
+                        provider: backend.name().to_string(),
+                    };
+                    self.store_block(&result.cid, content).await;
+                    self.track_pin(&result, name);
+                    return Ok(result);
+                }
+                Err(e) => {
+                    log::warn!("{}添加失败: {}, 尝试远程IPFS节点", backend.name(), e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        
+        // 其次尝试远程API节点
         if let Some(ref api_config) = self.api_config {
-            match self.upload_to_remote_api(content, name, api_config).await {
+            match self.with_retry("上传到远程IPFS节点", || self.upload_to_remote_api(content, name, api_config)).await {
                 Ok(result) => {
                     log::info!("成功上传到远程IPFS节点: {}", result.cid);
+                    self.store_block(&result.cid, content).await;
+                    self.track_pin(&result, name);
                     return Ok(result);
                 }
                 Err(e) => {
-                    log::warn!("远程IPFS节点上传失败: {}, 尝试Pinata", e);
+                    log::warn!("远程IPFS节点上传失败: {}, 尝试Pin服务提供商", e);
+                    last_error = Some(e);
                 }
             }
         }
         
-        // 回退到Pinata
-        if let Some(ref pinata) = self.pinata_config {
-            match self.upload_to_pinata(content, name, pinata).await {
+        // 回退到Pin服务提供商
+        for provider in &self.pinning_providers {
+            let operation = format!("上传到{}", provider.name());
+            match self.with_retry(&operation, || provider.upload(&self.client, content, name)).await {
                 Ok(result) => {
-                    log::info!("成功上传到Pinata: {}", result.cid);
+                    log::info!("成功上传到{}: {}", provider.name(), result.cid);
+                    self.track_pin(&result, name);
                     return Ok(result);
                 }
                 Err(e) => {
-                    log::error!("Pinata上传失败: {}", e);
-                    anyhow::bail!("所有IPFS上传方式都失败");
+                    log::error!("{}上传失败: {}", provider.name(), e);
+                    last_error = Some(e);
                 }
             }
         }
         
-        anyhow::bail!("未配置任何IPFS上传方式。请提供远程IPFS节点API或Pinata凭据")
+        match last_error {
+            Some(e) => Err(DiapError::from_ipfs(e.context("所有IPFS上传方式都失败"))),
+            None => Err(DiapError::ipfs(
+                IpfsErrorKind::NotConfigured,
+                "未配置任何IPFS上传方式。请提供远程IPFS节点API或Pin服务提供商凭据",
+            )),
+        }
     }
     
     /// 上传到远程IPFS API节点
@@ -177,8 +473,102 @@ impl IpfsClient {
             .await
             .context("发送上传请求失败")?;
         
+        Self::parse_add_response(response).await
+    }
+    
+    /// 以流的方式上传内容，不在内存中保留完整内容
+    /// 仅支持远程API节点（Pinata的JSON接口无法接收任意文件）
+    /// 流只能读取一次，因此不会重试
+    pub async fn upload_stream<R>(&self, reader: R, name: &str) -> Result<IpfsUploadResult>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        self.upload_body(Self::reader_body(reader), None, name).await
+    }
+    
+    /// 以流的方式上传本地文件（适用于大型能力清单或模型文件）
+    /// 失败重试时重新打开文件
+    pub async fn upload_file(&self, path: impl AsRef<Path>) -> Result<IpfsUploadResult> {
+        let path = path.as_ref();
+        let name = path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("file");
+        
+        self.with_retry("流式上传文件", || async move {
+            let file = tokio::fs::File::open(path).await
+                .with_context(|| format!("无法打开文件: {:?}", path))?;
+            let length = file.metadata().await
+                .with_context(|| format!("无法读取文件信息: {:?}", path))?
+                .len();
+            
+            log::info!("📤 流式上传文件: {:?} ({} 字节)", path, length);
+            self.upload_body(Self::reader_body(file), Some(length), name).await
+        }).await
+    }
+    
+    /// 将AsyncRead包装为分块的请求体
+    fn reader_body<R>(reader: R) -> reqwest::Body
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let stream = futures::stream::try_unfold(reader, |mut reader| async move {
+            let mut chunk = vec![0u8; UPLOAD_CHUNK_SIZE];
+            let n = reader.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok::<_, std::io::Error>(None);
+            }
+            chunk.truncate(n);
+            Ok(Some((chunk, reader)))
+        });
+        reqwest::Body::wrap_stream(stream)
+    }
+    
+    /// 上传流式请求体到远程API节点
+    async fn upload_body(
+        &self,
+        body: reqwest::Body,
+        length: Option<u64>,
+        name: &str,
+    ) -> Result<IpfsUploadResult> {
+        use reqwest::multipart;
+        
+        let api_config = self.api_config.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("流式上传需要配置远程IPFS节点API"))?;
+        
+        let part = match length {
+            Some(length) => multipart::Part::stream_with_length(body, length),
+            None => multipart::Part::stream(body),
+        };
+        let form = multipart::Form::new()
+            .text("pin", "true")
+            .part("file", part.file_name(name.to_string()));
+        
+        let url = format!("{}/api/v0/add", api_config.api_url);
+        
+        let response = self.client
+            .post(&url)
+            .multipart(form)
+            .send()
+            .await
+            .context("发送上传请求失败")?;
+        
+        let result = Self::parse_add_response(response).await?;
+        log::info!("成功流式上传到远程IPFS节点: {}", result.cid);
+        self.track_pin(&result, name);
+        Ok(result)
+    }
+    
+    /// 记录上传的CID到Pin跟踪表
+    fn track_pin(&self, result: &IpfsUploadResult, name: &str) {
+        if let Some(ref tracker) = self.pin_tracker {
+            tracker.record_upload(result, name);
+        }
+    }
+    
+    /// 解析 /api/v0/add 的响应
+    pub(crate) async fn parse_add_response(response: reqwest::Response) -> Result<IpfsUploadResult> {
         if !response.status().is_success() {
-            anyhow::bail!("上传失败: {}", response.status());
+            return Err(HttpStatusError::error(response.status(), format!("上传失败: {}", response.status())));
         }
         
         let result: serde_json::Value = response.json().await?;
@@ -199,71 +589,44 @@ impl IpfsClient {
         })
     }
     
-    /// 上传到Pinata
-    async fn upload_to_pinata(
-        &self,
-        content: &str,
-        name: &str,
-        config: &PinataConfig,
-    ) -> Result<IpfsUploadResult> {
-        let url = "https://api.pinata.cloud/pinning/pinJSONToIPFS";
-        
-        // 构建请求体
-        let body = serde_json::json!({
-            "pinataContent": serde_json::from_str::<serde_json::Value>(content)?,
-            "pinataMetadata": {
-                "name": name,
-                "keyvalues": {
-                    "type": "did-document",
-                    "uploaded_by": "diap-rs-sdk"
-                }
-            }
-        });
-        
-        // 发送请求
-        let response = self.client
-            .post(url)
-            .header("pinata_api_key", &config.api_key)
-            .header("pinata_secret_api_key", &config.api_secret)
-            .json(&body)
-            .send()
-            .await
-            .context("发送请求到Pinata失败")?;
+    /// 从IPFS获取内容
+    pub async fn get(&self, cid: &str) -> DiapResult<String> {
+        log::info!("🔍 开始从IPFS获取内容: {}", cid);
         
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Pinata返回错误 {}: {}", status, error_text);
+        if let Some(content) = self.load_block(cid).await {
+            log::info!("📦 从本地块存储获取内容: {}", cid);
+            return Ok(content);
         }
         
-        // 解析响应
-        #[derive(Deserialize)]
-        struct PinataResponse {
-            #[serde(rename = "IpfsHash")]
-            ipfs_hash: String,
-            #[serde(rename = "PinSize")]
-            pin_size: u64,
+        let metrics = crate::metrics::global();
+        let start = Instant::now();
+        let fetched = self.get_from_network(cid).await;
+        metrics.ipfs_fetch.observe_since(start);
+        if fetched.is_err() {
+            metrics.ipfs_fetch_failures.inc();
         }
-        
-        let pinata_response: PinataResponse = response.json().await
-            .context("解析Pinata响应失败")?;
-        
-        Ok(IpfsUploadResult {
-            cid: pinata_response.ipfs_hash,
-            size: pinata_response.pin_size,
-            uploaded_at: chrono::Utc::now().to_rfc3339(),
-            provider: "Pinata".to_string(),
-        })
+        let content = fetched.map_err(DiapError::from_ipfs)?;
+        self.store_block(cid, &content).await;
+        Ok(content)
     }
     
-    /// 从IPFS获取内容
-    pub async fn get(&self, cid: &str) -> Result<String> {
-        log::info!("🔍 开始从IPFS获取内容: {}", cid);
+    /// 从内容后端、配置网关或公共网关获取内容
+    async fn get_from_network(&self, cid: &str) -> Result<String> {
+        if let Some(ref backend) = self.backend {
+            match backend.get(cid).await {
+                Ok(Some(data)) => {
+                    log::info!("✅ 从{}获取内容: {}", backend.name(), cid);
+                    return String::from_utf8(data).context("内容不是有效的UTF-8");
+                }
+                Ok(None) => log::info!("{}中找不到内容: {}", backend.name(), cid),
+                Err(e) => log::warn!("❌ 从{}获取失败: {}", backend.name(), e),
+            }
+        }
         
         // 优先使用配置的网关
         if let Some(ref api_config) = self.api_config {
             log::info!("尝试从配置网关获取: {}", api_config.gateway_url);
-            match self.get_from_gateway(&api_config.gateway_url, cid).await {
+            match self.with_retry("从配置网关获取", || self.get_from_gateway(&api_config.gateway_url, cid)).await {
                 Ok(content) => {
                     log::info!("✅ 成功从配置网关获取内容: {}", cid);
                     return Ok(content);
@@ -274,18 +637,62 @@ impl IpfsClient {
             }
         }
         
-        // 使用公共IPFS网关
-        for gateway in &self.public_gateways {
-            match self.get_from_gateway(gateway, cid).await {
-                Ok(content) => return Ok(content),
+        // 使用公共IPFS网关（健康分高的优先）
+        let mut last_error = None;
+        for gateway in self.public_gateways.ranked() {
+            let started = Instant::now();
+            match self.get_from_gateway(&gateway, cid).await {
+                Ok(content) => {
+                    self.public_gateways.record_success(&gateway, started.elapsed());
+                    return Ok(content);
+                }
                 Err(e) => {
+                    self.public_gateways.record_failure(&gateway);
                     log::warn!("从{}获取失败: {}", gateway, e);
+                    last_error = Some(e);
                     continue;
                 }
             }
         }
         
-        anyhow::bail!("无法从任何网关获取内容")
+        // 保留最后一个网关的错误，便于区分超时和内容不存在
+        match last_error {
+            Some(e) => Err(e.context("无法从任何网关获取内容")),
+            None => anyhow::bail!("无法从任何网关获取内容"),
+        }
+    }
+    
+    /// 从本地块存储读取（读取失败或不是UTF-8时视为未命中）
+    async fn load_block(&self, cid: &str) -> Option<String> {
+        let store = self.block_store.as_ref()?;
+        match store.get(cid).await {
+            Ok(Some(data)) => String::from_utf8(data).ok(),
+            Ok(None) => None,
+            Err(e) => {
+                log::warn!("读取本地块存储失败: {}", e);
+                None
+            }
+        }
+    }
+    
+    /// 回填本地块存储（失败只记录日志）；内容与CID不符时不写入，一次恶意网关响应不会永久污染缓存
+    async fn store_block(&self, cid: &str, content: &str) {
+        if let Some(ref store) = self.block_store {
+            match crate::cid_compute::content_matches_cid(content.as_bytes(), cid) {
+                Ok(true) => {}
+                Ok(false) => {
+                    log::warn!("⚠️ 内容与CID不符，不写入本地块存储: {}", cid);
+                    return;
+                }
+                Err(e) => {
+                    log::warn!("计算CID失败，不写入本地块存储: {} ({})", cid, e);
+                    return;
+                }
+            }
+            if let Err(e) = store.put(cid, content.as_bytes()).await {
+                log::warn!("写入本地块存储失败: {}", e);
+            }
+        }
     }
     
     /// 从指定网关获取内容
@@ -299,7 +706,7 @@ impl IpfsClient {
             .context("发送请求失败")?;
         
         if !response.status().is_success() {
-            anyhow::bail!("网关返回错误: {}", response.status());
+            return Err(HttpStatusError::error(response.status(), format!("网关返回错误: {}", response.status())));
         }
         
         let content = response.text().await
@@ -308,33 +715,119 @@ impl IpfsClient {
         Ok(content)
     }
     
-    /// Pin内容到远程IPFS节点
-    pub async fn pin(&self, cid: &str) -> Result<()> {
+    /// Pin内容（优先远程IPFS节点，其次第一个Pin服务提供商）
+    pub async fn pin(&self, cid: &str) -> DiapResult<()> {
         if let Some(ref api_config) = self.api_config {
-            let url = format!("{}/api/v0/pin/add?arg={}", api_config.api_url, cid);
-            
-            let response = self.client
-                .post(&url)
-                .send()
-                .await
-                .context("发送pin请求失败")?;
-            
-            if !response.status().is_success() {
-                anyhow::bail!("Pin失败: {}", response.status());
-            }
-            
+            self.with_retry("Pin", || self.pin_on_remote_api(cid, api_config)).await
+                .map_err(DiapError::from_ipfs)?;
             log::info!("成功pin内容: {}", cid);
             Ok(())
+        } else if let Some(provider) = self.pinning_providers.first() {
+            self.with_retry(&format!("{} Pin", provider.name()), || provider.pin(&self.client, cid)).await
+                .map_err(DiapError::from_ipfs)?;
+            log::info!("成功通过{} pin内容: {}", provider.name(), cid);
+            Ok(())
         } else {
             log::warn!("未配置远程IPFS节点，跳过pin操作");
             Ok(())
         }
     }
+    
+    /// 查询内容是否仍被pin（优先远程IPFS节点，其次第一个Pin服务提供商）
+    pub async fn is_pinned(&self, cid: &str) -> DiapResult<bool> {
+        if let Some(ref api_config) = self.api_config {
+            self.with_retry("查询Pin状态", || self.is_pinned_on_remote_api(cid, api_config)).await
+                .map_err(DiapError::from_ipfs)
+        } else if let Some(provider) = self.pinning_providers.first() {
+            self.with_retry(&format!("查询{} Pin状态", provider.name()), || provider.is_pinned(&self.client, cid)).await
+                .map_err(DiapError::from_ipfs)
+        } else {
+            Err(DiapError::ipfs(IpfsErrorKind::NotConfigured, "未配置远程IPFS节点或Pin服务提供商，无法查询pin状态"))
+        }
+    }
+    
+    /// 取消pin（优先远程IPFS节点，其次第一个Pin服务提供商）
+    pub async fn unpin(&self, cid: &str) -> DiapResult<()> {
+        if let Some(ref api_config) = self.api_config {
+            self.with_retry("取消Pin", || self.unpin_on_remote_api(cid, api_config)).await
+                .map_err(DiapError::from_ipfs)?;
+        } else if let Some(provider) = self.pinning_providers.first() {
+            self.with_retry(&format!("取消{} Pin", provider.name()), || provider.unpin(&self.client, cid)).await
+                .map_err(DiapError::from_ipfs)?;
+        } else {
+            return Err(DiapError::ipfs(IpfsErrorKind::NotConfigured, "未配置远程IPFS节点或Pin服务提供商，无法取消pin"));
+        }
+        
+        log::info!("已取消pin: {}", cid);
+        Ok(())
+    }
+    
+    async fn pin_on_remote_api(&self, cid: &str, config: &RemoteIpfsConfig) -> Result<()> {
+        let url = format!("{}/api/v0/pin/add?arg={}", config.api_url, cid);
+        
+        let response = self.client
+            .post(&url)
+            .send()
+            .await
+            .context("发送pin请求失败")?;
+        
+        if !response.status().is_success() {
+            return Err(HttpStatusError::error(response.status(), format!("Pin失败: {}", response.status())));
+        }
+        
+        Ok(())
+    }
+    
+    async fn is_pinned_on_remote_api(&self, cid: &str, config: &RemoteIpfsConfig) -> Result<bool> {
+        let url = format!("{}/api/v0/pin/ls?arg={}&type=recursive", config.api_url, cid);
+        
+        let response = self.client
+            .post(&url)
+            .send()
+            .await
+            .context("发送pin查询请求失败")?;
+        
+        let status = response.status();
+        if status.is_success() {
+            return Ok(true);
+        }
+        
+        // Kubo对未pin的内容返回500和 "is not pinned"
+        let error_text = response.text().await.unwrap_or_default();
+        if error_text.contains("not pinned") {
+            return Ok(false);
+        }
+        Err(HttpStatusError::error(status, format!("查询pin状态失败 {}: {}", status, error_text)))
+    }
+    
+    async fn unpin_on_remote_api(&self, cid: &str, config: &RemoteIpfsConfig) -> Result<()> {
+        let url = format!("{}/api/v0/pin/rm?arg={}", config.api_url, cid);
+        
+        let response = self.client
+            .post(&url)
+            .send()
+            .await
+            .context("发送取消pin请求失败")?;
+        
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            // 本来就没有pin，视为成功
+            if error_text.contains("not pinned") {
+                return Ok(());
+            }
+            return Err(HttpStatusError::error(status, format!("取消pin失败 {}: {}", status, error_text)));
+        }
+        
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     
     #[tokio::test]
     async fn test_ipfs_client_creation() {
@@ -347,16 +840,167 @@ mod tests {
         );
         
         assert!(client.api_config.is_some());
-        assert!(client.pinata_config.is_none());
+        assert!(client.pinning_providers.is_empty());
     }
     
     #[tokio::test]
     async fn test_ipfs_client_public_only() {
         let client = IpfsClient::new_public_only(30);
         assert!(client.api_config.is_none());
-        assert!(!client.public_gateways.is_empty());
+        assert!(!client.public_gateways().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_upload_stream() {
+        use tokio::io::AsyncWriteExt;
+        
+        // 模拟IPFS API：读取完整请求后返回收到的请求体大小
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut data = Vec::new();
+            let mut chunk = [0u8; 64 * 1024];
+            while !data.ends_with(b"0\r\n\r\n") {
+                let n = stream.read(&mut chunk).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                data.extend_from_slice(&chunk[..n]);
+            }
+            let body = format!(r#"{{"Hash":"bafytest","Size":"{}"}}"#, data.len());
+            let reply = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+            stream.write_all(reply.as_bytes()).await.unwrap();
+        });
+        
+        let client = IpfsClient::new_with_remote_node(api_url.clone(), api_url, 30);
+        let content = vec![7u8; UPLOAD_CHUNK_SIZE * 2 + 17];
+        let result = client.upload_stream(std::io::Cursor::new(content.clone()), "model.bin").await.unwrap();
+        
+        assert_eq!(result.cid, "bafytest");
+        assert_eq!(result.provider, "remote_api");
+        assert!(result.size > content.len() as u64);
+    }
+    
+    #[tokio::test]
+    async fn test_upload_stream_requires_api_node() {
+        let client = IpfsClient::new_public_only(30);
+        assert!(client.upload_stream(std::io::Cursor::new(vec![1u8]), "x").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_get_records_gateway_health() {
+        let (url, _) = status_sequence_gateway(vec![200]).await;
+        let client = IpfsClient::new_public_only(30);
+        for gateway in client.public_gateways() {
+            client.remove_gateway(&gateway);
+        }
+        client.add_gateway("http://127.0.0.1:1");
+        client.add_gateway(&url);
+        
+        assert_eq!(client.get("bafy").await.unwrap(), "content");
+        
+        // 失败的网关被排到后面
+        assert_eq!(client.public_gateways()[0], url);
+        let health = client.gateway_health();
+        assert_eq!(health[0].successes, 1);
+        assert_eq!(health[1].failures, 1);
+    }
+    
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        };
+        
+        assert_eq!(policy.backoff_for(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff_for(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff_for(10), Duration::from_secs(5));
+        
+        let jittered = RetryPolicy::default().backoff_for(1);
+        assert!(jittered >= Duration::from_millis(160) && jittered <= Duration::from_millis(240));
+    }
+    
+    /// 按顺序返回给定状态码的模拟网关，返回请求计数
+    async fn status_sequence_gateway(statuses: Vec<u16>) -> (String, Arc<AtomicUsize>) {
+        use tokio::io::AsyncWriteExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                
+                let body = if status == 200 { "content" } else { "" };
+                let reply = format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        
+        (url, requests)
     }
     
+    #[tokio::test]
+    async fn test_retry_transient_errors() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        };
+        
+        // 5xx重试后成功
+        let (url, requests) = status_sequence_gateway(vec![503, 502, 200]).await;
+        let client = IpfsClient::new_with_remote_node(url.clone(), url, 30).with_retry_policy(policy.clone());
+        assert_eq!(client.get("bafy").await.unwrap(), "content");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        
+        // 4xx不重试
+        let (url, requests) = status_sequence_gateway(vec![404, 200]).await;
+        let client = IpfsClient::new_with_remote_node(url.clone(), url, 30).with_retry_policy(policy);
+        let config = client.api_config.clone().unwrap();
+        let result = client.with_retry("获取", || client.get_from_gateway(&config.gateway_url, "bafy")).await;
+        assert!(result.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_uses_block_store_offline() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = BlockStore::open(dir.path()).unwrap();
+
+        use crate::cid_compute::{compute_content_cid, CidOptions};
+        let cid = compute_content_cid(b"content", &CidOptions::default()).unwrap();
+
+        // 首次获取从网关拉取并回填本地块存储
+        let (url, requests) = status_sequence_gateway(vec![200, 200]).await;
+        let client = IpfsClient::new_with_remote_node(url.clone(), url, 30)
+            .with_retry_policy(RetryPolicy::none())
+            .with_block_store(store.clone());
+        assert_eq!(client.get(&cid).await.unwrap(), "content");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(store.contains(&cid).await);
+
+        // 网关返回的内容与请求的CID不符时不写入
+        let other = compute_content_cid(b"other", &CidOptions::default()).unwrap();
+        client.get(&other).await.unwrap();
+        assert!(!store.contains(&other).await);
+
+        // 网络不可用时仍可从本地块存储解析
+        let offline = IpfsClient::new_with_remote_node("http://127.0.0.1:1".to_string(), "http://127.0.0.1:1".to_string(), 1)
+            .with_block_store(store);
+        for gateway in offline.public_gateways() {
+            offline.remove_gateway(&gateway);
+        }
+        assert_eq!(offline.get(&cid).await.unwrap(), "content");
+        assert!(offline.get("bafyunknown").await.is_err());
+    }
+
     // 注意：以下测试需要实际的IPFS节点或Pinata凭证
     // 在CI环境中应该使用mock
 }
@@ -1,16 +1,26 @@
 // DIAP Rust SDK - 内置IPFS节点管理器
-// 自动启动和管理本地IPFS节点，实现完全去中心化
+// 自动启动和管理本地IPFS节点，实现完全去中心化：
+// 按需下载固定版本的Kubo，初始化DIAP配置的仓库（开启pubsub、指定网关端口），
+// 监督daemon进程（健康检查、崩溃后重启），并把API/网关地址提供给IpfsClient
 
 use anyhow::{Context, Result};
 use std::process::{Command, Child};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::time::sleep;
 use log;
-use crate::kubo_installer::KuboInstaller;
+use crate::ipfs_client::IpfsClient;
+use crate::kubo_installer::{KuboInstaller, DEFAULT_KUBO_VERSION};
+
+/// 连续健康检查失败多少次视为节点失去响应
+const MAX_HEALTH_FAILURES: u32 = 3;
+
+/// 重启退避上限
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
 
 /// IPFS节点配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +51,21 @@ pub struct IpfsNodeConfig {
     
     /// 是否启用详细日志
     pub verbose_logging: bool,
+    
+    /// 找不到ipfs可执行文件时是否自动下载Kubo
+    pub auto_download: bool,
+    
+    /// 自动下载的Kubo版本（固定版本，避免仓库格式意外升级）
+    pub kubo_version: String,
+    
+    /// 是否开启pubsub（DIAP的IPNS over pubsub和吊销广播依赖它）
+    pub enable_pubsub: bool,
+    
+    /// 监督进程的健康检查间隔（秒）
+    pub health_check_interval: u64,
+    
+    /// 监督进程最多连续重启次数，超过后进入错误状态
+    pub max_restarts: u32,
 }
 
 impl Default for IpfsNodeConfig {
@@ -61,6 +86,33 @@ impl Default for IpfsNodeConfig {
             enable_swarm: true,
             swarm_port: 4001,
             verbose_logging: false,
+            auto_download: true,
+            kubo_version: DEFAULT_KUBO_VERSION.to_string(),
+            enable_pubsub: true,
+            health_check_interval: 10,
+            max_restarts: 5,
+        }
+    }
+}
+
+impl From<&crate::config_manager::DIAPConfig> for IpfsNodeConfig {
+    fn from(config: &crate::config_manager::DIAPConfig) -> Self {
+        let local = &config.ipfs.local_node;
+        let defaults = Self::default();
+        Self {
+            data_dir: local.data_dir.clone().unwrap_or(defaults.data_dir),
+            api_port: local.api_port,
+            gateway_port: local.gateway_port,
+            auto_start: local.auto_start,
+            startup_timeout: config.ipfs.timeout_seconds,
+            enable_bootstrap: local.enable_bootstrap,
+            swarm_port: local.swarm_port,
+            verbose_logging: config.logging.level == "debug" || config.logging.level == "trace",
+            auto_download: local.auto_download,
+            kubo_version: local.kubo_version.clone().unwrap_or(defaults.kubo_version),
+            health_check_interval: local.health_check_interval_secs,
+            max_restarts: local.max_restarts,
+            ..defaults
         }
     }
 }
@@ -80,6 +132,8 @@ pub struct IpfsNodeManager {
     config: IpfsNodeConfig,
     status: Arc<RwLock<IpfsNodeStatus>>,
     process: Arc<RwLock<Option<Child>>>,
+    ipfs_path: Arc<RwLock<Option<PathBuf>>>,
+    restarts: Arc<AtomicU32>,
     api_url: String,
     gateway_url: String,
 }
@@ -94,11 +148,22 @@ impl IpfsNodeManager {
             config,
             status: Arc::new(RwLock::new(IpfsNodeStatus::Stopped)),
             process: Arc::new(RwLock::new(None)),
+            ipfs_path: Arc::new(RwLock::new(None)),
+            restarts: Arc::new(AtomicU32::new(0)),
             api_url,
             gateway_url,
         }
     }
     
+    /// 启动节点并开始监督，返回管理器和指向该节点的IpfsClient
+    pub async fn launch(config: IpfsNodeConfig, timeout_seconds: u64) -> Result<(Arc<Self>, IpfsClient)> {
+        let manager = Arc::new(Self::new(config));
+        manager.start().await?;
+        manager.clone().spawn_supervisor();
+        let client = manager.ipfs_client(timeout_seconds);
+        Ok((manager, client))
+    }
+    
     /// 启动IPFS节点
     pub async fn start(&self) -> Result<()> {
         log::info!("🚀 启动内置IPFS节点...");
@@ -107,20 +172,14 @@ impl IpfsNodeManager {
         log::info!("  网关端口: {}", self.config.gateway_port);
         log::info!("  Swarm端口: {}", self.config.swarm_port);
         
-        // 检查IPFS是否已安装
-        self.check_ipfs_installed().await?;
-        
         // 设置状态为启动中
         {
             let mut status = self.status.write().await;
             *status = IpfsNodeStatus::Starting;
         }
         
-        // 初始化IPFS仓库（如果不存在）
-        self.init_ipfs_repo().await?;
-        
         // 检查是否已有IPFS节点在运行
-        if self.is_existing_node_running().await? {
+        if self.is_existing_node_running().await {
             log::info!("✅ 检测到现有IPFS节点正在运行，直接使用");
             log::info!("  API地址: {}", self.api_url);
             log::info!("  网关地址: {}", self.gateway_url);
@@ -134,8 +193,20 @@ impl IpfsNodeManager {
             return Ok(());
         }
         
+        // 查找（或下载）ipfs可执行文件，初始化并配置仓库
+        let ipfs_path = match self.resolve_ipfs_executable().await {
+            Ok(path) => path,
+            Err(e) => return Err(self.fail(e).await),
+        };
+        if let Err(e) = self.init_ipfs_repo(&ipfs_path).await {
+            return Err(self.fail(e).await);
+        }
+        
         // 启动新的IPFS daemon
-        let child = self.start_ipfs_daemon().await?;
+        let child = match self.start_ipfs_daemon(&ipfs_path) {
+            Ok(child) => child,
+            Err(e) => return Err(self.fail(e).await),
+        };
         
         // 保存进程句柄
         {
@@ -170,20 +241,7 @@ impl IpfsNodeManager {
         }
         
         // 终止进程
-        {
-            let mut process = self.process.write().await;
-            if let Some(mut child) = process.take() {
-                match child.kill() {
-                    Ok(_) => {
-                        log::info!("✓ IPFS进程已终止");
-                        let _ = child.wait();
-                    }
-                    Err(e) => {
-                        log::warn!("终止IPFS进程时出错: {}", e);
-                    }
-                }
-            }
-        }
+        self.kill_process().await;
         
         // 设置状态为已停止
         {
@@ -204,11 +262,63 @@ impl IpfsNodeManager {
         Ok(())
     }
     
+    /// 启动监督任务：定期检查进程和API健康，进程退出或连续失去响应时重启（指数退避）
+    /// 调用stop后监督任务结束；连续重启超过max_restarts次后进入错误状态并结束
+    pub fn spawn_supervisor(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(self.config.health_check_interval.max(1)));
+            ticker.tick().await;
+            let mut health_failures = 0u32;
+            loop {
+                ticker.tick().await;
+                if matches!(self.status().await, IpfsNodeStatus::Stopping | IpfsNodeStatus::Stopped) {
+                    log::info!("IPFS监督任务结束（节点已停止）");
+                    return;
+                }
+                
+                let exited = self.child_exited().await;
+                if !exited && self.check_api_health().await.is_ok() {
+                    health_failures = 0;
+                    continue;
+                }
+                health_failures += 1;
+                if !exited && health_failures < MAX_HEALTH_FAILURES {
+                    log::warn!("⚠️ IPFS节点健康检查失败 ({}/{})", health_failures, MAX_HEALTH_FAILURES);
+                    continue;
+                }
+                
+                let attempt = self.restarts.fetch_add(1, Ordering::Relaxed) + 1;
+                if attempt > self.config.max_restarts {
+                    log::error!("❌ IPFS节点重启次数超过上限({})，停止监督", self.config.max_restarts);
+                    *self.status.write().await = IpfsNodeStatus::Error("重启次数超过上限".to_string());
+                    return;
+                }
+                
+                let backoff = restart_backoff(attempt);
+                log::warn!("🔄 IPFS节点{}，{:?}后第{}次重启", if exited { "进程已退出" } else { "失去响应" }, backoff, attempt);
+                self.kill_process().await;
+                sleep(backoff).await;
+                match self.start().await {
+                    Ok(()) => {
+                        health_failures = 0;
+                        self.restarts.store(0, Ordering::Relaxed);
+                    }
+                    Err(e) => log::error!("IPFS节点重启失败: {}", e),
+                }
+            }
+        })
+    }
+    
     /// 获取节点状态
     pub async fn status(&self) -> IpfsNodeStatus {
         self.status.read().await.clone()
     }
     
+    /// 当前连续重启次数（重启成功后清零）
+    pub fn restart_count(&self) -> u32 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+    
     /// 获取API URL
     pub fn api_url(&self) -> &str {
         &self.api_url
@@ -219,6 +329,11 @@ impl IpfsNodeManager {
         &self.gateway_url
     }
     
+    /// 使用本节点API和网关的IPFS客户端
+    pub fn ipfs_client(&self, timeout_seconds: u64) -> IpfsClient {
+        IpfsClient::new_with_remote_node(self.api_url.clone(), self.gateway_url.clone(), timeout_seconds)
+    }
+    
     /// 检查节点是否健康
     pub async fn is_healthy(&self) -> bool {
         self.check_api_health().await.is_ok()
@@ -244,174 +359,109 @@ impl IpfsNodeManager {
         Ok(info)
     }
     
-    /// 检查IPFS是否已安装
-    async fn check_ipfs_installed(&self) -> Result<()> {
-        // 首先尝试从PATH中查找ipfs
-        if let Ok(output) = Command::new("ipfs").arg("--version").output() {
-            if output.status.success() {
-                let version = String::from_utf8_lossy(&output.stdout);
-                log::info!("✓ 检测到IPFS (PATH): {}", version.trim());
-                return Ok(());
-            }
-        }
-        
-        // 如果PATH中没有，尝试常见安装路径
-        let common_paths = [
-            r"D:\APPs\kubo\ipfs.exe",  // Windows常见路径
-            r"C:\Program Files\Kubo\ipfs.exe",
-            r"C:\Program Files (x86)\Kubo\ipfs.exe",
-            "/usr/local/bin/ipfs",     // Linux/Mac路径
-            "/usr/bin/ipfs",
-            "ipfs"  // 再次尝试PATH
+    /// DIAP对仓库的配置项（ipfs config --json <key> <value>）
+    pub fn repo_config(&self) -> Vec<(&'static str, serde_json::Value)> {
+        let swarm: Vec<String> = if self.config.enable_swarm {
+            vec![
+                format!("/ip4/0.0.0.0/tcp/{}", self.config.swarm_port),
+                format!("/ip4/0.0.0.0/udp/{}/quic-v1", self.config.swarm_port),
+            ]
+        } else {
+            Vec::new()
+        };
+        let mut entries = vec![
+            ("Addresses.API", serde_json::json!(format!("/ip4/127.0.0.1/tcp/{}", self.config.api_port))),
+            ("Addresses.Gateway", serde_json::json!(format!("/ip4/127.0.0.1/tcp/{}", self.config.gateway_port))),
+            ("Addresses.Swarm", serde_json::json!(swarm)),
+            ("Pubsub.Enabled", serde_json::json!(self.config.enable_pubsub)),
+            ("Ipns.UsePubsub", serde_json::json!(self.config.enable_pubsub)),
         ];
-        
-        for path in &common_paths {
-            if let Ok(output) = Command::new(path).arg("--version").output() {
-                if output.status.success() {
-                    let version = String::from_utf8_lossy(&output.stdout);
-                    log::info!("✓ 检测到IPFS ({}): {}", path, version.trim());
-                    return Ok(());
-                }
-            }
-        }
-        
-        // 如果都找不到，尝试自动安装Kubo
-        log::info!("未找到IPFS，尝试自动安装Kubo...");
-        let installer = KuboInstaller::new();
-        match installer.ensure_kubo_installed().await {
-            Ok(_) => {
-                log::info!("✓ Kubo自动安装成功");
-                Ok(())
-            }
-            Err(e) => {
-                log::error!("Kubo自动安装失败: {}", e);
-                anyhow::bail!("无法找到IPFS，自动安装也失败。请手动安装IPFS或检查网络连接");
-            }
+        if !self.config.enable_bootstrap {
+            entries.push(("Bootstrap", serde_json::json!([])));
         }
+        entries
     }
     
-    /// 检查现有IPFS节点是否运行
-    async fn is_existing_node_running(&self) -> Result<bool> {
-        // 使用相同的路径检测逻辑
-        let ipfs_path = self.find_ipfs_executable().await?;
-        
-        let output = tokio::process::Command::new(&ipfs_path)
-            .arg("id")
-            .output()
-            .await?;
-        
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            // 尝试解析节点ID来确认节点正在运行
-            if stdout.contains("ID") && stdout.contains("12D3KooW") {
-                return Ok(true);
-            }
-        }
-        
-        Ok(false)
+    /// 检查现有IPFS节点是否运行（配置的API端口已有节点响应）
+    async fn is_existing_node_running(&self) -> bool {
+        self.check_api_health().await.is_ok()
     }
     
-    /// 查找IPFS可执行文件路径
-    async fn find_ipfs_executable(&self) -> Result<String> {
-        // 首先尝试从PATH中查找ipfs
-        if let Ok(output) = Command::new("ipfs").arg("--version").output() {
-            if output.status.success() {
-                return Ok("ipfs".to_string());
-            }
+    /// 查找IPFS可执行文件（结果缓存），找不到且允许时下载固定版本的Kubo
+    async fn resolve_ipfs_executable(&self) -> Result<PathBuf> {
+        if let Some(path) = self.ipfs_path.read().await.clone() {
+            return Ok(path);
         }
         
-        // 如果PATH中没有，尝试常见安装路径
-        let common_paths = [
-            r"D:\APPs\kubo\ipfs.exe",  // Windows常见路径
-            r"C:\Program Files\Kubo\ipfs.exe",
-            r"C:\Program Files (x86)\Kubo\ipfs.exe",
-            "/usr/local/bin/ipfs",     // Linux/Mac路径
-            "/usr/bin/ipfs",
-        ];
-        
-        for path in &common_paths {
-            if let Ok(output) = Command::new(path).arg("--version").output() {
-                if output.status.success() {
-                    return Ok(path.to_string());
-                }
+        let path = match find_installed_ipfs() {
+            Some(path) => path,
+            None if self.config.auto_download => {
+                log::info!("未找到IPFS，自动安装Kubo {}...", self.config.kubo_version);
+                KuboInstaller::new()
+                    .with_version(&self.config.kubo_version)
+                    .ensure_kubo_installed()
+                    .await
+                    .context("无法找到IPFS，自动安装也失败。请手动安装IPFS或检查网络连接")?
             }
-        }
+            None => anyhow::bail!("未找到IPFS可执行文件，且未启用自动下载"),
+        };
         
-        // 最后尝试自动安装的Kubo
-        let installer = KuboInstaller::new();
-        let kubo_path = installer.ensure_kubo_installed().await?;
-        Ok(kubo_path.to_string_lossy().to_string())
+        *self.ipfs_path.write().await = Some(path.clone());
+        Ok(path)
     }
     
-    /// 初始化IPFS仓库
-    async fn init_ipfs_repo(&self) -> Result<()> {
-        // 首先检查全局IPFS仓库是否存在
-        let global_repo_path = dirs::home_dir()
-            .context("无法获取用户主目录")?
-            .join(".ipfs");
-        
-        if global_repo_path.exists() {
-            log::info!("✓ 检测到现有IPFS仓库: {:?}", global_repo_path);
-            log::info!("  使用现有IPFS配置，跳过初始化步骤");
-            return Ok(());
+    /// 初始化IPFS仓库（IPFS_PATH为数据目录），并写入DIAP配置
+    async fn init_ipfs_repo(&self, ipfs_path: &Path) -> Result<()> {
+        if self.config.data_dir.join("config").exists() {
+            log::info!("✓ IPFS仓库已存在: {:?}", self.config.data_dir);
+        } else {
+            log::info!("📁 初始化新的IPFS仓库...");
+            
+            // 创建数据目录
+            std::fs::create_dir_all(&self.config.data_dir)
+                .context("无法创建IPFS数据目录")?;
+            
+            self.run_ipfs(ipfs_path, &["init"]).await.context("IPFS初始化失败")?;
+            log::info!("✅ IPFS仓库初始化完成");
         }
         
-        // 检查自定义仓库是否已存在
-        let repo_path = self.config.data_dir.join(".ipfs");
-        if repo_path.exists() {
-            log::info!("✓ IPFS仓库已存在: {:?}", repo_path);
-            return Ok(());
+        // 每次启动都写入配置，端口等设置变化时随之更新
+        for (key, value) in self.repo_config() {
+            let value = value.to_string();
+            self.run_ipfs(ipfs_path, &["config", "--json", key, &value]).await
+                .with_context(|| format!("设置IPFS配置失败: {}", key))?;
         }
-        
-        // 只有在没有现有仓库时才初始化新的
-        log::info!("📁 初始化新的IPFS仓库...");
-        
-        // 创建数据目录
-        std::fs::create_dir_all(&self.config.data_dir)
-            .context("无法创建IPFS数据目录")?;
-        
-        // 设置IPFS_PATH环境变量
-        let ipfs_path = self.find_ipfs_executable().await?;
-        let mut cmd = Command::new(&ipfs_path);
-        cmd.arg("init");
-        cmd.arg("--profile=test"); // 使用测试配置，减少资源使用
-        cmd.env("IPFS_PATH", &self.config.data_dir);
-        
-        let output = cmd.output()
-            .context("无法初始化IPFS仓库")?;
+        Ok(())
+    }
+    
+    /// 以数据目录为IPFS_PATH运行一次ipfs命令
+    async fn run_ipfs(&self, ipfs_path: &Path, args: &[&str]) -> Result<()> {
+        let output = tokio::process::Command::new(ipfs_path)
+            .args(args)
+            .env("IPFS_PATH", &self.config.data_dir)
+            .output()
+            .await
+            .with_context(|| format!("无法执行ipfs {}", args.join(" ")))?;
         
         if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("IPFS初始化失败: {}", error);
+            anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
         }
-        
-        log::info!("✅ IPFS仓库初始化完成");
         Ok(())
     }
     
     /// 启动IPFS daemon
-    async fn start_ipfs_daemon(&self) -> Result<Child> {
+    fn start_ipfs_daemon(&self, ipfs_path: &Path) -> Result<Child> {
         log::info!("🚀 启动IPFS daemon...");
         
-        let ipfs_path = self.find_ipfs_executable().await?;
-        let mut cmd = Command::new(&ipfs_path);
+        let mut cmd = Command::new(ipfs_path);
         cmd.arg("daemon");
-        cmd.arg("--api-address");
-        cmd.arg(format!("/ip4/127.0.0.1/tcp/{}", self.config.api_port));
-        cmd.arg("--gateway-address");
-        cmd.arg(format!("/ip4/127.0.0.1/tcp/{}", self.config.gateway_port));
-        
-        if self.config.enable_swarm {
-            cmd.arg("--swarm-address");
-            cmd.arg(format!("/ip4/127.0.0.1/tcp/{}", self.config.swarm_port));
-        }
-        
-        if !self.config.enable_bootstrap {
-            cmd.arg("--disable-bootstrap");
-        }
+        cmd.arg("--migrate");
         
         // 设置IPFS_PATH环境变量
         cmd.env("IPFS_PATH", &self.config.data_dir);
+        if self.config.verbose_logging {
+            cmd.env("GOLOG_LOG_LEVEL", "debug");
+        }
         
         // 启动进程
         let child = cmd.spawn()
@@ -434,16 +484,54 @@ impl IpfsNodeManager {
                 return Ok(());
             }
             
+            // daemon提前退出（端口占用、仓库锁定等）时不必等到超时
+            if self.child_exited().await {
+                return Err(self.fail(anyhow::anyhow!("IPFS daemon启动后立即退出")).await);
+            }
+            
             sleep(Duration::from_millis(500)).await;
         }
         
-        // 设置错误状态
-        {
-            let mut status = self.status.write().await;
-            *status = IpfsNodeStatus::Error("启动超时".to_string());
+        Err(self.fail(anyhow::anyhow!("IPFS节点启动超时")).await)
+    }
+    
+    /// 记录错误状态并返回错误
+    async fn fail(&self, error: anyhow::Error) -> anyhow::Error {
+        *self.status.write().await = IpfsNodeStatus::Error(error.to_string());
+        error
+    }
+    
+    /// 由本管理器启动的daemon是否已退出（使用外部节点时为false）
+    async fn child_exited(&self) -> bool {
+        let mut process = self.process.write().await;
+        match process.as_mut().map(|child| child.try_wait()) {
+            Some(Ok(Some(exit))) => {
+                log::warn!("IPFS daemon已退出: {}", exit);
+                process.take();
+                true
+            }
+            Some(Err(e)) => {
+                log::warn!("无法获取IPFS daemon状态: {}", e);
+                false
+            }
+            Some(Ok(None)) | None => false,
+        }
+    }
+    
+    /// 终止由本管理器启动的daemon
+    async fn kill_process(&self) {
+        let mut process = self.process.write().await;
+        if let Some(mut child) = process.take() {
+            match child.kill() {
+                Ok(_) => {
+                    log::info!("✓ IPFS进程已终止");
+                    let _ = child.wait();
+                }
+                Err(e) => {
+                    log::warn!("终止IPFS进程时出错: {}", e);
+                }
+            }
         }
-        
-        anyhow::bail!("IPFS节点启动超时");
     }
     
     /// 检查API健康状态
@@ -466,6 +554,32 @@ impl IpfsNodeManager {
     }
 }
 
+/// 在PATH和常见安装路径中查找ipfs可执行文件
+fn find_installed_ipfs() -> Option<PathBuf> {
+    let candidates = [
+        "ipfs",
+        r"D:\APPs\kubo\ipfs.exe",  // Windows常见路径
+        r"C:\Program Files\Kubo\ipfs.exe",
+        r"C:\Program Files (x86)\Kubo\ipfs.exe",
+        "/usr/local/bin/ipfs",     // Linux/Mac路径
+        "/usr/bin/ipfs",
+    ];
+    
+    candidates.iter().find_map(|path| {
+        let output = Command::new(path).arg("--version").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        log::info!("✓ 检测到IPFS ({}): {}", path, String::from_utf8_lossy(&output.stdout).trim());
+        Some(PathBuf::from(path))
+    })
+}
+
+/// 第n次重启前的等待时间（1s, 2s, 4s...，上限60s）
+fn restart_backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1u64 << attempt.saturating_sub(1).min(6)).min(MAX_RESTART_BACKOFF)
+}
+
 /// IPFS节点信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IpfsNodeInfo {
@@ -517,13 +631,30 @@ mod tests {
         };
         
         let manager = IpfsNodeManager::new(config);
-        assert_eq!(manager.api_url(), "http://localhost:5001");
-        assert_eq!(manager.gateway_url(), "http://localhost:8080");
+        assert_eq!(manager.api_url(), "http://127.0.0.1:5001");
+        assert_eq!(manager.gateway_url(), "http://127.0.0.1:8080");
         
         let status = manager.status().await;
         assert_eq!(status, IpfsNodeStatus::Stopped);
     }
     
+    #[test]
+    fn test_repo_config_and_restart_backoff() {
+        let manager = IpfsNodeManager::new(IpfsNodeConfig {
+            gateway_port: 18080,
+            enable_bootstrap: false,
+            ..Default::default()
+        });
+        let config: std::collections::HashMap<_, _> = manager.repo_config().into_iter().collect();
+        assert_eq!(config["Addresses.Gateway"], "/ip4/127.0.0.1/tcp/18080");
+        assert_eq!(config["Pubsub.Enabled"], true);
+        assert_eq!(config["Bootstrap"], serde_json::json!([]));
+        
+        assert_eq!(restart_backoff(1), Duration::from_secs(1));
+        assert_eq!(restart_backoff(3), Duration::from_secs(4));
+        assert_eq!(restart_backoff(20), MAX_RESTART_BACKOFF);
+    }
+    
     // 注意：以下测试需要实际的IPFS安装
     #[tokio::test]
     #[ignore] // 需要实际的IPFS安装
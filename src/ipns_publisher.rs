@@ -0,0 +1,527 @@
+// DIAP Rust SDK - IPNS发布模块
+// 用智能体自己的Ed25519密钥在本地创建并签名IPNS记录（V2：DAG-CBOR数据 + signatureV2），
+// 直接写入libp2p DHT，不再依赖远程节点的 key/gen 和 name/publish 接口。
+// 记录格式与IPNS规范一致，名称即签名公钥对应的PeerID
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use libp2p::identity::{ed25519, PublicKey};
+use libp2p::kad::RecordKey;
+use libp2p::PeerId;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::agent_discovery::DhtBackend;
+use crate::cid_compute::{cbor_header, write_varint};
+use crate::clock::{SharedClock, system_clock};
+use crate::key_manager::Signer;
+
+/// 默认记录有效期（与config中的validity_days默认值一致）
+pub const DEFAULT_IPNS_VALIDITY: Duration = Duration::from_secs(365 * 24 * 3600);
+
+/// 默认缓存TTL
+pub const DEFAULT_IPNS_TTL: Duration = Duration::from_secs(3600);
+
+/// IPNS记录最大长度（规范限制）
+pub const MAX_IPNS_RECORD_SIZE: usize = 10 * 1024;
+
+/// signatureV2的签名前缀
+const SIGNATURE_V2_PREFIX: &[u8] = b"ipns-signature:";
+
+/// ValidityType::EOL
+const VALIDITY_EOL: u64 = 0;
+
+/// 公钥对应的IPNS名称（PeerID）
+pub fn ipns_name(public_key: &[u8; 32]) -> Result<PeerId> {
+    Ok(PeerId::from_public_key(&libp2p_public_key(public_key)?))
+}
+
+/// IPNS记录在DHT中的键：/ipns/ + PeerID的multihash字节
+pub fn ipns_key(name: &PeerId) -> RecordKey {
+    let mut key = b"/ipns/".to_vec();
+    key.extend_from_slice(&name.to_bytes());
+    RecordKey::new(&key)
+}
+
+fn libp2p_public_key(public_key: &[u8; 32]) -> Result<PublicKey> {
+    let key = ed25519::PublicKey::try_from_bytes(public_key).context("无效的Ed25519公钥")?;
+    Ok(PublicKey::from(key))
+}
+
+/// IPNS记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpnsRecord {
+    /// 指向的路径，例如 /ipfs/<cid>
+    pub value: String,
+
+    /// 序号（越大越新）
+    pub sequence: u64,
+
+    /// 过期时间（Unix秒）
+    pub validity: u64,
+
+    /// 解析方缓存时间
+    pub ttl: Duration,
+
+    /// protobuf编码的libp2p公钥
+    pub public_key: Vec<u8>,
+
+    /// 签名的DAG-CBOR数据
+    pub data: Vec<u8>,
+
+    /// signatureV2
+    pub signature: Vec<u8>,
+}
+
+impl IpnsRecord {
+    /// 创建并签名记录
+    pub fn create(signer: &dyn Signer, value: &str, sequence: u64, validity: u64, ttl: Duration) -> Result<Self> {
+        let validity_text = format_validity(validity)?;
+        let data = encode_cbor_data(value.as_bytes(), validity_text.as_bytes(), sequence, ttl.as_nanos() as u64);
+
+        let mut payload = SIGNATURE_V2_PREFIX.to_vec();
+        payload.extend_from_slice(&data);
+        let signature = signer.sign(&payload).context("签名IPNS记录失败")?;
+
+        Ok(Self {
+            value: value.to_string(),
+            sequence,
+            validity,
+            ttl,
+            public_key: libp2p_public_key(&signer.public_key())?.encode_protobuf(),
+            data,
+            signature,
+        })
+    }
+
+    /// 是否已过期
+    pub fn is_expired(&self, now_secs: u64) -> bool {
+        now_secs >= self.validity
+    }
+
+    /// 验证签名、名称与公钥的对应关系和有效期
+    pub fn verify(&self, name: &PeerId, now_secs: u64) -> Result<()> {
+        let public_key = PublicKey::try_decode_protobuf(&self.public_key).context("无效的IPNS公钥")?;
+        if PeerId::from_public_key(&public_key) != *name {
+            anyhow::bail!("IPNS记录公钥与名称不符: {}", name);
+        }
+
+        let mut payload = SIGNATURE_V2_PREFIX.to_vec();
+        payload.extend_from_slice(&self.data);
+        if !public_key.verify(&payload, &self.signature) {
+            anyhow::bail!("IPNS记录签名无效: {}", name);
+        }
+
+        // 外层字段只是便于读取的副本，必须与签名数据一致
+        let signed = decode_cbor_data(&self.data)?;
+        if signed.value != self.value.as_bytes()
+            || signed.sequence != self.sequence
+            || signed.validity_type != VALIDITY_EOL
+            || parse_validity(&signed.validity)? != self.validity
+        {
+            anyhow::bail!("IPNS记录字段与签名数据不一致: {}", name);
+        }
+
+        if self.is_expired(now_secs) {
+            anyhow::bail!("IPNS记录已过期: {}", name);
+        }
+        Ok(())
+    }
+
+    /// protobuf编码（IpnsEntry）
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let validity = format_validity(self.validity).unwrap_or_default();
+        let mut out = Vec::new();
+        put_bytes(&mut out, 1, self.value.as_bytes());
+        put_varint_field(&mut out, 3, VALIDITY_EOL);
+        put_bytes(&mut out, 4, validity.as_bytes());
+        put_varint_field(&mut out, 5, self.sequence);
+        put_varint_field(&mut out, 6, self.ttl.as_nanos() as u64);
+        put_bytes(&mut out, 7, &self.public_key);
+        put_bytes(&mut out, 8, &self.signature);
+        put_bytes(&mut out, 9, &self.data);
+        out
+    }
+
+    /// 从protobuf解析（字段取自签名数据，不校验签名）
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() > MAX_IPNS_RECORD_SIZE {
+            anyhow::bail!("IPNS记录过大: {} 字节", bytes.len());
+        }
+
+        let mut public_key = Vec::new();
+        let mut signature = Vec::new();
+        let mut data = Vec::new();
+        let mut reader = ProtoReader { buf: bytes };
+        while let Some((field, value)) = reader.next_field()? {
+            match (field, value) {
+                (7, ProtoValue::Bytes(v)) => public_key = v.to_vec(),
+                (8, ProtoValue::Bytes(v)) => signature = v.to_vec(),
+                (9, ProtoValue::Bytes(v)) => data = v.to_vec(),
+                _ => {}
+            }
+        }
+        if data.is_empty() || signature.is_empty() {
+            anyhow::bail!("IPNS记录缺少V2签名数据");
+        }
+
+        let signed = decode_cbor_data(&data)?;
+        Ok(Self {
+            value: String::from_utf8(signed.value).context("IPNS记录值不是UTF-8")?,
+            sequence: signed.sequence,
+            validity: parse_validity(&signed.validity)?,
+            ttl: Duration::from_nanos(signed.ttl),
+            public_key,
+            data,
+            signature,
+        })
+    }
+}
+
+/// IPNS发布器（本地签名 + DHT发布/解析）
+#[derive(Clone)]
+pub struct IpnsPublisher {
+    signer: Arc<dyn Signer>,
+    dht: Arc<dyn DhtBackend>,
+    name: PeerId,
+    validity: Duration,
+    ttl: Duration,
+    next_sequence: Arc<AtomicU64>,
+    clock: SharedClock,
+}
+
+impl IpnsPublisher {
+    /// 创建发布器，名称由签名器公钥派生
+    pub fn new(signer: Arc<dyn Signer>, dht: Arc<dyn DhtBackend>) -> Result<Self> {
+        let name = ipns_name(&signer.public_key())?;
+        Ok(Self {
+            signer,
+            dht,
+            name,
+            validity: DEFAULT_IPNS_VALIDITY,
+            ttl: DEFAULT_IPNS_TTL,
+            next_sequence: Arc::new(AtomicU64::new(0)),
+            clock: system_clock(),
+        })
+    }
+
+    /// 设置记录有效期
+    pub fn with_validity(mut self, validity: Duration) -> Self {
+        self.validity = validity;
+        self
+    }
+
+    /// 设置缓存TTL
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 使用指定时间源
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 本智能体的IPNS名称
+    pub fn name(&self) -> PeerId {
+        self.name
+    }
+
+    /// 发布CID（值为 /ipfs/<cid>）
+    pub async fn publish_cid(&self, cid: &str) -> Result<IpnsRecord> {
+        self.publish(&format!("/ipfs/{}", cid)).await
+    }
+
+    /// 签名并发布记录；序号取本地计数与DHT上已有记录的较大者
+    pub async fn publish(&self, value: &str) -> Result<IpnsRecord> {
+        let mut sequence = self.next_sequence.load(Ordering::SeqCst);
+        if let Some(current) = self.resolve_record(&self.name).await? {
+            sequence = sequence.max(current.sequence + 1);
+        }
+
+        let validity = self.clock.now_secs() + self.validity.as_secs();
+        let record = IpnsRecord::create(self.signer.as_ref(), value, sequence, validity, self.ttl)?;
+        self.dht.put_record(ipns_key(&self.name), record.to_bytes()).await
+            .context("发布IPNS记录到DHT失败")?;
+        self.next_sequence.fetch_max(sequence + 1, Ordering::SeqCst);
+
+        log::info!("📛 IPNS记录已发布: /ipns/{} -> {} (seq {})", self.name, value, sequence);
+        Ok(record)
+    }
+
+    /// 解析名称对应的值
+    pub async fn resolve(&self, name: &PeerId) -> Result<Option<String>> {
+        Ok(self.resolve_record(name).await?.map(|record| record.value))
+    }
+
+    /// 解析名称对应的最新有效记录（无效、过期的副本被忽略）
+    pub async fn resolve_record(&self, name: &PeerId) -> Result<Option<IpnsRecord>> {
+        let now = self.clock.now_secs();
+        let values = self.dht.get_records(ipns_key(name)).await?;
+        Ok(values.iter()
+            .filter_map(|value| match IpnsRecord::from_bytes(value).and_then(|r| r.verify(name, now).map(|_| r)) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    log::debug!("忽略无效的IPNS记录 {}: {}", name, e);
+                    None
+                }
+            })
+            .max_by_key(|record| (record.sequence, record.validity)))
+    }
+}
+
+fn format_validity(secs: u64) -> Result<String> {
+    let time = DateTime::<Utc>::from_timestamp(secs as i64, 0).context("无效的IPNS有效期")?;
+    Ok(time.to_rfc3339_opts(SecondsFormat::Nanos, true))
+}
+
+fn parse_validity(bytes: &[u8]) -> Result<u64> {
+    let text = std::str::from_utf8(bytes).context("IPNS有效期不是UTF-8")?;
+    let time = DateTime::parse_from_rfc3339(text).context("解析IPNS有效期失败")?;
+    Ok(time.timestamp().max(0) as u64)
+}
+
+// ---- DAG-CBOR数据（键按长度、再按字节序排列）----
+
+struct SignedData {
+    value: Vec<u8>,
+    validity: Vec<u8>,
+    validity_type: u64,
+    sequence: u64,
+    ttl: u64,
+}
+
+fn encode_cbor_data(value: &[u8], validity: &[u8], sequence: u64, ttl: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    cbor_header(&mut out, 5, 5);
+    cbor_text(&mut out, "TTL");
+    cbor_header(&mut out, 0, ttl);
+    cbor_text(&mut out, "Value");
+    cbor_bytes(&mut out, value);
+    cbor_text(&mut out, "Sequence");
+    cbor_header(&mut out, 0, sequence);
+    cbor_text(&mut out, "Validity");
+    cbor_bytes(&mut out, validity);
+    cbor_text(&mut out, "ValidityType");
+    cbor_header(&mut out, 0, VALIDITY_EOL);
+    out
+}
+
+fn decode_cbor_data(data: &[u8]) -> Result<SignedData> {
+    let mut reader = CborReader { buf: data };
+    let (major, entries) = reader.head()?;
+    if major != 5 {
+        anyhow::bail!("IPNS数据不是CBOR map");
+    }
+
+    let mut signed = SignedData { value: Vec::new(), validity: Vec::new(), validity_type: u64::MAX, sequence: 0, ttl: 0 };
+    let mut seen = 0;
+    for _ in 0..entries {
+        let key = reader.text()?;
+        match key.as_str() {
+            "Value" => signed.value = reader.bytes()?,
+            "Validity" => signed.validity = reader.bytes()?,
+            "ValidityType" => signed.validity_type = reader.uint()?,
+            "Sequence" => signed.sequence = reader.uint()?,
+            "TTL" => signed.ttl = reader.uint()?,
+            _ => {
+                reader.skip()?;
+                continue;
+            }
+        }
+        seen += 1;
+    }
+    if seen < 5 {
+        anyhow::bail!("IPNS数据缺少必需字段");
+    }
+    Ok(signed)
+}
+
+fn cbor_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    cbor_header(out, 2, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn cbor_text(out: &mut Vec<u8>, text: &str) {
+    cbor_header(out, 3, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+struct CborReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> CborReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < len {
+            anyhow::bail!("IPNS CBOR数据被截断");
+        }
+        let (head, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    fn head(&mut self) -> Result<(u8, u64)> {
+        let first = self.take(1)?[0];
+        let (major, info) = (first >> 5, first & 0x1f);
+        let value = match info {
+            0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into()?) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into()?) as u64,
+            27 => u64::from_be_bytes(self.take(8)?.try_into()?),
+            _ => anyhow::bail!("不支持的CBOR长度编码"),
+        };
+        Ok((major, value))
+    }
+
+    fn expect(&mut self, major: u8) -> Result<u64> {
+        let (actual, value) = self.head()?;
+        if actual != major {
+            anyhow::bail!("CBOR类型不符: 期望{} 实际{}", major, actual);
+        }
+        Ok(value)
+    }
+
+    fn uint(&mut self) -> Result<u64> {
+        self.expect(0)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>> {
+        let len = self.expect(2)? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn text(&mut self) -> Result<String> {
+        let len = self.expect(3)? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).context("CBOR文本不是UTF-8")
+    }
+
+    /// 跳过未知字段的值（只支持IPNS扩展字段会用到的标量、字节串和文本）
+    fn skip(&mut self) -> Result<()> {
+        let (major, value) = self.head()?;
+        match major {
+            0 | 1 | 7 => Ok(()),
+            2 | 3 => self.take(value as usize).map(|_| ()),
+            _ => anyhow::bail!("不支持的CBOR扩展字段类型: {}", major),
+        }
+    }
+}
+
+// ---- protobuf（IpnsEntry）----
+
+fn put_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+    write_varint(out, field << 3);
+    write_varint(out, value);
+}
+
+fn put_bytes(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(out, (field << 3) | 2);
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+enum ProtoValue<'a> {
+    Varint,
+    Bytes(&'a [u8]),
+}
+
+struct ProtoReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> ProtoReader<'a> {
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.buf.split_first().context("IPNS protobuf被截断")?;
+            self.buf = rest;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        anyhow::bail!("IPNS protobuf varint过长")
+    }
+
+    fn skip(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < len {
+            anyhow::bail!("IPNS protobuf被截断");
+        }
+        let (head, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    fn next_field(&mut self) -> Result<Option<(u64, ProtoValue<'a>)>> {
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+        let tag = self.varint()?;
+        let value = match tag & 0x7 {
+            0 => {
+                self.varint()?;
+                ProtoValue::Varint
+            }
+            1 => {
+                self.skip(8)?;
+                ProtoValue::Varint
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                ProtoValue::Bytes(self.skip(len)?)
+            }
+            5 => {
+                self.skip(4)?;
+                ProtoValue::Varint
+            }
+            wire => anyhow::bail!("不支持的protobuf字段类型: {}", wire),
+        };
+        Ok(Some((tag >> 3, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_discovery::KademliaDht;
+    use crate::clock::{Clock, MockClock};
+    use crate::key_manager::KeyPair;
+    use crate::libp2p_identity::LibP2PIdentity;
+
+    #[tokio::test]
+    async fn test_publish_and_resolve() {
+        let listen: libp2p::Multiaddr = "/ip4/127.0.0.1/tcp/0".parse().unwrap();
+        let identity = LibP2PIdentity::generate().unwrap();
+        let dht = Arc::new(KademliaDht::start(&identity, listen).await.unwrap());
+        let clock = MockClock::new(1_700_000_000);
+
+        let keypair = KeyPair::generate().unwrap();
+        let publisher = IpnsPublisher::new(Arc::new(keypair.clone()), dht)
+            .unwrap()
+            .with_validity(Duration::from_secs(3600))
+            .with_clock(Arc::new(clock.clone()));
+        assert_eq!(publisher.name(), ipns_name(&keypair.public_key).unwrap());
+
+        let first = publisher.publish_cid("bafyfirst").await.unwrap();
+        let second = publisher.publish_cid("bafysecond").await.unwrap();
+        assert_eq!((first.sequence, second.sequence), (0, 1));
+
+        // 编码往返后签名仍然有效
+        let decoded = IpnsRecord::from_bytes(&second.to_bytes()).unwrap();
+        assert_eq!(decoded, second);
+        decoded.verify(&publisher.name(), clock.now_secs()).unwrap();
+        assert!(decoded.verify(&ipns_name(&KeyPair::generate().unwrap().public_key).unwrap(), clock.now_secs()).is_err());
+
+        let mut tampered = second.clone();
+        tampered.value = "/ipfs/bafyevil".to_string();
+        assert!(tampered.verify(&publisher.name(), clock.now_secs()).is_err());
+
+        assert_eq!(publisher.resolve(&publisher.name()).await.unwrap().as_deref(), Some("/ipfs/bafysecond"));
+
+        clock.advance(Duration::from_secs(3600));
+        assert!(publisher.resolve(&publisher.name()).await.unwrap().is_none());
+    }
+}
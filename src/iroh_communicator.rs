@@ -5,13 +5,28 @@
  */
 
 use anyhow::{Result, anyhow};
+use base64::{Engine as _, engine::general_purpose};
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::sync::mpsc;
+
+use crate::datagram_signal::{Signal, SignalFilter, SignalKind};
+use crate::did_cache::DIDCache;
+use crate::did_resolver::DIDSignatureVerifier;
+use crate::event_channel::{bounded_event_channel, ChannelError, ChannelMetrics, EventReceiver, EventSender, OverflowPolicy, DEFAULT_CHANNEL_CAPACITY};
+use crate::error::{DiapError, DiapResult};
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::message_status::{AckKind, MessageAck, MessageStatus, MessageStatusTracker, MessageStatusUpdate};
+use crate::pending_requests::{PendingRequests, DEFAULT_REQUEST_TIMEOUT};
+use crate::timestamp_window::TimestampWindow;
 
 // Iroh核心组件 - 基于真实API
-use iroh::{Endpoint, NodeAddr};
+use iroh::endpoint::{Connection, RecvStream, SendStream};
+use iroh::{Endpoint, NodeAddr, NodeId};
+use tokio::task::JoinHandle;
 
 /// Iroh通信器配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +43,15 @@ pub struct IrohConfig {
     pub enable_relay: Option<bool>,
     /// 是否启用NAT穿透
     pub enable_nat_traversal: Option<bool>,
+    /// 请求等待响应的超时时间（秒）
+    #[serde(default)]
+    pub request_timeout: Option<u64>,
+    /// 收到的消息和信号的队列容量（默认1024）
+    #[serde(default)]
+    pub channel_capacity: Option<usize>,
+    /// 队列满时的处理策略（默认丢弃最旧的）
+    #[serde(default)]
+    pub overflow_policy: Option<OverflowPolicy>,
 }
 
 impl Default for IrohConfig {
@@ -39,6 +63,9 @@ impl Default for IrohConfig {
             connection_timeout: Some(30),
             enable_relay: Some(true),
             enable_nat_traversal: Some(true),
+            request_timeout: Some(DEFAULT_REQUEST_TIMEOUT.as_secs()),
+            channel_capacity: Some(DEFAULT_CHANNEL_CAPACITY),
+            overflow_policy: Some(OverflowPolicy::default()),
         }
     }
 }
@@ -56,6 +83,8 @@ pub enum IrohMessageType {
     ResourceResponse,
     /// 心跳消息
     Heartbeat,
+    /// 消息回执（内容为MessageAck的JSON）
+    Receipt,
     /// 自定义消息
     Custom(String),
 }
@@ -67,7 +96,7 @@ pub struct IrohMessage {
     pub message_id: String,
     /// 消息类型
     pub message_type: IrohMessageType,
-    /// 发送者DID
+    /// 发送者DID（未签名的消息只能填发送方的Iroh节点ID）
     pub from_did: String,
     /// 接收者DID（可选，用于直接通信）
     pub to_did: Option<String>,
@@ -75,12 +104,40 @@ pub struct IrohMessage {
     pub content: String,
     /// 时间戳
     pub timestamp: u64,
-    /// 签名（可选）
+    /// from_did的签名（未签名时接收方只认QUIC握手认证的节点ID）
     pub signature: Option<String>,
     /// 元数据
     pub metadata: HashMap<String, String>,
 }
 
+/// 送达回执的最大字节数
+const MAX_RECEIPT_SIZE: usize = 4096;
+
+/// 单条消息的最大字节数
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// 认证响应在元数据中的键
+const AUTH_RESPONSE_METADATA_KEY: &str = "response";
+
+/// 响应消息中指向原请求ID的元数据键
+pub const IN_REPLY_TO_METADATA_KEY: &str = "in_reply_to";
+
+impl IrohMessage {
+    /// 该消息回复的请求ID
+    pub fn in_reply_to(&self) -> Option<&str> {
+        self.metadata.get(IN_REPLY_TO_METADATA_KEY).map(String::as_str)
+    }
+
+    /// 签名覆盖的数据（不含签名字段）
+    pub fn signing_data(&self) -> Result<Vec<u8>> {
+        let unsigned = IrohMessage {
+            signature: None,
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned).map_err(|e| anyhow!("序列化消息失败: {}", e))
+    }
+}
+
 /// Iroh连接信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IrohConnection {
@@ -98,6 +155,9 @@ pub struct IrohConnection {
     pub data_hash: Option<String>,
 }
 
+/// 活跃连接表（节点ID -> 连接信息和NodeAddr），监听和心跳任务共享
+type SharedConnections = Arc<Mutex<HashMap<String, (IrohConnection, NodeAddr)>>>;
+
 /// Iroh通信器
 pub struct IrohCommunicator {
     /// 网络端点
@@ -105,26 +165,51 @@ pub struct IrohCommunicator {
     /// 配置
     _config: IrohConfig,
     /// 活跃连接（使用NodeAddr作为键）
-    connections: HashMap<String, (IrohConnection, NodeAddr)>,
+    connections: SharedConnections,
+    /// 发送者DID最近使用的节点ID（只记录签名有效的消息）
+    did_nodes: Arc<DashMap<String, String>>,
     /// 消息接收通道
-    message_receiver: mpsc::UnboundedReceiver<IrohMessage>,
+    message_receiver: EventReceiver<IrohMessage>,
     /// 消息发送通道
-    message_sender: mpsc::UnboundedSender<IrohMessage>,
+    message_sender: EventSender<IrohMessage>,
     /// 节点地址
     node_addr: NodeAddr,
+    /// 等待响应的请求
+    pending_requests: PendingRequests<IrohMessage>,
+    /// 按发送者DID文档验证签名
+    signature_verifier: DIDSignatureVerifier,
+    /// 应用协议（ALPN，由网络命名空间派生）
+    alpn: Vec<u8>,
+    /// 消息时间戳窗口
+    timestamp_window: TimestampWindow,
+    /// 信号数据报使用的应用协议
+    signal_alpn: Vec<u8>,
+    /// 发送信号用的连接（按节点ID复用）
+    signal_connections: Arc<tokio::sync::Mutex<HashMap<String, Connection>>>,
+    /// 信号接收通道
+    signal_receiver: EventReceiver<Signal>,
+    /// 信号发送通道
+    signal_sender: EventSender<Signal>,
+    /// 过期/乱序信号过滤
+    signal_filter: Arc<Mutex<SignalFilter>>,
+    /// 下一个信号序号（从当前毫秒时间开始，重启后接收方不会误判为乱序）
+    signal_seq: AtomicU64,
+    /// 已发送消息的回执状态
+    status_tracker: MessageStatusTracker,
+    /// 自动签发送达回执的本地身份（未设置时只回复固定确认文本）
+    receipt_signer: Option<Arc<dyn Signer>>,
 }
 
-// ALPN是Iroh约定的应用协议
-const ALPN: &[u8] = b"diap-iroh/communication/1";
-
 impl IrohCommunicator {
     /// 创建新的Iroh通信器
     pub async fn new(config: IrohConfig) -> Result<Self> {
         log::info!("🚀 创建Iroh通信器");
 
-        // 构建节点端点，配置ALPN支持
+        // 构建节点端点，配置ALPN支持（ALPN是Iroh约定的应用协议）
+        let alpn = crate::constants::network_params().iroh_alpn().into_bytes();
+        let signal_alpn = [alpn.as_slice(), b"/signal"].concat();
         let endpoint = Endpoint::builder()
-            .alpns(vec![ALPN.to_vec()])
+            .alpns(vec![alpn.clone(), signal_alpn.clone()])
             .bind()
             .await
             .map_err(|e| anyhow!("Failed to bind endpoint: {}", e))?;
@@ -132,18 +217,38 @@ impl IrohCommunicator {
         // 获取本地节点地址
         let node_addr = endpoint.node_addr();
 
-        // 创建消息通道
-        let (message_sender, message_receiver) = mpsc::unbounded_channel();
+        // 创建有界消息通道（应用处理不过来时按溢出策略处理，不会无限占用内存）
+        let capacity = config.channel_capacity.unwrap_or(DEFAULT_CHANNEL_CAPACITY);
+        let policy = config.overflow_policy.unwrap_or_default();
+        let (message_sender, message_receiver) = bounded_event_channel(capacity, policy);
+        let (signal_sender, signal_receiver) = bounded_event_channel(capacity, policy);
 
         log::info!("✅ Iroh通信器创建成功，节点ID: {}", node_addr.node_id);
 
+        let request_timeout = config.request_timeout
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+
         Ok(Self {
             endpoint,
             _config: config,
-            connections: HashMap::new(),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            did_nodes: Arc::new(DashMap::new()),
             message_receiver,
             message_sender,
             node_addr,
+            pending_requests: PendingRequests::new(request_timeout),
+            signature_verifier: DIDSignatureVerifier::new(DIDCache::new(None, None)),
+            alpn,
+            timestamp_window: TimestampWindow::default(),
+            signal_alpn,
+            signal_connections: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            signal_receiver,
+            signal_sender,
+            signal_filter: Arc::new(Mutex::new(SignalFilter::default())),
+            signal_seq: AtomicU64::new(Self::now_ms()),
+            status_tracker: MessageStatusTracker::new(),
+            receipt_signer: None,
         })
     }
 
@@ -154,92 +259,246 @@ impl IrohCommunicator {
     }
 
     /// 连接到远程节点（使用NodeAddr对象）
-    pub async fn connect_to_node_with_addr(&mut self, remote_addr: NodeAddr) -> Result<String> {
+    pub async fn connect_to_node_with_addr(&self, remote_addr: NodeAddr) -> DiapResult<String> {
         let remote_node_id = remote_addr.node_id.to_string();
         let node_addr_str = format!("{:?}", remote_addr.node_id);
         
         log::info!("🔗 连接到节点: {}", node_addr_str);
 
         // 连接到目标节点
-        let _conn = self.endpoint.connect(remote_addr.clone(), ALPN).await
-            .map_err(|e| anyhow!("Failed to connect to node: {}", e))?;
+        let _conn = self.endpoint.connect(remote_addr.clone(), &self.alpn).await
+            .map_err(|e| DiapError::p2p(format!("Failed to connect to node: {}", e)))?;
         
         // 记录连接
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
         let connection_info = IrohConnection {
             remote_node_id: remote_node_id.clone(),
             remote_addr: node_addr_str.clone(),
             connected: true,
-            connected_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
-            last_heartbeat: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            connected_at: now,
+            last_heartbeat: now,
             data_hash: None,
         };
 
         // 存储连接信息和NodeAddr
-        self.connections.insert(remote_node_id.clone(), (connection_info, remote_addr));
+        self.lock_connections().insert(remote_node_id.clone(), (connection_info, remote_addr));
 
         log::info!("✅ 已连接到节点: {} ({})", remote_node_id, node_addr_str);
         Ok(remote_node_id)
     }
 
-    /// 连接到远程节点（简化版本，需要预存的NodeAddr）
-    pub async fn connect_to_node(&mut self, node_id: &str) -> Result<String> {
-        log::info!("🔗 连接到节点: {}", node_id);
-
-        // 这里简化处理，实际应用中需要从discovery服务或缓存中获取NodeAddr
-        return Err(anyhow!("Please use connect_to_node_with_addr() with a proper NodeAddr object. NodeAddr cannot be parsed from string."));
+    /// 按节点ID连接：优先使用已知的NodeAddr，否则交给端点的发现服务查找地址
+    pub async fn connect_to_node(&self, node_id: &str) -> DiapResult<String> {
+        let remote_addr = match self.node_addr_of(node_id) {
+            Some(addr) => addr,
+            None => {
+                let node_id: NodeId = node_id.parse()
+                    .map_err(|e| DiapError::p2p(format!("无效的节点ID {}: {}", node_id, e)))?;
+                NodeAddr::new(node_id)
+            }
+        };
+        self.connect_to_node_with_addr(remote_addr).await
     }
 
     /// 断开连接
-    pub async fn disconnect_from_node(&mut self, node_id: &str) -> Result<()> {
-        if let Some((mut connection, _node_addr)) = self.connections.remove(node_id) {
+    pub async fn disconnect_from_node(&self, node_id: &str) -> Result<()> {
+        let removed = self.lock_connections().remove(node_id);
+        if let Some((mut connection, _node_addr)) = removed {
             connection.connected = false;
+            self.signal_connections.lock().await.remove(node_id);
             log::info!("🔌 已断开与节点的连接: {} ({})", node_id, connection.remote_addr);
         }
         Ok(())
     }
 
     /// 发送消息到指定节点
-    pub async fn send_message(&self, node_id: &str, message: IrohMessage) -> Result<()> {
-        if let Some((_connection, node_addr)) = self.connections.get(node_id) {
-            self.send_message_with_addr(node_addr.clone(), message).await
+    pub async fn send_message(&self, node_id: &str, message: IrohMessage) -> DiapResult<()> {
+        if let Some(node_addr) = self.node_addr_of(node_id) {
+            self.send_message_with_addr(node_addr, message).await
         } else {
-            Err(anyhow!("节点未连接: {}", node_id))
+            Err(DiapError::p2p(format!("节点未连接: {}", node_id)))
         }
     }
 
     /// 使用NodeAddr对象发送消息到指定节点
-    pub async fn send_message_with_addr(&self, remote_addr: NodeAddr, message: IrohMessage) -> Result<()> {
-        // 序列化消息
-        let message_data = serde_json::to_vec(&message)?;
+    /// 消息的回执状态可通过`message_status`查询或从`message_status_stream`订阅
+    pub async fn send_message_with_addr(&self, remote_addr: NodeAddr, message: IrohMessage) -> DiapResult<()> {
+        let tracked = !matches!(message.message_type, IrohMessageType::Receipt);
+        if tracked {
+            self.status_tracker.track(&message.message_id, message.to_did.as_deref());
+        }
+        let message_id = message.message_id.clone();
+        let result = self.transmit(remote_addr, message).await;
+        if let (Err(e), true) = (&result, tracked) {
+            self.status_tracker.mark_failed(&message_id, &e.to_string());
+        }
+        result
+    }
 
-        // 计算BLAKE3哈希用于验证
-        let hash = blake3::hash(&message_data);
-        let data_hash = hash.to_string();
-        
-        // 连接到目标节点并建立QUIC双向流
-        let conn = self.endpoint.connect(remote_addr, ALPN).await
-            .map_err(|e| anyhow!("Failed to connect for message sending: {}", e))?;
-        let (mut send_stream, _recv_stream) = conn.open_bi().await
-            .map_err(|e| anyhow!("Failed to open bidirectional stream: {}", e))?;
-        
-        // 发送数据
-        send_stream.write_all(&message_data).await
-            .map_err(|e| anyhow!("Failed to write message data: {}", e))?;
-        send_stream.finish()
-            .map_err(|e| anyhow!("Failed to finish stream: {}", e))?;
+    async fn transmit(&self, remote_addr: NodeAddr, message: IrohMessage) -> DiapResult<()> {
+        let (conn, mut recv_stream, data_hash) = write_message(&self.endpoint, &self.alpn, remote_addr, &message).await?;
+
+        // 对端在同一个流上回送签名的送达回执，后台读取，不阻塞发送方
+        let tracker = self.status_tracker.clone();
+        tokio::spawn(async move {
+            let _conn = conn;
+            let Ok(data) = recv_stream.read_to_end(MAX_RECEIPT_SIZE).await else {
+                return;
+            };
+            if let Ok(ack) = MessageAck::from_bytes(&data) {
+                if let Err(e) = tracker.record_ack(&ack) {
+                    log::warn!("⚠️ 忽略无效的送达回执: {}", e);
+                }
+            }
+        });
 
         log::debug!("📤 消息已发送 (消息ID: {}, 哈希: {})", 
                    message.message_id, data_hash);
         Ok(())
     }
 
+    /// 设置自动签发送达回执的本地身份：收到消息后在同一个流上回送签名回执
+    pub fn with_receipt_signer(mut self, signer: Arc<dyn Signer>) -> Self {
+        self.receipt_signer = Some(signer);
+        self
+    }
+
+    /// 应用处理完消息后向发送者发送已读回执
+    pub async fn send_read_receipt(&self, node_id: &str, signer: &dyn Signer, message: &IrohMessage) -> DiapResult<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let ack = MessageAck::new(signer, &message.message_id, &message.from_did, AckKind::Read, now)
+            .map_err(DiapError::from_p2p)?;
+        let content = String::from_utf8(ack.to_bytes().map_err(DiapError::from_p2p)?)
+            .map_err(|e| DiapError::p2p(format!("回执编码失败: {}", e)))?;
+        let receipt = self.create_reply(message, &signer.did(), &content, IrohMessageType::Receipt);
+        let receipt = self.sign_message(signer, receipt).map_err(DiapError::from_p2p)?;
+        self.send_message(node_id, receipt).await
+    }
+
+    /// 已发送消息的当前回执状态
+    pub fn message_status(&self, message_id: &str) -> Option<MessageStatus> {
+        self.status_tracker.status(message_id)
+    }
+
+    /// 订阅已发送消息的状态变化（已发送、已送达、已读、失败）
+    pub fn message_status_stream(&self) -> tokio::sync::broadcast::Receiver<MessageStatusUpdate> {
+        self.status_tracker.subscribe()
+    }
+
+    /// 发送低延迟信号（在线状态、输入中、进度等）
+    ///
+    /// 信号签名后作为单个QUIC数据报发出，不重传也不保证顺序；丢失的信号由下一条信号覆盖。
+    /// 需要可靠送达的内容请使用`send_message`
+    pub async fn send_signal(&self, node_id: &str, signer: &dyn Signer, kind: SignalKind, payload: &[u8]) -> DiapResult<()> {
+        let Some(node_addr) = self.node_addr_of(node_id) else {
+            return Err(DiapError::p2p(format!("节点未连接: {}", node_id)));
+        };
+
+        let seq = self.signal_seq.fetch_add(1, Ordering::Relaxed);
+        let datagram = Signal::sign(signer, kind, payload, seq, Self::now_ms())
+            .and_then(|signal| signal.encode())
+            .map_err(DiapError::from_p2p)?;
+
+        let conn = {
+            let mut signal_connections = self.signal_connections.lock().await;
+            match signal_connections.get(node_id) {
+                Some(conn) => conn.clone(),
+                None => {
+                    let conn = self.endpoint.connect(node_addr.clone(), &self.signal_alpn).await
+                        .map_err(|e| DiapError::p2p(format!("建立信号连接失败: {}", e)))?;
+                    signal_connections.insert(node_id.to_string(), conn.clone());
+                    conn
+                }
+            }
+        };
+
+        if conn.max_datagram_size().is_none_or(|max| datagram.len() > max) {
+            return Err(DiapError::p2p(format!("对端不接受该大小的数据报: {} 字节", datagram.len())));
+        }
+        if let Err(e) = conn.send_datagram(datagram.into()) {
+            // 连接已失效时丢弃缓存，下一条信号重新建立连接；本条信号不重传
+            self.signal_connections.lock().await.remove(node_id);
+            return Err(DiapError::p2p(format!("发送信号失败: {}", e)));
+        }
+
+        log::debug!("📶 信号已发送: 节点 {} #{}", node_id, seq);
+        Ok(())
+    }
+
+    /// 接收信号（已验证签名，过期和乱序的信号已被丢弃）
+    pub async fn receive_signal(&mut self) -> Option<Signal> {
+        self.signal_receiver.recv().await
+    }
+
+    fn now_ms() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+    }
+
+    /// 发送请求并等待对端回复（回复消息的in_reply_to指向请求ID），超时时间为request_timeout
+    /// 只接受来自该节点的回复，其他节点携带相同in_reply_to的消息按普通消息转发
+    pub async fn request_and_wait(&self, node_id: &str, request: IrohMessage) -> DiapResult<IrohMessage> {
+        let node_addr = self.node_addr_of(node_id)
+            .ok_or_else(|| DiapError::p2p(format!("节点未连接: {}", node_id)))?;
+        // 先登记再发送，避免响应先于登记到达
+        let response = self.pending_requests.register_from(&request.message_id, node_addr.node_id.to_string());
+        if let Err(e) = self.send_message_with_addr(node_addr, request).await {
+            self.pending_requests.cancel(response.request_id());
+            return Err(e);
+        }
+        response.wait().await
+    }
+
+    /// 创建对请求的回复消息
+    pub fn create_reply(&self, request: &IrohMessage, from_did: &str, content: &str, message_type: IrohMessageType) -> IrohMessage {
+        let mut metadata = HashMap::new();
+        metadata.insert(IN_REPLY_TO_METADATA_KEY.to_string(), request.message_id.clone());
+
+        IrohMessage {
+            message_id: crate::message_id::new_message_id(),
+            message_type,
+            from_did: from_did.to_string(),
+            to_did: Some(request.from_did.clone()),
+            content: content.to_string(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            signature: None,
+            metadata,
+        }
+    }
+
+    /// 用本地身份签名消息（from_did必须是签名者的DID）
+    pub fn sign_message(&self, signer: &dyn Signer, mut message: IrohMessage) -> Result<IrohMessage> {
+        if message.from_did != signer.did() {
+            return Err(anyhow!("消息发送者与签名者不一致: {}", message.from_did));
+        }
+        let signature = signer.sign(&message.signing_data()?)?;
+        message.signature = Some(general_purpose::STANDARD.encode(signature));
+        Ok(message)
+    }
+
+    /// 设置消息时间戳窗口（时间戳包含在签名中，窗口外的消息验证失败）
+    pub fn with_timestamp_window(mut self, window: TimestampWindow) -> Self {
+        self.timestamp_window = window;
+        self
+    }
+
+    /// 用from_did的DID文档中发布的密钥验证消息签名（文档会被缓存）
+    /// 时间戳超出窗口的消息直接判定为无效
+    pub fn verify_message(&self, message: &IrohMessage) -> DiapResult<bool> {
+        verify_signed_message(&self.signature_verifier, &self.timestamp_window, message)
+    }
+
+    /// 等待响应的请求数
+    pub fn pending_request_count(&self) -> usize {
+        self.pending_requests.len()
+    }
+
     /// 创建认证请求消息
     pub fn create_auth_request(&self, from_did: &str, to_did: &str, challenge: &str) -> IrohMessage {
         let mut metadata = HashMap::new();
         metadata.insert("challenge".to_string(), challenge.to_string());
 
         IrohMessage {
-            message_id: uuid::Uuid::new_v4().to_string(),
+            message_id: crate::message_id::new_message_id(),
             message_type: IrohMessageType::AuthRequest,
             from_did: from_did.to_string(),
             to_did: Some(to_did.to_string()),
@@ -250,13 +509,58 @@ impl IrohCommunicator {
         }
     }
 
+    /// 发起认证：发送签名的认证请求，等待对端签名的认证响应并验证
+    /// 本节点需要运行消息监听器才能收到响应
+    pub async fn request_auth(&self, node_id: &str, signer: &dyn Signer, to_did: &str, challenge: &str) -> DiapResult<IrohMessage> {
+        let request = self.create_auth_request(&signer.did(), to_did, challenge);
+        let request = self.sign_message(signer, request).map_err(DiapError::from_p2p)?;
+        let response = self.request_and_wait(node_id, request).await?;
+        self.check_auth_response(to_did, &response)?;
+        log::info!("🔐 认证完成: {} (节点 {})", to_did, node_id);
+        Ok(response)
+    }
+
+    /// 回复认证请求：签名的认证响应发回请求方最近使用的节点
+    pub async fn respond_auth(&self, signer: &dyn Signer, request: &IrohMessage, response: &str) -> DiapResult<()> {
+        let node_id = self.node_for_did(&request.from_did)
+            .ok_or_else(|| DiapError::p2p(format!("未知请求方节点: {}", request.from_did)))?;
+        let reply = self.create_auth_reply(signer, request, response)?;
+        self.send_message(&node_id, reply).await
+    }
+
+    /// 创建对认证请求的签名回复（带in_reply_to，可由request_and_wait匹配）
+    pub fn create_auth_reply(&self, signer: &dyn Signer, request: &IrohMessage, response: &str) -> DiapResult<IrohMessage> {
+        if !matches!(request.message_type, IrohMessageType::AuthRequest) {
+            return Err(DiapError::p2p(format!("不是认证请求: {}", request.message_id)));
+        }
+        let mut reply = self.create_reply(request, &signer.did(), &format!("认证响应: {}", response), IrohMessageType::AuthResponse);
+        reply.metadata.insert(AUTH_RESPONSE_METADATA_KEY.to_string(), response.to_string());
+        self.sign_message(signer, reply).map_err(DiapError::from_p2p)
+    }
+
+    /// 认证响应必须是预期DID签名的AuthResponse
+    fn check_auth_response(&self, expected_did: &str, response: &IrohMessage) -> DiapResult<()> {
+        if !matches!(response.message_type, IrohMessageType::AuthResponse) {
+            return Err(DiapError::p2p(format!("期望认证响应，收到: {:?}", response.message_type)));
+        }
+        if response.from_did != expected_did || !self.verify_message(response)? {
+            return Err(DiapError::p2p(format!("认证响应签名无效: {}", response.from_did)));
+        }
+        Ok(())
+    }
+
+    /// 发送者DID最近使用的节点ID（来自签名有效的入站消息）
+    pub fn node_for_did(&self, did: &str) -> Option<String> {
+        self.did_nodes.get(did).map(|node| node.clone())
+    }
+
     /// 创建认证响应消息
     pub fn create_auth_response(&self, from_did: &str, to_did: &str, response: &str) -> IrohMessage {
         let mut metadata = HashMap::new();
         metadata.insert("response".to_string(), response.to_string());
 
         IrohMessage {
-            message_id: uuid::Uuid::new_v4().to_string(),
+            message_id: crate::message_id::new_message_id(),
             message_type: IrohMessageType::AuthResponse,
             from_did: from_did.to_string(),
             to_did: Some(to_did.to_string()),
@@ -270,7 +574,7 @@ impl IrohCommunicator {
     /// 创建心跳消息
     pub fn create_heartbeat(&self, from_did: &str) -> IrohMessage {
         IrohMessage {
-            message_id: uuid::Uuid::new_v4().to_string(),
+            message_id: crate::message_id::new_message_id(),
             message_type: IrohMessageType::Heartbeat,
             from_did: from_did.to_string(),
             to_did: None,
@@ -284,7 +588,7 @@ impl IrohCommunicator {
     /// 创建自定义消息
     pub fn create_custom_message(&self, from_did: &str, to_did: Option<&str>, content: &str, message_type: &str) -> IrohMessage {
         IrohMessage {
-            message_id: uuid::Uuid::new_v4().to_string(),
+            message_id: crate::message_id::new_message_id(),
             message_type: IrohMessageType::Custom(message_type.to_string()),
             from_did: from_did.to_string(),
             to_did: to_did.map(|s| s.to_string()),
@@ -296,51 +600,67 @@ impl IrohCommunicator {
     }
 
     /// 获取活跃连接列表
-    pub fn get_connections(&self) -> HashMap<String, &IrohConnection> {
-        self.connections.iter().map(|(k, (conn, _))| (k.clone(), conn)).collect()
+    pub fn get_connections(&self) -> HashMap<String, IrohConnection> {
+        self.lock_connections().iter().map(|(k, (conn, _))| (k.clone(), conn.clone())).collect()
     }
 
     /// 检查连接状态
     pub fn is_connected(&self, node_id: &str) -> bool {
-        self.connections.get(node_id).map_or(false, |(conn, _)| conn.connected)
+        self.lock_connections().get(node_id).is_some_and(|(conn, _)| conn.connected)
     }
 
     /// 获取连接统计信息
     pub fn get_connection_stats(&self) -> HashMap<String, u64> {
+        let connections = self.lock_connections();
         let mut stats = HashMap::new();
-        stats.insert("total_connections".to_string(), self.connections.len() as u64);
+        stats.insert("total_connections".to_string(), connections.len() as u64);
         stats.insert("active_connections".to_string(), 
-            self.connections.values().filter(|(conn, _)| conn.connected).count() as u64);
+            connections.values().filter(|(conn, _)| conn.connected).count() as u64);
+        stats.insert("message_queue_depth".to_string(), self.message_receiver.depth() as u64);
+        stats.insert("signal_queue_depth".to_string(), self.signal_receiver.depth() as u64);
         stats
     }
 
-    /// 启动心跳监控
-    pub async fn start_heartbeat_monitor(&self, from_did: &str, interval: Duration) {
-        let message_sender = self.message_sender.clone();
-        let from_did = from_did.to_string();
+    /// 启动心跳：按间隔向所有已连接节点发送心跳，成功时更新last_heartbeat，失败时标记为未连接
+    /// 对端监听器收到心跳后同样更新该连接的last_heartbeat，心跳不会交给应用；心跳不签名，发送方为本节点ID
+    pub fn start_heartbeat_monitor(&self, interval: Duration) -> JoinHandle<()> {
+        let endpoint = self.endpoint.clone();
+        let alpn = self.alpn.clone();
+        let connections = self.connections.clone();
+        let from_did = self.node_addr.node_id.to_string();
 
         tokio::spawn(async move {
             let mut interval_timer = tokio::time::interval(interval);
             loop {
                 interval_timer.tick().await;
-                
-                let heartbeat = IrohMessage {
-                    message_id: uuid::Uuid::new_v4().to_string(),
-                    message_type: IrohMessageType::Heartbeat,
-                    from_did: from_did.clone(),
-                    to_did: None,
-                    content: "心跳".to_string(),
-                    timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-                    signature: None,
-                    metadata: HashMap::new(),
-                };
 
-                if let Err(e) = message_sender.send(heartbeat) {
-                    log::error!("发送心跳失败: {}", e);
-                    break;
+                let peers: Vec<(String, NodeAddr)> = match connections.lock() {
+                    Ok(connections) => connections.iter().map(|(id, (_, addr))| (id.clone(), addr.clone())).collect(),
+                    Err(_) => break,
+                };
+                for (node_id, node_addr) in peers {
+                    let heartbeat = IrohMessage {
+                        message_id: crate::message_id::new_message_id(),
+                        message_type: IrohMessageType::Heartbeat,
+                        from_did: from_did.clone(),
+                        to_did: None,
+                        content: "心跳".to_string(),
+                        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                        signature: None,
+                        metadata: HashMap::new(),
+                    };
+                    // 等对端结束流再释放连接，确认心跳已被处理
+                    let alive = match write_message(&endpoint, &alpn, node_addr, &heartbeat).await {
+                        Ok((_conn, mut recv_stream, _)) => recv_stream.read_to_end(MAX_RECEIPT_SIZE).await.is_ok(),
+                        Err(e) => {
+                            log::warn!("💔 心跳发送失败: {} ({})", node_id, e);
+                            false
+                        }
+                    };
+                    mark_heartbeat(&connections, &node_id, alive);
                 }
             }
-        });
+        })
     }
 
     /// 接收消息
@@ -348,60 +668,70 @@ impl IrohCommunicator {
         self.message_receiver.recv().await
     }
 
-    /// 启动消息监听器
-    pub async fn start_message_listener(&mut self) -> Result<()> {
-        log::info!("🎧 启动Iroh消息监听器");
-        
-        // 监听传入的连接
-        while let Some(conn_future) = self.endpoint.accept().await {
-            let conn_future = conn_future.await
-                .map_err(|e| anyhow!("Failed to accept connection: {}", e))?;
-            
-            let remote_node_id = conn_future.remote_node_id();
-            log::info!("📨 新连接建立，节点ID: {:?}", remote_node_id);
-            
-            // 处理传入的双向流
-            if let Ok((mut send_stream, mut recv_stream)) = conn_future.accept_bi().await {
-                log::info!("📡 接受双向流");
-                
-                // 读取消息数据
-                if let Ok(data) = recv_stream.read_to_end(1024).await {
-                    log::info!("📥 收到消息: {} 字节", data.len());
-                    
-                    // 反序列化消息
-                    if let Ok(message) = serde_json::from_slice::<IrohMessage>(&data) {
-                        log::info!("📨 收到消息: {} 来自节点: {:?}", 
-                                  message.message_id, remote_node_id);
-                        
-                        // 通过内部通道发送消息
-                        if let Err(e) = self.message_sender.send(message) {
-                            log::error!("Failed to forward message: {}", e);
-                        }
-                        
-                        // 发送响应
-                        let response = b"Message received successfully!";
-                        if let Err(e) = send_stream.write_all(response).await {
-                            log::error!("Failed to send response: {}", e);
-                        }
-                    }
-                }
-                
-                // 关闭流
-                send_stream.finish().map_err(|e| log::error!("Failed to finish stream: {}", e)).ok();
-            }
-        }
-        
+    /// 收到的消息队列指标（深度、丢弃、拒绝数）
+    pub fn message_queue_metrics(&self) -> ChannelMetrics {
+        self.message_receiver.metrics()
+    }
+
+    /// 收到的信号队列指标
+    pub fn signal_queue_metrics(&self) -> ChannelMetrics {
+        self.signal_receiver.metrics()
+    }
+
+    /// 运行消息监听器（阻塞直到端点关闭）
+    pub async fn start_message_listener(&self) -> Result<()> {
+        run_accept_loop(self.endpoint.clone(), self.inbound_handler()).await;
         Ok(())
     }
 
+    /// 在后台运行消息监听器：每个入站连接由单独任务处理，同一连接上可以有多个消息流
+    /// 回执身份和时间戳窗口在启动时确定，应先完成with_*配置
+    pub fn spawn_message_listener(&self) -> JoinHandle<()> {
+        tokio::spawn(run_accept_loop(self.endpoint.clone(), self.inbound_handler()))
+    }
+
+    /// 在后台运行消息监听器，收到的消息投递到指定通道而不是receive_message（供传输层自行分发）
+    pub fn spawn_message_listener_to(&self, sender: EventSender<IrohMessage>) -> JoinHandle<()> {
+        let handler = InboundHandler {
+            message_sender: sender,
+            ..self.inbound_handler()
+        };
+        tokio::spawn(run_accept_loop(self.endpoint.clone(), handler))
+    }
+
+    fn inbound_handler(&self) -> InboundHandler {
+        InboundHandler {
+            connections: self.connections.clone(),
+            did_nodes: self.did_nodes.clone(),
+            message_sender: self.message_sender.clone(),
+            pending_requests: self.pending_requests.clone(),
+            status_tracker: self.status_tracker.clone(),
+            receipt_signer: self.receipt_signer.clone(),
+            signature_verifier: self.signature_verifier.clone(),
+            timestamp_window: self.timestamp_window,
+            signal_alpn: self.signal_alpn.clone(),
+            signal_filter: self.signal_filter.clone(),
+            signal_sender: self.signal_sender.clone(),
+        }
+    }
+
+    fn lock_connections(&self) -> std::sync::MutexGuard<'_, HashMap<String, (IrohConnection, NodeAddr)>> {
+        self.connections.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn node_addr_of(&self, node_id: &str) -> Option<NodeAddr> {
+        self.lock_connections().get(node_id).map(|(_, addr)| addr.clone())
+    }
+
     /// 关闭通信器
     pub async fn shutdown(&mut self) -> Result<()> {
         // 断开所有连接
-        for (node_id, _) in self.connections.clone() {
+        for node_id in self.get_connected_nodes() {
             self.disconnect_from_node(&node_id).await?;
         }
 
-        // 关闭消息通道
+        // 取消等待中的请求并关闭消息通道
+        self.pending_requests.cancel_all();
         drop(self.message_sender.clone());
 
         log::info!("🔌 Iroh通信器已关闭");
@@ -415,12 +745,262 @@ impl IrohCommunicator {
 
     /// 获取连接的节点列表
     pub fn get_connected_nodes(&self) -> Vec<String> {
-        self.connections.keys().cloned().collect()
+        self.lock_connections().keys().cloned().collect()
     }
 
     /// 检查节点是否已连接
     pub fn is_node_connected(&self, node_id: &str) -> bool {
-        self.connections.contains_key(node_id)
+        self.lock_connections().contains_key(node_id)
+    }
+}
+
+/// 连接到节点，在新的双向流上写入消息并结束发送端，返回连接、接收端和消息哈希
+async fn write_message(endpoint: &Endpoint, alpn: &[u8], remote_addr: NodeAddr, message: &IrohMessage) -> DiapResult<(Connection, RecvStream, String)> {
+    // 序列化消息
+    let message_data = serde_json::to_vec(message)
+        .map_err(|e| DiapError::p2p(format!("Failed to serialize message: {}", e)))?;
+    if message_data.len() > MAX_MESSAGE_SIZE {
+        return Err(DiapError::p2p(format!("消息过大: {} 字节", message_data.len())));
+    }
+
+    // 计算BLAKE3哈希用于验证
+    let data_hash = blake3::hash(&message_data).to_string();
+
+    // 连接到目标节点并建立QUIC双向流
+    let conn = endpoint.connect(remote_addr, alpn).await
+        .map_err(|e| DiapError::p2p(format!("Failed to connect for message sending: {}", e)))?;
+    let (mut send_stream, recv_stream) = conn.open_bi().await
+        .map_err(|e| DiapError::p2p(format!("Failed to open bidirectional stream: {}", e)))?;
+
+    // 发送数据
+    send_stream.write_all(&message_data).await
+        .map_err(|e| DiapError::p2p(format!("Failed to write message data: {}", e)))?;
+    send_stream.finish()
+        .map_err(|e| DiapError::p2p(format!("Failed to finish stream: {}", e)))?;
+
+    Ok((conn, recv_stream, data_hash))
+}
+
+/// 检查时间戳窗口，并用from_did的DID文档验证消息签名
+fn verify_signed_message(verifier: &DIDSignatureVerifier, window: &TimestampWindow, message: &IrohMessage) -> DiapResult<bool> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if let Err(violation) = window.check(message.timestamp, now) {
+        log::warn!("⏱️ 消息时间戳超出窗口: {} ({})", message.message_id, violation);
+        return Ok(false);
+    }
+    let Some(signature) = &message.signature else {
+        return Ok(false);
+    };
+    let Ok(signature) = general_purpose::STANDARD.decode(signature) else {
+        return Ok(false);
+    };
+    let data = message.signing_data().map_err(DiapError::from_p2p)?;
+    verifier.verify(&message.from_did, &data, &signature)
+}
+
+/// 更新连接的心跳状态
+fn mark_heartbeat(connections: &SharedConnections, node_id: &str, alive: bool) {
+    if let Ok(mut connections) = connections.lock() {
+        if let Some((connection, _)) = connections.get_mut(node_id) {
+            connection.connected = alive;
+            if alive {
+                connection.last_heartbeat = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            }
+        }
+    }
+}
+
+/// 接受入站连接直到端点关闭
+async fn run_accept_loop(endpoint: Endpoint, handler: InboundHandler) {
+    log::info!("🎧 启动Iroh消息监听器");
+
+    while let Some(incoming) = endpoint.accept().await {
+        match incoming.await {
+            Ok(conn) => handler.accept_connection(conn),
+            Err(e) => log::warn!("接受连接失败: {}", e),
+        }
+    }
+
+    log::info!("🎧 Iroh消息监听器已停止");
+}
+
+/// 入站连接处理所需的共享状态（监听任务持有一份克隆）
+#[derive(Clone)]
+struct InboundHandler {
+    connections: SharedConnections,
+    did_nodes: Arc<DashMap<String, String>>,
+    message_sender: EventSender<IrohMessage>,
+    pending_requests: PendingRequests<IrohMessage>,
+    status_tracker: MessageStatusTracker,
+    receipt_signer: Option<Arc<dyn Signer>>,
+    signature_verifier: DIDSignatureVerifier,
+    timestamp_window: TimestampWindow,
+    signal_alpn: Vec<u8>,
+    signal_filter: Arc<Mutex<SignalFilter>>,
+    signal_sender: EventSender<Signal>,
+}
+
+impl InboundHandler {
+    fn accept_connection(&self, conn: Connection) {
+        let remote_node_id = conn.remote_node_id();
+        log::info!("📨 新连接建立，节点ID: {:?}", remote_node_id);
+
+        // 信号连接只承载数据报，交给单独的读取任务
+        if conn.alpn().as_deref() == Some(self.signal_alpn.as_slice()) {
+            self.spawn_signal_reader(conn);
+            return;
+        }
+
+        let Ok(remote_node_id) = remote_node_id else {
+            log::warn!("无法确定入站连接的节点ID");
+            return;
+        };
+        let node_id = remote_node_id.to_string();
+
+        // 记录入站节点，之后可以用send_message回复它
+        if let Ok(mut connections) = self.connections.lock() {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            connections.entry(node_id.clone()).or_insert_with(|| (
+                IrohConnection {
+                    remote_node_id: node_id.clone(),
+                    remote_addr: format!("{:?}", remote_node_id),
+                    connected: true,
+                    connected_at: now,
+                    last_heartbeat: now,
+                    data_hash: None,
+                },
+                NodeAddr::new(remote_node_id),
+            ));
+        }
+
+        let handler = self.clone();
+        tokio::spawn(async move {
+            while let Ok((send_stream, recv_stream)) = conn.accept_bi().await {
+                handler.handle_stream(&node_id, send_stream, recv_stream).await;
+            }
+        });
+    }
+
+    async fn handle_stream(&self, node_id: &str, mut send_stream: SendStream, mut recv_stream: RecvStream) {
+        let data = match recv_stream.read_to_end(MAX_MESSAGE_SIZE).await {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("读取消息失败: {} ({})", node_id, e);
+                return;
+            }
+        };
+        log::debug!("📥 收到消息: {} 字节", data.len());
+
+        let Ok(message) = serde_json::from_slice::<IrohMessage>(&data) else {
+            log::warn!("⚠️ 丢弃无法解析的消息，来自节点: {}", node_id);
+            send_stream.finish().ok();
+            return;
+        };
+        log::info!("📨 收到消息: {} 来自节点: {}", message.message_id, node_id);
+
+        // 带签名的消息必须由from_did的密钥签名；未签名的消息只能以QUIC握手认证的节点ID作为发送方，
+        // 否则任何节点都能冒充DID投递消息、回复别人的请求
+        if message.signature.is_some() {
+            if !verify_signed_message(&self.signature_verifier, &self.timestamp_window, &message).unwrap_or(false) {
+                log::warn!("⚠️ 丢弃签名无效的消息: {} (声称来自 {})", message.message_id, message.from_did);
+                send_stream.finish().ok();
+                return;
+            }
+            self.did_nodes.insert(message.from_did.clone(), node_id.to_string());
+        } else if message.from_did != node_id {
+            log::warn!("⚠️ 丢弃未签名的消息: {} (声称来自 {}，实际节点 {})", message.message_id, message.from_did, node_id);
+            send_stream.finish().ok();
+            return;
+        }
+
+        match message.message_type {
+            // 心跳只更新连接状态，不交给应用
+            IrohMessageType::Heartbeat => {
+                mark_heartbeat(&self.connections, node_id, true);
+                send_stream.finish().ok();
+                return;
+            }
+            // 已读回执只更新状态，不交给应用
+            IrohMessageType::Receipt => {
+                match MessageAck::from_bytes(message.content.as_bytes()) {
+                    Ok(ack) if ack.acker_did == message.from_did => {
+                        if let Err(e) = self.status_tracker.record_ack(&ack) {
+                            log::warn!("⚠️ 忽略无效的已读回执: {}", e);
+                        }
+                    }
+                    _ => log::warn!("⚠️ 丢弃格式错误的回执: {}", message.message_id),
+                }
+                send_stream.finish().ok();
+                return;
+            }
+            _ => {}
+        }
+
+        // 送达回执在消息交给应用之前签好，转发后写回
+        let receipt = self.receipt_signer.as_ref().and_then(|signer| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            MessageAck::new(signer.as_ref(), &message.message_id, &message.from_did, AckKind::Delivered, now)
+                .and_then(|ack| ack.to_bytes())
+                .map_err(|e| log::error!("签发送达回执失败: {}", e))
+                .ok()
+        });
+
+        // 等待中的请求的回复（来自请求发往的节点）直接交给等待方，其余消息通过内部通道转发
+        let reply_to = message.in_reply_to().map(str::to_string);
+        match reply_to {
+            Some(request_id) if self.pending_requests.awaits(&request_id, node_id) => {
+                self.pending_requests.complete_from(&request_id, node_id, message);
+            }
+            _ => {
+                // Block策略下队列满时在这里等待，背压传导到该连接的发送方
+                if let Err(e) = self.message_sender.send(message).await {
+                    log::warn!("⚠️ 转发消息失败: {}", e);
+                }
+            }
+        }
+
+        // 发送响应（设置了回执身份时为签名的送达回执）
+        let response = receipt.unwrap_or_else(|| b"Message received successfully!".to_vec());
+        if let Err(e) = send_stream.write_all(&response).await {
+            log::error!("Failed to send response: {}", e);
+        }
+        send_stream.finish().map_err(|e| log::error!("Failed to finish stream: {}", e)).ok();
+    }
+
+    /// 读取信号连接上的数据报，直到连接关闭
+    fn spawn_signal_reader(&self, conn: Connection) {
+        let verifier = self.signature_verifier.clone();
+        let filter = self.signal_filter.clone();
+        let sender = self.signal_sender.clone();
+
+        tokio::spawn(async move {
+            while let Ok(datagram) = conn.read_datagram().await {
+                let signal = match Signal::decode(&datagram) {
+                    Ok(signal) => signal,
+                    Err(e) => {
+                        log::debug!("丢弃无法解析的信号数据报: {}", e);
+                        continue;
+                    }
+                };
+                let valid = signal.signing_data()
+                    .map(|data| verifier.verify(&signal.from_did, &data, &signal.signature).unwrap_or(false))
+                    .unwrap_or(false);
+                if !valid {
+                    log::warn!("⚠️ 丢弃签名无效的信号 (声称来自 {})", signal.from_did);
+                    continue;
+                }
+                let accepted = filter.lock()
+                    .map(|mut filter| filter.accept(&signal, IrohCommunicator::now_ms()))
+                    .unwrap_or(false);
+                if accepted {
+                    match sender.send(signal).await {
+                        Ok(()) => {}
+                        Err(ChannelError::Closed) => break,
+                        Err(e) => log::debug!("丢弃信号: {}", e),
+                    }
+                }
+            }
+        });
     }
 }
 
@@ -454,4 +1034,29 @@ mod tests {
         assert_eq!(heartbeat.from_did, "did:alice");
         assert_eq!(heartbeat.to_did, None);
     }
+
+    #[tokio::test]
+    async fn test_auth_reply_verification() {
+        use crate::key_manager::KeyPair;
+
+        let communicator = IrohCommunicator::new(IrohConfig::default()).await.unwrap();
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+
+        let request = communicator.create_auth_request(&alice.did, &bob.did, "challenge123");
+        let request = communicator.sign_message(&alice, request).unwrap();
+        assert!(communicator.verify_message(&request).unwrap());
+
+        let reply = communicator.create_auth_reply(&bob, &request, "signed-challenge").unwrap();
+        assert_eq!(reply.in_reply_to(), Some(request.message_id.as_str()));
+        assert_eq!(reply.metadata.get(AUTH_RESPONSE_METADATA_KEY).map(String::as_str), Some("signed-challenge"));
+        assert!(communicator.check_auth_response(&bob.did, &reply).is_ok());
+
+        // 响应必须来自被认证的DID，且只能回复认证请求
+        assert!(communicator.check_auth_response(&alice.did, &reply).is_err());
+        let mut forged = reply.clone();
+        forged.content = "篡改".to_string();
+        assert!(communicator.check_auth_response(&bob.did, &forged).is_err());
+        assert!(communicator.create_auth_reply(&bob, &reply, "x").is_err());
+    }
 }
\ No newline at end of file
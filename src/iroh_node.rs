@@ -1,10 +1,20 @@
-// DIAP Rust SDK - Iroh节点接口（预留）
+// DIAP Rust SDK - Iroh节点接口
 // Iroh是下一代P2P网络协议，提供更高效的数据传输
-// 当前为预留接口，完整实现将在后续版本
+// IrohNode是基于IrohCommunicator的简化门面：启动监听、连接引导节点、收发原始字节
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "iroh")]
+use base64::{Engine as _, engine::general_purpose};
+
+#[cfg(feature = "iroh")]
+use crate::iroh_communicator::{IrohCommunicator, IrohConfig as IrohCommConfig, IrohMessageType};
+
+/// 原始字节消息的自定义消息类型
+#[cfg(feature = "iroh")]
+const DATA_MESSAGE_TYPE: &str = "data";
+
 /// Iroh节点配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IrohConfig {
@@ -21,43 +31,111 @@ pub struct IrohConfig {
     pub enable_nat_traversal: bool,
 }
 
-/// Iroh节点（预留）
+/// Iroh节点
 #[cfg(feature = "iroh")]
 pub struct IrohNode {
-    _config: IrohConfig,
-    // iroh_net的实际实现将在这里
+    config: IrohConfig,
+    communicator: IrohCommunicator,
+    listener: Option<tokio::task::JoinHandle<()>>,
 }
 
 #[cfg(feature = "iroh")]
 impl IrohNode {
-    /// 创建新的Iroh节点
+    /// 创建新的Iroh节点（绑定端点，尚未接受连接）
     pub async fn new(config: IrohConfig) -> Result<Self> {
         log::info!("🚀 创建Iroh节点: {}", config.node_name);
-        log::warn!("⚠️  Iroh功能当前为预留状态");
-        
-        Ok(Self { _config: config })
+
+        let listen_addr = match config.listen_addr.as_deref().map(str::parse) {
+            Some(Ok(addr)) => Some(addr),
+            Some(Err(_)) => {
+                log::warn!("⚠️ 忽略无法解析的Iroh监听地址: {:?}", config.listen_addr);
+                IrohCommConfig::default().listen_addr
+            }
+            None => IrohCommConfig::default().listen_addr,
+        };
+        let communicator = IrohCommunicator::new(IrohCommConfig {
+            listen_addr,
+            enable_relay: Some(config.enable_nat_traversal),
+            enable_nat_traversal: Some(config.enable_nat_traversal),
+            ..IrohCommConfig::default()
+        }).await?;
+
+        Ok(Self { config, communicator, listener: None })
     }
     
-    /// 启动节点
+    /// 启动节点：后台接受连接，并连接配置的Bootstrap节点（失败只记录日志）
     pub async fn start(&mut self) -> Result<()> {
+        if self.listener.is_some() {
+            return Ok(());
+        }
         log::info!("启动Iroh节点...");
-        // TODO: 实现iroh-net的实际启动逻辑
-        Err(anyhow::anyhow!("Iroh功能尚未实现，将在v0.3.0中添加"))
+        self.listener = Some(self.communicator.spawn_message_listener());
+
+        for node_id in self.config.bootstrap_nodes.clone() {
+            if let Err(e) = self.communicator.connect_to_node(&node_id).await {
+                log::warn!("连接Bootstrap节点失败 {}: {}", node_id, e);
+            }
+        }
+        log::info!("✅ Iroh节点已启动: {}", self.node_id());
+        Ok(())
+    }
+
+    /// 本节点ID
+    pub fn node_id(&self) -> String {
+        self.communicator.get_node_addr_object().node_id.to_string()
     }
     
-    /// 连接到其他节点
-    pub async fn connect(&mut self, _peer_addr: &str) -> Result<()> {
-        Err(anyhow::anyhow!("Iroh功能尚未实现"))
+    /// 连接到其他节点（节点ID）
+    pub async fn connect(&mut self, peer_addr: &str) -> Result<()> {
+        self.communicator.connect_to_node(peer_addr).await?;
+        Ok(())
     }
     
     /// 发送数据
-    pub async fn send_data(&self, _peer_id: &str, _data: &[u8]) -> Result<()> {
-        Err(anyhow::anyhow!("Iroh功能尚未实现"))
+    pub async fn send_data(&self, peer_id: &str, data: &[u8]) -> Result<()> {
+        // 未签名的消息以节点ID作为发送方
+        let message = self.communicator.create_custom_message(
+            &self.node_id(),
+            None,
+            &general_purpose::STANDARD.encode(data),
+            DATA_MESSAGE_TYPE,
+        );
+        self.communicator.send_message(peer_id, message).await?;
+        Ok(())
     }
     
-    /// 接收数据
-    pub async fn receive_data(&self) -> Result<Vec<u8>> {
-        Err(anyhow::anyhow!("Iroh功能尚未实现"))
+    /// 接收数据（其他类型的消息请通过communicator_mut().receive_message()处理，这里会跳过）
+    pub async fn receive_data(&mut self) -> Result<Vec<u8>> {
+        loop {
+            let message = self.communicator.receive_message().await
+                .ok_or_else(|| anyhow::anyhow!("Iroh节点已关闭"))?;
+            match &message.message_type {
+                IrohMessageType::Custom(kind) if kind == DATA_MESSAGE_TYPE => {
+                    return general_purpose::STANDARD.decode(&message.content)
+                        .map_err(|e| anyhow::anyhow!("数据消息解码失败: {}", e));
+                }
+                other => log::debug!("receive_data跳过非数据消息: {:?}", other),
+            }
+        }
+    }
+
+    /// 底层通信器（认证请求/响应、心跳、回执等完整功能）
+    pub fn communicator(&self) -> &IrohCommunicator {
+        &self.communicator
+    }
+
+    /// 底层通信器（可变）
+    pub fn communicator_mut(&mut self) -> &mut IrohCommunicator {
+        &mut self.communicator
+    }
+}
+
+#[cfg(feature = "iroh")]
+impl Drop for IrohNode {
+    fn drop(&mut self) {
+        if let Some(listener) = self.listener.take() {
+            listener.abort();
+        }
     }
 }
 
@@ -89,7 +167,7 @@ pub mod helpers {
     /// 获取Iroh功能状态信息
     pub fn get_iroh_status() -> String {
         if is_iroh_available() {
-            "Iroh功能已启用（预留接口）".to_string()
+            "Iroh功能已启用".to_string()
         } else {
             "Iroh功能未启用".to_string()
         }
@@ -106,7 +184,6 @@ mod tests {
     }
     
     #[tokio::test]
-    #[ignore] // 因为Iroh功能未完全实现
     async fn test_iroh_node_creation() {
         use super::*;
         
@@ -0,0 +1,521 @@
+// DIAP Rust SDK - 定时任务调度模块
+// 按类cron表达式或固定间隔运行注册的异步任务（重新发布DID、刷新IPNS、轮换nonce纪元、发送心跳），
+// 支持随机抖动避免集群同时触发，持久化上次运行时间以便重启后按计划继续
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, TimeZone, Timelike, Utc};
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::{SharedClock, system_clock};
+
+/// 任务函数
+pub type JobFn = Arc<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// cron字段的取值集合
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CronField {
+    values: Vec<u32>,
+    wildcard: bool,
+}
+
+impl CronField {
+    fn parse(field: &str, min: u32, max: u32) -> Result<Self> {
+        let mut values = Vec::new();
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().context("无效的cron步长")?),
+                None => (part, 1),
+            };
+            if step == 0 {
+                anyhow::bail!("cron步长不能为0: {}", part);
+            }
+
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((a, b)) = range.split_once('-') {
+                (a.parse().context("无效的cron范围")?, b.parse().context("无效的cron范围")?)
+            } else {
+                let value: u32 = range.parse().with_context(|| format!("无效的cron字段: {}", part))?;
+                // "5/15"表示从5开始每15
+                (value, if part.contains('/') { max } else { value })
+            };
+            if start < min || end > max || start > end {
+                anyhow::bail!("cron字段超出范围 {}-{}: {}", min, max, part);
+            }
+            values.extend((start..=end).step_by(step as usize));
+        }
+
+        values.sort_unstable();
+        values.dedup();
+        Ok(Self { values, wildcard: field == "*" })
+    }
+
+    fn matches(&self, value: u32) -> bool {
+        self.values.binary_search(&value).is_ok()
+    }
+
+    /// 不小于value的第一个取值
+    fn next_from(&self, value: u32) -> Option<u32> {
+        self.values.iter().copied().find(|v| *v >= value)
+    }
+}
+
+/// 类cron表达式（UTC）：分 时 日 月 周，支持 * a-b a,b */n
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: CronField,
+    hours: CronField,
+    days_of_month: CronField,
+    months: CronField,
+    days_of_week: CronField,
+}
+
+impl CronSchedule {
+    /// 解析cron表达式，例如 "*/15 * * * *"、"0 3 * * 1-5"
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            anyhow::bail!("cron表达式需要5个字段: {}", expression);
+        }
+
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: CronField::parse(fields[0], 0, 59)?,
+            hours: CronField::parse(fields[1], 0, 23)?,
+            days_of_month: CronField::parse(fields[2], 1, 31)?,
+            months: CronField::parse(fields[3], 1, 12)?,
+            // 0和7都表示周日
+            days_of_week: {
+                let mut field = CronField::parse(fields[4], 0, 7)?;
+                if field.matches(7) {
+                    field.values.retain(|v| *v != 7);
+                    if !field.matches(0) {
+                        field.values.insert(0, 0);
+                    }
+                }
+                field
+            },
+        })
+    }
+
+    /// 表达式原文
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// 严格晚于after（Unix秒）的下一次触发时间，五年内无匹配时返回None
+    pub fn next_after(&self, after: u64) -> Option<u64> {
+        // 从下一分钟开始
+        let start = Utc.timestamp_opt(((after / 60) + 1) as i64 * 60, 0).single()?;
+        let mut day = start.date_naive();
+        let mut first_day = true;
+
+        for _ in 0..366 * 5 {
+            if self.months.matches(day.month()) && self.day_matches(day) {
+                let (from_hour, from_minute) = if first_day { (start.hour(), start.minute()) } else { (0, 0) };
+                let mut hour = self.hours.next_from(from_hour);
+                while let Some(h) = hour {
+                    let minute_floor = if h == from_hour { from_minute } else { 0 };
+                    if let Some(m) = self.minutes.next_from(minute_floor) {
+                        let time = day.and_hms_opt(h, m, 0)?;
+                        return Some(time.and_utc().timestamp() as u64);
+                    }
+                    hour = self.hours.next_from(h + 1);
+                }
+            }
+            day = day.succ_opt()?;
+            first_day = false;
+        }
+        None
+    }
+
+    /// 日与周：两者都受限时满足其一即可（与cron语义一致）
+    fn day_matches(&self, day: chrono::NaiveDate) -> bool {
+        let dom = self.days_of_month.matches(day.day());
+        let dow = self.days_of_week.matches(day.weekday().num_days_from_sunday());
+        match (self.days_of_month.wildcard, self.days_of_week.wildcard) {
+            (true, true) => true,
+            (true, false) => dow,
+            (false, true) => dom,
+            (false, false) => dom || dow,
+        }
+    }
+}
+
+/// 调度计划
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// 固定间隔
+    Every(Duration),
+
+    /// 类cron表达式
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// 固定间隔
+    pub fn every(interval: Duration) -> Self {
+        Schedule::Every(interval)
+    }
+
+    /// 解析cron表达式
+    pub fn cron(expression: &str) -> Result<Self> {
+        Ok(Schedule::Cron(CronSchedule::parse(expression)?))
+    }
+
+    /// 上次运行后的下一次运行时间：从未运行时间隔任务立即运行，cron任务等待下一个时间点；
+    /// 停机期间错过的运行在启动后补运行一次
+    fn next_run(&self, last_run: Option<u64>, now: u64) -> Option<u64> {
+        match self {
+            Schedule::Every(interval) => Some(match last_run {
+                Some(last) => last + interval.as_secs().max(1),
+                None => now,
+            }),
+            Schedule::Cron(cron) => cron.next_after(last_run.unwrap_or(now)),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Schedule::Every(interval) => format!("every {}s", interval.as_secs()),
+            Schedule::Cron(cron) => cron.expression().to_string(),
+        }
+    }
+}
+
+/// 任务运行记录（持久化）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct JobRecord {
+    last_run: Option<u64>,
+    run_count: u64,
+    failure_count: u64,
+    last_error: Option<String>,
+}
+
+/// 任务信息（供管理接口查询）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    /// 任务名称
+    pub name: String,
+
+    /// 计划描述
+    pub schedule: String,
+
+    /// 是否启用
+    pub enabled: bool,
+
+    /// 下一次运行时间（Unix秒）
+    pub next_run: Option<u64>,
+
+    /// 上一次运行时间（Unix秒）
+    pub last_run: Option<u64>,
+
+    /// 运行次数
+    pub run_count: u64,
+
+    /// 失败次数
+    pub failure_count: u64,
+
+    /// 最近一次错误
+    pub last_error: Option<String>,
+}
+
+struct ScheduledJob {
+    schedule: Schedule,
+    jitter: Duration,
+    job: JobFn,
+    enabled: bool,
+    next_run: Option<u64>,
+    record: JobRecord,
+}
+
+/// 任务调度器
+#[derive(Clone)]
+pub struct JobScheduler {
+    jobs: Arc<DashMap<String, ScheduledJob>>,
+    path: Option<PathBuf>,
+    clock: SharedClock,
+}
+
+impl JobScheduler {
+    /// 创建内存调度器（系统时钟）
+    pub fn new() -> Self {
+        Self::new_with_clock(system_clock())
+    }
+
+    /// 使用指定时间源创建调度器
+    pub fn new_with_clock(clock: SharedClock) -> Self {
+        Self {
+            jobs: Arc::new(DashMap::new()),
+            path: None,
+            clock,
+        }
+    }
+
+    /// 持久化上次运行时间到文件；之后注册的同名任务从记录的时间继续
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// 注册任务
+    pub fn register<F, Fut>(&self, name: &str, schedule: Schedule, job: F) -> Result<()>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+    {
+        self.register_with_jitter(name, schedule, Duration::ZERO, job)
+    }
+
+    /// 注册任务，每次运行随机推迟0..jitter
+    pub fn register_with_jitter<F, Fut>(&self, name: &str, schedule: Schedule, jitter: Duration, job: F) -> Result<()>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+    {
+        let record = self.load_records()?.remove(name).unwrap_or_default();
+        let job: JobFn = Arc::new(move || Box::pin(job()));
+        let next_run = Self::plan(&schedule, jitter, record.last_run, self.clock.now_secs());
+
+        self.jobs.insert(name.to_string(), ScheduledJob {
+            schedule,
+            jitter,
+            job,
+            enabled: true,
+            next_run,
+            record,
+        });
+        log::info!("⏰ 注册定时任务: {}", name);
+        Ok(())
+    }
+
+    /// 移除任务
+    pub fn remove(&self, name: &str) -> bool {
+        self.jobs.remove(name).is_some()
+    }
+
+    /// 暂停任务
+    pub fn pause(&self, name: &str) -> Result<()> {
+        let mut job = self.jobs.get_mut(name)
+            .ok_or_else(|| anyhow::anyhow!("任务不存在: {}", name))?;
+        job.enabled = false;
+        Ok(())
+    }
+
+    /// 恢复任务
+    pub fn resume(&self, name: &str) -> Result<()> {
+        let mut job = self.jobs.get_mut(name)
+            .ok_or_else(|| anyhow::anyhow!("任务不存在: {}", name))?;
+        job.enabled = true;
+        Ok(())
+    }
+
+    /// 立即运行任务（不影响计划）
+    pub async fn trigger(&self, name: &str) -> Result<()> {
+        let job = self.jobs.get(name)
+            .map(|j| j.job.clone())
+            .ok_or_else(|| anyhow::anyhow!("任务不存在: {}", name))?;
+        let result = job().await;
+        self.record_run(name, &result, false)?;
+        result
+    }
+
+    /// 全部任务信息（按名称排序）
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self.jobs.iter().map(|entry| JobInfo {
+            name: entry.key().clone(),
+            schedule: entry.schedule.describe(),
+            enabled: entry.enabled,
+            next_run: entry.next_run,
+            last_run: entry.record.last_run,
+            run_count: entry.record.run_count,
+            failure_count: entry.record.failure_count,
+            last_error: entry.record.last_error.clone(),
+        }).collect();
+        jobs.sort_by(|a, b| a.name.cmp(&b.name));
+        jobs
+    }
+
+    /// 运行所有到期任务，返回运行的任务名和结果
+    pub async fn run_due(&self) -> Vec<(String, Result<()>)> {
+        let now = self.clock.now_secs();
+        let due: Vec<(String, JobFn)> = self.jobs.iter()
+            .filter(|j| j.enabled && j.next_run.is_some_and(|t| t <= now))
+            .map(|j| (j.key().clone(), j.job.clone()))
+            .collect();
+
+        let mut results = Vec::with_capacity(due.len());
+        for (name, job) in due {
+            log::debug!("⏰ 运行定时任务: {}", name);
+            let result = job().await;
+            if let Err(e) = &result {
+                log::warn!("定时任务 {} 失败: {}", name, e);
+            }
+            if let Err(e) = self.record_run(&name, &result, true) {
+                log::warn!("保存任务运行记录失败: {}", e);
+            }
+            results.push((name, result));
+        }
+        results
+    }
+
+    /// 启动后台调度：每隔tick检查一次到期任务
+    pub fn start(&self, tick: Duration) -> tokio::task::JoinHandle<()> {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(tick);
+            loop {
+                interval_timer.tick().await;
+                scheduler.run_due().await;
+            }
+        })
+    }
+
+    fn record_run(&self, name: &str, result: &Result<()>, reschedule: bool) -> Result<()> {
+        let now = self.clock.now_secs();
+        if let Some(mut job) = self.jobs.get_mut(name) {
+            job.record.last_run = Some(now);
+            job.record.run_count += 1;
+            match result {
+                Ok(()) => job.record.last_error = None,
+                Err(e) => {
+                    job.record.failure_count += 1;
+                    job.record.last_error = Some(e.to_string());
+                }
+            }
+            if reschedule {
+                job.next_run = Self::plan(&job.schedule, job.jitter, Some(now), now);
+            }
+        }
+        self.save()
+    }
+
+    fn plan(schedule: &Schedule, jitter: Duration, last_run: Option<u64>, now: u64) -> Option<u64> {
+        let next = schedule.next_run(last_run, now)?;
+        let jitter_secs = jitter.as_secs();
+        if jitter_secs == 0 {
+            return Some(next);
+        }
+        Some(next + rand::thread_rng().gen_range(0..=jitter_secs))
+    }
+
+    fn load_records(&self) -> Result<BTreeMap<String, JobRecord>> {
+        let Some(path) = &self.path else { return Ok(BTreeMap::new()) };
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取任务记录: {:?}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("无法解析任务记录: {:?}", path))
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        // 保留已持久化但当前未注册的任务记录
+        let mut records = self.load_records()?;
+        for job in self.jobs.iter() {
+            records.insert(job.key().clone(), job.record.clone());
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建任务记录目录: {:?}", parent))?;
+        }
+        let content = serde_json::to_string_pretty(&records).context("序列化任务记录失败")?;
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, content)
+            .with_context(|| format!("无法写入任务记录: {:?}", temp_path))?;
+        std::fs::rename(&temp_path, path)
+            .with_context(|| format!("无法保存任务记录: {:?}", path))?;
+        Ok(())
+    }
+}
+
+impl Default for JobScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn ts(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> u64 {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap().timestamp() as u64
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let every_quarter = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(every_quarter.next_after(ts(2025, 1, 1, 10, 7)), Some(ts(2025, 1, 1, 10, 15)));
+        assert_eq!(every_quarter.next_after(ts(2025, 1, 1, 23, 50)), Some(ts(2025, 1, 2, 0, 0)));
+
+        // 工作日凌晨3点：2025-01-04是周六
+        let weekdays = CronSchedule::parse("0 3 * * 1-5").unwrap();
+        assert_eq!(weekdays.next_after(ts(2025, 1, 3, 4, 0)), Some(ts(2025, 1, 6, 3, 0)));
+
+        let sunday = CronSchedule::parse("30 12 * * 7").unwrap();
+        assert_eq!(sunday.next_after(ts(2025, 1, 1, 0, 0)), Some(ts(2025, 1, 5, 12, 30)));
+
+        assert!(CronSchedule::parse("* * *").is_err());
+        assert!(CronSchedule::parse("61 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 30 2 *").unwrap().next_after(0).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_due_and_persist() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("jobs.json");
+        let clock = MockClock::new(ts(2025, 1, 1, 0, 0));
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let scheduler = JobScheduler::new_with_clock(Arc::new(clock.clone())).with_state_file(&path);
+        let counter = runs.clone();
+        scheduler.register("heartbeat", Schedule::every(Duration::from_secs(60)), move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }).unwrap();
+        scheduler.register("republish", Schedule::cron("0 * * * *").unwrap(), || async {
+            anyhow::bail!("IPFS不可用")
+        }).unwrap();
+
+        assert_eq!(scheduler.run_due().await.len(), 1);
+        assert!(scheduler.run_due().await.is_empty());
+
+        clock.advance(Duration::from_secs(3600));
+        let results = scheduler.run_due().await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        let info = scheduler.list();
+        assert_eq!(info[1].name, "republish");
+        assert_eq!(info[1].failure_count, 1);
+        assert!(info[1].last_error.as_deref().unwrap().contains("IPFS"));
+
+        // 暂停的任务不运行
+        scheduler.pause("heartbeat").unwrap();
+        clock.advance(Duration::from_secs(120));
+        assert!(scheduler.run_due().await.is_empty());
+
+        // 重启后从记录的上次运行时间继续
+        let restarted = JobScheduler::new_with_clock(Arc::new(clock.clone())).with_state_file(&path);
+        restarted.register("heartbeat", Schedule::every(Duration::from_secs(600)), || async { Ok(()) }).unwrap();
+        let info = restarted.list();
+        assert_eq!(info[0].run_count, 2);
+        assert_eq!(info[0].next_run, Some(ts(2025, 1, 1, 1, 0) + 600));
+    }
+}
@@ -2,13 +2,16 @@
 // Decentralized Intelligent Agent Protocol
 // 负责密钥的生成、存储、加载和导出
 
-use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier};
+use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer as _, Verifier};
 use rand::rngs::OsRng;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::path::PathBuf;
 use anyhow::{Context, Result};
-use bs58;
 use base64::{Engine as _, engine::general_purpose};
+use libp2p::PeerId;
+use crate::did_builder::{DIDBuilder, DIDPublishResult};
+use crate::did_key;
+use std::sync::Arc;
 
 /// 密钥对信息
 #[derive(Debug, Clone)]
@@ -45,6 +48,177 @@ struct KeyFile {
     version: String,
 }
 
+/// 签名器抽象
+/// 签名可以委托给外部密钥库（PKCS#11、macOS Keychain、Windows CNG等），
+/// 调用方无需持有原始私钥字节
+pub trait Signer: Send + Sync {
+    /// 签名者的DID
+    fn did(&self) -> String;
+    
+    /// Ed25519公钥（32字节）
+    fn public_key(&self) -> [u8; 32];
+    
+    /// 对数据进行Ed25519签名，返回64字节签名
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>>;
+    
+    /// 可导出的软件密钥对（硬件密钥返回None）
+    /// ZKP证明生成需要私钥，外部密钥库无法提供
+    fn keypair(&self) -> Option<&KeyPair> {
+        None
+    }
+}
+
+/// 外部签名回调类型
+pub type SignFn = Arc<dyn Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync>;
+
+/// 基于回调的签名器
+/// 用于桥接外部密钥库：私钥留在密钥库中，签名通过回调完成
+#[derive(Clone)]
+pub struct CallbackSigner {
+    did: String,
+    public_key: [u8; 32],
+    sign_fn: SignFn,
+}
+
+impl CallbackSigner {
+    /// 创建回调签名器，DID从公钥派生（did:key格式）
+    pub fn new(public_key: [u8; 32], sign_fn: SignFn) -> Result<Self> {
+        let did = KeyPair::derive_did_key(&public_key)?;
+        Ok(Self {
+            did,
+            public_key,
+            sign_fn,
+        })
+    }
+}
+
+impl std::fmt::Debug for CallbackSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackSigner")
+            .field("did", &self.did)
+            .finish()
+    }
+}
+
+impl Signer for CallbackSigner {
+    fn did(&self) -> String {
+        self.did.clone()
+    }
+    
+    fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+    
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let signature = (self.sign_fn)(data)?;
+        if signature.len() != 64 {
+            anyhow::bail!("外部签名器返回的签名长度错误: {}", signature.len());
+        }
+        Ok(signature)
+    }
+}
+
+impl Signer for KeyPair {
+    fn did(&self) -> String {
+        self.did.clone()
+    }
+    
+    fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+    
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        KeyPair::sign(self, data)
+    }
+    
+    fn keypair(&self) -> Option<&KeyPair> {
+        Some(self)
+    }
+}
+
+/// 签名信封：由签名者did:key签名的可序列化结构
+/// 签名内容是签名字段清空后的JSON，签名字段保存base64编码的Ed25519签名
+pub trait SignedEnvelope: Serialize + DeserializeOwned + Clone {
+    /// 类型名称（用于错误信息）
+    const KIND: &'static str;
+    
+    /// 签名者DID
+    fn signer_did(&self) -> &str;
+    
+    /// 签名（base64）
+    fn signature(&self) -> &str;
+    
+    /// 签名字段
+    fn signature_mut(&mut self) -> &mut String;
+    
+    /// 待签名数据
+    fn signing_data(&self) -> Result<Vec<u8>> {
+        let mut unsigned = self.clone();
+        unsigned.signature_mut().clear();
+        serde_json::to_vec(&unsigned).with_context(|| format!("序列化{}失败", Self::KIND))
+    }
+    
+    /// 用signer签名并写入签名字段
+    fn signed_by(mut self, signer: &dyn Signer) -> Result<Self> {
+        let signature = signer.sign(&self.signing_data()?)?;
+        *self.signature_mut() = general_purpose::STANDARD.encode(signature);
+        Ok(self)
+    }
+    
+    /// 验证签名（公钥从签名者的did:key中解析）
+    fn verify(&self) -> Result<bool> {
+        let signature = general_purpose::STANDARD.decode(self.signature())
+            .with_context(|| format!("解码{}签名失败", Self::KIND))?;
+        KeyPair::verify_with_did_key(self.signer_did(), &self.signing_data()?, &signature)
+    }
+    
+    /// 验证签名，失败时返回错误
+    fn require_valid(&self) -> Result<()> {
+        if !SignedEnvelope::verify(self)? {
+            anyhow::bail!("{}签名无效: {}", Self::KIND, self.signer_did());
+        }
+        Ok(())
+    }
+    
+    /// 序列化为消息内容
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).with_context(|| format!("序列化{}失败", Self::KIND))
+    }
+    
+    /// 从消息内容解析（不验证签名）
+    fn from_bytes(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).with_context(|| format!("解析{}失败", Self::KIND))
+    }
+}
+
+/// 加密密钥文件格式（私钥使用Argon2 + AES-256-GCM加密存储）
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedKeyFile {
+    /// 密钥类型
+    key_type: String,
+    
+    /// 加密后的私钥（salt:nonce:ciphertext，均为base64）
+    encrypted_private_key: String,
+    
+    /// 公钥（hex编码）
+    public_key: String,
+    
+    /// DID（did:key格式）
+    did: String,
+    
+    /// 密钥派生函数
+    kdf: String,
+    
+    /// 加密算法
+    cipher: String,
+    
+    /// 创建时间
+    created_at: String,
+    
+    /// 版本
+    version: String,
+}
+
 /// 密钥导出格式
 #[derive(Debug, Serialize, Deserialize)]
 pub struct KeyBackup {
@@ -121,12 +295,6 @@ impl KeyPair {
     
     /// 保存密钥对到文件
     pub fn save_to_file(&self, path: &PathBuf) -> Result<()> {
-        // 确保目录存在
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("无法创建密钥目录: {:?}", parent))?;
-        }
-        
         let key_file = KeyFile {
             key_type: "Ed25519".to_string(),
             private_key: hex::encode(self.private_key),
@@ -138,18 +306,7 @@ impl KeyPair {
         
         let content = serde_json::to_string_pretty(&key_file)
             .context("无法序列化密钥")?;
-        
-        std::fs::write(path, content)
-            .with_context(|| format!("无法写入密钥文件: {:?}", path))?;
-        
-        // 设置文件权限为600（仅所有者可读写）
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = std::fs::metadata(path)?.permissions();
-            perms.set_mode(0o600);
-            std::fs::set_permissions(path, perms)?;
-        }
+        write_key_file(path, &content)?;
         
         log::info!("密钥已保存到: {:?}", path);
         Ok(())
@@ -224,20 +381,20 @@ impl KeyPair {
         Ok(verifying_key.verify(data, &sig).is_ok())
     }
     
+    /// 使用did:key中的公钥验证签名
+    pub fn verify_with_did_key(did: &str, data: &[u8], signature: &[u8]) -> Result<bool> {
+        did_key::verify_with_did_key(did, data, signature)
+    }
+    
     /// 从公钥派生 did:key 标识符
     /// 使用 W3C DID 规范的 did:key 方法
-    /// 格式: did:key:z<multibase-multicodec-pubkey>
     fn derive_did_key(public_key: &[u8; 32]) -> Result<String> {
-        // Ed25519 公钥的 multicodec 前缀是 0xed01
-        // 参考: https://github.com/multiformats/multicodec/blob/master/table.csv
-        let mut multicodec_pubkey = vec![0xed, 0x01];
-        multicodec_pubkey.extend_from_slice(public_key);
-        
-        // 使用 base58btc 编码（前缀 'z'）
-        let multibase_key = format!("z{}", bs58::encode(&multicodec_pubkey).into_string());
-        
-        // 构造 did:key DID
-        Ok(format!("did:key:{}", multibase_key))
+        Ok(did_key::did_key_from_public_key(public_key))
+    }
+    
+    /// 从 did:key 标识符解析Ed25519公钥（derive_did_key的逆过程）
+    pub fn public_key_from_did_key(did: &str) -> Result<[u8; 32]> {
+        did_key::public_key_from_did_key(did)
     }
     
     /// 加密数据（使用AES-256-GCM + Argon2）
@@ -327,9 +484,24 @@ impl KeyPair {
     }
 }
 
+/// 密钥轮换结果
+#[derive(Debug, Clone)]
+pub struct KeyRotationResult {
+    /// 轮换前的DID
+    pub previous_did: String,
+    
+    /// 新密钥对
+    pub new_keypair: KeyPair,
+    
+    /// 新DID文档的发布结果（包含轮换证明）
+    pub publish_result: DIDPublishResult,
+    
+    /// 旧密钥的归档路径
+    pub archived_key_path: PathBuf,
+}
+
 /// 密钥管理器
 pub struct KeyManager {
-    #[allow(dead_code)]
     config_dir: PathBuf,
 }
 
@@ -351,6 +523,156 @@ impl KeyManager {
             Ok(keypair)
         }
     }
+    
+    /// 使用口令加密保存密钥（Argon2 + AES-256-GCM）
+    pub fn save_encrypted(&self, keypair: &KeyPair, path: &PathBuf, passphrase: &str) -> Result<()> {
+        let key_file = EncryptedKeyFile {
+            key_type: "Ed25519".to_string(),
+            encrypted_private_key: KeyPair::encrypt_data(&hex::encode(keypair.private_key), passphrase)?,
+            public_key: hex::encode(keypair.public_key),
+            did: keypair.did.clone(),
+            kdf: "Argon2id".to_string(),
+            cipher: "AES-256-GCM".to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            version: "3.0".to_string(),
+        };
+        
+        let content = serde_json::to_string_pretty(&key_file)
+            .context("无法序列化加密密钥")?;
+        write_key_file(path, &content)?;
+        
+        log::info!("加密密钥已保存到: {:?}", path);
+        Ok(())
+    }
+    
+    /// 加载加密密钥
+    /// 如果文件仍是旧的明文格式，加载后自动迁移为加密格式
+    pub fn load_encrypted(&self, path: &PathBuf, passphrase: &str) -> Result<KeyPair> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取密钥文件: {:?}", path))?;
+        
+        if let Ok(key_file) = serde_json::from_str::<EncryptedKeyFile>(&content) {
+            let private_key_hex = KeyPair::decrypt_data(&key_file.encrypted_private_key, passphrase)
+                .context("无法解密私钥（口令可能错误）")?;
+            
+            let private_key_bytes = hex::decode(private_key_hex.trim())
+                .context("无法解码私钥")?;
+            if private_key_bytes.len() != 32 {
+                anyhow::bail!("私钥长度错误");
+            }
+            
+            let mut private_key = [0u8; 32];
+            private_key.copy_from_slice(&private_key_bytes);
+            let keypair = KeyPair::from_private_key(private_key)?;
+            
+            if keypair.did != key_file.did {
+                anyhow::bail!("解密后的密钥与文件中记录的DID不一致");
+            }
+            
+            return Ok(keypair);
+        }
+        
+        // 旧的明文格式：加载并迁移（先写临时文件再替换，迁移中断时明文文件仍完整）
+        let keypair = KeyPair::from_file(path)?;
+        log::warn!("⚠️ 检测到明文密钥文件，正在迁移为加密格式: {:?}", path);
+        self.save_encrypted(&keypair, path, passphrase)?;
+        
+        Ok(keypair)
+    }
+    
+    /// 加载或生成加密密钥
+    pub fn load_or_generate_encrypted(&self, key_path: &PathBuf, passphrase: &str) -> Result<KeyPair> {
+        if key_path.exists() {
+            log::info!("从文件加载加密密钥: {:?}", key_path);
+            self.load_encrypted(key_path, passphrase)
+        } else {
+            log::info!("生成新密钥（加密存储）");
+            let keypair = KeyPair::generate()?;
+            self.save_encrypted(&keypair, key_path, passphrase)?;
+            Ok(keypair)
+        }
+    }
+    
+    /// 轮换密钥
+    /// 生成新的Ed25519密钥对，发布由旧密钥签名的新DID文档，
+    /// 并将旧密钥归档到 config_dir/rotated/ 目录。
+    /// 提供口令时旧密钥和新密钥都以加密格式保存（密钥原先加密存储时必须提供，否则会降级为明文）。
+    /// 旧DID（did:key）是自证明的，其DID文档仍保留在IPFS上，可继续被解析
+    pub async fn rotate_keypair(
+        &self,
+        key_path: &PathBuf,
+        current: &KeyPair,
+        builder: &DIDBuilder,
+        libp2p_peer_id: &PeerId,
+        passphrase: Option<&str>,
+    ) -> Result<KeyRotationResult> {
+        log::info!("🔄 开始密钥轮换: {}", current.did);
+        
+        let new_keypair = KeyPair::generate()?;
+        
+        // 先发布新文档，发布失败时不改动本地密钥文件
+        let publish_result = builder
+            .create_and_publish_rotated(&new_keypair, current, libp2p_peer_id)
+            .await
+            .context("发布轮换后的DID文档失败")?;
+        
+        let archived_key_path = self.config_dir
+            .join("rotated")
+            .join(format!("{}.key", chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f")));
+        self.save_keypair(current, &archived_key_path, passphrase)?;
+        self.save_keypair(&new_keypair, key_path, passphrase)?;
+        
+        log::info!("✅ 密钥轮换完成");
+        log::info!("  旧DID: {}", current.did);
+        log::info!("  新DID: {}", new_keypair.did);
+        log::info!("  新CID: {}", publish_result.cid);
+        
+        Ok(KeyRotationResult {
+            previous_did: current.did.clone(),
+            new_keypair,
+            publish_result,
+            archived_key_path,
+        })
+    }
+    
+    /// 按是否提供口令选择加密或明文格式保存
+    fn save_keypair(&self, keypair: &KeyPair, path: &PathBuf, passphrase: Option<&str>) -> Result<()> {
+        match passphrase {
+            Some(passphrase) => self.save_encrypted(keypair, path, passphrase),
+            None => keypair.save_to_file(path),
+        }
+    }
+}
+
+/// 原子写入密钥文件：先写入权限为600的临时文件并同步，再替换目标文件
+fn write_key_file(path: &PathBuf, content: &str) -> Result<()> {
+    use std::io::Write;
+    
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("无法创建密钥目录: {:?}", parent))?;
+    }
+    
+    let temp_path = path.with_extension("key.tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // 设置文件权限为600（仅所有者可读写），创建时即生效
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    {
+        let mut file = options.open(&temp_path)
+            .with_context(|| format!("无法写入密钥文件: {:?}", temp_path))?;
+        file.write_all(content.as_bytes())
+            .with_context(|| format!("无法写入密钥文件: {:?}", temp_path))?;
+        file.sync_all()
+            .with_context(|| format!("无法同步密钥文件: {:?}", temp_path))?;
+    }
+    std::fs::rename(&temp_path, path)
+        .with_context(|| format!("无法替换密钥文件: {:?}", path))?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -392,4 +714,72 @@ mod tests {
         assert_eq!(keypair1.private_key, keypair2.private_key);
         assert_eq!(keypair1.did, keypair2.did);
     }
+    
+    #[test]
+    fn test_callback_signer() {
+        let keypair = KeyPair::generate().unwrap();
+        let inner = keypair.clone();
+        let signer = CallbackSigner::new(
+            keypair.public_key,
+            Arc::new(move |data: &[u8]| inner.sign(data)),
+        ).unwrap();
+        
+        assert_eq!(Signer::did(&signer), keypair.did);
+        assert!(Signer::keypair(&signer).is_none());
+        
+        let signature = Signer::sign(&signer, b"external").unwrap();
+        assert!(keypair.verify(b"external", &signature).unwrap());
+    }
+    
+    #[test]
+    fn test_save_and_load_encrypted() {
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("encrypted.key");
+        let manager = KeyManager::new(temp_dir.path().to_path_buf());
+        
+        let keypair1 = KeyPair::generate().unwrap();
+        manager.save_encrypted(&keypair1, &key_path, "correct horse").unwrap();
+        
+        // 文件中不应包含明文私钥
+        let content = std::fs::read_to_string(&key_path).unwrap();
+        assert!(!content.contains(&hex::encode(keypair1.private_key)));
+        
+        let keypair2 = manager.load_encrypted(&key_path, "correct horse").unwrap();
+        assert_eq!(keypair1.private_key, keypair2.private_key);
+        
+        assert!(manager.load_encrypted(&key_path, "wrong passphrase").is_err());
+    }
+    
+    #[test]
+    fn test_migrate_plaintext_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("legacy.key");
+        let manager = KeyManager::new(temp_dir.path().to_path_buf());
+        
+        let keypair = KeyPair::generate().unwrap();
+        keypair.save_to_file(&key_path).unwrap();
+        
+        let migrated = manager.load_encrypted(&key_path, "passphrase").unwrap();
+        assert_eq!(migrated.did, keypair.did);
+        
+        // 迁移后旧格式加载应失败，新格式加载应成功
+        assert!(KeyPair::from_file(&key_path).is_err());
+        let reloaded = manager.load_encrypted(&key_path, "passphrase").unwrap();
+        assert_eq!(reloaded.private_key, keypair.private_key);
+        assert!(!key_path.with_extension("key.tmp").exists(), "迁移通过临时文件替换完成");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&key_path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+    
+    #[test]
+    fn test_public_key_from_did_key() {
+        let keypair = KeyPair::generate().unwrap();
+        let public_key = KeyPair::public_key_from_did_key(&keypair.did).unwrap();
+        assert_eq!(public_key, keypair.public_key);
+        
+        assert!(KeyPair::public_key_from_did_key("did:web:example.com").is_err());
+    }
 }
@@ -1,5 +1,6 @@
 // DIAP Rust SDK - Kubo自动安装器
-// 自动下载并安装Kubo (go-ipfs)二进制文件，实现零配置部署
+// 自动下载并安装Kubo (go-ipfs)二进制文件，实现零配置部署；
+// 归档的SHA-512须与发布的.sha512文件（或调用方固定的哈希）一致，否则不解压执行
 
 use anyhow::{Context, Result};
 use flate2::read::GzDecoder;
@@ -9,12 +10,17 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use tar::Archive;
 use log;
+use sha2::{Digest, Sha512};
+
+/// 自动下载的Kubo固定版本
+pub const DEFAULT_KUBO_VERSION: &str = "v0.32.1";
 
 /// Kubo安装器
 /// 负责自动下载和安装Kubo二进制文件
 pub struct KuboInstaller {
     install_dir: PathBuf,
     version: String,
+    expected_sha512: Option<String>,
 }
 
 impl KuboInstaller {
@@ -28,10 +34,29 @@ impl KuboInstaller {
         
         Self {
             install_dir,
-            version: "v0.32.1".to_string(), // Kubo最新稳定版本
+            version: DEFAULT_KUBO_VERSION.to_string(),
+            expected_sha512: None,
         }
     }
     
+    /// 指定Kubo版本（例如 "v0.32.1"）
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+    
+    /// 固定归档的SHA-512（十六进制），不再信任下载站点发布的.sha512文件
+    pub fn with_sha512(mut self, sha512: &str) -> Self {
+        self.expected_sha512 = Some(sha512.trim().to_ascii_lowercase());
+        self
+    }
+    
+    /// 指定安装目录
+    pub fn with_install_dir(mut self, install_dir: impl Into<PathBuf>) -> Self {
+        self.install_dir = install_dir.into();
+        self
+    }
+    
     /// 确保Kubo已安装，如果未安装则自动下载安装
     pub async fn ensure_kubo_installed(&self) -> Result<PathBuf> {
         let ipfs_path = self.get_kubo_path();
@@ -45,7 +70,7 @@ impl KuboInstaller {
                 log::info!("✓ Kubo验证成功");
                 return Ok(ipfs_path);
             } else {
-                log::warn!("Kubo文件损坏，重新下载...");
+                log::warn!("Kubo文件损坏或版本不是{}，重新下载...", self.version);
             }
         }
         
@@ -81,11 +106,28 @@ impl KuboInstaller {
         let download_url = self.build_download_url()?;
         log::info!("  下载URL: {}", download_url);
         
-        // 下载文件
-        let temp_file = self.download_file(&download_url).await?;
+        // 下载文件并校验SHA-512
+        let (temp_file, actual_sha512) = self.download_file(&download_url).await?;
+        let expected_sha512 = match &self.expected_sha512 {
+            Some(sha512) => Ok(sha512.clone()),
+            None => self.fetch_published_sha512(&download_url).await,
+        };
+        let verified = expected_sha512.and_then(|expected| {
+            if expected != actual_sha512 {
+                anyhow::bail!("Kubo归档SHA-512不匹配: 期望 {}，实际 {}", expected, actual_sha512);
+            }
+            Ok(())
+        });
+        if let Err(e) = verified {
+            let _ = fs::remove_file(&temp_file);
+            return Err(e.context("Kubo归档校验失败，已放弃安装"));
+        }
+        log::info!("✓ SHA-512校验通过");
         
         // 解压文件
-        self.extract_kubo(&temp_file)?;
+        let extracted = self.extract_kubo(&temp_file);
+        let _ = fs::remove_file(&temp_file);
+        extracted?;
         
         // 设置可执行权限（Unix系统）
         #[cfg(not(target_os = "windows"))]
@@ -122,8 +164,19 @@ impl KuboInstaller {
         Ok((os.to_string(), arch.to_string()))
     }
     
-    /// 下载文件到临时目录
-    async fn download_file(&self, url: &str) -> Result<PathBuf> {
+    /// 下载站点发布的SHA-512（与归档同名加.sha512后缀）
+    async fn fetch_published_sha512(&self, url: &str) -> Result<String> {
+        let checksum_url = format!("{}.sha512", url);
+        let response = reqwest::get(&checksum_url).await
+            .with_context(|| format!("下载校验文件失败: {}", checksum_url))?;
+        if !response.status().is_success() {
+            anyhow::bail!("下载校验文件失败: HTTP {}", response.status());
+        }
+        parse_sha512_file(&response.text().await.context("读取校验文件失败")?)
+    }
+    
+    /// 下载文件到临时目录，返回文件路径和SHA-512（十六进制）
+    async fn download_file(&self, url: &str) -> Result<(PathBuf, String)> {
         use tokio::io::AsyncWriteExt;
         
         let client = reqwest::Client::new();
@@ -149,10 +202,12 @@ impl KuboInstaller {
         
         // 下载并写入文件
         let mut stream = response.bytes_stream();
+        let mut hasher = Sha512::new();
         use futures::StreamExt;
         
         while let Some(item) = stream.next().await {
             let chunk = item.context("下载流错误")?;
+            hasher.update(&chunk);
             file.write_all(&chunk)
                 .await
                 .context("写入文件失败")?;
@@ -167,8 +222,9 @@ impl KuboInstaller {
             }
         }
         
+        file.flush().await.context("写入文件失败")?;
         log::info!("✓ 下载完成: {} bytes", downloaded);
-        Ok(temp_file)
+        Ok((temp_file, hex::encode(hasher.finalize())))
     }
     
     /// 解压Kubo归档文件
@@ -253,8 +309,10 @@ impl KuboInstaller {
             .arg("--version")
             .output();
         
+        // 输出形如 "ipfs version 0.32.1"，版本不符时重新下载固定版本
         match output {
-            Ok(result) => Ok(result.status.success()),
+            Ok(result) => Ok(result.status.success()
+                && String::from_utf8_lossy(&result.stdout).contains(self.version.trim_start_matches('v'))),
             Err(_) => Ok(false),
         }
     }
@@ -270,6 +328,15 @@ impl KuboInstaller {
     }
 }
 
+/// 解析.sha512文件（`<hex>  <文件名>`），返回小写十六进制哈希
+fn parse_sha512_file(content: &str) -> Result<String> {
+    let hash = content.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+    if hash.len() != 128 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("无效的SHA-512校验文件");
+    }
+    Ok(hash)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -298,6 +365,15 @@ mod tests {
         assert!(!arch.is_empty());
     }
     
+    #[test]
+    fn test_parse_sha512_file() {
+        let hash = hex::encode(Sha512::digest(b"kubo"));
+        let content = format!("{}  kubo_v0.32.1_linux-amd64.tar.gz\n", hash.to_ascii_uppercase());
+        assert_eq!(parse_sha512_file(&content).unwrap(), hash);
+        assert!(parse_sha512_file("").is_err());
+        assert!(parse_sha512_file("<html>not found</html>").is_err());
+    }
+    
     #[test]
     fn test_download_url() {
         let installer = KuboInstaller::new();
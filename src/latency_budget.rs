@@ -0,0 +1,239 @@
+// DIAP Rust SDK - 延迟预算
+// 交互式认证可以为 解析→获取→验证 整条路径指定总预算（例如800ms）；
+// 每个子步骤只使用剩余预算，超时后返回"临时"结果，并记录超支指标
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 步骤超出剩余预算
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("超出延迟预算: {step}（总预算{budget_ms}ms，已用{elapsed_ms}ms）")]
+pub struct BudgetExceeded {
+    /// 超时的步骤
+    pub step: String,
+
+    /// 总预算（毫秒）
+    pub budget_ms: u64,
+
+    /// 超时时已用时间（毫秒）
+    pub elapsed_ms: u64,
+}
+
+/// 延迟预算指标快照
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetMetricsSnapshot {
+    /// 在预算内完成的步骤数
+    pub steps_completed: u64,
+
+    /// 超出预算的次数
+    pub overruns: u64,
+
+    /// 按步骤统计的超支次数
+    pub overruns_by_step: HashMap<String, u64>,
+}
+
+/// 延迟预算指标（可在多个预算之间共享）
+#[derive(Debug, Clone, Default)]
+pub struct BudgetMetrics {
+    steps_completed: Arc<AtomicU64>,
+    overruns: Arc<AtomicU64>,
+    overruns_by_step: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl BudgetMetrics {
+    /// 创建指标
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前指标
+    pub fn snapshot(&self) -> BudgetMetricsSnapshot {
+        BudgetMetricsSnapshot {
+            steps_completed: self.steps_completed.load(Ordering::Relaxed),
+            overruns: self.overruns.load(Ordering::Relaxed),
+            overruns_by_step: self.overruns_by_step.lock().unwrap().clone(),
+        }
+    }
+
+    fn record_completed(&self) {
+        self.steps_completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_overrun(&self, step: &str) {
+        self.overruns.fetch_add(1, Ordering::Relaxed);
+        *self.overruns_by_step.lock().unwrap().entry(step.to_string()).or_insert(0) += 1;
+    }
+}
+
+/// 一次验证的延迟预算（从创建时开始计时）
+#[derive(Debug, Clone)]
+pub struct LatencyBudget {
+    total: Duration,
+    started: Instant,
+    metrics: Option<BudgetMetrics>,
+}
+
+impl LatencyBudget {
+    /// 创建总时长为total的预算
+    pub fn new(total: Duration) -> Self {
+        Self {
+            total,
+            started: Instant::now(),
+            metrics: None,
+        }
+    }
+
+    /// 创建预算（毫秒）
+    pub fn from_millis(total_ms: u64) -> Self {
+        Self::new(Duration::from_millis(total_ms))
+    }
+
+    /// 把完成和超支情况记录到指标
+    pub fn with_metrics(mut self, metrics: BudgetMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// 总预算
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// 已用时间
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// 剩余预算
+    pub fn remaining(&self) -> Duration {
+        self.total.saturating_sub(self.elapsed())
+    }
+
+    /// 预算是否已用完
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// 在剩余预算内执行一个步骤；超时时取消该步骤并返回BudgetExceeded
+    pub async fn run<F, T>(&self, step: &str, future: F) -> Result<T, BudgetExceeded>
+    where
+        F: Future<Output = T>,
+    {
+        let remaining = self.remaining();
+        let result = if remaining.is_zero() {
+            None
+        } else {
+            tokio::time::timeout(remaining, future).await.ok()
+        };
+
+        match result {
+            Some(value) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_completed();
+                }
+                Ok(value)
+            }
+            None => {
+                log::warn!("⏱️ 超出延迟预算: {} ({:?} / {:?})", step, self.elapsed(), self.total);
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_overrun(step);
+                }
+                Err(BudgetExceeded {
+                    step: step.to_string(),
+                    budget_ms: self.total.as_millis() as u64,
+                    elapsed_ms: self.elapsed().as_millis() as u64,
+                })
+            }
+        }
+    }
+}
+
+/// 可选预算下执行步骤（没有预算时不限时）
+pub(crate) async fn run_within<F, T>(budget: Option<&LatencyBudget>, step: &str, future: F) -> Result<T, BudgetExceeded>
+where
+    F: Future<Output = T>,
+{
+    match budget {
+        Some(budget) => budget.run(step, future).await,
+        None => Ok(future.await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_steps_share_remaining_budget() {
+        let metrics = BudgetMetrics::new();
+        let budget = LatencyBudget::from_millis(200).with_metrics(metrics.clone());
+
+        assert_eq!(budget.run("fast", async { 1 }).await.unwrap(), 1);
+        let err = budget.run("slow", tokio::time::sleep(Duration::from_secs(5))).await.unwrap_err();
+        assert_eq!(err.step, "slow");
+        assert!(err.elapsed_ms >= 200 && err.elapsed_ms < 5000);
+
+        // 预算用完后后续步骤立即超支
+        assert!(budget.is_exhausted());
+        assert!(budget.run("after", async { 2 }).await.is_err());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.steps_completed, 1);
+        assert_eq!(snapshot.overruns, 2);
+        assert_eq!(snapshot.overruns_by_step.get("slow"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_message_verification_returns_provisional() {
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::{CallbackSigner, KeyPair};
+        use crate::pubsub_authenticator::{PubSubMessageType, PubsubAuthenticator};
+
+        // 只接受连接、从不响应的网关
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let gateway = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+        let client = IpfsClient::new_public_only(30);
+        for existing in client.public_gateways() {
+            client.remove_gateway(&existing);
+        }
+        client.add_gateway(&gateway);
+        let manager = IdentityManager::new(client);
+
+        let metrics = BudgetMetrics::new();
+        let budget = LatencyBudget::from_millis(300).with_metrics(metrics.clone());
+        let identity = manager.verify_identity_with_budget("QmStalled", b"proof", b"nonce", &budget).await.unwrap();
+        assert!(identity.provisional);
+        assert!(!identity.zkp_verified);
+
+        let keypair = KeyPair::generate().unwrap();
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+        let sender = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None);
+        sender.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmStalled".to_string()).await.unwrap();
+        let message = sender.create_authenticated_message("tasks", PubSubMessageType::Heartbeat, b"ping", None).await.unwrap();
+
+        let receiver = PubsubAuthenticator::new(manager, None, None);
+        let started = Instant::now();
+        let budget = LatencyBudget::from_millis(300).with_metrics(metrics.clone());
+        let verification = receiver.verify_message_with_budget(&message, &budget).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(verification.provisional);
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d.contains("✓ Nonce")));
+        assert!(verification.details.iter().any(|d| d.contains("获取DID文档")));
+
+        // 临时结果不计为验证失败
+        assert_eq!(receiver.verification_failure_count(), 0);
+        assert_eq!(metrics.snapshot().overruns_by_step.get("获取DID文档"), Some(&2));
+    }
+}
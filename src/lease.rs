@@ -0,0 +1,484 @@
+// DIAP Rust SDK - 分布式租约模块
+// 智能体在协调主题上发布签名的租约声明来争用命名租约，每次易主时fencing token递增，
+// 持有者需在到期前续约；同一token的并发声明按(声明时间, DID)确定唯一胜者，
+// 保证一个集群中只有一个智能体执行单例任务。声明时间由声明者填写，只有落在本地时间窗口内才接受，
+// 且只能在现任持有者获取后的争用窗口内抢占，回填时间戳无法事后夺走租约
+
+use anyhow::Result;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::{SharedClock, system_clock};
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::SignedMessage;
+use crate::timestamp_window::TimestampWindow;
+
+/// 租约消息类型标识（PubSubMessageType::Custom）
+pub const LEASE_MESSAGE_TYPE: &str = "lease";
+
+/// 默认租期
+pub const DEFAULT_LEASE_TTL: Duration = Duration::from_secs(30);
+
+/// 默认争用窗口：声明后需等待该时长，确认没有更优先的并发声明后再开始工作
+pub const DEFAULT_CONTENTION_WINDOW: Duration = Duration::from_secs(2);
+
+/// 租约操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeaseAction {
+    /// 获取（fencing token为上一任token+1）
+    Claim,
+
+    /// 续约（token不变，延长到期时间）
+    Renew,
+
+    /// 主动释放
+    Release,
+}
+
+/// 签名的租约声明
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaseClaim {
+    /// 租约名称
+    pub lease: String,
+
+    /// 声明者DID
+    pub holder_did: String,
+
+    /// 操作
+    pub action: LeaseAction,
+
+    /// fencing token
+    pub fencing_token: u64,
+
+    /// 声明时间（毫秒）
+    pub issued_at: u64,
+
+    /// 到期时间（毫秒）
+    pub expires_at: u64,
+
+    /// 声明者签名（base64）
+    pub signature: String,
+}
+
+impl LeaseClaim {
+    fn new(
+        signer: &dyn Signer,
+        lease: &str,
+        action: LeaseAction,
+        fencing_token: u64,
+        issued_at: u64,
+        expires_at: u64,
+    ) -> Result<Self> {
+        Self {
+            lease: lease.to_string(),
+            holder_did: signer.did(),
+            action,
+            fencing_token,
+            issued_at,
+            expires_at,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// 同一token的并发声明中是否优先于other（声明时间早者优先，相同时DID小者优先）
+    fn precedes(&self, other: &LeaseClaim) -> bool {
+        (self.issued_at, &self.holder_did) < (other.issued_at, &other.holder_did)
+    }
+}
+
+impl SignedEnvelope for LeaseClaim {
+    const KIND: &'static str = "租约声明";
+
+    fn signer_did(&self) -> &str {
+        &self.holder_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+impl SignedMessage for LeaseClaim {
+    const MESSAGE_TYPE: &'static str = LEASE_MESSAGE_TYPE;
+}
+
+/// 租约当前状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    /// 租约名称
+    pub name: String,
+
+    /// 持有者DID
+    pub holder_did: String,
+
+    /// fencing token
+    pub fencing_token: u64,
+
+    /// 本地观察到获取的时间（毫秒）
+    pub acquired_at: u64,
+
+    /// 到期时间（毫秒）
+    pub expires_at: u64,
+
+    /// 是否已释放
+    pub released: bool,
+}
+
+impl Lease {
+    /// 在now时刻是否仍被持有
+    pub fn is_active_at(&self, now_millis: u64) -> bool {
+        !self.released && now_millis < self.expires_at
+    }
+}
+
+/// 处理声明的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeaseOutcome {
+    /// 声明者获得租约
+    Acquired(Lease),
+
+    /// 并发声明中胜出，取代了先到的同token声明
+    Preempted { lease: Lease, previous_holder: String },
+
+    /// 续约成功
+    Renewed(Lease),
+
+    /// 已释放
+    Released(Lease),
+
+    /// 声明被拒绝（租约被他人持有、token过期等）
+    Rejected(String),
+
+    /// 已处理过的声明
+    Duplicate,
+}
+
+/// 租约表：跟踪协调主题上的声明，所有成员按相同规则得出相同持有者
+#[derive(Clone)]
+pub struct LeaseTable {
+    leases: Arc<DashMap<String, (Lease, LeaseClaim)>>,
+    contention_window: Duration,
+    max_ttl: Duration,
+    timestamp_window: TimestampWindow,
+    clock: SharedClock,
+}
+
+impl LeaseTable {
+    /// 创建租约表（系统时钟）
+    pub fn new() -> Self {
+        Self::new_with_clock(system_clock())
+    }
+
+    /// 使用指定时间源创建租约表
+    pub fn new_with_clock(clock: SharedClock) -> Self {
+        Self {
+            leases: Arc::new(DashMap::new()),
+            contention_window: DEFAULT_CONTENTION_WINDOW,
+            max_ttl: Duration::from_secs(3600),
+            timestamp_window: TimestampWindow::default(),
+            clock,
+        }
+    }
+
+    /// 设置争用窗口
+    pub fn with_contention_window(mut self, window: Duration) -> Self {
+        self.contention_window = window;
+        self
+    }
+
+    /// 设置声明时间相对本地时钟的允许范围
+    pub fn with_timestamp_window(mut self, window: TimestampWindow) -> Self {
+        self.timestamp_window = window;
+        self
+    }
+
+    /// 设置允许的最长租期
+    pub fn with_max_ttl(mut self, max_ttl: Duration) -> Self {
+        self.max_ttl = max_ttl;
+        self
+    }
+
+    /// 生成获取租约的声明并在本地记录；租约被他人持有时返回错误
+    pub fn claim(&self, signer: &dyn Signer, name: &str, ttl: Duration) -> Result<LeaseClaim> {
+        let now = self.clock.now_millis();
+        let token = match self.current(name) {
+            Some(lease) if lease.is_active_at(now) && lease.holder_did != signer.did() => {
+                anyhow::bail!("租约 {} 由 {} 持有，至 {}", name, lease.holder_did, lease.expires_at);
+            }
+            Some(lease) if lease.is_active_at(now) => {
+                anyhow::bail!("已持有租约 {}，请使用续约", name);
+            }
+            Some(lease) => lease.fencing_token + 1,
+            None => 1,
+        };
+
+        let claim = LeaseClaim::new(signer, name, LeaseAction::Claim, token, now, now + self.clamp_ttl(ttl))?;
+        self.observe(&claim)?;
+        Ok(claim)
+    }
+
+    /// 生成续约声明并在本地记录
+    pub fn renew(&self, signer: &dyn Signer, name: &str, ttl: Duration) -> Result<LeaseClaim> {
+        let lease = self.held_lease(signer, name)?;
+        let now = self.clock.now_millis();
+        let claim = LeaseClaim::new(
+            signer, name, LeaseAction::Renew, lease.fencing_token, now, now + self.clamp_ttl(ttl),
+        )?;
+        self.observe(&claim)?;
+        Ok(claim)
+    }
+
+    /// 生成释放声明并在本地记录
+    pub fn release(&self, signer: &dyn Signer, name: &str) -> Result<LeaseClaim> {
+        let lease = self.held_lease(signer, name)?;
+        let now = self.clock.now_millis();
+        let claim = LeaseClaim::new(signer, name, LeaseAction::Release, lease.fencing_token, now, now)?;
+        self.observe(&claim)?;
+        Ok(claim)
+    }
+
+    /// 处理来自协调主题（或本地）的声明
+    pub fn observe(&self, claim: &LeaseClaim) -> Result<LeaseOutcome> {
+        if !claim.verify()? {
+            anyhow::bail!("租约声明签名无效: {}", claim.holder_did);
+        }
+        if claim.expires_at < claim.issued_at
+            || claim.expires_at - claim.issued_at > self.max_ttl.as_millis() as u64
+        {
+            return Ok(LeaseOutcome::Rejected(format!("租期无效: {}", claim.lease)));
+        }
+
+        let now = self.clock.now_millis();
+        if let Err(violation) = self.timestamp_window.check(claim.issued_at / 1000, now / 1000) {
+            return Ok(LeaseOutcome::Rejected(format!("租约 {} 的声明时间无效: {}", claim.lease, violation)));
+        }
+        let current = self.leases.get(&claim.lease).map(|entry| entry.clone());
+
+        let outcome = match (claim.action, current) {
+            (_, Some((_, last))) if &last == claim => LeaseOutcome::Duplicate,
+
+            (LeaseAction::Claim, None) => {
+                if claim.fencing_token != 1 {
+                    // 错过了之前的声明，以对端的token为准
+                    log::debug!("租约 {} 首次观察到token {}", claim.lease, claim.fencing_token);
+                }
+                self.install(claim, now)
+            }
+            (LeaseAction::Claim, Some((lease, last))) => {
+                if claim.fencing_token == lease.fencing_token {
+                    // 只在现任持有者（按本地接收时间）的争用窗口内允许抢占
+                    let contending = now < lease.acquired_at + self.contention_window.as_millis() as u64;
+                    if lease.holder_did != claim.holder_did && contending && claim.precedes(&last) {
+                        let previous_holder = lease.holder_did.clone();
+                        match self.install(claim, now) {
+                            LeaseOutcome::Acquired(lease) => LeaseOutcome::Preempted { lease, previous_holder },
+                            other => other,
+                        }
+                    } else {
+                        LeaseOutcome::Rejected(format!("租约 {} 的token {} 已被 {} 获取",
+                                                       claim.lease, claim.fencing_token, lease.holder_did))
+                    }
+                } else if claim.fencing_token < lease.fencing_token {
+                    LeaseOutcome::Rejected(format!("过期的fencing token: {} < {}",
+                                                   claim.fencing_token, lease.fencing_token))
+                } else if lease.is_active_at(now) {
+                    LeaseOutcome::Rejected(format!("租约 {} 仍由 {} 持有", claim.lease, lease.holder_did))
+                } else {
+                    self.install(claim, now)
+                }
+            }
+
+            (LeaseAction::Renew | LeaseAction::Release, None) => {
+                LeaseOutcome::Rejected(format!("未知租约: {}", claim.lease))
+            }
+            (action, Some((mut lease, _))) => {
+                if lease.holder_did != claim.holder_did || lease.fencing_token != claim.fencing_token {
+                    LeaseOutcome::Rejected(format!("{} 未持有租约 {} (token {})",
+                                                   claim.holder_did, claim.lease, claim.fencing_token))
+                } else if !lease.is_active_at(claim.issued_at) {
+                    LeaseOutcome::Rejected(format!("租约 {} 已到期或释放", claim.lease))
+                } else if action == LeaseAction::Renew {
+                    lease.expires_at = lease.expires_at.max(claim.expires_at);
+                    self.leases.insert(claim.lease.clone(), (lease.clone(), claim.clone()));
+                    LeaseOutcome::Renewed(lease)
+                } else {
+                    lease.released = true;
+                    lease.expires_at = lease.expires_at.min(claim.issued_at);
+                    self.leases.insert(claim.lease.clone(), (lease.clone(), claim.clone()));
+                    log::info!("🔓 租约已释放: {} (token {})", lease.name, lease.fencing_token);
+                    LeaseOutcome::Released(lease)
+                }
+            }
+        };
+
+        if let LeaseOutcome::Rejected(reason) = &outcome {
+            log::debug!("拒绝租约声明: {}", reason);
+        }
+        Ok(outcome)
+    }
+
+    /// 租约当前状态（可能已到期）
+    pub fn current(&self, name: &str) -> Option<Lease> {
+        self.leases.get(name).map(|entry| entry.0.clone())
+    }
+
+    /// 当前有效的持有者
+    pub fn holder(&self, name: &str) -> Option<String> {
+        let now = self.clock.now_millis();
+        self.current(name)
+            .filter(|lease| lease.is_active_at(now))
+            .map(|lease| lease.holder_did)
+    }
+
+    /// did是否持有租约且已过争用窗口，可以安全开始单例任务
+    pub fn is_confirmed_holder(&self, name: &str, did: &str) -> bool {
+        let now = self.clock.now_millis();
+        self.current(name).is_some_and(|lease| {
+            lease.holder_did == did
+                && lease.is_active_at(now)
+                && now >= lease.acquired_at + self.contention_window.as_millis() as u64
+        })
+    }
+
+    /// 验证fencing token仍为租约的当前token（受保护资源在执行写入前调用）
+    pub fn verify_fencing_token(&self, name: &str, token: u64) -> bool {
+        let now = self.clock.now_millis();
+        self.current(name)
+            .is_some_and(|lease| lease.fencing_token == token && lease.is_active_at(now))
+    }
+
+    fn held_lease(&self, signer: &dyn Signer, name: &str) -> Result<Lease> {
+        let now = self.clock.now_millis();
+        match self.current(name) {
+            Some(lease) if lease.holder_did == signer.did() && lease.is_active_at(now) => Ok(lease),
+            _ => anyhow::bail!("未持有租约: {}", name),
+        }
+    }
+
+    fn install(&self, claim: &LeaseClaim, now: u64) -> LeaseOutcome {
+        let lease = Lease {
+            name: claim.lease.clone(),
+            holder_did: claim.holder_did.clone(),
+            fencing_token: claim.fencing_token,
+            acquired_at: now,
+            expires_at: claim.expires_at,
+            released: false,
+        };
+        self.leases.insert(claim.lease.clone(), (lease.clone(), claim.clone()));
+        log::info!("🔒 租约 {} 由 {} 获取 (token {})", lease.name, lease.holder_did, lease.fencing_token);
+        LeaseOutcome::Acquired(lease)
+    }
+
+    fn clamp_ttl(&self, ttl: Duration) -> u64 {
+        ttl.min(self.max_ttl).as_millis() as u64
+    }
+}
+
+impl Default for LeaseTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::clock::{Clock, MockClock};
+
+    #[test]
+    fn test_concurrent_claims_converge() {
+        let clock = MockClock::new(1_000);
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let alice_table = LeaseTable::new_with_clock(Arc::new(clock.clone()));
+        let bob_table = LeaseTable::new_with_clock(Arc::new(clock.clone()));
+
+        let alice_claim = alice_table.claim(&alice, "compactor", DEFAULT_LEASE_TTL).unwrap();
+        clock.advance(Duration::from_millis(10));
+        let bob_claim = bob_table.claim(&bob, "compactor", DEFAULT_LEASE_TTL).unwrap();
+        assert_eq!(alice_claim.fencing_token, bob_claim.fencing_token);
+
+        // 两边以不同顺序收到对方的声明后得出相同持有者
+        assert!(matches!(alice_table.observe(&bob_claim).unwrap(), LeaseOutcome::Rejected(_)));
+        assert!(matches!(bob_table.observe(&alice_claim).unwrap(), LeaseOutcome::Preempted { .. }));
+        assert_eq!(alice_table.holder("compactor"), Some(alice.did.clone()));
+        assert_eq!(bob_table.holder("compactor"), Some(alice.did.clone()));
+
+        assert!(!alice_table.is_confirmed_holder("compactor", &alice.did));
+        clock.advance(DEFAULT_CONTENTION_WINDOW);
+        assert!(alice_table.is_confirmed_holder("compactor", &alice.did));
+        assert!(!bob_table.is_confirmed_holder("compactor", &bob.did));
+    }
+
+    #[test]
+    fn test_renew_expire_and_fencing() {
+        let clock = MockClock::new(1_000);
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let table = LeaseTable::new_with_clock(Arc::new(clock.clone()));
+
+        let first = table.claim(&alice, "job", Duration::from_secs(10)).unwrap();
+        assert!(table.claim(&bob, "job", Duration::from_secs(10)).is_err());
+
+        clock.advance(Duration::from_secs(8));
+        let renewal = table.renew(&alice, "job", Duration::from_secs(10)).unwrap();
+        assert_eq!(renewal.fencing_token, first.fencing_token);
+        clock.advance(Duration::from_secs(8));
+        assert_eq!(table.holder("job"), Some(alice.did.clone()));
+
+        // 到期后他人获取，旧token失效
+        clock.advance(Duration::from_secs(3));
+        let second = table.claim(&bob, "job", Duration::from_secs(10)).unwrap();
+        assert_eq!(second.fencing_token, first.fencing_token + 1);
+        assert!(!table.verify_fencing_token("job", first.fencing_token));
+        assert!(table.verify_fencing_token("job", second.fencing_token));
+
+        // 旧持有者不能续约或释放
+        let stale = LeaseClaim::new(&alice, "job", LeaseAction::Release, first.fencing_token,
+                                    clock.now_millis(), clock.now_millis()).unwrap();
+        assert!(matches!(table.observe(&stale).unwrap(), LeaseOutcome::Rejected(_)));
+
+        table.release(&bob, "job").unwrap();
+        assert_eq!(table.holder("job"), None);
+        assert_eq!(table.claim(&alice, "job", Duration::from_secs(10)).unwrap().fencing_token, 3);
+    }
+
+    #[test]
+    fn test_backdated_claim_cannot_preempt() {
+        let clock = MockClock::new(1_000_000);
+        let alice = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+        let table = LeaseTable::new_with_clock(Arc::new(clock.clone()));
+        let first = table.claim(&alice, "job", DEFAULT_LEASE_TTL).unwrap();
+
+        // 争用窗口之后，声明时间早于现任持有者的同token声明也不能抢占
+        clock.advance(DEFAULT_CONTENTION_WINDOW);
+        let backdated = LeaseClaim::new(&mallory, "job", LeaseAction::Claim, first.fencing_token,
+                                        first.issued_at - 1, first.issued_at + 10_000).unwrap();
+        assert!(matches!(table.observe(&backdated).unwrap(), LeaseOutcome::Rejected(_)));
+        assert_eq!(table.holder("job"), Some(alice.did.clone()));
+
+        // 声明时间超出本地时间窗口的声明直接拒绝
+        let now = clock.now_millis();
+        let stale = LeaseClaim::new(&mallory, "other", LeaseAction::Claim, 1, now - 600_000, now - 590_000).unwrap();
+        assert!(matches!(table.observe(&stale).unwrap(), LeaseOutcome::Rejected(_)));
+        assert_eq!(table.holder("other"), None);
+    }
+
+    #[test]
+    fn test_rejects_forged_claim() {
+        let alice = KeyPair::generate().unwrap();
+        let table = LeaseTable::new();
+        let mut claim = table.claim(&alice, "job", DEFAULT_LEASE_TTL).unwrap();
+        claim.expires_at += 1_000_000;
+        assert!(table.observe(&claim).is_err());
+    }
+}
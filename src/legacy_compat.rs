@@ -0,0 +1,394 @@
+// DIAP Rust SDK - 旧版消息兼容层
+// 新版消息带有版本化信封，仍可解析v0（无信封的bincode）消息，并统计旧版流量以便判断何时移除兼容
+// v2在消息末尾增加not_before（定时消息），v0/v1消息解码时not_before为None
+// v3在消息末尾增加capability（能力令牌），v0/v1/v2消息解码时capability为None
+// v4在消息末尾增加session_token（会话令牌），v0~v3消息解码时session_token为None
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::pubsub_authenticator::{AuthenticatedMessage, PubSubMessageType};
+
+/// 信封魔数
+pub const ENVELOPE_MAGIC: &[u8; 4] = b"DIAP";
+
+/// 当前信封版本
+pub const CURRENT_ENVELOPE_VERSION: u8 = 4;
+
+/// v0/v1消息体（不含not_before）
+#[derive(Serialize, Deserialize)]
+struct LegacyMessage {
+    message_id: String,
+    message_type: PubSubMessageType,
+    from_did: String,
+    to_did: Option<String>,
+    from_peer_id: String,
+    did_cid: String,
+    topic: String,
+    content: Vec<u8>,
+    nonce: String,
+    zkp_proof: Vec<u8>,
+    signature: Vec<u8>,
+    timestamp: u64,
+}
+
+impl From<LegacyMessage> for AuthenticatedMessage {
+    fn from(m: LegacyMessage) -> Self {
+        AuthenticatedMessage {
+            message_id: m.message_id,
+            message_type: m.message_type,
+            from_did: m.from_did,
+            to_did: m.to_did,
+            from_peer_id: m.from_peer_id,
+            did_cid: m.did_cid,
+            topic: m.topic,
+            content: m.content,
+            nonce: m.nonce,
+            zkp_proof: m.zkp_proof,
+            signature: m.signature,
+            timestamp: m.timestamp,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        }
+    }
+}
+
+impl From<&AuthenticatedMessage> for LegacyMessage {
+    fn from(m: &AuthenticatedMessage) -> Self {
+        LegacyMessage {
+            message_id: m.message_id.clone(),
+            message_type: m.message_type.clone(),
+            from_did: m.from_did.clone(),
+            to_did: m.to_did.clone(),
+            from_peer_id: m.from_peer_id.clone(),
+            did_cid: m.did_cid.clone(),
+            topic: m.topic.clone(),
+            content: m.content.clone(),
+            nonce: m.nonce.clone(),
+            zkp_proof: m.zkp_proof.clone(),
+            signature: m.signature.clone(),
+            timestamp: m.timestamp,
+        }
+    }
+}
+
+/// v2消息体（含not_before，不含capability）
+#[derive(Serialize, Deserialize)]
+struct MessageV2 {
+    message_id: String,
+    message_type: PubSubMessageType,
+    from_did: String,
+    to_did: Option<String>,
+    from_peer_id: String,
+    did_cid: String,
+    topic: String,
+    content: Vec<u8>,
+    nonce: String,
+    zkp_proof: Vec<u8>,
+    signature: Vec<u8>,
+    timestamp: u64,
+    not_before: Option<u64>,
+}
+
+impl From<MessageV2> for AuthenticatedMessage {
+    fn from(m: MessageV2) -> Self {
+        AuthenticatedMessage {
+            message_id: m.message_id,
+            message_type: m.message_type,
+            from_did: m.from_did,
+            to_did: m.to_did,
+            from_peer_id: m.from_peer_id,
+            did_cid: m.did_cid,
+            topic: m.topic,
+            content: m.content,
+            nonce: m.nonce,
+            zkp_proof: m.zkp_proof,
+            signature: m.signature,
+            timestamp: m.timestamp,
+            not_before: m.not_before,
+            capability: None,
+            session_token: None,
+        }
+    }
+}
+
+/// v3消息体（含capability，不含session_token）
+#[derive(Serialize, Deserialize)]
+struct MessageV3 {
+    message_id: String,
+    message_type: PubSubMessageType,
+    from_did: String,
+    to_did: Option<String>,
+    from_peer_id: String,
+    did_cid: String,
+    topic: String,
+    content: Vec<u8>,
+    nonce: String,
+    zkp_proof: Vec<u8>,
+    signature: Vec<u8>,
+    timestamp: u64,
+    not_before: Option<u64>,
+    capability: Option<String>,
+}
+
+impl From<MessageV3> for AuthenticatedMessage {
+    fn from(m: MessageV3) -> Self {
+        AuthenticatedMessage {
+            message_id: m.message_id,
+            message_type: m.message_type,
+            from_did: m.from_did,
+            to_did: m.to_did,
+            from_peer_id: m.from_peer_id,
+            did_cid: m.did_cid,
+            topic: m.topic,
+            content: m.content,
+            nonce: m.nonce,
+            zkp_proof: m.zkp_proof,
+            signature: m.signature,
+            timestamp: m.timestamp,
+            not_before: m.not_before,
+            capability: m.capability,
+            session_token: None,
+        }
+    }
+}
+
+/// 消息线格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WireFormat {
+    /// v0：直接bincode序列化，无信封（0.2.x及更早版本）
+    LegacyV0,
+    /// 带版本号的信封
+    Versioned(u8),
+}
+
+/// 旧版流量统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LegacyTrafficStats {
+    /// 收到的v0消息数
+    pub legacy_messages: u64,
+
+    /// 收到的版本化消息数
+    pub versioned_messages: u64,
+
+    /// 最近一次收到v0消息的时间（Unix秒，0表示从未收到）
+    pub last_legacy_seen: u64,
+}
+
+impl LegacyTrafficStats {
+    /// v0消息占比
+    pub fn legacy_ratio(&self) -> f64 {
+        let total = self.legacy_messages + self.versioned_messages;
+        if total == 0 {
+            0.0
+        } else {
+            self.legacy_messages as f64 / total as f64
+        }
+    }
+}
+
+static LEGACY_MESSAGES: AtomicU64 = AtomicU64::new(0);
+static VERSIONED_MESSAGES: AtomicU64 = AtomicU64::new(0);
+static LAST_LEGACY_SEEN: AtomicU64 = AtomicU64::new(0);
+
+/// 获取进程内的旧版流量统计
+pub fn legacy_traffic_stats() -> LegacyTrafficStats {
+    LegacyTrafficStats {
+        legacy_messages: LEGACY_MESSAGES.load(Ordering::Relaxed),
+        versioned_messages: VERSIONED_MESSAGES.load(Ordering::Relaxed),
+        last_legacy_seen: LAST_LEGACY_SEEN.load(Ordering::Relaxed),
+    }
+}
+
+/// 以当前信封格式编码消息
+pub fn encode_message(message: &AuthenticatedMessage) -> Result<Vec<u8>> {
+    let body = bincode::serialize(message).context("序列化消息失败")?;
+
+    let mut data = Vec::with_capacity(ENVELOPE_MAGIC.len() + 1 + body.len());
+    data.extend_from_slice(ENVELOPE_MAGIC);
+    data.push(CURRENT_ENVELOPE_VERSION);
+    data.extend_from_slice(&body);
+    Ok(data)
+}
+
+/// 以v0格式编码消息（发送给尚未升级的节点）
+/// v0无法携带not_before、capability和session_token，定时消息和携带令牌的消息在旧节点上将无法通过验证
+pub fn encode_message_v0(message: &AuthenticatedMessage) -> Result<Vec<u8>> {
+    if message.not_before.is_some() {
+        log::warn!("⚠️ 定时消息以v0格式发送，not_before将丢失: {}", message.message_id);
+    }
+    if message.capability.is_some() {
+        log::warn!("⚠️ 携带能力令牌的消息以v0格式发送，capability将丢失: {}", message.message_id);
+    }
+    if message.session_token.is_some() {
+        log::warn!("⚠️ 携带会话令牌的消息以v0格式发送，session_token将丢失: {}", message.message_id);
+    }
+    bincode::serialize(&LegacyMessage::from(message)).context("序列化消息失败")
+}
+
+/// 解码消息，自动识别v0与版本化格式
+pub fn decode_message(data: &[u8]) -> Result<(AuthenticatedMessage, WireFormat)> {
+    let header_len = ENVELOPE_MAGIC.len() + 1;
+
+    if data.len() >= header_len && &data[..ENVELOPE_MAGIC.len()] == ENVELOPE_MAGIC {
+        let version = data[ENVELOPE_MAGIC.len()];
+        if version == 0 || version > CURRENT_ENVELOPE_VERSION {
+            anyhow::bail!("不支持的消息信封版本: {}", version);
+        }
+
+        let body = &data[header_len..];
+        let message = match version {
+            1 => bincode::deserialize::<LegacyMessage>(body).map(AuthenticatedMessage::from),
+            2 => bincode::deserialize::<MessageV2>(body).map(AuthenticatedMessage::from),
+            3 => bincode::deserialize::<MessageV3>(body).map(AuthenticatedMessage::from),
+            _ => bincode::deserialize(body),
+        }
+        .context("反序列化消息失败")?;
+        VERSIONED_MESSAGES.fetch_add(1, Ordering::Relaxed);
+        return Ok((message, WireFormat::Versioned(version)));
+    }
+
+    // v0：无信封（v0首字段是message_id的u64长度，不会与魔数冲突）
+    let message = bincode::deserialize::<LegacyMessage>(data)
+        .map(AuthenticatedMessage::from)
+        .context("反序列化消息失败")?;
+    LEGACY_MESSAGES.fetch_add(1, Ordering::Relaxed);
+    LAST_LEGACY_SEEN.store(chrono::Utc::now().timestamp() as u64, Ordering::Relaxed);
+    log::debug!("收到v0格式消息");
+
+    Ok((message, WireFormat::LegacyV0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pubsub_authenticator::PubSubMessageType;
+
+    fn message() -> AuthenticatedMessage {
+        AuthenticatedMessage {
+            message_id: "m1".to_string(),
+            message_type: PubSubMessageType::Heartbeat,
+            from_did: "did:key:alice".to_string(),
+            to_did: None,
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: "general".to_string(),
+            content: b"hello".to_vec(),
+            nonce: "n1".to_string(),
+            zkp_proof: Vec::new(),
+            signature: vec![1, 2, 3],
+            timestamp: 42,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_decode_both_formats() {
+        let before = legacy_traffic_stats();
+
+        let (decoded, format) = decode_message(&encode_message(&message()).unwrap()).unwrap();
+        assert_eq!(format, WireFormat::Versioned(CURRENT_ENVELOPE_VERSION));
+        assert_eq!(decoded.message_id, "m1");
+
+        let (decoded, format) = decode_message(&encode_message_v0(&message()).unwrap()).unwrap();
+        assert_eq!(format, WireFormat::LegacyV0);
+        assert_eq!(decoded.signature, vec![1, 2, 3]);
+
+        let after = legacy_traffic_stats();
+        assert!(after.legacy_messages > before.legacy_messages);
+        assert!(after.versioned_messages > before.versioned_messages);
+        assert!(after.last_legacy_seen > 0);
+    }
+
+    #[test]
+    fn test_not_before_survives_v2_and_v1_still_decodes() {
+        let mut scheduled = message();
+        scheduled.not_before = Some(1_700_000_000);
+        let (decoded, _) = decode_message(&encode_message(&scheduled).unwrap()).unwrap();
+        assert_eq!(decoded.not_before, Some(1_700_000_000));
+
+        // v1信封（0.2.7版本发送的消息）
+        let mut v1 = ENVELOPE_MAGIC.to_vec();
+        v1.push(1);
+        v1.extend(bincode::serialize(&LegacyMessage::from(&message())).unwrap());
+        let (decoded, format) = decode_message(&v1).unwrap();
+        assert_eq!(format, WireFormat::Versioned(1));
+        assert_eq!(decoded.message_id, "m1");
+        assert_eq!(decoded.not_before, None);
+    }
+
+    #[test]
+    fn test_capability_survives_v3_and_v2_still_decodes() {
+        let mut invocation = message();
+        invocation.capability = Some("token".to_string());
+        let (decoded, _) = decode_message(&encode_message(&invocation).unwrap()).unwrap();
+        assert_eq!(decoded.capability.as_deref(), Some("token"));
+
+        // v2信封（只有not_before）
+        let mut scheduled = message();
+        scheduled.not_before = Some(1_700_000_000);
+        let mut v2 = ENVELOPE_MAGIC.to_vec();
+        v2.push(2);
+        v2.extend(bincode::serialize(&MessageV2 {
+            message_id: scheduled.message_id.clone(),
+            message_type: scheduled.message_type.clone(),
+            from_did: scheduled.from_did.clone(),
+            to_did: None,
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: scheduled.topic.clone(),
+            content: scheduled.content.clone(),
+            nonce: scheduled.nonce.clone(),
+            zkp_proof: Vec::new(),
+            signature: scheduled.signature.clone(),
+            timestamp: 42,
+            not_before: scheduled.not_before,
+        }).unwrap());
+        let (decoded, format) = decode_message(&v2).unwrap();
+        assert_eq!(format, WireFormat::Versioned(2));
+        assert_eq!(decoded.not_before, Some(1_700_000_000));
+        assert_eq!(decoded.capability, None);
+    }
+
+    #[test]
+    fn test_session_token_survives_v4_and_v3_still_decodes() {
+        let mut fast_path = message();
+        fast_path.session_token = Some("header.claims.sig".to_string());
+        let (decoded, _) = decode_message(&encode_message(&fast_path).unwrap()).unwrap();
+        assert_eq!(decoded.session_token.as_deref(), Some("header.claims.sig"));
+
+        // v3信封（携带capability）
+        let mut v3 = ENVELOPE_MAGIC.to_vec();
+        v3.push(3);
+        v3.extend(bincode::serialize(&MessageV3 {
+            message_id: "m1".to_string(),
+            message_type: PubSubMessageType::Heartbeat,
+            from_did: "did:key:alice".to_string(),
+            to_did: None,
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: "general".to_string(),
+            content: b"hello".to_vec(),
+            nonce: "n1".to_string(),
+            zkp_proof: Vec::new(),
+            signature: vec![1, 2, 3],
+            timestamp: 42,
+            not_before: None,
+            capability: Some("token".to_string()),
+        }).unwrap());
+        let (decoded, format) = decode_message(&v3).unwrap();
+        assert_eq!(format, WireFormat::Versioned(3));
+        assert_eq!(decoded.capability.as_deref(), Some("token"));
+        assert_eq!(decoded.session_token, None);
+    }
+
+    #[test]
+    fn test_reject_future_version() {
+        let mut data = encode_message(&message()).unwrap();
+        data[ENVELOPE_MAGIC.len()] = CURRENT_ENVELOPE_VERSION + 1;
+        assert!(decode_message(&data).is_err());
+    }
+}
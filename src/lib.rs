@@ -4,50 +4,351 @@
  * 使用零知识证明验证DID-CID绑定，无需IPNS
  */
 
+// ============ 可移植模块（含wasm32） ============
+// 纯计算，不依赖tokio运行时、文件系统和libp2p传输
+
+// 协议常量与网络参数
+pub mod constants;
+
+// did:key编解码
+pub mod did_key;
+
+// 确定性CID计算（上传前得知CID）
+pub mod cid_compute;
+
+// 浏览器绑定（wasm32-unknown-unknown）
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+// 协议常量与网络参数
+pub use constants::{
+    NetworkParams,
+    network_params,
+    set_network_params,
+    PROTOCOL_VERSION,
+    DEFAULT_NAMESPACE,
+    DIAP_PROTOCOL,
+    DIAP_REQUEST_PROTOCOL_NAME,
+    DIAP_RELAY_PROTOCOL_NAME,
+    DIAP_BLOCK_PROTOCOL_NAME,
+    IROH_ALPN,
+    AGENT_TOPIC_PREFIX,
+    SHARD_TOPIC_PREFIX,
+    DID_CONTEXT_V1,
+    ED25519_2020_CONTEXT,
+};
+
+// did:key编解码
+pub use did_key::{
+    did_key_from_public_key,
+    public_key_from_did_key,
+    public_key_multibase,
+    verify_with_did_key,
+};
+
+// 确定性CID计算
+pub use cid_compute::{
+    CidOptions, CidVersion, CidCodec,
+    compute_cid, compute_cid_with, compute_content_cid,
+    document_matches_cid, content_matches_cid,
+};
+
+/// 以下模块依赖tokio运行时、文件系统或libp2p传输，不参与wasm32构建
+macro_rules! native_only {
+    ($($item:item)*) => {
+        $(
+            #[cfg(not(target_arch = "wasm32"))]
+            $item
+        )*
+    };
+}
+
+native_only! {
+
 // ============ 核心模块 ============
 
+// 统一错误类型
+pub mod error;
+
 // 密钥管理
 pub mod key_manager;
 
 // IPFS客户端
 pub mod ipfs_client;
 
+// 网关健康评分
+pub mod gateway_health;
+
+// 本地块存储（离线DID解析）
+pub mod block_store;
+
+// Pin生命周期管理
+pub mod pin_manager;
+
+// Pin服务提供商
+pub mod pinning_provider;
+
 // 内置IPFS节点管理器（仅Kubo分支使用）
 #[cfg(feature = "kubo")]
 pub mod ipfs_node_manager;
 
 // Kubo自动安装器
+#[cfg(feature = "node")]
 pub mod kubo_installer;
 
 // DID构建器（简化版）
 pub mod did_builder;
 
+// DID两阶段发布（预提交CID后上传）
+pub mod did_commitment;
+
+// DID解析
+pub mod did_resolver;
+
+// 只读验证器（verifier档位）
+pub mod verifier;
+
 // libp2p身份
 pub mod libp2p_identity;
+#[cfg(feature = "node")]
 pub mod libp2p_node;
 
+// 对端连接管理
+#[cfg(feature = "node")]
+pub mod connection_manager;
+
+// 基于Kademlia的智能体发现
+#[cfg(feature = "node")]
+pub mod agent_discovery;
+
+// 内嵌IPFS后端（块存储 + DHT provider + 块交换）
+#[cfg(feature = "node")]
+pub mod embedded_ipfs;
+
+// IPNS发布（本地签名记录，直接发布到DHT）
+#[cfg(feature = "node")]
+pub mod ipns_publisher;
+
+// 内嵌HTTP服务的公共部分
+#[cfg(feature = "node")]
+pub mod http_server;
+
+// 对端地址簿（DID -> PeerID与多地址，可持久化）
+#[cfg(feature = "node")]
+pub mod address_book;
+
+// 统一传输层（libp2p / Iroh / HTTP）
+#[cfg(feature = "node")]
+pub mod transport;
+
+// 内嵌HTTP服务的TLS
+#[cfg(feature = "tls")]
+pub mod tls;
+
+// 追踪订阅者与OTLP导出
+#[cfg(feature = "otel")]
+pub mod telemetry;
+
+// 本地管理接口
+#[cfg(feature = "node")]
+pub mod admin_api;
+
+// DID签名的HTTP请求认证（Authorization: DIDWba）
+pub mod did_wba_auth;
+
+// REST接口（OpenAPI文档）
+#[cfg(feature = "node")]
+pub mod rest_api;
+
+// 中继/基础设施节点（diap relay）
+#[cfg(feature = "node")]
+pub mod relay_node;
+
+// 终端仪表盘（diap top）
+#[cfg(feature = "tui")]
+pub mod tui_dashboard;
+
+// libp2p请求-响应编解码器
+pub mod p2p_codec;
+
+// 请求-响应匹配
+pub mod pending_requests;
+
 // 签名PeerID（隐私保护）
 pub mod encrypted_peer_id;
 
+// 成对DID（降低跨关系可关联性）
+pub mod pairwise_did;
+
 // ZKP模块 (基于Noir)
 
 // 统一身份管理
 pub mod identity_manager;
 
+// 时间源抽象
+pub mod clock;
+
+// 消息ID生成（UUIDv7）
+pub mod message_id;
+
 // Nonce管理器（防重放攻击）
 pub mod nonce_manager;
 
+// Nonce持久化存储
+pub mod nonce_store;
+
+// 消息时间戳窗口（时钟偏差容忍）
+pub mod timestamp_window;
+
+// 运行指标（Prometheus导出）
+pub mod metrics;
+
 // DID文档缓存
 pub mod did_cache;
 
 // IPFS Pubsub认证通讯
 pub mod pubsub_authenticator;
 
+// 主题配置导出/导入（签名文档）
+pub mod topic_policy;
+
+// 分层主题、通配符订阅与收件箱
+pub mod topic_pattern;
+
+// 旧版消息兼容层
+pub mod legacy_compat;
+
+// 流量混淆（填充与掩护流量）
+pub mod traffic_obfuscation;
+
+// 洋葱路由（多跳中继投递）
+pub mod onion_routing;
+
+// 端到端加密（keyAgreement + ChaCha20-Poly1305）
+pub mod e2e_encryption;
+
+// 消息归档（保留策略与阅后即焚）
+pub mod message_archive;
+
+// DID吊销
+pub mod did_revocation;
+
+// DID文档更新通知（缓存失效）
+pub mod did_update;
+
+// DHT验证提示（跳过IPFS获取）
+pub mod verification_hint;
+
+// 延迟预算（交互式认证的截止时间）
+pub mod latency_budget;
+
+// 验证信任等级（按主题/消息类型配置）
+pub mod trust_level;
+
+// 基于能力的授权令牌（可委托、限时）
+pub mod capabilities;
+
+// 集群管理（策略包灰度下发、确认跟踪与回滚）
+pub mod fleet;
+
+// 安全会话（认证后的X25519密钥协商与对称加密）
+pub mod secure_session;
+
+// 远程功能开关（签名的运维命令、本地允许策略与审计日志）
+pub mod feature_toggles;
+
+// 跨智能体时钟同步估计
+pub mod clock_sync;
+
+// 会话令牌（验证成功后签发，凭令牌跳过逐条ZKP验证）
+pub mod session_token;
+
+// 群组消息（共享主题密钥，成员变更时轮换）
+pub mod group_messaging;
+
+// PeerID与DID绑定（派生或联合签名）
+pub mod peer_binding;
+
+// 低延迟信号（签名的不可靠数据报）
+pub mod datagram_signal;
+
+// 离线消息队列（存储转发与送达回执）
+pub mod store_and_forward;
+
+// 消息状态跟踪（送达回执与已读回执）
+pub mod message_status;
+
+// 按DID的熔断器
+pub mod circuit_breaker;
+
+// 速率限制与滥用事件
+pub mod rate_limiter;
+
+// 信誉与信任评分
+pub mod trust;
+
+// 消息准入控制（熔断、限流、信誉和自定义主题策略）
+pub mod admission;
+
+// 有界事件通道（溢出策略与队列深度指标）
+pub mod event_channel;
+
+// 数据导出与删除（类GDPR）
+pub mod data_privacy;
+
+// 验证证据包（供审计方独立复核）
+pub mod evidence_bundle;
+
+// 选择性披露凭证（SD-JWT）
+pub mod credentials;
+
+// 智能体检查点（热迁移）
+pub mod agent_checkpoint;
+
+// 信任图（引荐关系）
+pub mod trust_graph;
+
+// 智能体邀请（引导入网）
+pub mod agent_invite;
+
+// diap:// URI与二维码
+pub mod diap_uri;
+
+// 配对协议（短认证字符串）
+pub mod pairing;
+
+// 定时消息邮箱
+pub mod scheduled_mailbox;
+
+// 可靠广播（阈值确认）
+pub mod reliable_broadcast;
+
+// CRDT状态同步
+pub mod crdt_sync;
+
+// 分布式租约
+pub mod lease;
+
+// 差分隐私统计
+pub mod private_stats;
+
+// 定时任务调度
+pub mod job_scheduler;
+
+// 密钥透明日志
+pub mod transparency_log;
+
+// 认证审计日志
+pub mod audit_log;
+
 
 // Noir ZKP集成（新版本）
 pub mod noir_zkp;
 pub mod noir_verifier;
 
+// Noir电路编译流水线
+pub mod noir_circuit_build;
+
 
 // 智能体验证闭环
 pub mod agent_verification;
@@ -56,27 +357,133 @@ pub mod agent_verification;
 pub mod ipfs_bidirectional_verification;
 
 // 智能体认证管理器（统一API）
+#[cfg(feature = "node")]
 pub mod agent_auth;
 
 // ZKP密钥生成器
+#[cfg(feature = "node")]
 pub mod key_generator;
 
-// Iroh节点（预留）
+// 可信设置仪式（版本化密钥包）
+pub mod setup_ceremony;
+
+// Groth16批量验证
+#[cfg(feature = "arkworks-zkp")]
+pub mod groth16_batch;
+
+// Iroh节点
 pub mod iroh_node;
 
 // 配置管理（保留）
 pub mod config_manager;
 
+// 启动自检
+#[cfg(feature = "node")]
+pub mod self_test;
+
+// 项目模板生成（diap new）
+pub mod project_template;
+
+// C ABI绑定（供其他语言嵌入）
+#[cfg(feature = "ffi")]
+pub mod diap_ffi;
+
 // ============ 公共导出 ============
 
+// 统一错误类型
+pub use error::{
+    DiapError,
+    DiapResult,
+    IpfsErrorKind,
+    AuthErrorKind,
+};
+
 // 密钥管理
 pub use key_manager::{
-    KeyPair, KeyManager, KeyBackup
+    KeyPair, KeyManager, KeyBackup, KeyRotationResult,
+    Signer, CallbackSigner, SignFn,
+    SignedEnvelope,
 };
 
 // IPFS客户端
 pub use ipfs_client::{
-    IpfsClient, IpfsUploadResult
+    IpfsClient, IpfsUploadResult, IpfsBackend, RetryPolicy
+};
+
+// 内嵌IPFS后端
+#[cfg(feature = "node")]
+pub use embedded_ipfs::{
+    EmbeddedIpfs,
+    BlockPeerRecord,
+    block_key,
+    block_peer_key,
+    DEFAULT_BLOCK_FETCH_TIMEOUT,
+};
+
+// IPNS发布
+#[cfg(feature = "node")]
+pub use ipns_publisher::{
+    IpnsPublisher,
+    IpnsRecord,
+    ipns_name,
+    ipns_key,
+    DEFAULT_IPNS_VALIDITY,
+    DEFAULT_IPNS_TTL,
+};
+
+// 对端地址簿
+#[cfg(feature = "node")]
+pub use address_book::{
+    AddressBook,
+    AddressSource,
+    PeerEntry,
+    MAX_ADDRESSES_PER_PEER,
+};
+
+// 统一传输层
+#[cfg(feature = "node")]
+pub use transport::{
+    Transport,
+    TransportMessage,
+    TransportPeer,
+    Reachability,
+    RequestHandler,
+    Libp2pTransport,
+    HttpTransport,
+    start_transport,
+    DEFAULT_TRANSPORT_TIMEOUT,
+};
+
+#[cfg(all(feature = "node", feature = "iroh"))]
+pub use transport::IrohTransport;
+
+// 网关健康评分
+pub use gateway_health::{
+    GatewayHealth,
+    GatewayHealthTracker,
+};
+
+// 本地块存储
+pub use block_store::{
+    BlockStore,
+    BlockStoreStats,
+};
+
+// Pin生命周期管理
+pub use pin_manager::{
+    PinManager,
+    PinTracker,
+    PinRecord,
+    PinStatus,
+    PinVerificationReport,
+};
+
+// Pin服务提供商
+pub use pinning_provider::{
+    PinningProvider,
+    PinataProvider,
+    PinningServiceProvider,
+    HostedPinningProvider,
 };
 
 // 内置IPFS节点管理器（仅Kubo分支使用）
@@ -89,28 +496,152 @@ pub use ipfs_node_manager::{
 };
 
 // Kubo自动安装器
-pub use kubo_installer::KuboInstaller;
+#[cfg(feature = "node")]
+pub use kubo_installer::{KuboInstaller, DEFAULT_KUBO_VERSION};
 
 // DID构建器
 pub use did_builder::{
     DIDBuilder, DIDPublishResult, 
     DIDDocument, 
+    KeyRotationProof,
+    DIDDocumentUpdate,
+    DIDVersion,
     VerificationMethod,
     Service,
     get_did_document_from_cid,
+    get_did_document_history,
     verify_did_document_integrity,
 };
 
+// DID两阶段发布
+pub use did_commitment::{
+    CidCommitment, CommitmentStatus, PendingPublication,
+    CID_COMMITMENT_MESSAGE_TYPE, MAX_PENDING_COMMITMENTS,
+};
+
+// DID解析
+pub use did_resolver::{DIDResolver, DIDSignatureVerifier};
+
+// 只读验证器
+pub use verifier::DiapVerifier;
+
 // libp2p模块
 pub use libp2p_identity::{
     LibP2PIdentity, LibP2PIdentityManager
 };
 
+#[cfg(feature = "node")]
 pub use libp2p_node::{
     LibP2PNode, NodeInfo
 };
 
+#[cfg(feature = "node")]
+pub use connection_manager::{
+    ConnectionManager,
+    PeerInfo,
+    PeerState,
+    DialRequest,
+    DialFn,
+};
+
+#[cfg(feature = "node")]
+pub use agent_discovery::{
+    AgentDiscovery,
+    AgentRecord,
+    DhtBackend,
+    KademliaDht,
+    capability_key,
+    agent_record_key,
+    DIAP_KAD_PROTOCOL,
+    DEFAULT_AGENT_RECORD_TTL,
+};
+
+#[cfg(feature = "node")]
+pub use admin_api::{
+    AdminServer,
+    AdminEvent,
+    AdminEvents,
+    StatusCollector,
+    AgentStatus,
+    PeerStatus,
+    TopicStatus,
+    ResourceUsage,
+    fetch_status,
+    DEFAULT_ADMIN_ADDR,
+    CIRCUITS_PATH,
+    JOBS_PATH,
+};
+
+// REST接口
+#[cfg(feature = "node")]
+pub use rest_api::{
+    RestApi,
+    ApiRouter,
+    RouteRegistry,
+    ApiRequest,
+    ApiResponse,
+    ApiError,
+    HttpMethod,
+    PublishFn,
+    DEFAULT_REST_ADDR,
+    API_BASE_PATH,
+    OPENAPI_PATH,
+};
+
+// DIDWba请求认证
+pub use did_wba_auth::{
+    DidWbaAuthorization,
+    DidRequestVerifier,
+    AuthenticatedCaller,
+    sign_request,
+    DIDWBA_SCHEME,
+};
+
+// TLS
+#[cfg(feature = "tls")]
+pub use tls::{
+    TlsIdentity,
+    TlsAcceptor,
+};
+
+// 追踪
+#[cfg(feature = "otel")]
+pub use telemetry::{
+    TelemetryConfig,
+    TelemetryGuard,
+    DEFAULT_SERVICE_NAME,
+};
+
+// 中继节点
+#[cfg(feature = "node")]
+pub use relay_node::{
+    RelayNode,
+    RelayConfig,
+    RelayRole,
+    RelayQuotas,
+    RelayService,
+    RelayRequest,
+    RelayResponse,
+    RelayQuotaError,
+    RelayMetricsSnapshot,
+    relay_request,
+};
+
+pub use p2p_codec::{
+    DIAPCodec,
+    DIAP_REQUEST_PROTOCOL,
+    request_protocol,
+    DEFAULT_MAX_MESSAGE_SIZE,
+};
+
+pub use pending_requests::{
+    PendingRequests,
+    PendingResponse,
+    DEFAULT_REQUEST_TIMEOUT,
+};
+
 // Iroh P2P通信器
+#[cfg(feature = "iroh")]
 pub mod iroh_communicator;
 
 // 签名PeerID（隐私保护）
@@ -122,6 +653,12 @@ pub use encrypted_peer_id::{
     verify_encrypted_peer_id_ownership,
 };
 
+// 成对DID
+pub use pairwise_did::{
+    PairwiseIdentityManager,
+    LinkageProof,
+};
+
 // ZKP模块 (基于Noir)
 
 // 嵌入Noir电路模块
@@ -131,6 +668,15 @@ pub mod noir_embedded;
 // 通用Noir管理器
 pub mod noir_universal;
 
+// UltraHonk证明后端（Barretenberg）
+pub mod noir_ultra_honk;
+
+// 证明缓存
+pub mod proof_cache;
+
+// Arkworks证明迁移层（弃用遥测与迁移报告）
+pub mod zkp_migration;
+
 // Noir ZKP集成
 pub use noir_zkp::{
     NoirZKPManager,
@@ -140,13 +686,28 @@ pub use noir_zkp::{
     NoirProverInputs,
 };
 
+// Noir电路编译流水线
+pub use noir_circuit_build::{
+    CircuitBuilder, CircuitBuildError, CircuitManifest, CircuitSource, CompiledCircuit,
+    CIRCUIT_MANIFEST_FILE,
+};
+
 // Noir验证器
 pub use noir_verifier::{
     NoirVerifier,
     NoirVerificationResult,
     ImprovedNoirZKPManager,
+    VerifierExporter,
+    VerifierArtifact,
+    VerifierBundle,
+    SOLIDITY_VERIFIER_FILE,
+    VERIFIER_BUNDLE_FILE,
 };
 
+// Groth16链上验证合约导出
+#[cfg(feature = "arkworks-zkp")]
+pub use noir_verifier::groth16_solidity_verifier;
+
 // 导出通用管理器
 pub use noir_universal::{
     UniversalNoirManager,
@@ -155,6 +716,28 @@ pub use noir_universal::{
     PerformanceStats,
 };
 
+// UltraHonk证明后端
+pub use noir_ultra_honk::{
+    UltraHonkProver,
+    UltraHonkProof,
+    ULTRA_HONK_SCHEME,
+};
+
+// 证明缓存
+pub use proof_cache::{
+    ProofCache,
+    ProofCacheStats,
+};
+
+// Arkworks证明迁移层
+pub use zkp_migration::{
+    ProofFormat,
+    DeprecationTelemetry,
+    LegacyProofSource,
+    MigrationReport,
+    LEGACY_ARKWORKS_PROOF_PREFIX,
+};
+
 // 导出嵌入模块（如果启用）
 #[cfg(feature = "embedded-noir")]
 pub use noir_embedded::{
@@ -187,6 +770,7 @@ pub use ipfs_bidirectional_verification::{
 };
 
 // 智能体认证管理器
+#[cfg(feature = "node")]
 pub use agent_auth::{
     AgentAuthManager,
     AuthResult,
@@ -194,12 +778,36 @@ pub use agent_auth::{
 };
 
 // ZKP密钥生成器
+#[cfg(feature = "node")]
 pub use key_generator::{
     generate_simple_zkp_keys,
     ensure_zkp_keys_exist,
     generate_noir_keys,
 };
 
+// 可信设置仪式
+pub use setup_ceremony::{
+    SetupCeremony,
+    KeyBundle,
+    KeyBundleManifest,
+    VersionedProof,
+    SharedSrs,
+    Contribution,
+    SetupError,
+    SETUP_MANIFEST_FILE,
+};
+
+#[cfg(feature = "arkworks-zkp")]
+pub use setup_ceremony::{groth16_prove, groth16_verify};
+
+// Groth16批量验证
+#[cfg(feature = "arkworks-zkp")]
+pub use groth16_batch::{
+    Groth16BatchVerifier,
+    ProofEnvelope,
+    verify_batch,
+};
+
 // 身份管理
 pub use identity_manager::{
     IdentityManager,
@@ -207,16 +815,80 @@ pub use identity_manager::{
     ServiceInfo,
     IdentityRegistration,
     IdentityVerification,
+    BindingProofInput,
 };
 
 // 配置管理
 pub use config_manager::{
     DIAPConfig,
     AgentConfig,
+    HttpServerConfig,
+    TlsSettings,
     IpfsConfig,
     IpnsConfig,
     CacheConfig,
     LoggingConfig,
+    MessagingConfig,
+    NetworkConfig,
+    TransportConfig,
+    TransportKind,
+    Libp2pProtocol,
+    LocalIpfsNodeConfig,
+    ConfigLoader,
+    ConfigSource,
+};
+
+// 项目模板生成
+pub use project_template::{
+    scaffold_project,
+    render_files,
+};
+
+// 启动自检
+#[cfg(feature = "node")]
+pub use self_test::{
+    run_self_test,
+    SelfTestOptions,
+    SelfTestReport,
+    SelfTestStep,
+    SelfTestStatus,
+};
+
+// C ABI绑定
+#[cfg(feature = "ffi")]
+pub use diap_ffi::{
+    DiapStatus,
+    DiapBytes,
+    DiapKeyPair,
+    DiapClient,
+};
+
+// 时间源
+pub use clock::{
+    Clock,
+    SharedClock,
+    SystemClock,
+    MonotonicClock,
+    MockClock,
+    system_clock,
+};
+
+// 消息ID生成
+pub use message_id::{
+    IdStrategy,
+    set_id_strategy,
+    id_strategy,
+    new_message_id,
+    new_id_with,
+    id_timestamp_millis,
+};
+
+// 运行指标
+pub use metrics::{
+    Metrics,
+    MetricsSnapshot,
+    HistogramSnapshot,
+    PROMETHEUS_CONTENT_TYPE,
 };
 
 // Nonce管理器
@@ -225,11 +897,31 @@ pub use nonce_manager::{
     NonceRecord,
 };
 
+// Nonce持久化
+pub use nonce_store::{
+    NonceStore,
+    FileNonceStore,
+};
+
+#[cfg(feature = "sled")]
+pub use nonce_store::SledNonceStore;
+
+// 时间戳窗口
+pub use timestamp_window::{
+    TimestampWindow,
+    TimestampViolation,
+    DEFAULT_MAX_MESSAGE_AGE,
+    DEFAULT_MAX_CLOCK_SKEW,
+};
+
 // DID文档缓存
 pub use did_cache::{
     DIDCache,
     CacheEntry,
     CacheStats as DIDCacheStats,
+    NegativeEntry,
+    WarmReport,
+    DEFAULT_NEGATIVE_TTL,
 };
 
 // Pubsub认证器
@@ -239,7 +931,421 @@ pub use pubsub_authenticator::{
     MessageVerification,
     TopicPolicy,
     TopicConfig,
+    RateLimit,
+    TopicEncryption,
     PubSubMessageType,
+    VerificationFailure,
+    SignedMessage,
+};
+
+// 主题配置导出/导入
+pub use topic_policy::{
+    TopicPolicyDocument,
+    TOPIC_POLICY_VERSION,
+};
+
+// 分层主题与通配符
+pub use topic_pattern::{
+    TopicPattern,
+    inbox_topic,
+    inbox_owner,
+    shard_topic,
+    all_shard_topics,
+};
+
+// 旧版消息兼容
+pub use legacy_compat::{
+    WireFormat,
+    LegacyTrafficStats,
+    legacy_traffic_stats,
+};
+
+// 流量混淆
+pub use traffic_obfuscation::{
+    TrafficObfuscator,
+    ObfuscationConfig,
+    ObfuscationStats,
+    CoverMessage,
+};
+
+// 洋葱路由
+pub use onion_routing::{
+    OnionRouter,
+    OnionPacket,
+    PeeledOnion,
+    RelayDirectory,
+    RelayInfo,
+};
+
+// 端到端加密
+pub use e2e_encryption::{
+    EncryptedPayload,
+    KEY_AGREEMENT_TYPE,
+    key_agreement_method,
+    key_agreement_from_did_key,
+};
+
+// 消息归档
+pub use message_archive::{
+    MessageArchive,
+    ArchivedMessage,
+    RetentionPolicy,
+    DeletionAck,
+    ComplianceReport,
+    TopicRetentionReport,
+};
+
+// DID吊销
+pub use did_revocation::{
+    RevocationRecord,
+    RevocationRegistry,
+    REVOCATION_TOPIC,
+};
+
+// DID文档更新通知
+pub use did_update::{
+    DidUpdatedEvent,
+    DID_UPDATED_MESSAGE_TYPE,
+    DID_UPDATE_TOPIC,
+};
+
+// DHT验证提示
+pub use verification_hint::{
+    VerificationHint,
+    HintSource,
+    verification_hint_key,
+    DEFAULT_HINT_TTL,
+};
+
+#[cfg(feature = "node")]
+pub use verification_hint::DhtHints;
+
+// 延迟预算
+pub use latency_budget::{
+    LatencyBudget,
+    BudgetExceeded,
+    BudgetMetrics,
+    BudgetMetricsSnapshot,
+};
+
+// 验证信任等级
+pub use trust_level::{
+    TrustLevel,
+    TrustPolicy,
+    message_type_key,
+};
+
+// 能力令牌
+pub use capabilities::{
+    CapabilityToken,
+    CapabilityPolicy,
+    MAX_DELEGATION_DEPTH,
+};
+
+// 集群管理
+pub use fleet::{
+    FleetController,
+    FleetAgent,
+    FleetDirective,
+    FleetAck,
+    AckStatus,
+    PolicyBundle,
+    PolicyContents,
+    RolloutPlan,
+    RolloutState,
+    RolloutStatus,
+    FLEET_POLICY_MESSAGE_TYPE,
+    FLEET_ACK_MESSAGE_TYPE,
+};
+
+// 安全会话
+pub use secure_session::{
+    SecureSession,
+    SessionInitiator,
+    SessionOffer,
+    SessionAccept,
+    SessionFrame,
+    DEFAULT_SESSION_TTL,
+    MAX_SESSION_TTL,
+};
+
+// 远程功能开关
+pub use feature_toggles::{
+    FeatureToggles,
+    SdkFeature,
+    ToggleCommand,
+    TogglePolicy,
+    ToggleAuditEntry,
+    FEATURE_TOGGLE_MESSAGE_TYPE,
+};
+
+// 时钟同步
+pub use clock_sync::{
+    PeerClockOffsets,
+    TimeSyncPacket,
+    OffsetSample,
+    TIME_SYNC_MESSAGE_TYPE,
+};
+
+// 会话令牌
+pub use session_token::{
+    SessionToken,
+    SessionTokenClaims,
+    DEFAULT_SESSION_TOKEN_TTL,
+};
+
+// 群组消息
+pub use group_messaging::{
+    GroupAdmin,
+    GroupKeyring,
+    GroupKeyDistribution,
+    GroupCiphertext,
+    GROUP_KEY_MESSAGE_TYPE,
+    GROUP_MESSAGE_TYPE,
+};
+
+// PeerID绑定
+pub use peer_binding::{
+    PeerIdBinding,
+    PeerBindingRegistry,
+    PeerBindingCheck,
+    derived_peer_id,
+    PEER_BINDING_MESSAGE_TYPE,
+};
+
+// 低延迟信号
+pub use datagram_signal::{
+    Signal,
+    SignalKind,
+    SignalFilter,
+    MAX_SIGNAL_DATAGRAM_SIZE,
+    DEFAULT_SIGNAL_MAX_AGE_MS,
+};
+
+// 离线消息队列
+pub use store_and_forward::{
+    OfflineQueue,
+    QueuedMessage,
+    QueuedEnvelope,
+    InboundSequencer,
+    DeliveryReceipt,
+    DELIVERY_RECEIPT_MESSAGE_TYPE,
+    RECEIPT_RETENTION_SECS,
+    MAX_RETAINED_RECEIPTS,
+    MAX_BUFFERED_PER_SENDER,
+};
+
+// 消息状态
+pub use message_status::{
+    MessageStatus,
+    MessageStatusUpdate,
+    MessageStatusTracker,
+    MessageAck,
+    AckKind,
+};
+
+// 熔断器
+pub use circuit_breaker::{
+    CircuitBreakers,
+    CircuitBreakerConfig,
+    CircuitState,
+    CircuitStatus,
+    CircuitEvent,
+    FailureKind,
+};
+
+// 速率限制
+pub use rate_limiter::{
+    RateLimiter,
+    RateLimitConfig,
+    RateLimitScope,
+    AbuseEvent,
+    AbuseKind,
+    AbuseCallback,
+};
+
+// 信誉与信任评分
+pub use trust::{
+    ReputationStore,
+    ReputationEvent,
+    ReputationRecord,
+    TrustWeights,
+    TopicPolicyHook,
+    MAX_TRUST_SCORE,
+};
+
+// 消息准入控制
+pub use admission::{
+    AdmissionControl,
+    AdmissionRejection,
+};
+
+// 有界事件通道
+pub use event_channel::{
+    bounded_event_channel,
+    EventSender,
+    EventReceiver,
+    OverflowPolicy,
+    ChannelError,
+    ChannelMetrics,
+    DEFAULT_CHANNEL_CAPACITY,
+};
+
+// 数据导出与删除
+pub use data_privacy::{
+    DataPrivacyManager,
+    DataExport,
+    ErasureReport,
+    ErasedStore,
+};
+
+// 验证证据包
+pub use evidence_bundle::{
+    EvidenceBundle,
+    EvidenceCollector,
+    EvidenceDocument,
+    EvidenceMessage,
+    IdentityProofRecord,
+    EvidenceCheck,
+    EvidenceReport,
+    EVIDENCE_BUNDLE_VERSION,
+};
+
+// 选择性披露凭证
+pub use credentials::{
+    CredentialBuilder,
+    CredentialPayload,
+    SdJwtCredential,
+    Disclosure,
+    PresentationRequest,
+    VerifiedPresentation,
+    verify_presentation,
+    SD_JWT_TYPE,
+    KB_JWT_TYPE,
+};
+
+// 智能体检查点
+pub use agent_checkpoint::{
+    AgentCheckpoint,
+    ConnectionIntent,
+    SessionResumption,
+    RestoredAgent,
+    PeerSessionTable,
+    SESSION_RESUME_MESSAGE_TYPE,
+};
+
+// 信任图
+pub use trust_graph::{
+    TrustGraph,
+    Introduction,
+};
+
+// 智能体邀请
+pub use agent_invite::{
+    AgentInvite,
+    InviteBootstrap,
+    InviteAnnouncement,
+    AcceptedInvite,
+    INVITE_ANNOUNCE_MESSAGE_TYPE,
+};
+
+// diap:// URI
+pub use diap_uri::{
+    DiapUri,
+    DIAP_URI_SCHEME,
+};
+
+// 配对协议
+pub use pairing::{
+    PairingSession,
+    PairingCommit,
+    PairingResponse,
+    PairingReveal,
+    PairingRole,
+    PairingState,
+    PairedPeer,
+    PairedPeerStore,
+};
+
+// 定时消息邮箱
+pub use scheduled_mailbox::ScheduledMailbox;
+
+// 可靠广播
+pub use reliable_broadcast::{
+    BroadcastAck,
+    BroadcastTracker,
+    DeliveryCertificate,
+    PendingBroadcast,
+    BROADCAST_ACK_MESSAGE_TYPE,
+};
+
+// CRDT状态同步
+pub use crdt_sync::{
+    CrdtReplica,
+    CrdtState,
+    CrdtSyncMessage,
+    CrdtMetrics,
+    LwwMap,
+    LwwEntry,
+    OrSet,
+    Stamp,
+    MergeOutcome,
+    CRDT_SYNC_MESSAGE_TYPE,
+};
+
+// 分布式租约
+pub use lease::{
+    LeaseTable,
+    LeaseClaim,
+    LeaseAction,
+    Lease,
+    LeaseOutcome,
+    LEASE_MESSAGE_TYPE,
+    DEFAULT_LEASE_TTL,
+    DEFAULT_CONTENTION_WINDOW,
+};
+
+// 差分隐私统计
+pub use private_stats::{
+    PrivacyConfig,
+    StatsReport,
+    StatsAggregator,
+    AggregateStats,
+    laplace_noise,
+    STATS_REPORT_MESSAGE_TYPE,
+    DEFAULT_MIN_CONTRIBUTORS,
+};
+
+// 定时任务调度
+pub use job_scheduler::{
+    JobScheduler,
+    JobInfo,
+    JobFn,
+    Schedule,
+    CronSchedule,
+};
+
+// 密钥透明日志
+pub use transparency_log::{
+    TransparencyLog,
+    TransparencyEvent,
+    TransparencyMonitor,
+    TransparencyPolicy,
+    LogEntry,
+    SignedTreeHead,
+    InclusionProof,
+    RotationEvidence,
+    SubmitReceipt,
+    Equivocation,
+};
+
+// 审计日志
+pub use audit_log::{
+    AuditLog,
+    AuditEvent,
+    AuditRecord,
+    AuditQuery,
+    AuditAnchor,
 };
 
 
@@ -250,6 +1356,7 @@ pub use iroh_node::{
 };
 
 // Iroh P2P通信器
+#[cfg(feature = "iroh")]
 pub use iroh_communicator::{
     IrohCommunicator,
     IrohMessage,
@@ -258,6 +1365,8 @@ pub use iroh_communicator::{
     IrohConnection,
 };
 
+} // native_only!
+
 // ============ 常用类型重导出 ============
 pub use serde::{Deserialize, Serialize};
 pub use anyhow::Result;
@@ -69,6 +69,18 @@ impl LibP2PIdentity {
         })
     }
     
+    /// 从DID的Ed25519密钥派生（PeerID与DID使用同一把密钥，可由did:key直接计算PeerID）
+    pub fn from_did_keypair(keypair: &crate::key_manager::KeyPair) -> Result<Self> {
+        let keypair = Keypair::ed25519_from_bytes(keypair.private_key)
+            .context("无法从DID密钥派生libp2p密钥")?;
+        let peer_id = PeerId::from(keypair.public());
+        
+        Ok(Self {
+            keypair,
+            peer_id,
+        })
+    }
+    
     /// 从文件加载
     pub fn from_file(path: &PathBuf) -> Result<Self> {
         let content = std::fs::read_to_string(path)
@@ -11,7 +11,9 @@ use libp2p::{
 };
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
+use crate::connection_manager::ConnectionManager;
 use crate::libp2p_identity::LibP2PIdentity;
+use crate::error::{DiapError, DiapResult};
 
 /// libp2p节点信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +38,9 @@ pub struct LibP2PNode {
     
     /// 当前监听的地址
     listen_addrs: Vec<Multiaddr>,
+    
+    /// 对端连接管理
+    connection_manager: ConnectionManager,
 }
 
 impl LibP2PNode {
@@ -48,13 +53,24 @@ impl LibP2PNode {
         Ok(Self {
             peer_id: identity.peer_id().clone(),
             listen_addrs: Vec::new(),
+            connection_manager: ConnectionManager::new(),
         })
     }
     
+    /// 按SDK网络配置创建节点（监听地址）
+    pub fn from_config(identity: &LibP2PIdentity, config: &crate::config_manager::NetworkConfig) -> Result<Self> {
+        let mut node = Self::new(identity)?;
+        for addr in &config.listen_addrs {
+            node.add_listen_addr(addr)?;
+        }
+        Ok(node)
+    }
+    
     /// 添加监听地址
-    pub fn add_listen_addr(&mut self, addr: &str) -> Result<()> {
+    pub fn add_listen_addr(&mut self, addr: &str) -> DiapResult<()> {
         let multiaddr = Multiaddr::from_str(addr)
-            .with_context(|| format!("无效的多地址: {}", addr))?;
+            .with_context(|| format!("无效的多地址: {}", addr))
+            .map_err(DiapError::from_p2p)?;
         
         self.listen_addrs.push(multiaddr);
         log::info!("添加监听地址: {}", addr);
@@ -76,7 +92,7 @@ impl LibP2PNode {
         NodeInfo {
             peer_id: self.peer_id.to_base58(),
             multiaddrs,
-            protocols: vec!["/diap/1.0.0".to_string()],
+            protocols: vec![crate::constants::network_params().protocol()],
             updated_at: chrono::Utc::now().to_rfc3339(),
         }
     }
@@ -85,6 +101,11 @@ impl LibP2PNode {
     pub fn peer_id(&self) -> &PeerId {
         &self.peer_id
     }
+    
+    /// 对端连接管理器（网络事件应转发给它）
+    pub fn connection_manager(&self) -> &ConnectionManager {
+        &self.connection_manager
+    }
 }
 
 // 注意：完整的libp2p Swarm实现需要定义NetworkBehaviour
@@ -0,0 +1,434 @@
+// DIAP Rust SDK - 消息归档模块
+// 按主题保留策略归档消息，到期自动删除，并跟踪接收方的删除确认以生成合规报告
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::clock::{SharedClock, system_clock};
+use crate::pubsub_authenticator::AuthenticatedMessage;
+
+/// 删除确认消息类型标识（PubSubMessageType::Custom）
+pub const DELETION_ACK_MESSAGE_TYPE: &str = "deletion_ack";
+
+/// 主题保留策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// 归档消息保留时长（小时）
+    pub retention_hours: u64,
+
+    /// 接收方删除后是否需要发送删除确认
+    pub require_deletion_ack: bool,
+}
+
+/// 删除确认（接收方删除到期消息后发回给发送方）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletionAck {
+    /// 被删除的消息ID
+    pub message_id: String,
+
+    /// 主题
+    pub topic: String,
+
+    /// 删除时间
+    pub deleted_at: u64,
+}
+
+impl DeletionAck {
+    /// 序列化为消息内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化删除确认失败")
+    }
+
+    /// 从消息内容解析
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).context("解析删除确认失败")
+    }
+}
+
+/// 归档的消息
+#[derive(Debug, Clone)]
+pub struct ArchivedMessage {
+    /// 原始消息
+    pub message: AuthenticatedMessage,
+
+    /// 归档时间
+    pub archived_at: u64,
+
+    /// 到期时间（无保留策略时为空）
+    pub expires_at: Option<u64>,
+
+    /// 是否为本地发出的消息
+    pub outgoing: bool,
+}
+
+/// 已删除的到期消息
+#[derive(Debug, Clone)]
+pub struct ExpiredMessage {
+    /// 消息ID
+    pub message_id: String,
+
+    /// 主题
+    pub topic: String,
+
+    /// 发送者DID
+    pub from_did: String,
+
+    /// 是否需要向发送者发送删除确认
+    pub ack_required: bool,
+}
+
+/// 单个主题的合规情况
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TopicRetentionReport {
+    /// 主题
+    pub topic: String,
+
+    /// 保留时长（小时）
+    pub retention_hours: u64,
+
+    /// 当前归档消息数
+    pub archived_messages: usize,
+
+    /// 已到期但尚未删除的消息数
+    pub overdue_messages: usize,
+
+    /// 已按策略删除的消息数
+    pub deleted_messages: u64,
+
+    /// 等待接收方删除确认的已发消息数
+    pub pending_acks: usize,
+
+    /// 已收到的删除确认数
+    pub acks_received: u64,
+}
+
+/// 保留策略合规报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceReport {
+    /// 生成时间
+    pub generated_at: u64,
+
+    /// 各主题情况
+    pub topics: Vec<TopicRetentionReport>,
+}
+
+impl ComplianceReport {
+    /// 是否完全合规（没有逾期消息）
+    pub fn is_compliant(&self) -> bool {
+        self.topics.iter().all(|t| t.overdue_messages == 0)
+    }
+}
+
+/// 消息归档
+#[derive(Clone)]
+pub struct MessageArchive {
+    /// 消息ID -> 归档消息
+    messages: Arc<DashMap<String, ArchivedMessage>>,
+
+    /// 主题 -> 保留策略
+    policies: Arc<DashMap<String, RetentionPolicy>>,
+
+    /// 已发消息ID -> 已确认删除的接收方DID
+    acks: Arc<DashMap<String, HashSet<String>>>,
+
+    /// 要求删除确认的已发消息ID -> (主题, 接收方DID)，消息到期删除后仍用于校验确认者
+    ack_expected: Arc<DashMap<String, (String, Option<String>)>>,
+
+    /// 主题 -> 已删除消息数
+    deleted_counts: Arc<DashMap<String, u64>>,
+
+    /// 主题 -> 已收到确认数
+    ack_counts: Arc<DashMap<String, u64>>,
+
+    /// 时间源
+    clock: SharedClock,
+}
+
+impl MessageArchive {
+    /// 创建空的消息归档
+    pub fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+
+    /// 使用指定时间源创建消息归档
+    pub fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            messages: Arc::new(DashMap::new()),
+            policies: Arc::new(DashMap::new()),
+            acks: Arc::new(DashMap::new()),
+            ack_expected: Arc::new(DashMap::new()),
+            deleted_counts: Arc::new(DashMap::new()),
+            ack_counts: Arc::new(DashMap::new()),
+            clock,
+        }
+    }
+
+    /// 设置主题保留策略（None表示永久保留）
+    pub fn set_policy(&self, topic: &str, policy: Option<RetentionPolicy>) {
+        match policy {
+            Some(policy) => {
+                log::info!("🗑️ 主题 {} 消息保留 {} 小时", topic, policy.retention_hours);
+                self.policies.insert(topic.to_string(), policy);
+            }
+            None => {
+                self.policies.remove(topic);
+            }
+        }
+    }
+
+    /// 获取主题保留策略
+    pub fn policy(&self, topic: &str) -> Option<RetentionPolicy> {
+        self.policies.get(topic).map(|p| p.clone())
+    }
+
+    /// 归档消息
+    pub fn archive(&self, message: &AuthenticatedMessage, outgoing: bool) {
+        let now = self.clock.now_secs();
+        let policy = self.policy(&message.topic);
+        let expires_at = policy.as_ref().map(|p| now + p.retention_hours * 3600);
+        if outgoing && policy.is_some_and(|p| p.require_deletion_ack) {
+            self.ack_expected.insert(
+                message.message_id.clone(),
+                (message.topic.clone(), message.to_did.clone()),
+            );
+        }
+
+        self.messages.insert(message.message_id.clone(), ArchivedMessage {
+            message: message.clone(),
+            archived_at: now,
+            expires_at,
+            outgoing,
+        });
+    }
+
+    /// 获取归档消息
+    pub fn get(&self, message_id: &str) -> Option<ArchivedMessage> {
+        self.messages.get(message_id).map(|m| m.clone())
+    }
+
+    /// 归档消息数
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// 归档是否为空
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// 获取与指定DID相关（发送或接收）的归档消息
+    pub fn messages_for_did(&self, did: &str) -> Vec<ArchivedMessage> {
+        self.messages
+            .iter()
+            .filter(|entry| Self::involves(&entry.message, did))
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// 删除与指定DID相关的归档消息及其删除确认，返回删除的消息数
+    pub fn erase_did(&self, did: &str) -> usize {
+        let before = self.messages.len();
+        self.messages.retain(|_, archived| !Self::involves(&archived.message, did));
+        for mut entry in self.acks.iter_mut() {
+            entry.value_mut().remove(did);
+        }
+        self.ack_expected.retain(|_, (_, to_did)| to_did.as_deref() != Some(did));
+        before.saturating_sub(self.messages.len())
+    }
+
+    fn involves(message: &AuthenticatedMessage, did: &str) -> bool {
+        message.from_did == did || message.to_did.as_deref() == Some(did)
+    }
+
+    /// 删除所有到期消息
+    pub fn purge_expired(&self) -> Vec<ExpiredMessage> {
+        self.purge_expired_at(self.clock.now_secs())
+    }
+
+    /// 删除在指定时间点已到期的消息
+    pub fn purge_expired_at(&self, now: u64) -> Vec<ExpiredMessage> {
+        let expired_ids: Vec<String> = self.messages
+            .iter()
+            .filter(|entry| entry.expires_at.is_some_and(|t| t <= now))
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        let mut expired = Vec::with_capacity(expired_ids.len());
+        for message_id in expired_ids {
+            let Some((_, archived)) = self.messages.remove(&message_id) else { continue };
+            let topic = archived.message.topic.clone();
+            *self.deleted_counts.entry(topic.clone()).or_insert(0) += 1;
+
+            // 只有收到的消息才需要向发送方确认
+            let ack_required = !archived.outgoing
+                && self.policy(&topic).is_some_and(|p| p.require_deletion_ack);
+
+            expired.push(ExpiredMessage {
+                message_id,
+                topic,
+                from_did: archived.message.from_did,
+                ack_required,
+            });
+        }
+
+        if !expired.is_empty() {
+            log::info!("🗑️ 删除 {} 条到期消息", expired.len());
+        }
+        expired
+    }
+
+    /// 记录收到的删除确认：只接受本地发出且要求确认的消息，确认者须为原接收方（广播消息不限），
+    /// 返回是否为新的确认
+    pub fn record_ack(&self, from_did: &str, ack: &DeletionAck) -> Result<bool> {
+        let Some((topic, to_did)) = self.ack_expected.get(&ack.message_id).map(|e| e.clone()) else {
+            return Ok(false);
+        };
+        if to_did.is_some_and(|did| did != from_did) {
+            anyhow::bail!("删除确认者不是消息接收方: {}", from_did);
+        }
+
+        let newly_acked = self.acks
+            .entry(ack.message_id.clone())
+            .or_default()
+            .insert(from_did.to_string());
+
+        if newly_acked {
+            *self.ack_counts.entry(topic).or_insert(0) += 1;
+            log::debug!("收到删除确认: {} 来自 {}", ack.message_id, from_did);
+        }
+        Ok(newly_acked)
+    }
+
+    /// 生成合规报告
+    pub fn compliance_report(&self) -> ComplianceReport {
+        let now = self.clock.now_secs();
+        let mut topics: HashMap<String, TopicRetentionReport> = HashMap::new();
+
+        for entry in self.policies.iter() {
+            topics.insert(entry.key().clone(), TopicRetentionReport {
+                topic: entry.key().clone(),
+                retention_hours: entry.retention_hours,
+                deleted_messages: self.deleted_counts.get(entry.key()).map(|c| *c).unwrap_or(0),
+                acks_received: self.ack_counts.get(entry.key()).map(|c| *c).unwrap_or(0),
+                ..Default::default()
+            });
+        }
+
+        for entry in self.messages.iter() {
+            let Some(report) = topics.get_mut(&entry.message.topic) else { continue };
+            report.archived_messages += 1;
+            if entry.expires_at.is_some_and(|t| t <= now) {
+                report.overdue_messages += 1;
+            }
+        }
+
+        // 已发出且要求确认、但尚未收到任何确认的消息
+        for entry in self.policies.iter().filter(|p| p.require_deletion_ack) {
+            let topic = entry.key();
+            let Some(report) = topics.get_mut(topic) else { continue };
+            report.pending_acks = self.messages
+                .iter()
+                .filter(|m| m.outgoing && &m.message.topic == topic && !self.acks.contains_key(m.key()))
+                .count();
+        }
+
+        let mut topics: Vec<TopicRetentionReport> = topics.into_values().collect();
+        topics.sort_by(|a, b| a.topic.cmp(&b.topic));
+
+        ComplianceReport {
+            generated_at: now,
+            topics,
+        }
+    }
+
+}
+
+impl Default for MessageArchive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::pubsub_authenticator::PubSubMessageType;
+
+    fn message(id: &str, topic: &str) -> AuthenticatedMessage {
+        AuthenticatedMessage {
+            message_id: id.to_string(),
+            message_type: PubSubMessageType::Custom("test".to_string()),
+            from_did: "did:key:sender".to_string(),
+            to_did: None,
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: topic.to_string(),
+            content: b"hello".to_vec(),
+            nonce: String::new(),
+            zkp_proof: Vec::new(),
+            signature: Vec::new(),
+            timestamp: 0,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_purge_expired() {
+        let clock = MockClock::new(1_000_000);
+        let archive = MessageArchive::with_clock(Arc::new(clock.clone()));
+        archive.set_policy("ephemeral", Some(RetentionPolicy {
+            retention_hours: 1,
+            require_deletion_ack: true,
+        }));
+
+        archive.archive(&message("m1", "ephemeral"), false);
+        archive.archive(&message("m2", "permanent"), false);
+
+        // 未到期
+        assert!(archive.purge_expired().is_empty());
+
+        clock.advance(std::time::Duration::from_secs(3600));
+        let expired = archive.purge_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].message_id, "m1");
+        assert!(expired[0].ack_required);
+        assert!(archive.get("m2").is_some());
+    }
+
+    #[test]
+    fn test_compliance_report() {
+        let archive = MessageArchive::new();
+        archive.set_policy("ephemeral", Some(RetentionPolicy {
+            retention_hours: 24,
+            require_deletion_ack: true,
+        }));
+
+        let mut direct = message("out1", "ephemeral");
+        direct.to_did = Some("did:key:bob".to_string());
+        archive.archive(&direct, true);
+        archive.archive(&message("out2", "ephemeral"), true);
+
+        let ack = |message_id: &str| DeletionAck {
+            message_id: message_id.to_string(),
+            topic: "ephemeral".to_string(),
+            deleted_at: 0,
+        };
+        assert!(archive.record_ack("did:key:mallory", &ack("out1")).is_err(), "非接收方的确认被拒绝");
+        assert!(!archive.record_ack("did:key:bob", &ack("unknown")).unwrap());
+        assert!(archive.record_ack("did:key:bob", &ack("out1")).unwrap());
+        assert!(!archive.record_ack("did:key:bob", &ack("out1")).unwrap(), "重复确认不重复计数");
+
+        let report = archive.compliance_report();
+        assert!(report.is_compliant());
+        assert_eq!(report.topics.len(), 1);
+        assert_eq!(report.topics[0].archived_messages, 2);
+        assert_eq!(report.topics[0].pending_acks, 1);
+        assert_eq!(report.topics[0].acks_received, 1);
+    }
+}
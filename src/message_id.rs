@@ -0,0 +1,101 @@
+// DIAP Rust SDK - 消息ID生成
+// 统一生成消息/请求ID：默认使用按时间排序的UUIDv7，便于归档索引和日志关联；可切换回旧版随机UUIDv4
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+use uuid::Uuid;
+
+/// ID生成策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdStrategy {
+    /// 按时间排序的UUIDv7（默认）
+    #[default]
+    UuidV7,
+
+    /// 旧版随机UUIDv4
+    LegacyV4,
+}
+
+impl IdStrategy {
+    fn to_u8(self) -> u8 {
+        match self {
+            IdStrategy::UuidV7 => 0,
+            IdStrategy::LegacyV4 => 1,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => IdStrategy::LegacyV4,
+            _ => IdStrategy::UuidV7,
+        }
+    }
+}
+
+/// 进程级ID生成策略
+static ID_STRATEGY: AtomicU8 = AtomicU8::new(0);
+
+/// 设置进程级ID生成策略
+pub fn set_id_strategy(strategy: IdStrategy) {
+    ID_STRATEGY.store(strategy.to_u8(), Ordering::Relaxed);
+    log::debug!("消息ID策略: {:?}", strategy);
+}
+
+/// 当前ID生成策略
+pub fn id_strategy() -> IdStrategy {
+    IdStrategy::from_u8(ID_STRATEGY.load(Ordering::Relaxed))
+}
+
+/// 按当前策略生成新的消息/请求ID
+pub fn new_message_id() -> String {
+    new_id_with(id_strategy())
+}
+
+/// 按指定策略生成ID
+pub fn new_id_with(strategy: IdStrategy) -> String {
+    match strategy {
+        IdStrategy::UuidV7 => Uuid::now_v7().to_string(),
+        IdStrategy::LegacyV4 => Uuid::new_v4().to_string(),
+    }
+}
+
+/// 从UUIDv7 ID中提取创建时间（Unix毫秒）；旧版ID或无法解析时返回None
+pub fn id_timestamp_millis(id: &str) -> Option<u64> {
+    let uuid = Uuid::parse_str(id).ok()?;
+    let (secs, nanos) = uuid.get_timestamp()?.to_unix();
+    Some(secs * 1000 + (nanos / 1_000_000) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_v7_ids_sort_by_time() {
+        let ids: Vec<String> = (0..50)
+            .map(|_| {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                new_id_with(IdStrategy::UuidV7)
+            })
+            .collect();
+
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let created = id_timestamp_millis(&ids[0]).unwrap();
+        assert!(now.abs_diff(created) < 5_000);
+    }
+
+    #[test]
+    fn test_legacy_v4_ids() {
+        let id = new_id_with(IdStrategy::LegacyV4);
+        assert_eq!(Uuid::parse_str(&id).unwrap().get_version_num(), 4);
+        assert!(id_timestamp_millis(&id).is_none());
+
+        let strategy: IdStrategy = serde_json::from_str("\"legacy_v4\"").unwrap();
+        assert_eq!(strategy, IdStrategy::LegacyV4);
+    }
+}
@@ -0,0 +1,270 @@
+// DIAP Rust SDK - 消息状态跟踪（送达回执与已读回执）
+// 接收方收到请求后自动回送签名的送达回执，应用处理完后可再发已读回执；
+// 发送方按消息ID跟踪状态，并通过广播流把状态变化推送给订阅者
+
+use anyhow::Result;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::key_manager::{SignedEnvelope, Signer};
+
+/// 状态更新广播通道容量
+const STATUS_CHANNEL_CAPACITY: usize = 256;
+
+/// 回执类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AckKind {
+    /// 已送达（传输层收到并通过签名验证后自动发送）
+    Delivered,
+    /// 已读/已处理（由应用显式发送）
+    Read,
+}
+
+/// 签名的消息回执
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageAck {
+    /// 被确认的消息ID
+    pub message_id: String,
+
+    /// 回执类型
+    pub kind: AckKind,
+
+    /// 确认者DID（原消息的接收方）
+    pub acker_did: String,
+
+    /// 原消息发送者DID
+    pub sender_did: String,
+
+    /// 确认时间
+    pub acked_at: u64,
+
+    /// 确认者签名（base64）
+    pub signature: String,
+}
+
+impl MessageAck {
+    /// 创建并签名回执
+    pub fn new(signer: &dyn Signer, message_id: &str, sender_did: &str, kind: AckKind, acked_at: u64) -> Result<Self> {
+        Self {
+            message_id: message_id.to_string(),
+            kind,
+            acker_did: signer.did(),
+            sender_did: sender_did.to_string(),
+            acked_at,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+}
+
+impl SignedEnvelope for MessageAck {
+    const KIND: &'static str = "消息回执";
+
+    fn signer_did(&self) -> &str {
+        &self.acker_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 发送方视角的消息状态（只会前进：已发送 -> 已送达 -> 已读）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageStatus {
+    /// 已发出，尚未收到回执
+    Sent,
+    /// 对方已收到
+    Delivered { at: u64 },
+    /// 对方已读/已处理
+    Read { at: u64 },
+    /// 发送失败
+    Failed(String),
+}
+
+impl MessageStatus {
+    fn rank(&self) -> u8 {
+        match self {
+            MessageStatus::Sent => 0,
+            MessageStatus::Delivered { .. } => 1,
+            MessageStatus::Read { .. } | MessageStatus::Failed(_) => 2,
+        }
+    }
+}
+
+/// 状态变化事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageStatusUpdate {
+    /// 消息ID
+    pub message_id: String,
+
+    /// 接收方DID（发送时未指定则为None）
+    pub peer_did: Option<String>,
+
+    /// 新状态
+    pub status: MessageStatus,
+}
+
+#[derive(Debug, Clone)]
+struct TrackedMessage {
+    peer_did: Option<String>,
+    status: MessageStatus,
+}
+
+/// 消息状态跟踪器（可克隆，克隆体共享状态）
+#[derive(Clone)]
+pub struct MessageStatusTracker {
+    messages: Arc<DashMap<String, TrackedMessage>>,
+    updates: broadcast::Sender<MessageStatusUpdate>,
+}
+
+impl MessageStatusTracker {
+    /// 创建跟踪器
+    pub fn new() -> Self {
+        Self {
+            messages: Arc::new(DashMap::new()),
+            updates: broadcast::channel(STATUS_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// 订阅状态变化
+    pub fn subscribe(&self) -> broadcast::Receiver<MessageStatusUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// 开始跟踪已发出的消息（peer_did为None时接受任意确认者的回执）
+    pub fn track(&self, message_id: &str, peer_did: Option<&str>) {
+        self.messages.insert(message_id.to_string(), TrackedMessage {
+            peer_did: peer_did.map(str::to_string),
+            status: MessageStatus::Sent,
+        });
+        self.publish(message_id, peer_did.map(str::to_string), MessageStatus::Sent);
+    }
+
+    /// 标记发送失败
+    pub fn mark_failed(&self, message_id: &str, reason: &str) {
+        self.advance(message_id, MessageStatus::Failed(reason.to_string()));
+    }
+
+    /// 记录收到的回执：验证签名且确认者为原接收方时推进状态，返回状态是否变化
+    pub fn record_ack(&self, ack: &MessageAck) -> Result<bool> {
+        if !ack.verify()? {
+            anyhow::bail!("消息回执签名无效: {}", ack.message_id);
+        }
+        let Some(peer_did) = self.messages.get(&ack.message_id).map(|tracked| tracked.peer_did.clone()) else {
+            return Ok(false);
+        };
+        if peer_did.is_some_and(|did| did != ack.acker_did) {
+            anyhow::bail!("回执确认者不是消息接收方: {}", ack.acker_did);
+        }
+
+        let status = match ack.kind {
+            AckKind::Delivered => MessageStatus::Delivered { at: ack.acked_at },
+            AckKind::Read => MessageStatus::Read { at: ack.acked_at },
+        };
+        Ok(self.advance(&ack.message_id, status))
+    }
+
+    /// 当前状态
+    pub fn status(&self, message_id: &str) -> Option<MessageStatus> {
+        self.messages.get(message_id).map(|tracked| tracked.status.clone())
+    }
+
+    /// 停止跟踪（应用不再关心该消息时调用，避免表无限增长）
+    pub fn forget(&self, message_id: &str) -> Option<MessageStatus> {
+        self.messages.remove(message_id).map(|(_, tracked)| tracked.status)
+    }
+
+    /// 跟踪中的消息数
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// 是否没有跟踪中的消息
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// 状态只前进不后退（已读后迟到的送达回执被忽略）
+    fn advance(&self, message_id: &str, status: MessageStatus) -> bool {
+        let peer_did = {
+            let Some(mut tracked) = self.messages.get_mut(message_id) else {
+                return false;
+            };
+            if status.rank() <= tracked.status.rank() {
+                return false;
+            }
+            tracked.status = status.clone();
+            tracked.peer_did.clone()
+        };
+        self.publish(message_id, peer_did, status);
+        true
+    }
+
+    fn publish(&self, message_id: &str, peer_did: Option<String>, status: MessageStatus) {
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.updates.send(MessageStatusUpdate {
+            message_id: message_id.to_string(),
+            peer_did,
+            status,
+        });
+    }
+}
+
+impl Default for MessageStatusTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+
+    #[test]
+    fn test_status_progression() {
+        let sender = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+
+        let tracker = MessageStatusTracker::new();
+        let mut updates = tracker.subscribe();
+        tracker.track("req-1", Some(&bob.did));
+        assert_eq!(updates.try_recv().unwrap().status, MessageStatus::Sent);
+
+        let spoofed = MessageAck::new(&mallory, "req-1", &sender.did, AckKind::Read, 5).unwrap();
+        assert!(tracker.record_ack(&spoofed).is_err(), "非接收方的回执被拒绝");
+
+        let mut forged = MessageAck::new(&bob, "req-1", &sender.did, AckKind::Delivered, 5).unwrap();
+        forged.kind = AckKind::Read;
+        assert!(tracker.record_ack(&forged).is_err(), "篡改后签名无效");
+
+        let delivered = MessageAck::new(&bob, "req-1", &sender.did, AckKind::Delivered, 5).unwrap();
+        let read = MessageAck::new(&bob, "req-1", &sender.did, AckKind::Read, 9).unwrap();
+        let decoded = MessageAck::from_bytes(&read.to_bytes().unwrap()).unwrap();
+        assert!(tracker.record_ack(&decoded).unwrap());
+        assert!(!tracker.record_ack(&delivered).unwrap(), "迟到的送达回执不会让状态后退");
+        assert_eq!(tracker.status("req-1"), Some(MessageStatus::Read { at: 9 }));
+
+        let update = updates.try_recv().unwrap();
+        assert_eq!(update.peer_did.as_deref(), Some(bob.did.as_str()));
+        assert_eq!(update.status, MessageStatus::Read { at: 9 });
+        assert!(updates.try_recv().is_err());
+
+        // 未跟踪的消息
+        let unknown = MessageAck::new(&bob, "req-2", &sender.did, AckKind::Delivered, 5).unwrap();
+        assert!(!tracker.record_ack(&unknown).unwrap());
+
+        tracker.track("req-3", None);
+        tracker.mark_failed("req-3", "连接失败");
+        assert_eq!(tracker.forget("req-3"), Some(MessageStatus::Failed("连接失败".to_string())));
+        assert_eq!(tracker.len(), 1);
+    }
+}
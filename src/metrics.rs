@@ -0,0 +1,292 @@
+// DIAP Rust SDK - 运行指标
+// 证明生成/验证延迟、IPFS上传/获取耗时、Pubsub消息数、nonce重放拒绝数和对端数，
+// 以Prometheus文本格式（REST接口 /metrics）或 MetricsSnapshot 导出
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 延迟直方图的桶上界（秒）
+pub const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Prometheus文本格式的Content-Type
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 单调递增计数器
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    /// 加一
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// 增加n
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// 当前值
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 可增可减的测量值
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    /// 设置
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    /// 当前值
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 延迟直方图（固定桶，见 LATENCY_BUCKETS）
+#[derive(Debug)]
+pub struct Histogram {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: LATENCY_BUCKETS.iter().map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    /// 记录一次耗时
+    pub fn observe(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        // 桶只记录落入的第一个区间，导出时再累加
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// 从start到现在的耗时
+    pub fn observe_since(&self, start: Instant) {
+        self.observe(start.elapsed());
+    }
+
+    /// 快照
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = LATENCY_BUCKETS.iter().zip(&self.buckets)
+            .map(|(bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+        let count = self.count.load(Ordering::Relaxed);
+        let sum_seconds = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        HistogramSnapshot { count, sum_seconds, buckets }
+    }
+}
+
+/// 直方图快照
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramSnapshot {
+    /// 观测次数
+    pub count: u64,
+
+    /// 总耗时（秒）
+    pub sum_seconds: f64,
+
+    /// （桶上界秒数, 累计次数）
+    pub buckets: Vec<(f64, u64)>,
+}
+
+impl HistogramSnapshot {
+    /// 平均耗时
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_secs_f64(self.sum_seconds / self.count as f64))
+    }
+
+    /// 分位数的上界估计（所在桶的上界；超出最大桶时为None）
+    pub fn quantile_upper_bound(&self, quantile: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (quantile.clamp(0.0, 1.0) * self.count as f64).ceil().max(1.0) as u64;
+        self.buckets.iter().find(|(_, cumulative)| *cumulative >= rank).map(|(bound, _)| *bound)
+    }
+}
+
+/// SDK运行指标
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// ZKP证明生成耗时
+    pub proof_generation: Histogram,
+
+    /// ZKP证明验证耗时
+    pub proof_verification: Histogram,
+
+    /// IPFS上传耗时（含回退到Pin服务）
+    pub ipfs_upload: Histogram,
+
+    /// IPFS获取耗时
+    pub ipfs_fetch: Histogram,
+
+    /// IPFS上传失败次数
+    pub ipfs_upload_failures: Counter,
+
+    /// IPFS获取失败次数
+    pub ipfs_fetch_failures: Counter,
+
+    /// 创建（待发布）的认证消息数
+    pub pubsub_published: Counter,
+
+    /// 验证通过的消息数
+    pub pubsub_verified: Counter,
+
+    /// 验证未通过的消息数
+    pub pubsub_rejected: Counter,
+
+    /// 因nonce重放被拒绝的次数
+    pub nonce_replays: Counter,
+
+    /// 已连接对端数
+    pub peers_connected: Gauge,
+
+    /// 已知对端数（含断开待重连）
+    pub peers_known: Gauge,
+}
+
+static GLOBAL: OnceLock<Metrics> = OnceLock::new();
+
+/// 进程级指标（SDK内部的埋点都记录到这里）
+pub fn global() -> &'static Metrics {
+    GLOBAL.get_or_init(Metrics::default)
+}
+
+impl Metrics {
+    /// 快照
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            proof_generation: self.proof_generation.snapshot(),
+            proof_verification: self.proof_verification.snapshot(),
+            ipfs_upload: self.ipfs_upload.snapshot(),
+            ipfs_fetch: self.ipfs_fetch.snapshot(),
+            ipfs_upload_failures: self.ipfs_upload_failures.get(),
+            ipfs_fetch_failures: self.ipfs_fetch_failures.get(),
+            pubsub_published: self.pubsub_published.get(),
+            pubsub_verified: self.pubsub_verified.get(),
+            pubsub_rejected: self.pubsub_rejected.get(),
+            nonce_replays: self.nonce_replays.get(),
+            peers_connected: self.peers_connected.get(),
+            peers_known: self.peers_known.get(),
+        }
+    }
+
+    /// Prometheus文本格式
+    pub fn render_prometheus(&self) -> String {
+        self.snapshot().to_prometheus()
+    }
+}
+
+/// 指标快照
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub proof_generation: HistogramSnapshot,
+    pub proof_verification: HistogramSnapshot,
+    pub ipfs_upload: HistogramSnapshot,
+    pub ipfs_fetch: HistogramSnapshot,
+    pub ipfs_upload_failures: u64,
+    pub ipfs_fetch_failures: u64,
+    pub pubsub_published: u64,
+    pub pubsub_verified: u64,
+    pub pubsub_rejected: u64,
+    pub nonce_replays: u64,
+    pub peers_connected: i64,
+    pub peers_known: i64,
+}
+
+impl MetricsSnapshot {
+    /// Prometheus文本格式（指标名以 diap_ 开头）
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let histograms = [
+            ("diap_proof_generation_seconds", "ZKP proof generation latency", &self.proof_generation),
+            ("diap_proof_verification_seconds", "ZKP proof verification latency", &self.proof_verification),
+            ("diap_ipfs_upload_seconds", "IPFS upload duration", &self.ipfs_upload),
+            ("diap_ipfs_fetch_seconds", "IPFS fetch duration", &self.ipfs_fetch),
+        ];
+        for (name, help, histogram) in histograms {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} histogram", name, help, name);
+            for (bound, cumulative) in &histogram.buckets {
+                let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+            }
+            let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+            let _ = writeln!(out, "{}_sum {}\n{}_count {}", name, histogram.sum_seconds, name, histogram.count);
+        }
+
+        let counters = [
+            ("diap_ipfs_upload_failures_total", "Failed IPFS uploads", self.ipfs_upload_failures),
+            ("diap_ipfs_fetch_failures_total", "Failed IPFS fetches", self.ipfs_fetch_failures),
+            ("diap_pubsub_messages_published_total", "Authenticated messages created for publishing", self.pubsub_published),
+            ("diap_pubsub_messages_verified_total", "Received messages that passed verification", self.pubsub_verified),
+            ("diap_pubsub_messages_rejected_total", "Received messages that failed verification", self.pubsub_rejected),
+            ("diap_nonce_replays_total", "Messages rejected as nonce replays", self.nonce_replays),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+        }
+
+        let gauges = [
+            ("diap_peers_connected", "Connected peers", self.peers_connected),
+            ("diap_peers_known", "Known peers including those awaiting reconnect", self.peers_known),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge\n{} {}", name, help, name, name, value);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_and_prometheus_output() {
+        let metrics = Metrics::default();
+        metrics.proof_generation.observe(Duration::from_millis(3));
+        metrics.proof_generation.observe(Duration::from_millis(40));
+        metrics.proof_generation.observe(Duration::from_secs(60));
+        metrics.nonce_replays.inc();
+        metrics.peers_connected.set(2);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.proof_generation.count, 3);
+        assert_eq!(snapshot.proof_generation.buckets[0], (0.005, 1));
+        assert_eq!(snapshot.proof_generation.buckets.last().unwrap().1, 2);
+        assert_eq!(snapshot.proof_generation.quantile_upper_bound(0.5), Some(0.05));
+        assert_eq!(snapshot.proof_generation.quantile_upper_bound(1.0), None);
+        assert!(snapshot.ipfs_fetch.mean().is_none());
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("diap_proof_generation_seconds_bucket{le=\"0.05\"} 2\n"));
+        assert!(text.contains("diap_proof_generation_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("diap_proof_generation_seconds_count 3\n"));
+        assert!(text.contains("diap_nonce_replays_total 1\n"));
+        assert!(text.contains("# TYPE diap_peers_connected gauge\ndiap_peers_connected 2\n"));
+    }
+}
@@ -0,0 +1,491 @@
+// DIAP Rust SDK - Noir电路编译流水线
+// 首次使用时确保电路已编译：优先复用本地产物，源码变化时调用nargo重新编译，
+// 没有nargo时使用调用方打包的ACIR产物；所有产物都按清单（circuit_manifest.json）校验哈希，
+// 工具链版本不匹配时给出可操作的错误提示，省去手动准备电路的步骤
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// 电路清单文件名（位于电路目录下，与Nargo.toml同级）
+pub const CIRCUIT_MANIFEST_FILE: &str = "circuit_manifest.json";
+
+/// 电路清单：固定工具链版本和源码/产物哈希
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitManifest {
+    /// 电路名称（Nargo.toml中的name）
+    pub circuit_name: String,
+
+    /// 编译产物使用的Noir版本（不含commit后缀）
+    pub noir_version: String,
+
+    /// Nargo.toml和所有.nr源码的SHA-256
+    pub source_sha256: String,
+
+    /// ACIR产物（target/<name>.json）中abi和bytecode的SHA-256
+    pub acir_sha256: String,
+}
+
+impl CircuitManifest {
+    /// 从电路目录读取清单（不存在时返回None）
+    pub fn load(circuit_dir: &Path) -> Result<Option<Self>> {
+        let path = circuit_dir.join(CIRCUIT_MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = std::fs::read(&path)
+            .with_context(|| format!("读取电路清单失败: {}", path.display()))?;
+        let manifest = serde_json::from_slice(&data).context("解析电路清单失败")?;
+        Ok(Some(manifest))
+    }
+
+    /// 写入电路清单
+    pub fn save(&self, circuit_dir: &Path) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self).context("序列化电路清单失败")?;
+        std::fs::write(circuit_dir.join(CIRCUIT_MANIFEST_FILE), data).context("写入电路清单失败")
+    }
+}
+
+/// 电路产物来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitSource {
+    /// 复用已有产物
+    Cached,
+    /// 本次调用nargo编译
+    Compiled,
+    /// 使用调用方打包的ACIR产物
+    Bundled,
+}
+
+/// 编译就绪的电路
+#[derive(Debug, Clone)]
+pub struct CompiledCircuit {
+    /// 电路名称
+    pub name: String,
+
+    /// ACIR产物路径
+    pub acir_path: PathBuf,
+
+    /// ACIR产物SHA-256
+    pub acir_sha256: String,
+
+    /// 编译产物使用的Noir版本
+    pub noir_version: String,
+
+    /// 产物来源
+    pub source: CircuitSource,
+}
+
+/// 电路准备失败的原因（每种都附带处理建议）
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CircuitBuildError {
+    /// 找不到nargo，且没有可用的打包产物
+    #[error("需要编译Noir电路但未找到nargo（{reason}）；请安装Noir工具链：noirup --version {required}，或通过with_bundled_artifact提供预编译的ACIR")]
+    NargoNotFound { reason: String, required: String },
+
+    /// nargo版本与清单不一致
+    #[error("nargo版本不匹配：电路要求 {required}，当前为 {found}；请运行 noirup --version {required}，或删除{manifest}后以当前版本重新编译")]
+    VersionMismatch { required: String, found: String, manifest: &'static str },
+
+    /// nargo compile失败
+    #[error("Noir电路编译失败：{stderr}")]
+    CompileFailed { stderr: String },
+
+    /// 产物哈希与清单不一致
+    #[error("电路产物哈希与清单不一致：{path}（期望 {expected}，实际 {actual}）；产物可能被篡改或由其他工具链生成，请删除target目录后重新编译")]
+    HashMismatch { path: String, expected: String, actual: String },
+}
+
+/// Noir电路编译流水线
+pub struct CircuitBuilder {
+    /// 电路目录（包含Nargo.toml）
+    circuit_dir: PathBuf,
+
+    /// nargo可执行文件
+    nargo: String,
+
+    /// 调用方打包的ACIR产物
+    bundled_artifact: Option<Vec<u8>>,
+}
+
+impl CircuitBuilder {
+    /// 创建编译流水线
+    pub fn new(circuit_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            circuit_dir: circuit_dir.into(),
+            nargo: "nargo".to_string(),
+            bundled_artifact: None,
+        }
+    }
+
+    /// 使用指定的nargo可执行文件
+    pub fn with_nargo(mut self, nargo: impl Into<String>) -> Self {
+        self.nargo = nargo.into();
+        self
+    }
+
+    /// 提供预编译的ACIR产物（例如通过include_bytes!打包），没有nargo时使用
+    pub fn with_bundled_artifact(mut self, acir: Vec<u8>) -> Self {
+        self.bundled_artifact = Some(acir);
+        self
+    }
+
+    /// 确保电路已编译并通过清单校验
+    pub async fn ensure_compiled(&self) -> Result<CompiledCircuit> {
+        let name = self.circuit_name()?;
+        let source_sha256 = self.source_hash()?;
+        let acir_path = self.circuit_dir.join("target").join(format!("{}.json", name));
+        let manifest = CircuitManifest::load(&self.circuit_dir)?;
+
+        // 1. 源码未变化且产物哈希一致时直接复用
+        if acir_path.exists() {
+            let acir = std::fs::read(&acir_path).context("读取电路产物失败")?;
+            let acir_sha256 = artifact_hash(&acir);
+            match &manifest {
+                Some(m) if m.source_sha256 == source_sha256 && m.acir_sha256 == acir_sha256 => {
+                    log::debug!("电路产物已是最新: {}", acir_path.display());
+                    return Ok(self.compiled(name, acir_path, m, CircuitSource::Cached));
+                }
+                None => {
+                    // 首次使用已有产物：记录清单，后续按清单校验
+                    let pinned = CircuitManifest {
+                        circuit_name: name.clone(),
+                        noir_version: artifact_noir_version(&acir).unwrap_or_default(),
+                        source_sha256,
+                        acir_sha256,
+                    };
+                    pinned.save(&self.circuit_dir)?;
+                    log::info!("📌 已记录电路清单: {}", self.circuit_dir.join(CIRCUIT_MANIFEST_FILE).display());
+                    return Ok(self.compiled(name, acir_path, &pinned, CircuitSource::Cached));
+                }
+                Some(_) => {
+                    log::info!("🔄 电路源码或产物已变化，需要重新编译 (产物 {})", &acir_sha256[..12]);
+                }
+            }
+        }
+
+        // 2. 调用nargo编译
+        let required = manifest.as_ref().map(|m| m.noir_version.clone()).unwrap_or_default();
+        match self.nargo_version().await {
+            Ok(found) => {
+                if !required.is_empty() && base_version(&found) != base_version(&required) {
+                    return Err(CircuitBuildError::VersionMismatch {
+                        required: base_version(&required).to_string(),
+                        found,
+                        manifest: CIRCUIT_MANIFEST_FILE,
+                    }.into());
+                }
+                self.compile(name, acir_path, source_sha256, manifest, found).await
+            }
+            Err(reason) => {
+                // 3. 没有nargo时使用打包的产物
+                if let Some(acir) = &self.bundled_artifact {
+                    return self.install_bundled(name, acir_path, acir, manifest);
+                }
+                Err(CircuitBuildError::NargoNotFound {
+                    reason,
+                    required: if required.is_empty() { "<版本>".to_string() } else { base_version(&required).to_string() },
+                }.into())
+            }
+        }
+    }
+
+    /// 查询nargo版本（`nargo version = x.y.z`）
+    pub async fn nargo_version(&self) -> std::result::Result<String, String> {
+        let output = tokio::process::Command::new(&self.nargo)
+            .arg("--version")
+            .output()
+            .await
+            .map_err(|e| format!("{}: {}", self.nargo, e))?;
+        if !output.status.success() {
+            return Err(format!("{} --version 退出码 {:?}", self.nargo, output.status.code()));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        parse_nargo_version(&stdout).ok_or_else(|| format!("无法解析nargo版本输出: {}", stdout.trim()))
+    }
+
+    async fn compile(
+        &self,
+        name: String,
+        acir_path: PathBuf,
+        source_sha256: String,
+        manifest: Option<CircuitManifest>,
+        noir_version: String,
+    ) -> Result<CompiledCircuit> {
+        log::info!("🔨 编译Noir电路: {} (nargo {})", self.circuit_dir.display(), noir_version);
+        let output = tokio::process::Command::new(&self.nargo)
+            .arg("compile")
+            .current_dir(&self.circuit_dir)
+            .output()
+            .await
+            .context("启动nargo失败")?;
+        if !output.status.success() {
+            return Err(CircuitBuildError::CompileFailed {
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            }.into());
+        }
+
+        let acir = std::fs::read(&acir_path)
+            .with_context(|| format!("nargo compile未生成产物: {}", acir_path.display()))?;
+        let acir_sha256 = artifact_hash(&acir);
+
+        // 源码未变化时，同版本工具链的编译结果应与清单一致
+        if let Some(m) = manifest.as_ref().filter(|m| m.source_sha256 == source_sha256) {
+            if m.acir_sha256 != acir_sha256 {
+                return Err(CircuitBuildError::HashMismatch {
+                    path: acir_path.display().to_string(),
+                    expected: m.acir_sha256.clone(),
+                    actual: acir_sha256,
+                }.into());
+            }
+        }
+
+        let pinned = CircuitManifest {
+            circuit_name: name.clone(),
+            noir_version: base_version(&noir_version).to_string(),
+            source_sha256,
+            acir_sha256,
+        };
+        pinned.save(&self.circuit_dir)?;
+        log::info!("✅ Noir电路编译完成: {}", acir_path.display());
+        Ok(self.compiled(name, acir_path, &pinned, CircuitSource::Compiled))
+    }
+
+    fn install_bundled(
+        &self,
+        name: String,
+        acir_path: PathBuf,
+        acir: &[u8],
+        manifest: Option<CircuitManifest>,
+    ) -> Result<CompiledCircuit> {
+        let acir_sha256 = artifact_hash(acir);
+        if let Some(m) = &manifest {
+            if m.acir_sha256 != acir_sha256 {
+                return Err(CircuitBuildError::HashMismatch {
+                    path: "<bundled>".to_string(),
+                    expected: m.acir_sha256.clone(),
+                    actual: acir_sha256,
+                }.into());
+            }
+        }
+
+        if let Some(parent) = acir_path.parent() {
+            std::fs::create_dir_all(parent).context("创建电路产物目录失败")?;
+        }
+        std::fs::write(&acir_path, acir).context("写入打包的电路产物失败")?;
+
+        let pinned = match manifest {
+            Some(m) => m,
+            None => {
+                let pinned = CircuitManifest {
+                    circuit_name: name.clone(),
+                    noir_version: artifact_noir_version(acir).unwrap_or_default(),
+                    source_sha256: self.source_hash()?,
+                    acir_sha256,
+                };
+                pinned.save(&self.circuit_dir)?;
+                pinned
+            }
+        };
+        log::info!("📦 使用打包的Noir电路产物: {}", acir_path.display());
+        Ok(self.compiled(name, acir_path, &pinned, CircuitSource::Bundled))
+    }
+
+    fn compiled(&self, name: String, acir_path: PathBuf, manifest: &CircuitManifest, source: CircuitSource) -> CompiledCircuit {
+        CompiledCircuit {
+            name,
+            acir_path,
+            acir_sha256: manifest.acir_sha256.clone(),
+            noir_version: manifest.noir_version.clone(),
+            source,
+        }
+    }
+
+    /// 电路名称（Nargo.toml中的name）
+    fn circuit_name(&self) -> Result<String> {
+        let nargo_toml = self.circuit_dir.join("Nargo.toml");
+        let content = std::fs::read_to_string(&nargo_toml)
+            .with_context(|| format!("读取Nargo.toml失败: {}", nargo_toml.display()))?;
+        content
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("name"))
+            .filter_map(|rest| rest.trim_start().strip_prefix('='))
+            .map(|value| value.trim().trim_matches('"').to_string())
+            .next()
+            .context("Nargo.toml中缺少name字段")
+    }
+
+    /// Nargo.toml和src下所有.nr文件的哈希（按相对路径排序）
+    fn source_hash(&self) -> Result<String> {
+        let mut files = vec![PathBuf::from("Nargo.toml")];
+        collect_nr_files(&self.circuit_dir, Path::new("src"), &mut files)?;
+        files.sort();
+
+        let mut hasher = Sha256::new();
+        for relative in files {
+            let content = std::fs::read(self.circuit_dir.join(&relative))
+                .with_context(|| format!("读取电路源码失败: {}", relative.display()))?;
+            hasher.update(relative.to_string_lossy().replace('\\', "/").as_bytes());
+            hasher.update([0]);
+            hasher.update((content.len() as u64).to_be_bytes());
+            hasher.update(&content);
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+fn collect_nr_files(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let dir = root.join(relative);
+    if !dir.exists() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(&dir).with_context(|| format!("读取目录失败: {}", dir.display()))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_nr_files(root, &path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "nr") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 产物哈希：只覆盖abi和bytecode，debug信息里的源码绝对路径因机器而异
+fn artifact_hash(acir: &[u8]) -> String {
+    let program = serde_json::from_slice::<serde_json::Value>(acir).ok().and_then(|value| {
+        let bytecode = value.get("bytecode")?.as_str()?.to_string();
+        let abi = serde_json::to_vec(value.get("abi")?).ok()?;
+        Some((abi, bytecode))
+    });
+    let mut hasher = Sha256::new();
+    match program {
+        Some((abi, bytecode)) => {
+            hasher.update(&abi);
+            hasher.update([0]);
+            hasher.update(bytecode.as_bytes());
+        }
+        None => hasher.update(acir),
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// 去掉版本号中的commit后缀（1.0.0-beta.13+6e46... -> 1.0.0-beta.13）
+fn base_version(version: &str) -> &str {
+    version.split('+').next().unwrap_or(version).trim()
+}
+
+/// 解析 `nargo --version` 输出
+fn parse_nargo_version(stdout: &str) -> Option<String> {
+    stdout
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("nargo version"))
+        .and_then(|rest| rest.trim_start().strip_prefix('='))
+        .map(|version| base_version(version).to_string())
+        .or_else(|| {
+            // 旧版本输出格式：nargo 0.x.y (git version hash: ...)
+            stdout.split_whitespace().nth(1).filter(|v| v.chars().next().is_some_and(|c| c.is_ascii_digit())).map(str::to_string)
+        })
+}
+
+/// ACIR产物中记录的Noir版本
+fn artifact_noir_version(acir: &[u8]) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(acir).ok()?;
+    value.get("noir_version")?.as_str().map(|v| base_version(v).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Nargo.toml"), "[package]\nname = \"demo\"\ntype = \"bin\"\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.nr"), "fn main(x: Field) { assert(x == 1); }\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_nargo_version() {
+        assert_eq!(parse_nargo_version("nargo version = 1.0.0-beta.13\nnoirc version = 1.0.0-beta.13+6e46\n").as_deref(), Some("1.0.0-beta.13"));
+        assert_eq!(parse_nargo_version("nargo 0.19.4 (git version hash: abc)").as_deref(), Some("0.19.4"));
+        assert_eq!(parse_nargo_version("garbage"), None);
+    }
+
+    #[test]
+    fn test_repository_manifest_matches_sources() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("noir_circuits");
+        let manifest = CircuitManifest::load(&dir).unwrap().unwrap();
+        assert_eq!(manifest.source_sha256, CircuitBuilder::new(&dir).source_hash().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_existing_artifact_is_pinned_and_checked() {
+        let dir = circuit_dir();
+        std::fs::create_dir_all(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join("target/demo.json"), br#"{"noir_version":"1.0.0-beta.13+abc"}"#).unwrap();
+
+        let builder = CircuitBuilder::new(dir.path()).with_nargo("/nonexistent/nargo");
+        let circuit = builder.ensure_compiled().await.unwrap();
+        assert_eq!(circuit.source, CircuitSource::Cached);
+        assert_eq!(circuit.noir_version, "1.0.0-beta.13");
+        assert_eq!(CircuitManifest::load(dir.path()).unwrap().unwrap().circuit_name, "demo");
+
+        // 产物被改动后需要重新编译，没有nargo时给出安装提示
+        std::fs::write(dir.path().join("target/demo.json"), b"{}").unwrap();
+        let err = builder.ensure_compiled().await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(CircuitBuildError::NargoNotFound { required, .. }) if required == "1.0.0-beta.13"));
+        assert!(err.to_string().contains("noirup --version 1.0.0-beta.13"));
+    }
+
+    #[tokio::test]
+    async fn test_bundled_artifact_checked_against_manifest() {
+        let dir = circuit_dir();
+        let acir = br#"{"noir_version":"1.0.0"}"#.to_vec();
+
+        let circuit = CircuitBuilder::new(dir.path())
+            .with_nargo("/nonexistent/nargo")
+            .with_bundled_artifact(acir.clone())
+            .ensure_compiled()
+            .await
+            .unwrap();
+        assert_eq!(circuit.source, CircuitSource::Bundled);
+        assert_eq!(std::fs::read(&circuit.acir_path).unwrap(), acir);
+
+        // 清单固定后，其他产物不能冒充
+        std::fs::remove_dir_all(dir.path().join("target")).unwrap();
+        let err = CircuitBuilder::new(dir.path())
+            .with_nargo("/nonexistent/nargo")
+            .with_bundled_artifact(b"{}".to_vec())
+            .ensure_compiled()
+            .await
+            .unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(CircuitBuildError::HashMismatch { .. })));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_compile_with_nargo() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = circuit_dir();
+        let nargo = dir.path().join("fake-nargo");
+        std::fs::write(&nargo, "#!/bin/sh\nif [ \"$1\" = \"--version\" ]; then echo 'nargo version = 1.0.0'; exit 0; fi\nmkdir -p target && echo '{\"noir_version\":\"1.0.0\"}' > target/demo.json\n").unwrap();
+        std::fs::set_permissions(&nargo, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let builder = CircuitBuilder::new(dir.path()).with_nargo(nargo.to_string_lossy());
+
+        let circuit = builder.ensure_compiled().await.unwrap();
+        assert_eq!(circuit.source, CircuitSource::Compiled);
+        assert_eq!(builder.ensure_compiled().await.unwrap().source, CircuitSource::Cached);
+
+        // 源码变化后重新编译；清单要求的版本与本地nargo不一致时报错
+        std::fs::write(dir.path().join("src/main.nr"), "fn main(x: Field) { assert(x == 2); }\n").unwrap();
+        let mut manifest = CircuitManifest::load(dir.path()).unwrap().unwrap();
+        manifest.noir_version = "0.36.0".to_string();
+        manifest.save(dir.path()).unwrap();
+        let err = builder.ensure_compiled().await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(CircuitBuildError::VersionMismatch { found, .. }) if found == "1.0.0"));
+    }
+}
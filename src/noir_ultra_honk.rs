@@ -0,0 +1,283 @@
+// DIAP Rust SDK - UltraHonk证明后端
+// 使用Barretenberg（bb）的UltraHonk方案为Noir电路生成和验证证明；
+// UltraHonk基于通用SRS，不需要像Groth16那样为每个电路单独进行可信设置
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::sync::OnceCell;
+
+use crate::noir_circuit_build::{CircuitBuilder, CompiledCircuit};
+
+/// bb的方案名称
+pub const ULTRA_HONK_SCHEME: &str = "ultra_honk";
+
+/// UltraHonk证明
+#[derive(Debug, Clone)]
+pub struct UltraHonkProof {
+    /// 证明（bb输出的原始字节）
+    pub proof: Vec<u8>,
+
+    /// 公共输入（bb输出的字段元素字节）
+    pub public_inputs: Vec<u8>,
+
+    /// 电路输出
+    pub circuit_output: String,
+
+    /// 生成耗时（毫秒）
+    pub generation_time_ms: u64,
+}
+
+/// UltraHonk证明器：nargo execute生成见证，bb prove/verify生成和验证证明
+pub struct UltraHonkProver {
+    /// Noir电路目录
+    circuits_path: PathBuf,
+
+    /// nargo可执行文件
+    nargo: String,
+
+    /// bb可执行文件
+    bb: String,
+
+    /// 已编译的电路（首次使用时准备）
+    circuit: OnceCell<CompiledCircuit>,
+
+    /// 验证密钥（由电路确定，首次验证时生成）
+    verification_key: OnceCell<Vec<u8>>,
+}
+
+impl UltraHonkProver {
+    /// 创建证明器
+    pub fn new(circuits_path: impl Into<PathBuf>) -> Self {
+        Self {
+            circuits_path: circuits_path.into(),
+            nargo: "nargo".to_string(),
+            bb: "bb".to_string(),
+            circuit: OnceCell::new(),
+            verification_key: OnceCell::new(),
+        }
+    }
+
+    /// 使用指定的nargo可执行文件
+    pub fn with_nargo(mut self, nargo: impl Into<String>) -> Self {
+        self.nargo = nargo.into();
+        self
+    }
+
+    /// 使用指定的bb可执行文件
+    pub fn with_bb(mut self, bb: impl Into<String>) -> Self {
+        self.bb = bb.into();
+        self
+    }
+
+    /// bb是否可用
+    pub async fn is_available(&self) -> bool {
+        self.bb_output(&["--version"]).await.is_ok()
+    }
+
+    /// 按Prover.toml格式的输入生成证明
+    pub async fn prove(&self, prover_toml: &str) -> Result<UltraHonkProof> {
+        let start_time = std::time::Instant::now();
+        let circuit = self.circuit().await?;
+        let work = WorkDir::create(&self.circuits_path)?;
+
+        // 1. nargo execute生成见证（使用独立的输入文件，不覆盖Prover.toml）
+        let tag = work.tag();
+        let prover_path = self.circuits_path.join(format!("{}.toml", tag));
+        std::fs::write(&prover_path, prover_toml).context("写入证明输入失败")?;
+        let output = tokio::process::Command::new(&self.nargo)
+            .args(["execute", "--prover-name", &tag, &tag])
+            .current_dir(&self.circuits_path)
+            .output()
+            .await;
+        let _ = std::fs::remove_file(&prover_path);
+        let output = output.with_context(|| format!("启动{}失败", self.nargo))?;
+        if !output.status.success() {
+            anyhow::bail!("Noir电路执行失败: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let circuit_output = extract_circuit_output(&String::from_utf8_lossy(&output.stdout));
+        let witness_path = self.circuits_path.join("target").join(format!("{}.gz", tag));
+        let witness = std::fs::read(&witness_path).context("nargo未生成见证文件");
+        let _ = std::fs::remove_file(&witness_path);
+        std::fs::write(work.path().join("witness.gz"), witness?)?;
+
+        // 2. bb prove生成UltraHonk证明
+        let acir = circuit.acir_path.to_string_lossy().into_owned();
+        let witness = work.arg("witness.gz");
+        let out = work.arg("");
+        self.bb_output(&["prove", "--scheme", ULTRA_HONK_SCHEME, "-b", &acir, "-w", &witness, "-o", &out]).await
+            .context("生成UltraHonk证明失败")?;
+        let proof = std::fs::read(work.path().join("proof")).context("bb未生成证明")?;
+        let public_inputs = std::fs::read(work.path().join("public_inputs")).context("bb未生成公共输入")?;
+
+        let generation_time_ms = start_time.elapsed().as_millis() as u64;
+        log::info!("✅ UltraHonk证明生成成功，耗时: {}ms", generation_time_ms);
+        Ok(UltraHonkProof {
+            proof,
+            public_inputs,
+            circuit_output,
+            generation_time_ms,
+        })
+    }
+
+    /// 验证证明
+    pub async fn verify(&self, proof: &[u8], public_inputs: &[u8]) -> Result<bool> {
+        let vk = self.verification_key().await?;
+        let work = WorkDir::create(&self.circuits_path)?;
+        std::fs::write(work.path().join("vk"), vk)?;
+        std::fs::write(work.path().join("proof"), proof)?;
+        std::fs::write(work.path().join("public_inputs"), public_inputs)?;
+
+        let output = tokio::process::Command::new(&self.bb)
+            .args(["verify", "--scheme", ULTRA_HONK_SCHEME])
+            .args(["-k", &work.arg("vk"), "-p", &work.arg("proof"), "-i", &work.arg("public_inputs")])
+            .output()
+            .await
+            .with_context(|| format!("启动{}失败", self.bb))?;
+        Ok(output.status.success())
+    }
+
+    /// 验证密钥（同一电路只生成一次）
+    pub async fn verification_key(&self) -> Result<&[u8]> {
+        let vk = self.verification_key.get_or_try_init(|| async {
+            let circuit = self.circuit().await?;
+            let work = WorkDir::create(&self.circuits_path)?;
+            let acir = circuit.acir_path.to_string_lossy().into_owned();
+            self.bb_output(&["write_vk", "--scheme", ULTRA_HONK_SCHEME, "-b", &acir, "-o", &work.arg("")]).await
+                .context("生成验证密钥失败")?;
+            std::fs::read(work.path().join("vk")).context("bb未生成验证密钥")
+        }).await?;
+        Ok(vk)
+    }
+
+    async fn circuit(&self) -> Result<&CompiledCircuit> {
+        self.circuit.get_or_try_init(|| async {
+            let version = self.bb_output(&["--version"]).await
+                .context("未找到bb（Barretenberg），请通过 bbup 安装与nargo版本匹配的bb")?;
+            log::info!("🔧 初始化UltraHonk后端 (bb {})", version.trim());
+            CircuitBuilder::new(&self.circuits_path)
+                .with_nargo(self.nargo.clone())
+                .ensure_compiled()
+                .await
+        }).await
+    }
+
+    async fn bb_output(&self, args: &[&str]) -> Result<String> {
+        let output = tokio::process::Command::new(&self.bb)
+            .args(args)
+            .output()
+            .await
+            .with_context(|| format!("启动{}失败", self.bb))?;
+        if !output.status.success() {
+            anyhow::bail!("{} {} 失败: {}", self.bb, args.first().unwrap_or(&""), String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// 单次调用的临时目录（位于电路的target目录下，结束时删除）
+struct WorkDir(PathBuf);
+
+impl WorkDir {
+    fn create(circuits_path: &Path) -> Result<Self> {
+        let path = circuits_path
+            .join("target")
+            .join(format!("{}_{}", ULTRA_HONK_SCHEME, hex::encode(rand::random::<[u8; 8]>())));
+        std::fs::create_dir_all(&path).context("创建UltraHonk工作目录失败")?;
+        Ok(Self(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+
+    fn tag(&self) -> String {
+        self.0.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+    }
+
+    fn arg(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().trim_end_matches('/').to_string()
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// 从nargo execute输出中提取电路输出（"Circuit output: 0x24"）
+fn extract_circuit_output(stdout: &str) -> String {
+    stdout
+        .lines()
+        .find_map(|line| line.split("Circuit output:").nth(1))
+        .map(|output| output.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_prove_and_verify() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let circuit = dir.path().join("circuit");
+        std::fs::create_dir_all(circuit.join("src")).unwrap();
+        std::fs::create_dir_all(circuit.join("target")).unwrap();
+        std::fs::write(circuit.join("Nargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        std::fs::write(circuit.join("src/main.nr"), "fn main(x: pub Field, y: Field) -> pub Field { x + y }\n").unwrap();
+        std::fs::write(circuit.join("target/demo.json"), br#"{"noir_version":"1.0.0","bytecode":"H4sI"}"#).unwrap();
+
+        // 模拟nargo：execute把输入文件复制为见证
+        let nargo = dir.path().join("fake-nargo");
+        std::fs::write(&nargo, concat!(
+            "#!/bin/sh\n",
+            "[ \"$1\" = execute ] || exit 1\n",
+            "cp \"$3.toml\" \"target/$4.gz\" && echo '[demo] Circuit output: 0x2a'\n",
+        )).unwrap();
+        // 模拟bb：证明即见证内容，只有x = 1的证明能通过验证
+        let bb = dir.path().join("fake-bb");
+        std::fs::write(&bb, concat!(
+            "#!/bin/sh\n",
+            "case \"$1\" in\n",
+            "  --version) echo 0.82.2 ;;\n",
+            "  prove) cp \"$7\" \"$9/proof\" && printf 'inputs' > \"$9/public_inputs\" ;;\n",
+            "  write_vk) printf 'vk' > \"$7/vk\" ;;\n",
+            "  verify) grep -q 'x = 1' \"$7\" && [ \"$(cat \"$5\")\" = vk ] ;;\n",
+            "  *) exit 1 ;;\n",
+            "esac\n",
+        )).unwrap();
+        for tool in [&nargo, &bb] {
+            std::fs::set_permissions(tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let prover = UltraHonkProver::new(&circuit)
+            .with_nargo(nargo.to_string_lossy())
+            .with_bb(bb.to_string_lossy());
+        assert!(prover.is_available().await);
+
+        let proof = prover.prove("x = 1\ny = 41\n").await.unwrap();
+        assert_eq!(proof.circuit_output, "0x2a");
+        assert_eq!(proof.public_inputs, b"inputs");
+        assert!(prover.verify(&proof.proof, &proof.public_inputs).await.unwrap());
+        assert_eq!(prover.verification_key().await.unwrap(), b"vk");
+
+        let forged = prover.prove("x = 2\ny = 40\n").await.unwrap();
+        assert!(!prover.verify(&forged.proof, &forged.public_inputs).await.unwrap());
+
+        // 临时文件全部清理，Prover.toml不受影响
+        let leftovers: Vec<_> = std::fs::read_dir(circuit.join("target")).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name != "demo.json")
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+        assert!(!circuit.join("Prover.toml").exists());
+
+        // 没有bb时给出安装提示
+        let err = UltraHonkProver::new(&circuit).with_bb("/nonexistent/bb").prove("x = 1").await.unwrap_err();
+        assert!(format!("{:#}", err).contains("bbup"));
+    }
+}
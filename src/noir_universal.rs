@@ -1,9 +1,13 @@
 // DIAP Rust SDK - 通用Noir管理器
 // 支持多种后端：嵌入电路、外部Noir、arkworks等
+// Arkworks后端已弃用：旧证明仍可验证，默认不再生成（见zkp_migration）
 
 use anyhow::{Context, Result};
 use log;
 use std::path::PathBuf;
+use crate::proof_cache::{ProofCache, ProofCacheStats};
+use crate::noir_ultra_honk::UltraHonkProver;
+use crate::zkp_migration::{DeprecationTelemetry, ProofFormat};
 
 // 导入不同后端的模块
 #[cfg(feature = "embedded-noir")]
@@ -22,12 +26,21 @@ pub enum NoirBackend {
     Embedded,
     /// 外部Noir编译器（需要nargo）
     External,
-    /// Arkworks ZKP库（Rust原生）
+    /// Arkworks ZKP库（Rust原生，已弃用：只验证旧证明，生成需显式开启）
     Arkworks,
+    /// UltraHonk（Barretenberg，通用可信设置，需要nargo和bb）
+    UltraHonk,
     /// 简化实现（fallback）
     Simplified,
 }
 
+impl NoirBackend {
+    /// 是否需要针对每个电路的可信设置（Groth16需要，UltraHonk使用通用SRS）
+    pub fn requires_trusted_setup(&self) -> bool {
+        matches!(self, NoirBackend::Arkworks)
+    }
+}
+
 /// 通用Noir ZKP管理器
 pub struct UniversalNoirManager {
     backend: NoirBackend,
@@ -35,7 +48,14 @@ pub struct UniversalNoirManager {
     embedded_manager: Option<EmbeddedNoirZKPManager>,
     #[cfg(feature = "external-noir")]
     external_manager: Option<NoirZKPManager>,
+    ultra_honk: Option<UltraHonkProver>,
     circuits_path: PathBuf,
+    /// 证明缓存（按后端和输入哈希）
+    proof_cache: ProofCache<NoirProofResult>,
+    /// 是否允许用已弃用的Arkworks后端生成证明
+    legacy_generation: bool,
+    /// 旧版证明的弃用遥测
+    deprecation: DeprecationTelemetry,
 }
 
 impl UniversalNoirManager {
@@ -55,7 +75,11 @@ impl UniversalNoirManager {
             embedded_manager: None,
             #[cfg(feature = "external-noir")]
             external_manager: None,
+            ultra_honk: None,
             circuits_path,
+            proof_cache: ProofCache::default(),
+            legacy_generation: false,
+            deprecation: DeprecationTelemetry::default(),
         };
         
         // 初始化选定的后端
@@ -76,7 +100,11 @@ impl UniversalNoirManager {
             embedded_manager: None,
             #[cfg(feature = "external-noir")]
             external_manager: None,
+            ultra_honk: None,
             circuits_path,
+            proof_cache: ProofCache::default(),
+            legacy_generation: false,
+            deprecation: DeprecationTelemetry::default(),
         };
         
         manager.initialize_backend().await?;
@@ -85,7 +113,8 @@ impl UniversalNoirManager {
     
     /// 自动选择最佳后端
     async fn select_best_backend() -> Result<NoirBackend> {
-        // 优先级：嵌入 > 外部 > arkworks > 简化
+        // 优先级：嵌入 > 外部 > 简化
+        // Arkworks已弃用，不再自动选择；启用arkworks-zkp特性只为验证旧证明
         
         if cfg!(feature = "embedded-noir") {
             log::info!("✅ 嵌入Noir后端可用");
@@ -100,11 +129,6 @@ impl UniversalNoirManager {
             }
         }
         
-        if cfg!(feature = "arkworks-zkp") {
-            log::info!("✅ Arkworks ZKP后端可用");
-            return Ok(NoirBackend::Arkworks);
-        }
-        
         log::info!("⚠️  使用简化后端");
         Ok(NoirBackend::Simplified)
     }
@@ -162,6 +186,12 @@ impl UniversalNoirManager {
                 self.external_manager = Some(NoirZKPManager::new(&self.circuits_path)?);
             }
             
+            #[cfg(not(feature = "embedded-noir"))]
+            NoirBackend::Embedded => {
+                log::warn!("⚠️  嵌入Noir后端不可用，使用简化后端");
+                self.backend = NoirBackend::Simplified;
+            }
+            
             #[cfg(not(feature = "external-noir"))]
             NoirBackend::External => {
                 log::warn!("⚠️  外部Noir后端不可用，使用简化后端");
@@ -173,6 +203,12 @@ impl UniversalNoirManager {
                 // Arkworks后端不需要特殊初始化
             }
             
+            NoirBackend::UltraHonk => {
+                log::info!("🔧 初始化UltraHonk后端");
+                // 电路编译和bb检查推迟到首次生成证明
+                self.ultra_honk = Some(UltraHonkProver::new(&self.circuits_path));
+            }
+            
             NoirBackend::Simplified => {
                 log::info!("🔧 初始化简化后端");
                 // 简化后端不需要特殊初始化
@@ -183,7 +219,27 @@ impl UniversalNoirManager {
     }
     
     /// 生成证明
+    /// 相同后端、相同输入的证明在缓存有效期内直接复用
+    #[tracing::instrument(name = "diap.noir_prove", skip_all)]
     pub async fn generate_proof(&mut self, inputs: &NoirProverInputs) -> Result<NoirProofResult> {
+        let scheme = format!("{:?}", self.backend);
+        let inputs_hash = inputs.cache_key();
+        
+        if let Some(cached) = self.proof_cache.get(&scheme, &inputs_hash) {
+            log::info!("♻️ 复用缓存的证明");
+            return Ok(cached);
+        }
+        
+        let start = std::time::Instant::now();
+        let result = self.generate_proof_uncached(inputs).await;
+        crate::metrics::global().proof_generation.observe_since(start);
+        let result = result?;
+        self.proof_cache.put(&scheme, &inputs_hash, &inputs.expected_did_hash, result.clone());
+        Ok(result)
+    }
+    
+    /// 生成证明（不经过缓存）
+    async fn generate_proof_uncached(&mut self, inputs: &NoirProverInputs) -> Result<NoirProofResult> {
         match self.backend {
             #[cfg(feature = "embedded-noir")]
             NoirBackend::Embedded => {
@@ -236,15 +292,37 @@ impl UniversalNoirManager {
                 }
             }
             
+            #[cfg(not(feature = "embedded-noir"))]
+            NoirBackend::Embedded => {
+                Err(anyhow::anyhow!("嵌入Noir后端不可用"))
+            }
+            
             #[cfg(not(feature = "external-noir"))]
             NoirBackend::External => {
                 Err(anyhow::anyhow!("外部Noir后端不可用"))
             }
             
             NoirBackend::Arkworks => {
+                if !self.legacy_generation {
+                    anyhow::bail!("Arkworks证明生成已弃用，请切换到Noir后端（或调用set_legacy_generation(true)临时开启）");
+                }
+                log::warn!("⚠️ 正在生成已弃用的Arkworks证明");
                 self.generate_proof_arkworks(inputs).await
             }
             
+            NoirBackend::UltraHonk => {
+                let prover = self.ultra_honk.as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("UltraHonk后端未初始化"))?;
+                let result = prover.prove(&inputs.circuit_inputs().to_prover_toml()).await?;
+                Ok(NoirProofResult {
+                    proof: result.proof,
+                    public_inputs: result.public_inputs,
+                    circuit_output: result.circuit_output,
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                    generation_time_ms: result.generation_time_ms,
+                })
+            }
+            
             NoirBackend::Simplified => {
                 self.generate_proof_simplified(inputs).await
             }
@@ -252,7 +330,30 @@ impl UniversalNoirManager {
     }
     
     /// 验证证明
+    /// 旧版Arkworks证明无论当前后端如何都走兼容验证，并记录弃用遥测
     pub async fn verify_proof(&self, proof: &[u8], public_inputs: &[u8]) -> Result<NoirVerificationResult> {
+        self.verify_proof_with_source(None, proof, public_inputs).await
+    }
+    
+    /// 验证来自指定DID的证明（弃用遥测按发送者统计）
+    pub async fn verify_proof_from(&self, source_did: &str, proof: &[u8], public_inputs: &[u8]) -> Result<NoirVerificationResult> {
+        self.verify_proof_with_source(Some(source_did), proof, public_inputs).await
+    }
+    
+    #[tracing::instrument(name = "diap.noir_verify", skip_all, fields(proof_len = proof.len()))]
+    async fn verify_proof_with_source(&self, source_did: Option<&str>, proof: &[u8], public_inputs: &[u8]) -> Result<NoirVerificationResult> {
+        let start = std::time::Instant::now();
+        let result = self.verify_proof_by_format(source_did, proof, public_inputs).await;
+        crate::metrics::global().proof_verification.observe_since(start);
+        result
+    }
+    
+    async fn verify_proof_by_format(&self, source_did: Option<&str>, proof: &[u8], public_inputs: &[u8]) -> Result<NoirVerificationResult> {
+        if ProofFormat::detect(proof) == ProofFormat::LegacyArkworks {
+            self.deprecation.record(source_did);
+            return self.verify_proof_arkworks(proof, public_inputs).await;
+        }
+        
         match self.backend {
             #[cfg(feature = "embedded-noir")]
             NoirBackend::Embedded => {
@@ -284,6 +385,11 @@ impl UniversalNoirManager {
                 }
             }
             
+            #[cfg(not(feature = "embedded-noir"))]
+            NoirBackend::Embedded => {
+                Err(anyhow::anyhow!("嵌入Noir后端不可用"))
+            }
+            
             #[cfg(not(feature = "external-noir"))]
             NoirBackend::External => {
                 Err(anyhow::anyhow!("外部Noir后端不可用"))
@@ -293,6 +399,18 @@ impl UniversalNoirManager {
                 self.verify_proof_arkworks(proof, public_inputs).await
             }
             
+            NoirBackend::UltraHonk => {
+                let prover = self.ultra_honk.as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("UltraHonk后端未初始化"))?;
+                let start_time = std::time::Instant::now();
+                let is_valid = prover.verify(proof, public_inputs).await?;
+                Ok(NoirVerificationResult {
+                    is_valid,
+                    verification_time_ms: start_time.elapsed().as_millis() as u64,
+                    error_message: if is_valid { None } else { Some("UltraHonk验证失败".to_string()) },
+                })
+            }
+            
             NoirBackend::Simplified => {
                 self.verify_proof_simplified(proof, public_inputs).await
             }
@@ -413,6 +531,41 @@ impl UniversalNoirManager {
         Ok(())
     }
     
+    /// 替换证明缓存（例如调整TTL或容量）
+    pub fn set_proof_cache(&mut self, proof_cache: ProofCache<NoirProofResult>) {
+        self.proof_cache = proof_cache;
+    }
+    
+    /// 是否允许用已弃用的Arkworks后端生成证明（仅供过渡期仍需向旧节点发送证明的部署）
+    pub fn set_legacy_generation(&mut self, allow: bool) {
+        self.legacy_generation = allow;
+    }
+    
+    /// 替换弃用遥测（多个管理器可共享同一份统计）
+    pub fn set_deprecation_telemetry(&mut self, telemetry: DeprecationTelemetry) {
+        self.deprecation = telemetry;
+    }
+    
+    /// 旧版证明的弃用遥测
+    pub fn deprecation_telemetry(&self) -> &DeprecationTelemetry {
+        &self.deprecation
+    }
+    
+    /// 获取证明缓存（用于失效操作）
+    pub fn proof_cache(&self) -> &ProofCache<NoirProofResult> {
+        &self.proof_cache
+    }
+    
+    /// 获取证明缓存统计
+    pub fn proof_cache_stats(&self) -> ProofCacheStats {
+        self.proof_cache.stats()
+    }
+    
+    /// 使指定DID哈希的全部缓存证明失效（例如密钥轮换或DID文档更新后）
+    pub fn invalidate_cached_proofs(&self, expected_did_hash: &str) -> usize {
+        self.proof_cache.invalidate_binding(expected_did_hash)
+    }
+    
     /// 获取性能统计
     pub fn get_performance_stats(&self) -> PerformanceStats {
         match self.backend {
@@ -433,7 +586,7 @@ impl UniversalNoirManager {
             
             _ => PerformanceStats {
                 backend_type: self.backend.clone(),
-                cache_entries: 0,
+                cache_entries: self.proof_cache.len(),
                 memory_usage_bytes: 0,
                 is_optimized: false,
             }
@@ -479,6 +632,28 @@ pub struct NoirProverInputs {
 }
 
 impl NoirProverInputs {
+    /// 证明缓存键（全部输入的哈希）
+    pub fn cache_key(&self) -> String {
+        ProofCache::<NoirProofResult>::inputs_hash(&[
+            self.expected_did_hash.as_bytes(),
+            self.public_key_hash.as_bytes(),
+            self.nonce_hash.as_bytes(),
+            self.expected_output.as_bytes(),
+        ])
+    }
+    
+    /// 转换为Noir电路输入（私有输入为零）
+    pub fn circuit_inputs(&self) -> crate::noir_zkp::NoirProverInputs {
+        crate::noir_zkp::NoirProverInputs {
+            expected_did_hash: [self.expected_did_hash.parse::<u64>().unwrap_or(0), 0],
+            public_key_hash: self.public_key_hash.parse::<u64>().unwrap_or(0),
+            nonce_hash: self.nonce_hash.parse::<u64>().unwrap_or(0),
+            secret_key: [0, 0],
+            did_document_hash: [0, 0],
+            nonce: [0, 0],
+        }
+    }
+    
     /// 序列化公共输入
     pub fn serialize_public_inputs(&self) -> Result<Vec<u8>> {
         let public_inputs = vec![
@@ -559,6 +734,54 @@ mod tests {
         assert!(verify_result.unwrap().is_valid);
     }
     
+    #[tokio::test]
+    async fn test_proof_cache_reuse() {
+        let mut manager = UniversalNoirManager::with_backend(NoirBackend::Simplified).await.unwrap();
+        
+        let inputs = NoirProverInputs {
+            expected_did_hash: "did_hash".to_string(),
+            public_key_hash: "pk_hash".to_string(),
+            nonce_hash: "nonce_hash".to_string(),
+            expected_output: "expected_output".to_string(),
+        };
+        
+        let first = manager.generate_proof(&inputs).await.unwrap();
+        let second = manager.generate_proof(&inputs).await.unwrap();
+        assert_eq!(first.timestamp, second.timestamp);
+        
+        let stats = manager.proof_cache_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        
+        assert_eq!(manager.invalidate_cached_proofs("did_hash"), 1);
+        manager.generate_proof(&inputs).await.unwrap();
+        assert_eq!(manager.proof_cache_stats().misses, 2);
+    }
+    
+    #[tokio::test]
+    async fn test_ultra_honk_backend() {
+        assert!(NoirBackend::Arkworks.requires_trusted_setup());
+        assert!(!NoirBackend::UltraHonk.requires_trusted_setup());
+        
+        let mut manager = UniversalNoirManager::with_backend(NoirBackend::UltraHonk).await.unwrap();
+        assert!(matches!(manager.get_backend_info().backend_type, NoirBackend::UltraHonk));
+        
+        // 输入与其他后端相同，转换为电路的Prover.toml
+        let inputs = NoirProverInputs {
+            expected_did_hash: "7".to_string(),
+            public_key_hash: "3".to_string(),
+            nonce_hash: "5".to_string(),
+            expected_output: "0x0".to_string(),
+        };
+        let toml = inputs.circuit_inputs().to_prover_toml();
+        assert!(toml.contains("expected_did_hash = [7, 0]"));
+        assert!(toml.contains("public_key_hash = 3"));
+        
+        // 使用不存在的bb时报错而不是回退到其他方案
+        manager.ultra_honk = Some(UltraHonkProver::new(&manager.circuits_path).with_bb("/nonexistent/bb"));
+        assert!(manager.generate_proof(&inputs).await.is_err());
+    }
+    
     #[test]
     fn test_performance_stats() {
         let manager = UniversalNoirManager::new();
@@ -4,8 +4,11 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 // use std::process::Command; // 已移除，使用跨平台实现
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
+use crate::noir_circuit_build::CircuitBuilder;
+
 /// Noir验证结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NoirVerificationResult {
@@ -194,6 +197,238 @@ impl NoirVerifier {
     }
 }
 
+/// 导出的链上验证合约文件名
+pub const SOLIDITY_VERIFIER_FILE: &str = "DiapVerifier.sol";
+
+/// 导出的验证器描述文件名
+pub const VERIFIER_BUNDLE_FILE: &str = "verifier.json";
+
+/// 链上验证器描述：合约或外部验证方需要的电路信息和验证密钥
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifierBundle {
+    /// 证明系统（"ultra_honk" 或 "groth16"）
+    pub proof_system: String,
+
+    /// 电路名称
+    pub circuit_name: String,
+
+    /// 编译电路使用的Noir版本（Groth16为空）
+    pub noir_version: String,
+
+    /// 电路产物哈希（与circuit_manifest.json一致）
+    pub circuit_hash: String,
+
+    /// 生成验证密钥的工具版本
+    pub prover_version: String,
+
+    /// 公共输入名称（按证明中的顺序）
+    pub public_inputs: Vec<String>,
+
+    /// 验证密钥（hex）
+    pub verification_key: String,
+}
+
+/// 导出的验证器产物
+#[derive(Debug, Clone)]
+pub struct VerifierArtifact {
+    /// Solidity验证合约路径
+    pub solidity_path: PathBuf,
+
+    /// 验证器描述文件路径
+    pub bundle_path: PathBuf,
+
+    /// 验证器描述
+    pub bundle: VerifierBundle,
+}
+
+/// 链上验证器导出：用Barretenberg（bb）为Noir电路生成UltraHonk验证合约
+pub struct VerifierExporter {
+    /// Noir电路目录
+    circuits_path: PathBuf,
+
+    /// bb可执行文件
+    bb: String,
+}
+
+impl VerifierExporter {
+    /// 创建导出器
+    pub fn new(circuits_path: impl Into<PathBuf>) -> Self {
+        Self {
+            circuits_path: circuits_path.into(),
+            bb: "bb".to_string(),
+        }
+    }
+
+    /// 使用指定的bb可执行文件
+    pub fn with_bb(mut self, bb: impl Into<String>) -> Self {
+        self.bb = bb.into();
+        self
+    }
+
+    /// 导出Solidity验证合约和验证器描述到out_dir
+    /// 验证密钥使用keccak作为Fiat-Shamir哈希，生成的证明才能在EVM上验证
+    pub async fn export(&self, out_dir: impl AsRef<Path>) -> Result<VerifierArtifact> {
+        let out_dir = out_dir.as_ref();
+        std::fs::create_dir_all(out_dir).context("创建导出目录失败")?;
+
+        let circuit = CircuitBuilder::new(&self.circuits_path).ensure_compiled().await?;
+        let prover_version = self.bb_output(&["--version"]).await
+            .context("未找到bb（Barretenberg），请通过 bbup 安装与nargo版本匹配的bb")?;
+        log::info!("📤 导出链上验证器: {} (bb {})", circuit.name, prover_version.trim());
+
+        let acir = circuit.acir_path.to_string_lossy().into_owned();
+        let out = out_dir.to_string_lossy().into_owned();
+        self.bb_output(&["write_vk", "-b", &acir, "-o", &out, "--oracle_hash", "keccak"]).await
+            .context("生成验证密钥失败")?;
+        let vk_path = out_dir.join("vk");
+        let verification_key = std::fs::read(&vk_path)
+            .with_context(|| format!("bb未生成验证密钥: {}", vk_path.display()))?;
+
+        let solidity_path = out_dir.join(SOLIDITY_VERIFIER_FILE);
+        let vk = vk_path.to_string_lossy().into_owned();
+        let sol = solidity_path.to_string_lossy().into_owned();
+        self.bb_output(&["write_solidity_verifier", "-k", &vk, "-o", &sol]).await
+            .context("生成Solidity验证合约失败")?;
+        if !solidity_path.exists() {
+            anyhow::bail!("bb未生成验证合约: {}", solidity_path.display());
+        }
+
+        let abi = std::fs::read(&circuit.acir_path).context("读取电路产物失败")?;
+        let bundle = VerifierBundle {
+            proof_system: "ultra_honk".to_string(),
+            circuit_name: circuit.name,
+            noir_version: circuit.noir_version,
+            circuit_hash: circuit.acir_sha256,
+            prover_version: prover_version.trim().to_string(),
+            public_inputs: public_input_names(&abi),
+            verification_key: hex::encode(verification_key),
+        };
+        let bundle_path = out_dir.join(VERIFIER_BUNDLE_FILE);
+        std::fs::write(&bundle_path, serde_json::to_vec_pretty(&bundle)?).context("写入验证器描述失败")?;
+
+        log::info!("✅ 验证合约已导出: {}", solidity_path.display());
+        Ok(VerifierArtifact { solidity_path, bundle_path, bundle })
+    }
+
+    async fn bb_output(&self, args: &[&str]) -> Result<String> {
+        let output = tokio::process::Command::new(&self.bb)
+            .args(args)
+            .output()
+            .await
+            .with_context(|| format!("启动{}失败", self.bb))?;
+        if !output.status.success() {
+            anyhow::bail!("{} {} 失败: {}", self.bb, args.first().unwrap_or(&""), String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// 电路ABI中的公共输入名称（公共参数在前，返回值最后）
+fn public_input_names(artifact: &[u8]) -> Vec<String> {
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(artifact) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = value["abi"]["parameters"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|param| param["visibility"] == "public")
+        .filter_map(|param| param["name"].as_str().map(str::to_string))
+        .collect();
+    if !value["abi"]["return_type"].is_null() {
+        names.push("return_value".to_string());
+    }
+    names
+}
+
+/// 为Groth16验证密钥生成Solidity验证合约（BN254，使用EIP-196/197预编译）
+/// 调用合约时proof.b按EIP-197顺序传入：[[x.c1, x.c0], [y.c1, y.c0]]
+#[cfg(feature = "arkworks-zkp")]
+pub fn groth16_solidity_verifier(vk: &ark_groth16::VerifyingKey<ark_bn254::Bn254>) -> String {
+    use ark_bn254::{Fq, Fr, G1Affine, G2Affine};
+    use ark_ff::PrimeField;
+    use std::fmt::Write;
+
+    fn g1(name: &str, point: &G1Affine, out: &mut String) {
+        let _ = writeln!(out, "    uint256 constant {}_X = {};", name, point.x.into_bigint());
+        let _ = writeln!(out, "    uint256 constant {}_Y = {};", name, point.y.into_bigint());
+    }
+    fn g2(name: &str, point: &G2Affine, out: &mut String) {
+        let _ = writeln!(out, "    uint256 constant {}_X1 = {};", name, point.x.c1.into_bigint());
+        let _ = writeln!(out, "    uint256 constant {}_X0 = {};", name, point.x.c0.into_bigint());
+        let _ = writeln!(out, "    uint256 constant {}_Y1 = {};", name, point.y.c1.into_bigint());
+        let _ = writeln!(out, "    uint256 constant {}_Y0 = {};", name, point.y.c0.into_bigint());
+    }
+
+    let inputs = vk.gamma_abc_g1.len().saturating_sub(1);
+    let mut constants = String::new();
+    let _ = writeln!(constants, "    uint256 constant PRIME_Q = {};", Fq::MODULUS);
+    let _ = writeln!(constants, "    uint256 constant SNARK_SCALAR_FIELD = {};", Fr::MODULUS);
+    g1("ALPHA", &vk.alpha_g1, &mut constants);
+    g2("BETA", &vk.beta_g2, &mut constants);
+    g2("GAMMA", &vk.gamma_g2, &mut constants);
+    g2("DELTA", &vk.delta_g2, &mut constants);
+    for (i, point) in vk.gamma_abc_g1.iter().enumerate() {
+        g1(&format!("IC{}", i), point, &mut constants);
+    }
+
+    let mut accumulate = String::new();
+    for i in 0..inputs {
+        let _ = writeln!(accumulate, "        require(input[{i}] < SNARK_SCALAR_FIELD, \"input out of field\");");
+        let _ = writeln!(accumulate, "        (x, y) = ecAdd(x, y, IC{n}_X, IC{n}_Y, input[{i}]);", n = i + 1);
+    }
+
+    format!(r#"// SPDX-License-Identifier: MIT
+// Generated by diap-rs-sdk: Groth16 verifier for DIAP DID-CID binding proofs
+pragma solidity ^0.8.20;
+
+contract DiapGroth16Verifier {{
+{constants}
+    function verifyProof(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256[{inputs}] calldata input
+    ) external view returns (bool) {{
+        uint256 x = IC0_X;
+        uint256 y = IC0_Y;
+{accumulate}
+        uint256[24] memory p = [
+            a[0], (PRIME_Q - (a[1] % PRIME_Q)) % PRIME_Q, b[0][0], b[0][1], b[1][0], b[1][1],
+            ALPHA_X, ALPHA_Y, BETA_X1, BETA_X0, BETA_Y1, BETA_Y0,
+            x, y, GAMMA_X1, GAMMA_X0, GAMMA_Y1, GAMMA_Y0,
+            c[0], c[1], DELTA_X1, DELTA_X0, DELTA_Y1, DELTA_Y0
+        ];
+        uint256[1] memory result;
+        bool ok;
+        assembly {{
+            ok := staticcall(gas(), 0x08, p, 768, result, 32)
+        }}
+        return ok && result[0] == 1;
+    }}
+
+    /// (x, y) + s * (px, py)
+    function ecAdd(uint256 x, uint256 y, uint256 px, uint256 py, uint256 s) internal view returns (uint256, uint256) {{
+        uint256[3] memory mulInput = [px, py, s];
+        uint256[4] memory addInput;
+        bool ok;
+        assembly {{
+            ok := staticcall(gas(), 0x07, mulInput, 96, add(addInput, 64), 64)
+        }}
+        require(ok, "ecMul failed");
+        addInput[0] = x;
+        addInput[1] = y;
+        uint256[2] memory sum;
+        assembly {{
+            ok := staticcall(gas(), 0x06, addInput, 128, sum, 64)
+        }}
+        require(ok, "ecAdd failed");
+        return (sum[0], sum[1]);
+    }}
+}}
+"#)
+}
+
 /// 改进的Noir ZKP管理器
 pub struct ImprovedNoirZKPManager {
     verifier: NoirVerifier,
@@ -244,4 +479,76 @@ mod tests {
         assert!(result.is_valid);
         assert!(result.error_message.is_none());
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_export_solidity_verifier() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let circuit = dir.path().join("circuit");
+        std::fs::create_dir_all(circuit.join("src")).unwrap();
+        std::fs::create_dir_all(circuit.join("target")).unwrap();
+        std::fs::write(circuit.join("Nargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        std::fs::write(circuit.join("src/main.nr"), "fn main(x: pub Field, y: Field) -> pub Field { x + y }\n").unwrap();
+        std::fs::write(
+            circuit.join("target/demo.json"),
+            r#"{"noir_version":"1.0.0","abi":{"parameters":[{"name":"x","visibility":"public"},{"name":"y","visibility":"private"}],"return_type":{"abi_type":{"kind":"field"}}},"bytecode":"H4sI"}"#,
+        ).unwrap();
+
+        // 模拟bb：write_vk写入vk，write_solidity_verifier写入合约
+        let bb = dir.path().join("fake-bb");
+        std::fs::write(&bb, concat!(
+            "#!/bin/sh\n",
+            "case \"$1\" in\n",
+            "  --version) echo 0.82.2 ;;\n",
+            "  write_vk) printf 'vk-bytes' > \"$5/vk\" ;;\n",
+            "  write_solidity_verifier) echo 'contract HonkVerifier {}' > \"$5\" ;;\n",
+            "  *) exit 1 ;;\n",
+            "esac\n",
+        )).unwrap();
+        std::fs::set_permissions(&bb, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let out = dir.path().join("out");
+        let artifact = VerifierExporter::new(&circuit).with_bb(bb.to_string_lossy()).export(&out).await.unwrap();
+        assert!(std::fs::read_to_string(&artifact.solidity_path).unwrap().contains("HonkVerifier"));
+        assert_eq!(artifact.bundle.public_inputs, vec!["x".to_string(), "return_value".to_string()]);
+        assert_eq!(artifact.bundle.verification_key, hex::encode(b"vk-bytes"));
+        assert_eq!(artifact.bundle.prover_version, "0.82.2");
+
+        let saved: VerifierBundle = serde_json::from_slice(&std::fs::read(&artifact.bundle_path).unwrap()).unwrap();
+        assert_eq!(saved, artifact.bundle);
+
+        // 没有bb时给出安装提示
+        let err = VerifierExporter::new(&circuit).with_bb("/nonexistent/bb").export(&out).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("bbup"));
+    }
+
+    #[cfg(feature = "arkworks-zkp")]
+    #[test]
+    fn test_groth16_solidity_verifier() {
+        use ark_bn254::{Bn254, Fr};
+        use ark_groth16::Groth16;
+        use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+        use ark_relations::lc;
+        use ark_snark::CircuitSpecificSetupSNARK;
+
+        /// x * x == y，其中y为公共输入
+        struct Square;
+        impl ConstraintSynthesizer<Fr> for Square {
+            fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+                let x = cs.new_witness_variable(|| Ok(Fr::from(3u64)))?;
+                let y = cs.new_input_variable(|| Ok(Fr::from(9u64)))?;
+                cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + y)
+            }
+        }
+
+        let (_, vk) = Groth16::<Bn254>::setup(Square, &mut rand::rngs::OsRng).unwrap();
+        let contract = groth16_solidity_verifier(&vk);
+        assert!(contract.contains("contract DiapGroth16Verifier"));
+        assert!(contract.contains("uint256[1] calldata input"));
+        assert!(contract.contains("IC1_X"));
+        assert!(!contract.contains("IC2_X"));
+        assert!(contract.contains("21888242871839275222246405745257275088548364400416034343698204186575808495617"));
+    }
 }
@@ -7,8 +7,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tokio::fs;
 use crate::{
-    KeyPair, DIDDocument, AgentInfo,
+    KeyPair, DIDDocument, AgentInfo, DiapError, DiapResult,
 };
+use crate::noir_circuit_build::{CircuitBuilder, CompiledCircuit};
 
 /// Noir ZKP Circuit Manager
 /// 
@@ -21,6 +22,8 @@ pub struct NoirZKPManager {
     cache: HashMap<String, Vec<u8>>,
     /// Performance metrics
     metrics: PerformanceMetrics,
+    /// Circuit prepared on first use
+    compiled: Option<CompiledCircuit>,
 }
 
 /// Performance metrics for ZKP operations
@@ -62,6 +65,35 @@ pub struct NoirProverInputs {
     pub nonce: [u64; 2],
 }
 
+impl NoirProverInputs {
+    /// Render the inputs in Prover.toml format
+    pub fn to_prover_toml(&self) -> String {
+        format!(
+            r#"# DIAP Noir Circuit - Prover Inputs
+# Public inputs (known to verifier)
+expected_did_hash = [{}, {}]  # CID multi-hash part
+public_key_hash = {}          # Public key hash
+nonce_hash = {}              # Nonce hash
+
+# Private inputs (secret witness)
+secret_key = [{}, {}]        # Secret key parts
+did_document_hash = [{}, {}] # DID document hash
+nonce = [{}, {}]             # Nonce parts
+"#,
+            self.expected_did_hash[0],
+            self.expected_did_hash[1],
+            self.public_key_hash,
+            self.nonce_hash,
+            self.secret_key[0],
+            self.secret_key[1],
+            self.did_document_hash[0],
+            self.did_document_hash[1],
+            self.nonce[0],
+            self.nonce[1],
+        )
+    }
+}
+
 impl NoirZKPManager {
     /// Create a new Noir ZKP Manager
     pub fn new(circuits_path: String) -> Self {
@@ -69,9 +101,22 @@ impl NoirZKPManager {
             circuits_path,
             cache: HashMap::new(),
             metrics: PerformanceMetrics::default(),
+            compiled: None,
         }
     }
     
+    /// Make sure the circuit is compiled and matches its manifest (runs nargo on first use)
+    pub async fn prepare_circuit(&mut self) -> DiapResult<&CompiledCircuit> {
+        if self.compiled.is_none() {
+            let circuit = CircuitBuilder::new(&self.circuits_path)
+                .ensure_compiled()
+                .await
+                .map_err(DiapError::from_zkp)?;
+            self.compiled = Some(circuit);
+        }
+        Ok(self.compiled.as_ref().expect("circuit prepared above"))
+    }
+    
     /// Generate a DID-CID binding proof using Noir circuit
     pub async fn generate_did_binding_proof(
         &mut self,
@@ -79,21 +124,24 @@ impl NoirZKPManager {
         did_document: &DIDDocument,
         cid_hash: &[u8],
         nonce: &[u8],
-    ) -> Result<NoirProofResult> {
+    ) -> DiapResult<NoirProofResult> {
         let start_time = std::time::Instant::now();
         
         log::info!("🔐 Generating DID-CID binding proof with Noir circuit");
         
+        // 0. Compile the circuit on first use
+        self.prepare_circuit().await?;
+        
         // 1. Prepare circuit inputs
         let inputs = self.prepare_circuit_inputs(
             keypair,
             did_document,
             cid_hash,
             nonce,
-        ).await?;
+        ).await.map_err(DiapError::from_zkp)?;
         
         // 2. Generate the proof using nargo
-        let proof_result = self.execute_noir_circuit(&inputs).await?;
+        let proof_result = self.execute_noir_circuit(&inputs).await.map_err(DiapError::from_zkp)?;
         
         // 3. Update metrics
         let generation_time = start_time.elapsed().as_millis() as u64;
@@ -117,14 +165,15 @@ impl NoirZKPManager {
         proof: &[u8],
         public_inputs: &[u8],
         expected_output: &str,
-    ) -> Result<bool> {
+    ) -> DiapResult<bool> {
         let start_time = std::time::Instant::now();
         
         log::info!("🔍 Verifying DID-CID binding proof with Noir circuit");
         
         // For now, we'll use a simplified verification
         // In a full implementation, this would use the Noir verifier
-        let is_valid = self.verify_noir_proof(proof, public_inputs, expected_output).await?;
+        let is_valid = self.verify_noir_proof(proof, public_inputs, expected_output).await
+            .map_err(DiapError::from_zkp)?;
         
         // Update metrics
         let verification_time = start_time.elapsed().as_millis() as u64;
@@ -301,31 +350,7 @@ impl NoirZKPManager {
     }
     
     fn create_prover_toml(&self, inputs: &NoirProverInputs) -> Result<String> {
-        let toml_content = format!(
-            r#"# DIAP Noir Circuit - Prover Inputs
-# Public inputs (known to verifier)
-expected_did_hash = [{}, {}]  # CID multi-hash part
-public_key_hash = {}          # Public key hash
-nonce_hash = {}              # Nonce hash
-
-# Private inputs (secret witness)
-secret_key = [{}, {}]        # Secret key parts
-did_document_hash = [{}, {}] # DID document hash
-nonce = [{}, {}]             # Nonce parts
-"#,
-            inputs.expected_did_hash[0],
-            inputs.expected_did_hash[1],
-            inputs.public_key_hash,
-            inputs.nonce_hash,
-            inputs.secret_key[0],
-            inputs.secret_key[1],
-            inputs.did_document_hash[0],
-            inputs.did_document_hash[1],
-            inputs.nonce[0],
-            inputs.nonce[1],
-        );
-        
-        Ok(toml_content)
+        Ok(inputs.to_prover_toml())
     }
     
     fn extract_circuit_output(&self, stdout: &str) -> Result<String> {
@@ -487,8 +512,11 @@ impl NoirAgent {
             id: self.keypair.did.clone(),
             verification_method: vec![],
             authentication: vec![],
+            key_agreement: vec![],
             service: None,
             created: chrono::Utc::now().to_rfc3339(),
+            key_rotation: None,
+            previous_version_cid: None,
         })
     }
     
@@ -1,11 +1,15 @@
 // DIAP Rust SDK - Nonce管理器
 // 防止重放攻击，跟踪已使用的nonce
 
-use anyhow::{Context, Result};
+use crate::error::{AuthErrorKind, DiapError, DiapResult};
 use dashmap::DashMap;
-use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH, Duration};
+use dashmap::mapref::entry::Entry;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
+use crate::clock::{Clock, SharedClock, SystemClock, system_clock};
+use crate::nonce_store::NonceStore;
+use crate::timestamp_window::TimestampWindow;
 
 /// Nonce记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,8 +37,17 @@ pub struct NonceManager {
     /// nonce有效期（秒）
     validity_duration: u64,
     
+    /// 允许nonce时间戳超前本地时钟的秒数（容忍时钟误差）
+    max_future_skew: u64,
+    
     /// 清理间隔（秒）
     cleanup_interval: u64,
+    
+    /// 时间源
+    clock: SharedClock,
+    
+    /// 持久化后端（未设置时仅保存在内存中）
+    store: Arc<OnceLock<Arc<dyn NonceStore>>>,
 }
 
 impl NonceManager {
@@ -44,13 +57,25 @@ impl NonceManager {
     /// * `validity_duration` - nonce有效期（秒），默认300秒（5分钟）
     /// * `cleanup_interval` - 清理过期nonce的间隔（秒），默认60秒
     pub fn new(validity_duration: Option<u64>, cleanup_interval: Option<u64>) -> Self {
+        Self::new_with_clock(validity_duration, cleanup_interval, system_clock())
+    }
+    
+    /// 使用指定时间源创建Nonce管理器
+    pub fn new_with_clock(
+        validity_duration: Option<u64>,
+        cleanup_interval: Option<u64>,
+        clock: SharedClock,
+    ) -> Self {
         let validity = validity_duration.unwrap_or(300);
         let cleanup = cleanup_interval.unwrap_or(60);
         
         let manager = Self {
             nonces: Arc::new(DashMap::new()),
             validity_duration: validity,
+            max_future_skew: 0,
             cleanup_interval: cleanup,
+            clock,
+            store: Arc::new(OnceLock::new()),
         };
         
         // 启动后台清理任务
@@ -63,14 +88,65 @@ impl NonceManager {
         manager
     }
     
+    /// 接入持久化后端：加载仍在有效期内的记录，之后每条新nonce落盘后才算验证通过
+    pub fn with_store(self, store: Arc<dyn NonceStore>) -> anyhow::Result<Self> {
+        let now = self.clock.now_secs();
+        let mut loaded = 0;
+        for record in store.load()? {
+            if record.expires_at >= now {
+                self.nonces.insert(record.nonce.clone(), record);
+                loaded += 1;
+            }
+        }
+        store.remove_expired(now)?;
+        
+        if self.store.set(store).is_err() {
+            anyhow::bail!("Nonce管理器已设置持久化后端");
+        }
+        log::info!("💾 Nonce持久化已启用，恢复 {} 条记录", loaded);
+        Ok(self)
+    }
+    
+    /// 与时间戳窗口对齐：有效期取最大消息年龄，并容忍窗口允许的时钟超前
+    pub fn with_timestamp_window(mut self, window: &TimestampWindow) -> Self {
+        self.validity_duration = window.max_age_secs;
+        self.max_future_skew = window.max_future_skew_secs;
+        self
+    }
+    
+    /// nonce有效期（秒）
+    pub fn validity_secs(&self) -> u64 {
+        self.validity_duration
+    }
+    
+    /// 允许的时钟超前量（秒）
+    pub fn max_future_skew_secs(&self) -> u64 {
+        self.max_future_skew
+    }
+    
+    /// 解析nonce中的时间戳（Unix秒）
+    pub fn nonce_timestamp(nonce: &str) -> Option<u64> {
+        nonce.split(':').next()?.parse().ok()
+    }
+    
+    /// 是否启用了持久化
+    pub fn is_persistent(&self) -> bool {
+        self.store.get().is_some()
+    }
+    
     /// 生成新的nonce
     /// 格式: timestamp:uuid:random
     pub fn generate_nonce() -> String {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        
+        Self::generate_nonce_with_clock(&SystemClock)
+    }
+    
+    /// 使用指定时间源生成nonce
+    pub fn generate_nonce_with_clock(clock: &dyn Clock) -> String {
+        Self::generate_nonce_at(clock.now_secs())
+    }
+    
+    /// 以指定时间戳（Unix秒）生成nonce，用于定时消息
+    pub fn generate_nonce_at(timestamp: u64) -> String {
         let uuid = uuid::Uuid::new_v4();
         let random = rand::random::<u64>();
         
@@ -82,54 +158,80 @@ impl NonceManager {
     /// # 返回
     /// * `Ok(true)` - nonce有效且未被使用
     /// * `Ok(false)` - nonce已被使用（重放攻击）
-    /// * `Err` - nonce格式错误或已过期
-    pub fn verify_and_record(&self, nonce: &str, did: &str) -> Result<bool> {
+    /// * `Err` - nonce格式错误（`AuthErrorKind::InvalidNonce`）或已过期（`AuthErrorKind::NonceExpired`）
+    pub fn verify_and_record(&self, nonce: &str, did: &str) -> DiapResult<bool> {
+        self.verify_and_record_with_offset(nonce, did, 0)
+    }
+    
+    /// 验证并记录nonce，按对方时钟相对本地的偏移（毫秒，对方超前为正）校正有效期检查
+    pub fn verify_and_record_with_offset(&self, nonce: &str, did: &str, clock_offset_ms: i64) -> DiapResult<bool> {
         // 1. 解析nonce
         let parts: Vec<&str> = nonce.split(':').collect();
         if parts.len() < 2 {
-            return Err(anyhow::anyhow!("Nonce格式错误"));
+            return Err(DiapError::auth(AuthErrorKind::InvalidNonce, "Nonce格式错误"));
         }
         
         let timestamp: u64 = parts[0].parse()
-            .context("无法解析时间戳")?;
+            .map_err(|_| DiapError::auth(AuthErrorKind::InvalidNonce, "无法解析时间戳"))?;
         
-        // 2. 检查时间戳是否在有效期内
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        // 2. 检查时间戳是否在有效期内（以对方时钟下的当前时间比较）
+        let now_ms = self.clock.now_millis();
+        let now = now_ms / 1000;
+        let peer_now = now_ms.saturating_add_signed(clock_offset_ms) / 1000;
         
-        if timestamp > now {
-            return Err(anyhow::anyhow!("Nonce时间戳在未来"));
+        if timestamp > peer_now + self.max_future_skew {
+            return Err(DiapError::auth(AuthErrorKind::NonceExpired, "Nonce时间戳在未来"));
         }
         
-        if now - timestamp > self.validity_duration {
-            return Err(anyhow::anyhow!(
-                "Nonce已过期（超过{}秒）",
-                self.validity_duration
+        if peer_now.saturating_sub(timestamp) > self.validity_duration {
+            return Err(DiapError::auth(
+                AuthErrorKind::NonceExpired,
+                format!("Nonce已过期（超过{}秒）", self.validity_duration),
             ));
         }
         
         // 3. 检查是否已被使用
-        if self.nonces.contains_key(nonce) {
-            log::warn!("检测到重放攻击！Nonce已被使用: {}", nonce);
-            return Ok(false);
-        }
+        let entry = match self.nonces.entry(nonce.to_string()) {
+            Entry::Occupied(_) => {
+                log::warn!("检测到重放攻击！Nonce已被使用: {}", nonce);
+                crate::metrics::global().nonce_replays.inc();
+                return Ok(false);
+            }
+            Entry::Vacant(entry) => entry,
+        };
         
-        // 4. 记录nonce
+        // 4. 记录nonce（启用持久化时先落盘，失败则拒绝）
         let record = NonceRecord {
             nonce: nonce.to_string(),
             used_at: now,
             did: did.to_string(),
-            expires_at: now + self.validity_duration,
+            expires_at: now.max(timestamp.saturating_add_signed(-clock_offset_ms / 1000)) + self.validity_duration,
         };
         
-        self.nonces.insert(nonce.to_string(), record);
+        if let Some(store) = self.store.get() {
+            if let Err(e) = store.record(&record) {
+                log::error!("❌ Nonce持久化失败: {}", e);
+                return Err(DiapError::auth(
+                    AuthErrorKind::NonceStoreUnavailable,
+                    format!("无法持久化nonce: {}", e),
+                ));
+            }
+        }
+        entry.insert(record);
         
         log::debug!("✓ Nonce验证通过并已记录: {}", nonce);
         Ok(true)
     }
     
+    /// 验证并记录nonce，重放时返回`AuthErrorKind::NonceReplay`错误
+    pub fn check_and_record(&self, nonce: &str, did: &str) -> DiapResult<()> {
+        if self.verify_and_record(nonce, did)? {
+            Ok(())
+        } else {
+            Err(DiapError::auth(AuthErrorKind::NonceReplay, format!("Nonce已被使用: {}", nonce)))
+        }
+    }
+    
     /// 检查nonce是否已被使用
     pub fn is_used(&self, nonce: &str) -> bool {
         self.nonces.contains_key(nonce)
@@ -140,12 +242,18 @@ impl NonceManager {
         self.nonces.get(nonce).map(|r| r.clone())
     }
     
+    /// 获取指定DID使用过的nonce记录
+    pub fn records_for_did(&self, did: &str) -> Vec<NonceRecord> {
+        self.nonces
+            .iter()
+            .filter(|record| record.did == did)
+            .map(|record| record.value().clone())
+            .collect()
+    }
+    
     /// 清理过期的nonce
     pub fn cleanup_expired(&self) -> usize {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let now = self.clock.now_secs();
         
         let mut removed = 0;
         
@@ -162,6 +270,12 @@ impl NonceManager {
             log::info!("🧹 清理了 {} 个过期nonce", removed);
         }
         
+        if let Some(store) = self.store.get() {
+            if let Err(e) = store.remove_expired(now) {
+                log::warn!("压缩nonce存储失败: {}", e);
+            }
+        }
+        
         removed
     }
     
@@ -180,6 +294,8 @@ impl NonceManager {
     fn start_cleanup_task(&self) {
         let nonces = self.nonces.clone();
         let interval = self.cleanup_interval;
+        let clock = self.clock.clone();
+        let store = self.store.clone();
         
         tokio::spawn(async move {
             let mut interval_timer = tokio::time::interval(Duration::from_secs(interval));
@@ -187,10 +303,7 @@ impl NonceManager {
             loop {
                 interval_timer.tick().await;
                 
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
+                let now = clock.now_secs();
                 
                 let mut removed = 0;
                 nonces.retain(|_, record| {
@@ -205,6 +318,12 @@ impl NonceManager {
                 if removed > 0 {
                     log::debug!("🧹 后台清理了 {} 个过期nonce", removed);
                 }
+                
+                if let Some(store) = store.get() {
+                    if let Err(e) = store.remove_expired(now) {
+                        log::warn!("压缩nonce存储失败: {}", e);
+                    }
+                }
             }
         });
     }
@@ -219,6 +338,8 @@ impl Default for NonceManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::MockClock;
+    use std::time::{SystemTime, UNIX_EPOCH};
     
     #[test]
     fn test_generate_nonce() {
@@ -289,12 +410,86 @@ mod tests {
         assert_eq!(manager.count(), 0);
     }
     
+    #[tokio::test]
+    async fn test_mock_clock_expiry() {
+        let clock = MockClock::new(1_000_000);
+        let manager = NonceManager::new_with_clock(Some(300), Some(60), Arc::new(clock.clone()));
+        
+        let nonce = NonceManager::generate_nonce_with_clock(&clock);
+        assert!(manager.verify_and_record(&nonce, "did:key:test").unwrap());
+        
+        // 推进到有效期之后，同一时间戳的nonce已过期，记录也可被清理
+        clock.advance(Duration::from_secs(301));
+        let stale = format!("{}:test:abc", 1_000_000);
+        assert!(manager.verify_and_record(&stale, "did:key:test").is_err());
+        assert_eq!(manager.cleanup_expired(), 1);
+    }
+    
     #[test]
     fn test_invalid_nonce_format() {
         let manager = NonceManager::new(Some(300), Some(60));
         
         let result = manager.verify_and_record("invalid", "did:key:test");
-        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().auth_kind(), Some(AuthErrorKind::InvalidNonce));
+    }
+    
+    #[tokio::test]
+    async fn test_check_and_record_replay() {
+        let clock = MockClock::new(1_000_000);
+        let manager = NonceManager::new_with_clock(Some(300), Some(60), Arc::new(clock.clone()));
+        
+        let nonce = NonceManager::generate_nonce_with_clock(&clock);
+        assert!(manager.check_and_record(&nonce, "did:key:test").is_ok());
+        assert!(manager.check_and_record(&nonce, "did:key:test").unwrap_err().is_nonce_replay());
+    }
+    
+    #[tokio::test]
+    async fn test_timestamp_window_skew() {
+        let clock = MockClock::new(1_000_000);
+        let window = TimestampWindow::new(Duration::from_secs(60), Duration::from_secs(10));
+        let manager = NonceManager::new_with_clock(Some(300), Some(60), Arc::new(clock.clone()))
+            .with_timestamp_window(&window);
+        assert_eq!(manager.validity_secs(), 60);
+        
+        // 稍微超前的nonce被接受，记录保留到它按年龄过期为止
+        let ahead = NonceManager::generate_nonce_at(1_000_008);
+        assert_eq!(NonceManager::nonce_timestamp(&ahead), Some(1_000_008));
+        assert!(manager.verify_and_record(&ahead, "did:key:test").unwrap());
+        assert_eq!(manager.get_record(&ahead).unwrap().expires_at, 1_000_068);
+        assert!(manager.verify_and_record(&NonceManager::generate_nonce_at(1_000_011), "did:key:test").is_err());
+        
+        clock.advance(Duration::from_secs(61));
+        assert!(manager.verify_and_record(&NonceManager::generate_nonce_at(1_000_000), "did:key:test").is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_replay_protection_survives_restart() {
+        use crate::nonce_store::FileNonceStore;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nonces.jsonl");
+        let clock = MockClock::new(1_000_000);
+        let open = || {
+            let store = Arc::new(FileNonceStore::open(&path).unwrap());
+            NonceManager::new_with_clock(Some(300), Some(60), Arc::new(clock.clone())).with_store(store).unwrap()
+        };
+        
+        let nonce = NonceManager::generate_nonce_with_clock(&clock);
+        let manager = open();
+        assert!(manager.is_persistent());
+        assert!(manager.verify_and_record(&nonce, "did:key:test").unwrap());
+        drop(manager);
+        
+        // 重启后同一nonce仍被识别为重放
+        clock.advance(Duration::from_secs(10));
+        let restarted = open();
+        assert_eq!(restarted.count(), 1);
+        assert!(!restarted.verify_and_record(&nonce, "did:key:test").unwrap());
+        
+        // 过期后重启不再加载，存储也被压缩
+        clock.advance(Duration::from_secs(600));
+        assert_eq!(open().count(), 0);
+        assert!(FileNonceStore::open(&path).unwrap().load().unwrap().is_empty());
     }
 }
 
@@ -0,0 +1,210 @@
+// DIAP Rust SDK - Nonce持久化存储
+// NonceManager默认只在内存中记录nonce，进程重启后重放窗口会重新打开；
+// 接入持久化后端后，已使用的nonce在有效期内跨重启保留，过期记录按TTL压缩清理
+
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::nonce_manager::NonceRecord;
+
+/// Nonce持久化后端（SQLite等其他存储实现该trait即可接入）
+pub trait NonceStore: Send + Sync {
+    /// 加载全部记录（启动时调用，调用方会丢弃已过期的记录）
+    fn load(&self) -> Result<Vec<NonceRecord>>;
+
+    /// 持久化一条新记录，返回前必须已落盘
+    fn record(&self, record: &NonceRecord) -> Result<()>;
+
+    /// 删除 expires_at 早于 now 的记录，返回删除数量
+    fn remove_expired(&self, now: u64) -> Result<usize>;
+}
+
+/// 基于文件的nonce存储：每条记录追加一行JSON，压缩时重写文件
+pub struct FileNonceStore {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl FileNonceStore {
+    /// 打开（或创建）nonce日志文件
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建nonce存储目录: {:?}", parent))?;
+        }
+        let mut file = Self::open_append(&path)?;
+        // 上次崩溃时最后一行可能没有写完，补上换行，避免新记录接在残缺行后面
+        let content = std::fs::read(&path).with_context(|| format!("无法读取nonce存储: {:?}", path))?;
+        if content.last().is_some_and(|b| *b != b'\n') {
+            file.write_all(b"\n")?;
+        }
+        log::info!("💾 Nonce存储: {:?}", path);
+        Ok(Self { path, file: Mutex::new(file) })
+    }
+
+    /// 存储文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn open_append(path: &Path) -> Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("无法打开nonce存储: {:?}", path))
+    }
+
+    fn read_records(path: &Path) -> Result<Vec<NonceRecord>> {
+        let file = File::open(path).with_context(|| format!("无法读取nonce存储: {:?}", path))?;
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // 崩溃时最后一行可能只写了一半，跳过即可
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(e) => log::warn!("跳过损坏的nonce记录: {}", e),
+            }
+        }
+        Ok(records)
+    }
+}
+
+impl NonceStore for FileNonceStore {
+    fn load(&self) -> Result<Vec<NonceRecord>> {
+        let _guard = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        Self::read_records(&self.path)
+    }
+
+    fn record(&self, record: &NonceRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record).context("序列化nonce记录失败")?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(&line).context("写入nonce存储失败")?;
+        file.sync_data().context("同步nonce存储失败")?;
+        Ok(())
+    }
+
+    fn remove_expired(&self, now: u64) -> Result<usize> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let records = Self::read_records(&self.path)?;
+        let live: Vec<&NonceRecord> = records.iter().filter(|r| r.expires_at >= now).collect();
+        let removed = records.len() - live.len();
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        let temp_path = self.path.with_extension("compact");
+        let mut content = Vec::new();
+        for record in &live {
+            serde_json::to_writer(&mut content, record)?;
+            content.push(b'\n');
+        }
+        {
+            let mut temp = File::create(&temp_path)
+                .with_context(|| format!("无法写入nonce存储: {:?}", temp_path))?;
+            temp.write_all(&content)?;
+            temp.sync_all()?;
+        }
+        std::fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("无法替换nonce存储: {:?}", self.path))?;
+        *file = Self::open_append(&self.path)?;
+
+        log::debug!("🧹 压缩nonce存储: 删除 {} 条过期记录", removed);
+        Ok(removed)
+    }
+}
+
+/// 基于sled的nonce存储（nonce -> JSON记录）
+#[cfg(feature = "sled")]
+pub struct SledNonceStore {
+    tree: sled::Tree,
+}
+
+#[cfg(feature = "sled")]
+impl SledNonceStore {
+    /// 打开sled数据库
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let db = sled::open(path.as_ref())
+            .with_context(|| format!("无法打开sled数据库: {:?}", path.as_ref()))?;
+        Self::from_tree(db.open_tree("diap_nonces")?)
+    }
+
+    /// 使用已打开的sled树（与应用共享同一个数据库）
+    pub fn from_tree(tree: sled::Tree) -> Result<Self> {
+        Ok(Self { tree })
+    }
+}
+
+#[cfg(feature = "sled")]
+impl NonceStore for SledNonceStore {
+    fn load(&self) -> Result<Vec<NonceRecord>> {
+        self.tree.iter()
+            .values()
+            .map(|value| Ok(serde_json::from_slice(&value?)?))
+            .collect()
+    }
+
+    fn record(&self, record: &NonceRecord) -> Result<()> {
+        self.tree.insert(record.nonce.as_bytes(), serde_json::to_vec(record)?)?;
+        self.tree.flush().context("同步nonce存储失败")?;
+        Ok(())
+    }
+
+    fn remove_expired(&self, now: u64) -> Result<usize> {
+        let mut removed = 0;
+        for entry in self.tree.iter() {
+            let (key, value) = entry?;
+            let record: NonceRecord = serde_json::from_slice(&value)?;
+            if record.expires_at < now {
+                self.tree.remove(key)?;
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            self.tree.flush()?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(nonce: &str, expires_at: u64) -> NonceRecord {
+        NonceRecord {
+            nonce: nonce.to_string(),
+            used_at: expires_at - 300,
+            did: "did:key:test".to_string(),
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn test_file_store_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nonces.jsonl");
+        let store = FileNonceStore::open(&path).unwrap();
+        store.record(&record("a", 1_000)).unwrap();
+        store.record(&record("b", 2_000)).unwrap();
+
+        // 模拟崩溃时写了一半的行
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"nonce\":").unwrap();
+        let store = FileNonceStore::open(&path).unwrap();
+        store.record(&record("c", 3_000)).unwrap();
+        assert_eq!(store.load().unwrap().len(), 3);
+
+        assert_eq!(store.remove_expired(1_500).unwrap(), 1);
+        let nonces: Vec<String> = store.load().unwrap().into_iter().map(|r| r.nonce).collect();
+        assert_eq!(nonces, vec!["b", "c"]);
+    }
+}
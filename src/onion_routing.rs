@@ -0,0 +1,321 @@
+// DIAP Rust SDK - 洋葱路由模块
+// 将消息按中继链逐层加密，中间中继只知道上一跳和下一跳，无法得知发送方DID和最终内容
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use ed25519_dalek::VerifyingKey;
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::sync::Arc;
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+use crate::key_manager::KeyPair;
+
+/// 层密钥派生域分隔标签
+const ONION_KEY_TAG: &[u8] = b"DIAP_ONION_LAYER_V1";
+
+/// 洋葱数据包（每一跳看到的都是这一结构）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnionPacket {
+    /// 本层临时X25519公钥
+    pub ephemeral_public: [u8; 32],
+
+    /// AES-GCM nonce
+    pub nonce: [u8; 12],
+
+    /// 本层密文
+    pub ciphertext: Vec<u8>,
+}
+
+impl OnionPacket {
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self).context("序列化洋葱数据包失败")
+    }
+
+    /// 从字节反序列化
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        bincode::deserialize(data).context("反序列化洋葱数据包失败")
+    }
+}
+
+/// 解密后的单层内容
+#[derive(Debug, Serialize, Deserialize)]
+struct OnionLayer {
+    /// 下一跳DID（None表示本节点为最终接收者）
+    next_hop: Option<String>,
+
+    /// 内层数据（下一层数据包或最终载荷）
+    inner: Vec<u8>,
+}
+
+/// 剥离一层后的结果
+#[derive(Debug, Clone)]
+pub enum PeeledOnion {
+    /// 转发给下一跳
+    Forward {
+        next_hop: String,
+        packet: OnionPacket,
+    },
+
+    /// 本节点为最终接收者
+    Deliver {
+        payload: Vec<u8>,
+    },
+}
+
+/// 中继信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayInfo {
+    /// 中继DID（did:key格式，用于派生加密公钥）
+    pub did: String,
+
+    /// 最后一次公告时间
+    pub last_seen: u64,
+}
+
+/// 中继目录
+/// 记录愿意转发洋葱数据包的智能体，用于选择中继链
+#[derive(Clone, Default)]
+pub struct RelayDirectory {
+    relays: Arc<DashMap<String, RelayInfo>>,
+}
+
+impl RelayDirectory {
+    /// 创建空的中继目录
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册（或刷新）中继
+    pub fn register(&self, did: &str) -> Result<()> {
+        // 提前校验DID可用于派生加密公钥
+        KeyPair::public_key_from_did_key(did)?;
+
+        self.relays.insert(did.to_string(), RelayInfo {
+            did: did.to_string(),
+            last_seen: chrono::Utc::now().timestamp() as u64,
+        });
+        log::debug!("注册中继: {}", did);
+        Ok(())
+    }
+
+    /// 移除中继
+    pub fn remove(&self, did: &str) {
+        self.relays.remove(did);
+    }
+
+    /// 中继数量
+    pub fn len(&self) -> usize {
+        self.relays.len()
+    }
+
+    /// 目录是否为空
+    pub fn is_empty(&self) -> bool {
+        self.relays.is_empty()
+    }
+
+    /// 随机选择中继链（排除发送方和接收方）
+    pub fn select_route(&self, hops: usize, exclude: &[&str]) -> Result<Vec<String>> {
+        let mut candidates: Vec<String> = self.relays
+            .iter()
+            .map(|entry| entry.key().clone())
+            .filter(|did| !exclude.contains(&did.as_str()))
+            .collect();
+
+        if candidates.len() < hops {
+            anyhow::bail!("可用中继不足: 需要 {}，可用 {}", hops, candidates.len());
+        }
+
+        candidates.shuffle(&mut rand::thread_rng());
+        candidates.truncate(hops);
+        Ok(candidates)
+    }
+}
+
+/// 洋葱路由器
+/// 负责构建多层加密数据包，以及在中继/接收方剥离自己的一层
+pub struct OnionRouter {
+    /// 本地DID
+    did: String,
+
+    /// 由Ed25519私钥转换的X25519私钥
+    secret: StaticSecret,
+}
+
+impl OnionRouter {
+    /// 从本地密钥对创建洋葱路由器
+    pub fn new(keypair: &KeyPair) -> Self {
+        // Ed25519私钥 -> X25519私钥（SHA-512前32字节，clamp由x25519完成）
+        let hash = Sha512::digest(keypair.private_key);
+        let mut scalar = [0u8; 32];
+        scalar.copy_from_slice(&hash[..32]);
+
+        Self {
+            did: keypair.did.clone(),
+            secret: StaticSecret::from(scalar),
+        }
+    }
+
+    /// 本地DID
+    pub fn did(&self) -> &str {
+        &self.did
+    }
+
+    /// 构建洋葱数据包
+    /// 返回第一跳DID和数据包；route为中继链，recipient_did为最终接收者
+    pub fn build_packet(
+        route: &[String],
+        recipient_did: &str,
+        payload: &[u8],
+    ) -> Result<(String, OnionPacket)> {
+        // 最内层：发给接收者，next_hop为空
+        let mut packet = Self::encrypt_layer(recipient_did, &OnionLayer {
+            next_hop: None,
+            inner: payload.to_vec(),
+        })?;
+        let mut next_hop = recipient_did.to_string();
+
+        // 从后往前逐层包裹
+        for relay_did in route.iter().rev() {
+            packet = Self::encrypt_layer(relay_did, &OnionLayer {
+                next_hop: Some(next_hop),
+                inner: packet.to_bytes()?,
+            })?;
+            next_hop = relay_did.clone();
+        }
+
+        log::info!("🧅 构建洋葱数据包: {} 跳中继", route.len());
+        Ok((next_hop, packet))
+    }
+
+    /// 剥离本节点对应的一层
+    pub fn peel(&self, packet: &OnionPacket) -> Result<PeeledOnion> {
+        let peer_public = X25519PublicKey::from(packet.ephemeral_public);
+        let shared = self.secret.diffie_hellman(&peer_public);
+        let cipher = Self::layer_cipher(shared.as_bytes())?;
+
+        let plaintext = cipher.decrypt(Nonce::from_slice(&packet.nonce), packet.ciphertext.as_ref())
+            .map_err(|_| anyhow::anyhow!("洋葱层解密失败：数据包不是发给本节点的"))?;
+
+        let layer: OnionLayer = bincode::deserialize(&plaintext)
+            .context("解析洋葱层失败")?;
+
+        match layer.next_hop {
+            Some(next_hop) => {
+                log::debug!("🧅 剥离一层，转发至: {}", next_hop);
+                Ok(PeeledOnion::Forward {
+                    next_hop,
+                    packet: OnionPacket::from_bytes(&layer.inner)?,
+                })
+            }
+            None => {
+                log::info!("🧅 洋葱数据包已送达");
+                Ok(PeeledOnion::Deliver { payload: layer.inner })
+            }
+        }
+    }
+
+    /// 对单层加密：使用临时X25519密钥与目标DID的公钥协商
+    fn encrypt_layer(target_did: &str, layer: &OnionLayer) -> Result<OnionPacket> {
+        let target_public = Self::x25519_public_from_did(target_did)?;
+
+        let ephemeral = StaticSecret::random_from_rng(rand::rngs::OsRng);
+        let ephemeral_public = X25519PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(&target_public);
+        let cipher = Self::layer_cipher(shared.as_bytes())?;
+
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let plaintext = bincode::serialize(layer).context("序列化洋葱层失败")?;
+        let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+            .map_err(|e| anyhow::anyhow!("洋葱层加密失败: {:?}", e))?;
+
+        Ok(OnionPacket {
+            ephemeral_public: ephemeral_public.to_bytes(),
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// 从共享密钥派生AES-256-GCM密钥
+    fn layer_cipher(shared_secret: &[u8]) -> Result<Aes256Gcm> {
+        let mut hasher = Sha256::new();
+        hasher.update(ONION_KEY_TAG);
+        hasher.update(shared_secret);
+        let key = hasher.finalize();
+
+        Aes256Gcm::new_from_slice(&key).map_err(|e| anyhow::anyhow!("创建洋葱层密钥失败: {:?}", e))
+    }
+
+    /// 从did:key的Ed25519公钥转换出X25519公钥
+    fn x25519_public_from_did(did: &str) -> Result<X25519PublicKey> {
+        let public_key = KeyPair::public_key_from_did_key(did)?;
+        let verifying_key = VerifyingKey::from_bytes(&public_key)
+            .context("无效的Ed25519公钥")?;
+        Ok(X25519PublicKey::from(verifying_key.to_montgomery().to_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_onion_route_delivery() {
+        let relays: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate().unwrap()).collect();
+        let recipient = KeyPair::generate().unwrap();
+        let route: Vec<String> = relays.iter().map(|k| k.did.clone()).collect();
+
+        let (first_hop, mut packet) = OnionRouter::build_packet(&route, &recipient.did, b"secret").unwrap();
+        assert_eq!(first_hop, relays[0].did);
+
+        // 每个中继依次剥离一层
+        for (i, relay) in relays.iter().enumerate() {
+            match OnionRouter::new(relay).peel(&packet).unwrap() {
+                PeeledOnion::Forward { next_hop, packet: inner } => {
+                    let expected = relays.get(i + 1).map(|k| k.did.clone()).unwrap_or(recipient.did.clone());
+                    assert_eq!(next_hop, expected);
+                    packet = inner;
+                }
+                PeeledOnion::Deliver { .. } => panic!("中继不应收到最终载荷"),
+            }
+        }
+
+        match OnionRouter::new(&recipient).peel(&packet).unwrap() {
+            PeeledOnion::Deliver { payload } => assert_eq!(payload, b"secret"),
+            PeeledOnion::Forward { .. } => panic!("接收者应收到最终载荷"),
+        }
+    }
+
+    #[test]
+    fn test_wrong_relay_cannot_peel() {
+        let relay = KeyPair::generate().unwrap();
+        let recipient = KeyPair::generate().unwrap();
+        let outsider = KeyPair::generate().unwrap();
+
+        let (_, packet) = OnionRouter::build_packet(std::slice::from_ref(&relay.did), &recipient.did, b"data").unwrap();
+        assert!(OnionRouter::new(&outsider).peel(&packet).is_err());
+        assert!(OnionRouter::new(&recipient).peel(&packet).is_err());
+    }
+
+    #[test]
+    fn test_select_route() {
+        let directory = RelayDirectory::new();
+        let relays: Vec<KeyPair> = (0..4).map(|_| KeyPair::generate().unwrap()).collect();
+        for relay in &relays {
+            directory.register(&relay.did).unwrap();
+        }
+
+        let route = directory.select_route(3, &[relays[0].did.as_str()]).unwrap();
+        assert_eq!(route.len(), 3);
+        assert!(!route.contains(&relays[0].did));
+        assert!(directory.select_route(4, &[relays[0].did.as_str()]).is_err());
+    }
+}
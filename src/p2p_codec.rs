@@ -0,0 +1,168 @@
+// DIAP Rust SDK - libp2p请求-响应编解码器
+// 基于异步 request_response::Codec 的长度前缀帧格式，不在同步方法中阻塞运行时，
+// 也不依赖读到流末尾，支持可配置的最大消息大小（默认16MB），可承载多MB负载
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p::request_response::Codec;
+use libp2p::StreamProtocol;
+use std::io;
+
+/// DIAP请求-响应协议名（默认命名空间）
+pub const DIAP_REQUEST_PROTOCOL: StreamProtocol = StreamProtocol::new(crate::constants::DIAP_REQUEST_PROTOCOL_NAME);
+
+/// 当前网络参数下的请求-响应协议名
+pub fn request_protocol() -> StreamProtocol {
+    StreamProtocol::try_from_owned(crate::constants::network_params().request_protocol())
+        .unwrap_or(DIAP_REQUEST_PROTOCOL)
+}
+
+/// 默认最大消息大小（16MB）
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// 长度前缀字节数（u32大端）
+const LENGTH_PREFIX_LEN: usize = 4;
+
+/// DIAP编解码器
+///
+/// 帧格式：4字节大端长度 + 负载。请求和响应均为原始字节，
+/// 上层自行序列化（例如 `PubsubAuthenticator::serialize_message`）
+#[derive(Debug, Clone, Copy)]
+pub struct DIAPCodec {
+    max_message_size: usize,
+}
+
+impl DIAPCodec {
+    /// 创建编解码器，指定最大消息大小（字节）
+    pub fn new(max_message_size: usize) -> Self {
+        Self {
+            max_message_size: max_message_size.min(u32::MAX as usize),
+        }
+    }
+
+    /// 最大消息大小
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+}
+
+impl Default for DIAPCodec {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MESSAGE_SIZE)
+    }
+}
+
+#[async_trait]
+impl Codec for DIAPCodec {
+    type Protocol = StreamProtocol;
+    type Request = Vec<u8>;
+    type Response = Vec<u8>;
+
+    async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_frame(io, self.max_message_size).await
+    }
+
+    async fn read_response<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_frame(io, self.max_message_size).await
+    }
+
+    async fn write_request<T>(&mut self, _: &Self::Protocol, io: &mut T, req: Self::Request) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_frame(io, &req, self.max_message_size).await
+    }
+
+    async fn write_response<T>(&mut self, _: &Self::Protocol, io: &mut T, res: Self::Response) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_frame(io, &res, self.max_message_size).await
+    }
+}
+
+/// 读取一帧（先读长度，超过上限时在分配内存前拒绝）
+pub async fn read_frame<T>(io: &mut T, max_message_size: usize) -> io::Result<Vec<u8>>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    let mut prefix = [0u8; LENGTH_PREFIX_LEN];
+    io.read_exact(&mut prefix).await?;
+    let len = u32::from_be_bytes(prefix) as usize;
+
+    if len > max_message_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("消息过大: {} 字节（上限 {} 字节）", len, max_message_size),
+        ));
+    }
+
+    let mut buffer = vec![0u8; len];
+    io.read_exact(&mut buffer).await?;
+    Ok(buffer)
+}
+
+/// 写入一帧并关闭写端
+pub async fn write_frame<T>(io: &mut T, data: &[u8], max_message_size: usize) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin + Send,
+{
+    if data.len() > max_message_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("消息过大: {} 字节（上限 {} 字节）", data.len(), max_message_size),
+        ));
+    }
+
+    io.write_all(&(data.len() as u32).to_be_bytes()).await?;
+    io.write_all(data).await?;
+    io.close().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+
+    #[tokio::test]
+    async fn test_large_message_roundtrip() {
+        let mut codec = DIAPCodec::default();
+        let payload: Vec<u8> = (0..5 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+        let mut wire = Cursor::new(Vec::new());
+        codec.write_request(&DIAP_REQUEST_PROTOCOL, &mut wire, payload.clone()).await.unwrap();
+
+        // 帧后追加的数据不会被读入（不依赖流末尾）
+        let mut bytes = wire.into_inner();
+        bytes.extend_from_slice(b"trailing");
+        let mut reader = Cursor::new(bytes);
+        let decoded = codec.read_request(&DIAP_REQUEST_PROTOCOL, &mut reader).await.unwrap();
+        assert_eq!(decoded, payload);
+    }
+
+    #[tokio::test]
+    async fn test_size_limit_and_truncation() {
+        let mut codec = DIAPCodec::new(1024);
+
+        let mut wire = Cursor::new(Vec::new());
+        let err = codec.write_response(&DIAP_REQUEST_PROTOCOL, &mut wire, vec![0u8; 2048]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        // 对端声明的长度超过上限
+        let mut oversized = Cursor::new((4096u32).to_be_bytes().to_vec());
+        let err = codec.read_response(&DIAP_REQUEST_PROTOCOL, &mut oversized).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // 帧被截断
+        let mut truncated = (100u32).to_be_bytes().to_vec();
+        truncated.extend_from_slice(&[1, 2, 3]);
+        let err = codec.read_request(&DIAP_REQUEST_PROTOCOL, &mut Cursor::new(truncated)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
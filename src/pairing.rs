@@ -0,0 +1,514 @@
+// DIAP Rust SDK - 配对协议（短认证字符串）
+// 两个智能体在带外场景首次接触时交换临时密钥，双方显示相同的6位SAS供人工核对，确认后互相记为可信对端，不依赖注册表抵御中间人攻击
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+
+use crate::key_manager::{KeyPair, Signer};
+
+/// 承诺值域分隔标签
+const PAIRING_COMMIT_TAG: &[u8] = b"DIAP_PAIRING_COMMIT_V1";
+
+/// SAS派生域分隔标签
+const PAIRING_SAS_TAG: &[u8] = b"DIAP_PAIRING_SAS_V1";
+
+/// 第一步：发起方提交临时公钥的承诺（防止发起方看到响应方公钥后再选择自己的公钥）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingCommit {
+    /// 配对ID
+    pub pairing_id: String,
+
+    /// 发起方DID
+    pub did: String,
+
+    /// 承诺值 SHA256(标签 || 配对ID || DID || 临时公钥 || 随机数)
+    pub commitment: [u8; 32],
+
+    /// 发起方签名
+    pub signature: Vec<u8>,
+}
+
+/// 第二步：响应方发送临时公钥
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingResponse {
+    /// 配对ID
+    pub pairing_id: String,
+
+    /// 响应方DID
+    pub did: String,
+
+    /// 响应方临时X25519公钥
+    pub ephemeral_public: [u8; 32],
+
+    /// 响应方签名
+    pub signature: Vec<u8>,
+}
+
+/// 第三步：发起方揭示临时公钥和随机数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingReveal {
+    /// 配对ID
+    pub pairing_id: String,
+
+    /// 发起方临时X25519公钥
+    pub ephemeral_public: [u8; 32],
+
+    /// 承诺随机数
+    pub nonce: [u8; 32],
+
+    /// 发起方签名
+    pub signature: Vec<u8>,
+}
+
+/// 配对角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PairingRole {
+    /// 发起方
+    Initiator,
+
+    /// 响应方
+    Responder,
+}
+
+/// 配对状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PairingState {
+    /// 发起方等待响应
+    AwaitingResponse,
+
+    /// 响应方等待揭示
+    AwaitingReveal,
+
+    /// SAS已生成，等待人工确认
+    AwaitingConfirmation,
+
+    /// 已确认
+    Confirmed,
+
+    /// 已拒绝（SAS不一致）
+    Rejected,
+}
+
+/// 配对成功的可信对端
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PairedPeer {
+    /// 对端DID
+    pub did: String,
+
+    /// 配对ID
+    pub pairing_id: String,
+
+    /// 配对记录指纹（SAS派生哈希的hex，用于审计）
+    pub fingerprint: String,
+
+    /// 配对时间
+    pub paired_at: u64,
+}
+
+/// 配对会话
+pub struct PairingSession {
+    /// 角色
+    role: PairingRole,
+
+    /// 配对ID
+    pairing_id: String,
+
+    /// 本地DID
+    local_did: String,
+
+    /// 对端DID
+    peer_did: Option<String>,
+
+    /// 临时私钥
+    secret: StaticSecret,
+
+    /// 承诺随机数（仅发起方）
+    nonce: [u8; 32],
+
+    /// 发起方的承诺（仅响应方）
+    peer_commitment: Option<[u8; 32]>,
+
+    /// 短认证字符串
+    sas: Option<String>,
+
+    /// 配对记录指纹
+    fingerprint: Option<[u8; 32]>,
+
+    /// 状态
+    state: PairingState,
+}
+
+impl PairingSession {
+    /// 发起配对，返回会话和需要发送给对端的承诺
+    pub fn initiate(signer: &dyn Signer) -> Result<(Self, PairingCommit)> {
+        let mut nonce = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let session = Self::new(PairingRole::Initiator, crate::message_id::new_message_id(), signer.did(), nonce);
+        let mut commit = PairingCommit {
+            pairing_id: session.pairing_id.clone(),
+            did: session.local_did.clone(),
+            commitment: session.commitment(),
+            signature: Vec::new(),
+        };
+        commit.signature = signer.sign(&signing_data(&commit, |c| c.signature = Vec::new())?)?;
+
+        log::info!("🤝 发起配对: {}", session.pairing_id);
+        Ok((session, commit))
+    }
+
+    /// 响应配对，返回会话和需要发送给发起方的临时公钥
+    pub fn respond(signer: &dyn Signer, commit: &PairingCommit) -> Result<(Self, PairingResponse)> {
+        let data = signing_data(commit, |c| c.signature = Vec::new())?;
+        if !KeyPair::verify_with_did_key(&commit.did, &data, &commit.signature)? {
+            anyhow::bail!("配对承诺签名无效: {}", commit.did);
+        }
+        if commit.did == signer.did() {
+            anyhow::bail!("不能与自己配对");
+        }
+
+        let mut session = Self::new(PairingRole::Responder, commit.pairing_id.clone(), signer.did(), [0u8; 32]);
+        session.peer_did = Some(commit.did.clone());
+        session.peer_commitment = Some(commit.commitment);
+        session.state = PairingState::AwaitingReveal;
+
+        let mut response = PairingResponse {
+            pairing_id: session.pairing_id.clone(),
+            did: session.local_did.clone(),
+            ephemeral_public: session.public_key(),
+            signature: Vec::new(),
+        };
+        response.signature = signer.sign(&signing_data(&response, |r| r.signature = Vec::new())?)?;
+
+        Ok((session, response))
+    }
+
+    /// 发起方处理响应：生成SAS，返回需要发送给对端的揭示消息
+    pub fn handle_response(&mut self, signer: &dyn Signer, response: &PairingResponse) -> Result<PairingReveal> {
+        self.expect(PairingRole::Initiator, PairingState::AwaitingResponse)?;
+        if response.pairing_id != self.pairing_id {
+            anyhow::bail!("配对ID不匹配: {}", response.pairing_id);
+        }
+        let data = signing_data(response, |r| r.signature = Vec::new())?;
+        if !KeyPair::verify_with_did_key(&response.did, &data, &response.signature)? {
+            anyhow::bail!("配对响应签名无效: {}", response.did);
+        }
+
+        self.peer_did = Some(response.did.clone());
+        self.derive_sas(self.public_key(), response.ephemeral_public, &self.local_did.clone(), &response.did);
+
+        let mut reveal = PairingReveal {
+            pairing_id: self.pairing_id.clone(),
+            ephemeral_public: self.public_key(),
+            nonce: self.nonce,
+            signature: Vec::new(),
+        };
+        reveal.signature = signer.sign(&signing_data(&reveal, |r| r.signature = Vec::new())?)?;
+        Ok(reveal)
+    }
+
+    /// 响应方处理揭示：校验承诺并生成SAS
+    pub fn handle_reveal(&mut self, reveal: &PairingReveal) -> Result<()> {
+        self.expect(PairingRole::Responder, PairingState::AwaitingReveal)?;
+        if reveal.pairing_id != self.pairing_id {
+            anyhow::bail!("配对ID不匹配: {}", reveal.pairing_id);
+        }
+
+        let peer_did = self.peer_did.clone().context("缺少对端DID")?;
+        let data = signing_data(reveal, |r| r.signature = Vec::new())?;
+        if !KeyPair::verify_with_did_key(&peer_did, &data, &reveal.signature)? {
+            anyhow::bail!("配对揭示签名无效: {}", peer_did);
+        }
+
+        let expected = commitment(&self.pairing_id, &peer_did, &reveal.ephemeral_public, &reveal.nonce);
+        if Some(expected) != self.peer_commitment {
+            self.state = PairingState::Rejected;
+            anyhow::bail!("临时公钥与承诺不一致，可能存在中间人攻击");
+        }
+
+        self.derive_sas(reveal.ephemeral_public, self.public_key(), &peer_did, &self.local_did.clone());
+        Ok(())
+    }
+
+    /// 6位短认证字符串（双方显示一致时确认）
+    pub fn sas(&self) -> Option<&str> {
+        self.sas.as_deref()
+    }
+
+    /// 当前状态
+    pub fn state(&self) -> PairingState {
+        self.state
+    }
+
+    /// 本地角色
+    pub fn role(&self) -> PairingRole {
+        self.role
+    }
+
+    /// 配对ID
+    pub fn pairing_id(&self) -> &str {
+        &self.pairing_id
+    }
+
+    /// 对端DID
+    pub fn peer_did(&self) -> Option<&str> {
+        self.peer_did.as_deref()
+    }
+
+    /// 人工确认SAS一致
+    pub fn confirm(&mut self) -> Result<PairedPeer> {
+        if self.state != PairingState::AwaitingConfirmation {
+            anyhow::bail!("当前状态无法确认配对: {:?}", self.state);
+        }
+
+        self.state = PairingState::Confirmed;
+        let peer = PairedPeer {
+            did: self.peer_did.clone().context("缺少对端DID")?,
+            pairing_id: self.pairing_id.clone(),
+            fingerprint: hex::encode(self.fingerprint.context("缺少配对指纹")?),
+            paired_at: chrono::Utc::now().timestamp() as u64,
+        };
+        log::info!("✅ 配对已确认: {}", peer.did);
+        Ok(peer)
+    }
+
+    /// 人工发现SAS不一致，拒绝配对
+    pub fn reject(&mut self) {
+        log::warn!("⚠️ 配对被拒绝（SAS不一致）: {}", self.pairing_id);
+        self.state = PairingState::Rejected;
+    }
+
+    fn new(role: PairingRole, pairing_id: String, local_did: String, nonce: [u8; 32]) -> Self {
+        Self {
+            role,
+            pairing_id,
+            local_did,
+            peer_did: None,
+            secret: StaticSecret::random_from_rng(rand::thread_rng()),
+            nonce,
+            peer_commitment: None,
+            sas: None,
+            fingerprint: None,
+            state: PairingState::AwaitingResponse,
+        }
+    }
+
+    fn public_key(&self) -> [u8; 32] {
+        X25519PublicKey::from(&self.secret).to_bytes()
+    }
+
+    fn commitment(&self) -> [u8; 32] {
+        commitment(&self.pairing_id, &self.local_did, &self.public_key(), &self.nonce)
+    }
+
+    fn expect(&self, role: PairingRole, state: PairingState) -> Result<()> {
+        if self.role != role || self.state != state {
+            anyhow::bail!("配对状态错误: {:?} / {:?}", self.role, self.state);
+        }
+        Ok(())
+    }
+
+    /// 由双方DID、临时公钥和共享密钥派生SAS（参数均按发起方在前排列）
+    fn derive_sas(&mut self, initiator_public: [u8; 32], responder_public: [u8; 32], initiator_did: &str, responder_did: &str) {
+        let peer_public = match self.role {
+            PairingRole::Initiator => responder_public,
+            PairingRole::Responder => initiator_public,
+        };
+        let shared = self.secret.diffie_hellman(&X25519PublicKey::from(peer_public));
+
+        let mut hasher = Sha256::new();
+        hasher.update(PAIRING_SAS_TAG);
+        for part in [self.pairing_id.as_bytes(), initiator_did.as_bytes(), responder_did.as_bytes()] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        hasher.update(initiator_public);
+        hasher.update(responder_public);
+        hasher.update(shared.as_bytes());
+        let digest: [u8; 32] = hasher.finalize().into();
+
+        let code = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % 1_000_000;
+        self.sas = Some(format!("{:06}", code));
+        self.fingerprint = Some(digest);
+        self.state = PairingState::AwaitingConfirmation;
+    }
+}
+
+fn commitment(pairing_id: &str, did: &str, ephemeral_public: &[u8; 32], nonce: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(PAIRING_COMMIT_TAG);
+    for part in [pairing_id.as_bytes(), did.as_bytes()] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.update(ephemeral_public);
+    hasher.update(nonce);
+    hasher.finalize().into()
+}
+
+/// 清空签名字段后序列化，作为签名数据
+fn signing_data<T: Clone + Serialize>(message: &T, clear_signature: impl Fn(&mut T)) -> Result<Vec<u8>> {
+    let mut unsigned = message.clone();
+    clear_signature(&mut unsigned);
+    serde_json::to_vec(&unsigned).context("序列化配对消息失败")
+}
+
+/// 可信对端存储（可选持久化到JSON文件）
+#[derive(Clone, Default)]
+pub struct PairedPeerStore {
+    /// 持久化文件路径（None表示仅内存）
+    path: Option<PathBuf>,
+
+    /// DID -> 配对记录
+    peers: Arc<DashMap<String, PairedPeer>>,
+}
+
+impl PairedPeerStore {
+    /// 仅内存的存储
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// 打开持久化存储（文件不存在时为空）
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let peers = DashMap::new();
+
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("无法读取配对记录: {:?}", path))?;
+            let records: Vec<PairedPeer> = serde_json::from_str(&content)
+                .with_context(|| format!("无法解析配对记录: {:?}", path))?;
+            for record in records {
+                peers.insert(record.did.clone(), record);
+            }
+        }
+
+        Ok(Self {
+            path: Some(path),
+            peers: Arc::new(peers),
+        })
+    }
+
+    /// 添加可信对端并持久化
+    pub fn add(&self, peer: PairedPeer) -> Result<()> {
+        self.peers.insert(peer.did.clone(), peer);
+        self.save()
+    }
+
+    /// 移除可信对端，返回是否存在
+    pub fn remove(&self, did: &str) -> Result<bool> {
+        let removed = self.peers.remove(did).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// 是否为已配对的可信对端
+    pub fn is_trusted(&self, did: &str) -> bool {
+        self.peers.contains_key(did)
+    }
+
+    /// 获取配对记录
+    pub fn get(&self, did: &str) -> Option<PairedPeer> {
+        self.peers.get(did).map(|p| p.clone())
+    }
+
+    /// 全部配对记录（按DID排序）
+    pub fn list(&self) -> Vec<PairedPeer> {
+        let mut peers: Vec<PairedPeer> = self.peers.iter().map(|p| p.clone()).collect();
+        peers.sort_by(|a, b| a.did.cmp(&b.did));
+        peers
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建配对记录目录: {:?}", parent))?;
+        }
+
+        let content = serde_json::to_string_pretty(&self.list()).context("序列化配对记录失败")?;
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, content)
+            .with_context(|| format!("无法写入配对记录: {:?}", temp_path))?;
+        std::fs::rename(&temp_path, path)
+            .with_context(|| format!("无法保存配对记录: {:?}", path))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairing_matching_sas() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+
+        let (mut initiator, commit) = PairingSession::initiate(&alice).unwrap();
+        let (mut responder, response) = PairingSession::respond(&bob, &commit).unwrap();
+        let reveal = initiator.handle_response(&alice, &response).unwrap();
+        responder.handle_reveal(&reveal).unwrap();
+
+        let sas = initiator.sas().unwrap();
+        assert_eq!(sas.len(), 6);
+        assert_eq!(Some(sas), responder.sas());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = PairedPeerStore::open(dir.path().join("peers.json")).unwrap();
+        store.add(responder.confirm().unwrap()).unwrap();
+        let bob_view = initiator.confirm().unwrap();
+        assert_eq!(bob_view.did, bob.did);
+
+        let reopened = PairedPeerStore::open(dir.path().join("peers.json")).unwrap();
+        assert!(reopened.is_trusted(&alice.did));
+        assert_eq!(reopened.get(&alice.did).unwrap().fingerprint, bob_view.fingerprint);
+    }
+
+    #[test]
+    fn test_mitm_changes_sas() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+
+        // Mallory分别与双方配对，双方看到的SAS不同
+        let (mut alice_session, commit) = PairingSession::initiate(&alice).unwrap();
+        let (mut mallory_to_alice, response) = PairingSession::respond(&mallory, &commit).unwrap();
+        let reveal = alice_session.handle_response(&alice, &response).unwrap();
+        mallory_to_alice.handle_reveal(&reveal).unwrap();
+
+        let (mut mallory_to_bob, commit) = PairingSession::initiate(&mallory).unwrap();
+        let (mut bob_session, response) = PairingSession::respond(&bob, &commit).unwrap();
+        let reveal = mallory_to_bob.handle_response(&mallory, &response).unwrap();
+        bob_session.handle_reveal(&reveal).unwrap();
+
+        assert_ne!(alice_session.sas(), bob_session.sas());
+        bob_session.reject();
+        assert!(bob_session.confirm().is_err());
+    }
+
+    #[test]
+    fn test_reveal_must_match_commitment() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+
+        let (mut initiator, commit) = PairingSession::initiate(&alice).unwrap();
+        let (mut responder, response) = PairingSession::respond(&bob, &commit).unwrap();
+        let mut reveal = initiator.handle_response(&alice, &response).unwrap();
+
+        // 发起方事后更换临时公钥（重新签名也无法通过承诺校验）
+        reveal.ephemeral_public = X25519PublicKey::from(&StaticSecret::random_from_rng(rand::thread_rng())).to_bytes();
+        reveal.signature = alice.sign(&signing_data(&reveal, |r| r.signature = Vec::new()).unwrap()).unwrap();
+        assert!(responder.handle_reveal(&reveal).is_err());
+        assert_eq!(responder.state(), PairingState::Rejected);
+    }
+}
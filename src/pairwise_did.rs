@@ -0,0 +1,218 @@
+// DIAP Rust SDK - 成对DID模块
+// 为每个交互对象从主密钥确定性派生独立的did:key，降低智能体在不同关系之间的可关联性
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use crate::key_manager::KeyPair;
+
+/// 派生域分隔标签
+const PAIRWISE_DERIVATION_TAG: &[u8] = b"DIAP_PAIRWISE_DID_V1";
+
+/// 关联证明
+/// 主身份与成对身份互相签名，按需向交互对象证明二者属于同一智能体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkageProof {
+    /// 主DID
+    pub master_did: String,
+
+    /// 成对DID
+    pub pairwise_did: String,
+
+    /// 交互对象DID
+    pub counterparty_did: String,
+
+    /// 签发时间
+    pub issued_at: String,
+
+    /// 主密钥签名（base64）
+    pub master_signature: String,
+
+    /// 成对密钥签名（base64）
+    pub pairwise_signature: String,
+}
+
+impl LinkageProof {
+    /// 验证关联证明（两个签名都必须有效）
+    pub fn verify(&self) -> Result<bool> {
+        let data = Self::signing_data(
+            &self.master_did,
+            &self.pairwise_did,
+            &self.counterparty_did,
+            &self.issued_at,
+        );
+
+        let master_ok = Self::verify_signature(&self.master_did, data.as_bytes(), &self.master_signature)?;
+        let pairwise_ok = Self::verify_signature(&self.pairwise_did, data.as_bytes(), &self.pairwise_signature)?;
+
+        Ok(master_ok && pairwise_ok)
+    }
+
+    fn verify_signature(did: &str, data: &[u8], signature_b64: &str) -> Result<bool> {
+        let sig_bytes = general_purpose::STANDARD.decode(signature_b64)
+            .context("解码签名失败")?;
+        KeyPair::verify_with_did_key(did, data, &sig_bytes)
+    }
+
+    fn signing_data(master_did: &str, pairwise_did: &str, counterparty_did: &str, issued_at: &str) -> String {
+        format!("DIAP_PAIRWISE_LINK:{}:{}:{}:{}", master_did, pairwise_did, counterparty_did, issued_at)
+    }
+}
+
+/// 成对身份管理器
+/// 同一主密钥对同一交互对象总是派生出相同的成对DID
+#[derive(Clone)]
+pub struct PairwiseIdentityManager {
+    /// 主密钥对（派生种子）
+    master: Arc<KeyPair>,
+
+    /// 交互对象DID -> 成对密钥对
+    identities: Arc<DashMap<String, KeyPair>>,
+
+    /// 成对DID -> 交互对象DID（用于处理传入消息）
+    reverse_index: Arc<DashMap<String, String>>,
+}
+
+impl PairwiseIdentityManager {
+    /// 创建新的成对身份管理器
+    pub fn new(master: KeyPair) -> Self {
+        log::info!("🎭 创建成对身份管理器，主DID: {}", master.did);
+
+        Self {
+            master: Arc::new(master),
+            identities: Arc::new(DashMap::new()),
+            reverse_index: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// 获取主DID
+    pub fn master_did(&self) -> &str {
+        &self.master.did
+    }
+
+    /// 获取（或派生）与指定交互对象使用的成对身份
+    pub fn identity_for(&self, counterparty_did: &str) -> Result<KeyPair> {
+        if let Some(keypair) = self.identities.get(counterparty_did) {
+            return Ok(keypair.clone());
+        }
+
+        let keypair = Self::derive_keypair(&self.master, counterparty_did)?;
+        self.identities.insert(counterparty_did.to_string(), keypair.clone());
+        self.reverse_index.insert(keypair.did.clone(), counterparty_did.to_string());
+
+        log::debug!("派生成对DID: {} -> {}", counterparty_did, keypair.did);
+        Ok(keypair)
+    }
+
+    /// 发起联系时自动选择身份：有明确接收者时使用成对身份，广播时使用主身份
+    pub fn select_identity(&self, to_did: Option<&str>) -> Result<KeyPair> {
+        match to_did {
+            Some(counterparty) => self.identity_for(counterparty),
+            None => Ok((*self.master).clone()),
+        }
+    }
+
+    /// 根据成对DID查找对应的交互对象
+    pub fn counterparty_for(&self, pairwise_did: &str) -> Option<String> {
+        self.reverse_index.get(pairwise_did).map(|c| c.clone())
+    }
+
+    /// 生成关联证明（按需披露成对DID与主DID的关系）
+    pub fn linkage_proof(&self, counterparty_did: &str) -> Result<LinkageProof> {
+        let pairwise = self.identity_for(counterparty_did)?;
+        let issued_at = chrono::Utc::now().to_rfc3339();
+
+        let data = LinkageProof::signing_data(
+            &self.master.did,
+            &pairwise.did,
+            counterparty_did,
+            &issued_at,
+        );
+
+        let master_signature = self.master.sign(data.as_bytes())?;
+        let pairwise_signature = pairwise.sign(data.as_bytes())?;
+
+        log::info!("🔗 生成关联证明: {} -> {}", pairwise.did, self.master.did);
+
+        Ok(LinkageProof {
+            master_did: self.master.did.clone(),
+            pairwise_did: pairwise.did,
+            counterparty_did: counterparty_did.to_string(),
+            issued_at,
+            master_signature: general_purpose::STANDARD.encode(master_signature),
+            pairwise_signature: general_purpose::STANDARD.encode(pairwise_signature),
+        })
+    }
+
+    /// 忘记与指定交互对象的成对身份（再次联系时会重新派生出相同的DID）
+    pub fn forget(&self, counterparty_did: &str) -> Option<String> {
+        let (_, keypair) = self.identities.remove(counterparty_did)?;
+        self.reverse_index.remove(&keypair.did);
+        Some(keypair.did)
+    }
+    
+    /// 已建立成对身份的交互对象列表
+    pub fn known_counterparties(&self) -> Vec<String> {
+        self.identities.iter().map(|entry| entry.key().clone()).collect()
+    }
+
+    /// 从主密钥和交互对象DID确定性派生密钥对
+    fn derive_keypair(master: &KeyPair, counterparty_did: &str) -> Result<KeyPair> {
+        let mut hasher = Sha256::new();
+        hasher.update(PAIRWISE_DERIVATION_TAG);
+        hasher.update(master.private_key);
+        hasher.update(counterparty_did.as_bytes());
+        let seed = hasher.finalize();
+
+        let mut private_key = [0u8; 32];
+        private_key.copy_from_slice(&seed);
+        KeyPair::from_private_key(private_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairwise_derivation_is_deterministic() {
+        let master = KeyPair::generate().unwrap();
+        let manager1 = PairwiseIdentityManager::new(master.clone());
+        let manager2 = PairwiseIdentityManager::new(master.clone());
+
+        let a1 = manager1.identity_for("did:key:alice").unwrap();
+        let a2 = manager2.identity_for("did:key:alice").unwrap();
+        let b1 = manager1.identity_for("did:key:bob").unwrap();
+
+        assert_eq!(a1.did, a2.did);
+        assert_ne!(a1.did, b1.did);
+        assert_ne!(a1.did, master.did);
+        assert_eq!(manager1.counterparty_for(&a1.did), Some("did:key:alice".to_string()));
+    }
+
+    #[test]
+    fn test_select_identity() {
+        let master = KeyPair::generate().unwrap();
+        let manager = PairwiseIdentityManager::new(master.clone());
+
+        assert_eq!(manager.select_identity(None).unwrap().did, master.did);
+        assert_ne!(manager.select_identity(Some("did:key:bob")).unwrap().did, master.did);
+    }
+
+    #[test]
+    fn test_linkage_proof() {
+        let master = KeyPair::generate().unwrap();
+        let manager = PairwiseIdentityManager::new(master);
+
+        let proof = manager.linkage_proof("did:key:alice").unwrap();
+        assert!(proof.verify().unwrap());
+
+        // 替换成对DID后验证应失败
+        let mut tampered = proof.clone();
+        tampered.pairwise_did = manager.identity_for("did:key:bob").unwrap().did;
+        assert!(!tampered.verify().unwrap());
+    }
+}
@@ -0,0 +1,305 @@
+// DIAP Rust SDK - PeerID与DID绑定
+// libp2p身份密钥和DID密钥默认是两把独立的密钥。两种方式把它们绑定起来，使传输层PeerID和应用层DID签名可以互相校验：
+//   派生：libp2p密钥直接由DID的Ed25519密钥派生，PeerID可由did:key计算得到，无需额外数据
+//   联合签名：两把密钥分别对同一段绑定声明签名，声明随消息发布，接收方记录后用于后续校验
+// 会话中途出现与绑定不符的PeerID即视为冒用
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use libp2p::identity::PublicKey;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::key_manager::{KeyPair, Signer};
+use crate::libp2p_identity::LibP2PIdentity;
+use crate::pubsub_authenticator::{AuthenticatedMessage, PubSubMessageType};
+
+/// PeerID绑定声明消息类型标识（PubSubMessageType::Custom）
+pub const PEER_BINDING_MESSAGE_TYPE: &str = "peer_binding";
+
+/// 绑定声明签名域分隔标签
+const PEER_BINDING_TAG: &str = "DIAP_PEER_BINDING_V1";
+
+/// 由did:key派生的PeerID（libp2p密钥由DID密钥派生时两者一致）
+pub fn derived_peer_id(did: &str) -> Result<PeerId> {
+    let public_key = KeyPair::public_key_from_did_key(did)?;
+    let public_key = libp2p::identity::ed25519::PublicKey::try_from_bytes(&public_key)
+        .context("无效的Ed25519公钥")?;
+    Ok(PublicKey::from(public_key).to_peer_id())
+}
+
+/// DID与PeerID的联合签名绑定声明
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerIdBinding {
+    /// DID
+    pub did: String,
+
+    /// PeerID（base58）
+    pub peer_id: String,
+
+    /// libp2p公钥（protobuf编码，base64）
+    pub peer_public_key: String,
+
+    /// 声明时间（Unix秒，新的声明替换旧的）
+    pub issued_at: u64,
+
+    /// DID密钥签名（base64）
+    pub did_signature: String,
+
+    /// libp2p密钥签名（base64）
+    pub peer_signature: String,
+}
+
+impl PeerIdBinding {
+    /// 用DID签名器和libp2p身份联合签名
+    pub fn create(signer: &dyn Signer, identity: &LibP2PIdentity, issued_at: u64) -> Result<Self> {
+        let mut binding = Self {
+            did: signer.did(),
+            peer_id: identity.peer_id_string(),
+            peer_public_key: general_purpose::STANDARD.encode(identity.keypair().public().encode_protobuf()),
+            issued_at,
+            did_signature: String::new(),
+            peer_signature: String::new(),
+        };
+        let statement = binding.statement();
+        binding.did_signature = general_purpose::STANDARD.encode(signer.sign(&statement)?);
+        binding.peer_signature = general_purpose::STANDARD.encode(
+            identity.keypair().sign(&statement).context("libp2p密钥签名失败")?
+        );
+        Ok(binding)
+    }
+
+    /// 双方签名的声明内容
+    fn statement(&self) -> Vec<u8> {
+        format!("{}:{}:{}:{}", PEER_BINDING_TAG, self.did, self.peer_id, self.issued_at).into_bytes()
+    }
+
+    /// 验证两个签名，且libp2p公钥确实对应声明的PeerID
+    pub fn verify(&self) -> Result<PeerId> {
+        let peer_id: PeerId = self.peer_id.parse().context("无效的PeerID")?;
+        let public_key = PublicKey::try_decode_protobuf(
+            &general_purpose::STANDARD.decode(&self.peer_public_key).context("解码libp2p公钥失败")?
+        ).context("解析libp2p公钥失败")?;
+        if public_key.to_peer_id() != peer_id {
+            anyhow::bail!("libp2p公钥与PeerID不符: {}", self.peer_id);
+        }
+
+        let statement = self.statement();
+        let did_signature = general_purpose::STANDARD.decode(&self.did_signature).context("解码DID签名失败")?;
+        if !KeyPair::verify_with_did_key(&self.did, &statement, &did_signature)? {
+            anyhow::bail!("绑定声明的DID签名无效");
+        }
+        let peer_signature = general_purpose::STANDARD.decode(&self.peer_signature).context("解码libp2p签名失败")?;
+        if !public_key.verify(&statement, &peer_signature) {
+            anyhow::bail!("绑定声明的libp2p签名无效");
+        }
+        Ok(peer_id)
+    }
+
+    /// 序列化为消息内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化PeerID绑定失败")
+    }
+
+    /// 从认证消息中解析绑定声明（声明的DID和PeerID必须与消息发送者一致）
+    pub fn from_message(message: &AuthenticatedMessage) -> Result<Self> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == PEER_BINDING_MESSAGE_TYPE => {}
+            _ => anyhow::bail!("不是PeerID绑定消息: {}", message.message_id),
+        }
+        let binding: Self = serde_json::from_slice(&message.content).context("解析PeerID绑定失败")?;
+        if binding.did != message.from_did || binding.peer_id != message.from_peer_id {
+            anyhow::bail!("PeerID绑定与消息发送者不一致");
+        }
+        Ok(binding)
+    }
+}
+
+/// PeerID与DID的校验结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerBindingCheck {
+    /// PeerID由DID密钥派生
+    Derived,
+    /// PeerID与已记录的联合签名绑定一致
+    CoSigned,
+    /// 没有该DID的绑定信息
+    Unknown,
+    /// PeerID与绑定不符（可能被冒用）
+    Mismatch,
+}
+
+/// 已知的PeerID绑定
+#[derive(Default)]
+pub struct PeerBindingRegistry {
+    bindings: RwLock<HashMap<String, PeerIdBinding>>,
+}
+
+impl PeerBindingRegistry {
+    /// 创建空的绑定表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 验证并记录绑定声明（同一DID只保留最新的声明），返回是否替换了现有记录
+    pub fn record(&self, binding: PeerIdBinding) -> Result<bool> {
+        binding.verify()?;
+        let mut bindings = self.bindings.write().unwrap();
+        if let Some(existing) = bindings.get(&binding.did) {
+            if binding.issued_at <= existing.issued_at {
+                anyhow::bail!("PeerID绑定声明不比现有记录新: {}", binding.did);
+            }
+        }
+        log::info!("🔗 记录PeerID绑定: {} -> {}", binding.did, binding.peer_id);
+        Ok(bindings.insert(binding.did.clone(), binding).is_some())
+    }
+
+    /// DID当前绑定的PeerID
+    pub fn bound_peer(&self, did: &str) -> Option<String> {
+        self.bindings.read().unwrap().get(did).map(|binding| binding.peer_id.clone())
+    }
+
+    /// 校验DID与PeerID是否一致
+    /// 已记录联合签名绑定时以绑定为准（即使PeerID也能由DID派生）
+    pub fn check(&self, did: &str, peer_id: &str) -> PeerBindingCheck {
+        if let Some(bound) = self.bound_peer(did) {
+            return if bound == peer_id { PeerBindingCheck::CoSigned } else { PeerBindingCheck::Mismatch };
+        }
+        match derived_peer_id(did) {
+            Ok(derived) if derived.to_base58() == peer_id => PeerBindingCheck::Derived,
+            _ => PeerBindingCheck::Unknown,
+        }
+    }
+
+    /// 移除DID的绑定
+    pub fn forget(&self, did: &str) {
+        self.bindings.write().unwrap().remove(did);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_and_cosigned_bindings() {
+        let keypair = KeyPair::generate().unwrap();
+        let registry = PeerBindingRegistry::new();
+
+        // 派生：PeerID可由did:key计算
+        let derived = LibP2PIdentity::from_did_keypair(&keypair).unwrap();
+        assert_eq!(derived_peer_id(&keypair.did).unwrap(), *derived.peer_id());
+        assert_eq!(registry.check(&keypair.did, &derived.peer_id_string()), PeerBindingCheck::Derived);
+
+        // 联合签名：独立的libp2p密钥
+        let identity = LibP2PIdentity::generate().unwrap();
+        assert_eq!(registry.check(&keypair.did, &identity.peer_id_string()), PeerBindingCheck::Unknown);
+        let binding = PeerIdBinding::create(&keypair, &identity, 100).unwrap();
+        assert!(!registry.record(binding.clone()).unwrap());
+        assert_eq!(registry.check(&keypair.did, &identity.peer_id_string()), PeerBindingCheck::CoSigned);
+        assert_eq!(registry.check(&keypair.did, &derived.peer_id_string()), PeerBindingCheck::Mismatch);
+        assert!(registry.record(binding.clone()).is_err(), "旧声明不能替换");
+
+        // 冒用者用自己的libp2p密钥声明他人的DID
+        let mut forged = PeerIdBinding::create(&keypair, &identity, 200).unwrap();
+        let attacker = LibP2PIdentity::generate().unwrap();
+        forged.peer_id = attacker.peer_id_string();
+        forged.peer_public_key = general_purpose::STANDARD.encode(attacker.keypair().public().encode_protobuf());
+        assert!(forged.verify().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_detects_peer_spoofing() {
+        use crate::did_resolver::DIDResolver;
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::CallbackSigner;
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+        use crate::verification_hint::{HintSource, VerificationHint, DEFAULT_HINT_TTL};
+        use std::sync::Arc;
+
+        struct Hints(HashMap<String, VerificationHint>);
+
+        #[async_trait::async_trait]
+        impl HintSource for Hints {
+            async fn lookup(&self, did: &str) -> Result<Option<VerificationHint>> {
+                Ok(self.0.get(did).cloned())
+            }
+        }
+
+        fn offline_client() -> IpfsClient {
+            let client = IpfsClient::new_public_only(1);
+            for gateway in client.public_gateways() {
+                client.remove_gateway(&gateway);
+            }
+            client
+        }
+
+        async fn sender(keypair: &KeyPair, peer_id: PeerId) -> PubsubAuthenticator {
+            let kp = keypair.clone();
+            let signer = CallbackSigner::new(kp.public_key, Arc::new(move |data| kp.sign(data))).unwrap();
+            let authenticator = PubsubAuthenticator::new(IdentityManager::new(offline_client()), None, None);
+            authenticator.set_local_signer(Arc::new(signer), peer_id, "QmCurrent".to_string()).await.unwrap();
+            authenticator
+        }
+
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let (derived_key, cosigned_key) = (KeyPair::generate().unwrap(), KeyPair::generate().unwrap());
+        let hints = [&derived_key, &cosigned_key].into_iter()
+            .map(|kp| {
+                let document = DIDResolver::resolve_did_key(&kp.did).unwrap();
+                (kp.did.clone(), VerificationHint::sign(kp, &document, "QmCurrent", now, DEFAULT_HINT_TTL).unwrap())
+            })
+            .collect();
+        let receiver = PubsubAuthenticator::new(IdentityManager::new(offline_client()), None, None)
+            .with_verification_hints(Arc::new(Hints(hints)))
+            .with_required_peer_binding(true);
+
+        // 派生：PeerID由DID密钥计算，无需额外声明
+        let derived_identity = LibP2PIdentity::from_did_keypair(&derived_key).unwrap();
+        let alice = sender(&derived_key, *derived_identity.peer_id()).await;
+        let message = alice.create_heartbeat("chat").await.unwrap();
+        let verification = receiver.verify_message_from_peer(&message, derived_identity.peer_id()).await.unwrap();
+        assert!(verification.verified, "{:?}", verification.details);
+        let message = alice.create_heartbeat("chat").await.unwrap();
+        assert!(!receiver.verify_message_from_peer(&message, &PeerId::random()).await.unwrap().verified);
+
+        // 联合签名：独立的libp2p身份，先发布绑定
+        let identity = LibP2PIdentity::generate().unwrap();
+        let bob = sender(&cosigned_key, *identity.peer_id()).await;
+        let message = bob.create_heartbeat("chat").await.unwrap();
+        assert!(!receiver.verify_message(&message).await.unwrap().verified, "缺少绑定");
+
+        let binding = bob.create_peer_binding_message("chat", &identity).await.unwrap();
+        assert!(!receiver.handle_peer_binding(&binding).unwrap());
+        let message = bob.create_heartbeat("chat").await.unwrap();
+        assert!(receiver.verify_message_from_peer(&message, identity.peer_id()).await.unwrap().verified);
+
+        // 会话中途换成其他PeerID转发bob的消息
+        let attacker = PeerId::random();
+        let mut spoofed = bob.create_heartbeat("chat").await.unwrap();
+        spoofed.from_peer_id = attacker.to_base58();
+        let verification = receiver.verify_message_from_peer(&spoofed, &attacker).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d.contains("可能被冒用")));
+
+        // 经gossipsub收到的消息按发布者PeerID检查
+        #[cfg(feature = "node")]
+        {
+            use crate::transport::TransportMessage;
+            let delivered = |source: PeerId, message: &crate::pubsub_authenticator::AuthenticatedMessage| TransportMessage {
+                from: source.to_base58(),
+                source_peer: Some(source),
+                topic: "chat".to_string(),
+                data: PubsubAuthenticator::serialize_message(message).unwrap(),
+            };
+            let message = alice.create_heartbeat("chat").await.unwrap();
+            let (_, verification) = delivered(*derived_identity.peer_id(), &message).verify_with(&receiver).await.unwrap();
+            assert!(verification.verified, "{:?}", verification.details);
+            let message = alice.create_heartbeat("chat").await.unwrap();
+            let (_, verification) = delivered(PeerId::random(), &message).verify_with(&receiver).await.unwrap();
+            assert!(!verification.verified);
+        }
+    }
+}
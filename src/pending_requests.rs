@@ -0,0 +1,218 @@
+// DIAP Rust SDK - 请求-响应匹配
+// 按请求ID登记oneshot通道（可指定应答方），收到对应响应时唤醒等待方，超时后自动移除，
+// 使send_request类接口可以直接await响应
+
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::error::{DiapError, DiapResult};
+
+/// 默认请求超时
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 登记的请求：期望的应答方（None表示不限）和等待方
+struct PendingEntry<R> {
+    responder: Option<String>,
+    sender: oneshot::Sender<R>,
+}
+
+type PendingMap<R> = Arc<DashMap<String, PendingEntry<R>>>;
+
+/// 等待响应的请求表
+pub struct PendingRequests<R> {
+    pending: PendingMap<R>,
+    request_timeout: Duration,
+}
+
+impl<R> Clone for PendingRequests<R> {
+    fn clone(&self) -> Self {
+        Self {
+            pending: self.pending.clone(),
+            request_timeout: self.request_timeout,
+        }
+    }
+}
+
+impl<R> Default for PendingRequests<R> {
+    fn default() -> Self {
+        Self::new(DEFAULT_REQUEST_TIMEOUT)
+    }
+}
+
+impl<R> PendingRequests<R> {
+    /// 创建请求表，request_timeout为默认等待时间
+    pub fn new(request_timeout: Duration) -> Self {
+        Self {
+            pending: Arc::new(DashMap::new()),
+            request_timeout,
+        }
+    }
+
+    /// 默认等待时间
+    pub fn request_timeout(&self) -> Duration {
+        self.request_timeout
+    }
+
+    /// 登记请求，返回等待响应的句柄（应在发送请求前登记，避免响应先于登记到达）
+    pub fn register(&self, request_id: &str) -> PendingResponse<R> {
+        self.insert(request_id, None)
+    }
+
+    /// 登记发往指定对端的请求，只接受该对端的响应（见 `complete_from`）
+    pub fn register_from(&self, request_id: &str, responder: impl Into<String>) -> PendingResponse<R> {
+        self.insert(request_id, Some(responder.into()))
+    }
+
+    fn insert(&self, request_id: &str, responder: Option<String>) -> PendingResponse<R> {
+        let (sender, receiver) = oneshot::channel();
+        if self.pending.insert(request_id.to_string(), PendingEntry { responder, sender }).is_some() {
+            log::warn!("⚠️ 请求ID重复登记，之前的等待方将收到取消: {}", request_id);
+        }
+
+        PendingResponse {
+            request_id: request_id.to_string(),
+            receiver,
+            pending: self.pending.clone(),
+            timeout: self.request_timeout,
+        }
+    }
+
+    /// 交付响应，返回是否有等待方（false表示未登记、已超时或等待方已放弃）
+    /// 登记时指定了应答方的请求只能通过 `complete_from` 完成
+    pub fn complete(&self, request_id: &str, response: R) -> bool {
+        match self.pending.remove_if(request_id, |_, entry| entry.responder.is_none()) {
+            Some((_, entry)) => entry.sender.send(response).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 交付来自responder的响应；应答方与登记时不符的响应被忽略，请求继续等待
+    pub fn complete_from(&self, request_id: &str, responder: &str, response: R) -> bool {
+        let matches = |entry: &PendingEntry<R>| entry.responder.as_deref().is_none_or(|expected| expected == responder);
+        match self.pending.remove_if(request_id, |_, entry| matches(entry)) {
+            Some((_, entry)) => entry.sender.send(response).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 是否在等待responder对该请求的响应
+    pub fn awaits(&self, request_id: &str, responder: &str) -> bool {
+        self.pending.get(request_id)
+            .is_some_and(|entry| entry.responder.as_deref().is_none_or(|expected| expected == responder))
+    }
+
+    /// 取消请求，等待方收到错误
+    pub fn cancel(&self, request_id: &str) -> bool {
+        self.pending.remove(request_id).is_some()
+    }
+
+    /// 取消所有请求（例如连接关闭时）
+    pub fn cancel_all(&self) {
+        self.pending.clear();
+    }
+
+    /// 是否仍在等待该请求
+    pub fn contains(&self, request_id: &str) -> bool {
+        self.pending.contains_key(request_id)
+    }
+
+    /// 等待中的请求数
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 是否没有等待中的请求
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// 等待中的响应
+pub struct PendingResponse<R> {
+    request_id: String,
+    receiver: oneshot::Receiver<R>,
+    pending: PendingMap<R>,
+    timeout: Duration,
+}
+
+impl<R> PendingResponse<R> {
+    /// 请求ID
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// 覆盖默认超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 等待响应，超时或被取消时返回P2P错误
+    pub async fn wait(self) -> DiapResult<R> {
+        let result = tokio::time::timeout(self.timeout, self.receiver).await;
+        match result {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(DiapError::p2p(format!("请求已取消: {}", self.request_id))),
+            Err(_) => {
+                self.pending.remove(&self.request_id);
+                Err(DiapError::p2p(format!(
+                    "等待响应超时（{}秒）: {}",
+                    self.timeout.as_secs_f64(),
+                    self.request_id
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_response_resolves_waiter() {
+        let requests: PendingRequests<String> = PendingRequests::new(Duration::from_secs(5));
+        let response = requests.register("r1");
+
+        let responder = requests.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            assert!(responder.complete("r1", "pong".to_string()));
+        });
+
+        assert_eq!(response.wait().await.unwrap(), "pong");
+        assert!(requests.is_empty());
+        // 迟到的响应没有等待方
+        assert!(!requests.complete("r1", "late".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_cancel() {
+        let requests: PendingRequests<u32> = PendingRequests::new(Duration::from_millis(20));
+
+        let err = requests.register("slow").wait().await.unwrap_err();
+        assert!(err.to_string().contains("超时"));
+        assert!(!requests.contains("slow"));
+
+        let response = requests.register("cancelled").with_timeout(Duration::from_secs(5));
+        assert!(requests.cancel("cancelled"));
+        assert!(response.wait().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_only_expected_responder_completes() {
+        let requests: PendingRequests<&str> = PendingRequests::new(Duration::from_secs(5));
+        let response = requests.register_from("r1", "node-b");
+
+        // 其他对端伪造的回复被忽略，请求继续等待
+        assert!(!requests.awaits("r1", "node-x"));
+        assert!(!requests.complete_from("r1", "node-x", "forged"));
+        assert!(!requests.complete("r1", "forged"));
+        assert!(requests.awaits("r1", "node-b"));
+
+        assert!(requests.complete_from("r1", "node-b", "pong"));
+        assert_eq!(response.wait().await.unwrap(), "pong");
+    }
+}
@@ -0,0 +1,387 @@
+// DIAP Rust SDK - Pin生命周期管理
+// 跟踪SDK发布过的全部CID（DID文档、注册表条目等），定期检查是否仍被pin，缺失时重新pin或告警
+
+use anyhow::Result;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::clock::{SharedClock, system_clock};
+use crate::ipfs_client::{IpfsClient, IpfsUploadResult};
+
+/// Pin状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PinStatus {
+    /// 尚未检查
+    Unverified,
+
+    /// 已确认pin
+    Pinned,
+
+    /// 缺失后已重新pin
+    Repinned,
+
+    /// 缺失（未能重新pin）
+    Missing,
+}
+
+/// Pin记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinRecord {
+    /// 内容CID
+    pub cid: String,
+
+    /// 发布时的名称（例如 did.json）
+    pub name: String,
+
+    /// 发布所用的提供商
+    pub provider: String,
+
+    /// 内容大小（字节）
+    pub size: u64,
+
+    /// 记录时间
+    pub pinned_at: u64,
+
+    /// 最近检查时间
+    pub last_verified: Option<u64>,
+
+    /// 当前状态
+    pub status: PinStatus,
+
+    /// 重新pin次数
+    pub repin_count: u32,
+}
+
+/// Pin检查报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PinVerificationReport {
+    /// 检查的CID数量
+    pub checked: usize,
+
+    /// 仍被pin的CID
+    pub healthy: Vec<String>,
+
+    /// 已重新pin的CID
+    pub repinned: Vec<String>,
+
+    /// 缺失且未能重新pin的CID
+    pub missing: Vec<String>,
+
+    /// 无法查询状态的CID及错误
+    pub errors: Vec<(String, String)>,
+}
+
+impl PinVerificationReport {
+    /// 是否全部正常
+    pub fn is_healthy(&self) -> bool {
+        self.missing.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// Pin跟踪表（IpfsClient上传成功后自动记录）
+#[derive(Clone)]
+pub struct PinTracker {
+    /// CID -> Pin记录
+    records: Arc<DashMap<String, PinRecord>>,
+
+    /// 时间源
+    clock: SharedClock,
+}
+
+impl PinTracker {
+    /// 创建跟踪表
+    pub fn new() -> Self {
+        Self::new_with_clock(system_clock())
+    }
+
+    /// 使用指定时间源创建跟踪表
+    pub fn new_with_clock(clock: SharedClock) -> Self {
+        Self {
+            records: Arc::new(DashMap::new()),
+            clock,
+        }
+    }
+
+    /// 记录一次上传
+    pub fn record_upload(&self, result: &IpfsUploadResult, name: &str) {
+        self.track(&result.cid, name, &result.provider, result.size);
+    }
+
+    /// 记录需要保持pin的CID（已存在时只更新名称）
+    pub fn track(&self, cid: &str, name: &str, provider: &str, size: u64) {
+        if let Some(mut record) = self.records.get_mut(cid) {
+            record.name = name.to_string();
+            return;
+        }
+
+        self.records.insert(cid.to_string(), PinRecord {
+            cid: cid.to_string(),
+            name: name.to_string(),
+            provider: provider.to_string(),
+            size,
+            pinned_at: self.clock.now_secs(),
+            last_verified: None,
+            status: PinStatus::Unverified,
+            repin_count: 0,
+        });
+        log::debug!("📌 跟踪pin: {} ({})", cid, name);
+    }
+
+    /// 停止跟踪，返回是否存在
+    pub fn untrack(&self, cid: &str) -> bool {
+        self.records.remove(cid).is_some()
+    }
+
+    /// 获取单条记录
+    pub fn get(&self, cid: &str) -> Option<PinRecord> {
+        self.records.get(cid).map(|r| r.clone())
+    }
+
+    /// 全部记录（按记录时间排序）
+    pub fn list(&self) -> Vec<PinRecord> {
+        let mut records: Vec<PinRecord> = self.records.iter().map(|r| r.clone()).collect();
+        records.sort_by(|a, b| a.pinned_at.cmp(&b.pinned_at).then_with(|| a.cid.cmp(&b.cid)));
+        records
+    }
+
+    /// 记录数量
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    fn update_status(&self, cid: &str, status: PinStatus) {
+        if let Some(mut record) = self.records.get_mut(cid) {
+            record.last_verified = Some(self.clock.now_secs());
+            if status == PinStatus::Repinned {
+                record.repin_count += 1;
+            }
+            record.status = status;
+        }
+    }
+}
+
+impl Default for PinTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pin生命周期管理器
+#[derive(Clone)]
+pub struct PinManager {
+    /// 带跟踪的IPFS客户端
+    ipfs_client: IpfsClient,
+
+    /// Pin跟踪表
+    tracker: PinTracker,
+
+    /// 发现缺失时是否自动重新pin
+    auto_repin: bool,
+}
+
+impl PinManager {
+    /// 创建Pin管理器
+    /// 传入的客户端会挂载跟踪表，之后通过 `ipfs_client()` 上传的内容会被自动跟踪
+    pub fn new(ipfs_client: IpfsClient) -> Self {
+        Self::with_tracker(ipfs_client, PinTracker::new())
+    }
+
+    /// 使用指定跟踪表创建Pin管理器
+    pub fn with_tracker(ipfs_client: IpfsClient, tracker: PinTracker) -> Self {
+        Self {
+            ipfs_client: ipfs_client.with_pin_tracker(tracker.clone()),
+            tracker,
+            auto_repin: true,
+        }
+    }
+
+    /// 设置发现缺失时是否自动重新pin（关闭时只告警）
+    pub fn with_auto_repin(mut self, auto_repin: bool) -> Self {
+        self.auto_repin = auto_repin;
+        self
+    }
+
+    /// 带跟踪的IPFS客户端（用于DID发布等上传操作）
+    pub fn ipfs_client(&self) -> &IpfsClient {
+        &self.ipfs_client
+    }
+
+    /// Pin跟踪表
+    pub fn tracker(&self) -> &PinTracker {
+        &self.tracker
+    }
+
+    /// 手动跟踪已发布的CID
+    pub fn track(&self, cid: &str, name: &str) {
+        self.tracker.track(cid, name, "manual", 0);
+    }
+
+    /// 列出全部跟踪的pin
+    pub fn list_pins(&self) -> Vec<PinRecord> {
+        self.tracker.list()
+    }
+
+    /// 检查全部跟踪的CID是否仍被pin，缺失时按配置重新pin或告警
+    pub async fn verify_pins(&self) -> PinVerificationReport {
+        let mut report = PinVerificationReport::default();
+
+        for record in self.tracker.list() {
+            report.checked += 1;
+            let cid = record.cid;
+
+            match self.ipfs_client.is_pinned(&cid).await {
+                Ok(true) => {
+                    self.tracker.update_status(&cid, PinStatus::Pinned);
+                    report.healthy.push(cid);
+                }
+                Ok(false) if self.auto_repin => match self.ipfs_client.pin(&cid).await {
+                    Ok(()) => {
+                        log::warn!("📌 pin缺失，已重新pin: {}", cid);
+                        self.tracker.update_status(&cid, PinStatus::Repinned);
+                        report.repinned.push(cid);
+                    }
+                    Err(e) => {
+                        log::error!("❌ pin缺失且重新pin失败: {} ({})", cid, e);
+                        self.tracker.update_status(&cid, PinStatus::Missing);
+                        report.missing.push(cid);
+                    }
+                },
+                Ok(false) => {
+                    log::error!("❌ pin缺失: {}", cid);
+                    self.tracker.update_status(&cid, PinStatus::Missing);
+                    report.missing.push(cid);
+                }
+                Err(e) => {
+                    log::warn!("⚠️ 无法查询pin状态: {} ({})", cid, e);
+                    report.errors.push((cid, e.to_string()));
+                }
+            }
+        }
+
+        log::info!(
+            "📌 Pin检查完成: {} 个，正常 {}，重新pin {}，缺失 {}，错误 {}",
+            report.checked, report.healthy.len(), report.repinned.len(), report.missing.len(), report.errors.len()
+        );
+        report
+    }
+
+    /// 取消pin并停止跟踪
+    pub async fn unpin(&self, cid: &str) -> Result<()> {
+        self.ipfs_client.unpin(cid).await?;
+        self.tracker.untrack(cid);
+        Ok(())
+    }
+
+    /// 启动定期检查任务
+    pub fn start_repin_scheduler(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let manager = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(interval);
+            loop {
+                interval_timer.tick().await;
+
+                let report = manager.verify_pins().await;
+                if !report.is_healthy() {
+                    log::error!("❌ 定期pin检查发现问题: 缺失 {:?}，错误 {} 个", report.missing, report.errors.len());
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 模拟Kubo pin接口（add / pin/ls / pin/add / pin/rm）
+    async fn mock_pin_api(pinned: Arc<Mutex<HashSet<String>>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else { break };
+                let mut buf = vec![0u8; 64 * 1024];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+                let arg = path.split("arg=").nth(1).unwrap_or("").split('&').next().unwrap_or("").to_string();
+
+                let (status, body) = if path.starts_with("/api/v0/add") {
+                    pinned.lock().unwrap().insert("bafydid".to_string());
+                    (200, r#"{"Hash":"bafydid","Size":"10"}"#.to_string())
+                } else if path.starts_with("/api/v0/pin/ls") {
+                    if pinned.lock().unwrap().contains(&arg) {
+                        (200, "{}".to_string())
+                    } else {
+                        (500, format!(r#"{{"Message":"path '{}' is not pinned"}}"#, arg))
+                    }
+                } else if path.starts_with("/api/v0/pin/add") {
+                    pinned.lock().unwrap().insert(arg);
+                    (200, "{}".to_string())
+                } else if path.starts_with("/api/v0/pin/rm") {
+                    pinned.lock().unwrap().remove(&arg);
+                    (200, "{}".to_string())
+                } else {
+                    (404, String::new())
+                };
+
+                let reply = format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+                let _ = stream.write_all(reply.as_bytes()).await;
+            }
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn test_track_verify_and_repin() {
+        let pinned = Arc::new(Mutex::new(HashSet::new()));
+        let url = mock_pin_api(pinned.clone()).await;
+        let manager = PinManager::new(IpfsClient::new_with_remote_node(url.clone(), url, 5));
+
+        // 通过管理器的客户端上传的内容被自动跟踪
+        manager.ipfs_client().upload("{}", "did.json").await.unwrap();
+        assert_eq!(manager.list_pins()[0].cid, "bafydid");
+        assert_eq!(manager.list_pins()[0].status, PinStatus::Unverified);
+
+        let report = manager.verify_pins().await;
+        assert_eq!(report.healthy, vec!["bafydid".to_string()]);
+
+        // 节点丢失pin后自动重新pin
+        pinned.lock().unwrap().clear();
+        let report = manager.verify_pins().await;
+        assert_eq!(report.repinned, vec!["bafydid".to_string()]);
+        assert!(pinned.lock().unwrap().contains("bafydid"));
+        assert_eq!(manager.tracker().get("bafydid").unwrap().repin_count, 1);
+
+        manager.unpin("bafydid").await.unwrap();
+        assert!(manager.list_pins().is_empty());
+        assert!(pinned.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_missing_pin_alert_without_repin() {
+        let pinned = Arc::new(Mutex::new(HashSet::new()));
+        let url = mock_pin_api(pinned).await;
+        let manager = PinManager::new(IpfsClient::new_with_remote_node(url.clone(), url, 5))
+            .with_auto_repin(false);
+
+        manager.track("bafyregistry", "registry.json");
+        let report = manager.verify_pins().await;
+
+        assert!(!report.is_healthy());
+        assert_eq!(report.missing, vec!["bafyregistry".to_string()]);
+        assert_eq!(manager.tracker().get("bafyregistry").unwrap().status, PinStatus::Missing);
+    }
+}
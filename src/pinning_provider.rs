@@ -0,0 +1,461 @@
+// DIAP Rust SDK - Pin服务提供商
+// 可插拔的pin服务后端（Pinata、Web3.Storage、Filebase、NFT.Storage及任意IPFS Pinning Service API），部署时按需选择而无需修改IpfsClient
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+
+use crate::ipfs_client::{HttpStatusError, IpfsClient, IpfsUploadResult, PinataConfig};
+
+/// Pin服务提供商
+#[async_trait]
+pub trait PinningProvider: Send + Sync {
+    /// 提供商名称（写入IpfsUploadResult.provider）
+    fn name(&self) -> &str;
+
+    /// 上传内容并pin
+    async fn upload(&self, client: &Client, content: &str, name: &str) -> Result<IpfsUploadResult>;
+
+    /// 按CID pin已有内容
+    async fn pin(&self, client: &Client, cid: &str) -> Result<()>;
+
+    /// 查询CID是否已被pin
+    async fn is_pinned(&self, client: &Client, cid: &str) -> Result<bool>;
+
+    /// 取消pin
+    async fn unpin(&self, client: &Client, cid: &str) -> Result<()>;
+}
+
+/// 检查响应状态，失败时返回带状态码的错误（供重试策略判断）
+async fn check_status(response: reqwest::Response, operation: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let error_text = response.text().await.unwrap_or_default();
+    Err(HttpStatusError::error(status, format!("{}失败 {}: {}", operation, status, error_text)))
+}
+
+/// Pinata
+#[derive(Debug, Clone)]
+pub struct PinataProvider {
+    /// API凭据
+    config: PinataConfig,
+
+    /// API地址
+    base_url: String,
+}
+
+impl PinataProvider {
+    /// 使用API密钥创建
+    pub fn new(api_key: String, api_secret: String) -> Self {
+        Self {
+            config: PinataConfig { api_key, api_secret },
+            base_url: "https://api.pinata.cloud".to_string(),
+        }
+    }
+
+    /// 使用自定义API地址
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .header("pinata_api_key", &self.config.api_key)
+            .header("pinata_secret_api_key", &self.config.api_secret)
+    }
+}
+
+#[async_trait]
+impl PinningProvider for PinataProvider {
+    fn name(&self) -> &str {
+        "Pinata"
+    }
+
+    /// Pinata的JSON接口只接受JSON内容（DID文档）
+    async fn upload(&self, client: &Client, content: &str, name: &str) -> Result<IpfsUploadResult> {
+        let body = serde_json::json!({
+            "pinataContent": serde_json::from_str::<serde_json::Value>(content)?,
+            "pinataMetadata": {
+                "name": name,
+                "keyvalues": {
+                    "type": "did-document",
+                    "uploaded_by": "diap-rs-sdk"
+                }
+            }
+        });
+
+        let response = self.authorize(client.post(format!("{}/pinning/pinJSONToIPFS", self.base_url)))
+            .json(&body)
+            .send()
+            .await
+            .context("发送请求到Pinata失败")?;
+        let response = check_status(response, "Pinata上传").await?;
+
+        #[derive(Deserialize)]
+        struct PinataResponse {
+            #[serde(rename = "IpfsHash")]
+            ipfs_hash: String,
+            #[serde(rename = "PinSize")]
+            pin_size: u64,
+        }
+
+        let pinata_response: PinataResponse = response.json().await
+            .context("解析Pinata响应失败")?;
+
+        Ok(IpfsUploadResult {
+            cid: pinata_response.ipfs_hash,
+            size: pinata_response.pin_size,
+            uploaded_at: chrono::Utc::now().to_rfc3339(),
+            provider: self.name().to_string(),
+        })
+    }
+
+    async fn pin(&self, client: &Client, cid: &str) -> Result<()> {
+        let response = self.authorize(client.post(format!("{}/pinning/pinByHash", self.base_url)))
+            .json(&serde_json::json!({ "hashToPin": cid }))
+            .send()
+            .await
+            .context("发送Pinata pin请求失败")?;
+        check_status(response, "Pinata pin").await?;
+        Ok(())
+    }
+
+    async fn is_pinned(&self, client: &Client, cid: &str) -> Result<bool> {
+        let url = format!("{}/data/pinList?hashContains={}&status=pinned", self.base_url, cid);
+        let response = self.authorize(client.get(&url))
+            .send()
+            .await
+            .context("发送Pinata查询请求失败")?;
+        let response = check_status(response, "Pinata查询").await?;
+
+        #[derive(Deserialize)]
+        struct PinListResponse {
+            count: u64,
+        }
+
+        let pin_list: PinListResponse = response.json().await
+            .context("解析Pinata响应失败")?;
+        Ok(pin_list.count > 0)
+    }
+
+    async fn unpin(&self, client: &Client, cid: &str) -> Result<()> {
+        let response = self.authorize(client.delete(format!("{}/pinning/unpin/{}", self.base_url, cid)))
+            .send()
+            .await
+            .context("发送Pinata取消pin请求失败")?;
+        check_status(response, "Pinata取消pin").await?;
+        Ok(())
+    }
+}
+
+/// 通用IPFS Pinning Service API（https://ipfs.github.io/pinning-services-api-spec/）
+/// 只能按CID pin已有内容，不支持上传
+#[derive(Debug, Clone)]
+pub struct PinningServiceProvider {
+    /// 提供商名称
+    name: String,
+
+    /// API端点（不含 /pins）
+    endpoint: String,
+
+    /// 访问令牌
+    token: String,
+}
+
+impl PinningServiceProvider {
+    /// 创建通用Pinning Service API提供商
+    pub fn new(name: &str, endpoint: &str, token: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// 查询CID的pin请求
+    async fn list_requests(&self, client: &Client, cid: &str, status: &str) -> Result<Vec<String>> {
+        let url = format!("{}/pins?cid={}&status={}", self.endpoint, cid, status);
+        let response = client.get(&url)
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .with_context(|| format!("发送{}查询请求失败", self.name))?;
+        let response = check_status(response, &format!("{}查询", self.name)).await?;
+
+        #[derive(Deserialize)]
+        struct PinStatus {
+            requestid: String,
+        }
+        #[derive(Deserialize)]
+        struct PinResults {
+            #[serde(default)]
+            results: Vec<PinStatus>,
+        }
+
+        let results: PinResults = response.json().await
+            .with_context(|| format!("解析{}响应失败", self.name))?;
+        Ok(results.results.into_iter().map(|r| r.requestid).collect())
+    }
+}
+
+#[async_trait]
+impl PinningProvider for PinningServiceProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn upload(&self, _client: &Client, _content: &str, _name: &str) -> Result<IpfsUploadResult> {
+        anyhow::bail!("{}不支持上传内容，只能按CID pin", self.name)
+    }
+
+    async fn pin(&self, client: &Client, cid: &str) -> Result<()> {
+        let response = client.post(format!("{}/pins", self.endpoint))
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({ "cid": cid }))
+            .send()
+            .await
+            .with_context(|| format!("发送{} pin请求失败", self.name))?;
+        check_status(response, &format!("{} pin", self.name)).await?;
+        Ok(())
+    }
+
+    async fn is_pinned(&self, client: &Client, cid: &str) -> Result<bool> {
+        Ok(!self.list_requests(client, cid, "pinned").await?.is_empty())
+    }
+
+    async fn unpin(&self, client: &Client, cid: &str) -> Result<()> {
+        for request_id in self.list_requests(client, cid, "queued,pinning,pinned,failed").await? {
+            let response = client.delete(format!("{}/pins/{}", self.endpoint, request_id))
+                .bearer_auth(&self.token)
+                .send()
+                .await
+                .with_context(|| format!("发送{}取消pin请求失败", self.name))?;
+            check_status(response, &format!("{}取消pin", self.name)).await?;
+        }
+        Ok(())
+    }
+}
+
+/// 上传接口的响应格式
+#[derive(Debug, Clone, Copy)]
+enum UploadApi {
+    /// Web3.Storage: POST /upload，返回 {"cid": ...}
+    Web3Storage,
+
+    /// NFT.Storage: POST /upload，返回 {"ok": true, "value": {"cid": ..., "size": ...}}
+    NftStorage,
+
+    /// Kubo RPC: POST /api/v0/add（multipart）
+    KuboRpc,
+}
+
+/// 上传接口 + Pinning Service API 组合的提供商（Web3.Storage、NFT.Storage、Filebase）
+#[derive(Debug, Clone)]
+pub struct HostedPinningProvider {
+    /// 上传接口类型
+    upload_api: UploadApi,
+
+    /// 上传接口地址
+    upload_url: String,
+
+    /// 访问令牌
+    token: String,
+
+    /// pin管理（Pinning Service API）
+    pinning: PinningServiceProvider,
+}
+
+impl HostedPinningProvider {
+    /// Web3.Storage
+    pub fn web3_storage(token: &str) -> Self {
+        Self {
+            upload_api: UploadApi::Web3Storage,
+            upload_url: "https://api.web3.storage/upload".to_string(),
+            token: token.to_string(),
+            pinning: PinningServiceProvider::new("Web3.Storage", "https://api.web3.storage", token),
+        }
+    }
+
+    /// NFT.Storage
+    pub fn nft_storage(token: &str) -> Self {
+        Self {
+            upload_api: UploadApi::NftStorage,
+            upload_url: "https://api.nft.storage/upload".to_string(),
+            token: token.to_string(),
+            pinning: PinningServiceProvider::new("NFT.Storage", "https://api.nft.storage", token),
+        }
+    }
+
+    /// Filebase（令牌为存储桶的IPFS RPC/Pinning令牌）
+    pub fn filebase(token: &str) -> Self {
+        Self {
+            upload_api: UploadApi::KuboRpc,
+            upload_url: "https://rpc.filebase.io/api/v0/add".to_string(),
+            token: token.to_string(),
+            pinning: PinningServiceProvider::new("Filebase", "https://api.filebase.io/v1/ipfs", token),
+        }
+    }
+
+    /// 使用自定义地址（自建网关或测试）
+    pub fn with_endpoints(mut self, upload_url: &str, pinning_endpoint: &str) -> Self {
+        self.upload_url = upload_url.to_string();
+        self.pinning = PinningServiceProvider::new(&self.pinning.name, pinning_endpoint, &self.token);
+        self
+    }
+}
+
+#[async_trait]
+impl PinningProvider for HostedPinningProvider {
+    fn name(&self) -> &str {
+        self.pinning.name()
+    }
+
+    async fn upload(&self, client: &Client, content: &str, name: &str) -> Result<IpfsUploadResult> {
+        let request = client.post(&self.upload_url).bearer_auth(&self.token);
+
+        let response = match self.upload_api {
+            UploadApi::KuboRpc => {
+                let form = reqwest::multipart::Form::new()
+                    .part("file", reqwest::multipart::Part::text(content.to_string()).file_name(name.to_string()));
+                let response = request.multipart(form).send().await
+                    .with_context(|| format!("发送请求到{}失败", self.name()))?;
+                let mut result = IpfsClient::parse_add_response(response).await?;
+                result.provider = self.name().to_string();
+                return Ok(result);
+            }
+            _ => request
+                .header("X-Name", name)
+                .body(content.to_string())
+                .send()
+                .await
+                .with_context(|| format!("发送请求到{}失败", self.name()))?,
+        };
+        let response = check_status(response, &format!("{}上传", self.name())).await?;
+        let json: serde_json::Value = response.json().await
+            .with_context(|| format!("解析{}响应失败", self.name()))?;
+
+        let value = match self.upload_api {
+            UploadApi::NftStorage => &json["value"],
+            _ => &json,
+        };
+        let cid = value["cid"].as_str()
+            .ok_or_else(|| anyhow::anyhow!("{}响应缺少CID", self.name()))?;
+
+        Ok(IpfsUploadResult {
+            cid: cid.to_string(),
+            size: value["size"].as_u64().unwrap_or(content.len() as u64),
+            uploaded_at: chrono::Utc::now().to_rfc3339(),
+            provider: self.name().to_string(),
+        })
+    }
+
+    async fn pin(&self, client: &Client, cid: &str) -> Result<()> {
+        self.pinning.pin(client, cid).await
+    }
+
+    async fn is_pinned(&self, client: &Client, cid: &str) -> Result<bool> {
+        self.pinning.is_pinned(client, cid).await
+    }
+
+    async fn unpin(&self, client: &Client, cid: &str) -> Result<()> {
+        self.pinning.unpin(client, cid).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 模拟上传接口（同时返回Web3.Storage和NFT.Storage格式）和Pinning Service API
+    async fn mock_hosted_api(pins: Arc<Mutex<HashMap<String, String>>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else { break };
+                let mut buf = vec![0u8; 64 * 1024];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let mut parts = request.split_whitespace();
+                let method = parts.next().unwrap_or("").to_string();
+                let path = parts.next().unwrap_or("").to_string();
+                let authorized = request.contains("authorization: Bearer secret");
+                let body = request.split("\r\n\r\n").nth(1).unwrap_or("").to_string();
+
+                let (status, reply) = if !authorized {
+                    (401, String::new())
+                } else if method == "POST" && path == "/upload" {
+                    pins.lock().unwrap().insert("bafyupload".to_string(), "r0".to_string());
+                    (200, format!(r#"{{"cid":"bafyupload","ok":true,"value":{{"cid":"bafyupload","size":{}}}}}"#, body.len()))
+                } else if method == "POST" && path == "/pins" {
+                    let cid = serde_json::from_str::<serde_json::Value>(&body).unwrap()["cid"].as_str().unwrap().to_string();
+                    pins.lock().unwrap().insert(cid, "r1".to_string());
+                    (202, r#"{"requestid":"r1","status":"queued"}"#.to_string())
+                } else if method == "GET" && path.starts_with("/pins?cid=") {
+                    let cid = path["/pins?cid=".len()..].split('&').next().unwrap().to_string();
+                    let results: Vec<String> = pins.lock().unwrap().get(&cid)
+                        .map(|id| vec![format!(r#"{{"requestid":"{}","status":"pinned"}}"#, id)])
+                        .unwrap_or_default();
+                    (200, format!(r#"{{"count":{},"results":[{}]}}"#, results.len(), results.join(",")))
+                } else if method == "DELETE" && path.starts_with("/pins/") {
+                    let id = path["/pins/".len()..].to_string();
+                    pins.lock().unwrap().retain(|_, v| *v != id);
+                    (202, String::new())
+                } else {
+                    (404, String::new())
+                };
+
+                let response = format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, reply.len(), reply);
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn test_hosted_provider_lifecycle() {
+        let pins = Arc::new(Mutex::new(HashMap::new()));
+        let url = mock_hosted_api(pins.clone()).await;
+        let provider = HostedPinningProvider::nft_storage("secret")
+            .with_endpoints(&format!("{}/upload", url), &url);
+        let client = Client::new();
+
+        let result = provider.upload(&client, "{\"id\":\"did:key:z\"}", "did.json").await.unwrap();
+        assert_eq!(result.cid, "bafyupload");
+        assert_eq!(result.provider, "NFT.Storage");
+        assert!(provider.is_pinned(&client, "bafyupload").await.unwrap());
+
+        provider.pin(&client, "bafyother").await.unwrap();
+        assert!(provider.is_pinned(&client, "bafyother").await.unwrap());
+        provider.unpin(&client, "bafyother").await.unwrap();
+        assert!(!provider.is_pinned(&client, "bafyother").await.unwrap());
+
+        // 错误的令牌
+        let unauthorized = PinningServiceProvider::new("Test", &url, "wrong");
+        assert!(unauthorized.pin(&client, "bafy").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ipfs_client_uses_provider() {
+        let pins = Arc::new(Mutex::new(HashMap::new()));
+        let url = mock_hosted_api(pins).await;
+        let provider = HostedPinningProvider::web3_storage("secret")
+            .with_endpoints(&format!("{}/upload", url), &url);
+
+        let client = IpfsClient::new_public_only(5).with_pinning_provider(Arc::new(provider));
+        assert_eq!(client.pinning_providers(), vec!["Web3.Storage".to_string()]);
+
+        let result = client.upload("{}", "did.json").await.unwrap();
+        assert_eq!(result.provider, "Web3.Storage");
+        assert!(client.is_pinned("bafyupload").await.unwrap());
+    }
+}
@@ -0,0 +1,272 @@
+// DIAP Rust SDK - 差分隐私统计模块
+// 智能体向共享主题发布用量/负载指标前先截断到固定范围并加入拉普拉斯噪声（本地差分隐私），
+// 汇总方只在某个时间窗口内的独立贡献者达到k个时才给出聚合结果（k-匿名阈值），
+// 使整个集群的统计可以被聚合，而单个智能体的活动模式不会暴露
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::pubsub_authenticator::{AuthenticatedMessage, PubSubMessageType};
+
+/// 统计报告消息类型标识（PubSubMessageType::Custom）
+pub const STATS_REPORT_MESSAGE_TYPE: &str = "stats_report";
+
+/// 默认k-匿名阈值
+pub const DEFAULT_MIN_CONTRIBUTORS: usize = 5;
+
+/// 隐私参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrivacyConfig {
+    /// 隐私预算ε（越小噪声越大；None表示不加噪声，只做截断）
+    pub epsilon: Option<f64>,
+
+    /// 单个智能体的指标上限（也是查询敏感度），超出的值被截断
+    pub max_value: f64,
+
+    /// 聚合所需的最少独立贡献者数
+    pub min_contributors: usize,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            epsilon: Some(1.0),
+            max_value: 1000.0,
+            min_contributors: DEFAULT_MIN_CONTRIBUTORS,
+        }
+    }
+}
+
+impl PrivacyConfig {
+    /// 截断并加噪（本地差分隐私）
+    pub fn privatize<R: Rng + ?Sized>(&self, value: f64, rng: &mut R) -> f64 {
+        let clamped = if value.is_finite() { value.clamp(0.0, self.max_value) } else { 0.0 };
+        match self.epsilon {
+            Some(epsilon) if epsilon > 0.0 => clamped + laplace_noise(self.max_value / epsilon, rng),
+            _ => clamped,
+        }
+    }
+}
+
+/// 拉普拉斯噪声采样（逆变换法）
+pub fn laplace_noise<R: Rng + ?Sized>(scale: f64, rng: &mut R) -> f64 {
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+/// 单个智能体在某个时间窗口内的指标报告
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsReport {
+    /// 报告者DID
+    pub reporter_did: String,
+
+    /// 指标名称
+    pub metric: String,
+
+    /// 指标值（已截断，按配置可能已加噪）
+    pub value: f64,
+
+    /// 时间窗口起点（秒，按窗口长度对齐）
+    pub window_start: u64,
+
+    /// 时间窗口长度（秒）
+    pub window_secs: u64,
+
+    /// 是否加入了噪声
+    pub noised: bool,
+}
+
+impl StatsReport {
+    /// 生成报告：对原始值截断、加噪，并把时间对齐到窗口起点
+    pub fn new(
+        reporter_did: &str,
+        metric: &str,
+        raw_value: f64,
+        now_secs: u64,
+        window_secs: u64,
+        config: &PrivacyConfig,
+    ) -> Self {
+        let window_secs = window_secs.max(1);
+        Self {
+            reporter_did: reporter_did.to_string(),
+            metric: metric.to_string(),
+            value: config.privatize(raw_value, &mut rand::thread_rng()),
+            window_start: now_secs - now_secs % window_secs,
+            window_secs,
+            noised: config.epsilon.is_some_and(|epsilon| epsilon > 0.0),
+        }
+    }
+
+    /// 序列化为消息内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化统计报告失败")
+    }
+
+    /// 从认证消息中解析统计报告
+    pub fn from_message(message: &AuthenticatedMessage) -> Result<Self> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == STATS_REPORT_MESSAGE_TYPE => {}
+            _ => anyhow::bail!("不是统计报告消息: {}", message.message_id),
+        }
+
+        let report: Self = serde_json::from_slice(&message.content)
+            .context("解析统计报告失败")?;
+        if report.reporter_did != message.from_did {
+            anyhow::bail!("统计报告DID与消息发送者不一致");
+        }
+        Ok(report)
+    }
+}
+
+/// 聚合结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregateStats {
+    /// 指标名称
+    pub metric: String,
+
+    /// 时间窗口起点（秒）
+    pub window_start: u64,
+
+    /// 独立贡献者数
+    pub contributors: usize,
+
+    /// 总和
+    pub sum: f64,
+
+    /// 平均值
+    pub mean: f64,
+}
+
+/// 统计聚合器
+pub struct StatsAggregator {
+    config: PrivacyConfig,
+
+    /// (指标, 窗口起点) -> 报告者DID -> 值（同一窗口内重复报告以最后一次为准）
+    reports: DashMap<(String, u64), HashMap<String, f64>>,
+}
+
+impl StatsAggregator {
+    /// 创建聚合器
+    pub fn new(config: PrivacyConfig) -> Self {
+        Self {
+            config,
+            reports: DashMap::new(),
+        }
+    }
+
+    /// 隐私参数
+    pub fn config(&self) -> &PrivacyConfig {
+        &self.config
+    }
+
+    /// 记录一份报告；无论是否声明已加噪，值都按上限截断，防止单个智能体拉偏聚合结果
+    pub fn observe(&self, report: &StatsReport) -> Result<()> {
+        if !report.value.is_finite() {
+            anyhow::bail!("统计报告的值无效: {} ({})", report.value, report.reporter_did);
+        }
+        let value = report.value.clamp(0.0, self.config.max_value);
+        self.reports
+            .entry((report.metric.clone(), report.window_start))
+            .or_default()
+            .insert(report.reporter_did.clone(), value);
+        Ok(())
+    }
+
+    /// 聚合某个窗口的指标；独立贡献者不足k个时返回None
+    pub fn aggregate(&self, metric: &str, window_start: u64) -> Option<AggregateStats> {
+        let reports = self.reports.get(&(metric.to_string(), window_start))?;
+        let contributors = reports.len();
+        if contributors == 0 || contributors < self.config.min_contributors {
+            log::debug!("统计窗口贡献者不足: {} @{} ({}/{})", metric, window_start, contributors, self.config.min_contributors);
+            return None;
+        }
+
+        // 每份报告在observe时已截断到[0, max_value]
+        let sum = reports.values().sum::<f64>();
+        Some(AggregateStats {
+            metric: metric.to_string(),
+            window_start,
+            contributors,
+            sum,
+            mean: sum / contributors as f64,
+        })
+    }
+
+    /// 某个指标所有达到阈值的窗口的聚合结果（按时间排序）
+    pub fn aggregates(&self, metric: &str) -> Vec<AggregateStats> {
+        let mut windows: Vec<u64> = self.reports.iter()
+            .filter(|entry| entry.key().0 == metric)
+            .map(|entry| entry.key().1)
+            .collect();
+        windows.sort_unstable();
+        windows.into_iter().filter_map(|window| self.aggregate(metric, window)).collect()
+    }
+
+    /// 丢弃早于指定时间的窗口，返回丢弃的窗口数
+    pub fn prune_before(&self, window_start: u64) -> usize {
+        let before = self.reports.len();
+        self.reports.retain(|(_, start), _| *start >= window_start);
+        before - self.reports.len()
+    }
+}
+
+impl Default for StatsAggregator {
+    fn default() -> Self {
+        Self::new(PrivacyConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_privatize_clamps_and_adds_noise() {
+        let exact = PrivacyConfig { epsilon: None, max_value: 10.0, min_contributors: 1 };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        assert_eq!(exact.privatize(25.0, &mut rng), 10.0);
+        assert_eq!(exact.privatize(-3.0, &mut rng), 0.0);
+        assert_eq!(exact.privatize(f64::NAN, &mut rng), 0.0);
+
+        // 噪声均值接近0
+        let noisy = PrivacyConfig { epsilon: Some(1.0), ..exact };
+        let samples: Vec<f64> = (0..20_000).map(|_| noisy.privatize(5.0, &mut rng)).collect();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!((mean - 5.0).abs() < 0.5, "mean = {}", mean);
+        assert!(samples.iter().any(|v| (v - 5.0).abs() > 1.0));
+    }
+
+    #[test]
+    fn test_k_anonymity_threshold() {
+        let config = PrivacyConfig { epsilon: None, max_value: 100.0, min_contributors: 3 };
+        let aggregator = StatsAggregator::new(config.clone());
+        let report = |did: &str, value: f64| StatsReport::new(did, "cpu", value, 1_000_030, 60, &config);
+
+        aggregator.observe(&report("did:key:a", 10.0)).unwrap();
+        aggregator.observe(&report("did:key:b", 20.0)).unwrap();
+        // 同一智能体重复报告不增加贡献者数
+        aggregator.observe(&report("did:key:b", 30.0)).unwrap();
+        assert_eq!(report("did:key:a", 1.0).window_start, 1_000_020);
+        assert!(aggregator.aggregate("cpu", 1_000_020).is_none());
+
+        aggregator.observe(&report("did:key:c", 500.0)).unwrap();
+        let stats = aggregator.aggregate("cpu", 1_000_020).unwrap();
+        assert_eq!(stats.contributors, 3);
+        assert_eq!(stats.sum, 140.0);
+        assert_eq!(aggregator.aggregates("cpu").len(), 1);
+
+        // 声明已加噪的报告同样截断，非有限值被拒绝
+        let forged = StatsReport { value: 1e12, noised: true, ..report("did:key:d", 0.0) };
+        aggregator.observe(&forged).unwrap();
+        assert_eq!(aggregator.aggregate("cpu", 1_000_020).unwrap().sum, 240.0);
+        assert!(aggregator.observe(&StatsReport { value: f64::NAN, ..forged.clone() }).is_err());
+        assert!(aggregator.observe(&StatsReport { value: f64::INFINITY, ..forged }).is_err());
+
+        assert_eq!(aggregator.prune_before(1_000_080), 1);
+        assert!(aggregator.aggregates("cpu").is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+// DIAP Rust SDK - 项目模板生成
+// `diap new <name>` 生成最小的智能体项目：配置文件、消息处理骨架、Dockerfile，
+// 配置由当前SDK的DIAPConfig序列化得到，与SDK版本保持一致
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::config_manager::DIAPConfig;
+use crate::VERSION;
+
+/// 生成智能体项目，返回项目目录
+/// 目标目录已存在且非空时拒绝覆盖
+pub fn scaffold_project(name: &str, parent_dir: &Path) -> Result<PathBuf> {
+    validate_project_name(name)?;
+
+    let root = parent_dir.join(name);
+    if root.exists() && root.read_dir().map(|mut d| d.next().is_some()).unwrap_or(true) {
+        anyhow::bail!("目标目录已存在且非空: {:?}", root);
+    }
+
+    for (relative, content) in render_files(name)? {
+        let path = root.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建目录: {:?}", parent))?;
+        }
+        std::fs::write(&path, content)
+            .with_context(|| format!("无法写入文件: {:?}", path))?;
+        log::debug!("生成 {:?}", path);
+    }
+
+    log::info!("✅ 已生成智能体项目: {:?}", root);
+    Ok(root)
+}
+
+/// 模板文件（相对路径, 内容）
+pub fn render_files(name: &str) -> Result<Vec<(&'static str, String)>> {
+    Ok(vec![
+        ("Cargo.toml", cargo_toml(name)),
+        ("diap.toml", config_toml(name)?),
+        ("src/main.rs", MAIN_RS.replace("{{name}}", name)),
+        ("src/handlers.rs", HANDLERS_RS.to_string()),
+        ("Dockerfile", DOCKERFILE.replace("{{name}}", name)),
+        (".dockerignore", "target/\nkeys/\n.diap/\n".to_string()),
+        (".gitignore", "/target\n/keys\n/.diap\n".to_string()),
+    ])
+}
+
+/// 项目名需是合法的crate名
+fn validate_project_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        anyhow::bail!("无效的项目名（需以字母开头，仅含字母、数字、-、_）: {}", name);
+    }
+    Ok(())
+}
+
+fn cargo_toml(name: &str) -> String {
+    format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+diap-rs-sdk = "{VERSION}"
+tokio = {{ version = "1.0", features = ["full"] }}
+anyhow = "1.0"
+log = "0.4"
+env_logger = "0.10"
+libp2p = "0.53"
+"#
+    )
+}
+
+/// 以SDK默认配置为基础，路径改为项目内的相对路径
+fn config_toml(name: &str) -> Result<String> {
+    let mut config = DIAPConfig::default();
+    config.agent.name = name.to_string();
+    config.agent.private_key_path = PathBuf::from("keys/agent.key");
+    config.cache.cache_dir = Some(PathBuf::from(".diap/cache"));
+    toml::to_string_pretty(&config).context("无法序列化配置")
+}
+
+const MAIN_RS: &str = r#"// {{name}} - DIAP智能体
+// 由 `diap new` 生成：加载diap.toml，初始化身份并处理订阅主题上的认证消息
+
+mod handlers;
+
+use anyhow::Result;
+use diap_rs_sdk::{ConfigLoader, IdentityManager, IpfsClient, KeyManager, PubsubAuthenticator};
+use std::path::PathBuf;
+
+/// 订阅的主题
+const TOPICS: &[&str] = &["{{name}}/tasks"];
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // diap.toml，可用 DIAP_<段>__<项> 环境变量或 --set 段.项=值 覆盖
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (loader, _) = ConfigLoader::new().with_file("diap.toml").with_cli_args(&args)?;
+    let config = loader.load()?;
+    env_logger::Builder::new().parse_filters(&config.logging.level).init();
+    config.apply_runtime_settings();
+
+    let keypair = KeyManager::new(PathBuf::from("keys"))
+        .load_or_generate(&config.agent.private_key_path)?;
+    log::info!("🤖 {} 启动，DID: {}", config.agent.name, keypair.did);
+
+    let ipfs = IpfsClient::from_config(&config.ipfs);
+    let authenticator = PubsubAuthenticator::new(IdentityManager::new(ipfs), None, None);
+    authenticator
+        .set_local_identity(keypair, libp2p::PeerId::random(), String::new())
+        .await?;
+
+    for topic in TOPICS {
+        authenticator.subscribe_topic(topic).await?;
+    }
+
+    // 将网络层收到的消息交给handlers::handle_message，把返回的回复发布出去
+    handlers::on_start(&authenticator).await?;
+    tokio::signal::ctrl_c().await?;
+    log::info!("👋 {} 退出", config.agent.name);
+    Ok(())
+}
+"#;
+
+const HANDLERS_RS: &str = r#"// 消息处理骨架：在这里实现智能体的业务逻辑
+
+use anyhow::Result;
+use diap_rs_sdk::{AuthenticatedMessage, PubSubMessageType, PubsubAuthenticator};
+
+/// 启动时调用（例如发布上线心跳）
+pub async fn on_start(_authenticator: &PubsubAuthenticator) -> Result<()> {
+    Ok(())
+}
+
+/// 处理收到的认证消息，返回需要发布的回复
+#[allow(dead_code)] // 接入网络层后移除
+pub async fn handle_message(
+    authenticator: &PubsubAuthenticator,
+    message: &AuthenticatedMessage,
+) -> Result<Option<AuthenticatedMessage>> {
+    let verification = authenticator.verify_message(message).await?;
+    if !verification.verified {
+        log::warn!("丢弃未通过验证的消息: {:?}", verification.details);
+        return Ok(None);
+    }
+
+    match &message.message_type {
+        PubSubMessageType::Custom(kind) if kind == "task" => {
+            // TODO: 处理任务
+            let reply = authenticator
+                .create_authenticated_message(
+                    &message.topic,
+                    PubSubMessageType::Custom("task_result".to_string()),
+                    b"done",
+                    Some(message.from_did.clone()),
+                )
+                .await?;
+            Ok(Some(reply))
+        }
+        _ => Ok(None),
+    }
+}
+"#;
+
+const DOCKERFILE: &str = r#"FROM rust:1-bookworm AS build
+WORKDIR /app
+COPY . .
+RUN cargo build --release
+
+FROM debian:bookworm-slim
+RUN apt-get update && apt-get install -y --no-install-recommends ca-certificates && rm -rf /var/lib/apt/lists/*
+WORKDIR /app
+COPY --from=build /app/target/release/{{name}} /usr/local/bin/{{name}}
+COPY diap.toml /app/diap.toml
+VOLUME ["/app/keys"]
+CMD ["{{name}}"]
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaffold_project() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = scaffold_project("my-agent", dir.path()).unwrap();
+
+        let config = DIAPConfig::from_file(&root.join("diap.toml")).unwrap();
+        assert_eq!(config.agent.name, "my-agent");
+        assert_eq!(config.agent.private_key_path, PathBuf::from("keys/agent.key"));
+
+        let cargo = std::fs::read_to_string(root.join("Cargo.toml")).unwrap();
+        assert!(cargo.contains(&format!("diap-rs-sdk = \"{}\"", VERSION)));
+        let dockerfile = std::fs::read_to_string(root.join("Dockerfile")).unwrap();
+        assert!(dockerfile.contains("/usr/local/bin/my-agent"));
+        assert!(root.join("src/handlers.rs").exists());
+
+        // 不覆盖已有项目，拒绝无效名称
+        assert!(scaffold_project("my-agent", dir.path()).is_err());
+        assert!(scaffold_project("../escape", dir.path()).is_err());
+        assert!(scaffold_project("1agent", dir.path()).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+// DIAP Rust SDK - 证明缓存
+// 按（证明方案，输入哈希）缓存生成的证明，TTL内重复证明同一DID-CID绑定时直接复用
+
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use crate::clock::{SharedClock, system_clock};
+
+/// 缓存的证明
+#[derive(Debug, Clone)]
+struct CachedProof<T> {
+    /// 证明结果
+    value: T,
+
+    /// 证明方案
+    scheme: String,
+
+    /// 绑定标识（例如DID哈希），用于按绑定失效
+    binding: String,
+
+    /// 过期时间
+    expires_at: u64,
+
+    /// 最近访问序号（用于LRU驱逐）
+    last_used: u64,
+}
+
+/// 证明缓存统计
+#[derive(Debug, Clone, Default)]
+pub struct ProofCacheStats {
+    /// 当前条目数
+    pub entries: usize,
+
+    /// 命中次数
+    pub hits: u64,
+
+    /// 未命中次数
+    pub misses: u64,
+
+    /// LRU驱逐次数
+    pub evictions: u64,
+
+    /// 主动失效的条目数
+    pub invalidations: u64,
+
+    /// 最大条目数
+    pub max_entries: usize,
+
+    /// 缓存有效期（秒）
+    pub ttl: u64,
+}
+
+impl ProofCacheStats {
+    /// 命中率
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// 证明LRU缓存
+#[derive(Clone)]
+pub struct ProofCache<T: Clone> {
+    /// 方案:输入哈希 -> 缓存的证明
+    entries: Arc<DashMap<String, CachedProof<T>>>,
+
+    /// 缓存有效期（秒）
+    ttl: u64,
+
+    /// 最大条目数
+    max_entries: usize,
+
+    /// 时间源
+    clock: SharedClock,
+
+    /// 访问序号
+    access_counter: Arc<AtomicU64>,
+
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+    evictions: Arc<AtomicU64>,
+    invalidations: Arc<AtomicU64>,
+}
+
+impl<T: Clone> ProofCache<T> {
+    /// 创建证明缓存
+    ///
+    /// # 参数
+    /// * `ttl` - 缓存有效期（秒），默认300秒
+    /// * `max_entries` - 最大缓存条目数，默认256
+    pub fn new(ttl: Option<u64>, max_entries: Option<usize>) -> Self {
+        Self::new_with_clock(ttl, max_entries, system_clock())
+    }
+
+    /// 使用指定时间源创建证明缓存
+    pub fn new_with_clock(ttl: Option<u64>, max_entries: Option<usize>, clock: SharedClock) -> Self {
+        Self {
+            entries: Arc::new(DashMap::new()),
+            ttl: ttl.unwrap_or(300),
+            max_entries: max_entries.unwrap_or(256).max(1),
+            clock,
+            access_counter: Arc::new(AtomicU64::new(0)),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+            evictions: Arc::new(AtomicU64::new(0)),
+            invalidations: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 计算输入哈希（各部分带长度前缀，避免拼接歧义）
+    pub fn inputs_hash(parts: &[&[u8]]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    /// 查询缓存的证明
+    pub fn get(&self, scheme: &str, inputs_hash: &str) -> Option<T> {
+        let key = Self::key(scheme, inputs_hash);
+
+        if let Some(mut entry) = self.entries.get_mut(&key) {
+            if entry.expires_at > self.clock.now_secs() {
+                entry.last_used = self.tick();
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                log::debug!("✓ 证明缓存命中: {}", key);
+                return Some(entry.value.clone());
+            }
+
+            drop(entry);
+            self.entries.remove(&key);
+            log::debug!("证明缓存已过期: {}", key);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    /// 缓存证明
+    pub fn put(&self, scheme: &str, inputs_hash: &str, binding: &str, value: T) {
+        let key = Self::key(scheme, inputs_hash);
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.max_entries {
+            self.evict_lru();
+        }
+
+        self.entries.insert(key, CachedProof {
+            value,
+            scheme: scheme.to_string(),
+            binding: binding.to_string(),
+            expires_at: self.clock.now_secs() + self.ttl,
+            last_used: self.tick(),
+        });
+    }
+
+    /// 使单个证明失效
+    pub fn invalidate(&self, scheme: &str, inputs_hash: &str) -> bool {
+        let removed = self.entries.remove(&Self::key(scheme, inputs_hash)).is_some();
+        if removed {
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+        removed
+    }
+
+    /// 使某个绑定（例如DID密钥轮换后）的全部证明失效，返回失效数量
+    pub fn invalidate_binding(&self, binding: &str) -> usize {
+        self.invalidate_where(|entry| entry.binding == binding)
+    }
+
+    /// 使某个证明方案的全部证明失效，返回失效数量
+    pub fn invalidate_scheme(&self, scheme: &str) -> usize {
+        self.invalidate_where(|entry| entry.scheme == scheme)
+    }
+
+    /// 清空缓存
+    pub fn clear(&self) {
+        let count = self.invalidate_where(|_| true);
+        log::info!("🧹 清空证明缓存: {} 个条目", count);
+    }
+
+    /// 当前条目数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 获取缓存统计
+    pub fn stats(&self) -> ProofCacheStats {
+        ProofCacheStats {
+            entries: self.entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            max_entries: self.max_entries,
+            ttl: self.ttl,
+        }
+    }
+
+    fn invalidate_where(&self, predicate: impl Fn(&CachedProof<T>) -> bool) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| !predicate(entry));
+        let removed = before.saturating_sub(self.entries.len());
+        self.invalidations.fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+
+    /// 驱逐最久未使用的条目
+    fn evict_lru(&self) {
+        let oldest = self.entries
+            .iter()
+            .min_by_key(|entry| entry.last_used)
+            .map(|entry| entry.key().clone());
+
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            log::debug!("驱逐LRU证明缓存: {}", key);
+        }
+    }
+
+    fn key(scheme: &str, inputs_hash: &str) -> String {
+        format!("{}:{}", scheme, inputs_hash)
+    }
+
+    fn tick(&self) -> u64 {
+        self.access_counter.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl<T: Clone> Default for ProofCache<T> {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_hit_miss_and_lru() {
+        let cache: ProofCache<Vec<u8>> = ProofCache::new(Some(60), Some(2));
+        let h1 = ProofCache::<Vec<u8>>::inputs_hash(&[b"did1", b"cid1"]);
+        let h2 = ProofCache::<Vec<u8>>::inputs_hash(&[b"did2", b"cid2"]);
+        let h3 = ProofCache::<Vec<u8>>::inputs_hash(&[b"did3", b"cid3"]);
+
+        assert!(cache.get("noir", &h1).is_none());
+        cache.put("noir", &h1, "did1", vec![1]);
+        cache.put("noir", &h2, "did2", vec![2]);
+        assert_eq!(cache.get("noir", &h1), Some(vec![1]));
+
+        // h2最久未使用，被驱逐
+        cache.put("noir", &h3, "did3", vec![3]);
+        assert!(cache.get("noir", &h2).is_none());
+        assert!(cache.get("noir", &h1).is_some());
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.evictions, 1);
+    }
+
+    #[test]
+    fn test_invalidation_and_ttl() {
+        let cache: ProofCache<Vec<u8>> = ProofCache::new(Some(60), None);
+        cache.put("noir", "a", "did1", vec![1]);
+        cache.put("noir", "b", "did1", vec![2]);
+        cache.put("arkworks", "c", "did2", vec![3]);
+
+        assert_eq!(cache.invalidate_binding("did1"), 2);
+        assert_eq!(cache.invalidate_scheme("arkworks"), 1);
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().invalidations, 3);
+
+        let clock = MockClock::new(1_000);
+        let expiring: ProofCache<Vec<u8>> = ProofCache::new_with_clock(Some(60), None, Arc::new(clock.clone()));
+        expiring.put("noir", "a", "did1", vec![1]);
+        assert!(expiring.get("noir", "a").is_some());
+        clock.advance(std::time::Duration::from_secs(60));
+        assert!(expiring.get("noir", "a").is_none());
+    }
+}
@@ -5,13 +5,49 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use libp2p::PeerId;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::RwLock;
-use std::collections::HashMap;
+use tracing::Instrument;
+use std::collections::{HashMap, VecDeque};
 
 use crate::identity_manager::IdentityManager;
-use crate::key_manager::KeyPair;
+use crate::key_manager::{KeyPair, SignedEnvelope, Signer};
 use crate::nonce_manager::NonceManager;
 use crate::did_cache::DIDCache;
+use crate::did_revocation::RevocationRegistry;
+use crate::did_update::{DidUpdatedEvent, DID_UPDATED_MESSAGE_TYPE};
+use crate::verification_hint::HintSource;
+use crate::latency_budget::{self, LatencyBudget};
+use crate::trust_level::{self, TrustLevel, TrustPolicy};
+use crate::capabilities::{CapabilityPolicy, CapabilityToken};
+use crate::session_token::SessionToken;
+use crate::did_commitment::{CidCommitment, CID_COMMITMENT_MESSAGE_TYPE, MAX_PENDING_COMMITMENTS};
+use crate::peer_binding::{PeerBindingCheck, PeerBindingRegistry, PeerIdBinding, PEER_BINDING_MESSAGE_TYPE};
+use crate::admission::{AdmissionControl, AdmissionRejection};
+use crate::circuit_breaker::CircuitBreakers;
+use crate::rate_limiter::{AbuseCallback, AbuseEvent, AbuseKind, RateLimitScope, RateLimiter};
+use crate::store_and_forward::{DeliveryReceipt, OfflineQueue, DELIVERY_RECEIPT_MESSAGE_TYPE};
+use crate::libp2p_identity::LibP2PIdentity;
+use crate::group_messaging::{GroupAdmin, GroupCiphertext, GroupKeyDistribution, GroupKeyring, GROUP_KEY_MESSAGE_TYPE, GROUP_MESSAGE_TYPE};
+use crate::clock_sync::{PeerClockOffsets, TimeSyncPacket, TIME_SYNC_MESSAGE_TYPE};
+use crate::feature_toggles::{FeatureToggles, SdkFeature, ToggleAuditEntry, ToggleCommand, FEATURE_TOGGLE_MESSAGE_TYPE};
+use crate::legacy_compat;
+use crate::clock::{SharedClock, system_clock};
+use crate::agent_checkpoint::{AgentCheckpoint, ConnectionIntent, RestoredAgent, SessionResumption, CHECKPOINT_VERSION};
+use crate::agent_invite::{AgentInvite, AcceptedInvite, InviteAnnouncement, InviteBootstrap, INVITE_ANNOUNCE_MESSAGE_TYPE};
+use crate::trust_graph::TrustGraph;
+use crate::trust::{ReputationStore, TopicPolicyHook};
+use crate::audit_log::{AuditEvent, AuditLog};
+use crate::e2e_encryption::{self, EncryptedPayload};
+use crate::crdt_sync::{CrdtReplica, CrdtSyncMessage, MergeOutcome, CRDT_SYNC_MESSAGE_TYPE};
+use crate::lease::{LeaseClaim, LeaseOutcome, LeaseTable, LEASE_MESSAGE_TYPE};
+use crate::private_stats::{PrivacyConfig, StatsAggregator, StatsReport, STATS_REPORT_MESSAGE_TYPE};
+use crate::reliable_broadcast::{BroadcastAck, BroadcastTracker, DeliveryCertificate, BROADCAST_ACK_MESSAGE_TYPE};
+use crate::topic_policy::TopicPolicyDocument;
+use crate::fleet::{AckStatus, FleetAck, FleetAgent, FleetController, FleetDirective, PolicyBundle, RolloutStatus, FLEET_ACK_MESSAGE_TYPE, FLEET_POLICY_MESSAGE_TYPE};
+use crate::topic_pattern::{self, TopicPattern};
+use crate::timestamp_window::TimestampWindow;
+use crate::message_archive::{MessageArchive, RetentionPolicy, DeletionAck, ComplianceReport, DELETION_ACK_MESSAGE_TYPE};
 
 /// PubSub消息类型
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,6 +104,84 @@ pub struct AuthenticatedMessage {
     
     /// 时间戳
     pub timestamp: u64,
+    
+    /// 最早可投递时间（Unix秒，None表示立即投递），已包含在签名中
+    #[serde(default)]
+    pub not_before: Option<u64>,
+    
+    /// 能力令牌（紧凑编码，见 capabilities 模块），已包含在签名中
+    #[serde(default)]
+    pub capability: Option<String>,
+    
+    /// 会话令牌（JWT，见 session_token 模块），接收方信任签发者时跳过ZKP验证，已包含在签名中
+    #[serde(default)]
+    pub session_token: Option<String>,
+}
+
+impl AuthenticatedMessage {
+    /// 签名数据：内容 + nonce + 主题（定时消息追加not_before，携带能力令牌、会话令牌时追加令牌）
+    pub fn signing_data(&self) -> Vec<u8> {
+        Self::build_signing_data(
+            &self.content,
+            &self.nonce,
+            &self.topic,
+            self.not_before,
+            self.capability.as_deref(),
+            self.session_token.as_deref(),
+        )
+    }
+    
+    /// 在指定时间是否已可投递
+    pub fn is_deliverable_at(&self, now: u64) -> bool {
+        self.not_before.is_none_or(|not_before| now >= not_before)
+    }
+    
+    fn build_signing_data(
+        content: &[u8],
+        nonce: &str,
+        topic: &str,
+        not_before: Option<u64>,
+        capability: Option<&str>,
+        session_token: Option<&str>,
+    ) -> Vec<u8> {
+        let mut sign_data = Vec::new();
+        sign_data.extend_from_slice(content);
+        sign_data.extend_from_slice(nonce.as_bytes());
+        sign_data.extend_from_slice(topic.as_bytes());
+        if let Some(not_before) = not_before {
+            sign_data.extend_from_slice(b"not_before:");
+            sign_data.extend_from_slice(&not_before.to_be_bytes());
+        }
+        if let Some(capability) = capability {
+            sign_data.extend_from_slice(b"capability:");
+            sign_data.extend_from_slice(capability.as_bytes());
+        }
+        if let Some(session_token) = session_token {
+            sign_data.extend_from_slice(b"session_token:");
+            sign_data.extend_from_slice(session_token.as_bytes());
+        }
+        sign_data
+    }
+}
+
+/// 作为PubSubMessageType::Custom消息内容传递的签名信封
+pub trait SignedMessage: SignedEnvelope {
+    /// 消息类型标识
+    const MESSAGE_TYPE: &'static str;
+    
+    /// 从认证消息中解析（不验证签名），签名者必须是消息发送者
+    fn from_message(message: &AuthenticatedMessage) -> Result<Self> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == Self::MESSAGE_TYPE => {}
+            _ => anyhow::bail!("不是{}消息: {}", Self::KIND, message.message_id),
+        }
+        
+        let envelope = Self::from_bytes(&message.content)?;
+        if envelope.signer_did() != message.from_did {
+            anyhow::bail!("{}签名者与消息发送者不一致", Self::KIND);
+        }
+        Ok(envelope)
+    }
 }
 
 /// Pubsub消息验证结果
@@ -84,6 +198,61 @@ pub struct MessageVerification {
     
     /// 验证时间戳
     pub verified_at: u64,
+    
+    /// 超出延迟预算，结果只包含已完成的步骤（未通过但不计为验证失败）
+    #[serde(default)]
+    pub provisional: bool,
+    
+    /// 通过验证时达到的信任等级
+    #[serde(default)]
+    pub trust_level: Option<TrustLevel>,
+}
+
+impl MessageVerification {
+    /// 是否通过验证且达到指定信任等级
+    pub fn meets(&self, required: TrustLevel) -> bool {
+        self.verified && self.trust_level.is_some_and(|level| level.satisfies(required))
+    }
+}
+
+/// check_message的结果
+struct CheckOutcome {
+    verification: MessageVerification,
+    
+    /// 发送者身份已被证明（签名由属于from_did的密钥生成，且不是重放）
+    sender_proven: bool,
+}
+
+impl From<MessageVerification> for CheckOutcome {
+    /// 在验证签名之前结束的检查，发送者身份未被证明
+    fn from(verification: MessageVerification) -> Self {
+        Self { verification, sender_proven: false }
+    }
+}
+
+/// 保留的最近验证失败记录数
+pub const MAX_RECENT_VERIFICATION_FAILURES: usize = 100;
+
+/// DID更新事件广播通道容量
+const DID_UPDATE_CHANNEL_CAPACITY: usize = 64;
+
+/// 验证失败记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationFailure {
+    /// 消息ID
+    pub message_id: String,
+    
+    /// 发送者DID
+    pub from_did: String,
+    
+    /// 主题
+    pub topic: String,
+    
+    /// 失败原因
+    pub reasons: Vec<String>,
+    
+    /// 验证时间戳
+    pub at: u64,
 }
 
 /// 主题授权策略
@@ -103,7 +272,7 @@ pub enum TopicPolicy {
 }
 
 /// 主题配置
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TopicConfig {
     /// 主题名称
     pub name: String,
@@ -111,11 +280,43 @@ pub struct TopicConfig {
     /// 授权策略
     pub policy: TopicPolicy,
     
-    /// 是否需要ZKP验证
+    /// 是否需要ZKP验证（为false时接收方接受不带证明的消息，例如外部签名器发出的消息）
     pub require_zkp: bool,
     
     /// 是否需要签名验证
     pub require_signature: bool,
+    
+    /// 消息保留策略（None表示永久保留）
+    pub retention: Option<RetentionPolicy>,
+    
+    /// 每个发送者DID的速率限制（None表示不限制）
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+    
+    /// 端到端加密要求
+    #[serde(default)]
+    pub encryption: TopicEncryption,
+}
+
+/// 速率限制（令牌桶）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// 每秒允许的消息数
+    pub per_second: f64,
+    
+    /// 突发容量
+    pub burst: u32,
+}
+
+/// 主题的端到端加密要求
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TopicEncryption {
+    /// 明文和加密内容均可
+    #[default]
+    Optional,
+    
+    /// 消息内容必须经过端到端加密
+    Required,
 }
 
 /// Pubsub认证器
@@ -129,8 +330,8 @@ pub struct PubsubAuthenticator {
     /// DID文档缓存
     did_cache: Arc<DIDCache>,
     
-    /// 本地密钥对
-    keypair: Arc<RwLock<Option<KeyPair>>>,
+    /// 本地签名器（软件密钥对或外部密钥库）
+    signer: Arc<RwLock<Option<Arc<dyn Signer>>>>,
     
     /// 本地PeerID
     peer_id: Arc<RwLock<Option<PeerId>>>,
@@ -141,11 +342,86 @@ pub struct PubsubAuthenticator {
     /// 主题配置
     topic_configs: Arc<RwLock<HashMap<String, TopicConfig>>>,
     
-    /// 订阅的主题列表
+    /// 订阅的主题列表（可包含通配符模式）
     subscribed_topics: Arc<RwLock<Vec<String>>>,
     
     /// 消息统计
     message_stats: Arc<RwLock<HashMap<String, u64>>>, // topic -> message_count
+    
+    /// 消息归档（执行主题保留策略）
+    message_archive: Arc<MessageArchive>,
+    
+    /// 时间源
+    clock: SharedClock,
+    
+    /// 消息时间戳窗口（与nonce有效期对齐）
+    timestamp_window: TimestampWindow,
+    
+    /// 信任图（记录引荐关系）
+    trust_graph: TrustGraph,
+    
+    /// 可靠广播确认跟踪
+    broadcast_tracker: BroadcastTracker,
+    
+    /// 最近的验证失败
+    verification_failures: Arc<std::sync::Mutex<VecDeque<VerificationFailure>>>,
+    
+    /// 验证失败总数
+    verification_failure_total: Arc<AtomicU64>,
+    
+    /// 最近导入的主题配置文档签发时间
+    last_policy_import: Arc<RwLock<Option<u64>>>,
+    
+    /// 已应用的DID更新事件（供验证结果缓存等其他组件订阅）
+    did_updates: tokio::sync::broadcast::Sender<DidUpdatedEvent>,
+    
+    /// DHT验证提示来源（可选）
+    verification_hints: Option<Arc<dyn HintSource>>,
+    
+    /// 按主题和消息类型要求的最低信任等级
+    trust_policy: Arc<RwLock<TrustPolicy>>,
+    
+    /// 按主题要求的能力令牌
+    capability_policy: Arc<RwLock<CapabilityPolicy>>,
+    
+    /// 可远程开关的子系统
+    feature_toggles: Arc<FeatureToggles>,
+    
+    /// 各对等方的时钟偏移估计（验证时间戳和nonce时校正）
+    clock_offsets: Arc<PeerClockOffsets>,
+    
+    /// 本地持有的会话令牌（有效期内发送的消息携带令牌，不再生成ZKP证明）
+    session_token: Arc<RwLock<Option<SessionToken>>>,
+    
+    /// 受信任的会话令牌签发者
+    trusted_token_issuers: Arc<RwLock<Vec<String>>>,
+    
+    /// 已加入的群组（群组ID -> 密钥环）
+    groups: Arc<RwLock<HashMap<String, GroupKeyring>>>,
+    
+    /// 本地管理的群组
+    group_admins: Arc<RwLock<HashMap<String, GroupAdmin>>>,
+    
+    /// 已知的PeerID与DID绑定
+    peer_bindings: Arc<PeerBindingRegistry>,
+    
+    /// 收到的CID预提交声明（CID -> 声明），文档可获取之前引用该CID的消息视为待定
+    cid_commitments: Arc<std::sync::Mutex<HashMap<String, CidCommitment>>>,
+    
+    /// 是否拒绝没有PeerID绑定的消息
+    require_peer_binding: bool,
+    
+    /// 发给离线DID的消息队列
+    offline_queue: Arc<OfflineQueue>,
+    
+    /// 准入控制（熔断、限流、信誉和自定义主题策略）
+    admission: AdmissionControl,
+    
+    /// 审计日志（可选，记录每次验证尝试和nonce拒绝）
+    audit_log: Option<AuditLog>,
+    
+    /// 吊销注册表（可选，已吊销DID的消息直接拒绝）
+    revocations: Option<RevocationRegistry>,
 }
 
 impl PubsubAuthenticator {
@@ -157,17 +433,248 @@ impl PubsubAuthenticator {
     ) -> Self {
         log::info!("🔐 创建Pubsub认证器");
         
+        let nonce_manager = nonce_manager.unwrap_or_default();
+        // 默认窗口沿用nonce管理器的有效期，不改变既有的接受范围
+        let timestamp_window = TimestampWindow {
+            max_age_secs: nonce_manager.validity_secs(),
+            max_future_skew_secs: nonce_manager.max_future_skew_secs(),
+        };
+        
         Self {
             identity_manager: Arc::new(identity_manager),
-            nonce_manager: Arc::new(nonce_manager.unwrap_or_default()),
+            nonce_manager: Arc::new(nonce_manager),
             did_cache: Arc::new(did_cache.unwrap_or_default()),
-            keypair: Arc::new(RwLock::new(None)),
+            signer: Arc::new(RwLock::new(None)),
             peer_id: Arc::new(RwLock::new(None)),
             local_cid: Arc::new(RwLock::new(None)),
             topic_configs: Arc::new(RwLock::new(HashMap::new())),
             subscribed_topics: Arc::new(RwLock::new(Vec::new())),
             message_stats: Arc::new(RwLock::new(HashMap::new())),
+            message_archive: Arc::new(MessageArchive::new()),
+            clock: system_clock(),
+            timestamp_window,
+            trust_graph: TrustGraph::new(),
+            broadcast_tracker: BroadcastTracker::new(),
+            verification_failures: Arc::new(std::sync::Mutex::new(VecDeque::new())),
+            verification_failure_total: Arc::new(AtomicU64::new(0)),
+            last_policy_import: Arc::new(RwLock::new(None)),
+            did_updates: tokio::sync::broadcast::channel(DID_UPDATE_CHANNEL_CAPACITY).0,
+            verification_hints: None,
+            trust_policy: Arc::new(RwLock::new(TrustPolicy::default())),
+            capability_policy: Arc::new(RwLock::new(CapabilityPolicy::default())),
+            feature_toggles: Arc::new(FeatureToggles::default()),
+            clock_offsets: Arc::new(PeerClockOffsets::default()),
+            session_token: Arc::new(RwLock::new(None)),
+            trusted_token_issuers: Arc::new(RwLock::new(Vec::new())),
+            groups: Arc::new(RwLock::new(HashMap::new())),
+            group_admins: Arc::new(RwLock::new(HashMap::new())),
+            peer_bindings: Arc::new(PeerBindingRegistry::new()),
+            cid_commitments: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            require_peer_binding: false,
+            offline_queue: Arc::new(OfflineQueue::new()),
+            admission: AdmissionControl::new(),
+            audit_log: None,
+            revocations: None,
+        }
+    }
+    
+    /// 使用指定时间源（用于消息时间戳、nonce生成和消息归档）
+    /// nonce管理器和DID缓存的时间源在创建它们时单独指定
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.message_archive = Arc::new(MessageArchive::with_clock(clock.clone()));
+        self.clock = clock;
+        self
+    }
+    
+    /// 设置消息时间戳窗口：拒绝早于max_age或超前max_future_skew的消息
+    /// nonce管理器的有效期和时钟容差同步调整（共享已记录的nonce）
+    pub fn with_timestamp_window(mut self, window: TimestampWindow) -> Self {
+        self.nonce_manager = Arc::new(self.nonce_manager.as_ref().clone().with_timestamp_window(&window));
+        self.timestamp_window = window;
+        self
+    }
+    
+    /// 使用DHT验证提示：发送者的提示有效时不再从IPFS获取DID文档
+    pub fn with_verification_hints(mut self, hints: Arc<dyn HintSource>) -> Self {
+        self.verification_hints = Some(hints);
+        self
+    }
+    
+    /// 使用远程功能开关（默认所有子系统开启且拒绝远程命令）
+    pub fn with_feature_toggles(mut self, toggles: Arc<FeatureToggles>) -> Self {
+        self.feature_toggles = toggles;
+        self
+    }
+    
+    /// 远程功能开关
+    pub fn feature_toggles(&self) -> &Arc<FeatureToggles> {
+        &self.feature_toggles
+    }
+    
+    /// 使用指定的时钟偏移估计表（例如与其他组件共享）
+    pub fn with_clock_offsets(mut self, offsets: Arc<PeerClockOffsets>) -> Self {
+        self.clock_offsets = offsets;
+        self
+    }
+    
+    /// 各对等方的时钟偏移估计
+    pub fn clock_offsets(&self) -> &Arc<PeerClockOffsets> {
+        &self.clock_offsets
+    }
+    
+    /// 使用指定的PeerID绑定表（例如与连接处理共享）
+    pub fn with_peer_bindings(mut self, bindings: Arc<PeerBindingRegistry>) -> Self {
+        self.peer_bindings = bindings;
+        self
+    }
+    
+    /// 要求发送者的PeerID由DID密钥派生或有联合签名绑定，否则验证不通过
+    pub fn with_required_peer_binding(mut self, required: bool) -> Self {
+        self.require_peer_binding = required;
+        self
+    }
+    
+    /// 已知的PeerID绑定
+    pub fn peer_bindings(&self) -> &Arc<PeerBindingRegistry> {
+        &self.peer_bindings
+    }
+    
+    /// 使用指定的离线消息队列（例如OfflineQueue::open打开的落盘队列）
+    pub fn with_offline_queue(mut self, queue: Arc<OfflineQueue>) -> Self {
+        self.offline_queue = queue;
+        self
+    }
+    
+    /// 离线消息队列
+    pub fn offline_queue(&self) -> &Arc<OfflineQueue> {
+        &self.offline_queue
+    }
+    
+    /// 使用指定的熔断器（例如与投递层共享，让投递失败也计入同一个DID的熔断）
+    pub fn with_circuit_breakers(mut self, breakers: CircuitBreakers) -> Self {
+        self.admission = self.admission.with_circuit_breakers(breakers);
+        self
+    }
+    
+    /// 按DID的熔断器
+    pub fn circuit_breakers(&self) -> &CircuitBreakers {
+        self.admission.circuit_breakers()
+    }
+    
+    /// 使用指定的速率限制器（全局的发送者/主题配额；主题内每个发送者的配额取自TopicConfig.rate_limit）
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.admission = self.admission.with_rate_limiter(limiter);
+        self
+    }
+    
+    /// 速率限制器
+    pub fn rate_limiter(&self) -> &Arc<RateLimiter> {
+        self.admission.rate_limiter()
+    }
+    
+    /// 注册滥用事件回调（消息因超出配额被拒绝时调用）
+    pub fn on_abuse(&self, callback: AbuseCallback) {
+        self.admission.rate_limiter().on_abuse(callback);
+    }
+    
+    /// 使用指定的信誉存储（例如ReputationStore::open打开的持久化存储）
+    pub fn with_reputation(mut self, reputation: ReputationStore) -> Self {
+        self.admission = self.admission.with_reputation(reputation);
+        self
+    }
+    
+    /// 信誉存储（验证结果和速率限制违规会自动计入）
+    pub fn reputation(&self) -> &ReputationStore {
+        self.admission.reputation()
+    }
+    
+    /// DID的信任分
+    pub fn trust_score(&self, did: &str) -> f64 {
+        self.admission.reputation().trust_score(did)
+    }
+    
+    /// 准入控制（熔断、限流、信誉和自定义主题策略）
+    pub fn admission(&self) -> &AdmissionControl {
+        &self.admission
+    }
+    
+    /// 使用审计日志：每次验证尝试（含熔断、限流拒绝）和nonce拒绝都追加到日志
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+    
+    /// 审计日志
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit_log.as_ref()
+    }
+    
+    /// 使用吊销注册表（已吊销DID的消息在验证签名前拒绝）
+    pub fn with_revocation_registry(mut self, registry: RevocationRegistry) -> Self {
+        self.revocations = Some(registry);
+        self
+    }
+    
+    /// 设置TopicPolicy::Custom主题的策略钩子（例如ReputationStore::min_score_policy）
+    /// 未设置时Custom主题接受所有通过认证的发送者
+    pub async fn set_topic_policy_hook(&self, hook: TopicPolicyHook) {
+        self.admission.set_topic_policy_hook(hook).await;
+    }
+    
+    /// 发给某个DID、尚未收到送达回执的消息（按发送顺序）
+    pub fn pending_messages(&self, did: &str) -> Vec<AuthenticatedMessage> {
+        self.offline_queue.pending_messages(did)
+    }
+    
+    /// 设置信任等级策略（低于要求等级的消息验证不通过）
+    pub async fn set_trust_policy(&self, policy: TrustPolicy) {
+        *self.trust_policy.write().await = policy;
+    }
+    
+    /// 当前的信任等级策略
+    pub async fn trust_policy(&self) -> TrustPolicy {
+        self.trust_policy.read().await.clone()
+    }
+    
+    /// 设置按主题要求的能力令牌
+    pub async fn set_capability_policy(&self, policy: CapabilityPolicy) {
+        *self.capability_policy.write().await = policy;
+    }
+    
+    /// 当前能力令牌策略
+    pub async fn capability_policy(&self) -> CapabilityPolicy {
+        self.capability_policy.read().await.clone()
+    }
+    
+    /// 设置本地会话令牌（通常来自验证方的AgentVerificationResponse），主体必须是本地DID
+    /// 仅在接收方信任该令牌签发者时使用，否则接收方会因缺少ZKP证明而拒绝消息
+    pub async fn set_session_token(&self, token: &str) -> Result<()> {
+        let token = SessionToken::decode(token)?;
+        let local_did = self.signer.read().await
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?
+            .did();
+        if token.claims.sub != local_did {
+            anyhow::bail!("会话令牌主体不是本地身份: {}", token.claims.sub);
         }
+        token.verify(self.clock.now_secs())?;
+        *self.session_token.write().await = Some(token);
+        Ok(())
+    }
+    
+    /// 丢弃本地会话令牌（之后的消息重新携带ZKP证明）
+    pub async fn clear_session_token(&self) {
+        *self.session_token.write().await = None;
+    }
+    
+    /// 设置受信任的会话令牌签发者（空列表表示不接受会话令牌）
+    pub async fn set_trusted_token_issuers(&self, issuers: Vec<String>) {
+        *self.trusted_token_issuers.write().await = issuers;
+    }
+    
+    /// 当前的时间戳窗口
+    pub fn timestamp_window(&self) -> TimestampWindow {
+        self.timestamp_window
     }
     
     /// 设置本地身份
@@ -177,19 +684,48 @@ impl PubsubAuthenticator {
         peer_id: PeerId,
         cid: String,
     ) -> Result<()> {
-        *self.keypair.write().await = Some(keypair);
+        self.set_local_signer(Arc::new(keypair), peer_id, cid).await
+    }
+    
+    /// 设置本地身份（使用任意签名器，例如硬件密钥库）
+    /// 外部签名器无法导出私钥，消息不携带ZKP证明：接收方只在主题配置 require_zkp=false 时接受，
+    /// 要求ZKP的主题需要会话令牌（见 `set_session_token`），否则创建消息失败
+    pub async fn set_local_signer(
+        &self,
+        signer: Arc<dyn Signer>,
+        peer_id: PeerId,
+        cid: String,
+    ) -> Result<()> {
+        let did = signer.did();
+        *self.signer.write().await = Some(signer);
         *self.peer_id.write().await = Some(peer_id);
         *self.local_cid.write().await = Some(cid.clone());
         
         log::info!("✓ 设置本地身份");
+        log::info!("  DID: {}", did);
         log::info!("  CID: {}", cid);
+        log::info!("  收件箱: {}", topic_pattern::inbox_topic(&did));
         
         Ok(())
     }
     
-    /// 配置主题策略
+    /// 本地DID（未设置身份时为None）
+    pub async fn local_did(&self) -> Option<String> {
+        self.signer.read().await.as_ref().map(|signer| signer.did())
+    }
+    
+    /// 本地PeerID
+    pub async fn local_peer_id(&self) -> Option<PeerId> {
+        *self.peer_id.read().await
+    }
+    
+    /// 配置主题策略（主题名可以是通配符模式，例如 diap/agents/*/inbox）
     pub async fn configure_topic(&self, config: TopicConfig) -> Result<()> {
         let topic_name = config.name.clone();
+        if topic_pattern::is_pattern(&topic_name) {
+            TopicPattern::parse(&topic_name)?;
+        }
+        self.message_archive.set_policy(&topic_name, config.retention.clone());
         self.topic_configs.write().await.insert(topic_name.clone(), config);
         
         log::info!("✓ 配置主题: {}", topic_name);
@@ -197,6 +733,70 @@ impl PubsubAuthenticator {
         Ok(())
     }
     
+    /// 当前所有主题配置
+    pub async fn topic_configs(&self) -> Vec<TopicConfig> {
+        self.topic_configs.read().await.values().cloned().collect()
+    }
+    
+    /// 主题适用的配置：精确配置优先，否则取匹配的最具体通配符配置
+    pub async fn topic_config_for(&self, topic: &str) -> Option<TopicConfig> {
+        let configs = self.topic_configs.read().await;
+        if let Some(config) = configs.get(topic) {
+            return Some(config.clone());
+        }
+        configs.values()
+            .filter_map(|config| {
+                let pattern = TopicPattern::parse(&config.name).ok()?;
+                (pattern.is_wildcard() && pattern.matches(topic)).then(|| (pattern.specificity(), config))
+            })
+            .max_by(|(a, x), (b, y)| a.cmp(b).then_with(|| y.name.cmp(&x.name)))
+            .map(|(_, config)| config.clone())
+    }
+    
+    /// 主题配置的ZKP要求（未配置的主题为None）
+    async fn topic_requires_zkp(&self, topic: &str) -> Option<bool> {
+        self.topic_config_for(topic).await.map(|config| config.require_zkp)
+    }
+    
+    /// 以本地身份签发完整的主题配置集，供集群中其他节点导入
+    pub async fn export_topic_configs(&self) -> Result<TopicPolicyDocument> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        TopicPolicyDocument::sign(signer.as_ref(), self.topic_configs().await, self.clock.now_millis())
+    }
+    
+    /// 导入受信管理员签发的主题配置集，整体替换当前配置
+    /// 签发时间不晚于上次导入的文档会被拒绝（防止回滚），返回导入的主题数
+    pub async fn import_topic_configs(&self, document: &TopicPolicyDocument, admin_dids: &[String]) -> Result<usize> {
+        document.verify(admin_dids)?;
+        
+        let mut last_import = self.last_policy_import.write().await;
+        if last_import.is_some_and(|issued_at| document.issued_at <= issued_at) {
+            anyhow::bail!("主题配置文档不比当前配置新: {}", document.issued_at);
+        }
+        
+        self.replace_topic_configs(&document.topics).await;
+        *last_import = Some(document.issued_at);
+        
+        log::info!("✓ 导入主题配置: {} 个主题（签发者 {}）", document.topics.len(), document.issuer_did);
+        Ok(document.topics.len())
+    }
+    
+    /// 整体替换主题配置（同步更新保留策略）
+    async fn replace_topic_configs(&self, topics: &[TopicConfig]) {
+        let mut configs = self.topic_configs.write().await;
+        for name in configs.keys() {
+            if !topics.iter().any(|t| &t.name == name) {
+                self.message_archive.set_policy(name, None);
+            }
+        }
+        configs.clear();
+        for config in topics {
+            self.message_archive.set_policy(&config.name, config.retention.clone());
+            configs.insert(config.name.clone(), config.clone());
+        }
+    }
+    
     /// 创建认证消息
     pub async fn create_authenticated_message(
         &self,
@@ -204,9 +804,94 @@ impl PubsubAuthenticator {
         message_type: PubSubMessageType,
         content: &[u8],
         to_did: Option<String>,
+    ) -> Result<AuthenticatedMessage> {
+        self.build_message(topic, message_type, content, to_did, None, None).await
+    }
+    
+    /// 创建端到端加密的认证消息：内容用接收方DID文档的keyAgreement密钥加密，
+    /// 中继节点仍可验证签名但无法读取内容
+    pub async fn create_encrypted_message(
+        &self,
+        topic: &str,
+        message_type: PubSubMessageType,
+        content: &[u8],
+        recipient: &crate::did_builder::DIDDocument,
+    ) -> Result<AuthenticatedMessage> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        
+        let payload = e2e_encryption::encrypt_for(&signer.did(), recipient, content)?;
+        self.build_message(topic, message_type, &payload.to_bytes()?, Some(recipient.id.clone()), None, None).await
+    }
+    
+    /// 解密发给本地身份的消息内容（未加密的内容原样返回）
+    pub async fn decrypt_content(&self, message: &AuthenticatedMessage) -> Result<Vec<u8>> {
+        if !EncryptedPayload::is_encrypted(&message.content) {
+            return Ok(message.content.clone());
+        }
+        
+        let payload = EncryptedPayload::from_bytes(&message.content)?;
+        if payload.sender_did != message.from_did {
+            anyhow::bail!("加密内容的发送方与消息发送者不一致");
+        }
+        
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        let keypair = signer.keypair()
+            .ok_or_else(|| anyhow::anyhow!("外部签名器无法解密：需要本地私钥"))?;
+        e2e_encryption::decrypt(keypair, &payload)
+    }
+    
+    /// 创建定时消息：在not_before（Unix秒）之前不可投递
+    /// 邮箱/中继应持有消息直到该时间，接收方验证时也会拒绝提前到达的消息。
+    /// nonce以not_before为时间戳生成，因此在投递时间之后的有效期内仍然新鲜
+    pub async fn create_scheduled_message(
+        &self,
+        topic: &str,
+        message_type: PubSubMessageType,
+        content: &[u8],
+        to_did: Option<String>,
+        not_before: u64,
+    ) -> Result<AuthenticatedMessage> {
+        if not_before <= self.clock.now_secs() {
+            log::debug!("定时消息的投递时间已过，按普通消息创建");
+            return self.build_message(topic, message_type, content, to_did, None, None).await;
+        }
+        self.build_message(topic, message_type, content, to_did, Some(not_before), None).await
+    }
+    
+    /// 创建携带能力令牌的调用消息（本地身份必须是令牌的受众）
+    /// 接收方按能力令牌策略验证委托链后再分发请求
+    pub async fn create_invocation(
+        &self,
+        topic: &str,
+        message_type: PubSubMessageType,
+        content: &[u8],
+        to_did: Option<String>,
+        capability: &CapabilityToken,
+    ) -> Result<AuthenticatedMessage> {
+        let local_did = self.local_did().await
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        if capability.audience != local_did {
+            anyhow::bail!("能力令牌的受众不是本地身份: {}", capability.audience);
+        }
+        if !capability.allows(topic, &trust_level::message_type_key(&message_type)) {
+            anyhow::bail!("能力令牌不允许在{}上发送该类型的消息", topic);
+        }
+        self.build_message(topic, message_type, content, to_did, None, Some(capability.encode()?)).await
+    }
+    
+    async fn build_message(
+        &self,
+        topic: &str,
+        message_type: PubSubMessageType,
+        content: &[u8],
+        to_did: Option<String>,
+        not_before: Option<u64>,
+        capability: Option<String>,
     ) -> Result<AuthenticatedMessage> {
         // 1. 检查本地身份
-        let keypair = self.keypair.read().await
+        let signer = self.signer.read().await
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?
             .clone();
@@ -221,39 +906,82 @@ impl PubsubAuthenticator {
             .ok_or_else(|| anyhow::anyhow!("未设置CID"))?
             .clone();
         
-        // 2. 生成nonce
-        let nonce = NonceManager::generate_nonce();
-        
-        // 3. 获取DID文档（用于ZKP证明）
-        let did_document = crate::did_builder::get_did_document_from_cid(
-            self.identity_manager.ipfs_client(),
-            &cid
-        ).await?;
-        
-        // 4. 生成ZKP证明
-        let zkp_proof = self.identity_manager.generate_binding_proof(
-            &keypair,
-            &did_document,
-            &cid,
-            nonce.as_bytes(),
-        )?;
+        // 2. 生成nonce（定时消息以投递时间为nonce时间戳）
+        let nonce = match not_before {
+            Some(not_before) => NonceManager::generate_nonce_at(not_before),
+            None => NonceManager::generate_nonce_with_clock(self.clock.as_ref()),
+        };
         
-        // 5. 签名消息内容
-        use ed25519_dalek::{SigningKey, Signer};
-        let signing_key = SigningKey::from_bytes(&keypair.private_key);
+        // 2.5 有效的会话令牌（绑定当前DID文档）可代替ZKP证明，过期后丢弃并回退到ZKP
+        let session_token = {
+            let mut held = self.session_token.write().await;
+            match held.as_ref() {
+                Some(token) if token.is_expired(self.clock.now_secs()) => {
+                    log::info!("🎫 会话令牌已过期，恢复逐条消息ZKP");
+                    *held = None;
+                    None
+                }
+                Some(token) if token.claims.cid.as_deref().is_some_and(|bound| bound != cid) => None,
+                Some(token) => Some(token.encode()),
+                None => None,
+            }
+        };
         
-        let mut sign_data = Vec::new();
-        sign_data.extend_from_slice(content);
-        sign_data.extend_from_slice(nonce.as_bytes());
-        sign_data.extend_from_slice(topic.as_bytes());
+        // 3-4. 获取DID文档并生成ZKP证明（需要可导出的私钥）
+        let zkp_proof = match signer.keypair() {
+            _ if session_token.is_some() => {
+                log::debug!("消息携带会话令牌，不生成ZKP证明");
+                Vec::new()
+            }
+            _ if !self.feature_toggles.is_enabled(&SdkFeature::ZkpPerMessage) => {
+                log::debug!("逐条消息ZKP已关闭，消息仅携带签名");
+                Vec::new()
+            }
+            Some(keypair) => {
+                let did_document = crate::did_builder::get_did_document_from_cid(
+                    self.identity_manager.ipfs_client(),
+                    &cid
+                ).await?;
+                
+                self.identity_manager.generate_binding_proof(
+                    keypair,
+                    &did_document,
+                    &cid,
+                    nonce.as_bytes(),
+                )?
+            }
+            // 外部签名器不导出私钥，无法生成ZKP证明：要求ZKP的主题需要会话令牌
+            None => match self.topic_requires_zkp(topic).await {
+                Some(true) => {
+                    anyhow::bail!("主题{}要求ZKP证明，外部签名器无法生成，需要先设置会话令牌", topic);
+                }
+                Some(false) => {
+                    log::debug!("主题{}不要求ZKP，外部签名器的消息仅携带签名", topic);
+                    Vec::new()
+                }
+                None => {
+                    log::warn!("⚠️ 外部签名器无法生成ZKP证明，消息仅携带签名（接收方只在主题不要求ZKP时接受）");
+                    Vec::new()
+                }
+            },
+        };
         
-        let signature = signing_key.sign(&sign_data);
+        // 5. 签名消息内容
+        let sign_data = AuthenticatedMessage::build_signing_data(
+            content,
+            &nonce,
+            topic,
+            not_before,
+            capability.as_deref(),
+            session_token.as_deref(),
+        );
+        let signature = signer.sign(&sign_data)?;
         
         // 6. 构造认证消息
         let message = AuthenticatedMessage {
-            message_id: uuid::Uuid::new_v4().to_string(),
+            message_id: crate::message_id::new_message_id(),
             message_type,
-            from_did: keypair.did.clone(),
+            from_did: signer.did(),
             to_did,
             from_peer_id: peer_id,
             did_cid: cid,
@@ -261,13 +989,15 @@ impl PubsubAuthenticator {
             content: content.to_vec(),
             nonce,
             zkp_proof: zkp_proof,
-            signature: signature.to_bytes().to_vec(),
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)?
-                .as_secs(),
+            signature,
+            timestamp: self.clock.now_secs(),
+            not_before,
+            capability,
+            session_token,
         };
         
         log::debug!("✓ 创建认证消息: {}", message.message_id);
+        crate::metrics::global().pubsub_published.inc();
         
         Ok(message)
     }
@@ -277,31 +1007,305 @@ impl PubsubAuthenticator {
         &self,
         message: &AuthenticatedMessage,
     ) -> Result<MessageVerification> {
-        let mut details = Vec::new();
+        self.verify_message_within(message, None, None).await
+    }
+    
+    /// 在延迟预算内验证认证消息（例如交互式认证的800ms）
+    /// 获取DID文档、验证证明等步骤只使用剩余预算，超出时返回provisional结果
+    pub async fn verify_message_with_budget(
+        &self,
+        message: &AuthenticatedMessage,
+        budget: &LatencyBudget,
+    ) -> Result<MessageVerification> {
+        self.verify_message_within(message, Some(budget), None).await
+    }
+    
+    #[tracing::instrument(
+        name = "diap.verify_message",
+        skip_all,
+        fields(message_id = %message.message_id, from_did = %message.from_did, topic = %message.topic, verified = tracing::field::Empty),
+    )]
+    async fn verify_message_within(
+        &self,
+        message: &AuthenticatedMessage,
+        budget: Option<&LatencyBudget>,
+        source: Option<&PeerId>,
+    ) -> Result<MessageVerification> {
+        let verification = self.screen_and_check(message, budget, source).await?;
+        self.audit_verification(message, &verification);
+        Ok(verification)
+    }
+    
+    /// 经过准入控制的验证：熔断和配额检查在前，验证结果按发送者身份是否被证明结算
+    async fn screen_and_check(
+        &self,
+        message: &AuthenticatedMessage,
+        budget: Option<&LatencyBudget>,
+        source: Option<&PeerId>,
+    ) -> Result<MessageVerification> {
+        let source_key = source.map(|peer| peer.to_base58());
+        let peer = source_key.as_deref();
+        let topic_limit = self.topic_config_for(&message.topic).await.and_then(|config| config.rate_limit);
+        
+        // 熔断中的发送者或对端、已耗尽配额的发送者在验证前直接拒绝；配额只在验证通过后消耗
+        let now_ms = self.clock.now_millis();
+        if let Err(rejection) = self.admission.screen(&message.from_did, peer, &message.topic, topic_limit.as_ref(), now_ms) {
+            return Ok(self.reject_inadmissible(message, rejection, false));
+        }
+        
+        let CheckOutcome { verification, sender_proven } = self.check_and_count(message, budget).await?;
+        if verification.verified {
+            let now_ms = self.clock.now_millis();
+            if let Err(rejection) = self.admission.accept(&message.from_did, peer, &message.topic, topic_limit.as_ref(), now_ms) {
+                return Ok(self.reject_inadmissible(message, rejection, true));
+            }
+        } else if !verification.provisional {
+            self.record_verification_failure(message, &verification);
+            self.admission.reject(&message.from_did, peer, sender_proven);
+        }
+        Ok(verification)
+    }
+    
+    /// 只做消息验证，不经过熔断、限流和信誉（只读验证器使用）
+    #[tracing::instrument(
+        name = "diap.verify_message",
+        skip_all,
+        fields(message_id = %message.message_id, from_did = %message.from_did, topic = %message.topic, verified = tracing::field::Empty),
+    )]
+    pub(crate) async fn verify_message_unscreened(
+        &self,
+        message: &AuthenticatedMessage,
+        budget: Option<&LatencyBudget>,
+    ) -> Result<MessageVerification> {
+        let verification = self.check_and_count(message, budget).await?.verification;
+        if !verification.verified && !verification.provisional {
+            self.record_verification_failure(message, &verification);
+        }
+        self.audit_verification(message, &verification);
+        Ok(verification)
+    }
+    
+    async fn check_and_count(
+        &self,
+        message: &AuthenticatedMessage,
+        budget: Option<&LatencyBudget>,
+    ) -> Result<CheckOutcome> {
+        let outcome = self.check_message(message, budget).await?;
+        tracing::Span::current().record("verified", outcome.verification.verified);
+        if outcome.verification.verified {
+            crate::metrics::global().pubsub_verified.inc();
+        } else if !outcome.verification.provisional {
+            crate::metrics::global().pubsub_rejected.inc();
+        }
+        Ok(outcome)
+    }
+    
+    fn reject_inadmissible(
+        &self,
+        message: &AuthenticatedMessage,
+        rejection: AdmissionRejection,
+        authenticated: bool,
+    ) -> MessageVerification {
+        match rejection {
+            AdmissionRejection::CircuitOpen => {
+                log::debug!("⛔ 发送者处于熔断冷却期，跳过验证: {}", message.from_did);
+                MessageVerification {
+                    verified: false,
+                    from_did: message.from_did.clone(),
+                    details: vec!["✗ 发送者连续验证失败，处于熔断冷却期".to_string()],
+                    verified_at: self.clock.now_secs(),
+                    provisional: false,
+                    trust_level: None,
+                }
+            }
+            AdmissionRejection::RateLimited { scope, limit } => self.reject_rate_limited(message, scope, limit, authenticated),
+        }
+    }
+    
+    fn reject_rate_limited(
+        &self,
+        message: &AuthenticatedMessage,
+        scope: RateLimitScope,
+        limit: RateLimit,
+        authenticated: bool,
+    ) -> MessageVerification {
+        let now = self.clock.now_secs();
+        let detail = format!("✗ 超出速率限制（{:?}: 每秒{}条，突发{}条）", scope, limit.per_second, limit.burst);
+        self.admission.rate_limiter().report(&AbuseEvent {
+            did: message.from_did.clone(),
+            topic: message.topic.clone(),
+            message_id: message.message_id.clone(),
+            kind: AbuseKind::RateLimited { scope, limit },
+            authenticated,
+            at: now,
+        });
+        MessageVerification {
+            verified: false,
+            from_did: message.from_did.clone(),
+            details: vec![detail],
+            verified_at: now,
+            provisional: false,
+            trust_level: None,
+        }
+    }
+    
+    /// 验证从传输层对端收到的消息：gossipsub签名来源或直连对端的PeerID必须与消息声明的PeerID一致，
+    /// 再按DID与PeerID的绑定做完整验证，会话中途出现的PeerID冒用在这里被发现
+    pub async fn verify_message_from_peer(
+        &self,
+        message: &AuthenticatedMessage,
+        source: &PeerId,
+    ) -> Result<MessageVerification> {
+        if message.from_peer_id != source.to_base58() {
+            log::warn!("⚠️ 传输层PeerID与消息声明不符: {} != {}", source, message.from_peer_id);
+            let verification = MessageVerification {
+                verified: false,
+                from_did: message.from_did.clone(),
+                details: vec![format!("✗ 传输层PeerID {} 与消息声明的PeerID不符", source)],
+                verified_at: self.clock.now_secs(),
+                provisional: false,
+                trust_level: None,
+            };
+            self.record_verification_failure(message, &verification);
+            self.audit_verification(message, &verification);
+            return Ok(verification);
+        }
+        self.verify_message_within(message, None, Some(source)).await
+    }
+    
+    /// 验证失败总数
+    pub fn verification_failure_count(&self) -> u64 {
+        self.verification_failure_total.load(Ordering::Relaxed)
+    }
+    
+    /// 最近的验证失败（最新的在后）
+    pub fn recent_verification_failures(&self) -> Vec<VerificationFailure> {
+        self.verification_failures.lock().unwrap().iter().cloned().collect()
+    }
+    
+    fn audit_verification(&self, message: &AuthenticatedMessage, verification: &MessageVerification) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record_or_warn(&message.from_did, AuditEvent::Verification {
+                message_id: message.message_id.clone(),
+                topic: message.topic.clone(),
+                verified: verification.verified,
+                provisional: verification.provisional,
+                reasons: verification.details.iter().filter(|d| d.starts_with('✗')).cloned().collect(),
+            });
+        }
+    }
+    
+    fn audit_nonce_rejection(&self, message: &AuthenticatedMessage, reason: &str) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record_or_warn(&message.from_did, AuditEvent::NonceRejected {
+                message_id: message.message_id.clone(),
+                nonce: message.nonce.clone(),
+                reason: reason.to_string(),
+            });
+        }
+    }
+    
+    fn record_verification_failure(&self, message: &AuthenticatedMessage, verification: &MessageVerification) {
+        self.verification_failure_total.fetch_add(1, Ordering::Relaxed);
+        let mut failures = self.verification_failures.lock().unwrap();
+        if failures.len() >= MAX_RECENT_VERIFICATION_FAILURES {
+            failures.pop_front();
+        }
+        failures.push_back(VerificationFailure {
+            message_id: message.message_id.clone(),
+            from_did: message.from_did.clone(),
+            topic: message.topic.clone(),
+            reasons: verification.details.iter().filter(|d| d.starts_with('✗')).cloned().collect(),
+            at: verification.verified_at,
+        });
+    }
+    
+    async fn check_message(
+        &self,
+        message: &AuthenticatedMessage,
+        budget: Option<&LatencyBudget>,
+    ) -> Result<CheckOutcome> {
+        let mut details = Vec::new();
         let mut verified = true;
+        let mut provisional = false;
+        let mut nonce_fresh = false;
         
-        log::info!("🔍 验证消息: {}", message.message_id);
-        log::info!("  发送者DID: {}", message.from_did);
+        // 0. 定时消息不得提前投递（提前到达时不消耗nonce，到期后仍可验证）
+        if let Some(not_before) = message.not_before {
+            let now = self.clock.now_secs();
+            if now < not_before {
+                log::warn!("⏳ 消息尚未到投递时间: {} (还需{}秒)", message.message_id, not_before - now);
+                return Ok(MessageVerification {
+                    verified: false,
+                    from_did: message.from_did.clone(),
+                    details: vec![format!("✗ 消息在{}之前不可投递", not_before)],
+                    verified_at: now,
+                    provisional: false,
+                    trust_level: None,
+                }.into());
+            }
+        }
+        
+        // 0.5 时间戳窗口（nonce中的时间戳受签名保护；窗口外的消息不消耗nonce，也不进入重放记录）
+        // 已估计出发送者时钟偏移时，以发送者时钟下的当前时间比较
+        let clock_offset = self.clock_offsets.correction_ms(&message.from_did, self.clock.now_millis());
+        if let Some(timestamp) = NonceManager::nonce_timestamp(&message.nonce) {
+            let now = self.clock.now_secs();
+            let peer_now = self.clock.now_millis().saturating_add_signed(clock_offset) / 1000;
+            if let Err(violation) = self.timestamp_window.check(timestamp, peer_now) {
+                log::warn!("⏱️ 消息时间戳超出窗口: {} ({})", message.message_id, violation);
+                self.audit_nonce_rejection(message, &violation.to_string());
+                return Ok(MessageVerification {
+                    verified: false,
+                    from_did: message.from_did.clone(),
+                    details: vec![format!("✗ {}", violation)],
+                    verified_at: now,
+                    provisional: false,
+                    trust_level: None,
+                }.into());
+            }
+        }
+        
+        // 0.8 已吊销的DID不再接受任何消息
+        if self.revocations.as_ref().is_some_and(|registry| registry.is_revoked(&message.from_did)) {
+            log::warn!("⛔ 拒绝已吊销DID的消息: {}", message.from_did);
+            return Ok(MessageVerification {
+                verified: false,
+                from_did: message.from_did.clone(),
+                details: vec!["✗ 发送者DID已吊销".to_string()],
+                verified_at: self.clock.now_secs(),
+                provisional: false,
+                trust_level: None,
+            }.into());
+        }
         
         // 1. 验证nonce（防重放）
-        match self.nonce_manager.verify_and_record(&message.nonce, &message.from_did) {
+        match self.nonce_manager.verify_and_record_with_offset(&message.nonce, &message.from_did, clock_offset) {
             Ok(true) => {
+                nonce_fresh = true;
                 details.push("✓ Nonce验证通过".to_string());
             }
             Ok(false) => {
                 verified = false;
                 details.push("✗ Nonce已被使用（重放攻击）".to_string());
                 log::warn!("检测到重放攻击！消息ID: {}", message.message_id);
+                self.audit_nonce_rejection(message, "重放");
             }
             Err(e) => {
                 verified = false;
                 details.push(format!("✗ Nonce验证失败: {}", e));
+                self.audit_nonce_rejection(message, &e.to_string());
             }
         }
         
-        // 2. 检查主题授权
-        let topic_config = self.topic_configs.read().await;
-        if let Some(config) = topic_config.get(&message.topic) {
+        // 2. 检查主题授权（收件箱主题只接受发给其所有者的消息）
+        if let Some(owner) = topic_pattern::inbox_owner(&message.topic) {
+            if message.to_did.as_deref() != Some(owner) {
+                verified = false;
+                details.push("✗ 收件箱消息的接收者与主题不符".to_string());
+            }
+        }
+        if let Some(config) = self.topic_config_for(&message.topic).await {
             match &config.policy {
                 TopicPolicy::AllowAuthenticated => {
                     // 通过认证即可
@@ -319,65 +1323,244 @@ impl PubsubAuthenticator {
                     }
                 }
                 TopicPolicy::Custom => {
-                    // 自定义验证逻辑
+                    // 自定义验证逻辑（例如按信任分）
+                    if !self.admission.custom_policy_allows(&message.topic, &message.from_did).await {
+                        verified = false;
+                        details.push("✗ 自定义主题策略拒绝该发送者".to_string());
+                    }
                 }
             }
+            
+            if config.encryption == TopicEncryption::Required && !EncryptedPayload::is_encrypted(&message.content) {
+                verified = false;
+                details.push("✗ 主题要求端到端加密".to_string());
+            }
         }
         
-        // 3. 获取DID文档（先从缓存）
-        let did_document = if let Some(doc) = self.did_cache.get(&message.did_cid) {
-            details.push("✓ 从缓存获取DID文档".to_string());
-            doc
-        } else {
-            match crate::did_builder::get_did_document_from_cid(
-                self.identity_manager.ipfs_client(),
-                &message.did_cid
-            ).await {
-                Ok(doc) => {
-                    self.did_cache.put(message.did_cid.clone(), doc.clone()).ok();
-                    details.push("✓ 从IPFS获取DID文档并缓存".to_string());
-                    doc
+        // 2.1 PeerID与DID绑定（派生或联合签名）
+        match self.peer_bindings.check(&message.from_did, &message.from_peer_id) {
+            PeerBindingCheck::Derived => details.push("✓ PeerID由DID密钥派生".to_string()),
+            PeerBindingCheck::CoSigned => details.push("✓ PeerID与联合签名绑定一致".to_string()),
+            PeerBindingCheck::Unknown if self.require_peer_binding => {
+                verified = false;
+                details.push("✗ 缺少PeerID与DID的绑定".to_string());
+            }
+            PeerBindingCheck::Unknown => {}
+            PeerBindingCheck::Mismatch => {
+                verified = false;
+                log::warn!("⚠️ 检测到PeerID冒用: {} 声明 {}", message.from_did, message.from_peer_id);
+                details.push("✗ PeerID与DID绑定不符（可能被冒用）".to_string());
+            }
+        }
+        
+        // 2.2 能力令牌（要求令牌的主题上，调用者必须持有认可签发者授权的有效委托链）
+        if let Some(trusted_issuers) = self.capability_policy.read().await.trusted_issuers(&message.topic) {
+            let action = trust_level::message_type_key(&message.message_type);
+            match message.capability.as_deref().map(CapabilityToken::decode) {
+                None => {
+                    verified = false;
+                    details.push("✗ 主题要求能力令牌".to_string());
                 }
-                Err(e) => {
-                    details.push(format!("✗ 获取DID文档失败: {}", e));
-                    
-                    return Ok(MessageVerification {
-                        verified: false,
-                        from_did: message.from_did.clone(),
-                        details,
-                        verified_at: std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)?
-                            .as_secs(),
-                    });
+                Some(Err(e)) => {
+                    verified = false;
+                    details.push(format!("✗ 能力令牌无效: {}", e));
+                }
+                Some(Ok(token)) => {
+                    match token.check_invocation(&message.from_did, &message.topic, &action, trusted_issuers, self.clock.now_secs()) {
+                        Ok(()) => details.push(format!(
+                            "✓ 能力令牌验证通过（根签发者 {}，委托深度 {}）",
+                            token.root_issuer(),
+                            token.depth()
+                        )),
+                        Err(e) => {
+                            verified = false;
+                            details.push(format!("✗ 能力令牌无效: {}", e));
+                        }
+                    }
                 }
             }
-        };
+        }
         
-        // 4. 验证ZKP证明
-        let zkp_result = self.identity_manager.verify_identity_with_zkp(
-            &message.did_cid,
-            &message.zkp_proof,
-            message.nonce.as_bytes(),
-        ).await;
+        // 要求最高信任等级的消息不使用验证提示和缓存，总是重新获取DID文档并验证ZKP
+        let required_level = self.trust_policy.read().await.required_level(&message.topic, &message.message_type);
+        let need_fresh = required_level == Some(TrustLevel::FullZkpFreshDoc);
+        let mut achieved_level = TrustLevel::FullZkpFreshDoc;
         
-        match zkp_result {
-            Ok(verification) if verification.zkp_verified => {
-                details.push("✓ ZKP证明验证通过".to_string());
-            }
-            Ok(_) => {
-                verified = false;
-                details.push("✗ ZKP证明验证失败".to_string());
+        // 2.5 DHT验证提示（签名有效、未过期且CID一致时直接使用其中的公钥，跳过IPFS解析）
+        let hinted = if need_fresh || !self.feature_toggles.is_enabled(&SdkFeature::VerificationHints) {
+            None
+        } else {
+            self.hinted_public_key(message, budget, &mut details).await
+        };
+        let public_key_bytes = if let Some(public_key) = hinted {
+            achieved_level = TrustLevel::SignatureOnly;
+            public_key.to_vec()
+        } else {
+            // 3. 获取DID文档（先从缓存）
+            let cached = if need_fresh {
+                None
+            } else {
+                let span = tracing::info_span!("diap.did_cache", cid = %message.did_cid, hit = tracing::field::Empty);
+                span.in_scope(|| {
+                    let cached = self.did_cache.get(&message.did_cid);
+                    span.record("hit", cached.is_some());
+                    cached
+                })
+            };
+            let did_document = if let Some(doc) = cached {
+                details.push("✓ 从缓存获取DID文档".to_string());
+                achieved_level = TrustLevel::SignaturePlusCachedDoc;
+                doc
+            } else if let Some(reason) = self.did_cache.negative_reason(&message.did_cid)
+                .filter(|_| !self.has_commitment(&message.did_cid, &message.from_did)) {
+                // 最近解析失败过，负缓存有效期内不再请求IPFS
+                details.push(format!("✗ 获取DID文档失败（负缓存）: {}", reason));
+                return Ok(MessageVerification {
+                    verified: false,
+                    from_did: message.from_did.clone(),
+                    details,
+                    verified_at: self.clock.now_secs(),
+                    provisional: false,
+                    trust_level: None,
+                }.into());
+            } else {
+                let fetched = latency_budget::run_within(budget, "获取DID文档", crate::did_builder::get_did_document_from_cid(
+                    self.identity_manager.ipfs_client(),
+                    &message.did_cid
+                )).await;
+                let fetched = match fetched {
+                    Ok(fetched) => fetched,
+                    Err(exceeded) => {
+                        // 超时不写入负缓存，下次仍会尝试获取
+                        details.push(format!("⏱ {}", exceeded));
+                        return Ok(MessageVerification {
+                            verified: false,
+                            from_did: message.from_did.clone(),
+                            details,
+                            verified_at: self.clock.now_secs(),
+                            provisional: true,
+                            trust_level: None,
+                        }.into());
+                    }
+                };
+                match fetched {
+                    Ok(doc) => {
+                        self.did_cache.put(message.did_cid.clone(), doc.clone()).ok();
+                        self.cid_commitments.lock().unwrap().remove(&message.did_cid);
+                        details.push("✓ 从IPFS获取DID文档并缓存".to_string());
+                        doc
+                    }
+                    Err(e) if self.has_commitment(&message.did_cid, &message.from_did) => {
+                        // 已预提交、尚未上传：身份待定，不写入负缓存
+                        details.push(format!("⏳ DID文档尚未发布（已预提交CID），身份待定: {}", e));
+                        return Ok(MessageVerification {
+                            verified: false,
+                            from_did: message.from_did.clone(),
+                            details,
+                            verified_at: self.clock.now_secs(),
+                            provisional: true,
+                            trust_level: None,
+                        }.into());
+                    }
+                    Err(e) => {
+                        self.did_cache.put_negative(&message.did_cid, e.to_string());
+                        details.push(format!("✗ 获取DID文档失败: {}", e));
+                    
+                        return Ok(MessageVerification {
+                            verified: false,
+                            from_did: message.from_did.clone(),
+                            details,
+                            verified_at: self.clock.now_secs(),
+                            provisional: false,
+                            trust_level: None,
+                        }.into());
+                    }
+                }
+            };
+            
+            // 3.5 DID文档必须属于消息声明的发送者（from_did不在签名范围内，否则可用自己的文档和密钥冒用他人DID）
+            if did_document.id != message.from_did {
+                log::warn!("⚠️ DID文档与发送者不符: {} 声明为 {}", did_document.id, message.from_did);
+                details.push(format!("✗ DID文档属于{}，与发送者DID不符", did_document.id));
+                return Ok(MessageVerification {
+                    verified: false,
+                    from_did: message.from_did.clone(),
+                    details,
+                    verified_at: self.clock.now_secs(),
+                    provisional: false,
+                    trust_level: None,
+                }.into());
             }
-            Err(e) => {
-                verified = false;
-                details.push(format!("✗ ZKP验证错误: {}", e));
+        
+            // 4. 验证ZKP证明（逐条ZKP被远程关闭或携带受信任的会话令牌时跳过，信任等级最高为签名+DID文档）
+            let token_accepted = match message.session_token.as_deref() {
+                Some(token) if !need_fresh => {
+                    let trusted_issuers = self.trusted_token_issuers.read().await;
+                    match SessionToken::decode(token).and_then(|token| {
+                        token.check(&message.from_did, &message.did_cid, &trusted_issuers, self.clock.now_secs())?;
+                        Ok(token)
+                    }) {
+                        Ok(token) => {
+                            details.push(format!(
+                                "✓ 会话令牌有效（签发者 {}，有效至 {}），跳过ZKP验证",
+                                token.claims.iss,
+                                token.claims.exp
+                            ));
+                            achieved_level = achieved_level.min(TrustLevel::SignaturePlusCachedDoc);
+                            true
+                        }
+                        Err(e) => {
+                            details.push(format!("⚠ 会话令牌不可用，回退到ZKP验证: {}", e));
+                            false
+                        }
+                    }
+                }
+                _ => false,
+            };
+            let zkp_result = if token_accepted {
+                None
+            } else if message.zkp_proof.is_empty() && self.topic_requires_zkp(&message.topic).await == Some(false) {
+                details.push("⚠ 主题不要求ZKP，消息未携带证明，跳过ZKP验证".to_string());
+                achieved_level = achieved_level.min(TrustLevel::SignaturePlusCachedDoc);
+                None
+            } else if self.feature_toggles.is_enabled(&SdkFeature::ZkpPerMessage) {
+                Some(self.identity_manager.verify_identity_within(
+                    &message.did_cid,
+                    &message.zkp_proof,
+                    message.nonce.as_bytes(),
+                    budget,
+                ).instrument(tracing::info_span!("diap.zkp_verify")).await)
+            } else {
+                details.push("⚠ 逐条消息ZKP已关闭，跳过ZKP验证".to_string());
+                achieved_level = achieved_level.min(TrustLevel::SignaturePlusCachedDoc);
+                None
+            };
+        
+            match zkp_result {
+                None => {}
+                Some(Ok(verification)) if verification.provisional => {
+                    provisional = true;
+                    details.extend(verification.verification_details);
+                }
+                Some(Ok(verification)) if verification.zkp_verified => {
+                    details.push("✓ ZKP证明验证通过".to_string());
+                }
+                Some(Ok(_)) => {
+                    verified = false;
+                    details.push("✗ ZKP证明验证失败".to_string());
+                }
+                Some(Err(e)) => {
+                    verified = false;
+                    details.push(format!("✗ ZKP验证错误: {}", e));
+                }
             }
-        }
+        
+            self.extract_public_key(&did_document)?
+        };
         
         // 5. 验证消息签名
         use ed25519_dalek::{VerifyingKey, Verifier, Signature};
         
-        let public_key_bytes = self.extract_public_key(&did_document)?;
         let key_bytes = if public_key_bytes.len() > 32 {
             &public_key_bytes[public_key_bytes.len() - 32..]
         } else {
@@ -392,33 +1575,83 @@ impl PubsubAuthenticator {
             message.signature.as_slice().try_into().context("签名长度错误")?
         );
         
-        let mut sign_data = Vec::new();
-        sign_data.extend_from_slice(&message.content);
-        sign_data.extend_from_slice(message.nonce.as_bytes());
-        sign_data.extend_from_slice(message.topic.as_bytes());
+        let signature_valid = tracing::info_span!("diap.signature_verify")
+            .in_scope(|| verifying_key.verify(&message.signing_data(), &signature));
+        let signature_valid = signature_valid.is_ok();
+        if signature_valid {
+            details.push("✓ 消息签名验证通过".to_string());
+        } else {
+            verified = false;
+            details.push("✗ 消息签名验证失败".to_string());
+        }
         
-        match verifying_key.verify(&sign_data, &signature) {
-            Ok(_) => {
-                details.push("✓ 消息签名验证通过".to_string());
-            }
-            Err(_) => {
+        // 6. 信任等级（只有验证通过的结果才有等级）
+        let trust_level = (verified && !provisional).then_some(achieved_level);
+        if let (Some(required), Some(achieved)) = (required_level, trust_level) {
+            if !achieved.satisfies(required) {
                 verified = false;
-                details.push("✗ 消息签名验证失败".to_string());
+                details.push(format!("✗ 信任等级不足: 需要{}，实际{}", required, achieved));
             }
         }
         
-        log::info!("验证结果: {}", if verified { "✅ 通过" } else { "❌ 失败" });
+        // 超出预算时已完成的步骤仍保留在details中，但结果不算通过
+        let verified = verified && !provisional;
+        tracing::info!(verified, provisional, "验证结果: {}", if verified { "✅ 通过" } else if provisional { "⏱ 临时" } else { "❌ 失败" });
         
-        Ok(MessageVerification {
-            verified,
-            from_did: message.from_did.clone(),
-            details,
-            verified_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)?
-                .as_secs(),
+        Ok(CheckOutcome {
+            verification: MessageVerification {
+                verified,
+                from_did: message.from_did.clone(),
+                details,
+                verified_at: self.clock.now_secs(),
+                provisional,
+                trust_level,
+            },
+            // 公钥来自属于from_did的文档或验证提示，签名有效且nonce未被使用过
+            sender_proven: signature_valid && nonce_fresh,
         })
     }
     
+    /// 查找发送者的DHT验证提示；缺失或过期时记录原因并返回None（回退到完整解析）
+    async fn hinted_public_key(
+        &self,
+        message: &AuthenticatedMessage,
+        budget: Option<&LatencyBudget>,
+        details: &mut Vec<String>,
+    ) -> Option<[u8; 32]> {
+        let source = self.verification_hints.as_ref()?;
+        let hint = match latency_budget::run_within(budget, "查询验证提示", source.lookup(&message.from_did)).await {
+            Ok(Ok(Some(hint))) => hint,
+            Ok(Ok(None)) => return None,
+            Ok(Err(e)) => {
+                log::debug!("查询验证提示失败: {}", e);
+                return None;
+            }
+            Err(exceeded) => {
+                log::debug!("{}", exceeded);
+                return None;
+            }
+        };
+        
+        let reason = if hint.did != message.from_did || !hint.verify().unwrap_or(false) {
+            "签名无效"
+        } else if hint.is_expired(self.clock.now_secs()) {
+            "已过期"
+        } else if hint.cid != message.did_cid {
+            "CID与消息不一致"
+        } else {
+            match hint.public_key_bytes() {
+                Ok(public_key) => {
+                    details.push(format!("✓ 使用DHT验证提示（{}）", hint.cid));
+                    return Some(public_key);
+                }
+                Err(_) => "公钥格式错误",
+            }
+        };
+        details.push(format!("⚠ 验证提示{}，回退到完整解析", reason));
+        None
+    }
+    
     /// 从DID文档提取公钥
     fn extract_public_key(&self, did_document: &crate::did_builder::DIDDocument) -> Result<Vec<u8>> {
         let vm = did_document.verification_method.first()
@@ -434,14 +1667,12 @@ impl PubsubAuthenticator {
     
     /// 序列化消息为字节
     pub fn serialize_message(message: &AuthenticatedMessage) -> Result<Vec<u8>> {
-        bincode::serialize(message)
-            .context("序列化消息失败")
+        legacy_compat::encode_message(message)
     }
     
-    /// 反序列化消息
+    /// 反序列化消息（兼容v0格式）
     pub fn deserialize_message(data: &[u8]) -> Result<AuthenticatedMessage> {
-        bincode::deserialize(data)
-            .context("反序列化消息失败")
+        legacy_compat::decode_message(data).map(|(message, _)| message)
     }
     
     /// 获取缓存统计
@@ -454,8 +1685,11 @@ impl PubsubAuthenticator {
         self.nonce_manager.count()
     }
     
-    /// 订阅主题
+    /// 订阅主题（支持通配符模式，例如 diap/agents/*/inbox 或 diap/**）
     pub async fn subscribe_topic(&self, topic: &str) -> Result<()> {
+        if topic_pattern::is_pattern(topic) {
+            TopicPattern::parse(topic)?;
+        }
         let mut topics = self.subscribed_topics.write().await;
         if !topics.contains(&topic.to_string()) {
             topics.push(topic.to_string());
@@ -477,6 +1711,38 @@ impl PubsubAuthenticator {
         self.subscribed_topics.read().await.clone()
     }
     
+    /// 本地DID的收件箱主题（设置身份后自动订阅）
+    pub async fn inbox_topic(&self) -> Option<String> {
+        self.local_did().await.map(|did| topic_pattern::inbox_topic(&did))
+    }
+    
+    /// 是否接收该主题的消息：精确订阅、匹配的通配符订阅或本地收件箱
+    pub async fn is_subscribed(&self, topic: &str) -> bool {
+        if self.inbox_topic().await.as_deref() == Some(topic) {
+            return true;
+        }
+        self.subscribed_topics.read().await.iter().any(|subscription| {
+            subscription == topic
+                || TopicPattern::parse(subscription).is_ok_and(|pattern| pattern.matches(topic))
+        })
+    }
+    
+    /// 需要在gossipsub上加入的分片网络主题
+    /// 精确订阅和收件箱各自映射到一个分片；存在通配符订阅时需要加入全部分片
+    pub async fn gossip_topics(&self, shard_count: u16) -> Vec<String> {
+        let subscriptions = self.get_subscribed_topics().await;
+        if subscriptions.iter().any(|topic| topic_pattern::is_pattern(topic)) {
+            return topic_pattern::all_shard_topics(shard_count);
+        }
+        let mut shards: Vec<String> = subscriptions.iter()
+            .chain(self.inbox_topic().await.iter())
+            .map(|topic| topic_pattern::shard_topic(topic, shard_count))
+            .collect();
+        shards.sort();
+        shards.dedup();
+        shards
+    }
+    
     /// 更新消息统计
     pub async fn update_message_stats(&self, topic: &str) {
         let mut stats = self.message_stats.write().await;
@@ -488,6 +1754,692 @@ impl PubsubAuthenticator {
         self.message_stats.read().await.clone()
     }
     
+    /// 获取消息归档
+    pub fn message_archive(&self) -> Arc<MessageArchive> {
+        self.message_archive.clone()
+    }
+    
+    /// 归档消息（outgoing表示本地发出的消息）
+    pub fn archive_message(&self, message: &AuthenticatedMessage, outgoing: bool) {
+        if !self.feature_toggles.is_enabled(&SdkFeature::MessageArchive) {
+            return;
+        }
+        self.message_archive.archive(message, outgoing);
+    }
+    
+    /// 执行保留策略：删除到期消息，并为要求确认的主题生成删除确认消息
+    /// 返回的消息由调用方发布到对应主题
+    pub async fn enforce_retention(&self) -> Result<Vec<AuthenticatedMessage>> {
+        let expired = self.message_archive.purge_expired();
+        let mut acks = Vec::new();
+        
+        for item in expired.into_iter().filter(|item| item.ack_required) {
+            let ack = DeletionAck {
+                message_id: item.message_id,
+                topic: item.topic.clone(),
+                deleted_at: self.clock.now_secs(),
+            };
+            
+            acks.push(self.create_authenticated_message(
+                &item.topic,
+                PubSubMessageType::Custom(DELETION_ACK_MESSAGE_TYPE.to_string()),
+                &ack.to_bytes()?,
+                Some(item.from_did),
+            ).await?);
+        }
+        
+        Ok(acks)
+    }
+    
+    /// 处理收到的删除确认消息（消息应已通过verify_message验证）
+    pub fn handle_deletion_ack(&self, message: &AuthenticatedMessage) -> Result<()> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == DELETION_ACK_MESSAGE_TYPE => {}
+            _ => anyhow::bail!("不是删除确认消息: {}", message.message_id),
+        }
+        
+        let ack = DeletionAck::from_bytes(&message.content)?;
+        self.message_archive.record_ack(&message.from_did, &ack)?;
+        Ok(())
+    }
+    
+    /// 生成保留策略合规报告
+    pub fn compliance_report(&self) -> ComplianceReport {
+        self.message_archive.compliance_report()
+    }
+    
+    /// 创建检查点，用于将智能体热迁移到其他主机
+    /// outbox为尚未发出的消息，connection_intents为需要在新主机上重建的连接；会话密钥用口令加密
+    pub async fn checkpoint(
+        &self,
+        passphrase: &str,
+        outbox: Vec<AuthenticatedMessage>,
+        connection_intents: Vec<ConnectionIntent>,
+    ) -> Result<AgentCheckpoint> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        let keypair = signer.keypair()
+            .ok_or_else(|| anyhow::anyhow!("外部密钥库中的密钥无法导出，请在新主机上重新接入密钥库"))?;
+        let peer_id = self.peer_id.read().await
+            .ok_or_else(|| anyhow::anyhow!("未设置PeerID"))?;
+        let cid = self.local_cid.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置CID"))?;
+        
+        let checkpoint = AgentCheckpoint {
+            version: CHECKPOINT_VERSION,
+            checkpoint_id: crate::message_id::new_message_id(),
+            did: keypair.did.clone(),
+            cid,
+            peer_id: peer_id.to_string(),
+            session_key: keypair.export_backup(Some(passphrase))?,
+            subscribed_topics: self.subscribed_topics.read().await.clone(),
+            topic_configs: self.topic_configs.read().await.values().cloned().collect(),
+            outbox,
+            connection_intents,
+            created_at: self.clock.now_secs(),
+        };
+        
+        log::info!("📸 创建检查点: {} ({} 条待发消息)", checkpoint.checkpoint_id, checkpoint.outbox.len());
+        Ok(checkpoint)
+    }
+    
+    /// 从检查点恢复本地身份、订阅和主题配置
+    /// 返回待重发的消息、待重建的连接，以及需要广播给对端的恢复通知
+    pub async fn restore(
+        &self,
+        checkpoint: AgentCheckpoint,
+        passphrase: &str,
+        peer_id: PeerId,
+        addresses: Vec<String>,
+    ) -> Result<RestoredAgent> {
+        let keypair = checkpoint.session_keypair(passphrase)?;
+        
+        self.set_local_identity(keypair.clone(), peer_id, checkpoint.cid.clone()).await?;
+        for config in &checkpoint.topic_configs {
+            self.configure_topic(config.clone()).await?;
+        }
+        for topic in &checkpoint.subscribed_topics {
+            self.subscribe_topic(topic).await?;
+        }
+        
+        let resumption = SessionResumption::new(&keypair, &checkpoint, peer_id.to_string(), addresses)?;
+        log::info!("✅ 从检查点恢复: {}", checkpoint.checkpoint_id);
+        
+        Ok(RestoredAgent {
+            keypair,
+            outbox: checkpoint.outbox,
+            connection_intents: checkpoint.connection_intents,
+            resumption,
+        })
+    }
+    
+    /// 信任图
+    pub fn trust_graph(&self) -> &TrustGraph {
+        &self.trust_graph
+    }
+    
+    /// 签发邀请，有效期为ttl
+    pub async fn create_invite(&self, bootstrap: InviteBootstrap, ttl: std::time::Duration) -> Result<AgentInvite> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        let cid = self.local_cid.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置CID"))?;
+        
+        let invite = AgentInvite::new(signer.as_ref(), cid, bootstrap, self.clock.now_secs(), ttl.as_secs())?;
+        log::info!("✉️ 签发邀请: {} (有效期至 {})", invite.invite_id, invite.expires_at);
+        Ok(invite)
+    }
+    
+    /// 接受邀请：校验后订阅邀请中的主题，将邀请人记为引荐人
+    /// 返回需要连接的引导节点、注册表CID和待广播的入网通知
+    pub async fn accept_invite(&self, invite: &AgentInvite) -> Result<AcceptedInvite> {
+        invite.validate(self.clock.now_secs())?;
+        
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        let peer_id = self.peer_id.read().await
+            .ok_or_else(|| anyhow::anyhow!("未设置PeerID"))?;
+        let cid = self.local_cid.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置CID"))?;
+        
+        for topic in &invite.bootstrap.topics {
+            self.subscribe_topic(topic).await?;
+        }
+        
+        let announcement = InviteAnnouncement::new(
+            signer.as_ref(),
+            invite.clone(),
+            cid,
+            peer_id.to_string(),
+            self.clock.now_secs(),
+        )?;
+        self.trust_graph.record_introduction(announcement.introduction());
+        
+        log::info!("✅ 接受邀请: {} (邀请人: {})", invite.invite_id, invite.inviter_did);
+        
+        Ok(AcceptedInvite {
+            bootstrap_peers: invite.bootstrap.bootstrap_peers.clone(),
+            registry_cids: invite.bootstrap.registry_cids.clone(),
+            announcement,
+        })
+    }
+    
+    /// 创建入网通知消息
+    pub async fn create_invite_announcement(
+        &self,
+        topic: &str,
+        announcement: &InviteAnnouncement,
+    ) -> Result<AuthenticatedMessage> {
+        self.create_authenticated_message(
+            topic,
+            PubSubMessageType::Custom(INVITE_ANNOUNCE_MESSAGE_TYPE.to_string()),
+            &announcement.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 处理收到的入网通知（消息应已通过verify_message验证），返回是否新增引荐关系
+    pub fn handle_invite_announcement(&self, message: &AuthenticatedMessage) -> Result<bool> {
+        let announcement = InviteAnnouncement::from_message(message)?;
+        if !announcement.verify()? {
+            anyhow::bail!("入网通知验证失败: {}", announcement.did);
+        }
+        
+        Ok(self.trust_graph.record_introduction(announcement.introduction()))
+    }
+    
+    /// 可靠广播确认跟踪器
+    pub fn broadcast_tracker(&self) -> &BroadcastTracker {
+        &self.broadcast_tracker
+    }
+    
+    /// 创建可靠广播：收到members中threshold个成员的签名确认后才视为送达
+    pub async fn create_reliable_broadcast(
+        &self,
+        topic: &str,
+        message_type: PubSubMessageType,
+        content: &[u8],
+        members: Vec<String>,
+        threshold: usize,
+    ) -> Result<AuthenticatedMessage> {
+        let message = self.create_authenticated_message(topic, message_type, content, None).await?;
+        self.broadcast_tracker.track(&message, members, threshold)?;
+        log::info!("📢 创建可靠广播: {} (阈值 {})", message.message_id, threshold);
+        Ok(message)
+    }
+    
+    /// 确认收到的广播（消息应已通过verify_message验证），返回发给发送者的确认消息
+    pub async fn acknowledge_broadcast(&self, message: &AuthenticatedMessage) -> Result<AuthenticatedMessage> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        
+        let ack = BroadcastAck::new(signer.as_ref(), message, self.clock.now_secs())?;
+        self.create_authenticated_message(
+            &message.topic,
+            PubSubMessageType::Custom(BROADCAST_ACK_MESSAGE_TYPE.to_string()),
+            &ack.to_bytes()?,
+            Some(message.from_did.clone()),
+        ).await
+    }
+    
+    /// 处理收到的广播确认（消息应已通过verify_message验证）
+    /// 达到阈值时返回由本地身份签名的送达证书
+    pub async fn handle_broadcast_ack(&self, message: &AuthenticatedMessage) -> Result<Option<DeliveryCertificate>> {
+        let ack = BroadcastAck::from_message(message)?;
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        
+        self.broadcast_tracker.record_ack(ack, signer.as_ref(), self.clock.now_secs())
+    }
+    
+    /// 创建携带本地CRDT副本状态的同步消息
+    pub async fn create_crdt_sync(&self, topic: &str, replica: &CrdtReplica) -> Result<AuthenticatedMessage> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        
+        let sync = replica.sync_message(signer.as_ref())?;
+        self.create_authenticated_message(
+            topic,
+            PubSubMessageType::Custom(CRDT_SYNC_MESSAGE_TYPE.to_string()),
+            &sync.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 将收到的CRDT同步消息合并到本地副本（消息应已通过verify_message验证）
+    pub fn handle_crdt_sync(&self, message: &AuthenticatedMessage, replica: &CrdtReplica) -> Result<MergeOutcome> {
+        let sync = CrdtSyncMessage::from_message(message)?;
+        replica.apply_sync(&sync)
+    }
+    
+    /// 将租约声明（由LeaseTable::claim/renew/release生成）发布到协调主题
+    pub async fn create_lease_message(&self, topic: &str, claim: &LeaseClaim) -> Result<AuthenticatedMessage> {
+        self.create_authenticated_message(
+            topic,
+            PubSubMessageType::Custom(LEASE_MESSAGE_TYPE.to_string()),
+            &claim.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 处理协调主题上收到的租约声明（消息应已通过verify_message验证）
+    pub fn handle_lease_message(&self, message: &AuthenticatedMessage, table: &LeaseTable) -> Result<LeaseOutcome> {
+        let claim = LeaseClaim::from_message(message)?;
+        table.observe(&claim)
+    }
+    
+    /// 在集群管理主题上发布策略下发（由FleetController::start_rollout/advance/rollback生成）
+    pub async fn create_fleet_directive(&self, topic: &str, directive: &FleetDirective) -> Result<AuthenticatedMessage> {
+        self.create_authenticated_message(
+            topic,
+            PubSubMessageType::Custom(FLEET_POLICY_MESSAGE_TYPE.to_string()),
+            &directive.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 处理收到的策略下发（消息应已通过verify_message验证）
+    /// 本地身份是目标时应用策略包并返回发给控制器的确认消息；不是目标时返回None
+    pub async fn handle_fleet_directive(
+        &self,
+        message: &AuthenticatedMessage,
+        agent: &FleetAgent,
+    ) -> Result<Option<AuthenticatedMessage>> {
+        let directive = FleetDirective::from_message(message)?;
+        let local_did = self.local_did().await
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        if !directive.targets(&local_did) {
+            return Ok(None);
+        }
+        
+        let status = match agent.accept(&directive.bundle) {
+            Ok(true) => match self.apply_policy_bundle(&directive.bundle).await {
+                Ok(()) => {
+                    agent.mark_applied(&directive.bundle);
+                    AckStatus::Applied
+                }
+                Err(e) => AckStatus::Rejected(e.to_string()),
+            },
+            Ok(false) => AckStatus::Applied,
+            Err(e) => AckStatus::Rejected(e.to_string()),
+        };
+        
+        let ack = FleetAck {
+            agent_did: local_did,
+            revision: directive.bundle.revision,
+            status,
+        };
+        self.create_authenticated_message(
+            &message.topic,
+            PubSubMessageType::Custom(FLEET_ACK_MESSAGE_TYPE.to_string()),
+            &ack.to_bytes()?,
+            Some(directive.bundle.controller_did.clone()),
+        ).await.map(Some)
+    }
+    
+    /// 处理收到的策略确认（消息应已通过verify_message验证）
+    pub fn handle_fleet_ack(&self, message: &AuthenticatedMessage, controller: &FleetController) -> Result<RolloutStatus> {
+        controller.record_ack(&FleetAck::from_message(message)?)
+    }
+    
+    /// 应用策略包：主题配置整体替换，然后应用允许列表和日志级别
+    async fn apply_policy_bundle(&self, bundle: &PolicyBundle) -> Result<()> {
+        let log_level = bundle.contents.log_level_filter()?;
+        for topic in bundle.contents.allow_lists.keys() {
+            if topic_pattern::is_pattern(topic) {
+                TopicPattern::parse(topic)?;
+            }
+        }
+        
+        if let Some(topics) = &bundle.contents.topics {
+            self.replace_topic_configs(topics).await;
+        }
+        let mut configs = self.topic_configs.write().await;
+        for (topic, dids) in &bundle.contents.allow_lists {
+            let config = configs.entry(topic.clone()).or_insert_with(|| TopicConfig {
+                name: topic.clone(),
+                policy: TopicPolicy::AllowAuthenticated,
+                require_zkp: false,
+                require_signature: true,
+                retention: None,
+                rate_limit: None,
+                encryption: TopicEncryption::Optional,
+            });
+            config.policy = TopicPolicy::AllowList(dids.clone());
+        }
+        drop(configs);
+        if let Some(level) = log_level {
+            log::set_max_level(level);
+        }
+        
+        log::info!("✓ 应用策略包 {}（控制器 {}）", bundle.revision, bundle.controller_did);
+        Ok(())
+    }
+    
+    /// 向对等方发起时钟同步请求
+    pub async fn create_time_sync_request(&self, topic: &str, peer_did: &str) -> Result<AuthenticatedMessage> {
+        let request = self.clock_offsets.start_request(peer_did, self.clock.now_millis());
+        self.create_authenticated_message(
+            topic,
+            PubSubMessageType::Custom(TIME_SYNC_MESSAGE_TYPE.to_string()),
+            &request.to_bytes()?,
+            Some(peer_did.to_string()),
+        ).await
+    }
+    
+    /// 处理时钟同步消息：请求返回回复消息，回复则记录偏移样本并返回None
+    /// 时钟偏差过大的对等方无法通过时间戳窗口，因此这里直接用did:key验证消息签名，
+    /// 回复通过一次性的请求ID防重放
+    pub async fn handle_time_sync(&self, message: &AuthenticatedMessage) -> Result<Option<AuthenticatedMessage>> {
+        let receive_ms = self.clock.now_millis();
+        let packet = TimeSyncPacket::from_message(message)?;
+        if !KeyPair::verify_with_did_key(&message.from_did, &message.signing_data(), &message.signature)? {
+            anyhow::bail!("时钟同步消息签名无效");
+        }
+        
+        match packet {
+            TimeSyncPacket::Request { .. } => {
+                let response = PeerClockOffsets::respond(&packet, receive_ms, self.clock.now_millis())?;
+                self.create_authenticated_message(
+                    &message.topic,
+                    PubSubMessageType::Custom(TIME_SYNC_MESSAGE_TYPE.to_string()),
+                    &response.to_bytes()?,
+                    Some(message.from_did.clone()),
+                ).await.map(Some)
+            }
+            TimeSyncPacket::Response { .. } => {
+                self.clock_offsets.record_response(&message.from_did, &packet, receive_ms)?;
+                Ok(None)
+            }
+        }
+    }
+    
+    /// 创建群组并下发第1代群组密钥（本地身份为管理员，需要本地私钥）
+    pub async fn create_group(
+        &self,
+        group_id: &str,
+        topic: &str,
+        members: Vec<crate::did_builder::DIDDocument>,
+    ) -> Result<AuthenticatedMessage> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        if self.groups.read().await.contains_key(group_id) {
+            anyhow::bail!("群组已存在: {}", group_id);
+        }
+        
+        let admin = GroupAdmin::new(group_id, topic, &signer.did(), members);
+        let distribution = admin.distribution(signer.as_ref(), self.clock.now_secs())?;
+        self.groups.write().await.insert(group_id.to_string(), admin.keyring().clone());
+        self.group_admins.write().await.insert(group_id.to_string(), admin);
+        log::info!("👥 创建群组 {}（{}名成员）", group_id, distribution.members.len());
+        self.publish_group_key(distribution).await
+    }
+    
+    /// 添加群组成员并轮换密钥（只有管理员可以调用），返回新的密钥下发消息
+    pub async fn add_group_member(&self, group_id: &str, member: crate::did_builder::DIDDocument) -> Result<AuthenticatedMessage> {
+        self.rotate_group(group_id, |admin| admin.add_member(member)).await
+    }
+    
+    /// 移除群组成员并轮换密钥（只有管理员可以调用），返回新的密钥下发消息
+    pub async fn remove_group_member(&self, group_id: &str, did: &str) -> Result<AuthenticatedMessage> {
+        self.rotate_group(group_id, |admin| admin.remove_member(did)).await
+    }
+    
+    async fn rotate_group(&self, group_id: &str, change: impl FnOnce(&mut GroupAdmin) -> bool) -> Result<AuthenticatedMessage> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        let mut admins = self.group_admins.write().await;
+        let admin = admins.get_mut(group_id)
+            .ok_or_else(|| anyhow::anyhow!("不是该群组的管理员: {}", group_id))?;
+        if !change(admin) {
+            anyhow::bail!("群组成员未变化: {}", group_id);
+        }
+        
+        let distribution = admin.distribution(signer.as_ref(), self.clock.now_secs())?;
+        self.groups.write().await.insert(group_id.to_string(), admin.keyring().clone());
+        drop(admins);
+        log::info!("🔄 群组 {} 成员变更，密钥轮换到第{}代", group_id, distribution.epoch);
+        self.publish_group_key(distribution).await
+    }
+    
+    async fn publish_group_key(&self, distribution: GroupKeyDistribution) -> Result<AuthenticatedMessage> {
+        self.create_authenticated_message(
+            &distribution.topic,
+            PubSubMessageType::Custom(GROUP_KEY_MESSAGE_TYPE.to_string()),
+            &distribution.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 向群组发送消息：用当前代群组密钥加密后发布到群组主题
+    pub async fn send_to_group(&self, group_id: &str, content: &[u8]) -> Result<AuthenticatedMessage> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        let (topic, sealed) = {
+            let groups = self.groups.read().await;
+            let keyring = groups.get(group_id)
+                .ok_or_else(|| anyhow::anyhow!("未加入群组: {}", group_id))?;
+            (keyring.topic().to_string(), keyring.encrypt(&signer.did(), content)?)
+        };
+        
+        self.create_authenticated_message(
+            &topic,
+            PubSubMessageType::Custom(GROUP_MESSAGE_TYPE.to_string()),
+            &sealed.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 处理群组主题上已通过验证的消息：密钥下发更新本地密钥环并返回None，群组消息解密后返回内容
+    /// 第一次收到包含本地身份的密钥下发时加入群组，之后只接受同一管理员的下发
+    pub async fn on_group_message(&self, message: &AuthenticatedMessage) -> Result<Option<Vec<u8>>> {
+        match &message.message_type {
+            PubSubMessageType::Custom(kind) if kind == GROUP_KEY_MESSAGE_TYPE => {
+                let distribution = GroupKeyDistribution::from_message(message)?;
+                let signer = self.signer.read().await.clone()
+                    .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+                if signer.did() == distribution.admin_did {
+                    return Ok(None);
+                }
+                let keypair = signer.keypair()
+                    .ok_or_else(|| anyhow::anyhow!("外部签名器无法解封群组密钥：需要本地私钥"))?;
+                
+                let mut groups = self.groups.write().await;
+                match groups.get_mut(&distribution.group_id) {
+                    Some(keyring) => {
+                        if let Err(e) = keyring.apply(keypair, &distribution) {
+                            if keyring.epoch().is_none() {
+                                groups.remove(&distribution.group_id);
+                                log::warn!("👋 已被移出群组: {}", distribution.group_id);
+                            }
+                            return Err(e);
+                        }
+                    }
+                    None => {
+                        let keyring = GroupKeyring::join(keypair, &distribution)?;
+                        log::info!("👥 加入群组 {}（管理员 {}）", distribution.group_id, distribution.admin_did);
+                        groups.insert(distribution.group_id.clone(), keyring);
+                    }
+                }
+                Ok(None)
+            }
+            PubSubMessageType::Custom(kind) if kind == GROUP_MESSAGE_TYPE => {
+                let sealed = GroupCiphertext::from_message(message)?;
+                let groups = self.groups.read().await;
+                let keyring = groups.get(&sealed.group_id)
+                    .ok_or_else(|| anyhow::anyhow!("未加入群组: {}", sealed.group_id))?;
+                if keyring.topic() != message.topic {
+                    anyhow::bail!("群组消息的主题不符: {}", message.topic);
+                }
+                keyring.decrypt(&message.from_did, &sealed).map(Some)
+            }
+            _ => anyhow::bail!("不是群组消息: {}", message.message_id),
+        }
+    }
+    
+    /// 已加入群组的密钥环
+    pub async fn group(&self, group_id: &str) -> Option<GroupKeyring> {
+        self.groups.read().await.get(group_id).cloned()
+    }
+    
+    /// 发布本地DID与libp2p身份的联合签名绑定（libp2p身份须与本地PeerID一致）
+    pub async fn create_peer_binding_message(&self, topic: &str, identity: &LibP2PIdentity) -> Result<AuthenticatedMessage> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        if self.peer_id.read().await.as_ref() != Some(identity.peer_id()) {
+            anyhow::bail!("libp2p身份与本地PeerID不一致: {}", identity.peer_id());
+        }
+        
+        let binding = PeerIdBinding::create(signer.as_ref(), identity, self.clock.now_secs())?;
+        self.peer_bindings.record(binding.clone())?;
+        self.create_authenticated_message(
+            topic,
+            PubSubMessageType::Custom(PEER_BINDING_MESSAGE_TYPE.to_string()),
+            &binding.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 处理对方发布的PeerID绑定（声明由DID和libp2p密钥联合签名，无需先验证消息），返回是否替换了旧绑定
+    pub fn handle_peer_binding(&self, message: &AuthenticatedMessage) -> Result<bool> {
+        self.peer_bindings.record(PeerIdBinding::from_message(message)?)
+    }
+    
+    /// 广播本地身份的CID预提交声明（文档上传之前发送）
+    pub async fn create_commitment_announcement(&self, topic: &str, commitment: &CidCommitment) -> Result<AuthenticatedMessage> {
+        if self.local_did().await.as_deref() != Some(commitment.did.as_str()) {
+            anyhow::bail!("CID预提交不属于本地身份: {}", commitment.did);
+        }
+        commitment.require_valid()?;
+        self.create_authenticated_message(
+            topic,
+            PubSubMessageType::Custom(CID_COMMITMENT_MESSAGE_TYPE.to_string()),
+            &commitment.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 处理对方广播的CID预提交（声明由DID持有者签名，无需先验证消息），返回是否为新的声明
+    pub fn handle_commitment_announcement(&self, message: &AuthenticatedMessage) -> Result<bool> {
+        let commitment = CidCommitment::from_message(message)?;
+        commitment.require_valid()?;
+        
+        let mut commitments = self.cid_commitments.lock().unwrap();
+        if commitments.get(&commitment.cid).is_some_and(|known| known.did != commitment.did) {
+            anyhow::bail!("CID已被其他DID预提交: {}", commitment.cid);
+        }
+        if !commitments.contains_key(&commitment.cid) && commitments.len() >= MAX_PENDING_COMMITMENTS {
+            let oldest = commitments.values()
+                .min_by_key(|known| known.committed_at)
+                .map(|known| known.cid.clone());
+            if let Some(oldest) = oldest {
+                commitments.remove(&oldest);
+            }
+        }
+        log::debug!("📝 收到CID预提交: {} -> {}", commitment.did, commitment.cid);
+        Ok(commitments.insert(commitment.cid.clone(), commitment).is_none())
+    }
+    
+    /// cid是否由did预提交且尚未确认发布
+    fn has_commitment(&self, cid: &str, did: &str) -> bool {
+        self.cid_commitments.lock().unwrap().get(cid).is_some_and(|commitment| commitment.did == did)
+    }
+    
+    /// 为收到的点对点消息签发送达回执（消息应已通过verify_message验证），返回发给发送者的回执消息
+    pub async fn create_delivery_receipt(&self, message: &AuthenticatedMessage) -> Result<AuthenticatedMessage> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        if message.to_did.as_deref() != Some(signer.did().as_str()) {
+            anyhow::bail!("消息的收件人不是本地身份: {}", message.message_id);
+        }
+        
+        let receipt = DeliveryReceipt::new(signer.as_ref(), message, self.clock.now_secs())?;
+        self.create_authenticated_message(
+            &message.topic,
+            PubSubMessageType::Custom(DELIVERY_RECEIPT_MESSAGE_TYPE.to_string()),
+            &receipt.to_bytes()?,
+            Some(message.from_did.clone()),
+        ).await
+    }
+    
+    /// 处理收到的送达回执（回执由收件人签名，无需先验证消息），返回是否确认了一条排队消息
+    pub fn handle_delivery_receipt(&self, message: &AuthenticatedMessage) -> Result<bool> {
+        self.offline_queue.acknowledge(&DeliveryReceipt::from_message(message)?)
+    }
+    
+    /// 发布运维开关命令（由ToggleCommand::sign签发）
+    pub async fn create_toggle_message(&self, topic: &str, command: &ToggleCommand) -> Result<AuthenticatedMessage> {
+        self.create_authenticated_message(
+            topic,
+            PubSubMessageType::Custom(FEATURE_TOGGLE_MESSAGE_TYPE.to_string()),
+            &command.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 处理收到的开关命令（消息应已通过verify_message验证），按本地允许策略应用并记录审计日志
+    pub fn handle_toggle_message(&self, message: &AuthenticatedMessage) -> Result<ToggleAuditEntry> {
+        self.feature_toggles.apply(&ToggleCommand::from_message(message)?)
+    }
+    
+    /// 以本地身份发布指标报告（按隐私参数截断、加噪并对齐到时间窗口）
+    pub async fn create_stats_report(
+        &self,
+        topic: &str,
+        metric: &str,
+        value: f64,
+        window_secs: u64,
+        config: &PrivacyConfig,
+    ) -> Result<AuthenticatedMessage> {
+        let did = self.local_did().await
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        let report = StatsReport::new(&did, metric, value, self.clock.now_secs(), window_secs, config);
+        self.create_authenticated_message(
+            topic,
+            PubSubMessageType::Custom(STATS_REPORT_MESSAGE_TYPE.to_string()),
+            &report.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 重新发布DID文档后，切换本地CID并创建DID更新通知（发布到 did_update_topic）
+    pub async fn create_did_update(&self, new_cid: &str) -> Result<AuthenticatedMessage> {
+        let signer = self.signer.read().await.clone()
+            .ok_or_else(|| anyhow::anyhow!("未设置本地身份"))?;
+        let previous_cid = self.local_cid.write().await.replace(new_cid.to_string());
+        
+        let event = DidUpdatedEvent::sign(signer.as_ref(), previous_cid.as_deref(), new_cid, self.clock.now_secs())?;
+        self.create_authenticated_message(
+            &crate::constants::network_params().did_update_topic(),
+            PubSubMessageType::Custom(DID_UPDATED_MESSAGE_TYPE.to_string()),
+            &event.to_bytes()?,
+            None,
+        ).await
+    }
+    
+    /// 处理收到的DID更新通知（消息应已通过verify_message验证）
+    /// 从DID缓存中移除旧CID，并转发给 subscribe_did_updates 的订阅者
+    pub fn handle_did_update(&self, message: &AuthenticatedMessage) -> Result<DidUpdatedEvent> {
+        let event = DidUpdatedEvent::from_message(message)?;
+        self.did_cache.apply_update(&event)?;
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.did_updates.send(event.clone());
+        Ok(event)
+    }
+    
+    /// 订阅DID更新通知主题，返回已应用事件的接收端（例如交给AgentVerificationManager::apply_did_update）
+    pub async fn subscribe_did_updates(&self) -> Result<tokio::sync::broadcast::Receiver<DidUpdatedEvent>> {
+        self.subscribe_topic(&crate::constants::network_params().did_update_topic()).await?;
+        Ok(self.did_updates.subscribe())
+    }
+    
+    /// 处理统计主题上收到的指标报告（消息应已通过verify_message验证）
+    pub fn handle_stats_report(&self, message: &AuthenticatedMessage, aggregator: &StatsAggregator) -> Result<StatsReport> {
+        let report = StatsReport::from_message(message)?;
+        aggregator.observe(&report)?;
+        Ok(report)
+    }
+    
     /// 创建简化的认证消息（用于演示）
     pub async fn create_simple_message(
         &self,
@@ -502,6 +2454,21 @@ impl PubsubAuthenticator {
         ).await
     }
     
+    /// 创建发往指定DID收件箱的直接消息
+    pub async fn create_direct_message(
+        &self,
+        to_did: &str,
+        message_type: PubSubMessageType,
+        content: &[u8],
+    ) -> Result<AuthenticatedMessage> {
+        self.create_authenticated_message(
+            &topic_pattern::inbox_topic(to_did),
+            message_type,
+            content,
+            Some(to_did.to_string()),
+        ).await
+    }
+    
     /// 创建身份验证请求消息
     pub async fn create_auth_request(
         &self,
@@ -536,10 +2503,7 @@ impl PubsubAuthenticator {
     
     /// 创建心跳消息
     pub async fn create_heartbeat(&self, topic: &str) -> Result<AuthenticatedMessage> {
-        let content = format!("HEARTBEAT:{}", std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs());
+        let content = format!("HEARTBEAT:{}", self.clock.now_secs());
         
         self.create_authenticated_message(
             topic,
@@ -553,6 +2517,38 @@ impl PubsubAuthenticator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::did_resolver::DIDResolver;
+    use crate::ipfs_client::IpfsClient;
+    use crate::circuit_breaker::CircuitState;
+    use crate::key_manager::CallbackSigner;
+    
+    /// 接收方：缓存中已有各发送者的DID文档，tasks主题不要求ZKP
+    async fn receiver(documents: &[(&str, &KeyPair)], rate_limit: Option<RateLimit>) -> PubsubAuthenticator {
+        let cache = DIDCache::new(None, None);
+        for (cid, keypair) in documents {
+            cache.put(cid.to_string(), DIDResolver::resolve_did_key(&keypair.did).unwrap()).unwrap();
+        }
+        let auth = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(5)), None, Some(cache));
+        auth.configure_topic(TopicConfig {
+            name: "tasks".to_string(),
+            policy: TopicPolicy::AllowAuthenticated,
+            require_zkp: false,
+            require_signature: true,
+            retention: None,
+            rate_limit,
+            encryption: TopicEncryption::Optional,
+        }).await.unwrap();
+        auth
+    }
+    
+    /// 发送方：回调签名器不生成ZKP证明，创建消息不需要访问IPFS
+    async fn sender(keypair: &KeyPair, cid: &str) -> PubsubAuthenticator {
+        let inner = keypair.clone();
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data: &[u8]| inner.sign(data))).unwrap();
+        let auth = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(5)), None, None);
+        auth.set_local_signer(Arc::new(signer), PeerId::random(), cid.to_string()).await.unwrap();
+        auth
+    }
     
     #[tokio::test]
     #[ignore] // 需要实际的IPFS和ZKP设置
@@ -560,5 +2556,78 @@ mod tests {
         // 这个测试需要完整的环境设置
         // 包括IPFS客户端、ZKP keys等
     }
+    
+    #[tokio::test]
+    async fn test_spoofed_from_did_rejected() {
+        let alice = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+        let limit = RateLimit { per_second: 0.001, burst: 1 };
+        let auth = receiver(&[("cid-alice", &alice), ("cid-mallory", &mallory)], Some(limit)).await;
+        let mallory_sender = sender(&mallory, "cid-mallory").await;
+        
+        // 签名有效、CID指向mallory自己的文档，但声明为alice发送
+        for _ in 0..3 {
+            let mut spoofed = mallory_sender.create_simple_message("tasks", "spoofed").await.unwrap();
+            spoofed.from_did = alice.did.clone();
+            let verification = auth.verify_message(&spoofed).await.unwrap();
+            assert!(!verification.verified);
+            assert!(verification.details.iter().any(|d| d.contains("与发送者DID不符")));
+        }
+        
+        // alice的配额未被消耗
+        let genuine = sender(&alice, "cid-alice").await.create_simple_message("tasks", "hello").await.unwrap();
+        assert!(auth.verify_message(&genuine).await.unwrap().verified);
+    }
+    
+    #[tokio::test]
+    async fn test_spoofed_failures_trip_peer_breaker() {
+        let alice = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+        let auth = receiver(&[("cid-alice", &alice), ("cid-mallory", &mallory)], None).await;
+        let mallory_sender = sender(&mallory, "cid-mallory").await;
+        let threshold = auth.circuit_breakers().config().verification_threshold;
+        
+        let mut mallory_peer = None;
+        for _ in 0..threshold {
+            let mut spoofed = mallory_sender.create_simple_message("tasks", "spoofed").await.unwrap();
+            spoofed.from_did = alice.did.clone();
+            let peer: PeerId = spoofed.from_peer_id.parse().unwrap();
+            assert!(!auth.verify_message_from_peer(&spoofed, &peer).await.unwrap().verified);
+            mallory_peer = Some(peer);
+        }
+        
+        // 失败记在传输层对端名下，alice的熔断器和信誉不受影响
+        let mallory_peer = mallory_peer.unwrap();
+        let initial_score = auth.reputation().weights().initial_score;
+        assert_eq!(auth.circuit_breakers().state(&alice.did), CircuitState::Closed);
+        assert!(auth.reputation().record_of(&alice.did).is_none());
+        assert!(auth.trust_score(&mallory_peer.to_base58()) < initial_score);
+        assert!(matches!(auth.circuit_breakers().state(&mallory_peer.to_base58()), CircuitState::Open { .. }));
+        
+        let genuine = sender(&alice, "cid-alice").await.create_simple_message("tasks", "hello").await.unwrap();
+        assert!(auth.verify_message(&genuine).await.unwrap().verified);
+        
+        // 熔断中的对端即使换用自己的DID也直接拒绝
+        let own = mallory_sender.create_simple_message("tasks", "own").await.unwrap();
+        let verification = auth.verify_message_from_peer(&own, &mallory_peer).await.unwrap();
+        assert!(verification.details.iter().any(|d| d.contains("熔断冷却期")));
+    }
+    
+    #[tokio::test]
+    async fn test_revoked_did_rejected() {
+        let alice = KeyPair::generate().unwrap();
+        let registry = RevocationRegistry::new();
+        let auth = receiver(&[("cid-alice", &alice)], None).await.with_revocation_registry(registry.clone());
+        let alice_sender = sender(&alice, "cid-alice").await;
+        
+        let before = alice_sender.create_simple_message("tasks", "before").await.unwrap();
+        assert!(auth.verify_message(&before).await.unwrap().verified);
+        
+        registry.revoke(&alice, "密钥泄露").unwrap();
+        let after = alice_sender.create_simple_message("tasks", "after").await.unwrap();
+        let verification = auth.verify_message(&after).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d.contains("已吊销")));
+    }
 }
 
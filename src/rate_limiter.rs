@@ -0,0 +1,310 @@
+// DIAP Rust SDK - 速率限制与滥用事件
+// 已认证但行为异常的智能体仍可能刷屏；这里按发送者DID、主题以及“发送者×主题”维护令牌桶，
+// 在验证消息时执行配额，超限时拒绝消息并通过AbuseEvent回调通知中继等上层组件
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::pubsub_authenticator::RateLimit;
+
+/// 令牌桶数量超过该值时清理已回满（等同于未使用）的桶
+const MAX_IDLE_BUCKETS: usize = 10_000;
+
+/// 速率限制的作用范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitScope {
+    /// 单个发送者DID的全部消息
+    Sender,
+    /// 单个主题上所有发送者的消息
+    Topic,
+    /// 单个发送者在单个主题上的消息（TopicConfig.rate_limit）
+    SenderOnTopic,
+}
+
+/// 全局速率限制配置（主题级的“发送者×主题”限制在TopicConfig中配置）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// 每个发送者DID的限制
+    #[serde(default)]
+    pub per_sender: Option<RateLimit>,
+
+    /// 每个主题的总限制
+    #[serde(default)]
+    pub per_topic: Option<RateLimit>,
+}
+
+/// 滥用类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AbuseKind {
+    /// 超出速率限制
+    RateLimited { scope: RateLimitScope, limit: RateLimit },
+}
+
+/// 滥用事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbuseEvent {
+    /// 发送者DID
+    pub did: String,
+
+    /// 主题
+    pub topic: String,
+
+    /// 被拒绝的消息ID
+    pub message_id: String,
+
+    /// 滥用类型
+    #[serde(flatten)]
+    pub kind: AbuseKind,
+
+    /// 发送者是否已通过验证（验证前的快速拒绝为false，此时DID可能是冒用的）
+    pub authenticated: bool,
+
+    /// 发生时间
+    pub at: u64,
+}
+
+/// 滥用事件回调
+pub type AbuseCallback = Arc<dyn Fn(&AbuseEvent) + Send + Sync>;
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    updated_ms: u64,
+    /// 最近一次使用的限制（用于清理时判断是否已回满）
+    limit: RateLimit,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now_ms: u64) {
+        let elapsed = now_ms.saturating_sub(self.updated_ms) as f64 / 1000.0;
+        self.tokens = (self.tokens + elapsed * self.limit.per_second.max(0.0)).min(capacity(&self.limit));
+        self.updated_ms = now_ms;
+    }
+}
+
+fn capacity(limit: &RateLimit) -> f64 {
+    f64::from(limit.burst.max(1))
+}
+
+/// 令牌桶速率限制器（可跨线程共享）
+pub struct RateLimiter {
+    config: RwLock<RateLimitConfig>,
+    buckets: Mutex<HashMap<(RateLimitScope, String), TokenBucket>>,
+    callbacks: RwLock<Vec<AbuseCallback>>,
+}
+
+impl RateLimiter {
+    /// 创建限制器
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            buckets: Mutex::new(HashMap::new()),
+            callbacks: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// 当前全局配置
+    pub fn config(&self) -> RateLimitConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// 更新全局配置（已有令牌桶保留，按新限制继续计算）
+    pub fn set_config(&self, config: RateLimitConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// 注册滥用事件回调
+    pub fn on_abuse(&self, callback: AbuseCallback) {
+        self.callbacks.write().unwrap().push(callback);
+    }
+
+    /// 检查是否还有配额但不消耗（用于验证前的快速拒绝），返回第一个已耗尽的范围
+    pub fn peek(&self, did: &str, topic: &str, topic_limit: Option<&RateLimit>, now_ms: u64) -> Result<(), (RateLimitScope, RateLimit)> {
+        self.evaluate(did, topic, topic_limit, now_ms, false)
+    }
+
+    /// 消耗一条消息的配额；任一范围耗尽时都不消耗，并返回该范围
+    pub fn acquire(&self, did: &str, topic: &str, topic_limit: Option<&RateLimit>, now_ms: u64) -> Result<(), (RateLimitScope, RateLimit)> {
+        self.evaluate(did, topic, topic_limit, now_ms, true)
+    }
+
+    /// 通知所有回调
+    pub fn report(&self, event: &AbuseEvent) {
+        log::warn!("🚫 超出速率限制: {} 在 {} ({:?})", event.did, event.topic, event.kind);
+        let callbacks = self.callbacks.read().unwrap().clone();
+        for callback in callbacks {
+            callback(event);
+        }
+    }
+
+    /// 清除某个DID的发送者级令牌桶（例如运维确认后放行）
+    pub fn reset(&self, did: &str) {
+        self.buckets.lock().unwrap().retain(|(scope, key), _| match scope {
+            RateLimitScope::Sender => key != did,
+            RateLimitScope::SenderOnTopic => !key.starts_with(&format!("{}\n", did)),
+            RateLimitScope::Topic => true,
+        });
+    }
+
+    /// 当前令牌桶数量
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.lock().unwrap().len()
+    }
+
+    fn evaluate(
+        &self,
+        did: &str,
+        topic: &str,
+        topic_limit: Option<&RateLimit>,
+        now_ms: u64,
+        consume: bool,
+    ) -> Result<(), (RateLimitScope, RateLimit)> {
+        let config = self.config();
+        let checks: Vec<((RateLimitScope, String), RateLimit)> = [
+            config.per_sender.clone().map(|limit| ((RateLimitScope::Sender, did.to_string()), limit)),
+            config.per_topic.clone().map(|limit| ((RateLimitScope::Topic, topic.to_string()), limit)),
+            topic_limit.map(|limit| ((RateLimitScope::SenderOnTopic, format!("{}\n{}", did, topic)), limit.clone())),
+        ].into_iter().flatten().collect();
+        if checks.is_empty() {
+            return Ok(());
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        for (key, limit) in &checks {
+            let bucket = buckets.entry(key.clone()).or_insert_with(|| TokenBucket {
+                tokens: capacity(limit),
+                updated_ms: now_ms,
+                limit: limit.clone(),
+            });
+            bucket.refill(now_ms);
+            bucket.limit = limit.clone();
+            bucket.tokens = bucket.tokens.min(capacity(limit));
+            if bucket.tokens < 1.0 {
+                return Err((key.0, limit.clone()));
+            }
+        }
+        if consume {
+            for (key, _) in &checks {
+                if let Some(bucket) = buckets.get_mut(key) {
+                    bucket.tokens -= 1.0;
+                }
+            }
+        }
+
+        if buckets.len() > MAX_IDLE_BUCKETS {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now_ms);
+                bucket.tokens < capacity(&bucket.limit)
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_buckets_per_scope() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            per_sender: Some(RateLimit { per_second: 1.0, burst: 3 }),
+            per_topic: None,
+        });
+        let topic_limit = RateLimit { per_second: 1.0, burst: 2 };
+
+        // 主题级限制更严
+        assert!(limiter.acquire("did:key:a", "t1", Some(&topic_limit), 0).is_ok());
+        assert!(limiter.acquire("did:key:a", "t1", Some(&topic_limit), 0).is_ok());
+        assert_eq!(limiter.acquire("did:key:a", "t1", Some(&topic_limit), 0), Err((RateLimitScope::SenderOnTopic, topic_limit.clone())));
+
+        // 其他主题仍受发送者级限制
+        assert!(limiter.acquire("did:key:a", "t2", None, 0).is_ok());
+        assert_eq!(limiter.peek("did:key:a", "t2", None, 0).unwrap_err().0, RateLimitScope::Sender);
+        assert!(limiter.acquire("did:key:b", "t2", None, 0).is_ok(), "其他发送者不受影响");
+
+        // 令牌按速率恢复
+        assert!(limiter.acquire("did:key:a", "t2", None, 1_000).is_ok());
+        assert!(limiter.acquire("did:key:a", "t2", None, 1_000).is_err());
+
+        limiter.reset("did:key:a");
+        assert!(limiter.acquire("did:key:a", "t1", Some(&topic_limit), 1_000).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_enforces_topic_quota() {
+        use crate::did_resolver::DIDResolver;
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::{CallbackSigner, KeyPair};
+        use crate::pubsub_authenticator::{PubSubMessageType, PubsubAuthenticator, TopicConfig, TopicEncryption, TopicPolicy};
+        use crate::verification_hint::{HintSource, VerificationHint, DEFAULT_HINT_TTL};
+
+        struct StaticHint(VerificationHint);
+
+        #[async_trait::async_trait]
+        impl HintSource for StaticHint {
+            async fn lookup(&self, _did: &str) -> anyhow::Result<Option<VerificationHint>> {
+                Ok(Some(self.0.clone()))
+            }
+        }
+
+        // 验证提示让消息无需访问网关即可通过验证
+        let keypair = KeyPair::generate().unwrap();
+        let document = DIDResolver::resolve_did_key(&keypair.did).unwrap();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let hint = VerificationHint::sign(&keypair, &document, "QmCurrent", now, DEFAULT_HINT_TTL).unwrap();
+        let client = IpfsClient::new_public_only(1);
+        for gateway in client.public_gateways() {
+            client.remove_gateway(&gateway);
+        }
+        let receiver = PubsubAuthenticator::new(IdentityManager::new(client), None, None)
+            .with_verification_hints(Arc::new(StaticHint(hint)));
+        receiver.configure_topic(TopicConfig {
+            name: "chat".to_string(),
+            policy: TopicPolicy::AllowAuthenticated,
+            require_zkp: false,
+            require_signature: true,
+            retention: None,
+            rate_limit: Some(RateLimit { per_second: 0.001, burst: 1 }),
+            encryption: TopicEncryption::Optional,
+        }).await.unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        receiver.on_abuse(Arc::new(move |event: &AbuseEvent| sink.lock().unwrap().push(event.clone())));
+
+        let did = keypair.did.clone();
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+        let sender = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None);
+        sender.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmCurrent".to_string()).await.unwrap();
+
+        let first = sender.create_authenticated_message("chat", PubSubMessageType::Heartbeat, b"1", None).await.unwrap();
+        assert!(receiver.verify_message(&first).await.unwrap().verified);
+
+        // 配额耗尽后直接拒绝，不再验证
+        let second = sender.create_authenticated_message("chat", PubSubMessageType::Heartbeat, b"2", None).await.unwrap();
+        let verification = receiver.verify_message(&second).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details[0].contains("速率限制"));
+
+        // 其他主题不受影响
+        let other = sender.create_authenticated_message("general", PubSubMessageType::Heartbeat, b"3", None).await.unwrap();
+        assert!(receiver.verify_message(&other).await.unwrap().verified);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].did.as_str(), events[0].topic.as_str()), (did.as_str(), "chat"));
+        assert_eq!(events[0].message_id, second.message_id);
+        assert!(matches!(events[0].kind, AbuseKind::RateLimited { scope: RateLimitScope::SenderOnTopic, .. }));
+        assert!(!events[0].authenticated);
+    }
+}
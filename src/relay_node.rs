@@ -0,0 +1,986 @@
+// DIAP Rust SDK - 中继/基础设施节点
+// 只运行网络骨干角色（DHT引导节点、邮箱存储、注册表镜像），使用独立的DID、配额和指标，
+// 运营方可以直接通过 `diap relay` 部署，无需编写代码
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use futures::StreamExt;
+use libp2p::{
+    identify,
+    multiaddr::Protocol,
+    noise, relay,
+    request_response::{self, ProtocolSupport},
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour, SwarmEvent},
+    tcp, yamux, Multiaddr, PeerId, StreamProtocol, Swarm,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::agent_discovery::{agent_record_key, AgentDiscovery, AgentRecord, DhtBackend, KademliaDht};
+use crate::clock::{SharedClock, system_clock};
+use crate::constants::network_params;
+use crate::key_manager::{KeyManager, KeyPair, SignedEnvelope, Signer};
+use crate::libp2p_identity::LibP2PIdentity;
+use crate::nonce_manager::NonceManager;
+use crate::p2p_codec::DIAPCodec;
+use crate::pubsub_authenticator::AuthenticatedMessage;
+use crate::timestamp_window::TimestampWindow;
+
+/// 默认DHT监听地址
+pub const DEFAULT_RELAY_DHT_ADDR: &str = "/ip4/0.0.0.0/tcp/4021";
+
+/// 默认中继服务监听地址
+pub const DEFAULT_RELAY_SERVICE_ADDR: &str = "/ip4/0.0.0.0/tcp/4022";
+
+/// 中继DID密钥文件名（位于数据目录）
+pub const RELAY_KEY_FILE: &str = "relay_key.json";
+
+/// 取件请求允许的时间偏差（秒），取件nonce在此期间内不能重用
+pub const FETCH_MAX_SKEW_SECS: u64 = 300;
+
+/// 中继角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RelayRole {
+    /// libp2p电路中继
+    CircuitRelay,
+
+    /// 离线智能体的邮箱存储
+    Mailbox,
+
+    /// DHT引导节点
+    Bootstrap,
+
+    /// 智能体注册表镜像
+    RegistryMirror,
+}
+
+impl FromStr for RelayRole {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "circuit-relay" => Ok(RelayRole::CircuitRelay),
+            "mailbox" => Ok(RelayRole::Mailbox),
+            "bootstrap" => Ok(RelayRole::Bootstrap),
+            "registry-mirror" => Ok(RelayRole::RegistryMirror),
+            other => anyhow::bail!("未知的中继角色: {}（可选: circuit-relay, mailbox, bootstrap, registry-mirror）", other),
+        }
+    }
+}
+
+impl std::fmt::Display for RelayRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RelayRole::CircuitRelay => "circuit-relay",
+            RelayRole::Mailbox => "mailbox",
+            RelayRole::Bootstrap => "bootstrap",
+            RelayRole::RegistryMirror => "registry-mirror",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 中继配额
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayQuotas {
+    /// 邮箱最多保存的消息总数
+    pub max_stored_messages: usize,
+
+    /// 每个收件DID最多保存的消息数
+    pub max_messages_per_did: usize,
+
+    /// 每个发件DID最多存放的消息数（防止单个发送方占满存储）
+    pub max_messages_per_sender: usize,
+
+    /// 单条消息最大内容字节数
+    pub max_message_bytes: usize,
+
+    /// 消息保存时长（秒）
+    pub message_ttl_secs: u64,
+
+    /// 注册表镜像最多保存的记录数
+    pub max_mirrored_records: usize,
+}
+
+impl Default for RelayQuotas {
+    fn default() -> Self {
+        Self {
+            max_stored_messages: 10_000,
+            max_messages_per_did: 100,
+            max_messages_per_sender: 100,
+            max_message_bytes: 64 * 1024,
+            message_ttl_secs: 7 * 24 * 3600,
+            max_mirrored_records: 10_000,
+        }
+    }
+}
+
+/// 中继节点配置（TOML）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayConfig {
+    /// 数据目录（保存中继DID密钥）
+    pub data_dir: PathBuf,
+
+    /// 启用的角色
+    pub roles: Vec<RelayRole>,
+
+    /// DHT监听地址
+    pub dht_listen_addr: String,
+
+    /// 中继服务（邮箱、镜像查询、电路中继）监听地址
+    pub service_listen_addr: String,
+
+    /// 其他引导节点（带/p2p/后缀的多地址）
+    pub bootstrap_peers: Vec<String>,
+
+    /// 注册表镜像的能力标签
+    pub mirror_capabilities: Vec<String>,
+
+    /// 注册表镜像刷新间隔（秒）
+    pub mirror_interval_secs: u64,
+
+    /// 指标日志与过期清理间隔（秒）
+    pub metrics_interval_secs: u64,
+
+    /// 配额
+    pub quotas: RelayQuotas,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("diap-relay"),
+            roles: vec![RelayRole::Bootstrap, RelayRole::Mailbox, RelayRole::RegistryMirror],
+            dht_listen_addr: DEFAULT_RELAY_DHT_ADDR.to_string(),
+            service_listen_addr: DEFAULT_RELAY_SERVICE_ADDR.to_string(),
+            bootstrap_peers: Vec::new(),
+            mirror_capabilities: Vec::new(),
+            mirror_interval_secs: 300,
+            metrics_interval_secs: 60,
+            quotas: RelayQuotas::default(),
+        }
+    }
+}
+
+impl RelayConfig {
+    /// 从TOML文件加载
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref())
+            .with_context(|| format!("无法读取中继配置: {:?}", path.as_ref()))?;
+        toml::from_str(&content).with_context(|| format!("无法解析中继配置: {:?}", path.as_ref()))
+    }
+
+    /// 是否启用角色
+    pub fn has_role(&self, role: RelayRole) -> bool {
+        self.roles.contains(&role)
+    }
+
+    /// 校验配置
+    pub fn validate(&self) -> Result<()> {
+        if self.roles.is_empty() {
+            anyhow::bail!("至少需要启用一个中继角色");
+        }
+        Multiaddr::from_str(&self.dht_listen_addr)
+            .with_context(|| format!("无效的DHT监听地址: {}", self.dht_listen_addr))?;
+        Multiaddr::from_str(&self.service_listen_addr)
+            .with_context(|| format!("无效的服务监听地址: {}", self.service_listen_addr))?;
+        for peer in &self.bootstrap_peers {
+            parse_peer_addr(peer)?;
+        }
+        if self.has_role(RelayRole::RegistryMirror) && self.mirror_capabilities.is_empty() {
+            log::warn!("⚠️ 注册表镜像未配置能力标签，不会镜像任何记录");
+        }
+        Ok(())
+    }
+}
+
+/// 中继服务请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RelayRequest {
+    /// 为离线收件人存放消息
+    Deposit(Box<AuthenticatedMessage>),
+
+    /// 收件人取回消息（签名证明控制该DID，nonce带时间戳且只能使用一次）
+    Fetch {
+        did: String,
+        nonce: String,
+        signature: String,
+    },
+
+    /// 查询镜像的智能体记录
+    Lookup {
+        capability: String,
+    },
+}
+
+impl RelayRequest {
+    /// 创建签名的取件请求（timestamp为当前Unix秒）
+    pub fn fetch(signer: &dyn Signer, timestamp: u64) -> Result<Self> {
+        let did = signer.did();
+        let nonce = NonceManager::generate_nonce_at(timestamp);
+        let signature = signer.sign(&fetch_signing_data(&did, &nonce))?;
+        Ok(RelayRequest::Fetch {
+            did,
+            nonce,
+            signature: general_purpose::STANDARD.encode(signature),
+        })
+    }
+
+    /// 序列化
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化中继请求失败")
+    }
+
+    /// 反序列化
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).context("解析中继请求失败")
+    }
+}
+
+/// 中继服务响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RelayResponse {
+    /// 已存放，附收件人当前待取消息数
+    Stored { pending: usize },
+
+    /// 取回的消息
+    Messages(Vec<AuthenticatedMessage>),
+
+    /// 镜像的智能体记录
+    Records(Vec<AgentRecord>),
+
+    /// 拒绝（配额、认证失败或角色未启用）
+    Rejected(String),
+}
+
+impl RelayResponse {
+    /// 序列化
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化中继响应失败")
+    }
+
+    /// 反序列化
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).context("解析中继响应失败")
+    }
+}
+
+/// 邮箱配额错误
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RelayQuotaError {
+    #[error("消息缺少收件人DID")]
+    MissingRecipient,
+
+    #[error("消息签名无效: {did}")]
+    InvalidSignature { did: String },
+
+    #[error("消息过大: {size} 字节（上限 {max}）")]
+    MessageTooLarge { size: usize, max: usize },
+
+    #[error("收件人邮箱已满: {did}（上限 {max} 条）")]
+    MailboxFull { did: String, max: usize },
+
+    #[error("发件人存放的消息过多: {did}（上限 {max} 条）")]
+    SenderQuotaExceeded { did: String, max: usize },
+
+    #[error("中继存储已满（上限 {max} 条）")]
+    StorageFull { max: usize },
+}
+
+/// 中继指标快照
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayMetricsSnapshot {
+    /// 存放的消息数
+    pub messages_stored: u64,
+
+    /// 投递（被取回）的消息数
+    pub messages_delivered: u64,
+
+    /// 过期清理的消息数
+    pub messages_expired: u64,
+
+    /// 因配额拒绝的请求数
+    pub quota_rejections: u64,
+
+    /// 认证失败的取件请求和签名无效的存放请求数
+    pub auth_failures: u64,
+
+    /// 镜像查询次数
+    pub lookups: u64,
+
+    /// 当前镜像的记录数
+    pub mirrored_records: u64,
+}
+
+/// 中继指标
+#[derive(Debug, Default)]
+pub struct RelayMetrics {
+    messages_stored: AtomicU64,
+    messages_delivered: AtomicU64,
+    messages_expired: AtomicU64,
+    quota_rejections: AtomicU64,
+    auth_failures: AtomicU64,
+    lookups: AtomicU64,
+    mirrored_records: AtomicU64,
+}
+
+impl RelayMetrics {
+    /// 当前指标
+    pub fn snapshot(&self) -> RelayMetricsSnapshot {
+        RelayMetricsSnapshot {
+            messages_stored: self.messages_stored.load(Ordering::Relaxed),
+            messages_delivered: self.messages_delivered.load(Ordering::Relaxed),
+            messages_expired: self.messages_expired.load(Ordering::Relaxed),
+            quota_rejections: self.quota_rejections.load(Ordering::Relaxed),
+            auth_failures: self.auth_failures.load(Ordering::Relaxed),
+            lookups: self.lookups.load(Ordering::Relaxed),
+            mirrored_records: self.mirrored_records.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 中继服务（请求处理，不涉及网络）
+pub struct RelayService {
+    roles: Vec<RelayRole>,
+    quotas: RelayQuotas,
+    clock: SharedClock,
+
+    /// 收件DID -> (存放时间, 消息)
+    mailboxes: Mutex<HashMap<String, VecDeque<(u64, AuthenticatedMessage)>>>,
+
+    /// PeerID -> 镜像的智能体记录
+    mirror: Mutex<HashMap<String, AgentRecord>>,
+
+    /// 已使用的取件nonce（防止窃听者重放取件请求清空邮箱）
+    fetch_nonces: NonceManager,
+
+    metrics: RelayMetrics,
+}
+
+impl RelayService {
+    /// 创建中继服务
+    pub fn new(roles: Vec<RelayRole>, quotas: RelayQuotas) -> Self {
+        Self::new_with_clock(roles, quotas, system_clock())
+    }
+
+    /// 使用指定时间源创建（需在tokio运行时中调用）
+    pub fn new_with_clock(roles: Vec<RelayRole>, quotas: RelayQuotas, clock: SharedClock) -> Self {
+        let skew = Duration::from_secs(FETCH_MAX_SKEW_SECS);
+        let fetch_nonces = NonceManager::new_with_clock(None, None, clock.clone())
+            .with_timestamp_window(&TimestampWindow::new(skew, skew));
+        Self {
+            roles,
+            quotas,
+            clock,
+            mailboxes: Mutex::new(HashMap::new()),
+            mirror: Mutex::new(HashMap::new()),
+            fetch_nonces,
+            metrics: RelayMetrics::default(),
+        }
+    }
+
+    /// 指标
+    pub fn metrics(&self) -> RelayMetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// 处理一个请求
+    pub fn handle(&self, request: RelayRequest) -> RelayResponse {
+        match request {
+            RelayRequest::Deposit(_) | RelayRequest::Fetch { .. } if !self.roles.contains(&RelayRole::Mailbox) => {
+                RelayResponse::Rejected("本中继未启用邮箱角色".to_string())
+            }
+            RelayRequest::Lookup { .. } if !self.roles.contains(&RelayRole::RegistryMirror) => {
+                RelayResponse::Rejected("本中继未启用注册表镜像角色".to_string())
+            }
+            RelayRequest::Deposit(message) => match self.deposit(*message) {
+                Ok(pending) => RelayResponse::Stored { pending },
+                Err(e) => RelayResponse::Rejected(e.to_string()),
+            },
+            RelayRequest::Fetch { did, nonce, signature } => match self.check_fetch(&did, &nonce, &signature) {
+                Ok(()) => RelayResponse::Messages(self.take(&did)),
+                Err(e) => {
+                    self.metrics.auth_failures.fetch_add(1, Ordering::Relaxed);
+                    RelayResponse::Rejected(e.to_string())
+                }
+            },
+            RelayRequest::Lookup { capability } => RelayResponse::Records(self.lookup(&capability)),
+        }
+    }
+
+    /// 存放消息，返回收件人当前待取消息数（只接受did:key发送方签名有效的消息）
+    pub fn deposit(&self, message: AuthenticatedMessage) -> Result<usize, RelayQuotaError> {
+        let result = self.try_deposit(message);
+        match &result {
+            Ok(_) => self.metrics.messages_stored.fetch_add(1, Ordering::Relaxed),
+            Err(RelayQuotaError::InvalidSignature { .. }) => self.metrics.auth_failures.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.metrics.quota_rejections.fetch_add(1, Ordering::Relaxed),
+        };
+        result
+    }
+
+    fn try_deposit(&self, message: AuthenticatedMessage) -> Result<usize, RelayQuotaError> {
+        let did = message.to_did.clone().ok_or(RelayQuotaError::MissingRecipient)?;
+        if message.content.len() > self.quotas.max_message_bytes {
+            return Err(RelayQuotaError::MessageTooLarge {
+                size: message.content.len(),
+                max: self.quotas.max_message_bytes,
+            });
+        }
+        // 先验签再占用配额，伪造发件人的消息不能挤占他人的配额
+        if !KeyPair::verify_with_did_key(&message.from_did, &message.signing_data(), &message.signature).unwrap_or(false) {
+            return Err(RelayQuotaError::InvalidSignature { did: message.from_did });
+        }
+
+        let mut mailboxes = self.mailboxes.lock().unwrap();
+        let total: usize = mailboxes.values().map(VecDeque::len).sum();
+        if total >= self.quotas.max_stored_messages {
+            return Err(RelayQuotaError::StorageFull { max: self.quotas.max_stored_messages });
+        }
+        let from_sender = mailboxes.values()
+            .flatten()
+            .filter(|(_, stored)| stored.from_did == message.from_did)
+            .count();
+        if from_sender >= self.quotas.max_messages_per_sender {
+            return Err(RelayQuotaError::SenderQuotaExceeded {
+                did: message.from_did,
+                max: self.quotas.max_messages_per_sender,
+            });
+        }
+        let mailbox = mailboxes.entry(did.clone()).or_default();
+        if mailbox.len() >= self.quotas.max_messages_per_did {
+            return Err(RelayQuotaError::MailboxFull { did, max: self.quotas.max_messages_per_did });
+        }
+        mailbox.push_back((self.clock.now_secs(), message));
+        log::debug!("📥 中继存放消息: {} ({}条待取)", did, mailbox.len());
+        Ok(mailbox.len())
+    }
+
+    /// 取出收件人的全部消息
+    fn take(&self, did: &str) -> Vec<AuthenticatedMessage> {
+        let messages: Vec<AuthenticatedMessage> = self.mailboxes.lock().unwrap()
+            .remove(did)
+            .map(|mailbox| mailbox.into_iter().map(|(_, message)| message).collect())
+            .unwrap_or_default();
+        self.metrics.messages_delivered.fetch_add(messages.len() as u64, Ordering::Relaxed);
+        messages
+    }
+
+    fn check_fetch(&self, did: &str, nonce: &str, signature: &str) -> Result<()> {
+        let sig_bytes = general_purpose::STANDARD.decode(signature).context("解码取件签名失败")?;
+        if !KeyPair::verify_with_did_key(did, &fetch_signing_data(did, nonce), &sig_bytes)? {
+            anyhow::bail!("取件签名无效");
+        }
+        // 验签通过后再记录nonce（同时检查nonce中的时间戳），伪造的请求不会占用nonce
+        self.fetch_nonces.check_and_record(nonce, did)
+            .map_err(|e| anyhow::anyhow!("取件请求无效: {}", e))
+    }
+
+    /// 清理过期消息，返回清理数量
+    pub fn purge_expired(&self) -> usize {
+        let cutoff = self.clock.now_secs().saturating_sub(self.quotas.message_ttl_secs);
+        let mut mailboxes = self.mailboxes.lock().unwrap();
+        let mut removed = 0;
+        for mailbox in mailboxes.values_mut() {
+            let before = mailbox.len();
+            mailbox.retain(|(stored_at, _)| *stored_at > cutoff);
+            removed += before - mailbox.len();
+        }
+        mailboxes.retain(|_, mailbox| !mailbox.is_empty());
+        self.metrics.messages_expired.fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+
+    /// 更新镜像（只保留签名有效、未过期的记录，超出配额时丢弃最旧的）
+    pub fn mirror_records(&self, records: Vec<AgentRecord>) -> usize {
+        let now = self.clock.now_secs();
+        let mut mirror = self.mirror.lock().unwrap();
+        let mut added = 0;
+        for record in records {
+            if record.is_expired(now) || !record.verify().unwrap_or(false) {
+                continue;
+            }
+            let newer = mirror.get(&record.peer_id).is_none_or(|existing| record.published_at > existing.published_at);
+            if newer {
+                mirror.insert(record.peer_id.clone(), record);
+                added += 1;
+            }
+        }
+        mirror.retain(|_, record| !record.is_expired(now));
+        while mirror.len() > self.quotas.max_mirrored_records {
+            let Some(oldest) = mirror.values().min_by_key(|record| record.published_at).map(|r| r.peer_id.clone()) else {
+                break;
+            };
+            mirror.remove(&oldest);
+        }
+        self.metrics.mirrored_records.store(mirror.len() as u64, Ordering::Relaxed);
+        added
+    }
+
+    /// 按能力查询镜像记录（最新发布的在前）
+    pub fn lookup(&self, capability: &str) -> Vec<AgentRecord> {
+        self.metrics.lookups.fetch_add(1, Ordering::Relaxed);
+        let now = self.clock.now_secs();
+        let mut records: Vec<AgentRecord> = self.mirror.lock().unwrap()
+            .values()
+            .filter(|record| record.has_capability(capability) && !record.is_expired(now))
+            .cloned()
+            .collect();
+        records.sort_by_key(|record| std::cmp::Reverse(record.published_at));
+        records
+    }
+}
+
+/// 运行中的中继节点
+pub struct RelayNode {
+    did: String,
+    peer_id: PeerId,
+    dht: Option<Arc<KademliaDht>>,
+    service_addrs: Vec<Multiaddr>,
+    service: Arc<RelayService>,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl RelayNode {
+    /// 按配置启动中继节点（中继DID密钥保存在数据目录中，libp2p身份由同一密钥派生）
+    pub async fn start(config: RelayConfig) -> Result<Self> {
+        config.validate()?;
+        std::fs::create_dir_all(&config.data_dir)
+            .with_context(|| format!("无法创建数据目录: {:?}", config.data_dir))?;
+        let keypair = KeyManager::new(config.data_dir.clone())
+            .load_or_generate(&config.data_dir.join(RELAY_KEY_FILE))?;
+        let identity = LibP2PIdentity::from_did_keypair(&keypair)?;
+        let peer_id = *identity.peer_id();
+
+        log::info!("🛰️ 启动中继节点: {}", keypair.did);
+        log::info!("   PeerID: {}", peer_id);
+        log::info!("   角色: {}", config.roles.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "));
+
+        let service = Arc::new(RelayService::new(config.roles.clone(), config.quotas.clone()));
+        let mut tasks = Vec::new();
+
+        // DHT：引导节点和注册表镜像都需要
+        let dht = if config.has_role(RelayRole::Bootstrap) || config.has_role(RelayRole::RegistryMirror) {
+            let dht = Arc::new(KademliaDht::start(&identity, Multiaddr::from_str(&config.dht_listen_addr)?).await?);
+            for peer in &config.bootstrap_peers {
+                let (peer_id, address) = parse_peer_addr(peer)?;
+                dht.add_peer(peer_id, address)?;
+            }
+            if !config.bootstrap_peers.is_empty() {
+                dht.bootstrap()?;
+            }
+            Some(dht)
+        } else {
+            None
+        };
+
+        // 邮箱和镜像查询通过中继服务协议提供，电路中继v2与其共用同一个Swarm
+        let service_addrs = if config.has_role(RelayRole::Mailbox)
+            || config.has_role(RelayRole::RegistryMirror)
+            || config.has_role(RelayRole::CircuitRelay)
+        {
+            let (swarm, addrs) = start_service_swarm(
+                &identity,
+                Multiaddr::from_str(&config.service_listen_addr)?,
+                config.has_role(RelayRole::CircuitRelay),
+            ).await?;
+            tasks.push(tokio::spawn(run_service(swarm, service.clone())));
+            addrs
+        } else {
+            Vec::new()
+        };
+
+        if let (Some(dht), true) = (&dht, config.has_role(RelayRole::RegistryMirror)) {
+            tasks.push(tokio::spawn(run_mirror(
+                dht.clone(),
+                service.clone(),
+                config.mirror_capabilities.clone(),
+                Duration::from_secs(config.mirror_interval_secs.max(1)),
+            )));
+        }
+
+        let maintenance_service = service.clone();
+        let interval = Duration::from_secs(config.metrics_interval_secs.max(1));
+        tasks.push(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                maintenance_service.purge_expired();
+                let m = maintenance_service.metrics();
+                log::info!(
+                    "📊 中继指标: 存放{} 投递{} 过期{} 配额拒绝{} 认证失败{} 镜像记录{} 查询{}",
+                    m.messages_stored, m.messages_delivered, m.messages_expired,
+                    m.quota_rejections, m.auth_failures, m.mirrored_records, m.lookups
+                );
+            }
+        }));
+
+        Ok(Self {
+            did: keypair.did,
+            peer_id,
+            dht,
+            service_addrs,
+            service,
+            tasks,
+        })
+    }
+
+    /// 中继DID
+    pub fn did(&self) -> &str {
+        &self.did
+    }
+
+    /// 中继PeerID
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    /// DHT监听地址
+    pub async fn dht_addrs(&self) -> Result<Vec<Multiaddr>> {
+        match &self.dht {
+            Some(dht) => dht.listen_addrs().await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// 中继服务地址（带/p2p/后缀，可直接交给relay_request）
+    pub fn service_addrs(&self) -> Vec<Multiaddr> {
+        self.service_addrs.iter()
+            .map(|addr| addr.clone().with(Protocol::P2p(self.peer_id)))
+            .collect()
+    }
+
+    /// 请求处理服务
+    pub fn service(&self) -> &RelayService {
+        &self.service
+    }
+
+    /// 当前指标
+    pub fn metrics(&self) -> RelayMetricsSnapshot {
+        self.service.metrics()
+    }
+
+    /// 停止节点
+    pub fn shutdown(self) {
+        drop(self);
+    }
+}
+
+impl Drop for RelayNode {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// 向中继发送一个请求（relay_addr需带/p2p/后缀）
+pub async fn relay_request(relay_addr: &Multiaddr, request: &RelayRequest, timeout: Duration) -> Result<RelayResponse> {
+    let (relay_peer, _) = parse_peer_addr(&relay_addr.to_string())?;
+    let identity = LibP2PIdentity::generate()?;
+    let mut swarm = build_service_swarm(&identity, false)?;
+    swarm.dial(relay_addr.clone()).with_context(|| format!("无法拨号中继: {}", relay_addr))?;
+    let payload = request.to_bytes()?;
+
+    let exchange = async {
+        let mut sent = false;
+        loop {
+            match swarm.select_next_some().await {
+                SwarmEvent::ConnectionEstablished { peer_id, .. } if peer_id == relay_peer && !sent => {
+                    swarm.behaviour_mut().service.send_request(&relay_peer, payload.clone());
+                    sent = true;
+                }
+                SwarmEvent::OutgoingConnectionError { error, .. } => {
+                    anyhow::bail!("连接中继失败: {}", error);
+                }
+                SwarmEvent::Behaviour(ServiceBehaviourEvent::Service(request_response::Event::Message {
+                    message: request_response::Message::Response { response, .. },
+                    ..
+                })) => return RelayResponse::from_bytes(&response),
+                SwarmEvent::Behaviour(ServiceBehaviourEvent::Service(request_response::Event::OutboundFailure { error, .. })) => {
+                    anyhow::bail!("中继请求失败: {}", error);
+                }
+                _ => {}
+            }
+        }
+    };
+    tokio::time::timeout(timeout, exchange).await
+        .map_err(|_| anyhow::anyhow!("中继请求超时"))?
+}
+
+fn relay_protocol() -> Result<StreamProtocol> {
+    StreamProtocol::try_from_owned(network_params().relay_protocol()).context("无效的中继协议名")
+}
+
+/// 中继服务Swarm的行为：DIAP中继服务协议，可选电路中继v2服务端（附带identify，供客户端获知观测地址）
+#[derive(NetworkBehaviour)]
+struct ServiceBehaviour {
+    service: request_response::Behaviour<DIAPCodec>,
+    relay: Toggle<relay::Behaviour>,
+    identify: Toggle<identify::Behaviour>,
+}
+
+fn build_service_swarm(identity: &LibP2PIdentity, circuit_relay: bool) -> Result<Swarm<ServiceBehaviour>> {
+    let protocol = relay_protocol()?;
+    Ok(libp2p::SwarmBuilder::with_existing_identity(identity.keypair().clone())
+        .with_tokio()
+        .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)
+        .context("创建TCP传输失败")?
+        .with_behaviour(|key| {
+            let peer_id = key.public().to_peer_id();
+            ServiceBehaviour {
+                service: request_response::Behaviour::with_codec(
+                    DIAPCodec::default(),
+                    [(protocol, ProtocolSupport::Full)],
+                    request_response::Config::default(),
+                ),
+                relay: Toggle::from(circuit_relay.then(|| relay::Behaviour::new(peer_id, relay::Config::default()))),
+                identify: Toggle::from(circuit_relay.then(|| identify::Behaviour::new(
+                    identify::Config::new(network_params().protocol(), key.public()),
+                ))),
+            }
+        })
+        .context("创建中继服务行为失败")?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+        .build())
+}
+
+/// 启动服务Swarm并等待监听地址就绪
+async fn start_service_swarm(
+    identity: &LibP2PIdentity,
+    listen_addr: Multiaddr,
+    circuit_relay: bool,
+) -> Result<(Swarm<ServiceBehaviour>, Vec<Multiaddr>)> {
+    let mut swarm = build_service_swarm(identity, circuit_relay)?;
+    swarm.listen_on(listen_addr.clone())
+        .with_context(|| format!("无法监听地址: {}", listen_addr))?;
+    let address = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
+            break address;
+        }
+    };
+    if circuit_relay {
+        // 预约响应中携带的中继地址；公网部署时应监听在可达地址上
+        swarm.add_external_address(address.clone());
+    }
+    log::info!("📮 中继服务已监听: {}", address);
+    Ok((swarm, vec![address]))
+}
+
+async fn run_service(mut swarm: Swarm<ServiceBehaviour>, service: Arc<RelayService>) {
+    loop {
+        match swarm.select_next_some().await {
+            SwarmEvent::Behaviour(ServiceBehaviourEvent::Service(request_response::Event::Message {
+                peer,
+                message: request_response::Message::Request { request, channel, .. },
+            })) => {
+                let response = match RelayRequest::from_bytes(&request) {
+                    Ok(request) => service.handle(request),
+                    Err(e) => RelayResponse::Rejected(e.to_string()),
+                };
+                let bytes = match response.to_bytes() {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        log::warn!("序列化中继响应失败: {}", e);
+                        continue;
+                    }
+                };
+                if swarm.behaviour_mut().service.send_response(channel, bytes).is_err() {
+                    log::debug!("中继响应未送达: {}", peer);
+                }
+            }
+            SwarmEvent::Behaviour(ServiceBehaviourEvent::Relay(event)) => match event {
+                relay::Event::ReservationReqAccepted { src_peer_id, .. } => {
+                    log::info!("🔁 接受电路预约: {}", src_peer_id);
+                }
+                relay::Event::CircuitReqAccepted { src_peer_id, dst_peer_id } => {
+                    log::debug!("🔁 建立中继电路: {} -> {}", src_peer_id, dst_peer_id);
+                }
+                other => log::debug!("电路中继事件: {:?}", other),
+            },
+            SwarmEvent::NewListenAddr { address, .. } => {
+                log::info!("📮 中继服务已监听: {}", address);
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn run_mirror(dht: Arc<KademliaDht>, service: Arc<RelayService>, capabilities: Vec<String>, interval: Duration) {
+    let discovery = AgentDiscovery::new(dht.clone());
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        for capability in &capabilities {
+            let records = match discovery.find_agents_by_capability(capability).await {
+                Ok(records) => records,
+                Err(e) => {
+                    log::warn!("镜像能力 {} 失败: {}", capability, e);
+                    continue;
+                }
+            };
+            // 在本节点重新存放记录，发布者离线后仍可从DHT取回
+            for record in &records {
+                let Ok(peer_id) = PeerId::from_str(&record.peer_id) else { continue };
+                if let Ok(bytes) = record.to_bytes() {
+                    if let Err(e) = dht.put_record(agent_record_key(&peer_id), bytes).await {
+                        log::debug!("重新存放智能体记录失败 {}: {}", record.did, e);
+                    }
+                }
+            }
+            let added = service.mirror_records(records);
+            log::debug!("🪞 镜像能力 {}: 新增/更新 {} 条记录", capability, added);
+        }
+    }
+}
+
+fn fetch_signing_data(did: &str, nonce: &str) -> Vec<u8> {
+    format!("{}:fetch:{}:{}", network_params().relay_protocol(), did, nonce).into_bytes()
+}
+
+/// 解析带/p2p/后缀的多地址
+fn parse_peer_addr(addr: &str) -> Result<(PeerId, Multiaddr)> {
+    let address = Multiaddr::from_str(addr).with_context(|| format!("无效的多地址: {}", addr))?;
+    let peer_id = address.iter()
+        .find_map(|protocol| match protocol {
+            Protocol::P2p(peer_id) => Some(peer_id),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow::anyhow!("多地址缺少/p2p/后缀: {}", addr))?;
+    Ok((peer_id, address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, MockClock};
+    use crate::pubsub_authenticator::PubSubMessageType;
+
+    fn message_to(sender: &KeyPair, did: &str, content: &[u8]) -> AuthenticatedMessage {
+        let mut message = AuthenticatedMessage {
+            message_id: format!("m-{}", rand::random::<u32>()),
+            message_type: PubSubMessageType::Custom("note".to_string()),
+            from_did: sender.did.clone(),
+            to_did: Some(did.to_string()),
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: "inbox".to_string(),
+            content: content.to_vec(),
+            nonce: NonceManager::generate_nonce(),
+            zkp_proof: Vec::new(),
+            signature: Vec::new(),
+            timestamp: 0,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        };
+        message.signature = sender.sign(&message.signing_data()).unwrap();
+        message
+    }
+
+    #[tokio::test]
+    async fn test_mailbox_quotas_and_fetch_auth() {
+        let clock = MockClock::new(1_000_000);
+        let quotas = RelayQuotas {
+            max_stored_messages: 3,
+            max_messages_per_did: 2,
+            max_message_bytes: 8,
+            message_ttl_secs: 60,
+            ..RelayQuotas::default()
+        };
+        let service = RelayService::new_with_clock(vec![RelayRole::Mailbox], quotas, Arc::new(clock.clone()));
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let sender = KeyPair::generate().unwrap();
+
+        assert_eq!(service.deposit(message_to(&sender, &alice.did, b"hi")).unwrap(), 1);
+        assert_eq!(service.deposit(message_to(&sender, &alice.did, b"again")).unwrap(), 2);
+        assert!(matches!(service.deposit(message_to(&sender, &alice.did, b"x")), Err(RelayQuotaError::MailboxFull { .. })));
+        assert!(matches!(service.deposit(message_to(&sender, &bob.did, b"too long!")), Err(RelayQuotaError::MessageTooLarge { .. })));
+        service.deposit(message_to(&sender, &bob.did, b"yo")).unwrap();
+        assert!(matches!(service.deposit(message_to(&sender, "did:key:carol", b"x")), Err(RelayQuotaError::StorageFull { .. })));
+
+        // 签名无效（冒充发件人）的消息不占用配额
+        let mut forged = message_to(&sender, &bob.did, b"spam");
+        forged.from_did = alice.did.clone();
+        assert!(matches!(service.deposit(forged), Err(RelayQuotaError::InvalidSignature { .. })));
+
+        // 只有收件人本人可以取件
+        let RelayRequest::Fetch { nonce, signature, .. } = RelayRequest::fetch(&bob, clock.now_secs()).unwrap() else {
+            unreachable!()
+        };
+        let forged = RelayRequest::Fetch { did: alice.did.clone(), nonce, signature };
+        assert!(matches!(service.handle(forged), RelayResponse::Rejected(_)));
+        let stale = RelayRequest::fetch(&alice, clock.now_secs() - FETCH_MAX_SKEW_SECS - 1).unwrap();
+        assert!(matches!(service.handle(stale), RelayResponse::Rejected(_)));
+        let fetch = RelayRequest::fetch(&alice, clock.now_secs()).unwrap();
+        match service.handle(fetch.clone()) {
+            RelayResponse::Messages(messages) => assert_eq!(messages.len(), 2),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        // 截获的取件请求不能重放
+        assert!(matches!(service.handle(fetch), RelayResponse::Rejected(_)));
+
+        // 过期清理与角色检查
+        clock.advance(Duration::from_secs(61));
+        assert_eq!(service.purge_expired(), 1);
+        assert!(matches!(service.handle(RelayRequest::Lookup { capability: "x".to_string() }), RelayResponse::Rejected(_)));
+
+        let metrics = service.metrics();
+        assert_eq!(metrics.messages_stored, 3);
+        assert_eq!(metrics.messages_delivered, 2);
+        assert_eq!(metrics.messages_expired, 1);
+        assert_eq!(metrics.quota_rejections, 3);
+        assert_eq!(metrics.auth_failures, 4);
+
+        // 单个发件人不能占满中继存储
+        let quotas = RelayQuotas { max_messages_per_sender: 1, ..RelayQuotas::default() };
+        let service = RelayService::new_with_clock(vec![RelayRole::Mailbox], quotas, Arc::new(clock.clone()));
+        service.deposit(message_to(&sender, &alice.did, b"1")).unwrap();
+        assert!(matches!(service.deposit(message_to(&sender, &bob.did, b"2")), Err(RelayQuotaError::SenderQuotaExceeded { .. })));
+        service.deposit(message_to(&bob, &alice.did, b"3")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_relay_node_over_network() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RelayConfig {
+            data_dir: dir.path().to_path_buf(),
+            roles: vec![RelayRole::Mailbox],
+            service_listen_addr: "/ip4/127.0.0.1/tcp/0".to_string(),
+            ..RelayConfig::default()
+        };
+        assert!(RelayConfig { roles: vec![RelayRole::CircuitRelay], ..config.clone() }.validate().is_ok());
+
+        let node = RelayNode::start(config.clone()).await.unwrap();
+        let relay_addr = node.service_addrs().remove(0);
+        let recipient = KeyPair::generate().unwrap();
+
+        let timeout = Duration::from_secs(10);
+        let sender = KeyPair::generate().unwrap();
+        let response = relay_request(&relay_addr, &RelayRequest::Deposit(Box::new(message_to(&sender, &recipient.did, b"hello"))), timeout).await.unwrap();
+        assert!(matches!(response, RelayResponse::Stored { pending: 1 }));
+
+        let now = system_clock().now_secs();
+        match relay_request(&relay_addr, &RelayRequest::fetch(&recipient, now).unwrap(), timeout).await.unwrap() {
+            RelayResponse::Messages(messages) => assert_eq!(messages[0].content, b"hello"),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert_eq!(node.metrics().messages_delivered, 1);
+
+        // 重启后沿用同一DID
+        let did = node.did().to_string();
+        node.shutdown();
+        let restarted = RelayNode::start(config).await.unwrap();
+        assert_eq!(restarted.did(), did);
+    }
+}
@@ -0,0 +1,373 @@
+// DIAP Rust SDK - 可靠广播模块
+// 发往群组的消息只有在收集到阈值数量成员的签名确认后才视为已送达，
+// 确认聚合为送达证书，发送者可向第三方出示
+
+use anyhow::Result;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::{AuthenticatedMessage, SignedMessage};
+
+/// 广播确认消息类型标识（PubSubMessageType::Custom）
+pub const BROADCAST_ACK_MESSAGE_TYPE: &str = "broadcast_ack";
+
+/// 成员对广播消息的签名确认
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastAck {
+    /// 被确认的消息ID
+    pub message_id: String,
+
+    /// 被确认消息的摘要（hex）
+    pub message_hash: String,
+
+    /// 广播发送者DID
+    pub sender_did: String,
+
+    /// 确认者DID
+    pub member_did: String,
+
+    /// 确认时间
+    pub acked_at: u64,
+
+    /// 确认者签名（base64）
+    pub signature: String,
+}
+
+impl BroadcastAck {
+    /// 对收到的广播消息签名确认
+    pub fn new(signer: &dyn Signer, message: &AuthenticatedMessage, acked_at: u64) -> Result<Self> {
+        Self {
+            message_id: message.message_id.clone(),
+            message_hash: message_hash(message),
+            sender_did: message.from_did.clone(),
+            member_did: signer.did(),
+            acked_at,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+}
+
+impl SignedEnvelope for BroadcastAck {
+    const KIND: &'static str = "广播确认";
+
+    fn signer_did(&self) -> &str {
+        &self.member_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+impl SignedMessage for BroadcastAck {
+    const MESSAGE_TYPE: &'static str = BROADCAST_ACK_MESSAGE_TYPE;
+}
+
+/// 送达证书：阈值数量成员的确认，由发送者签名绑定群组成员和阈值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryCertificate {
+    /// 消息ID
+    pub message_id: String,
+
+    /// 消息摘要（hex）
+    pub message_hash: String,
+
+    /// 发送者DID
+    pub sender_did: String,
+
+    /// 群组成员DID
+    pub members: Vec<String>,
+
+    /// 所需确认数
+    pub threshold: usize,
+
+    /// 成员确认
+    pub acks: Vec<BroadcastAck>,
+
+    /// 签发时间
+    pub issued_at: u64,
+
+    /// 发送者签名（base64）
+    pub signature: String,
+}
+
+impl DeliveryCertificate {
+    fn new(
+        signer: &dyn Signer,
+        broadcast: &PendingBroadcast,
+        acks: Vec<BroadcastAck>,
+        issued_at: u64,
+    ) -> Result<Self> {
+        Self {
+            message_id: broadcast.message_id.clone(),
+            message_hash: broadcast.message_hash.clone(),
+            sender_did: signer.did(),
+            members: broadcast.members.clone(),
+            threshold: broadcast.threshold,
+            acks,
+            issued_at,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// 验证证书：发送者签名、每个确认的签名和摘要、确认者属于群组且不重复、达到阈值
+    pub fn verify(&self) -> Result<bool> {
+        if self.threshold == 0 || self.threshold > self.members.len() {
+            return Ok(false);
+        }
+        if !SignedEnvelope::verify(self)? {
+            return Ok(false);
+        }
+
+        let mut seen = HashSet::new();
+        for ack in &self.acks {
+            if ack.message_id != self.message_id
+                || ack.message_hash != self.message_hash
+                || ack.sender_did != self.sender_did
+                || !self.members.contains(&ack.member_did)
+                || !seen.insert(ack.member_did.as_str())
+            {
+                return Ok(false);
+            }
+            if !ack.verify()? {
+                return Ok(false);
+            }
+        }
+
+        Ok(seen.len() >= self.threshold)
+    }
+
+    /// 针对第三方已知的群组验证：成员集合一致且阈值不低于期望值
+    pub fn verify_for_group(&self, members: &[String], min_threshold: usize) -> Result<bool> {
+        let expected: HashSet<&String> = members.iter().collect();
+        let actual: HashSet<&String> = self.members.iter().collect();
+        if expected != actual || self.threshold < min_threshold {
+            return Ok(false);
+        }
+        self.verify()
+    }
+
+    /// 证书是否对应该消息
+    pub fn covers(&self, message: &AuthenticatedMessage) -> bool {
+        self.message_id == message.message_id && self.message_hash == message_hash(message)
+    }
+}
+
+impl SignedEnvelope for DeliveryCertificate {
+    const KIND: &'static str = "送达证书";
+
+    fn signer_did(&self) -> &str {
+        &self.sender_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 等待确认的广播
+#[derive(Debug, Clone)]
+pub struct PendingBroadcast {
+    /// 消息ID
+    pub message_id: String,
+
+    /// 消息摘要（hex）
+    pub message_hash: String,
+
+    /// 群组成员DID
+    pub members: Vec<String>,
+
+    /// 所需确认数
+    pub threshold: usize,
+
+    /// 已收到的确认
+    pub acks: Vec<BroadcastAck>,
+}
+
+/// 广播确认跟踪器（发送者侧）
+#[derive(Clone, Default)]
+pub struct BroadcastTracker {
+    pending: Arc<DashMap<String, PendingBroadcast>>,
+    certificates: Arc<DashMap<String, DeliveryCertificate>>,
+}
+
+impl BroadcastTracker {
+    /// 创建跟踪器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始跟踪一条广播
+    pub fn track(&self, message: &AuthenticatedMessage, members: Vec<String>, threshold: usize) -> Result<()> {
+        if threshold == 0 || threshold > members.len() {
+            anyhow::bail!("无效的确认阈值: {}/{}", threshold, members.len());
+        }
+
+        self.pending.insert(message.message_id.clone(), PendingBroadcast {
+            message_id: message.message_id.clone(),
+            message_hash: message_hash(message),
+            members,
+            threshold,
+            acks: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// 记录确认，达到阈值时用signer签发送达证书
+    /// 返回Ok(None)表示确认已记录但尚未达到阈值，或确认已存在/广播未跟踪
+    pub fn record_ack(
+        &self,
+        ack: BroadcastAck,
+        signer: &dyn Signer,
+        now: u64,
+    ) -> Result<Option<DeliveryCertificate>> {
+        let Some(mut pending) = self.pending.get_mut(&ack.message_id) else {
+            log::debug!("忽略未跟踪广播的确认: {}", ack.message_id);
+            return Ok(None);
+        };
+
+        if ack.message_hash != pending.message_hash || ack.sender_did != signer.did() {
+            anyhow::bail!("广播确认与消息不匹配: {}", ack.message_id);
+        }
+        if !pending.members.contains(&ack.member_did) {
+            anyhow::bail!("确认者不是群组成员: {}", ack.member_did);
+        }
+        if !ack.verify()? {
+            anyhow::bail!("广播确认签名无效: {}", ack.member_did);
+        }
+        if pending.acks.iter().any(|a| a.member_did == ack.member_did) {
+            return Ok(None);
+        }
+
+        pending.acks.push(ack);
+        log::debug!("✓ 广播确认 {}/{}: {}", pending.acks.len(), pending.threshold, pending.message_id);
+        if pending.acks.len() < pending.threshold {
+            return Ok(None);
+        }
+
+        let certificate = DeliveryCertificate::new(signer, &pending, pending.acks.clone(), now)?;
+        let message_id = pending.message_id.clone();
+        drop(pending);
+
+        self.pending.remove(&message_id);
+        self.certificates.insert(message_id.clone(), certificate.clone());
+        log::info!("📜 广播已送达（达到确认阈值）: {}", message_id);
+        Ok(Some(certificate))
+    }
+
+    /// 已签发的送达证书
+    pub fn certificate(&self, message_id: &str) -> Option<DeliveryCertificate> {
+        self.certificates.get(message_id).map(|c| c.clone())
+    }
+
+    /// 是否已送达
+    pub fn is_delivered(&self, message_id: &str) -> bool {
+        self.certificates.contains_key(message_id)
+    }
+
+    /// 等待确认的广播
+    pub fn pending(&self, message_id: &str) -> Option<PendingBroadcast> {
+        self.pending.get(message_id).map(|p| p.clone())
+    }
+
+    /// 尚未确认的成员
+    pub fn missing_members(&self, message_id: &str) -> Vec<String> {
+        self.pending.get(message_id)
+            .map(|p| {
+                p.members.iter()
+                    .filter(|m| !p.acks.iter().any(|a| &a.member_did == *m))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// 消息摘要：覆盖消息ID、发送者和签名数据
+pub fn message_hash(message: &AuthenticatedMessage) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(message.message_id.as_bytes());
+    hasher.update(message.from_did.as_bytes());
+    hasher.update(message.signing_data());
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::pubsub_authenticator::PubSubMessageType;
+
+    fn broadcast(sender: &KeyPair) -> AuthenticatedMessage {
+        AuthenticatedMessage {
+            message_id: "b1".to_string(),
+            message_type: PubSubMessageType::Custom("task".to_string()),
+            from_did: sender.did.clone(),
+            to_did: None,
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: "group".to_string(),
+            content: b"deploy v2".to_vec(),
+            nonce: "n1".to_string(),
+            zkp_proof: Vec::new(),
+            signature: Vec::new(),
+            timestamp: 0,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_threshold_certificate() {
+        let sender = KeyPair::generate().unwrap();
+        let members: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate().unwrap()).collect();
+        let member_dids: Vec<String> = members.iter().map(|m| m.did.clone()).collect();
+        let message = broadcast(&sender);
+
+        let tracker = BroadcastTracker::new();
+        tracker.track(&message, member_dids.clone(), 2).unwrap();
+
+        let first = BroadcastAck::new(&members[0], &message, 10).unwrap();
+        assert!(tracker.record_ack(first.clone(), &sender, 10).unwrap().is_none());
+        // 重复确认不计数
+        assert!(tracker.record_ack(first, &sender, 10).unwrap().is_none());
+        assert_eq!(tracker.missing_members("b1").len(), 2);
+
+        // 非成员确认被拒绝
+        let outsider = KeyPair::generate().unwrap();
+        assert!(tracker.record_ack(BroadcastAck::new(&outsider, &message, 11).unwrap(), &sender, 11).is_err());
+
+        let certificate = tracker
+            .record_ack(BroadcastAck::new(&members[1], &message, 12).unwrap(), &sender, 12)
+            .unwrap()
+            .expect("达到阈值后应签发证书");
+        assert!(tracker.is_delivered("b1"));
+        assert!(certificate.covers(&message));
+        assert!(certificate.verify().unwrap());
+        assert!(certificate.verify_for_group(&member_dids, 2).unwrap());
+        assert!(!certificate.verify_for_group(&member_dids, 3).unwrap());
+
+        // 篡改证书
+        let mut forged = certificate.clone();
+        forged.threshold = 1;
+        assert!(!forged.verify().unwrap());
+        let mut forged = certificate;
+        forged.acks.pop();
+        assert!(!forged.verify().unwrap());
+    }
+}
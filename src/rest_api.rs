@@ -0,0 +1,1637 @@
+// DIAP Rust SDK - REST接口
+// 在 /diap/api 下提供身份增删改查、证明验证、消息发送和对端列表，
+// 路由由带类型的处理函数注册，OpenAPI文档（/diap/api/openapi.json）由请求/响应类型生成
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use futures::future::BoxFuture;
+use schemars::{JsonSchema, Schema, SchemaGenerator, generate::SchemaSettings};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+
+use crate::connection_manager::{ConnectionManager, PeerState};
+use crate::constants::network_params;
+use crate::did_wba_auth::{AuthenticatedCaller, DIDWBA_SCHEME, DidRequestVerifier};
+use crate::http_server::{HttpRequest, connection_limiter, constant_time_eq, read_request, write_response};
+use crate::identity_manager::{AgentInfo, IdentityManager, IdentityRegistration, IdentityVerification, ServiceInfo};
+use crate::key_manager::KeyPair;
+use crate::libp2p_identity::LibP2PIdentity;
+use crate::pubsub_authenticator::{AuthenticatedMessage, PubSubMessageType, PubsubAuthenticator};
+
+/// 默认REST接口地址（仅本机）
+pub const DEFAULT_REST_ADDR: &str = "127.0.0.1:8787";
+
+pub use crate::constants::{API_BASE_PATH, OPENAPI_PATH};
+
+/// 默认请求体上限
+pub const DEFAULT_MAX_BODY: usize = 1024 * 1024;
+
+const JSON: &str = "application/json";
+
+/// 发布消息到网络（由libp2p/Iroh层提供）
+pub type PublishFn = Arc<dyn Fn(AuthenticatedMessage) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// 路由处理函数
+pub type HandlerFn = Arc<dyn Fn(ApiRequest) -> BoxFuture<'static, ApiResponse> + Send + Sync>;
+
+/// 为OpenAPI文档生成类型的JSON Schema
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// HTTP方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+impl HttpMethod {
+    /// 方法名（大写）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Delete => "DELETE",
+        }
+    }
+
+    /// 解析方法名（不区分大小写）
+    pub fn parse(method: &str) -> Option<Self> {
+        match method.to_ascii_uppercase().as_str() {
+            "GET" => Some(HttpMethod::Get),
+            "POST" => Some(HttpMethod::Post),
+            "PUT" => Some(HttpMethod::Put),
+            "DELETE" => Some(HttpMethod::Delete),
+            _ => None,
+        }
+    }
+}
+
+/// 路由收到的请求
+#[derive(Debug, Clone)]
+pub struct ApiRequest {
+    /// 请求方法
+    pub method: HttpMethod,
+
+    /// 请求路径
+    pub path: String,
+
+    /// 路径参数（`{name}` 段）
+    pub params: HashMap<String, String>,
+
+    /// 原始查询串
+    pub query: String,
+
+    /// 请求头
+    pub headers: Vec<(String, String)>,
+
+    /// 请求体
+    pub body: Vec<u8>,
+
+    /// DIDWba认证通过且在授权列表中的调用方（令牌认证或公开路由时为None）
+    pub caller: Option<AuthenticatedCaller>,
+}
+
+impl ApiRequest {
+    /// 调用方DID
+    pub fn caller_did(&self) -> Option<&str> {
+        self.caller.as_ref().map(|caller| caller.did.as_str())
+    }
+
+    fn from_http(method: HttpMethod, request: HttpRequest, params: HashMap<String, String>, caller: Option<AuthenticatedCaller>) -> Self {
+        Self {
+            caller,
+            method,
+            path: request.path,
+            params,
+            query: request.query,
+            headers: request.headers,
+            body: request.body,
+        }
+    }
+
+    /// 路径参数
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    /// 请求头（不区分大小写）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 查询参数（已解码）
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query.split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| crate::http_server::percent_decode(&value.replace('+', " ")))
+    }
+
+    /// 按JSON解析请求体
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, ApiError> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| ApiError::bad_request(format!("请求体不是有效的JSON: {}", e)))
+    }
+}
+
+/// 路由返回的响应
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    /// 状态码
+    pub status: u16,
+
+    /// Content-Type
+    pub content_type: String,
+
+    /// 响应体
+    pub body: Vec<u8>,
+}
+
+impl ApiResponse {
+    /// JSON响应
+    pub fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self { status, content_type: JSON.to_string(), body },
+            Err(e) => ApiError::internal(format!("序列化响应失败: {}", e)).into(),
+        }
+    }
+
+    /// 自定义Content-Type的响应
+    pub fn raw(status: u16, content_type: impl Into<String>, body: impl Into<Vec<u8>>) -> Self {
+        Self { status, content_type: content_type.into(), body: body.into() }
+    }
+}
+
+/// 接口错误（状态码 + 错误信息）
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    /// 状态码
+    pub status: u16,
+
+    /// 错误信息
+    pub message: String,
+}
+
+impl ApiError {
+    /// 自定义状态码
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    /// 400
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(400, message)
+    }
+
+    /// 401
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(401, message)
+    }
+
+    /// 403
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(403, message)
+    }
+
+    /// 404
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(404, message)
+    }
+
+    /// 503（依赖的组件未配置）
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        Self::new(503, message)
+    }
+
+    /// 500
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(500, message)
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.status, self.message)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        ApiError::internal(format!("{:#}", error))
+    }
+}
+
+impl From<ApiError> for ApiResponse {
+    fn from(error: ApiError) -> Self {
+        let body = serde_json::to_vec(&ErrorBody { error: error.message }).unwrap_or_default();
+        ApiResponse { status: error.status, content_type: JSON.to_string(), body }
+    }
+}
+
+/// 错误响应体
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorBody {
+    /// 错误信息
+    pub error: String,
+}
+
+/// 接口操作描述（用于生成OpenAPI文档）
+#[derive(Clone)]
+pub struct Operation {
+    /// 请求方法
+    pub method: HttpMethod,
+
+    /// 路径模板（如 `/diap/api/identities/{did}`）
+    pub path: String,
+
+    /// 分组标签
+    pub tag: String,
+
+    /// 简要说明
+    pub summary: String,
+
+    /// 无需访问令牌
+    pub public: bool,
+
+    request_schema: Option<SchemaFn>,
+    response_schema: Option<SchemaFn>,
+}
+
+impl Operation {
+    /// operationId（方法 + 路径段）
+    pub fn operation_id(&self) -> String {
+        let mut id = self.method.as_str().to_ascii_lowercase();
+        for segment in self.path.trim_start_matches(network_params().api_base_path().as_str()).split('/').filter(|s| !s.is_empty()) {
+            id.push('_');
+            id.push_str(&segment.trim_matches(|c| c == '{' || c == '}').replace(['.', '-'], "_"));
+        }
+        id
+    }
+}
+
+#[derive(Clone)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+fn parse_segments(path: &str) -> Vec<Segment> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(name) => Segment::Param(name.to_string()),
+            None => Segment::Literal(segment.to_string()),
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+struct Route {
+    operation: Operation,
+    segments: Vec<Segment>,
+    handler: HandlerFn,
+}
+
+impl Route {
+    fn match_path(&self, path: &str) -> Option<HashMap<String, String>> {
+        let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+        if parts.len() != self.segments.len() {
+            return None;
+        }
+        let mut params = HashMap::new();
+        for (segment, part) in self.segments.iter().zip(parts) {
+            match segment {
+                Segment::Literal(literal) if literal == part => {}
+                Segment::Literal(_) => return None,
+                Segment::Param(name) => {
+                    params.insert(name.clone(), part.to_string());
+                }
+            }
+        }
+        Some(params)
+    }
+}
+
+/// 路由查找结果
+pub enum RouteMatch {
+    /// 找到处理函数
+    Found {
+        handler: HandlerFn,
+        params: HashMap<String, String>,
+        public: bool,
+    },
+
+    /// 路径存在但方法不支持
+    MethodNotAllowed,
+
+    /// 路径不存在
+    NotFound,
+}
+
+/// 路由表
+#[derive(Clone, Default)]
+pub struct ApiRouter {
+    routes: Vec<Route>,
+}
+
+impl ApiRouter {
+    /// 创建空路由表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册无请求体的路由，处理函数返回的值序列化为JSON
+    pub fn handle<Resp, F, Fut>(&mut self, method: HttpMethod, path: &str, tag: &str, summary: &str, handler: F) -> &mut Self
+    where
+        Resp: Serialize + JsonSchema + 'static,
+        F: Fn(ApiRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, ApiError>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let handler: HandlerFn = Arc::new(move |request| {
+            let future = handler(request);
+            Box::pin(async move {
+                match future.await {
+                    Ok(value) => ApiResponse::json(200, &value),
+                    Err(error) => error.into(),
+                }
+            })
+        });
+        self.insert(method, path, tag, summary, None, Some(|generator| generator.subschema_for::<Resp>()), handler)
+    }
+
+    /// 注册带JSON请求体的路由，请求体无法解析时返回400
+    pub fn handle_json<Req, Resp, F, Fut>(&mut self, method: HttpMethod, path: &str, tag: &str, summary: &str, handler: F) -> &mut Self
+    where
+        Req: DeserializeOwned + JsonSchema + Send + 'static,
+        Resp: Serialize + JsonSchema + 'static,
+        F: Fn(ApiRequest, Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, ApiError>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let handler: HandlerFn = Arc::new(move |request| {
+            let parsed = request.json::<Req>();
+            let future = parsed.map(|body| handler(request, body));
+            Box::pin(async move {
+                match future {
+                    Ok(future) => match future.await {
+                        Ok(value) => ApiResponse::json(200, &value),
+                        Err(error) => error.into(),
+                    },
+                    Err(error) => error.into(),
+                }
+            })
+        });
+        self.insert(
+            method,
+            path,
+            tag,
+            summary,
+            Some(|generator| generator.subschema_for::<Req>()),
+            Some(|generator| generator.subschema_for::<Resp>()),
+            handler,
+        )
+    }
+
+    /// 注册原始处理函数（自行构造响应，OpenAPI中不含请求/响应结构）
+    pub fn handle_raw(&mut self, method: HttpMethod, path: &str, tag: &str, summary: &str, handler: HandlerFn) -> &mut Self {
+        self.insert(method, path, tag, summary, None, None, handler)
+    }
+
+    /// 将最近注册的路由标记为无需访问令牌
+    pub fn public(&mut self) -> &mut Self {
+        if let Some(route) = self.routes.last_mut() {
+            route.operation.public = true;
+        }
+        self
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn insert(
+        &mut self,
+        method: HttpMethod,
+        path: &str,
+        tag: &str,
+        summary: &str,
+        request_schema: Option<SchemaFn>,
+        response_schema: Option<SchemaFn>,
+        handler: HandlerFn,
+    ) -> &mut Self {
+        // 同一方法和路径只保留最后注册的处理函数
+        self.routes.retain(|route| !(route.operation.method == method && route.operation.path == path));
+        self.routes.push(Route {
+            operation: Operation {
+                method,
+                path: path.to_string(),
+                tag: tag.to_string(),
+                summary: summary.to_string(),
+                public: false,
+                request_schema,
+                response_schema,
+            },
+            segments: parse_segments(path),
+            handler,
+        });
+        self
+    }
+
+    /// 移除路由，返回是否存在
+    pub fn remove(&mut self, method: HttpMethod, path: &str) -> bool {
+        let before = self.routes.len();
+        self.routes.retain(|route| !(route.operation.method == method && route.operation.path == path));
+        self.routes.len() != before
+    }
+
+    /// 合并另一个路由表（同一方法和路径以other为准）
+    pub fn merge(&mut self, other: ApiRouter) {
+        for route in other.routes {
+            self.routes.retain(|existing| {
+                !(existing.operation.method == route.operation.method && existing.operation.path == route.operation.path)
+            });
+            self.routes.push(route);
+        }
+    }
+
+    /// 查找路由（字面段优先于参数段）
+    pub fn find(&self, method: HttpMethod, path: &str) -> RouteMatch {
+        let mut candidates: Vec<(&Route, HashMap<String, String>)> = self.routes.iter()
+            .filter_map(|route| route.match_path(path).map(|params| (route, params)))
+            .collect();
+        if candidates.is_empty() {
+            return RouteMatch::NotFound;
+        }
+        candidates.sort_by_key(|(_, params)| params.len());
+        match candidates.into_iter().find(|(route, _)| route.operation.method == method) {
+            Some((route, params)) => RouteMatch::Found {
+                handler: route.handler.clone(),
+                params,
+                public: route.operation.public,
+            },
+            None => RouteMatch::MethodNotAllowed,
+        }
+    }
+
+    /// 已注册的操作
+    pub fn operations(&self) -> Vec<&Operation> {
+        self.routes.iter().map(|route| &route.operation).collect()
+    }
+
+    /// 生成OpenAPI 3.0文档，security_schemes为（名称, 方案）列表，任一方案通过即可访问非公开路由
+    pub fn openapi(&self, title: &str, version: &str, security_schemes: &[(&str, serde_json::Value)]) -> serde_json::Value {
+        let mut generator = SchemaSettings::openapi3().into_generator();
+        let error_schema = generator.subschema_for::<ErrorBody>();
+        let mut paths = serde_json::Map::new();
+
+        for route in &self.routes {
+            let operation = &route.operation;
+            let parameters: Vec<serde_json::Value> = route.segments.iter()
+                .filter_map(|segment| match segment {
+                    Segment::Param(name) => Some(serde_json::json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                    })),
+                    Segment::Literal(_) => None,
+                })
+                .collect();
+
+            let mut success = serde_json::json!({ "description": "成功" });
+            if let Some(schema) = operation.response_schema {
+                success["content"] = serde_json::json!({ JSON: { "schema": schema(&mut generator) } });
+            }
+            let mut entry = serde_json::json!({
+                "operationId": operation.operation_id(),
+                "summary": operation.summary,
+                "tags": [operation.tag],
+                "responses": {
+                    "200": success,
+                    "default": {
+                        "description": "错误",
+                        "content": { JSON: { "schema": error_schema } },
+                    },
+                },
+            });
+            if !parameters.is_empty() {
+                entry["parameters"] = serde_json::Value::Array(parameters);
+            }
+            if let Some(schema) = operation.request_schema {
+                entry["requestBody"] = serde_json::json!({
+                    "required": true,
+                    "content": { JSON: { "schema": schema(&mut generator) } },
+                });
+            }
+            if operation.public && !security_schemes.is_empty() {
+                entry["security"] = serde_json::json!([]);
+            }
+
+            let methods = paths.entry(operation.path.clone())
+                .or_insert_with(|| serde_json::json!({}));
+            methods[operation.method.as_str().to_ascii_lowercase()] = entry;
+        }
+
+        let mut document = serde_json::json!({
+            "openapi": "3.0.3",
+            "info": { "title": title, "version": version },
+            "paths": paths,
+            "components": { "schemas": generator.take_definitions(true) },
+        });
+        if !security_schemes.is_empty() {
+            let mut schemes = serde_json::Map::new();
+            let mut requirements = Vec::new();
+            for (name, scheme) in security_schemes {
+                schemes.insert(name.to_string(), scheme.clone());
+                requirements.push(serde_json::json!({ *name: [] }));
+            }
+            document["components"]["securitySchemes"] = serde_json::Value::Object(schemes);
+            document["security"] = serde_json::Value::Array(requirements);
+        }
+        document
+    }
+}
+
+/// 自定义路由注册表，启动前后都可增删路由，运行中的服务立即生效
+#[derive(Clone, Default)]
+pub struct RouteRegistry {
+    router: Arc<RwLock<ApiRouter>>,
+}
+
+impl RouteRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在写锁内注册路由
+    ///
+    /// ```ignore
+    /// api.routes().register(|router| {
+    ///     router.handle(HttpMethod::Get, "/diap/api/echo/{text}", "custom", "回显", |request| async move {
+    ///         Ok(request.param("text").unwrap_or_default().to_string())
+    ///     });
+    /// });
+    /// ```
+    pub fn register<F: FnOnce(&mut ApiRouter)>(&self, f: F) {
+        f(&mut self.router.write().unwrap());
+    }
+
+    /// 移除路由，返回是否存在
+    pub fn remove(&self, method: HttpMethod, path: &str) -> bool {
+        self.router.write().unwrap().remove(method, path)
+    }
+
+    /// 已注册路由的（方法, 路径）列表
+    pub fn list(&self) -> Vec<(HttpMethod, String)> {
+        self.router.read().unwrap().operations().into_iter()
+            .map(|operation| (operation.method, operation.path.clone()))
+            .collect()
+    }
+
+    fn find(&self, method: HttpMethod, path: &str) -> RouteMatch {
+        self.router.read().unwrap().find(method, path)
+    }
+
+    fn snapshot(&self) -> ApiRouter {
+        self.router.read().unwrap().clone()
+    }
+}
+
+// ============ 请求/响应类型 ============
+
+/// 服务端点
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ServiceEndpoint {
+    /// 服务类型
+    pub service_type: String,
+
+    /// 端点（字符串或对象）
+    pub endpoint: serde_json::Value,
+}
+
+/// 创建身份
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateIdentityRequest {
+    /// 智能体名称
+    pub name: String,
+
+    /// 描述
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// 服务端点
+    #[serde(default)]
+    pub services: Vec<ServiceEndpoint>,
+
+    /// 创建后立即发布DID文档到IPFS
+    #[serde(default)]
+    pub publish: bool,
+}
+
+/// 更新身份
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateIdentityRequest {
+    /// 新名称
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// 新描述
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// 新服务端点（替换原列表）
+    #[serde(default)]
+    pub services: Option<Vec<ServiceEndpoint>>,
+
+    /// 更新后重新发布DID文档
+    #[serde(default)]
+    pub publish: bool,
+}
+
+/// 身份资源
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IdentityResource {
+    /// DID
+    pub did: String,
+
+    /// 智能体名称
+    pub name: String,
+
+    /// 描述
+    pub description: Option<String>,
+
+    /// 服务端点
+    pub services: Vec<ServiceEndpoint>,
+
+    /// 公钥（base64）
+    pub public_key: String,
+
+    /// 已发布DID文档的CID
+    pub cid: Option<String>,
+
+    /// 发布时间
+    pub registered_at: Option<String>,
+}
+
+/// 删除结果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeletedResource {
+    /// 被删除的DID
+    pub did: String,
+}
+
+/// 验证DID-CID绑定证明
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VerifyProofRequest {
+    /// DID文档CID
+    pub cid: String,
+
+    /// 证明（base64）
+    pub proof: String,
+
+    /// 生成证明时使用的nonce（base64）
+    pub nonce: String,
+}
+
+/// 证明验证结果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VerifyProofResponse {
+    /// DID
+    pub did: String,
+
+    /// CID
+    pub cid: String,
+
+    /// 是否通过
+    pub verified: bool,
+
+    /// DID文档尚不可获取
+    pub pending: bool,
+
+    /// 超出延迟预算的临时结果
+    pub provisional: bool,
+
+    /// 验证细节
+    pub details: Vec<String>,
+
+    /// 验证时间
+    pub verified_at: String,
+}
+
+/// 消息内容编码
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentEncoding {
+    /// UTF-8文本
+    #[default]
+    Utf8,
+
+    /// base64编码的二进制
+    Base64,
+}
+
+/// 发送消息
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SendMessageRequest {
+    /// 主题
+    pub topic: String,
+
+    /// 消息内容
+    pub content: String,
+
+    /// 内容编码
+    #[serde(default)]
+    pub encoding: ContentEncoding,
+
+    /// 自定义消息类型（缺省为custom）
+    #[serde(default)]
+    pub message_type: Option<String>,
+
+    /// 接收者DID（缺省为广播）
+    #[serde(default)]
+    pub to_did: Option<String>,
+}
+
+/// 消息发送结果
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SendMessageResponse {
+    /// 消息ID
+    pub message_id: String,
+
+    /// 主题
+    pub topic: String,
+
+    /// 发送者DID
+    pub from_did: String,
+
+    /// 时间戳（秒）
+    pub timestamp: u64,
+}
+
+/// 对端
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PeerResource {
+    /// PeerID
+    pub peer_id: String,
+
+    /// DID（身份交换后已知）
+    pub did: Option<String>,
+
+    /// 连接状态
+    pub state: PeerState,
+
+    /// 往返延迟（毫秒）
+    pub latency_ms: Option<u64>,
+
+    /// 最后活跃时间（毫秒）
+    pub last_seen: Option<u64>,
+
+    /// 信任分（已识别DID且配置了信誉存储时）
+    pub trust_score: Option<f64>,
+}
+
+/// 健康检查
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HealthResponse {
+    /// 状态
+    pub status: String,
+
+    /// SDK版本
+    pub version: String,
+}
+
+// ============ 内置处理函数 ============
+
+/// 接口托管的身份
+struct ManagedIdentity {
+    keypair: KeyPair,
+    agent_info: AgentInfo,
+    registration: Option<IdentityRegistration>,
+}
+
+impl ManagedIdentity {
+    fn resource(&self) -> IdentityResource {
+        IdentityResource {
+            did: self.keypair.did.clone(),
+            name: self.agent_info.name.clone(),
+            description: self.agent_info.description.clone(),
+            services: self.agent_info.services.iter()
+                .map(|service| ServiceEndpoint { service_type: service.service_type.clone(), endpoint: service.endpoint.clone() })
+                .collect(),
+            public_key: general_purpose::STANDARD.encode(self.keypair.public_key),
+            cid: self.registration.as_ref().map(|registration| registration.cid.clone()),
+            registered_at: self.registration.as_ref().map(|registration| registration.registered_at.clone()),
+        }
+    }
+}
+
+fn service_infos(services: Vec<ServiceEndpoint>) -> Vec<ServiceInfo> {
+    services.into_iter()
+        .map(|service| ServiceInfo { service_type: service.service_type, endpoint: service.endpoint })
+        .collect()
+}
+
+/// 内置处理函数共享的状态
+struct ApiState {
+    identity_manager: IdentityManager,
+    identities: Mutex<HashMap<String, ManagedIdentity>>,
+    authenticator: Option<Arc<PubsubAuthenticator>>,
+    connection_manager: Option<ConnectionManager>,
+    publisher: Option<PublishFn>,
+}
+
+impl ApiState {
+    fn identity(&self, did: &str) -> Result<IdentityResource, ApiError> {
+        self.identities.lock().unwrap().get(did)
+            .map(ManagedIdentity::resource)
+            .ok_or_else(|| ApiError::not_found(format!("身份不存在: {}", did)))
+    }
+
+    async fn publish(&self, keypair: &KeyPair, agent_info: &AgentInfo) -> Result<IdentityRegistration> {
+        let peer_id = *LibP2PIdentity::from_did_keypair(keypair)?.peer_id();
+        self.identity_manager.register_identity(agent_info, keypair, &peer_id).await
+            .context("发布DID文档失败")
+    }
+
+    async fn create_identity(&self, request: CreateIdentityRequest) -> Result<IdentityResource, ApiError> {
+        if request.name.trim().is_empty() {
+            return Err(ApiError::bad_request("name不能为空"));
+        }
+        let keypair = KeyPair::generate()?;
+        let agent_info = AgentInfo {
+            name: request.name,
+            services: service_infos(request.services),
+            description: request.description,
+            tags: None,
+        };
+        let registration = if request.publish {
+            Some(self.publish(&keypair, &agent_info).await?)
+        } else {
+            None
+        };
+
+        let identity = ManagedIdentity { keypair, agent_info, registration };
+        let resource = identity.resource();
+        log::info!("🆔 REST接口创建身份: {}", resource.did);
+        self.identities.lock().unwrap().insert(resource.did.clone(), identity);
+        Ok(resource)
+    }
+
+    async fn update_identity(&self, did: &str, request: UpdateIdentityRequest) -> Result<IdentityResource, ApiError> {
+        let (keypair, mut agent_info) = {
+            let identities = self.identities.lock().unwrap();
+            let identity = identities.get(did)
+                .ok_or_else(|| ApiError::not_found(format!("身份不存在: {}", did)))?;
+            (identity.keypair.clone(), identity.agent_info.clone())
+        };
+        if let Some(name) = request.name {
+            agent_info.name = name;
+        }
+        if request.description.is_some() {
+            agent_info.description = request.description;
+        }
+        if let Some(services) = request.services {
+            agent_info.services = service_infos(services);
+        }
+        let registration = if request.publish {
+            Some(self.publish(&keypair, &agent_info).await?)
+        } else {
+            None
+        };
+
+        let mut identities = self.identities.lock().unwrap();
+        let identity = identities.get_mut(did)
+            .ok_or_else(|| ApiError::not_found(format!("身份不存在: {}", did)))?;
+        identity.agent_info = agent_info;
+        if registration.is_some() {
+            identity.registration = registration;
+        }
+        Ok(identity.resource())
+    }
+
+    async fn verify_proof(&self, request: VerifyProofRequest) -> Result<VerifyProofResponse, ApiError> {
+        let decode = |field: &str, value: &str| general_purpose::STANDARD.decode(value)
+            .map_err(|e| ApiError::bad_request(format!("{}不是有效的base64: {}", field, e)));
+        let proof = decode("proof", &request.proof)?;
+        let nonce = decode("nonce", &request.nonce)?;
+
+        let verification: IdentityVerification = self.identity_manager
+            .verify_identity_with_zkp(&request.cid, &proof, &nonce).await?;
+        Ok(VerifyProofResponse {
+            did: verification.did,
+            cid: verification.cid,
+            verified: verification.zkp_verified,
+            pending: verification.pending,
+            provisional: verification.provisional,
+            details: verification.verification_details,
+            verified_at: verification.verified_at,
+        })
+    }
+
+    async fn send_message(&self, request: SendMessageRequest) -> Result<SendMessageResponse, ApiError> {
+        let authenticator = self.authenticator.as_ref()
+            .ok_or_else(|| ApiError::unavailable("未配置消息认证器"))?;
+        let publisher = self.publisher.as_ref()
+            .ok_or_else(|| ApiError::unavailable("未配置消息发布通道"))?;
+        let content = match request.encoding {
+            ContentEncoding::Utf8 => request.content.into_bytes(),
+            ContentEncoding::Base64 => general_purpose::STANDARD.decode(&request.content)
+                .map_err(|e| ApiError::bad_request(format!("content不是有效的base64: {}", e)))?,
+        };
+        let message_type = PubSubMessageType::Custom(request.message_type.unwrap_or_else(|| "custom".to_string()));
+
+        let message = authenticator
+            .create_authenticated_message(&request.topic, message_type, &content, request.to_did)
+            .await
+            .map_err(|e| ApiError::new(409, format!("创建消息失败: {:#}", e)))?;
+        let response = SendMessageResponse {
+            message_id: message.message_id.clone(),
+            topic: message.topic.clone(),
+            from_did: message.from_did.clone(),
+            timestamp: message.timestamp,
+        };
+        publisher(message).await.map_err(|e| ApiError::new(502, format!("发布消息失败: {:#}", e)))?;
+        Ok(response)
+    }
+
+    fn peers(&self) -> Result<Vec<PeerResource>, ApiError> {
+        let manager = self.connection_manager.as_ref()
+            .ok_or_else(|| ApiError::unavailable("未配置连接管理器"))?;
+        let reputation = self.authenticator.as_ref().map(|authenticator| authenticator.reputation());
+        Ok(manager.known_peers().into_iter()
+            .map(|peer| PeerResource {
+                peer_id: peer.peer_id.to_base58(),
+                trust_score: reputation.zip(peer.did.as_deref()).map(|(reputation, did)| reputation.trust_score(did)),
+                did: peer.did,
+                state: peer.state,
+                latency_ms: peer.latency.map(|latency| latency.as_millis() as u64),
+                last_seen: peer.last_seen,
+            })
+            .collect())
+    }
+}
+
+fn builtin_routes(state: Arc<ApiState>) -> ApiRouter {
+    let mut router = ApiRouter::new();
+    let base = network_params().api_base_path();
+    let path = |suffix: &str| format!("{}{}", base, suffix);
+
+    router.handle(HttpMethod::Get, &path("/health"), "system", "健康检查", |_| async {
+        Ok(HealthResponse { status: "ok".to_string(), version: crate::VERSION.to_string() })
+    }).public();
+
+    let s = state.clone();
+    router.handle(HttpMethod::Get, &path("/identities"), "identities", "列出托管的身份", move |_| {
+        let s = s.clone();
+        async move {
+            let mut identities: Vec<IdentityResource> = s.identities.lock().unwrap().values().map(ManagedIdentity::resource).collect();
+            identities.sort_by(|a, b| a.did.cmp(&b.did));
+            Ok(identities)
+        }
+    });
+
+    let s = state.clone();
+    router.handle_json(HttpMethod::Post, &path("/identities"), "identities", "创建身份（可选立即发布DID文档）", move |_, body: CreateIdentityRequest| {
+        let s = s.clone();
+        async move { s.create_identity(body).await }
+    });
+
+    let s = state.clone();
+    router.handle(HttpMethod::Get, &path("/identities/{did}"), "identities", "查询身份", move |request| {
+        let s = s.clone();
+        async move { s.identity(request.param("did").unwrap_or_default()) }
+    });
+
+    let s = state.clone();
+    router.handle_json(HttpMethod::Put, &path("/identities/{did}"), "identities", "更新身份（可选重新发布DID文档）", move |request, body: UpdateIdentityRequest| {
+        let s = s.clone();
+        async move { s.update_identity(request.param("did").unwrap_or_default(), body).await }
+    });
+
+    let s = state.clone();
+    router.handle(HttpMethod::Delete, &path("/identities/{did}"), "identities", "删除托管的身份", move |request| {
+        let s = s.clone();
+        async move {
+            let did = request.param("did").unwrap_or_default().to_string();
+            match s.identities.lock().unwrap().remove(&did) {
+                Some(_) => Ok(DeletedResource { did }),
+                None => Err(ApiError::not_found(format!("身份不存在: {}", did))),
+            }
+        }
+    });
+
+    let s = state.clone();
+    router.handle_json(HttpMethod::Post, &path("/proofs/verify"), "verification", "验证DID-CID绑定证明", move |_, body: VerifyProofRequest| {
+        let s = s.clone();
+        async move { s.verify_proof(body).await }
+    });
+
+    let s = state.clone();
+    router.handle_json(HttpMethod::Post, &path("/messages"), "messaging", "签名并发布消息", move |_, body: SendMessageRequest| {
+        let s = s.clone();
+        async move { s.send_message(body).await }
+    });
+
+    let s = state;
+    router.handle(HttpMethod::Get, &path("/peers"), "network", "列出已知对端", move |_| {
+        let s = s.clone();
+        async move { s.peers() }
+    });
+
+    router.handle_raw(HttpMethod::Get, "/metrics", "system", "Prometheus指标", Arc::new(|_| {
+        Box::pin(async {
+            ApiResponse::raw(200, crate::metrics::PROMETHEUS_CONTENT_TYPE, crate::metrics::global().render_prometheus())
+        })
+    }));
+
+    router
+}
+
+// ============ 服务 ============
+
+/// REST接口服务
+pub struct RestApi {
+    identity_manager: IdentityManager,
+    authenticator: Option<Arc<PubsubAuthenticator>>,
+    connection_manager: Option<ConnectionManager>,
+    publisher: Option<PublishFn>,
+    routes: RouteRegistry,
+    token: Option<String>,
+    did_auth: Option<DidRequestVerifier>,
+    authorized_dids: HashSet<String>,
+    did_auth_replaces_token: bool,
+    max_body: usize,
+    #[cfg(feature = "tls")]
+    tls: Option<crate::tls::TlsAcceptor>,
+}
+
+impl RestApi {
+    /// 创建REST接口（身份发布和证明验证使用该身份管理器）
+    pub fn new(identity_manager: IdentityManager) -> Self {
+        Self {
+            identity_manager,
+            authenticator: None,
+            connection_manager: None,
+            publisher: None,
+            routes: RouteRegistry::new(),
+            token: None,
+            did_auth: None,
+            authorized_dids: HashSet::new(),
+            did_auth_replaces_token: false,
+            max_body: DEFAULT_MAX_BODY,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// 消息发送使用的认证器（本地身份即发送者）
+    pub fn with_authenticator(mut self, authenticator: Arc<PubsubAuthenticator>) -> Self {
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    /// 对端列表来源
+    pub fn with_connection_manager(mut self, connection_manager: ConnectionManager) -> Self {
+        self.connection_manager = Some(connection_manager);
+        self
+    }
+
+    /// 消息发布通道
+    pub fn with_publisher(mut self, publisher: PublishFn) -> Self {
+        self.publisher = Some(publisher);
+        self
+    }
+
+    /// 要求访问令牌（请求头 `Authorization: Bearer <token>`）
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// 接受DID签名的请求（`Authorization: DIDWba ...`），只放行 `with_authorized_did` 授权的DID；
+    /// 处理函数可从 `ApiRequest::caller` 取得调用方DID。配置了访问令牌时仍要求令牌，见 `with_did_auth_replacing_token`
+    pub fn with_did_auth(mut self, verifier: DidRequestVerifier) -> Self {
+        self.did_auth = Some(verifier);
+        self
+    }
+
+    /// 授权DID调用接口（内置路由可管理身份、以本节点身份签名发布消息）
+    pub fn with_authorized_did(mut self, did: impl Into<String>) -> Self {
+        self.authorized_dids.insert(did.into());
+        self
+    }
+
+    /// 显式允许授权DID的DIDWba签名代替访问令牌（默认配置了令牌时DIDWba请求被拒绝）
+    pub fn with_did_auth_replacing_token(mut self) -> Self {
+        self.did_auth_replaces_token = true;
+        self
+    }
+
+    /// 请求体上限
+    pub fn with_max_body(mut self, max_body: usize) -> Self {
+        self.max_body = max_body;
+        self
+    }
+
+    /// 启用HTTPS（见 `TlsIdentity::acceptor`）
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, acceptor: crate::tls::TlsAcceptor) -> Self {
+        self.tls = Some(acceptor);
+        self
+    }
+
+    fn scheme(&self) -> &'static str {
+        #[cfg(feature = "tls")]
+        if self.tls.is_some() {
+            return "https";
+        }
+        "http"
+    }
+
+    /// 自定义路由注册表（可在启动后继续使用；与内置路由同路径时覆盖内置路由）
+    pub fn routes(&self) -> RouteRegistry {
+        self.routes.clone()
+    }
+
+    fn builtin_router(&self) -> ApiRouter {
+        let state = Arc::new(ApiState {
+            identity_manager: self.identity_manager.clone(),
+            identities: Mutex::new(HashMap::new()),
+            authenticator: self.authenticator.clone(),
+            connection_manager: self.connection_manager.clone(),
+            publisher: self.publisher.clone(),
+        });
+        builtin_routes(state)
+    }
+
+    /// OpenAPI文档（包含当前已注册的自定义路由）
+    pub fn openapi(&self) -> serde_json::Value {
+        let mut router = self.builtin_router();
+        router.merge(self.routes.snapshot());
+        openapi_document(&router, self.token.is_some(), self.did_auth.is_some())
+    }
+
+    /// 绑定地址并在后台处理请求，返回实际监听地址
+    pub async fn bind(self, addr: &str) -> Result<(SocketAddr, tokio::task::JoinHandle<()>)> {
+        let listener = TcpListener::bind(addr).await
+            .with_context(|| format!("无法绑定REST接口地址: {}", addr))?;
+        let local_addr = listener.local_addr()?;
+        let did_auth_enabled = self.did_auth.is_some() && !self.authorized_dids.is_empty();
+        if self.token.is_none() && !did_auth_enabled && !local_addr.ip().is_loopback() {
+            log::warn!("⚠️ REST接口绑定在非回环地址上且未启用认证: {}", local_addr);
+        }
+        if self.token.is_some() && self.did_auth.is_some() && !self.did_auth_replaces_token {
+            log::warn!("⚠️ REST接口已配置访问令牌，DIDWba请求将被拒绝（见with_did_auth_replacing_token）");
+        }
+
+        log::info!("🌐 REST接口已启动: {}://{}{}", self.scheme(), local_addr, network_params().openapi_path());
+        #[cfg(feature = "tls")]
+        let tls = self.tls.clone();
+        let server = Arc::new(RestServer {
+            builtin: self.builtin_router(),
+            routes: self.routes,
+            token: self.token,
+            did_auth: self.did_auth,
+            authorized_dids: self.authorized_dids,
+            did_auth_replaces_token: self.did_auth_replaces_token,
+            max_body: self.max_body,
+        });
+        let limiter = connection_limiter();
+        let handle = tokio::spawn(async move {
+            loop {
+                let Ok(permit) = limiter.clone().acquire_owned().await else {
+                    break;
+                };
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        log::warn!("REST接口接受连接失败: {}", e);
+                        continue;
+                    }
+                };
+                let server = server.clone();
+                #[cfg(feature = "tls")]
+                let tls = tls.clone();
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    #[cfg(feature = "tls")]
+                    let result = match tls {
+                        Some(acceptor) => match acceptor.accept(stream).await {
+                            Ok(stream) => server.handle_connection(stream).await,
+                            Err(e) => Err(anyhow::anyhow!("TLS握手失败: {}", e)),
+                        },
+                        None => server.handle_connection(stream).await,
+                    };
+                    #[cfg(not(feature = "tls"))]
+                    let result = server.handle_connection(stream).await;
+                    if let Err(e) = result {
+                        log::debug!("REST请求处理失败 {}: {}", peer, e);
+                    }
+                });
+            }
+        });
+        Ok((local_addr, handle))
+    }
+}
+
+fn openapi_document(router: &ApiRouter, bearer_auth: bool, did_auth: bool) -> serde_json::Value {
+    let mut schemes = Vec::new();
+    if bearer_auth {
+        schemes.push(("bearerAuth", serde_json::json!({ "type": "http", "scheme": "bearer" })));
+    }
+    if did_auth {
+        schemes.push(("didWba", serde_json::json!({
+            "type": "http",
+            "scheme": DIDWBA_SCHEME,
+            "description": "Authorization: DIDWba did=\"...\", nonce=\"...\", signature=\"...\"",
+        })));
+    }
+    let mut document = router.openapi("DIAP Agent API", crate::VERSION, &schemes);
+    document["paths"][network_params().openapi_path()] = serde_json::json!({
+        "get": {
+            "operationId": "get_openapi",
+            "summary": "OpenAPI文档",
+            "tags": ["system"],
+            "security": [],
+            "responses": { "200": { "description": "OpenAPI 3.0文档" } },
+        }
+    });
+    document
+}
+
+/// 运行中的REST服务
+struct RestServer {
+    builtin: ApiRouter,
+    routes: RouteRegistry,
+    token: Option<String>,
+    did_auth: Option<DidRequestVerifier>,
+    authorized_dids: HashSet<String>,
+    did_auth_replaces_token: bool,
+    max_body: usize,
+}
+
+impl RestServer {
+    async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(&self, mut stream: S) -> Result<()> {
+        let response = match read_request(&mut stream, self.max_body).await {
+            Ok(request) => self.dispatch(request).await,
+            Err(e) => match e.status() {
+                Some(status) => ApiError::new(status, e.to_string()).into(),
+                None => return Err(e.into()),
+            },
+        };
+        write_response(&mut stream, response.status, &response.content_type, &response.body).await
+    }
+
+    async fn dispatch(&self, request: HttpRequest) -> ApiResponse {
+        let Some(method) = HttpMethod::parse(&request.method) else {
+            return ApiError::new(405, "不支持的请求方法").into();
+        };
+        if method == HttpMethod::Get && request.path == network_params().openapi_path() {
+            let mut router = self.builtin.clone();
+            router.merge(self.routes.snapshot());
+            return ApiResponse::json(200, &openapi_document(&router, self.token.is_some(), self.did_auth.is_some()));
+        }
+
+        match self.find(method, &request.path) {
+            RouteMatch::Found { handler, params, public } => {
+                let caller = match self.authenticate(method, &request) {
+                    Ok(caller) => caller,
+                    Err(_) if public => None,
+                    Err(error) => return error.into(),
+                };
+                handler(ApiRequest::from_http(method, request, params, caller)).await
+            }
+            RouteMatch::MethodNotAllowed => ApiError::new(405, "该路径不支持此方法").into(),
+            RouteMatch::NotFound => ApiError::not_found("路径不存在").into(),
+        }
+    }
+
+    /// 自定义路由优先，其次内置路由
+    fn find(&self, method: HttpMethod, path: &str) -> RouteMatch {
+        match self.routes.find(method, path) {
+            found @ RouteMatch::Found { .. } => found,
+            custom => match (self.builtin.find(method, path), custom) {
+                (RouteMatch::NotFound, RouteMatch::MethodNotAllowed) => RouteMatch::MethodNotAllowed,
+                (builtin, _) => builtin,
+            },
+        }
+    }
+
+    /// 认证请求：访问令牌，或授权DID的DIDWba签名（配置了令牌时需显式允许）；两者都未配置时不认证
+    fn authenticate(&self, method: HttpMethod, request: &HttpRequest) -> Result<Option<AuthenticatedCaller>, ApiError> {
+        let authorization = request.header("authorization").unwrap_or_default();
+        if authorization.starts_with(DIDWBA_SCHEME) {
+            let verifier = self.did_auth.as_ref()
+                .ok_or_else(|| ApiError::unauthorized("未启用DIDWba认证"))?;
+            if self.token.is_some() && !self.did_auth_replaces_token {
+                return Err(ApiError::unauthorized("需要访问令牌，DIDWba签名不能代替令牌"));
+            }
+            let path = match request.query.is_empty() {
+                true => request.path.clone(),
+                false => format!("{}?{}", request.path, request.query),
+            };
+            let caller = verifier.verify(authorization, method.as_str(), &path, &request.body)
+                .map_err(|e| ApiError::unauthorized(format!("DIDWba认证失败: {}", e)))?;
+            if !self.authorized_dids.contains(&caller.did) {
+                log::warn!("🚫 未授权的DID调用REST接口: {}", caller.did);
+                return Err(ApiError::forbidden(format!("DID未授权: {}", caller.did)));
+            }
+            return Ok(Some(caller));
+        }
+
+        match &self.token {
+            Some(expected) => {
+                let valid = authorization.strip_prefix("Bearer ")
+                    .is_some_and(|token| constant_time_eq(token.as_bytes(), expected.as_bytes()));
+                if valid { Ok(None) } else { Err(ApiError::unauthorized("缺少或错误的访问令牌")) }
+            }
+            None if self.did_auth.is_some() => Err(ApiError::unauthorized("需要DIDWba认证")),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipfs_client::IpfsClient;
+    use crate::key_manager::CallbackSigner;
+    use libp2p::PeerId;
+
+    fn client() -> reqwest::Client {
+        reqwest::Client::builder().no_proxy().build().unwrap()
+    }
+
+    #[test]
+    fn test_router_matching_and_openapi() {
+        let mut router = ApiRouter::new();
+        router.handle(HttpMethod::Get, "/diap/api/items/{id}", "items", "查询", |request| async move {
+            Ok(DeletedResource { did: request.param("id").unwrap().to_string() })
+        });
+        router.handle(HttpMethod::Get, "/diap/api/items/latest", "items", "最新", |_| async {
+            Ok(HealthResponse { status: "latest".to_string(), version: String::new() })
+        }).public();
+
+        assert!(matches!(router.find(HttpMethod::Get, "/diap/api/items/latest"), RouteMatch::Found { public: true, .. }));
+        match router.find(HttpMethod::Get, "/diap/api/items/42") {
+            RouteMatch::Found { params, public, .. } => {
+                assert_eq!(params["id"], "42");
+                assert!(!public);
+            }
+            _ => panic!("应匹配参数路由"),
+        }
+        assert!(matches!(router.find(HttpMethod::Delete, "/diap/api/items/42"), RouteMatch::MethodNotAllowed));
+        assert!(matches!(router.find(HttpMethod::Get, "/diap/api/other"), RouteMatch::NotFound));
+
+        let spec = router.openapi("t", "1", &[("bearerAuth", serde_json::json!({ "type": "http", "scheme": "bearer" }))]);
+        let operation = &spec["paths"]["/diap/api/items/{id}"]["get"];
+        assert_eq!(operation["operationId"], "get_items_id");
+        assert_eq!(operation["parameters"][0]["name"], "id");
+        assert_eq!(operation["responses"]["200"]["content"][JSON]["schema"]["$ref"], "#/components/schemas/DeletedResource");
+        assert!(spec["components"]["schemas"]["ErrorBody"].is_object());
+        assert_eq!(spec["paths"]["/diap/api/items/latest"]["get"]["security"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_rest_api_end_to_end() {
+        let identity_manager = IdentityManager::new(IpfsClient::new_public_only(5));
+
+        // 回调签名器不生成ZKP证明，创建消息不需要访问IPFS
+        let keypair = KeyPair::generate().unwrap();
+        let sender_did = keypair.did.clone();
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+        let authenticator = Arc::new(PubsubAuthenticator::new(identity_manager.clone(), None, None));
+        authenticator.set_local_signer(Arc::new(signer), PeerId::random(), "cid".to_string()).await.unwrap();
+
+        let connection_manager = ConnectionManager::new();
+        connection_manager.on_connected(PeerId::random(), None);
+
+        let published = Arc::new(Mutex::new(Vec::new()));
+        let sink = published.clone();
+        let publisher: PublishFn = Arc::new(move |message| {
+            sink.lock().unwrap().push(message);
+            Box::pin(async { Ok(()) })
+        });
+
+        let api = RestApi::new(identity_manager)
+            .with_authenticator(authenticator)
+            .with_connection_manager(connection_manager)
+            .with_publisher(publisher)
+            .with_token("secret");
+        let (addr, handle) = api.bind("127.0.0.1:0").await.unwrap();
+        let url = |path: &str| format!("http://{}{}{}", addr, API_BASE_PATH, path);
+        let http = client();
+
+        // 健康检查和OpenAPI文档无需令牌
+        assert_eq!(http.get(url("/health")).send().await.unwrap().status(), 200);
+        let spec: serde_json::Value = http.get(format!("http://{}{}", addr, OPENAPI_PATH)).send().await.unwrap().json().await.unwrap();
+        assert!(spec["paths"]["/diap/api/identities/{did}"]["put"]["requestBody"].is_object());
+        assert!(spec["components"]["schemas"]["CreateIdentityRequest"].is_object());
+        assert_eq!(http.get(url("/identities")).send().await.unwrap().status(), 401);
+
+        // 身份增删改查
+        let created: IdentityResource = http.post(url("/identities")).bearer_auth("secret")
+            .json(&serde_json::json!({ "name": "alice", "services": [{ "service_type": "messaging", "endpoint": "https://a.example" }] }))
+            .send().await.unwrap().json().await.unwrap();
+        assert!(created.did.starts_with("did:key:"));
+        assert!(created.cid.is_none());
+
+        let fetched: IdentityResource = http.get(url(&format!("/identities/{}", created.did))).bearer_auth("secret")
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(fetched.services[0].service_type, "messaging");
+
+        let updated: IdentityResource = http.put(url(&format!("/identities/{}", created.did))).bearer_auth("secret")
+            .json(&serde_json::json!({ "description": "翻译智能体" }))
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(updated.description.as_deref(), Some("翻译智能体"));
+        assert_eq!(updated.name, "alice");
+
+        let listed: Vec<IdentityResource> = http.get(url("/identities")).bearer_auth("secret").send().await.unwrap().json().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        let bad = http.post(url("/identities")).bearer_auth("secret").body("{").send().await.unwrap();
+        assert_eq!(bad.status(), 400);
+
+        assert_eq!(http.delete(url(&format!("/identities/{}", created.did))).bearer_auth("secret").send().await.unwrap().status(), 200);
+        assert_eq!(http.get(url(&format!("/identities/{}", created.did))).bearer_auth("secret").send().await.unwrap().status(), 404);
+
+        // 发送消息
+        let sent: SendMessageResponse = http.post(url("/messages")).bearer_auth("secret")
+            .json(&serde_json::json!({ "topic": "tasks", "content": "hello" }))
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(sent.from_did, sender_did);
+        assert_eq!(published.lock().unwrap()[0].content, b"hello");
+
+        // 证明参数校验、对端列表
+        let invalid = http.post(url("/proofs/verify")).bearer_auth("secret")
+            .json(&serde_json::json!({ "cid": "x", "proof": "!!", "nonce": "" }))
+            .send().await.unwrap();
+        assert_eq!(invalid.status(), 400);
+        let peers: Vec<PeerResource> = http.get(url("/peers")).bearer_auth("secret").send().await.unwrap().json().await.unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].state, PeerState::Connected);
+
+        let metrics = http.get(format!("http://{}/metrics", addr)).bearer_auth("secret").send().await.unwrap();
+        assert!(metrics.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+        assert!(metrics.text().await.unwrap().contains("# TYPE diap_peers_connected gauge\n"));
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_routes_before_and_after_startup() {
+        let api = RestApi::new(IdentityManager::new(IpfsClient::new_public_only(5)));
+        let routes = api.routes();
+        routes.register(|router| {
+            router.handle(HttpMethod::Get, "/custom/greet/{name}", "custom", "问候", |request| async move {
+                Ok(format!("hello {}", request.param("name").unwrap_or_default()))
+            });
+        });
+        let (addr, handle) = api.bind("127.0.0.1:0").await.unwrap();
+        let http = client();
+        let url = |path: &str| format!("http://{}{}", addr, path);
+
+        let greeting: String = http.get(url("/custom/greet/bob")).send().await.unwrap().json().await.unwrap();
+        assert_eq!(greeting, "hello bob");
+
+        // 启动后注册：新增POST端点并覆盖内置健康检查
+        routes.register(|router| {
+            router.handle_json(HttpMethod::Post, "/custom/sum", "custom", "求和", |_, numbers: Vec<i64>| async move {
+                Ok(numbers.iter().sum::<i64>())
+            });
+            router.handle_raw(HttpMethod::Get, "/diap/api/health", "system", "自定义健康检查", Arc::new(|_| {
+                Box::pin(async { ApiResponse::raw(200, "text/plain", "custom") })
+            }));
+        });
+        let sum: i64 = http.post(url("/custom/sum")).json(&[1, 2, 3]).send().await.unwrap().json().await.unwrap();
+        assert_eq!(sum, 6);
+        assert_eq!(http.get(url("/diap/api/health")).send().await.unwrap().text().await.unwrap(), "custom");
+        assert_eq!(http.get(url("/custom/sum")).send().await.unwrap().status(), 405);
+
+        let spec: serde_json::Value = http.get(url(OPENAPI_PATH)).send().await.unwrap().json().await.unwrap();
+        assert!(spec["paths"]["/custom/sum"]["post"]["requestBody"].is_object());
+
+        // 移除后恢复内置路由
+        assert!(routes.remove(HttpMethod::Get, "/diap/api/health"));
+        assert!(routes.remove(HttpMethod::Post, "/custom/sum"));
+        assert_eq!(http.post(url("/custom/sum")).json(&[1]).send().await.unwrap().status(), 404);
+        let health: HealthResponse = http.get(url("/diap/api/health")).send().await.unwrap().json().await.unwrap();
+        assert_eq!(health.status, "ok");
+        assert_eq!(routes.list(), vec![(HttpMethod::Get, "/custom/greet/{name}".to_string())]);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_did_signed_requests() {
+        use crate::did_cache::DIDCache;
+        use crate::did_resolver::DIDSignatureVerifier;
+        use crate::did_wba_auth::sign_request;
+        use crate::nonce_manager::NonceManager;
+
+        let verifier = DidRequestVerifier::new(DIDSignatureVerifier::new(DIDCache::new(None, None)), Arc::new(NonceManager::new(None, None)), "agent.test");
+        let keypair = KeyPair::generate().unwrap();
+        let api = RestApi::new(IdentityManager::new(IpfsClient::new_public_only(5)))
+            .with_did_auth(verifier.clone())
+            .with_authorized_did(keypair.did.clone());
+        api.routes().register(|router| {
+            router.handle_json(HttpMethod::Post, "/diap/api/whoami", "custom", "调用方DID", |request, _: serde_json::Value| async move {
+                Ok(request.caller_did().map(str::to_string))
+            });
+        });
+        let (addr, handle) = api.bind("127.0.0.1:0").await.unwrap();
+        let http = client();
+        let url = format!("http://{}/diap/api/whoami", addr);
+
+        let body = br#"{"hello":"world"}"#.to_vec();
+        let authorization = sign_request(&keypair, "agent.test", "POST", "/diap/api/whoami", &body).unwrap();
+        let send = |authorization: String, body: Vec<u8>| http.post(&url)
+            .header("Authorization", authorization)
+            .header("Content-Type", JSON)
+            .body(body)
+            .send();
+
+        let response = send(authorization.clone(), body.clone()).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let caller: Option<String> = response.json().await.unwrap();
+        assert_eq!(caller.as_deref(), Some(keypair.did.as_str()));
+
+        // 重放、篡改请求体、缺少认证
+        assert_eq!(send(authorization, body.clone()).await.unwrap().status(), 401);
+        let authorization = sign_request(&keypair, "agent.test", "POST", "/diap/api/whoami", &body).unwrap();
+        assert_eq!(send(authorization, b"{}".to_vec()).await.unwrap().status(), 401);
+        assert_eq!(http.post(&url).body("{}").send().await.unwrap().status(), 401);
+
+        // 签名有效但不在授权列表中的DID不能调用内置路由
+        let stranger = KeyPair::generate().unwrap();
+        let authorization = sign_request(&stranger, "agent.test", "POST", "/diap/api/whoami", &body).unwrap();
+        assert_eq!(send(authorization, body.clone()).await.unwrap().status(), 403);
+        let create = br#"{"name":"x"}"#.to_vec();
+        let authorization = sign_request(&stranger, "agent.test", "POST", "/diap/api/identities", &create).unwrap();
+        let response = http.post(format!("http://{}/diap/api/identities", addr))
+            .header("Authorization", authorization)
+            .body(create)
+            .send().await.unwrap();
+        assert_eq!(response.status(), 403);
+        assert_eq!(http.get(format!("http://{}/diap/api/health", addr)).send().await.unwrap().status(), 200);
+
+        let spec: serde_json::Value = http.get(format!("http://{}{}", addr, OPENAPI_PATH)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(spec["components"]["securitySchemes"]["didWba"]["scheme"], DIDWBA_SCHEME);
+        handle.abort();
+
+        // 配置了令牌时，DIDWba签名默认不能代替令牌
+        let api = RestApi::new(IdentityManager::new(IpfsClient::new_public_only(5)))
+            .with_token("secret")
+            .with_did_auth(verifier)
+            .with_authorized_did(keypair.did.clone());
+        let (addr, handle) = api.bind("127.0.0.1:0").await.unwrap();
+        let authorization = sign_request(&keypair, "agent.test", "GET", "/diap/api/identities", b"").unwrap();
+        let response = http.get(format!("http://{}/diap/api/identities", addr))
+            .header("Authorization", authorization)
+            .send().await.unwrap();
+        assert_eq!(response.status(), 401);
+        handle.abort();
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn test_https_with_self_signed_certificate() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_rustls::rustls::{ClientConfig, RootCertStore, pki_types::ServerName};
+
+        let identity = crate::tls::TlsIdentity::self_signed(&["localhost".to_string()]).unwrap();
+        let api = RestApi::new(IdentityManager::new(IpfsClient::new_public_only(5)))
+            .with_tls(identity.acceptor().unwrap());
+        let (addr, handle) = api.bind("127.0.0.1:0").await.unwrap();
+
+        // 客户端信任该自签名证书
+        let mut roots = RootCertStore::empty();
+        roots.add(identity.certificates().unwrap().remove(0)).unwrap();
+        let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions().unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+        let tcp = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut stream = connector.connect(ServerName::try_from("localhost").unwrap(), tcp).await.unwrap();
+
+        stream.write_all(b"GET /diap/api/health HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"status\":\"ok\""));
+
+        // 明文请求无法通过TLS端口
+        assert!(client().get(format!("http://{}/diap/api/health", addr)).send().await.is_err());
+        handle.abort();
+    }
+}
@@ -0,0 +1,199 @@
+// DIAP Rust SDK - 定时消息邮箱
+// 邮箱/中继持有带not_before的认证消息，到期后按投递时间顺序放出，用于多智能体协同的定时动作
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::clock::{SharedClock, system_clock};
+use crate::pubsub_authenticator::AuthenticatedMessage;
+
+/// 定时消息邮箱
+#[derive(Clone)]
+pub struct ScheduledMailbox {
+    /// (not_before, message_id) -> 消息
+    pending: Arc<Mutex<BTreeMap<(u64, String), AuthenticatedMessage>>>,
+
+    /// 时间源
+    clock: SharedClock,
+}
+
+impl ScheduledMailbox {
+    /// 创建邮箱（系统时钟）
+    pub fn new() -> Self {
+        Self::new_with_clock(system_clock())
+    }
+
+    /// 使用指定时间源创建邮箱
+    pub fn new_with_clock(clock: SharedClock) -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(BTreeMap::new())),
+            clock,
+        }
+    }
+
+    /// 接收消息：未到投递时间则持有并返回None，否则原样返回供立即投递
+    pub fn accept(&self, message: AuthenticatedMessage) -> Option<AuthenticatedMessage> {
+        match message.not_before {
+            Some(not_before) if !message.is_deliverable_at(self.clock.now_secs()) => {
+                log::debug!("⏳ 持有定时消息 {} 至 {}", message.message_id, not_before);
+                self.pending.lock().unwrap()
+                    .insert((not_before, message.message_id.clone()), message);
+                None
+            }
+            _ => Some(message),
+        }
+    }
+
+    /// 取出所有已到投递时间的消息（按投递时间排序）
+    pub fn take_due(&self) -> Vec<AuthenticatedMessage> {
+        let now = self.clock.now_secs();
+        let mut pending = self.pending.lock().unwrap();
+        let later = pending.split_off(&(now + 1, String::new()));
+        let due = std::mem::replace(&mut *pending, later);
+        due.into_values().collect()
+    }
+
+    /// 最近一条待投递消息的投递时间
+    pub fn next_due_at(&self) -> Option<u64> {
+        self.pending.lock().unwrap().keys().next().map(|(not_before, _)| *not_before)
+    }
+
+    /// 发给指定DID的待投递消息数
+    pub fn pending_for(&self, did: &str) -> usize {
+        self.pending.lock().unwrap()
+            .values()
+            .filter(|m| m.to_did.as_deref() == Some(did))
+            .count()
+    }
+
+    /// 取消一条待投递消息
+    pub fn cancel(&self, message_id: &str) -> Option<AuthenticatedMessage> {
+        let mut pending = self.pending.lock().unwrap();
+        let key = pending.keys().find(|(_, id)| id == message_id).cloned()?;
+        pending.remove(&key)
+    }
+
+    /// 待投递消息数
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// 是否没有待投递消息
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 启动后台投递任务：每隔interval检查一次，将到期消息发送到sender
+    /// 接收端关闭后任务退出
+    pub fn start_delivery(
+        &self,
+        interval: Duration,
+        sender: mpsc::Sender<AuthenticatedMessage>,
+    ) -> tokio::task::JoinHandle<()> {
+        let mailbox = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(interval);
+            loop {
+                interval_timer.tick().await;
+
+                for message in mailbox.take_due() {
+                    log::info!("📬 投递定时消息: {}", message.message_id);
+                    if sender.send(message).await.is_err() {
+                        log::debug!("定时消息接收端已关闭，停止投递");
+                        return;
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl Default for ScheduledMailbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::identity_manager::IdentityManager;
+    use crate::ipfs_client::IpfsClient;
+    use crate::key_manager::{CallbackSigner, KeyPair};
+    use crate::pubsub_authenticator::{PubSubMessageType, PubsubAuthenticator};
+    use libp2p::PeerId;
+
+    fn message(id: &str, not_before: Option<u64>) -> AuthenticatedMessage {
+        AuthenticatedMessage {
+            message_id: id.to_string(),
+            message_type: PubSubMessageType::Heartbeat,
+            from_did: "did:key:alice".to_string(),
+            to_did: Some("did:key:bob".to_string()),
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: "general".to_string(),
+            content: Vec::new(),
+            nonce: String::new(),
+            zkp_proof: Vec::new(),
+            signature: Vec::new(),
+            timestamp: 0,
+            not_before,
+            capability: None,
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_hold_until_due() {
+        let clock = MockClock::new(1_000_000);
+        let mailbox = ScheduledMailbox::new_with_clock(Arc::new(clock.clone()));
+
+        assert!(mailbox.accept(message("now", None)).is_some());
+        assert!(mailbox.accept(message("late", Some(1_000_200))).is_none());
+        assert!(mailbox.accept(message("soon", Some(1_000_100))).is_none());
+        assert_eq!(mailbox.next_due_at(), Some(1_000_100));
+        assert_eq!(mailbox.pending_for("did:key:bob"), 2);
+        assert!(mailbox.take_due().is_empty());
+
+        clock.advance(Duration::from_secs(100));
+        let due: Vec<String> = mailbox.take_due().into_iter().map(|m| m.message_id).collect();
+        assert_eq!(due, vec!["soon".to_string()]);
+
+        assert!(mailbox.cancel("late").is_some());
+        assert!(mailbox.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_receiver_enforces_not_before() {
+        let clock = MockClock::new(1_000_000);
+        let authenticator = || {
+            PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None)
+                .with_clock(Arc::new(clock.clone()))
+        };
+        let sender = authenticator();
+        // 外部签名器不生成ZKP证明，无需访问IPFS
+        let keypair = KeyPair::generate().unwrap();
+        let sign_key = keypair.clone();
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data: &[u8]| sign_key.sign(data))).unwrap();
+        sender.set_local_signer(Arc::new(signer), PeerId::random(), "bafysender".to_string()).await.unwrap();
+
+        let message = sender.create_scheduled_message(
+            "general", PubSubMessageType::Custom("launch".to_string()), b"go", None, 1_000_600,
+        ).await.unwrap();
+        assert_eq!(message.not_before, Some(1_000_600));
+
+        let receiver = authenticator();
+        let early = receiver.verify_message(&message).await.unwrap();
+        assert!(!early.verified);
+        assert!(early.details[0].contains("不可投递"));
+
+        // 篡改not_before会使签名失效
+        let mut tampered = message.clone();
+        tampered.not_before = None;
+        assert_ne!(tampered.signing_data(), message.signing_data());
+    }
+}
@@ -0,0 +1,503 @@
+// DIAP Rust SDK - 安全会话模块
+// 双向认证完成后，双方交换由DID签名的临时X25519公钥，派生出每个方向独立的对称密钥；
+// 后续流量用ChaCha20-Poly1305加密或认证，不必对每条消息都做完整的ZKP验证
+// 临时密钥只存在于会话中，会话密钥泄露不影响DID私钥，DID私钥泄露也无法解密过去的会话
+
+use anyhow::{Context, Result};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+
+use crate::clock::SharedClock;
+use crate::key_manager::{SignedEnvelope, Signer};
+
+/// 默认会话有效期
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(3600);
+
+/// 会话有效期上限（发起方请求更长时按上限计算）
+pub const MAX_SESSION_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// 会话邀请的最大有效时间
+pub const MAX_OFFER_AGE: Duration = Duration::from_secs(60);
+
+/// 会话密钥派生域分隔标签
+const SESSION_KEY_TAG: &[u8] = b"DIAP_SESSION_V1";
+
+/// 重放窗口大小（允许乱序到达的帧数）
+const REPLAY_WINDOW: u64 = 64;
+
+/// 会话邀请（发起方 -> 响应方）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionOffer {
+    /// 会话ID
+    pub session_id: String,
+
+    /// 发起方DID
+    pub initiator_did: String,
+
+    /// 响应方DID
+    pub responder_did: String,
+
+    /// 发起方临时X25519公钥
+    pub ephemeral_public: [u8; 32],
+
+    /// 创建时间（秒）
+    pub created_at: u64,
+
+    /// 会话有效期（秒，双方按MAX_SESSION_TTL截断）
+    pub ttl_secs: u64,
+
+    /// 发起方签名（base64）
+    pub signature: String,
+}
+
+impl SessionOffer {
+    /// 邀请的摘要（响应方签名时绑定，防止响应被挪用到其他邀请）
+    fn transcript_hash(&self) -> Result<[u8; 32]> {
+        Ok(Sha256::digest(serde_json::to_vec(self).context("序列化会话邀请失败")?).into())
+    }
+}
+
+impl SignedEnvelope for SessionOffer {
+    const KIND: &'static str = "会话邀请";
+
+    fn signer_did(&self) -> &str {
+        &self.initiator_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 会话响应（响应方 -> 发起方）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionAccept {
+    /// 会话ID
+    pub session_id: String,
+
+    /// 响应方DID
+    pub responder_did: String,
+
+    /// 响应方临时X25519公钥
+    pub ephemeral_public: [u8; 32],
+
+    /// 所响应邀请的摘要（hex）
+    pub offer_hash: String,
+
+    /// 响应方签名（base64）
+    pub signature: String,
+}
+
+impl SignedEnvelope for SessionAccept {
+    const KIND: &'static str = "会话响应";
+
+    fn signer_did(&self) -> &str {
+        &self.responder_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 会话中的一帧（加密数据或认证标签）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionFrame {
+    /// 会话ID
+    pub session_id: String,
+
+    /// 发送方向上的帧序号（兼作AEAD nonce）
+    pub counter: u64,
+
+    /// 密文（含认证标签）；sign生成的帧只有16字节标签
+    pub data: Vec<u8>,
+}
+
+impl SessionFrame {
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self).context("序列化会话帧失败")
+    }
+
+    /// 从字节解析
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        bincode::deserialize(data).context("解析会话帧失败")
+    }
+}
+
+/// 等待响应的会话发起方
+pub struct SessionInitiator {
+    offer: SessionOffer,
+    ephemeral: StaticSecret,
+    local_did: String,
+    clock: SharedClock,
+}
+
+impl SessionInitiator {
+    /// 创建会话邀请（发给已通过认证的对方）
+    pub fn new(signer: &dyn Signer, responder_did: &str, ttl: Duration, clock: SharedClock) -> Result<(Self, SessionOffer)> {
+        let ephemeral = StaticSecret::random_from_rng(rand::rngs::OsRng);
+        let offer = SessionOffer {
+            session_id: hex::encode(rand::random::<[u8; 16]>()),
+            initiator_did: signer.did(),
+            responder_did: responder_did.to_string(),
+            ephemeral_public: X25519PublicKey::from(&ephemeral).to_bytes(),
+            created_at: clock.now_secs(),
+            ttl_secs: ttl.min(MAX_SESSION_TTL).as_secs(),
+            signature: String::new(),
+        }
+        .signed_by(signer)?;
+
+        let initiator = Self {
+            offer: offer.clone(),
+            ephemeral,
+            local_did: signer.did(),
+            clock,
+        };
+        Ok((initiator, offer))
+    }
+
+    /// 验证响应并建立会话
+    pub fn complete(self, accept: &SessionAccept) -> Result<SecureSession> {
+        if accept.session_id != self.offer.session_id || accept.responder_did != self.offer.responder_did {
+            anyhow::bail!("会话响应与邀请不匹配");
+        }
+        if accept.offer_hash != hex::encode(self.offer.transcript_hash()?) {
+            anyhow::bail!("会话响应绑定的邀请摘要不一致");
+        }
+        accept.require_valid()?;
+
+        let shared = self.ephemeral.diffie_hellman(&X25519PublicKey::from(accept.ephemeral_public));
+        SecureSession::derive(&self.offer, accept, shared.as_bytes(), &self.local_did, true, self.clock)
+    }
+}
+
+/// 重放窗口：记录最高序号和其之前REPLAY_WINDOW帧的接收情况
+#[derive(Default)]
+struct ReplayWindow {
+    highest: Option<u64>,
+    seen: u64,
+}
+
+impl ReplayWindow {
+    fn check(&self, counter: u64) -> Result<()> {
+        match self.highest {
+            Some(highest) if counter <= highest => {
+                let offset = highest - counter;
+                if offset >= REPLAY_WINDOW {
+                    anyhow::bail!("会话帧过旧: {}", counter);
+                }
+                if self.seen & (1 << offset) != 0 {
+                    anyhow::bail!("会话帧重放: {}", counter);
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record(&mut self, counter: u64) {
+        match self.highest {
+            Some(highest) if counter <= highest => self.seen |= 1 << (highest - counter),
+            Some(highest) => {
+                let shift = counter - highest;
+                self.seen = if shift >= REPLAY_WINDOW { 0 } else { self.seen << shift };
+                self.seen |= 1;
+                self.highest = Some(counter);
+            }
+            None => {
+                self.seen = 1;
+                self.highest = Some(counter);
+            }
+        }
+    }
+}
+
+/// 已建立的安全会话
+pub struct SecureSession {
+    session_id: String,
+    local_did: String,
+    peer_did: String,
+    send_cipher: ChaCha20Poly1305,
+    recv_cipher: ChaCha20Poly1305,
+    send_counter: AtomicU64,
+    replay: Mutex<ReplayWindow>,
+    established_at: u64,
+    expires_at: u64,
+    clock: SharedClock,
+}
+
+impl SecureSession {
+    /// 响应会话邀请：验证发起方签名和时效，返回会话及发回给发起方的响应
+    pub fn accept(signer: &dyn Signer, offer: &SessionOffer, clock: SharedClock) -> Result<(Self, SessionAccept)> {
+        if offer.responder_did != signer.did() {
+            anyhow::bail!("会话邀请不是发给本节点的: {}", offer.responder_did);
+        }
+        let now = clock.now_secs();
+        if now.saturating_sub(offer.created_at) > MAX_OFFER_AGE.as_secs() || offer.created_at > now + MAX_OFFER_AGE.as_secs() {
+            anyhow::bail!("会话邀请已过期");
+        }
+        offer.require_valid()?;
+
+        let ephemeral = StaticSecret::random_from_rng(rand::rngs::OsRng);
+        let accept = SessionAccept {
+            session_id: offer.session_id.clone(),
+            responder_did: signer.did(),
+            ephemeral_public: X25519PublicKey::from(&ephemeral).to_bytes(),
+            offer_hash: hex::encode(offer.transcript_hash()?),
+            signature: String::new(),
+        }
+        .signed_by(signer)?;
+
+        let shared = ephemeral.diffie_hellman(&X25519PublicKey::from(offer.ephemeral_public));
+        let session = Self::derive(offer, &accept, shared.as_bytes(), &signer.did(), false, clock)?;
+        Ok((session, accept))
+    }
+
+    fn derive(
+        offer: &SessionOffer,
+        accept: &SessionAccept,
+        shared_secret: &[u8],
+        local_did: &str,
+        initiator: bool,
+        clock: SharedClock,
+    ) -> Result<Self> {
+        let transcript = Sha256::digest(serde_json::to_vec(&(offer, accept)).context("序列化会话握手失败")?);
+        let direction_key = |label: &[u8]| {
+            let mut hasher = Sha256::new();
+            hasher.update(SESSION_KEY_TAG);
+            hasher.update(shared_secret);
+            hasher.update(transcript);
+            hasher.update(label);
+            ChaCha20Poly1305::new(&hasher.finalize())
+        };
+        let (send_label, recv_label): (&[u8], &[u8]) = if initiator {
+            (b"initiator->responder", b"responder->initiator")
+        } else {
+            (b"responder->initiator", b"initiator->responder")
+        };
+        let peer_did = if initiator { &offer.responder_did } else { &offer.initiator_did };
+
+        let established_at = clock.now_secs();
+        log::info!("🔐 建立安全会话 {} ({} ↔ {})", offer.session_id, local_did, peer_did);
+        Ok(Self {
+            session_id: offer.session_id.clone(),
+            local_did: local_did.to_string(),
+            peer_did: peer_did.clone(),
+            send_cipher: direction_key(send_label),
+            recv_cipher: direction_key(recv_label),
+            send_counter: AtomicU64::new(0),
+            replay: Mutex::new(ReplayWindow::default()),
+            established_at,
+            // ttl_secs由发起方填写，截断并防止溢出
+            expires_at: offer.created_at.saturating_add(offer.ttl_secs.min(MAX_SESSION_TTL.as_secs())),
+            clock,
+        })
+    }
+
+    /// 会话ID
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// 本地DID
+    pub fn local_did(&self) -> &str {
+        &self.local_did
+    }
+
+    /// 对方DID
+    pub fn peer_did(&self) -> &str {
+        &self.peer_did
+    }
+
+    /// 建立时间（秒）
+    pub fn established_at(&self) -> u64 {
+        self.established_at
+    }
+
+    /// 过期时间（秒）
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    /// 会话是否已过期（过期后需要重新认证并建立会话）
+    pub fn is_expired(&self) -> bool {
+        self.clock.now_secs() >= self.expires_at
+    }
+
+    /// 加密发给对方的数据
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<SessionFrame> {
+        self.seal(plaintext, &[])
+    }
+
+    /// 解密对方发来的数据（拒绝重放和过旧的帧）
+    pub fn decrypt(&self, frame: &SessionFrame) -> Result<Vec<u8>> {
+        self.open(frame, &[])
+    }
+
+    /// 为明文数据生成会话认证标签（数据本身不加密）
+    pub fn sign(&self, data: &[u8]) -> Result<SessionFrame> {
+        self.seal(&[], data)
+    }
+
+    /// 验证对方为数据生成的会话认证标签
+    pub fn verify(&self, data: &[u8], frame: &SessionFrame) -> Result<()> {
+        self.open(frame, data).map(|_| ())
+    }
+
+    fn seal(&self, plaintext: &[u8], associated: &[u8]) -> Result<SessionFrame> {
+        if self.is_expired() {
+            anyhow::bail!("安全会话已过期: {}", self.session_id);
+        }
+        let counter = self.send_counter.fetch_add(1, Ordering::SeqCst);
+        let data = self.send_cipher
+            .encrypt(&frame_nonce(counter), Payload { msg: plaintext, aad: &self.frame_aad(associated) })
+            .map_err(|_| anyhow::anyhow!("加密会话帧失败"))?;
+        Ok(SessionFrame {
+            session_id: self.session_id.clone(),
+            counter,
+            data,
+        })
+    }
+
+    fn open(&self, frame: &SessionFrame, associated: &[u8]) -> Result<Vec<u8>> {
+        if self.is_expired() {
+            anyhow::bail!("安全会话已过期: {}", self.session_id);
+        }
+        if frame.session_id != self.session_id {
+            anyhow::bail!("会话帧不属于本会话: {}", frame.session_id);
+        }
+
+        // 先检查重放，认证通过后才记录序号，伪造的帧不会占用窗口
+        let mut replay = self.replay.lock().unwrap();
+        replay.check(frame.counter)?;
+        let plaintext = self.recv_cipher
+            .decrypt(&frame_nonce(frame.counter), Payload { msg: &frame.data, aad: &self.frame_aad(associated) })
+            .map_err(|_| anyhow::anyhow!("会话帧认证失败：密钥不匹配或内容被篡改"))?;
+        replay.record(frame.counter);
+        Ok(plaintext)
+    }
+
+    fn frame_aad(&self, associated: &[u8]) -> Vec<u8> {
+        let mut aad = self.session_id.as_bytes().to_vec();
+        aad.extend_from_slice(associated);
+        aad
+    }
+}
+
+fn frame_nonce(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    *Nonce::from_slice(&nonce)
+}
+
+/// 在同一进程中为两个已互相认证的智能体建立会话，返回(发起方会话, 响应方会话)
+pub fn establish_pair(
+    initiator: &dyn Signer,
+    responder: &dyn Signer,
+    ttl: Duration,
+    clock: SharedClock,
+) -> Result<(SecureSession, SecureSession)> {
+    let (pending, offer) = SessionInitiator::new(initiator, &responder.did(), ttl, clock.clone())?;
+    let (responder_session, accept) = SecureSession::accept(responder, &offer, clock)?;
+    Ok((pending.complete(&accept)?, responder_session))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::clock::MockClock;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_session_roundtrip() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let clock = MockClock::new(1_700_000_000);
+        let shared: SharedClock = Arc::new(clock.clone());
+
+        let (alice_session, bob_session) = establish_pair(&alice, &bob, DEFAULT_SESSION_TTL, shared).unwrap();
+        assert_eq!(alice_session.peer_did(), bob.did);
+        assert_eq!(bob_session.peer_did(), alice.did);
+
+        let frame = alice_session.encrypt(b"task: summarize").unwrap();
+        let bytes = frame.to_bytes().unwrap();
+        assert!(!bytes.windows(4).any(|w| w == b"task"));
+        assert_eq!(bob_session.decrypt(&SessionFrame::from_bytes(&bytes).unwrap()).unwrap(), b"task: summarize");
+        assert!(bob_session.decrypt(&frame).is_err(), "重放应被拒绝");
+
+        // 两个方向使用不同密钥：自己发出的帧不能被自己解密
+        let reply = bob_session.encrypt(b"ok").unwrap();
+        assert!(bob_session.decrypt(&reply).is_err());
+        assert_eq!(alice_session.decrypt(&reply).unwrap(), b"ok");
+
+        // 乱序到达的帧在窗口内仍可接受
+        let first = alice_session.encrypt(b"1").unwrap();
+        let second = alice_session.encrypt(b"2").unwrap();
+        assert_eq!(bob_session.decrypt(&second).unwrap(), b"2");
+        assert_eq!(bob_session.decrypt(&first).unwrap(), b"1");
+
+        // 认证标签
+        let tag = alice_session.sign(b"public status").unwrap();
+        assert!(bob_session.verify(b"public status", &tag).is_ok());
+        let tag = alice_session.sign(b"public status").unwrap();
+        assert!(bob_session.verify(b"tampered status", &tag).is_err());
+
+        // 会话过期后必须重新建立
+        clock.advance(DEFAULT_SESSION_TTL);
+        assert!(alice_session.is_expired());
+        assert!(alice_session.encrypt(b"late").is_err());
+    }
+
+    #[test]
+    fn test_handshake_rejects_tampering() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+        let clock: SharedClock = Arc::new(MockClock::new(1_700_000_000));
+
+        // 替换临时公钥会破坏发起方签名
+        let (_, mut offer) = SessionInitiator::new(&alice, &bob.did, DEFAULT_SESSION_TTL, clock.clone()).unwrap();
+        offer.ephemeral_public = [7u8; 32];
+        assert!(SecureSession::accept(&bob, &offer, clock.clone()).is_err());
+
+        // 不是发给自己的邀请
+        let (pending, offer) = SessionInitiator::new(&alice, &bob.did, DEFAULT_SESSION_TTL, clock.clone()).unwrap();
+        assert!(SecureSession::accept(&mallory, &offer, clock.clone()).is_err());
+
+        // 第三方冒充响应方
+        let (_, mut accept) = SecureSession::accept(&bob, &offer, clock.clone()).unwrap();
+        accept.responder_did = mallory.did.clone();
+        assert!(pending.complete(&accept).is_err());
+
+        // 过期的邀请
+        let stale_clock = MockClock::new(1_700_000_000);
+        let (_, offer) = SessionInitiator::new(&alice, &bob.did, DEFAULT_SESSION_TTL, Arc::new(stale_clock.clone())).unwrap();
+        stale_clock.advance(MAX_OFFER_AGE * 2);
+        assert!(SecureSession::accept(&bob, &offer, Arc::new(stale_clock)).is_err());
+
+        // 超长的有效期被截断
+        let (_, mut offer) = SessionInitiator::new(&alice, &bob.did, DEFAULT_SESSION_TTL, clock.clone()).unwrap();
+        offer.ttl_secs = u64::MAX;
+        let offer = offer.signed_by(&alice).unwrap();
+        let (session, _) = SecureSession::accept(&bob, &offer, clock).unwrap();
+        assert_eq!(session.expires_at(), offer.created_at + MAX_SESSION_TTL.as_secs());
+    }
+}
@@ -0,0 +1,376 @@
+// DIAP Rust SDK - 启动自检
+// 在智能体上线前跑一遍完整的内部闭环：生成密钥 -> 构建并发布DID文档（进程内模拟存储）
+// -> 生成并验证ZKP证明 -> 身份验证 -> 签名消息收发，逐步记录耗时和失败原因，
+// 用于在上线前发现缺失的ZKP密钥、损坏的电路等安装问题
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use libp2p::PeerId;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::block_store::BlockStore;
+use crate::did_builder::{get_did_document_from_cid, DIDBuilder, DIDPublishResult};
+use crate::identity_manager::IdentityManager;
+use crate::ipfs_client::{IpfsClient, IpfsUploadResult};
+use crate::key_manager::KeyPair;
+use crate::noir_universal::{NoirBackend, NoirProofResult, NoirProverInputs, UniversalNoirManager};
+use crate::pinning_provider::PinningProvider;
+use crate::pubsub_authenticator::{PubSubMessageType, PubsubAuthenticator};
+
+/// 自检选项
+#[derive(Debug, Clone)]
+pub struct SelfTestOptions {
+    /// ZKP后端（None表示与运行时相同的自动选择）
+    pub noir_backend: Option<NoirBackend>,
+
+    /// 消息收发轮数
+    pub message_rounds: usize,
+}
+
+impl Default for SelfTestOptions {
+    fn default() -> Self {
+        Self {
+            noir_backend: None,
+            message_rounds: 3,
+        }
+    }
+}
+
+/// 单个步骤的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelfTestStatus {
+    /// 通过
+    Passed,
+    /// 失败
+    Failed,
+    /// 前置步骤失败，未执行
+    Skipped,
+}
+
+/// 自检步骤
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestStep {
+    /// 步骤名称
+    pub name: String,
+
+    /// 结果
+    pub status: SelfTestStatus,
+
+    /// 耗时（毫秒）
+    pub duration_ms: u64,
+
+    /// 失败原因
+    pub error: Option<String>,
+}
+
+/// 自检报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    /// 各步骤结果（按执行顺序）
+    pub steps: Vec<SelfTestStep>,
+
+    /// 总耗时（毫秒）
+    pub total_ms: u64,
+}
+
+impl SelfTestReport {
+    /// 所有步骤是否都通过
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.status == SelfTestStatus::Passed)
+    }
+
+    /// 失败的步骤
+    pub fn failures(&self) -> Vec<&SelfTestStep> {
+        self.steps.iter().filter(|step| step.status == SelfTestStatus::Failed).collect()
+    }
+
+    /// 可读的摘要（每步一行）
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::with_capacity(self.steps.len() + 1);
+        for step in &self.steps {
+            let mark = match step.status {
+                SelfTestStatus::Passed => "✓",
+                SelfTestStatus::Failed => "✗",
+                SelfTestStatus::Skipped => "-",
+            };
+            let mut line = format!("{} {:<22} {:>6} ms", mark, step.name, step.duration_ms);
+            if let Some(error) = &step.error {
+                line.push_str(&format!("  {}", error));
+            }
+            lines.push(line);
+        }
+        lines.push(format!(
+            "{} 共 {} 步，耗时 {} ms",
+            if self.passed() { "✅ 自检通过" } else { "❌ 自检失败" },
+            self.steps.len(),
+            self.total_ms,
+        ));
+        lines.join("\n")
+    }
+}
+
+/// 运行自检
+pub async fn run_self_test(options: SelfTestOptions) -> SelfTestReport {
+    log::info!("🩺 开始自检");
+    let started = Instant::now();
+    let mut runner = StepRunner::default();
+
+    let keypair = runner.run("密钥生成", async {
+        let keypair = KeyPair::generate()?;
+        let signature = keypair.sign(b"diap-self-test")?;
+        if !KeyPair::verify_with_did_key(&keypair.did, b"diap-self-test", &signature)? {
+            anyhow::bail!("生成的密钥无法验证自身签名");
+        }
+        Ok(keypair)
+    }).await;
+
+    let store = runner.run("模拟存储", async { MockStore::open() }).await;
+
+    let published = match (&keypair, &store) {
+        (Some(keypair), Some(store)) => runner.run("DID文档构建与发布", async {
+            let peer_id = PeerId::random();
+            let result = DIDBuilder::new(store.ipfs_client()).create_and_publish(keypair, &peer_id).await?;
+            let fetched = get_did_document_from_cid(&store.ipfs_client(), &result.cid).await?;
+            if fetched.id != keypair.did {
+                anyhow::bail!("读回的DID文档不匹配: {}", fetched.id);
+            }
+            Ok((result, peer_id))
+        }).await,
+        _ => runner.skip("DID文档构建与发布"),
+    };
+
+    let manager = runner.run("ZKP后端初始化", async {
+        match options.noir_backend.clone() {
+            Some(backend) => UniversalNoirManager::with_backend(backend).await,
+            None => UniversalNoirManager::new().await,
+        }
+    }).await;
+
+    let proof = match (manager, &keypair, &published) {
+        (Some(mut manager), Some(keypair), Some((published, _))) => {
+            let proof = runner.run("ZKP证明生成", async {
+                let proof = manager.generate_proof(&prover_inputs(keypair, published)?).await?;
+                if proof.proof.is_empty() {
+                    anyhow::bail!("生成的证明为空");
+                }
+                Ok(proof)
+            }).await;
+            match proof {
+                Some(proof) => runner.run("ZKP证明验证", async {
+                    let result = manager.verify_proof(&proof.proof, &proof.public_inputs).await?;
+                    if !result.is_valid {
+                        anyhow::bail!("证明验证失败: {}", result.error_message.unwrap_or_default());
+                    }
+                    Ok(proof)
+                }).await,
+                None => runner.skip("ZKP证明验证"),
+            }
+        }
+        _ => {
+            runner.skip::<()>("ZKP证明生成");
+            runner.skip::<NoirProofResult>("ZKP证明验证")
+        }
+    };
+
+    match (&keypair, &store, &published, &proof) {
+        (Some(keypair), Some(store), Some((published, _)), Some(_)) => {
+            runner.run("身份验证", async {
+                let identity_manager = IdentityManager::new(store.ipfs_client());
+                let nonce = uuid::Uuid::new_v4().to_string();
+                let binding = identity_manager.generate_binding_proof(keypair, &published.did_document, &published.cid, nonce.as_bytes())?;
+                let verification = identity_manager.verify_identity_with_zkp(&published.cid, &binding, nonce.as_bytes()).await?;
+                if !verification.zkp_verified || verification.did != keypair.did {
+                    anyhow::bail!("身份验证未通过: {}", verification.verification_details.join("; "));
+                }
+                Ok(())
+            }).await;
+        }
+        _ => {
+            runner.skip::<()>("身份验证");
+        }
+    }
+
+    match (keypair, &store, &published) {
+        (Some(keypair), Some(store), Some((published, peer_id))) => {
+            runner.run("签名消息收发", async {
+                message_loop(keypair, *peer_id, &published.cid, store, options.message_rounds.max(1)).await
+            }).await;
+        }
+        _ => {
+            runner.skip::<()>("签名消息收发");
+        }
+    }
+
+    let report = SelfTestReport {
+        steps: runner.steps,
+        total_ms: started.elapsed().as_millis() as u64,
+    };
+    if report.passed() {
+        log::info!("✅ 自检通过，耗时 {} ms", report.total_ms);
+    } else {
+        for step in report.failures() {
+            log::error!("❌ 自检步骤失败: {} - {}", step.name, step.error.as_deref().unwrap_or(""));
+        }
+    }
+    report
+}
+
+/// 由DID文档派生证明输入
+fn prover_inputs(keypair: &KeyPair, published: &DIDPublishResult) -> Result<NoirProverInputs> {
+    let document = serde_json::to_vec(&published.did_document).context("序列化DID文档失败")?;
+    let did_hash = hex::encode(Sha256::digest(&document));
+    let nonce_hash = hex::encode(Sha256::digest(uuid::Uuid::new_v4().as_bytes()));
+    Ok(NoirProverInputs {
+        expected_output: hex::encode(Sha256::digest(format!("{}{}", did_hash, nonce_hash))),
+        expected_did_hash: did_hash,
+        public_key_hash: hex::encode(Sha256::digest(keypair.public_key)),
+        nonce_hash,
+    })
+}
+
+/// 发送方和接收方各自独立的认证器，签名消息往返若干轮，重放必须被拒绝
+async fn message_loop(keypair: KeyPair, peer_id: PeerId, cid: &str, store: &MockStore, rounds: usize) -> Result<()> {
+    let sender = PubsubAuthenticator::new(IdentityManager::new(store.ipfs_client()), None, None);
+    sender.set_local_identity(keypair, peer_id, cid.to_string()).await?;
+    let receiver = PubsubAuthenticator::new(IdentityManager::new(store.ipfs_client()), None, None);
+
+    let mut last = None;
+    for round in 0..rounds {
+        let content = format!("self-test #{}", round);
+        let message = sender
+            .create_authenticated_message("diap/self-test", PubSubMessageType::Heartbeat, content.as_bytes(), None)
+            .await?;
+        let verification = receiver.verify_message(&message).await?;
+        if !verification.verified {
+            anyhow::bail!("第 {} 轮消息验证失败: {}", round, verification.details.join("; "));
+        }
+        last = Some(message);
+    }
+
+    if let Some(message) = last {
+        if receiver.verify_message(&message).await?.verified {
+            anyhow::bail!("重放的消息没有被拒绝");
+        }
+    }
+    Ok(())
+}
+
+/// 逐步执行并记录结果
+#[derive(Default)]
+struct StepRunner {
+    steps: Vec<SelfTestStep>,
+}
+
+impl StepRunner {
+    async fn run<T>(&mut self, name: &str, step: impl Future<Output = Result<T>>) -> Option<T> {
+        let started = Instant::now();
+        let result = step.await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        match result {
+            Ok(value) => {
+                log::info!("✓ 自检 {} ({} ms)", name, duration_ms);
+                self.push(name, SelfTestStatus::Passed, duration_ms, None);
+                Some(value)
+            }
+            Err(e) => {
+                log::warn!("✗ 自检 {} 失败: {:#}", name, e);
+                self.push(name, SelfTestStatus::Failed, duration_ms, Some(format!("{:#}", e)));
+                None
+            }
+        }
+    }
+
+    fn skip<T>(&mut self, name: &str) -> Option<T> {
+        self.push(name, SelfTestStatus::Skipped, 0, Some("前置步骤失败，已跳过".to_string()));
+        None
+    }
+
+    fn push(&mut self, name: &str, status: SelfTestStatus, duration_ms: u64, error: Option<String>) {
+        self.steps.push(SelfTestStep { name: name.to_string(), status, duration_ms, error });
+    }
+}
+
+/// 进程内模拟存储：上传的内容写入临时块存储，读取时由块存储直接命中，不访问网络
+struct MockStore {
+    block_store: BlockStore,
+}
+
+impl MockStore {
+    fn open() -> Result<Self> {
+        let root = std::env::temp_dir().join(format!("diap-self-test-{}", uuid::Uuid::new_v4().simple()));
+        Ok(Self { block_store: BlockStore::open(root)? })
+    }
+
+    fn root(&self) -> PathBuf {
+        self.block_store.root().to_path_buf()
+    }
+
+    fn ipfs_client(&self) -> IpfsClient {
+        IpfsClient::new_public_only(5)
+            .with_block_store(self.block_store.clone())
+            .with_pinning_provider(std::sync::Arc::new(MockStoreProvider { block_store: self.block_store.clone() }))
+    }
+}
+
+impl Drop for MockStore {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(self.root());
+    }
+}
+
+/// 写入块存储的模拟上传提供商
+struct MockStoreProvider {
+    block_store: BlockStore,
+}
+
+#[async_trait]
+impl PinningProvider for MockStoreProvider {
+    fn name(&self) -> &str {
+        "self_test"
+    }
+
+    async fn upload(&self, _client: &Client, content: &str, _name: &str) -> Result<IpfsUploadResult> {
+        let cid = format!("bafkselftest{}", hex::encode(Sha256::digest(content.as_bytes())));
+        self.block_store.put(&cid, content.as_bytes()).await?;
+        Ok(IpfsUploadResult {
+            cid,
+            size: content.len() as u64,
+            uploaded_at: chrono::Utc::now().to_rfc3339(),
+            provider: self.name().to_string(),
+        })
+    }
+
+    async fn pin(&self, _client: &Client, _cid: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn is_pinned(&self, _client: &Client, cid: &str) -> Result<bool> {
+        Ok(self.block_store.contains(cid).await)
+    }
+
+    async fn unpin(&self, _client: &Client, _cid: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_self_test_passes_with_simplified_backend() {
+        let options = SelfTestOptions {
+            noir_backend: Some(NoirBackend::Simplified),
+            message_rounds: 2,
+        };
+        let report = run_self_test(options).await;
+        assert!(report.passed(), "{}", report.summary());
+        assert_eq!(report.steps.len(), 8);
+        assert!(report.summary().contains("自检通过"));
+    }
+}
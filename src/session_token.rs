@@ -0,0 +1,281 @@
+// DIAP Rust SDK - 会话令牌（快速重新认证）
+// 验证方在完整的ZKP验证成功后签发短期会话令牌（JWT紧凑格式，EdDSA签名，iss为签发者的did:key），
+// 令牌绑定被验证的DID（sub）和DID文档CID；之后的消息携带令牌即可只做签名验证，
+// 令牌过期后回退到完整的ZKP验证
+
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::key_manager::{KeyPair, Signer};
+
+/// 默认会话令牌有效期
+pub const DEFAULT_SESSION_TOKEN_TTL: Duration = Duration::from_secs(900);
+
+/// 签发时间允许的时钟偏差（秒）
+const ISSUED_AT_SKEW_SECS: u64 = 30;
+
+/// JWT头部
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct TokenHeader {
+    alg: String,
+    typ: String,
+}
+
+impl TokenHeader {
+    fn eddsa() -> Self {
+        Self { alg: "EdDSA".to_string(), typ: "JWT".to_string() }
+    }
+}
+
+/// 会话令牌声明
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionTokenClaims {
+    /// 签发者DID（验证方）
+    pub iss: String,
+
+    /// 被验证的DID
+    pub sub: String,
+
+    /// 签发时间（Unix秒）
+    pub iat: u64,
+
+    /// 过期时间（Unix秒）
+    pub exp: u64,
+
+    /// 令牌ID
+    pub jti: String,
+
+    /// 验证时使用的DID文档CID（文档更新后令牌失效）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+}
+
+/// 签名的会话令牌
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionToken {
+    /// 令牌声明
+    pub claims: SessionTokenClaims,
+
+    /// 签名输入（base64url头部.base64url声明），保留原文以便验证
+    signing_input: String,
+
+    /// 签发者签名
+    signature: Vec<u8>,
+}
+
+impl SessionToken {
+    /// 签发会话令牌
+    pub fn issue(
+        signer: &dyn Signer,
+        subject: &str,
+        did_cid: Option<&str>,
+        ttl: Duration,
+        now: u64,
+    ) -> Result<Self> {
+        let claims = SessionTokenClaims {
+            iss: signer.did(),
+            sub: subject.to_string(),
+            iat: now,
+            exp: now + ttl.as_secs(),
+            jti: hex::encode(rand::random::<[u8; 16]>()),
+            cid: did_cid.map(str::to_string),
+        };
+        let header = serde_json::to_vec(&TokenHeader::eddsa()).context("序列化令牌头部失败")?;
+        let body = serde_json::to_vec(&claims).context("序列化令牌声明失败")?;
+        let signing_input = format!(
+            "{}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(header),
+            general_purpose::URL_SAFE_NO_PAD.encode(body)
+        );
+        let signature = signer.sign(signing_input.as_bytes())?;
+        Ok(Self { claims, signing_input, signature })
+    }
+
+    /// 编码为JWT紧凑格式
+    pub fn encode(&self) -> String {
+        format!("{}.{}", self.signing_input, general_purpose::URL_SAFE_NO_PAD.encode(&self.signature))
+    }
+
+    /// 从JWT紧凑格式解码（不验证签名）
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let mut parts = encoded.split('.');
+        let (Some(header), Some(body), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            anyhow::bail!("会话令牌格式错误");
+        };
+
+        let header: TokenHeader = serde_json::from_slice(
+            &general_purpose::URL_SAFE_NO_PAD.decode(header).context("解码令牌头部失败")?
+        ).context("解析令牌头部失败")?;
+        if header != TokenHeader::eddsa() {
+            anyhow::bail!("不支持的会话令牌算法: {}", header.alg);
+        }
+        let claims = serde_json::from_slice(
+            &general_purpose::URL_SAFE_NO_PAD.decode(body).context("解码令牌声明失败")?
+        ).context("解析令牌声明失败")?;
+        let signature = general_purpose::URL_SAFE_NO_PAD.decode(signature).context("解码令牌签名失败")?;
+
+        Ok(Self {
+            claims,
+            signing_input: encoded[..encoded.rfind('.').unwrap_or(0)].to_string(),
+            signature,
+        })
+    }
+
+    /// 是否已过期
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.claims.exp
+    }
+
+    /// 验证签名和有效期
+    pub fn verify(&self, now: u64) -> Result<()> {
+        if !KeyPair::verify_with_did_key(&self.claims.iss, self.signing_input.as_bytes(), &self.signature)? {
+            anyhow::bail!("会话令牌签名无效");
+        }
+        if self.claims.iat > now + ISSUED_AT_SKEW_SECS {
+            anyhow::bail!("会话令牌签发时间在未来");
+        }
+        if self.is_expired(now) {
+            anyhow::bail!("会话令牌已过期");
+        }
+        Ok(())
+    }
+
+    /// 验证令牌可用于该发送者：签发者受信任、主体和DID文档CID一致、签名有效且未过期
+    pub fn check(&self, subject: &str, did_cid: &str, trusted_issuers: &[String], now: u64) -> Result<()> {
+        if !trusted_issuers.contains(&self.claims.iss) {
+            anyhow::bail!("会话令牌签发者不受信任: {}", self.claims.iss);
+        }
+        if self.claims.sub != subject {
+            anyhow::bail!("会话令牌主体与发送者不符: {}", self.claims.sub);
+        }
+        if self.claims.cid.as_deref().is_some_and(|cid| cid != did_cid) {
+            anyhow::bail!("会话令牌绑定的DID文档已变更");
+        }
+        self.verify(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_check() {
+        let verifier = KeyPair::generate().unwrap();
+        let agent = KeyPair::generate().unwrap();
+        let trusted = vec![verifier.did.clone()];
+        let now = 1_700_000_000;
+
+        let token = SessionToken::issue(&verifier, &agent.did, Some("QmDoc"), DEFAULT_SESSION_TOKEN_TTL, now).unwrap();
+        let encoded = token.encode();
+        assert_eq!(encoded.split('.').count(), 3);
+        let decoded = SessionToken::decode(&encoded).unwrap();
+        assert_eq!(decoded, token);
+        decoded.check(&agent.did, "QmDoc", &trusted, now + 60).unwrap();
+
+        assert!(decoded.check(&verifier.did, "QmDoc", &trusted, now).is_err(), "主体不符");
+        assert!(decoded.check(&agent.did, "QmNewDoc", &trusted, now).is_err(), "DID文档已变更");
+        assert!(decoded.check(&agent.did, "QmDoc", std::slice::from_ref(&agent.did), now).is_err(), "签发者不受信任");
+        assert!(decoded.check(&agent.did, "QmDoc", &trusted, now + 900).is_err(), "已过期");
+
+        // 篡改声明后签名失效
+        let mut parts: Vec<String> = encoded.split('.').map(str::to_string).collect();
+        let mut claims = token.claims.clone();
+        claims.exp += 3600;
+        parts[1] = general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap());
+        let forged = SessionToken::decode(&parts.join(".")).unwrap();
+        assert!(forged.verify(now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_fast_path() {
+        use crate::clock::MockClock;
+        use crate::did_cache::DIDCache;
+        use crate::did_resolver::DIDResolver;
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::CallbackSigner;
+        use crate::pubsub_authenticator::{PubSubMessageType, PubsubAuthenticator, TopicConfig, TopicEncryption, TopicPolicy};
+        use crate::trust_level::TrustLevel;
+        use std::sync::Arc;
+
+        fn offline_client() -> IpfsClient {
+            let client = IpfsClient::new_public_only(1);
+            for gateway in client.public_gateways() {
+                client.remove_gateway(&gateway);
+            }
+            client
+        }
+
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let verifier = KeyPair::generate().unwrap();
+        let agent = KeyPair::generate().unwrap();
+        let agent_did = agent.did.clone();
+
+        // 接收方已缓存发送者的DID文档，但外部签名器发出的消息没有ZKP证明
+        let cache = DIDCache::new(Some(3600), Some(16));
+        cache.put("QmCurrent".to_string(), DIDResolver::resolve_did_key(&agent_did).unwrap()).unwrap();
+        let receiver = PubsubAuthenticator::new(IdentityManager::new(offline_client()), None, Some(cache));
+
+        let clock = MockClock::new(now);
+        let sender = PubsubAuthenticator::new(IdentityManager::new(offline_client()), None, None)
+            .with_clock(Arc::new(clock.clone()));
+        let signer = CallbackSigner::new(agent.public_key, Arc::new(move |data| agent.sign(data))).unwrap();
+        sender.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmCurrent".to_string()).await.unwrap();
+
+        let message = sender.create_authenticated_message("chat", PubSubMessageType::Heartbeat, b"hi", None).await.unwrap();
+        assert!(!receiver.verify_message(&message).await.unwrap().verified);
+
+        let token = SessionToken::issue(&verifier, &agent_did, Some("QmCurrent"), DEFAULT_SESSION_TOKEN_TTL, now).unwrap();
+        sender.set_session_token(&token.encode()).await.unwrap();
+        let message = sender.create_authenticated_message("chat", PubSubMessageType::Heartbeat, b"hi", None).await.unwrap();
+        assert!(message.session_token.is_some());
+
+        // 签发者不受信任时回退到ZKP验证
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d.contains("回退到ZKP验证")));
+
+        receiver.set_trusted_token_issuers(vec![verifier.did.clone()]).await;
+        let message = sender.create_authenticated_message("chat", PubSubMessageType::Heartbeat, b"hi", None).await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(verification.verified, "{:?}", verification.details);
+        assert_eq!(verification.trust_level, Some(TrustLevel::SignaturePlusCachedDoc));
+
+        // 令牌不能被其他发送者挪用：篡改后签名失效
+        let mut stolen = message.clone();
+        stolen.from_did = verifier.did.clone();
+        assert!(!receiver.verify_message(&stolen).await.unwrap().verified);
+
+        // 令牌过期后不再随消息发送
+        clock.advance(DEFAULT_SESSION_TOKEN_TTL);
+        let message = sender.create_authenticated_message("chat", PubSubMessageType::Heartbeat, b"hi", None).await.unwrap();
+        assert!(message.session_token.is_none());
+
+        // 主题明确要求ZKP时，外部签名器没有会话令牌不能发送
+        clock.set(now);
+        sender.clear_session_token().await;
+        let topic = |name: &str, require_zkp: bool| TopicConfig {
+            name: name.to_string(),
+            policy: TopicPolicy::AllowAuthenticated,
+            require_zkp,
+            require_signature: true,
+            retention: None,
+            rate_limit: None,
+            encryption: TopicEncryption::Optional,
+        };
+        sender.configure_topic(topic("strict", true)).await.unwrap();
+        assert!(sender.create_authenticated_message("strict", PubSubMessageType::Heartbeat, b"hi", None).await.is_err());
+
+        // 不要求ZKP的主题：外部签名器的消息凭签名和DID文档通过
+        sender.configure_topic(topic("open", false)).await.unwrap();
+        receiver.configure_topic(topic("open", false)).await.unwrap();
+        let message = sender.create_authenticated_message("open", PubSubMessageType::Heartbeat, b"hi", None).await.unwrap();
+        assert!(message.session_token.is_none() && message.zkp_proof.is_empty());
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(verification.verified, "{:?}", verification.details);
+        assert_eq!(verification.trust_level, Some(TrustLevel::SignaturePlusCachedDoc));
+    }
+}
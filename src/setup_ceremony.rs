@@ -0,0 +1,434 @@
+// DIAP Rust SDK - 可信设置仪式
+// 把证明/验证密钥打包为带版本和哈希标识的密钥包，所有智能体使用同一份密钥；
+// 仪式输出由参与者贡献的熵确定（相同贡献可复现相同密钥），
+// 通用方案可导入共享SRS；验证时拒绝在其他密钥版本下生成的证明
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// 密钥包清单文件名
+pub const SETUP_MANIFEST_FILE: &str = "setup_manifest.json";
+
+/// 证明密钥文件名
+pub const PROVING_KEY_FILE: &str = "proving_key.bin";
+
+/// 验证密钥文件名
+pub const VERIFYING_KEY_FILE: &str = "verifying_key.bin";
+
+/// 仪式初始状态的域分隔标签
+const CEREMONY_DOMAIN: &[u8] = b"diap-setup-ceremony-v1";
+
+/// 仪式参与者的贡献记录（只公开熵的承诺）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Contribution {
+    /// 参与者标识（例如DID）
+    pub participant: String,
+
+    /// 贡献熵的SHA-256（hex）
+    pub commitment: String,
+}
+
+/// 密钥包清单
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBundleManifest {
+    /// 证明方案（groth16 / ultra_honk）
+    pub scheme: String,
+
+    /// 密钥版本
+    pub key_version: u32,
+
+    /// 电路哈希
+    pub circuit_hash: String,
+
+    /// 密钥标识：SHA-256(证明密钥哈希 || 验证密钥哈希)
+    pub key_id: String,
+
+    /// 证明密钥SHA-256（hex）
+    pub proving_key_sha256: String,
+
+    /// 验证密钥SHA-256（hex）
+    pub verifying_key_sha256: String,
+
+    /// 共享SRS的SHA-256（通用设置方案）
+    #[serde(default)]
+    pub srs_sha256: Option<String>,
+
+    /// 仪式贡献记录
+    #[serde(default)]
+    pub contributions: Vec<Contribution>,
+}
+
+/// 带版本标识的密钥包
+#[derive(Debug, Clone)]
+pub struct KeyBundle {
+    /// 清单
+    pub manifest: KeyBundleManifest,
+
+    /// 证明密钥
+    pub proving_key: Vec<u8>,
+
+    /// 验证密钥
+    pub verifying_key: Vec<u8>,
+}
+
+/// 带密钥版本标识的证明
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionedProof {
+    /// 生成证明时的密钥版本
+    pub key_version: u32,
+
+    /// 生成证明时的密钥标识
+    pub key_id: String,
+
+    /// 证明数据
+    pub proof: Vec<u8>,
+}
+
+/// 导入的共享SRS（结构化参考串）
+#[derive(Debug, Clone)]
+pub struct SharedSrs {
+    /// SRS数据
+    pub data: Vec<u8>,
+
+    /// SRS的SHA-256（hex）
+    pub sha256: String,
+}
+
+/// 可信设置错误
+#[derive(Debug, thiserror::Error)]
+pub enum SetupError {
+    /// 证明不是在当前密钥下生成的
+    #[error("证明的密钥版本不匹配：期望 v{expected_version} ({expected_key})，实际 v{found_version} ({found_key})")]
+    KeyVersionMismatch {
+        expected_version: u32,
+        expected_key: String,
+        found_version: u32,
+        found_key: String,
+    },
+
+    /// 密钥或SRS文件与清单中的哈希不一致
+    #[error("{name}哈希不匹配：期望 {expected}，实际 {actual}")]
+    HashMismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl KeyBundle {
+    /// 由证明密钥和验证密钥创建密钥包
+    pub fn new(scheme: &str, key_version: u32, circuit_hash: &str, proving_key: Vec<u8>, verifying_key: Vec<u8>) -> Self {
+        let proving_key_sha256 = sha256_hex(&proving_key);
+        let verifying_key_sha256 = sha256_hex(&verifying_key);
+        let key_id = sha256_hex(format!("{}{}", proving_key_sha256, verifying_key_sha256).as_bytes());
+        Self {
+            manifest: KeyBundleManifest {
+                scheme: scheme.to_string(),
+                key_version,
+                circuit_hash: circuit_hash.to_string(),
+                key_id,
+                proving_key_sha256,
+                verifying_key_sha256,
+                srs_sha256: None,
+                contributions: Vec::new(),
+            },
+            proving_key,
+            verifying_key,
+        }
+    }
+
+    /// 记录生成密钥时使用的共享SRS
+    pub fn with_srs(mut self, srs: &SharedSrs) -> Self {
+        self.manifest.srs_sha256 = Some(srs.sha256.clone());
+        self
+    }
+
+    /// 密钥标识
+    pub fn key_id(&self) -> &str {
+        &self.manifest.key_id
+    }
+
+    /// 密钥版本
+    pub fn key_version(&self) -> u32 {
+        self.manifest.key_version
+    }
+
+    /// 保存到目录（清单 + 两个密钥文件）
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).context("创建密钥目录失败")?;
+        std::fs::write(dir.join(PROVING_KEY_FILE), &self.proving_key).context("保存证明密钥失败")?;
+        std::fs::write(dir.join(VERIFYING_KEY_FILE), &self.verifying_key).context("保存验证密钥失败")?;
+        std::fs::write(dir.join(SETUP_MANIFEST_FILE), serde_json::to_vec_pretty(&self.manifest)?)
+            .context("保存密钥清单失败")?;
+        log::info!("💾 密钥包已保存: v{} {}", self.manifest.key_version, self.manifest.key_id);
+        Ok(())
+    }
+
+    /// 从目录加载并按清单校验密钥哈希
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let manifest: KeyBundleManifest = serde_json::from_slice(
+            &std::fs::read(dir.join(SETUP_MANIFEST_FILE)).context("读取密钥清单失败")?,
+        ).context("解析密钥清单失败")?;
+        let proving_key = std::fs::read(dir.join(PROVING_KEY_FILE)).context("读取证明密钥失败")?;
+        let verifying_key = std::fs::read(dir.join(VERIFYING_KEY_FILE)).context("读取验证密钥失败")?;
+
+        check_hash("证明密钥", &manifest.proving_key_sha256, &proving_key)?;
+        check_hash("验证密钥", &manifest.verifying_key_sha256, &verifying_key)?;
+        let expected = Self::new(&manifest.scheme, manifest.key_version, &manifest.circuit_hash, proving_key, verifying_key);
+        if expected.manifest.key_id != manifest.key_id {
+            return Err(SetupError::HashMismatch {
+                name: "密钥标识".to_string(),
+                expected: manifest.key_id,
+                actual: expected.manifest.key_id,
+            }.into());
+        }
+
+        log::info!("🔑 已加载密钥包: v{} {}", manifest.key_version, manifest.key_id);
+        Ok(Self { manifest, ..expected })
+    }
+
+    /// 为证明附加当前密钥版本
+    pub fn tag_proof(&self, proof: Vec<u8>) -> VersionedProof {
+        VersionedProof {
+            key_version: self.manifest.key_version,
+            key_id: self.manifest.key_id.clone(),
+            proof,
+        }
+    }
+
+    /// 检查证明是否在当前密钥下生成
+    pub fn check_proof(&self, proof: &VersionedProof) -> Result<(), SetupError> {
+        if proof.key_version != self.manifest.key_version || proof.key_id != self.manifest.key_id {
+            return Err(SetupError::KeyVersionMismatch {
+                expected_version: self.manifest.key_version,
+                expected_key: self.manifest.key_id.clone(),
+                found_version: proof.key_version,
+                found_key: proof.key_id.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl SharedSrs {
+    /// 导入共享SRS文件；提供expected_sha256时校验哈希
+    pub fn import(path: impl AsRef<Path>, expected_sha256: Option<&str>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).with_context(|| format!("读取SRS失败: {}", path.display()))?;
+        if let Some(expected) = expected_sha256 {
+            check_hash("SRS", expected, &data)?;
+        }
+        let sha256 = sha256_hex(&data);
+        log::info!("📥 已导入共享SRS: {} ({})", path.display(), sha256);
+        Ok(Self { data, sha256 })
+    }
+}
+
+/// 可信设置仪式：参与者依次贡献熵，最终种子由全部贡献确定
+pub struct SetupCeremony {
+    circuit_hash: String,
+    key_version: u32,
+    state: [u8; 32],
+    contributions: Vec<Contribution>,
+}
+
+impl SetupCeremony {
+    /// 为指定电路和密钥版本开始仪式
+    pub fn new(circuit_hash: &str, key_version: u32) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(CEREMONY_DOMAIN);
+        hasher.update(circuit_hash.as_bytes());
+        hasher.update(key_version.to_be_bytes());
+        Self {
+            circuit_hash: circuit_hash.to_string(),
+            key_version,
+            state: hasher.finalize().into(),
+            contributions: Vec::new(),
+        }
+    }
+
+    /// 加入一个参与者的熵（只记录承诺；熵本身应在贡献后销毁）
+    pub fn contribute(mut self, participant: &str, entropy: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(self.state);
+        hasher.update(entropy);
+        self.state = hasher.finalize().into();
+        self.contributions.push(Contribution {
+            participant: participant.to_string(),
+            commitment: sha256_hex(entropy),
+        });
+        self
+    }
+
+    /// 贡献记录
+    pub fn contributions(&self) -> &[Contribution] {
+        &self.contributions
+    }
+
+    /// 由全部贡献确定的种子（供外部工具生成密钥）
+    pub fn seed(&self) -> [u8; 32] {
+        self.state
+    }
+
+    /// 用外部生成的密钥结束仪式，生成带贡献记录的密钥包
+    pub fn finish(self, scheme: &str, proving_key: Vec<u8>, verifying_key: Vec<u8>) -> KeyBundle {
+        let mut bundle = KeyBundle::new(scheme, self.key_version, &self.circuit_hash, proving_key, verifying_key);
+        bundle.manifest.contributions = self.contributions;
+        bundle
+    }
+
+    /// 生成Groth16（BN254）密钥包
+    #[cfg(feature = "arkworks-zkp")]
+    pub fn groth16<C>(self, circuit: C) -> Result<KeyBundle>
+    where
+        C: ark_relations::r1cs::ConstraintSynthesizer<ark_bn254::Fr>,
+    {
+        use ark_serialize::CanonicalSerialize;
+        use ark_snark::CircuitSpecificSetupSNARK;
+        use rand::SeedableRng;
+
+        if self.contributions.is_empty() {
+            anyhow::bail!("可信设置至少需要一个贡献");
+        }
+        log::info!("🎲 运行可信设置仪式: v{} ({}个贡献)", self.key_version, self.contributions.len());
+
+        let mut rng = rand::rngs::StdRng::from_seed(self.state);
+        let (pk, vk) = ark_groth16::Groth16::<ark_bn254::Bn254>::setup(circuit, &mut rng)
+            .map_err(|e| anyhow::anyhow!("Groth16设置失败: {}", e))?;
+        let mut proving_key = Vec::new();
+        pk.serialize_compressed(&mut proving_key).context("序列化证明密钥失败")?;
+        let mut verifying_key = Vec::new();
+        vk.serialize_compressed(&mut verifying_key).context("序列化验证密钥失败")?;
+
+        Ok(self.finish("groth16", proving_key, verifying_key))
+    }
+}
+
+/// 使用密钥包生成Groth16证明
+#[cfg(feature = "arkworks-zkp")]
+pub fn groth16_prove<C>(bundle: &KeyBundle, circuit: C) -> Result<VersionedProof>
+where
+    C: ark_relations::r1cs::ConstraintSynthesizer<ark_bn254::Fr>,
+{
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_snark::SNARK;
+
+    let pk = ark_groth16::ProvingKey::<ark_bn254::Bn254>::deserialize_compressed(bundle.proving_key.as_slice())
+        .context("解析证明密钥失败")?;
+    let proof = ark_groth16::Groth16::<ark_bn254::Bn254>::prove(&pk, circuit, &mut rand::rngs::OsRng)
+        .map_err(|e| anyhow::anyhow!("生成Groth16证明失败: {}", e))?;
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).context("序列化证明失败")?;
+    Ok(bundle.tag_proof(bytes))
+}
+
+/// 使用密钥包验证Groth16证明（密钥版本不一致时直接拒绝）
+#[cfg(feature = "arkworks-zkp")]
+pub fn groth16_verify(bundle: &KeyBundle, proof: &VersionedProof, public_inputs: &[ark_bn254::Fr]) -> Result<bool> {
+    use ark_serialize::CanonicalDeserialize;
+    use ark_snark::SNARK;
+
+    bundle.check_proof(proof)?;
+    let vk = ark_groth16::VerifyingKey::<ark_bn254::Bn254>::deserialize_compressed(bundle.verifying_key.as_slice())
+        .context("解析验证密钥失败")?;
+    let Ok(groth16_proof) = ark_groth16::Proof::<ark_bn254::Bn254>::deserialize_compressed(proof.proof.as_slice()) else {
+        return Ok(false);
+    };
+    ark_groth16::Groth16::<ark_bn254::Bn254>::verify(&vk, public_inputs, &groth16_proof)
+        .map_err(|e| anyhow::anyhow!("Groth16验证失败: {}", e))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn check_hash(name: &str, expected: &str, data: &[u8]) -> Result<(), SetupError> {
+    let actual = sha256_hex(data);
+    if actual != expected {
+        return Err(SetupError::HashMismatch {
+            name: name.to_string(),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_roundtrip_and_version_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let srs_path = dir.path().join("srs.dat");
+        std::fs::write(&srs_path, b"shared-srs").unwrap();
+        let srs = SharedSrs::import(&srs_path, Some(&sha256_hex(b"shared-srs"))).unwrap();
+        assert!(matches!(
+            SharedSrs::import(&srs_path, Some("00")).unwrap_err().downcast_ref(),
+            Some(SetupError::HashMismatch { .. })
+        ));
+
+        let ceremony = SetupCeremony::new("circuit", 2).contribute("did:key:alice", b"entropy");
+        assert_eq!(ceremony.seed(), SetupCeremony::new("circuit", 2).contribute("did:key:carol", b"entropy").seed());
+        let bundle = ceremony.finish("ultra_honk", b"pk".to_vec(), b"vk".to_vec()).with_srs(&srs);
+        assert_eq!(bundle.manifest.contributions[0].commitment, sha256_hex(b"entropy"));
+        bundle.save(dir.path().join("keys")).unwrap();
+        let loaded = KeyBundle::load(dir.path().join("keys")).unwrap();
+        assert_eq!(loaded.manifest, bundle.manifest);
+        assert_eq!(loaded.manifest.srs_sha256.as_deref(), Some(srs.sha256.as_str()));
+
+        let proof = loaded.tag_proof(b"proof".to_vec());
+        loaded.check_proof(&proof).unwrap();
+        let other = KeyBundle::new("ultra_honk", 3, "circuit", b"pk".to_vec(), b"vk".to_vec());
+        assert!(matches!(other.check_proof(&proof), Err(SetupError::KeyVersionMismatch { found_version: 2, .. })));
+
+        // 被替换的密钥文件无法加载
+        std::fs::write(dir.path().join("keys").join(VERIFYING_KEY_FILE), b"other").unwrap();
+        assert!(KeyBundle::load(dir.path().join("keys")).is_err());
+    }
+
+    #[cfg(feature = "arkworks-zkp")]
+    #[test]
+    fn test_groth16_ceremony_is_reproducible() {
+        use ark_bn254::Fr;
+        use ark_relations::lc;
+        use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+        /// x * x == y，其中y为公共输入
+        struct Square(u64);
+        impl ConstraintSynthesizer<Fr> for Square {
+            fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+                let x = cs.new_witness_variable(|| Ok(Fr::from(self.0)))?;
+                let y = cs.new_input_variable(|| Ok(Fr::from(self.0 * self.0)))?;
+                cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + y)
+            }
+        }
+
+        let ceremony = |version| SetupCeremony::new("square", version)
+            .contribute("did:key:alice", b"alice-entropy")
+            .contribute("did:key:bob", b"bob-entropy");
+
+        // 相同贡献得到相同密钥：一方生成的证明可由另一方验证
+        let alice = ceremony(1).groth16(Square(3)).unwrap();
+        let bob = ceremony(1).groth16(Square(3)).unwrap();
+        assert_eq!(alice.key_id(), bob.key_id());
+        assert_eq!(alice.manifest.contributions.len(), 2);
+
+        let proof = groth16_prove(&alice, Square(3)).unwrap();
+        assert!(groth16_verify(&bob, &proof, &[Fr::from(9u64)]).unwrap());
+        assert!(!groth16_verify(&bob, &proof, &[Fr::from(10u64)]).unwrap());
+
+        // 新版本密钥拒绝旧版本证明
+        let rotated = ceremony(2).groth16(Square(3)).unwrap();
+        assert_ne!(rotated.key_id(), alice.key_id());
+        let err = groth16_verify(&rotated, &proof, &[Fr::from(9u64)]).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(SetupError::KeyVersionMismatch { .. })));
+
+        assert!(SetupCeremony::new("square", 1).groth16(Square(3)).is_err());
+    }
+}
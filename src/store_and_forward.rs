@@ -0,0 +1,746 @@
+// DIAP Rust SDK - 离线消息队列（存储转发）
+// 发给当前离线DID的消息先在本地排队（可选落盘），对方重新上线后按入队顺序投递；
+// 也可以把排队消息存放到指定的邮箱中继，由收件人自行取回。
+// 收件人处理消息后回送签名的送达回执，发送方收到回执才把消息移出队列；
+// 落盘队列只追加日志行，条目过多时再压缩
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::clock::{SharedClock, system_clock};
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::{AuthenticatedMessage, SignedMessage};
+use crate::reliable_broadcast::message_hash;
+
+/// 送达回执消息类型标识（PubSubMessageType::Custom）
+pub const DELIVERY_RECEIPT_MESSAGE_TYPE: &str = "delivery_receipt";
+
+/// 收件人签名的送达回执
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryReceipt {
+    /// 已送达的消息ID
+    pub message_id: String,
+
+    /// 消息摘要（hex），防止回执被套用到同ID的其他消息上
+    pub message_hash: String,
+
+    /// 原消息发送者DID
+    pub sender_did: String,
+
+    /// 收件人DID
+    pub recipient_did: String,
+
+    /// 送达时间
+    pub delivered_at: u64,
+
+    /// 收件人签名（base64）
+    pub signature: String,
+}
+
+impl DeliveryReceipt {
+    /// 收件人为收到的消息签发回执
+    pub fn new(signer: &dyn Signer, message: &AuthenticatedMessage, delivered_at: u64) -> Result<Self> {
+        Self {
+            message_id: message.message_id.clone(),
+            message_hash: message_hash(message),
+            sender_did: message.from_did.clone(),
+            recipient_did: signer.did(),
+            delivered_at,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+}
+
+impl SignedEnvelope for DeliveryReceipt {
+    const KIND: &'static str = "送达回执";
+
+    fn signer_did(&self) -> &str {
+        &self.recipient_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+impl SignedMessage for DeliveryReceipt {
+    const MESSAGE_TYPE: &'static str = DELIVERY_RECEIPT_MESSAGE_TYPE;
+}
+
+/// 送达回执保留时间（秒）
+pub const RECEIPT_RETENTION_SECS: u64 = 24 * 3600;
+
+/// 最多保留的送达回执数量
+pub const MAX_RETAINED_RECEIPTS: usize = 1024;
+
+/// 收件人为每个发送者最多缓存的乱序消息数量
+pub const MAX_BUFFERED_PER_SENDER: usize = 256;
+
+/// 日志条目超过该数量且多于存活消息两倍时压缩队列文件
+const COMPACT_THRESHOLD: usize = 1024;
+
+/// 排队中的消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedMessage {
+    /// 发给该收件人的序号（从0开始连续递增，决定投递顺序）
+    pub seq: u64,
+
+    /// 入队时间
+    pub queued_at: u64,
+
+    /// 最近一次投递（直接发送或存放到中继）的时间，未投递为None
+    pub sent_at: Option<u64>,
+
+    /// 消息
+    pub message: AuthenticatedMessage,
+}
+
+/// 直接投递时发给收件人的信封，收件人用InboundSequencer按序号恢复发送顺序
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedEnvelope {
+    /// 发送方队列标识（队列重建后变化，收件人据此重置序号）
+    pub stream_id: String,
+
+    /// 发给该收件人的序号
+    pub seq: u64,
+
+    /// 发送时仍在队列中的最小序号；更小的序号已确认或被放弃，收件人不再等待
+    pub oldest_pending: u64,
+
+    /// 消息
+    pub message: AuthenticatedMessage,
+}
+
+impl QueuedEnvelope {
+    /// 序列化为传输内容
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("序列化排队消息信封失败")
+    }
+
+    /// 从传输内容解析
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).context("解析排队消息信封失败")
+    }
+}
+
+/// 队列日志条目（每行一条，只追加）
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalOp {
+    Stream { id: String },
+    Counter { did: String, next_seq: u64 },
+    Enqueue { queued: Box<QueuedMessage> },
+    Sent { did: String, seq: u64, at: u64 },
+    Requeue { did: String, seq: u64 },
+    Remove { did: String, seq: u64 },
+    Discard { did: String },
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    stream_id: String,
+
+    /// 收件DID -> 下一个序号
+    next_seq: HashMap<String, u64>,
+
+    /// 收件DID -> 序号 -> 消息
+    queues: HashMap<String, BTreeMap<u64, QueuedMessage>>,
+
+    /// 消息ID -> (确认时间, 已收到的回执)
+    receipts: HashMap<String, (u64, DeliveryReceipt)>,
+
+    /// 队列文件中的日志条目数
+    journal_len: usize,
+}
+
+impl QueueState {
+    fn apply(&mut self, op: JournalOp) {
+        match op {
+            JournalOp::Stream { id } => self.stream_id = id,
+            JournalOp::Counter { did, next_seq } => {
+                let next = self.next_seq.entry(did).or_default();
+                *next = (*next).max(next_seq);
+            }
+            JournalOp::Enqueue { queued } => {
+                if let Some(did) = queued.message.to_did.clone() {
+                    let next = self.next_seq.entry(did.clone()).or_default();
+                    *next = (*next).max(queued.seq + 1);
+                    self.queues.entry(did).or_default().insert(queued.seq, *queued);
+                }
+            }
+            JournalOp::Sent { did, seq, at } => {
+                if let Some(queued) = self.queued_mut(&did, seq) {
+                    queued.sent_at = Some(at);
+                }
+            }
+            JournalOp::Requeue { did, seq } => {
+                if let Some(queued) = self.queued_mut(&did, seq) {
+                    queued.sent_at = None;
+                }
+            }
+            JournalOp::Remove { did, seq } => {
+                if let Some(queue) = self.queues.get_mut(&did) {
+                    queue.remove(&seq);
+                    if queue.is_empty() {
+                        self.queues.remove(&did);
+                    }
+                }
+            }
+            JournalOp::Discard { did } => {
+                self.queues.remove(&did);
+            }
+        }
+    }
+
+    fn queued_mut(&mut self, did: &str, seq: u64) -> Option<&mut QueuedMessage> {
+        self.queues.get_mut(did).and_then(|queue| queue.get_mut(&seq))
+    }
+
+    fn live_messages(&self) -> usize {
+        self.queues.values().map(BTreeMap::len).sum()
+    }
+
+    /// 清理过期回执，超出上限时移除最早确认的
+    fn prune_receipts(&mut self, now: u64) {
+        self.receipts.retain(|_, (acked_at, _)| now.saturating_sub(*acked_at) < RECEIPT_RETENTION_SECS);
+        while self.receipts.len() > MAX_RETAINED_RECEIPTS {
+            let oldest = self.receipts.iter()
+                .min_by_key(|(_, (acked_at, _))| *acked_at)
+                .map(|(id, _)| id.clone());
+            let Some(id) = oldest else { break };
+            self.receipts.remove(&id);
+        }
+    }
+}
+
+/// 离线消息队列
+pub struct OfflineQueue {
+    state: Mutex<QueueState>,
+
+    /// 落盘路径（None时只在内存中排队）
+    path: Option<PathBuf>,
+
+    clock: SharedClock,
+}
+
+impl OfflineQueue {
+    /// 创建内存队列
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                stream_id: new_stream_id(),
+                ..QueueState::default()
+            }),
+            path: None,
+            clock: system_clock(),
+        }
+    }
+
+    /// 打开（或创建）落盘队列，进程重启后未送达的消息仍会投递
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建离线队列目录: {:?}", parent))?;
+        }
+
+        let mut state = QueueState::default();
+        if path.exists() {
+            let content = std::fs::read_to_string(&path).with_context(|| format!("无法读取离线队列: {:?}", path))?;
+            let lines: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).collect();
+            for (i, line) in lines.iter().enumerate() {
+                match serde_json::from_str::<JournalOp>(line) {
+                    Ok(op) => state.apply(op),
+                    // 崩溃时可能留下写了一半的最后一行
+                    Err(e) if i + 1 == lines.len() => log::warn!("⚠️ 忽略离线队列末尾不完整的条目: {}", e),
+                    Err(e) => return Err(e).context("解析离线队列失败"),
+                }
+            }
+        }
+        if state.stream_id.is_empty() {
+            state.stream_id = new_stream_id();
+        }
+        log::info!("💾 离线队列: {:?} ({}条待投递)", path, state.live_messages());
+
+        let queue = Self {
+            state: Mutex::new(state),
+            path: Some(path),
+            clock: system_clock(),
+        };
+        queue.compact(&mut queue.state.lock().unwrap())?;
+        Ok(queue)
+    }
+
+    /// 使用指定时间源
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 为离线收件人排队一条消息，返回发给该收件人的序号
+    pub fn enqueue(&self, message: AuthenticatedMessage) -> Result<u64> {
+        let did = message.to_did.clone()
+            .ok_or_else(|| anyhow::anyhow!("消息缺少收件人DID: {}", message.message_id))?;
+
+        let mut state = self.state.lock().unwrap();
+        let seq = state.next_seq.get(&did).copied().unwrap_or(0);
+        log::debug!("📥 消息 {} 排队等待 {} 上线 (#{})", message.message_id, did, seq);
+        let queued = QueuedMessage {
+            seq,
+            queued_at: self.clock.now_secs(),
+            sent_at: None,
+            message,
+        };
+        self.commit(&mut state, vec![JournalOp::Enqueue { queued: Box::new(queued) }])?;
+        Ok(seq)
+    }
+
+    /// 等待送达回执的消息（按入队顺序，包含已投递但尚未确认的）
+    pub fn pending_messages(&self, did: &str) -> Vec<AuthenticatedMessage> {
+        self.state.lock().unwrap().queues.get(did)
+            .map(|queue| queue.values().map(|queued| queued.message.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// 有待投递消息的收件人
+    pub fn recipients(&self) -> Vec<String> {
+        self.state.lock().unwrap().queues.keys().cloned().collect()
+    }
+
+    /// 待确认消息总数
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().live_messages()
+    }
+
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 收件人上线后按入队顺序投递尚未投递的消息，返回本次投递的条数
+    ///
+    /// 每条消息装在带序号的信封里，收件人用InboundSequencer恢复顺序；
+    /// 某条投递失败时立即停止，后面的消息不会越过它先送达；已投递的消息保留到收到回执为止
+    pub async fn flush<F, Fut>(&self, did: &str, mut deliver: F) -> Result<usize>
+    where
+        F: FnMut(QueuedEnvelope) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let unsent: Vec<QueuedEnvelope> = {
+            let state = self.state.lock().unwrap();
+            state.queues.get(did)
+                .map(|queue| {
+                    let oldest_pending = queue.keys().next().copied().unwrap_or(0);
+                    queue.values()
+                        .filter(|queued| queued.sent_at.is_none())
+                        .map(|queued| QueuedEnvelope {
+                            stream_id: state.stream_id.clone(),
+                            seq: queued.seq,
+                            oldest_pending,
+                            message: queued.message.clone(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut delivered = 0;
+        let mut failure = None;
+        for envelope in unsent {
+            let (seq, message_id) = (envelope.seq, envelope.message.message_id.clone());
+            if let Err(e) = deliver(envelope).await {
+                log::warn!("⚠️ 投递排队消息 {} 失败，后续消息暂缓: {}", message_id, e);
+                failure = Some(e);
+                break;
+            }
+            self.mark_sent(did, seq)?;
+            delivered += 1;
+        }
+
+        match failure {
+            Some(e) if delivered == 0 => Err(e),
+            _ => Ok(delivered),
+        }
+    }
+
+    /// 把尚未投递的消息按顺序存放到邮箱中继（中继按存放顺序交给收件人），返回存放条数
+    #[cfg(feature = "node")]
+    pub async fn deposit_to_relay(
+        &self,
+        did: &str,
+        relay_addr: &libp2p::Multiaddr,
+        timeout: std::time::Duration,
+    ) -> Result<usize> {
+        use crate::relay_node::{RelayRequest, RelayResponse, relay_request};
+
+        self.flush(did, |envelope| async move {
+            match relay_request(relay_addr, &RelayRequest::Deposit(Box::new(envelope.message)), timeout).await? {
+                RelayResponse::Stored { .. } => Ok(()),
+                RelayResponse::Rejected(reason) => anyhow::bail!("中继拒绝存放: {}", reason),
+                other => anyhow::bail!("中继返回了意外的响应: {:?}", other),
+            }
+        }).await
+    }
+
+    /// 已投递但超过timeout_secs仍未收到回执的消息重新标记为未投递，返回数量
+    pub fn requeue_unacknowledged(&self, timeout_secs: u64) -> Result<usize> {
+        let now = self.clock.now_secs();
+        let mut state = self.state.lock().unwrap();
+        let ops: Vec<JournalOp> = state.queues.iter()
+            .flat_map(|(did, queue)| queue.values()
+                .filter(|queued| queued.sent_at.is_some_and(|sent_at| now.saturating_sub(sent_at) >= timeout_secs))
+                .map(|queued| JournalOp::Requeue { did: did.clone(), seq: queued.seq }))
+            .collect();
+        let requeued = ops.len();
+        self.commit(&mut state, ops)?;
+        Ok(requeued)
+    }
+
+    /// 处理送达回执：验证收件人签名和消息摘要后把消息移出队列，返回是否匹配到排队消息
+    pub fn acknowledge(&self, receipt: &DeliveryReceipt) -> Result<bool> {
+        if !receipt.verify()? {
+            anyhow::bail!("送达回执签名无效: {}", receipt.message_id);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let Some(queue) = state.queues.get(&receipt.recipient_did) else {
+            return Ok(false);
+        };
+        let Some(queued) = queue.values().find(|queued| queued.message.message_id == receipt.message_id) else {
+            return Ok(false);
+        };
+        if message_hash(&queued.message) != receipt.message_hash {
+            anyhow::bail!("送达回执与排队消息不符: {}", receipt.message_id);
+        }
+
+        let seq = queued.seq;
+        self.commit(&mut state, vec![JournalOp::Remove { did: receipt.recipient_did.clone(), seq }])?;
+        let now = self.clock.now_secs();
+        state.receipts.insert(receipt.message_id.clone(), (now, receipt.clone()));
+        state.prune_receipts(now);
+        log::debug!("✅ 消息 {} 已送达 {}", receipt.message_id, receipt.recipient_did);
+        Ok(true)
+    }
+
+    /// 已收到的送达回执（保留RECEIPT_RETENTION_SECS，最多MAX_RETAINED_RECEIPTS条）
+    pub fn receipt(&self, message_id: &str) -> Option<DeliveryReceipt> {
+        self.state.lock().unwrap().receipts.get(message_id).map(|(_, receipt)| receipt.clone())
+    }
+
+    /// 放弃发给某个DID的全部排队消息和回执，返回放弃的消息数量
+    pub fn discard(&self, did: &str) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let removed = state.queues.get(did).map(BTreeMap::len).unwrap_or(0);
+        state.receipts.retain(|_, (_, receipt)| receipt.recipient_did != did);
+        if removed > 0 {
+            self.commit(&mut state, vec![JournalOp::Discard { did: did.to_string() }])?;
+        }
+        Ok(removed)
+    }
+
+    fn mark_sent(&self, did: &str, seq: u64) -> Result<()> {
+        let at = self.clock.now_secs();
+        let mut state = self.state.lock().unwrap();
+        if state.queued_mut(did, seq).is_none() {
+            return Ok(());
+        }
+        self.commit(&mut state, vec![JournalOp::Sent { did: did.to_string(), seq, at }])
+    }
+
+    /// 追加日志后应用到内存状态
+    fn commit(&self, state: &mut QueueState, ops: Vec<JournalOp>) -> Result<()> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+        self.append(state, &ops)?;
+        for op in ops {
+            state.apply(op);
+        }
+        if state.journal_len > COMPACT_THRESHOLD && state.journal_len > 2 * state.live_messages() {
+            self.compact(state)?;
+        }
+        Ok(())
+    }
+
+    /// 把日志条目追加到队列文件末尾
+    fn append(&self, state: &mut QueueState, ops: &[JournalOp]) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut buf = Vec::new();
+        for op in ops {
+            serde_json::to_writer(&mut buf, op).context("序列化离线队列条目失败")?;
+            buf.push(b'\n');
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("无法打开离线队列: {:?}", path))?;
+        file.write_all(&buf).with_context(|| format!("无法写入离线队列: {:?}", path))?;
+        state.journal_len += ops.len();
+        Ok(())
+    }
+
+    /// 压缩队列文件：只保留当前状态（先写临时文件再改名，避免崩溃时留下半个文件）
+    fn compact(&self, state: &mut QueueState) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut ops = vec![JournalOp::Stream { id: state.stream_id.clone() }];
+        ops.extend(state.next_seq.iter().map(|(did, next_seq)| JournalOp::Counter { did: did.clone(), next_seq: *next_seq }));
+        ops.extend(state.queues.values().flat_map(BTreeMap::values).map(|queued| JournalOp::Enqueue { queued: Box::new(queued.clone()) }));
+
+        let mut buf = Vec::new();
+        for op in &ops {
+            serde_json::to_writer(&mut buf, op).context("序列化离线队列条目失败")?;
+            buf.push(b'\n');
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, buf).with_context(|| format!("无法写入离线队列: {:?}", tmp))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("无法替换离线队列文件: {:?}", path))?;
+        state.journal_len = ops.len();
+        Ok(())
+    }
+}
+
+impl Default for OfflineQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn new_stream_id() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+/// 收件人一侧按发送者恢复排队消息的发送顺序
+///
+/// 序号不连续时先缓存后到的消息，直到缺失的序号到达或发送方表明它已被放弃
+#[derive(Default)]
+pub struct InboundSequencer {
+    streams: Mutex<HashMap<String, InboundStream>>,
+}
+
+struct InboundStream {
+    stream_id: String,
+    next_seq: u64,
+    buffered: BTreeMap<u64, AuthenticatedMessage>,
+}
+
+impl InboundSequencer {
+    /// 创建排序器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 接收排队消息信封（消息应已通过verify_message验证），返回可以按顺序交给应用的消息
+    pub fn accept(&self, envelope: QueuedEnvelope) -> Result<Vec<AuthenticatedMessage>> {
+        if envelope.oldest_pending > envelope.seq {
+            anyhow::bail!("排队消息序号无效: {} < {}", envelope.seq, envelope.oldest_pending);
+        }
+
+        let mut streams = self.streams.lock().unwrap();
+        let sender = envelope.message.from_did.clone();
+        let stream = streams.entry(sender.clone()).or_insert_with(|| InboundStream {
+            stream_id: envelope.stream_id.clone(),
+            next_seq: envelope.oldest_pending,
+            buffered: BTreeMap::new(),
+        });
+        if stream.stream_id != envelope.stream_id {
+            log::debug!("🔄 {} 的离线队列已重建，重置序号", sender);
+            *stream = InboundStream {
+                stream_id: envelope.stream_id.clone(),
+                next_seq: envelope.oldest_pending,
+                buffered: BTreeMap::new(),
+            };
+        }
+
+        // 小于oldest_pending的序号已被发送方放弃，不再等待
+        if envelope.oldest_pending > stream.next_seq {
+            stream.next_seq = envelope.oldest_pending;
+            stream.buffered = stream.buffered.split_off(&stream.next_seq);
+        }
+        if envelope.seq < stream.next_seq || stream.buffered.contains_key(&envelope.seq) {
+            log::debug!("忽略重复的排队消息: {} (#{})", envelope.message.message_id, envelope.seq);
+            return Ok(Vec::new());
+        }
+        if envelope.seq != stream.next_seq && stream.buffered.len() >= MAX_BUFFERED_PER_SENDER {
+            anyhow::bail!("{} 的乱序消息过多，等待序号 {}", sender, stream.next_seq);
+        }
+
+        stream.buffered.insert(envelope.seq, envelope.message);
+        let mut ready = Vec::new();
+        while let Some(message) = stream.buffered.remove(&stream.next_seq) {
+            ready.push(message);
+            stream.next_seq += 1;
+        }
+        Ok(ready)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::pubsub_authenticator::PubSubMessageType;
+
+    fn message(id: &str, sender: &KeyPair, to: &str) -> AuthenticatedMessage {
+        AuthenticatedMessage {
+            message_id: id.to_string(),
+            message_type: PubSubMessageType::Custom("task".to_string()),
+            from_did: sender.did.clone(),
+            to_did: Some(to.to_string()),
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: "inbox".to_string(),
+            content: id.as_bytes().to_vec(),
+            nonce: id.to_string(),
+            zkp_proof: Vec::new(),
+            signature: Vec::new(),
+            timestamp: 0,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ordered_delivery_and_receipts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.json");
+        let sender = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+
+        let queue = OfflineQueue::open(&path).unwrap();
+        for id in ["m1", "m2", "m3"] {
+            queue.enqueue(message(id, &sender, &bob.did)).unwrap();
+        }
+        assert!(queue.enqueue(AuthenticatedMessage { to_did: None, ..message("x", &sender, &bob.did) }).is_err());
+        drop(queue);
+
+        // 重启后队列仍在，第二条投递失败时第三条不会越过它
+        let queue = OfflineQueue::open(&path).unwrap();
+        assert_eq!(queue.len(), 3);
+        let mut sent = Vec::new();
+        let delivered = queue.flush(&bob.did, |e| {
+            let fail = e.message.message_id == "m2";
+            sent.push(e.message.message_id);
+            async move { if fail { anyhow::bail!("offline") } else { Ok(()) } }
+        }).await.unwrap();
+        assert_eq!(delivered, 1);
+        assert_eq!(sent, vec!["m1", "m2"]);
+
+        let mut sent = Vec::new();
+        queue.flush(&bob.did, |e| {
+            sent.push(e.message.message_id);
+            async { Ok(()) }
+        }).await.unwrap();
+        assert_eq!(sent, vec!["m2", "m3"]);
+        let pending: Vec<String> = queue.pending_messages(&bob.did).into_iter().map(|m| m.message_id).collect();
+        assert_eq!(pending, vec!["m1", "m2", "m3"], "收到回执前保留");
+
+        // 回执必须由收件人签名且与排队消息一致
+        let forged = DeliveryReceipt::new(&sender, &message("m1", &sender, &bob.did), 10).unwrap();
+        assert!(!queue.acknowledge(&forged).unwrap());
+        let altered = AuthenticatedMessage { content: b"altered".to_vec(), ..message("m2", &sender, &bob.did) };
+        let mismatched = DeliveryReceipt::new(&bob, &altered, 10).unwrap();
+        assert!(queue.acknowledge(&mismatched).is_err());
+
+        let receipt = DeliveryReceipt::new(&bob, &message("m1", &sender, &bob.did), 10).unwrap();
+        assert!(queue.acknowledge(&receipt).unwrap());
+        assert_eq!(queue.receipt("m1"), Some(receipt));
+        assert_eq!(queue.pending_messages(&bob.did).len(), 2);
+
+        // 超时未确认的消息重新投递
+        assert_eq!(queue.requeue_unacknowledged(0).unwrap(), 2);
+        drop(queue);
+        let queue = OfflineQueue::open(&path).unwrap();
+        let mut sent = Vec::new();
+        queue.flush(&bob.did, |e| {
+            sent.push(e.message.message_id);
+            async { Ok(()) }
+        }).await.unwrap();
+        assert_eq!(sent, vec!["m2", "m3"]);
+    }
+
+    #[tokio::test]
+    async fn test_sequenced_delivery_across_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.json");
+        let sender = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+
+        let queue = OfflineQueue::open(&path).unwrap();
+        for id in ["m0", "m1", "m2"] {
+            queue.enqueue(message(id, &sender, &bob.did)).unwrap();
+        }
+        let mut envelopes = Vec::new();
+        queue.flush(&bob.did, |e| {
+            envelopes.push(e);
+            async { Ok(()) }
+        }).await.unwrap();
+        assert_eq!(envelopes.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        // 收件人按序号恢复顺序，重复的消息被忽略
+        let inbox = InboundSequencer::new();
+        assert!(inbox.accept(envelopes[1].clone()).unwrap().is_empty());
+        let ready: Vec<String> = inbox.accept(envelopes[0].clone()).unwrap().into_iter().map(|m| m.message_id).collect();
+        assert_eq!(ready, vec!["m0", "m1"]);
+        assert!(inbox.accept(envelopes[0].clone()).unwrap().is_empty());
+
+        // 确认全部消息后重启，序号继续递增；只追加日志，不整体重写
+        for id in ["m0", "m1", "m2"] {
+            queue.acknowledge(&DeliveryReceipt::new(&bob, &message(id, &sender, &bob.did), 10).unwrap()).unwrap();
+        }
+        assert!(queue.is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 10);
+        drop(queue);
+
+        let queue = OfflineQueue::open(&path).unwrap();
+        assert_eq!(queue.enqueue(message("m3", &sender, &bob.did)).unwrap(), 3);
+        queue.enqueue(message("m4", &sender, &bob.did)).unwrap();
+        queue.discard(&bob.did).unwrap();
+        queue.enqueue(message("m5", &sender, &bob.did)).unwrap();
+        let mut envelopes = Vec::new();
+        queue.flush(&bob.did, |e| {
+            envelopes.push(e);
+            async { Ok(()) }
+        }).await.unwrap();
+
+        // 被放弃的m3、m4不会让收件人一直等待
+        let ready: Vec<String> = inbox.accept(envelopes.remove(0)).unwrap().into_iter().map(|m| m.message_id).collect();
+        assert_eq!(ready, vec!["m5"]);
+    }
+
+    #[test]
+    fn test_receipts_are_pruned() {
+        let sender = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let clock = crate::clock::MockClock::new(1_000);
+        let queue = OfflineQueue::new().with_clock(std::sync::Arc::new(clock.clone()));
+
+        queue.enqueue(message("m1", &sender, &bob.did)).unwrap();
+        queue.acknowledge(&DeliveryReceipt::new(&bob, &message("m1", &sender, &bob.did), 10).unwrap()).unwrap();
+        assert!(queue.receipt("m1").is_some());
+
+        clock.advance(std::time::Duration::from_secs(RECEIPT_RETENTION_SECS));
+        queue.enqueue(message("m2", &sender, &bob.did)).unwrap();
+        queue.acknowledge(&DeliveryReceipt::new(&bob, &message("m2", &sender, &bob.did), 10).unwrap()).unwrap();
+        assert!(queue.receipt("m1").is_none());
+        assert!(queue.receipt("m2").is_some());
+
+        queue.discard(&bob.did).unwrap();
+        assert!(queue.receipt("m2").is_none());
+    }
+}
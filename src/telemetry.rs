@@ -0,0 +1,157 @@
+// DIAP Rust SDK - 追踪与OpenTelemetry导出
+// 验证流水线（DID获取 → 缓存 → ZKP验证 → 签名验证）以tracing span记录，
+// 这里安装订阅者：控制台输出（可选span耗时）和可选的OTLP导出，log宏的记录也会进入同一订阅者
+
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::config_manager::LoggingConfig;
+
+/// 默认服务名（OTLP资源属性 service.name）
+pub const DEFAULT_SERVICE_NAME: &str = "diap-agent";
+
+/// 追踪配置
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// 服务名
+    pub service_name: String,
+
+    /// 过滤规则（EnvFilter语法，RUST_LOG环境变量优先）
+    pub filter: String,
+
+    /// OTLP gRPC端点，None时只输出到控制台
+    pub otlp_endpoint: Option<String>,
+
+    /// span结束时输出耗时
+    pub span_timings: bool,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            service_name: DEFAULT_SERVICE_NAME.to_string(),
+            filter: "info".to_string(),
+            otlp_endpoint: None,
+            span_timings: false,
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// 从日志配置创建
+    pub fn from_logging(service_name: impl Into<String>, logging: &LoggingConfig) -> Self {
+        Self {
+            service_name: service_name.into(),
+            filter: logging.level.clone(),
+            otlp_endpoint: logging.otlp_endpoint.clone(),
+            span_timings: logging.span_timings,
+        }
+    }
+}
+
+/// 追踪守卫：drop时刷新并关闭OTLP导出器，应保持到进程退出前
+#[must_use = "守卫被drop时OTLP导出器随之关闭"]
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                log::warn!("⚠️ 关闭OTLP导出器失败: {}", e);
+            }
+        }
+    }
+}
+
+/// 安装全局tracing订阅者（代替 env_logger::init）
+/// 配置了OTLP端点时需要在tokio运行时中调用
+pub fn init(config: &TelemetryConfig) -> Result<TelemetryGuard> {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.filter))
+        .with_context(|| format!("无效的日志过滤规则: {}", config.filter))?;
+    let span_events = if config.span_timings { FmtSpan::CLOSE } else { FmtSpan::NONE };
+    let fmt = tracing_subscriber::fmt::layer().with_span_events(span_events);
+
+    let provider = config.otlp_endpoint.as_deref()
+        .map(|endpoint| otlp_provider(&config.service_name, endpoint))
+        .transpose()?;
+    let otel = provider.as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("diap-rs-sdk")));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(otel)
+        .try_init()
+        .context("已安装全局tracing订阅者或log记录器")?;
+
+    if let Some(endpoint) = &config.otlp_endpoint {
+        tracing::info!("📡 追踪数据导出到OTLP: {}", endpoint);
+    }
+    Ok(TelemetryGuard { provider })
+}
+
+/// OTLP（gRPC）导出的TracerProvider
+fn otlp_provider(service_name: &str, endpoint: &str) -> Result<SdkTracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .with_context(|| format!("创建OTLP导出器失败: {}", endpoint))?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name.to_string()).build())
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id};
+    use tracing_subscriber::layer::Context as LayerContext;
+    use crate::did_cache::DIDCache;
+    use crate::did_resolver::DIDSignatureVerifier;
+    use crate::key_manager::KeyPair;
+
+    /// 记录创建过的span名称
+    struct SpanNames(Arc<Mutex<Vec<String>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanNames {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: LayerContext<'_, S>) {
+            self.0.lock().unwrap().push(attrs.metadata().name().to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signature_verification_emits_span() {
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(SpanNames(names.clone()));
+
+        let keypair = KeyPair::generate().unwrap();
+        let signature = keypair.sign(b"payload").unwrap();
+        let verifier = DIDSignatureVerifier::new(DIDCache::new(None, None));
+        let valid = tracing::subscriber::with_default(subscriber, || {
+            verifier.verify(&keypair.did, b"payload", &signature).unwrap()
+        });
+        assert!(valid);
+        assert!(names.lock().unwrap().iter().any(|name| name == "diap.signature_verify"));
+
+        let config = TelemetryConfig::from_logging("agent", &LoggingConfig {
+            level: "debug".to_string(),
+            otlp_endpoint: None,
+            span_timings: true,
+        });
+        assert_eq!(config.filter, "debug");
+        assert!(config.span_timings);
+    }
+}
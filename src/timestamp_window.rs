@@ -0,0 +1,132 @@
+// DIAP Rust SDK - 时间戳窗口验证
+// 只接受时间戳落在 [now - max_age, now + max_future_skew] 内的消息：
+// 过旧的消息在记录nonce之前就被拒绝，nonce只需保留max_age，重放防护的内存占用有上界；
+// 允许少量未来偏移以容忍节点之间的时钟误差
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// 默认最大消息年龄（与NonceManager默认有效期一致）
+pub const DEFAULT_MAX_MESSAGE_AGE: Duration = Duration::from_secs(300);
+
+/// 默认允许的时钟超前量
+pub const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_secs(30);
+
+/// 时间戳窗口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimestampWindow {
+    /// 允许的最大消息年龄（秒）
+    pub max_age_secs: u64,
+
+    /// 允许时间戳超前本地时钟的最大秒数
+    pub max_future_skew_secs: u64,
+}
+
+impl Default for TimestampWindow {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MESSAGE_AGE, DEFAULT_MAX_CLOCK_SKEW)
+    }
+}
+
+impl TimestampWindow {
+    /// 创建时间戳窗口
+    pub fn new(max_age: Duration, max_future_skew: Duration) -> Self {
+        Self {
+            max_age_secs: max_age.as_secs(),
+            max_future_skew_secs: max_future_skew.as_secs(),
+        }
+    }
+
+    /// 检查时间戳（Unix秒）是否在窗口内
+    pub fn check(&self, timestamp: u64, now: u64) -> Result<(), TimestampViolation> {
+        if timestamp > now {
+            let ahead_secs = timestamp - now;
+            if ahead_secs > self.max_future_skew_secs {
+                return Err(TimestampViolation::InFuture { ahead_secs });
+            }
+        } else {
+            let age_secs = now - timestamp;
+            if age_secs > self.max_age_secs {
+                return Err(TimestampViolation::TooOld { age_secs });
+            }
+        }
+        Ok(())
+    }
+
+    /// 时间戳是否在窗口内
+    pub fn contains(&self, timestamp: u64, now: u64) -> bool {
+        self.check(timestamp, now).is_ok()
+    }
+}
+
+/// 时间戳超出窗口的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampViolation {
+    /// 消息过旧
+    TooOld { age_secs: u64 },
+
+    /// 时间戳超前本地时钟过多
+    InFuture { ahead_secs: u64 },
+}
+
+impl fmt::Display for TimestampViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampViolation::TooOld { age_secs } => write!(f, "消息已过期（{}秒前）", age_secs),
+            TimestampViolation::InFuture { ahead_secs } => write!(f, "消息时间戳超前本地时钟{}秒", ahead_secs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_bounds() {
+        let window = TimestampWindow::new(Duration::from_secs(60), Duration::from_secs(5));
+        let now = 1_000_000;
+
+        assert!(window.contains(now, now));
+        assert!(window.contains(now - 60, now));
+        assert!(window.contains(now + 5, now));
+        assert_eq!(window.check(now - 61, now), Err(TimestampViolation::TooOld { age_secs: 61 }));
+        assert_eq!(window.check(now + 6, now), Err(TimestampViolation::InFuture { ahead_secs: 6 }));
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_rejects_stale_messages() {
+        use crate::clock::MockClock;
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::{CallbackSigner, KeyPair};
+        use crate::nonce_manager::NonceManager;
+        use crate::pubsub_authenticator::{PubSubMessageType, PubsubAuthenticator};
+        use std::sync::Arc;
+
+        let clock = MockClock::new(1_000_000);
+        let authenticator = || {
+            let nonces = NonceManager::new_with_clock(None, None, Arc::new(clock.clone()));
+            PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(5)), Some(nonces), None)
+                .with_clock(Arc::new(clock.clone()))
+                .with_timestamp_window(TimestampWindow::new(Duration::from_secs(60), Duration::from_secs(5)))
+        };
+        let receiver = authenticator();
+        assert_eq!(receiver.timestamp_window().max_age_secs, 60);
+
+        // 回调签名器不生成ZKP证明，创建消息不需要访问IPFS
+        let keypair = KeyPair::generate().unwrap();
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+        let sender = authenticator();
+        sender.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "cid".to_string()).await.unwrap();
+        let message = sender.create_authenticated_message("tasks", PubSubMessageType::Heartbeat, b"ping", None).await.unwrap();
+
+        // 过旧的消息在记录nonce之前被拒绝
+        clock.advance(Duration::from_secs(61));
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details[0].contains("过期"));
+        assert_eq!(receiver.nonce_count(), 0);
+    }
+}
@@ -0,0 +1,155 @@
+// DIAP Rust SDK - 内嵌HTTP服务的TLS
+// did:web/did:wba解析要求HTTPS：加载PEM证书（如ACME客户端签发的证书），
+// 或在未提供证书时自动生成自签名证书
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::{self, ServerConfig};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject};
+
+pub use tokio_rustls::TlsAcceptor;
+
+use crate::config_manager::TlsSettings;
+
+/// PEM格式的证书链和私钥
+#[derive(Debug, Clone)]
+pub struct TlsIdentity {
+    /// 证书链（PEM）
+    pub cert_pem: String,
+
+    /// 私钥（PEM）
+    pub key_pem: String,
+}
+
+impl TlsIdentity {
+    /// 为给定主机名/IP生成自签名证书
+    pub fn self_signed(subject_alt_names: &[String]) -> Result<Self> {
+        if subject_alt_names.is_empty() {
+            anyhow::bail!("自签名证书至少需要一个主机名");
+        }
+        let certified = rcgen::generate_simple_self_signed(subject_alt_names.to_vec())
+            .context("生成自签名证书失败")?;
+        Ok(Self {
+            cert_pem: certified.cert.pem(),
+            key_pem: certified.signing_key.serialize_pem(),
+        })
+    }
+
+    /// 从PEM文件加载
+    pub fn from_pem_files(cert_path: &Path, key_path: &Path) -> Result<Self> {
+        Ok(Self {
+            cert_pem: std::fs::read_to_string(cert_path)
+                .with_context(|| format!("无法读取证书文件: {:?}", cert_path))?,
+            key_pem: std::fs::read_to_string(key_path)
+                .with_context(|| format!("无法读取私钥文件: {:?}", key_path))?,
+        })
+    }
+
+    /// 按配置加载证书：配置了证书文件且存在时读取文件；
+    /// 否则在允许自签名时生成证书（配置了路径则写入文件，重启后证书不变）
+    pub fn from_settings(settings: &TlsSettings) -> Result<Self> {
+        if let (Some(cert_path), Some(key_path)) = (&settings.cert_path, &settings.key_path) {
+            if cert_path.exists() && key_path.exists() {
+                log::info!("🔐 加载TLS证书: {:?}", cert_path);
+                return Self::from_pem_files(cert_path, key_path);
+            }
+            if !settings.self_signed {
+                anyhow::bail!("TLS证书文件不存在: {:?}", cert_path);
+            }
+        } else if !settings.self_signed {
+            anyhow::bail!("未配置TLS证书文件且未启用自签名证书");
+        }
+
+        log::info!("🔐 生成自签名TLS证书: {:?}", settings.subject_alt_names);
+        let identity = Self::self_signed(&settings.subject_alt_names)?;
+        if let (Some(cert_path), Some(key_path)) = (&settings.cert_path, &settings.key_path) {
+            identity.save(cert_path, key_path)?;
+        }
+        Ok(identity)
+    }
+
+    /// 写入PEM文件
+    pub fn save(&self, cert_path: &Path, key_path: &Path) -> Result<()> {
+        for path in [cert_path, key_path] {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("无法创建证书目录: {:?}", parent))?;
+            }
+        }
+        std::fs::write(cert_path, &self.cert_pem)
+            .with_context(|| format!("无法写入证书文件: {:?}", cert_path))?;
+        std::fs::write(key_path, &self.key_pem)
+            .with_context(|| format!("无法写入私钥文件: {:?}", key_path))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(key_path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+
+    /// 证书链（DER）
+    pub fn certificates(&self) -> Result<Vec<CertificateDer<'static>>> {
+        let certs = CertificateDer::pem_slice_iter(self.cert_pem.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .context("证书不是有效的PEM")?;
+        if certs.is_empty() {
+            anyhow::bail!("PEM中没有证书");
+        }
+        Ok(certs)
+    }
+
+    /// 私钥（DER）
+    pub fn private_key_der(&self) -> Result<Vec<u8>> {
+        let key = PrivateKeyDer::from_pem_slice(self.key_pem.as_bytes())
+            .context("私钥不是有效的PEM")?;
+        Ok(key.secret_der().to_vec())
+    }
+
+    /// rustls服务端配置（仅HTTP/1.1）
+    pub fn server_config(&self) -> Result<Arc<ServerConfig>> {
+        let key = PrivateKeyDer::from_pem_slice(self.key_pem.as_bytes())
+            .context("私钥不是有效的PEM")?;
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .context("TLS协议版本配置失败")?
+            .with_no_client_auth()
+            .with_single_cert(self.certificates()?, key)
+            .context("证书与私钥不匹配")?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+
+    /// TLS接收器
+    pub fn acceptor(&self) -> Result<TlsAcceptor> {
+        Ok(TlsAcceptor::from(self.server_config()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_signed_from_settings_is_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = TlsSettings {
+            cert_path: Some(dir.path().join("tls/cert.pem")),
+            key_path: Some(dir.path().join("tls/key.pem")),
+            ..TlsSettings::default()
+        };
+
+        let generated = TlsIdentity::from_settings(&settings).unwrap();
+        assert_eq!(generated.certificates().unwrap().len(), 1);
+        generated.server_config().unwrap();
+
+        // 第二次加载读取已保存的证书
+        let loaded = TlsIdentity::from_settings(&settings).unwrap();
+        assert_eq!(loaded.cert_pem, generated.cert_pem);
+
+        let strict = TlsSettings { cert_path: None, key_path: None, self_signed: false, ..TlsSettings::default() };
+        assert!(TlsIdentity::from_settings(&strict).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+// DIAP Rust SDK - 分层主题与通配符模块
+// 主题按 '/' 分段（例如 diap/agents/<did>/inbox）；订阅和主题配置可以使用通配符：
+//   *  匹配恰好一段
+//   ** 匹配零或多段
+// gossipsub本身只支持精确主题，因此大量细粒度主题通过分片映射到少量网络主题上传输
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+pub use crate::constants::{AGENT_TOPIC_PREFIX, SHARD_TOPIC_PREFIX};
+use crate::constants::network_params;
+
+/// 主题分段分隔符
+pub const TOPIC_SEPARATOR: char = '/';
+
+/// 收件箱主题后缀
+pub const INBOX_SUFFIX: &str = "inbox";
+
+/// 匹配恰好一段
+const SINGLE_WILDCARD: &str = "*";
+
+/// 匹配零或多段
+const MULTI_WILDCARD: &str = "**";
+
+/// 指定DID的收件箱主题
+pub fn inbox_topic(did: &str) -> String {
+    format!("{}/{}/{}", network_params().agent_topic_prefix(), did, INBOX_SUFFIX)
+}
+
+/// 若主题是某个DID的收件箱，返回该DID
+pub fn inbox_owner(topic: &str) -> Option<&str> {
+    let did = topic
+        .strip_prefix(network_params().agent_topic_prefix().as_str())?
+        .strip_prefix(TOPIC_SEPARATOR)?
+        .strip_suffix(INBOX_SUFFIX)?
+        .strip_suffix(TOPIC_SEPARATOR)?;
+    (!did.is_empty() && !did.contains(TOPIC_SEPARATOR)).then_some(did)
+}
+
+/// 主题名是否包含通配符字符（是否合法由 TopicPattern::parse 校验）
+pub fn is_pattern(topic: &str) -> bool {
+    topic.contains('*')
+}
+
+/// 主题到分片网络主题的映射（相同主题总是落在同一分片）
+pub fn shard_topic(topic: &str, shard_count: u16) -> String {
+    format!("{}/{}", network_params().shard_topic_prefix(), shard_index(topic, shard_count))
+}
+
+/// 主题所在的分片序号
+pub fn shard_index(topic: &str, shard_count: u16) -> u16 {
+    let digest = Sha256::digest(topic.as_bytes());
+    let value = u64::from_be_bytes(digest[..8].try_into().expect("摘要长度至少8字节"));
+    (value % shard_count.max(1) as u64) as u16
+}
+
+/// 全部分片网络主题
+pub fn all_shard_topics(shard_count: u16) -> Vec<String> {
+    let prefix = network_params().shard_topic_prefix();
+    (0..shard_count.max(1)).map(|i| format!("{}/{}", prefix, i)).collect()
+}
+
+/// 主题模式（也可以是不含通配符的精确主题）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicPattern {
+    raw: String,
+    segments: Vec<String>,
+}
+
+impl TopicPattern {
+    /// 解析主题模式
+    pub fn parse(pattern: &str) -> Result<Self> {
+        if pattern.is_empty() {
+            anyhow::bail!("主题不能为空");
+        }
+        let segments: Vec<String> = pattern.split(TOPIC_SEPARATOR).map(str::to_string).collect();
+        if segments.iter().any(|s| s.is_empty()) {
+            anyhow::bail!("主题包含空分段: {}", pattern);
+        }
+        if let Some(bad) = segments.iter().find(|s| s.contains('*') && *s != SINGLE_WILDCARD && *s != MULTI_WILDCARD) {
+            anyhow::bail!("通配符必须占据整个分段: {}", bad);
+        }
+        Ok(Self { raw: pattern.to_string(), segments })
+    }
+
+    /// 原始字符串
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// 是否包含通配符
+    pub fn is_wildcard(&self) -> bool {
+        self.segments.iter().any(|s| s == SINGLE_WILDCARD || s == MULTI_WILDCARD)
+    }
+
+    /// 精确分段数（越大越具体，用于在多个匹配的模式中选出最具体的一个）
+    pub fn specificity(&self) -> usize {
+        self.segments.iter().filter(|s| *s != SINGLE_WILDCARD && *s != MULTI_WILDCARD).count()
+    }
+
+    /// 主题是否匹配该模式
+    pub fn matches(&self, topic: &str) -> bool {
+        let topic: Vec<&str> = topic.split(TOPIC_SEPARATOR).collect();
+        let pattern: Vec<&str> = self.segments.iter().map(String::as_str).collect();
+        match_segments(&pattern, &topic)
+    }
+}
+
+impl std::fmt::Display for TopicPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+fn match_segments(pattern: &[&str], topic: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => topic.is_empty(),
+        Some((&MULTI_WILDCARD, rest)) => (0..=topic.len()).any(|skip| match_segments(rest, &topic[skip..])),
+        Some((&segment, rest)) => match topic.split_first() {
+            Some((&first, topic_rest)) => {
+                (segment == SINGLE_WILDCARD || segment == first) && match_segments(rest, topic_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matching() {
+        let inbox = TopicPattern::parse("diap/agents/*/inbox").unwrap();
+        assert!(inbox.matches(&inbox_topic("did:key:z6Mkabc")));
+        assert!(!inbox.matches("diap/agents/did:key:z6Mkabc/outbox"));
+        assert!(!inbox.matches("diap/agents/a/b/inbox"));
+
+        let all = TopicPattern::parse("diap/**").unwrap();
+        assert!(all.matches("diap"));
+        assert!(all.matches("diap/agents/x/inbox"));
+        assert!(!all.matches("other/topic"));
+
+        let middle = TopicPattern::parse("diap/**/inbox").unwrap();
+        assert!(middle.matches("diap/inbox"));
+        assert!(middle.matches("diap/agents/x/inbox"));
+        assert_eq!(middle.specificity(), 2);
+
+        assert!(TopicPattern::parse("diap//inbox").is_err());
+        assert!(TopicPattern::parse("diap/a*").is_err());
+        assert!(!TopicPattern::parse("general").unwrap().is_wildcard());
+    }
+
+    #[test]
+    fn test_inbox_and_shards() {
+        let topic = inbox_topic("did:key:z6Mkabc");
+        assert_eq!(inbox_owner(&topic), Some("did:key:z6Mkabc"));
+        assert_eq!(inbox_owner("diap/agents//inbox"), None);
+        assert_eq!(inbox_owner("general"), None);
+
+        let shard = shard_topic(&topic, 16);
+        assert_eq!(shard, shard_topic(&topic, 16));
+        assert!(all_shard_topics(16).contains(&shard));
+        assert_eq!(all_shard_topics(0), vec![format!("{}/0", SHARD_TOPIC_PREFIX)]);
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_wildcards_and_inbox() {
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::KeyPair;
+        use crate::pubsub_authenticator::{PubsubAuthenticator, TopicConfig, TopicEncryption, TopicPolicy};
+
+        let auth = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(5)), None, None);
+        let keypair = KeyPair::generate().unwrap();
+        auth.set_local_identity(keypair.clone(), libp2p::PeerId::random(), "cid".to_string()).await.unwrap();
+
+        // 设置身份后自动接收本地收件箱
+        let inbox = inbox_topic(&keypair.did);
+        assert_eq!(auth.inbox_topic().await, Some(inbox.clone()));
+        assert!(auth.is_subscribed(&inbox).await);
+        assert!(!auth.is_subscribed(&inbox_topic("did:key:other")).await);
+        assert_eq!(auth.gossip_topics(8).await, vec![shard_topic(&inbox, 8)]);
+
+        auth.subscribe_topic("diap/agents/*/inbox").await.unwrap();
+        assert!(auth.is_subscribed(&inbox_topic("did:key:other")).await);
+        assert_eq!(auth.gossip_topics(8).await.len(), 8);
+        assert!(auth.subscribe_topic("diap/a**").await.is_err());
+
+        let config = |name: &str, require_zkp: bool| TopicConfig {
+            name: name.to_string(),
+            policy: TopicPolicy::AllowAuthenticated,
+            require_zkp,
+            require_signature: true,
+            retention: None,
+            rate_limit: None,
+            encryption: TopicEncryption::Optional,
+        };
+        auth.configure_topic(config("diap/**", false)).await.unwrap();
+        auth.configure_topic(config("diap/agents/*/inbox", true)).await.unwrap();
+        assert_eq!(auth.topic_config_for(&inbox).await.unwrap().name, "diap/agents/*/inbox");
+        assert_eq!(auth.topic_config_for("diap/tasks").await.unwrap().name, "diap/**");
+        assert!(auth.topic_config_for("general").await.is_none());
+    }
+}
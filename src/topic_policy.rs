@@ -0,0 +1,201 @@
+// DIAP Rust SDK - 主题配置导出/导入模块
+// 把完整的主题配置集（授权策略、允许/拒绝列表、速率限制、加密要求、保留策略）
+// 打包成由管理员DID签名的文档，在集群中分发；导入方只接受受信管理员签发的、比已应用版本更新的文档
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::key_manager::{SignedEnvelope, Signer};
+use crate::pubsub_authenticator::TopicConfig;
+
+/// 文档格式版本
+pub const TOPIC_POLICY_VERSION: u32 = 1;
+
+/// 签名的主题配置文档
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicPolicyDocument {
+    /// 格式版本
+    pub version: u32,
+
+    /// 签发者DID
+    pub issuer_did: String,
+
+    /// 签发时间（毫秒，同时作为单调递增的配置版本，防止回滚到旧配置）
+    pub issued_at: u64,
+
+    /// 完整的主题配置集
+    pub topics: Vec<TopicConfig>,
+
+    /// 签发者签名（base64）
+    pub signature: String,
+}
+
+impl TopicPolicyDocument {
+    /// 签发主题配置文档（按主题名排序，保证相同配置得到相同文档）
+    pub fn sign(signer: &dyn Signer, mut topics: Vec<TopicConfig>, issued_at: u64) -> Result<Self> {
+        topics.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            version: TOPIC_POLICY_VERSION,
+            issuer_did: signer.did(),
+            issued_at,
+            topics,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// 验证签名，并确认签发者是受信管理员
+    pub fn verify(&self, admin_dids: &[String]) -> Result<()> {
+        if self.version != TOPIC_POLICY_VERSION {
+            anyhow::bail!("不支持的主题配置文档版本: {}", self.version);
+        }
+        if !admin_dids.contains(&self.issuer_did) {
+            anyhow::bail!("主题配置文档签发者不是受信管理员: {}", self.issuer_did);
+        }
+
+        if !SignedEnvelope::verify(self)? {
+            anyhow::bail!("主题配置文档签名无效");
+        }
+
+        let mut names: Vec<&str> = self.topics.iter().map(|t| t.name.as_str()).collect();
+        names.sort();
+        if names.windows(2).any(|w| w[0] == w[1]) {
+            anyhow::bail!("主题配置文档包含重复的主题");
+        }
+        Ok(())
+    }
+
+    /// 序列化为JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("序列化主题配置文档失败")
+    }
+
+    /// 从JSON解析
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("解析主题配置文档失败")
+    }
+
+    /// 保存到文件
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_json()?)
+            .with_context(|| format!("无法写入主题配置文档: {:?}", path))
+    }
+
+    /// 从文件加载
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取主题配置文档: {:?}", path))?;
+        Self::from_json(&json)
+    }
+}
+
+impl SignedEnvelope for TopicPolicyDocument {
+    const KIND: &'static str = "主题配置文档";
+
+    fn signer_did(&self) -> &str {
+        &self.issuer_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+    use crate::message_archive::RetentionPolicy;
+    use crate::pubsub_authenticator::{RateLimit, TopicEncryption, TopicPolicy};
+
+    fn topics(admin: &KeyPair) -> Vec<TopicConfig> {
+        vec![
+            TopicConfig {
+                name: "tasks".to_string(),
+                policy: TopicPolicy::AllowList(vec![admin.did.clone()]),
+                require_zkp: true,
+                require_signature: true,
+                retention: Some(RetentionPolicy { retention_hours: 24, require_deletion_ack: false }),
+                rate_limit: Some(RateLimit { per_second: 5.0, burst: 20 }),
+                encryption: TopicEncryption::Required,
+            },
+            TopicConfig {
+                name: "heartbeat".to_string(),
+                policy: TopicPolicy::AllowAuthenticated,
+                require_zkp: false,
+                require_signature: true,
+                retention: None,
+                rate_limit: None,
+                encryption: TopicEncryption::Optional,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_sign_and_verify_document() {
+        let admin = KeyPair::generate().unwrap();
+        let other = KeyPair::generate().unwrap();
+        let document = TopicPolicyDocument::sign(&admin, topics(&admin), 1_000).unwrap();
+        assert_eq!(document.topics[0].name, "heartbeat");
+
+        let admins = vec![admin.did.clone()];
+        let parsed = TopicPolicyDocument::from_json(&document.to_json().unwrap()).unwrap();
+        parsed.verify(&admins).unwrap();
+
+        // 非管理员签发、或内容被篡改都会被拒绝
+        assert!(parsed.verify(std::slice::from_ref(&other.did)).is_err());
+        let mut tampered = parsed.clone();
+        tampered.topics[1].policy = TopicPolicy::AllowAuthenticated;
+        assert!(tampered.verify(&admins).is_err());
+        let forged = TopicPolicyDocument::sign(&other, topics(&admin), 2_000).unwrap();
+        assert!(forged.verify(&admins).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_across_fleet() {
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+
+        let authenticator = || PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(5)), None, None);
+        let admin = KeyPair::generate().unwrap();
+        let admins = vec![admin.did.clone()];
+
+        let operator = authenticator();
+        operator.set_local_identity(admin.clone(), libp2p::PeerId::random(), "cid".to_string()).await.unwrap();
+        for config in topics(&admin) {
+            operator.configure_topic(config).await.unwrap();
+        }
+        let document = operator.export_topic_configs().await.unwrap();
+
+        let agent = authenticator();
+        agent.configure_topic(TopicConfig {
+            name: "stale".to_string(),
+            policy: TopicPolicy::AllowAuthenticated,
+            require_zkp: false,
+            require_signature: false,
+            retention: None,
+            rate_limit: None,
+            encryption: TopicEncryption::Optional,
+        }).await.unwrap();
+
+        assert_eq!(agent.import_topic_configs(&document, &admins).await.unwrap(), 2);
+        let mut names: Vec<String> = agent.topic_configs().await.into_iter().map(|t| t.name).collect();
+        names.sort();
+        assert_eq!(names, vec!["heartbeat", "tasks"]);
+
+        // 重放同一文档或旧文档被拒绝，更新的文档可以导入
+        assert!(agent.import_topic_configs(&document, &admins).await.is_err());
+        let newer = TopicPolicyDocument::sign(&admin, vec![], document.issued_at + 1).unwrap();
+        assert_eq!(agent.import_topic_configs(&newer, &admins).await.unwrap(), 0);
+        assert!(agent.topic_configs().await.is_empty());
+    }
+}
@@ -0,0 +1,351 @@
+// DIAP Rust SDK - 流量混淆模块
+// 对选定主题的消息做长度分桶填充，并以随机间隔注入掩护心跳，降低流量分析风险
+
+use anyhow::{Context, Result};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::pubsub_authenticator::{AuthenticatedMessage, PubsubAuthenticator};
+
+/// 长度前缀字节数
+const LENGTH_PREFIX_LEN: usize = 4;
+
+/// 流量混淆配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObfuscationConfig {
+    /// 启用混淆的主题
+    pub topics: Vec<String>,
+
+    /// 填充桶大小（字节，升序）
+    pub buckets: Vec<usize>,
+
+    /// 掩护流量最小间隔（毫秒）
+    pub min_cover_interval_ms: u64,
+
+    /// 掩护流量最大间隔（毫秒）
+    pub max_cover_interval_ms: u64,
+
+    /// 每分钟掩护流量带宽预算（字节）
+    pub cover_budget_bytes_per_minute: u64,
+}
+
+impl Default for ObfuscationConfig {
+    fn default() -> Self {
+        Self {
+            topics: Vec::new(),
+            buckets: vec![256, 1024, 4096, 16384, 65536],
+            min_cover_interval_ms: 5_000,
+            max_cover_interval_ms: 30_000,
+            cover_budget_bytes_per_minute: 256 * 1024,
+        }
+    }
+}
+
+/// 待发布的掩护消息
+#[derive(Debug, Clone)]
+pub struct CoverMessage {
+    /// 主题
+    pub topic: String,
+
+    /// 已填充的消息字节
+    pub payload: Vec<u8>,
+}
+
+/// 流量混淆统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ObfuscationStats {
+    /// 已填充的消息数
+    pub padded_messages: u64,
+
+    /// 填充字节总数
+    pub padding_bytes: u64,
+
+    /// 已发送的掩护消息数
+    pub cover_messages_sent: u64,
+
+    /// 因超出预算而跳过的掩护消息数
+    pub cover_messages_skipped: u64,
+}
+
+/// 带宽预算（固定一分钟窗口）
+struct BandwidthBudget {
+    window_start: Instant,
+    used_bytes: u64,
+    limit_bytes: u64,
+}
+
+impl BandwidthBudget {
+    fn try_consume(&mut self, bytes: u64) -> bool {
+        if self.window_start.elapsed() >= Duration::from_secs(60) {
+            self.window_start = Instant::now();
+            self.used_bytes = 0;
+        }
+
+        if self.used_bytes + bytes > self.limit_bytes {
+            return false;
+        }
+
+        self.used_bytes += bytes;
+        true
+    }
+}
+
+/// 流量混淆器
+#[derive(Clone)]
+pub struct TrafficObfuscator {
+    config: ObfuscationConfig,
+    budget: Arc<Mutex<BandwidthBudget>>,
+    running: Arc<AtomicBool>,
+    cover_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    padded_messages: Arc<AtomicU64>,
+    padding_bytes: Arc<AtomicU64>,
+    cover_sent: Arc<AtomicU64>,
+    cover_skipped: Arc<AtomicU64>,
+}
+
+impl TrafficObfuscator {
+    /// 创建新的流量混淆器
+    pub fn new(mut config: ObfuscationConfig) -> Self {
+        config.buckets.sort_unstable();
+        config.buckets.dedup();
+
+        log::info!("🕶️ 创建流量混淆器，主题: {:?}", config.topics);
+
+        let limit_bytes = config.cover_budget_bytes_per_minute;
+        Self {
+            config,
+            budget: Arc::new(Mutex::new(BandwidthBudget {
+                window_start: Instant::now(),
+                used_bytes: 0,
+                limit_bytes,
+            })),
+            running: Arc::new(AtomicBool::new(false)),
+            cover_task: Arc::new(Mutex::new(None)),
+            padded_messages: Arc::new(AtomicU64::new(0)),
+            padding_bytes: Arc::new(AtomicU64::new(0)),
+            cover_sent: Arc::new(AtomicU64::new(0)),
+            cover_skipped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 主题是否启用了混淆
+    pub fn is_obfuscated_topic(&self, topic: &str) -> bool {
+        self.config.topics.iter().any(|t| t == topic)
+    }
+
+    /// 填充到桶大小：4字节长度前缀 + 内容 + 随机填充
+    pub fn pad(&self, content: &[u8]) -> Result<Vec<u8>> {
+        let framed_len = content.len() + LENGTH_PREFIX_LEN;
+        let content_len = u32::try_from(content.len()).context("消息过大，无法填充")?;
+
+        let target_len = match self.config.buckets.iter().find(|&&b| b >= framed_len) {
+            Some(&bucket) => bucket,
+            None => {
+                // 超过最大桶时按最大桶的整数倍向上取整
+                let largest = self.config.buckets.last().copied().unwrap_or(framed_len).max(1);
+                framed_len.div_ceil(largest) * largest
+            }
+        };
+
+        let mut padded = Vec::with_capacity(target_len);
+        padded.extend_from_slice(&content_len.to_be_bytes());
+        padded.extend_from_slice(content);
+
+        let mut padding = vec![0u8; target_len - framed_len];
+        rand::thread_rng().fill_bytes(&mut padding);
+        padded.extend_from_slice(&padding);
+
+        self.padded_messages.fetch_add(1, Ordering::Relaxed);
+        self.padding_bytes.fetch_add(padding.len() as u64, Ordering::Relaxed);
+
+        Ok(padded)
+    }
+
+    /// 去除填充
+    pub fn unpad(padded: &[u8]) -> Result<Vec<u8>> {
+        if padded.len() < LENGTH_PREFIX_LEN {
+            anyhow::bail!("填充消息过短");
+        }
+
+        let mut len_bytes = [0u8; LENGTH_PREFIX_LEN];
+        len_bytes.copy_from_slice(&padded[..LENGTH_PREFIX_LEN]);
+        let content_len = u32::from_be_bytes(len_bytes) as usize;
+
+        if LENGTH_PREFIX_LEN + content_len > padded.len() {
+            anyhow::bail!("填充消息长度字段无效");
+        }
+
+        Ok(padded[LENGTH_PREFIX_LEN..LENGTH_PREFIX_LEN + content_len].to_vec())
+    }
+
+    /// 序列化待发送消息，混淆主题上的消息会被填充
+    pub fn prepare_outgoing(&self, message: &AuthenticatedMessage) -> Result<Vec<u8>> {
+        let data = PubsubAuthenticator::serialize_message(message)?;
+        if self.is_obfuscated_topic(&message.topic) {
+            self.pad(&data)
+        } else {
+            Ok(data)
+        }
+    }
+
+    /// 解析收到的消息
+    pub fn parse_incoming(&self, topic: &str, data: &[u8]) -> Result<AuthenticatedMessage> {
+        if self.is_obfuscated_topic(topic) {
+            PubsubAuthenticator::deserialize_message(&Self::unpad(data)?)
+        } else {
+            PubsubAuthenticator::deserialize_message(data)
+        }
+    }
+
+    /// 启动掩护流量：以随机间隔在混淆主题上生成心跳，
+    /// 返回的接收端由调用方发布到gossipsub；停止后重新启动会先终止旧的循环
+    pub fn start_cover_traffic(
+        &self,
+        authenticator: Arc<PubsubAuthenticator>,
+    ) -> mpsc::UnboundedReceiver<CoverMessage> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        if self.running.swap(true, Ordering::SeqCst) {
+            log::warn!("掩护流量已在运行");
+            return receiver;
+        }
+
+        let obfuscator = self.clone();
+        let handle = tokio::spawn(async move {
+            log::info!("🕶️ 掩护流量已启动");
+
+            while obfuscator.running.load(Ordering::SeqCst) {
+                let (delay, topic) = {
+                    let mut rng = rand::thread_rng();
+                    let min = obfuscator.config.min_cover_interval_ms;
+                    let max = obfuscator.config.max_cover_interval_ms.max(min);
+                    let delay = Duration::from_millis(rng.gen_range(min..=max));
+                    let topic = if obfuscator.config.topics.is_empty() {
+                        None
+                    } else {
+                        let index = rng.gen_range(0..obfuscator.config.topics.len());
+                        Some(obfuscator.config.topics[index].clone())
+                    };
+                    (delay, topic)
+                };
+
+                tokio::time::sleep(delay).await;
+
+                let Some(topic) = topic else { continue };
+                if !obfuscator.running.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let payload = match authenticator.create_heartbeat(&topic).await
+                    .and_then(|message| obfuscator.prepare_outgoing(&message))
+                {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        log::warn!("生成掩护消息失败: {}", e);
+                        continue;
+                    }
+                };
+
+                let within_budget = obfuscator.budget
+                    .lock()
+                    .map(|mut budget| budget.try_consume(payload.len() as u64))
+                    .unwrap_or(false);
+
+                if !within_budget {
+                    obfuscator.cover_skipped.fetch_add(1, Ordering::Relaxed);
+                    log::debug!("掩护流量超出带宽预算，跳过");
+                    continue;
+                }
+
+                if sender.send(CoverMessage { topic, payload }).is_err() {
+                    break;
+                }
+                obfuscator.cover_sent.fetch_add(1, Ordering::Relaxed);
+            }
+
+            obfuscator.running.store(false, Ordering::SeqCst);
+            log::info!("🕶️ 掩护流量已停止");
+        });
+        if let Some(previous) = self.cover_task.lock().unwrap().replace(handle) {
+            previous.abort();
+        }
+
+        receiver
+    }
+
+    /// 停止掩护流量
+    pub fn stop_cover_traffic(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.cover_task.lock().unwrap().take() {
+            handle.abort();
+        }
+    }
+
+    /// 获取统计信息
+    pub fn stats(&self) -> ObfuscationStats {
+        ObfuscationStats {
+            padded_messages: self.padded_messages.load(Ordering::Relaxed),
+            padding_bytes: self.padding_bytes.load(Ordering::Relaxed),
+            cover_messages_sent: self.cover_sent.load(Ordering::Relaxed),
+            cover_messages_skipped: self.cover_skipped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_to_bucket() {
+        let obfuscator = TrafficObfuscator::new(ObfuscationConfig::default());
+
+        let padded = obfuscator.pad(b"hello").unwrap();
+        assert_eq!(padded.len(), 256);
+        assert_eq!(TrafficObfuscator::unpad(&padded).unwrap(), b"hello");
+
+        let large = vec![7u8; 2000];
+        let padded = obfuscator.pad(&large).unwrap();
+        assert_eq!(padded.len(), 4096);
+        assert_eq!(TrafficObfuscator::unpad(&padded).unwrap(), large);
+    }
+
+    #[test]
+    fn test_pad_beyond_largest_bucket() {
+        let config = ObfuscationConfig {
+            buckets: vec![128],
+            ..Default::default()
+        };
+        let obfuscator = TrafficObfuscator::new(config);
+
+        let content = vec![1u8; 300];
+        let padded = obfuscator.pad(&content).unwrap();
+        assert_eq!(padded.len(), 384);
+        assert_eq!(TrafficObfuscator::unpad(&padded).unwrap(), content);
+    }
+
+    #[test]
+    fn test_bandwidth_budget() {
+        let mut budget = BandwidthBudget {
+            window_start: Instant::now(),
+            used_bytes: 0,
+            limit_bytes: 1000,
+        };
+
+        assert!(budget.try_consume(600));
+        assert!(!budget.try_consume(600));
+        assert!(budget.try_consume(400));
+    }
+
+    #[test]
+    fn test_unpad_rejects_invalid_length() {
+        let mut data = vec![0u8; 16];
+        data[..4].copy_from_slice(&100u32.to_be_bytes());
+        assert!(TrafficObfuscator::unpad(&data).is_err());
+    }
+}
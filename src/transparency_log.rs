@@ -0,0 +1,677 @@
+// DIAP Rust SDK - 密钥透明日志模块
+// 类似证书透明（CT）的仅追加Merkle日志：DID创建与轮换事件提交到日志，
+// 智能体可要求轮换附带包含证明后才信任，监控者可检测日志分叉（同一树大小不同根）和冲突轮换
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, RwLock};
+
+use crate::did_builder::KeyRotationProof;
+use crate::key_manager::{SignedEnvelope, Signer};
+
+/// 叶子哈希前缀（RFC 6962）
+const LEAF_PREFIX: u8 = 0x00;
+
+/// 内部节点哈希前缀（RFC 6962）
+const NODE_PREFIX: u8 = 0x01;
+
+/// 日志事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransparencyEvent {
+    /// DID创建
+    Created,
+
+    /// 密钥轮换（携带旧密钥签名的轮换证明）
+    Rotated {
+        proof: KeyRotationProof,
+    },
+}
+
+/// 日志条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// 事件对应的DID（轮换时为新DID）
+    pub did: String,
+
+    /// DID文档CID
+    pub cid: String,
+
+    /// 事件
+    pub event: TransparencyEvent,
+
+    /// 提交时间
+    pub timestamp: u64,
+}
+
+impl LogEntry {
+    /// DID创建事件
+    pub fn created(did: &str, cid: &str, timestamp: u64) -> Self {
+        Self {
+            did: did.to_string(),
+            cid: cid.to_string(),
+            event: TransparencyEvent::Created,
+            timestamp,
+        }
+    }
+
+    /// 密钥轮换事件
+    pub fn rotated(proof: KeyRotationProof, cid: &str, timestamp: u64) -> Self {
+        Self {
+            did: proof.new_did.clone(),
+            cid: cid.to_string(),
+            event: TransparencyEvent::Rotated { proof },
+            timestamp,
+        }
+    }
+
+    /// 轮换前的DID（仅轮换事件）
+    pub fn previous_did(&self) -> Option<&str> {
+        match &self.event {
+            TransparencyEvent::Rotated { proof } => Some(&proof.previous_did),
+            TransparencyEvent::Created => None,
+        }
+    }
+
+    /// 叶子哈希
+    pub fn leaf_hash(&self) -> Result<[u8; 32]> {
+        let data = serde_json::to_vec(self).context("序列化日志条目失败")?;
+        Ok(leaf_hash(&data))
+    }
+
+    /// 校验条目自身：轮换证明有效且指向本条目的DID
+    pub fn validate(&self) -> Result<()> {
+        if let TransparencyEvent::Rotated { proof } = &self.event {
+            if proof.new_did != self.did {
+                anyhow::bail!("轮换证明的新DID与条目不一致: {}", self.did);
+            }
+            if !proof.verify()? {
+                anyhow::bail!("轮换证明签名无效: {}", self.did);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 签名树头（STH）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedTreeHead {
+    /// 日志运营者DID（即日志ID）
+    pub log_did: String,
+
+    /// 树大小（条目数）
+    pub tree_size: u64,
+
+    /// Merkle根（hex）
+    pub root_hash: String,
+
+    /// 签发时间
+    pub timestamp: u64,
+
+    /// 运营者签名（base64）
+    pub signature: String,
+}
+
+impl SignedTreeHead {
+    fn new(signer: &dyn Signer, tree_size: u64, root: [u8; 32], timestamp: u64) -> Result<Self> {
+        Self {
+            log_did: signer.did(),
+            tree_size,
+            root_hash: hex::encode(root),
+            timestamp,
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// Merkle根
+    pub fn root(&self) -> Result<[u8; 32]> {
+        decode_hash(&self.root_hash)
+    }
+}
+
+impl SignedEnvelope for SignedTreeHead {
+    const KIND: &'static str = "树头";
+
+    fn signer_did(&self) -> &str {
+        &self.log_did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// 包含证明（审计路径）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// 条目索引
+    pub leaf_index: u64,
+
+    /// 证明对应的树大小
+    pub tree_size: u64,
+
+    /// 审计路径（hex）
+    pub audit_path: Vec<String>,
+}
+
+impl InclusionProof {
+    /// 验证叶子哈希包含在给定根的树中（RFC 9162 2.1.3.2）
+    pub fn verify(&self, leaf_hash: &[u8; 32], root: &[u8; 32]) -> Result<bool> {
+        if self.leaf_index >= self.tree_size {
+            return Ok(false);
+        }
+
+        let mut fn_ = self.leaf_index;
+        let mut sn = self.tree_size - 1;
+        let mut r = *leaf_hash;
+        for p in &self.audit_path {
+            let p = decode_hash(p)?;
+            if sn == 0 {
+                return Ok(false);
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                r = node_hash(&p, &r);
+                if fn_ & 1 == 0 {
+                    while fn_ & 1 == 0 && fn_ != 0 {
+                        fn_ >>= 1;
+                        sn >>= 1;
+                    }
+                }
+            } else {
+                r = node_hash(&r, &p);
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+        Ok(sn == 0 && r == *root)
+    }
+}
+
+/// 轮换的透明性证据：日志条目、包含证明和签名树头
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotationEvidence {
+    /// 日志条目
+    pub entry: LogEntry,
+
+    /// 包含证明
+    pub inclusion: InclusionProof,
+
+    /// 签名树头
+    pub tree_head: SignedTreeHead,
+}
+
+/// 提交结果
+#[derive(Debug, Clone)]
+pub struct SubmitReceipt {
+    /// 条目索引
+    pub index: u64,
+
+    /// 提交后的签名树头
+    pub tree_head: SignedTreeHead,
+
+    /// 条目在该树头下的包含证明
+    pub inclusion: InclusionProof,
+}
+
+impl SubmitReceipt {
+    /// 转换为轮换证据
+    pub fn into_evidence(self, entry: LogEntry) -> RotationEvidence {
+        RotationEvidence {
+            entry,
+            inclusion: self.inclusion,
+            tree_head: self.tree_head,
+        }
+    }
+}
+
+/// 透明日志（运营者侧）
+///
+/// 仅追加：同一DID只能创建一次，同一旧DID只能轮换一次
+pub struct TransparencyLog {
+    signer: Arc<dyn Signer>,
+    entries: Arc<RwLock<Vec<LogEntry>>>,
+    leaves: Arc<RwLock<Vec<[u8; 32]>>>,
+}
+
+impl TransparencyLog {
+    /// 创建日志，由signer签发树头
+    pub fn new(signer: Arc<dyn Signer>) -> Self {
+        Self {
+            signer,
+            entries: Arc::new(RwLock::new(Vec::new())),
+            leaves: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// 日志ID（运营者DID）
+    pub fn log_did(&self) -> String {
+        self.signer.did()
+    }
+
+    /// 条目数
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 提交事件，返回新的树头和包含证明
+    pub fn submit(&self, entry: LogEntry) -> Result<SubmitReceipt> {
+        entry.validate()?;
+        let leaf = entry.leaf_hash()?;
+
+        let index = {
+            let mut entries = self.entries.write().unwrap();
+            let mut leaves = self.leaves.write().unwrap();
+
+            for existing in entries.iter() {
+                if matches!(entry.event, TransparencyEvent::Created)
+                    && matches!(existing.event, TransparencyEvent::Created)
+                    && existing.did == entry.did
+                {
+                    anyhow::bail!("DID已存在创建记录: {}", entry.did);
+                }
+                if let (Some(prev), Some(existing_prev)) = (entry.previous_did(), existing.previous_did()) {
+                    if prev == existing_prev {
+                        anyhow::bail!("DID已被轮换: {} -> {}", prev, existing.did);
+                    }
+                }
+            }
+
+            entries.push(entry.clone());
+            leaves.push(leaf);
+            (leaves.len() - 1) as u64
+        };
+
+        log::info!("📜 透明日志新增条目 #{}: {}", index, entry.did);
+
+        let tree_head = self.signed_tree_head()?;
+        let inclusion = self.inclusion_proof(index, tree_head.tree_size)?;
+        Ok(SubmitReceipt { index, tree_head, inclusion })
+    }
+
+    /// 当前签名树头
+    pub fn signed_tree_head(&self) -> Result<SignedTreeHead> {
+        let leaves = self.leaves.read().unwrap();
+        let root = merkle_root(&leaves);
+        SignedTreeHead::new(
+            self.signer.as_ref(),
+            leaves.len() as u64,
+            root,
+            chrono::Utc::now().timestamp() as u64,
+        )
+    }
+
+    /// 生成条目在指定树大小下的包含证明
+    pub fn inclusion_proof(&self, index: u64, tree_size: u64) -> Result<InclusionProof> {
+        let leaves = self.leaves.read().unwrap();
+        if tree_size == 0 || tree_size > leaves.len() as u64 {
+            anyhow::bail!("无效的树大小: {}", tree_size);
+        }
+        if index >= tree_size {
+            anyhow::bail!("条目索引超出范围: {}", index);
+        }
+
+        let path = audit_path(index as usize, &leaves[..tree_size as usize]);
+        Ok(InclusionProof {
+            leaf_index: index,
+            tree_size,
+            audit_path: path.iter().map(hex::encode).collect(),
+        })
+    }
+
+    /// 按索引获取条目
+    pub fn entry(&self, index: u64) -> Option<LogEntry> {
+        self.entries.read().unwrap().get(index as usize).cloned()
+    }
+
+    /// 获取区间内的条目（供监控者审计）
+    pub fn entries(&self, start: u64, end: u64) -> Vec<LogEntry> {
+        let entries = self.entries.read().unwrap();
+        let end = (end as usize).min(entries.len());
+        let start = (start as usize).min(end);
+        entries[start..end].to_vec()
+    }
+
+    /// 与某DID相关的条目索引（作为新DID或旧DID）
+    pub fn entries_for(&self, did: &str) -> Vec<u64> {
+        self.entries.read().unwrap()
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.did == did || e.previous_did() == Some(did))
+            .map(|(i, _)| i as u64)
+            .collect()
+    }
+}
+
+/// 轮换信任策略
+#[derive(Debug, Clone, Default)]
+pub struct TransparencyPolicy {
+    /// 受信任的日志（运营者DID）
+    pub trusted_logs: Vec<String>,
+
+    /// 是否要求轮换附带包含证明
+    pub require_inclusion: bool,
+}
+
+impl TransparencyPolicy {
+    /// 要求由指定日志证明包含的策略
+    pub fn require(trusted_logs: Vec<String>) -> Self {
+        Self {
+            trusted_logs,
+            require_inclusion: true,
+        }
+    }
+
+    /// 验证轮换证明；启用require_inclusion时必须附带受信任日志的包含证据
+    pub fn verify_rotation(
+        &self,
+        proof: &KeyRotationProof,
+        evidence: Option<&RotationEvidence>,
+    ) -> Result<bool> {
+        if !proof.verify()? {
+            return Ok(false);
+        }
+
+        let evidence = match evidence {
+            Some(evidence) => evidence,
+            None if self.require_inclusion => {
+                log::warn!("⚠️ 轮换缺少透明日志证据: {} -> {}", proof.previous_did, proof.new_did);
+                return Ok(false);
+            }
+            None => return Ok(true),
+        };
+
+        let matches = match &evidence.entry.event {
+            TransparencyEvent::Rotated { proof: logged } => {
+                logged.previous_did == proof.previous_did
+                    && logged.new_did == proof.new_did
+                    && logged.signature == proof.signature
+            }
+            TransparencyEvent::Created => false,
+        };
+        if !matches {
+            return Ok(false);
+        }
+
+        if !self.trusted_logs.is_empty() && !self.trusted_logs.contains(&evidence.tree_head.log_did) {
+            log::warn!("⚠️ 不受信任的透明日志: {}", evidence.tree_head.log_did);
+            return Ok(false);
+        }
+        if !evidence.tree_head.verify()? {
+            return Ok(false);
+        }
+        if evidence.inclusion.tree_size != evidence.tree_head.tree_size {
+            return Ok(false);
+        }
+
+        evidence.inclusion.verify(&evidence.entry.leaf_hash()?, &evidence.tree_head.root()?)
+    }
+}
+
+/// 检测到的日志不一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Equivocation {
+    /// 同一日志对同一树大小签发了不同的根
+    ForkedTreeHead {
+        first: SignedTreeHead,
+        second: SignedTreeHead,
+    },
+
+    /// 同一旧DID被轮换到不同的新DID
+    ConflictingRotation {
+        previous_did: String,
+        first_new_did: String,
+        second_new_did: String,
+    },
+
+    /// 条目重算的根与树头不一致
+    RootMismatch {
+        tree_head: SignedTreeHead,
+        computed_root: String,
+    },
+}
+
+/// 透明日志监控者
+///
+/// 收集各方看到的树头和日志条目，检测分叉与冲突轮换
+#[derive(Clone, Default)]
+pub struct TransparencyMonitor {
+    /// (日志DID, 树大小) -> 已见树头
+    tree_heads: Arc<DashMap<(String, u64), SignedTreeHead>>,
+
+    /// 旧DID -> 新DID
+    rotations: Arc<DashMap<String, String>>,
+}
+
+impl TransparencyMonitor {
+    /// 创建监控者
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一个树头（可来自其他智能体的gossip），发现分叉时返回证据
+    pub fn observe_tree_head(&self, sth: &SignedTreeHead) -> Result<Option<Equivocation>> {
+        if !sth.verify()? {
+            anyhow::bail!("树头签名无效: {}", sth.log_did);
+        }
+
+        let key = (sth.log_did.clone(), sth.tree_size);
+        if let Some(existing) = self.tree_heads.get(&key) {
+            if existing.root_hash != sth.root_hash {
+                log::error!("🚨 透明日志分叉: {} (size={})", sth.log_did, sth.tree_size);
+                return Ok(Some(Equivocation::ForkedTreeHead {
+                    first: existing.clone(),
+                    second: sth.clone(),
+                }));
+            }
+            return Ok(None);
+        }
+
+        self.tree_heads.insert(key, sth.clone());
+        Ok(None)
+    }
+
+    /// 记录一个轮换条目，发现冲突轮换时返回证据
+    pub fn observe_entry(&self, entry: &LogEntry) -> Option<Equivocation> {
+        let previous_did = entry.previous_did()?;
+
+        if let Some(existing) = self.rotations.get(previous_did) {
+            if *existing != entry.did {
+                log::error!("🚨 冲突的密钥轮换: {} -> {} / {}", previous_did, *existing, entry.did);
+                return Some(Equivocation::ConflictingRotation {
+                    previous_did: previous_did.to_string(),
+                    first_new_did: existing.clone(),
+                    second_new_did: entry.did.clone(),
+                });
+            }
+            return None;
+        }
+
+        self.rotations.insert(previous_did.to_string(), entry.did.clone());
+        None
+    }
+
+    /// 审计：用完整条目列表重算根并与树头比对，同时检查冲突轮换
+    pub fn audit(&self, entries: &[LogEntry], sth: &SignedTreeHead) -> Result<Vec<Equivocation>> {
+        let mut findings = Vec::new();
+        if let Some(fork) = self.observe_tree_head(sth)? {
+            findings.push(fork);
+        }
+
+        if entries.len() as u64 != sth.tree_size {
+            anyhow::bail!("条目数({})与树大小({})不一致", entries.len(), sth.tree_size);
+        }
+
+        let mut leaves = Vec::with_capacity(entries.len());
+        for entry in entries {
+            leaves.push(entry.leaf_hash()?);
+            if let Some(conflict) = self.observe_entry(entry) {
+                findings.push(conflict);
+            }
+        }
+
+        let computed = hex::encode(merkle_root(&leaves));
+        if computed != sth.root_hash {
+            findings.push(Equivocation::RootMismatch {
+                tree_head: sth.clone(),
+                computed_root: computed,
+            });
+        }
+
+        Ok(findings)
+    }
+}
+
+fn leaf_hash(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// 小于n的最大2的幂
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    match leaves.len() {
+        0 => Sha256::digest([]).into(),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&merkle_root(&leaves[..k]), &merkle_root(&leaves[k..]))
+        }
+    }
+}
+
+fn audit_path(index: usize, leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+    let k = split_point(n);
+    if index < k {
+        let mut path = audit_path(index, &leaves[..k]);
+        path.push(merkle_root(&leaves[k..]));
+        path
+    } else {
+        let mut path = audit_path(index - k, &leaves[k..]);
+        path.push(merkle_root(&leaves[..k]));
+        path
+    }
+}
+
+fn decode_hash(value: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(value).context("解码哈希失败")?;
+    <[u8; 32]>::try_from(bytes.as_slice())
+        .map_err(|_| anyhow::anyhow!("哈希长度无效: {}", bytes.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_manager::KeyPair;
+
+    fn operator() -> TransparencyLog {
+        TransparencyLog::new(Arc::new(KeyPair::generate().unwrap()))
+    }
+
+    #[test]
+    fn test_inclusion_proofs_for_all_sizes() {
+        let log = operator();
+        for i in 0..7 {
+            let did = KeyPair::generate().unwrap().did;
+            log.submit(LogEntry::created(&did, &format!("bafy{}", i), i)).unwrap();
+        }
+
+        for size in 1..=7u64 {
+            let leaves = log.leaves.read().unwrap()[..size as usize].to_vec();
+            let root = merkle_root(&leaves);
+            for index in 0..size {
+                let proof = log.inclusion_proof(index, size).unwrap();
+                assert!(proof.verify(&leaves[index as usize], &root).unwrap());
+                if size > 1 {
+                    let other = leaves[((index + 1) % size) as usize];
+                    assert!(!proof.verify(&other, &root).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotation_requires_inclusion() {
+        let log = operator();
+        let old_key = KeyPair::generate().unwrap();
+        let new_key = KeyPair::generate().unwrap();
+        log.submit(LogEntry::created(&old_key.did, "bafyold", 1)).unwrap();
+
+        let rotation = KeyRotationProof::sign(&old_key, &new_key.did).unwrap();
+        let entry = LogEntry::rotated(rotation.clone(), "bafynew", 2);
+        let evidence = log.submit(entry.clone()).unwrap().into_evidence(entry);
+
+        let policy = TransparencyPolicy::require(vec![log.log_did()]);
+        assert!(!policy.verify_rotation(&rotation, None).unwrap());
+        assert!(policy.verify_rotation(&rotation, Some(&evidence)).unwrap());
+        assert!(TransparencyPolicy::default().verify_rotation(&rotation, None).unwrap());
+
+        // 不受信任的日志
+        let other = TransparencyPolicy::require(vec!["did:key:zOther".to_string()]);
+        assert!(!other.verify_rotation(&rotation, Some(&evidence)).unwrap());
+
+        // 同一旧DID不能再次轮换
+        let rogue = KeyPair::generate().unwrap();
+        let second = KeyRotationProof::sign(&old_key, &rogue.did).unwrap();
+        assert!(log.submit(LogEntry::rotated(second, "bafyrogue", 3)).is_err());
+        assert_eq!(log.entries_for(&old_key.did), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_monitor_detects_equivocation() {
+        let signer = Arc::new(KeyPair::generate().unwrap());
+        let old_key = KeyPair::generate().unwrap();
+
+        // 恶意运营者向不同受害者展示两份不同的日志
+        let view_a = TransparencyLog::new(signer.clone());
+        let view_b = TransparencyLog::new(signer);
+        let entry_a = LogEntry::rotated(KeyRotationProof::sign(&old_key, &KeyPair::generate().unwrap().did).unwrap(), "bafya", 1);
+        let entry_b = LogEntry::rotated(KeyRotationProof::sign(&old_key, &KeyPair::generate().unwrap().did).unwrap(), "bafyb", 1);
+        let sth_a = view_a.submit(entry_a.clone()).unwrap().tree_head;
+        let sth_b = view_b.submit(entry_b.clone()).unwrap().tree_head;
+
+        let monitor = TransparencyMonitor::new();
+        assert!(monitor.audit(&[entry_a], &sth_a).unwrap().is_empty());
+        let findings = monitor.audit(std::slice::from_ref(&entry_b), &sth_b).unwrap();
+        assert!(findings.iter().any(|f| matches!(f, Equivocation::ForkedTreeHead { .. })));
+        assert!(findings.iter().any(|f| matches!(f, Equivocation::ConflictingRotation { .. })));
+
+        // 条目被篡改时根不一致
+        let mut tampered = entry_b;
+        tampered.cid = "bafyevil".to_string();
+        let findings = TransparencyMonitor::new().audit(&[tampered], &sth_b).unwrap();
+        assert!(matches!(findings[0], Equivocation::RootMismatch { .. }));
+    }
+}
@@ -0,0 +1,1313 @@
+// DIAP Rust SDK - 统一传输层
+// 上层（认证、发现、消息）只依赖Transport trait：请求-响应、主题发布订阅、对等节点发现。
+// 具体实现由TransportConfig选择：libp2p（request-response + gossipsub + mDNS）、Iroh（QUIC）或HTTP。
+// libp2p传输内置NAT穿透：AutoNAT探测可达性，处于NAT后时在配置的中继上预约电路（relay v2），
+// 对方经中继连入后由DCUtR尝试打洞升级为直连。底层协议可选TCP、QUIC和WebSocket/WSS，拨号时按配置的优先级排序地址
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use libp2p::{
+    autonat,
+    core::{transport::OptionalTransport, upgrade},
+    dcutr, dns, gossipsub, identify, mdns,
+    multiaddr::Protocol,
+    noise, quic, relay,
+    request_response::{self, OutboundRequestId, ProtocolSupport, ResponseChannel},
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour, SwarmEvent},
+    tcp, websocket, yamux, Multiaddr, PeerId, Swarm, Transport as _,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::address_book::{is_relayed, transport_hint, AddressBook, AddressSource};
+use crate::config_manager::{Libp2pProtocol, TransportConfig, TransportKind};
+use crate::connection_manager::ConnectionManager;
+use crate::constants::network_params;
+use crate::http_server::{connection_limiter, read_request, write_response};
+use crate::libp2p_identity::LibP2PIdentity;
+use crate::p2p_codec::{self, DIAPCodec, DEFAULT_MAX_MESSAGE_SIZE};
+use crate::pubsub_authenticator::{AuthenticatedMessage, MessageVerification, PubsubAuthenticator};
+
+/// 默认请求超时
+pub const DEFAULT_TRANSPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// libp2p传输的默认监听地址
+pub const DEFAULT_LIBP2P_TRANSPORT_ADDR: &str = "/ip4/0.0.0.0/tcp/0";
+
+/// HTTP传输的默认监听地址
+pub const DEFAULT_HTTP_TRANSPORT_ADDR: &str = "127.0.0.1:0";
+
+pub use crate::constants::{HTTP_PUBLISH_PATH, HTTP_REQUEST_PATH};
+
+/// HTTP发布主题头
+pub const HTTP_TOPIC_HEADER: &str = "x-diap-topic";
+
+/// HTTP传输最多保存的对等节点数
+pub const MAX_HTTP_PEERS: usize = 256;
+
+/// 每个主题的订阅缓冲
+const SUBSCRIPTION_CAPACITY: usize = 256;
+
+/// 启动时从地址簿重连的最近在线对端数
+pub const MAX_RECONNECT_PEERS: usize = 8;
+
+/// 响应状态：成功
+const RESPONSE_OK: u8 = 1;
+
+/// 响应状态：处理器返回错误（其后为错误文本）
+const RESPONSE_ERROR: u8 = 0;
+
+/// 入站请求处理器：参数为对方节点标识和请求体，返回响应体。
+/// 节点标识来自传输层连接（libp2p为PeerID，HTTP为对端套接字地址），并非应用层身份，需要身份的请求应自带签名
+pub type RequestHandler = Arc<dyn Fn(String, Vec<u8>) -> BoxFuture<'static, Result<Vec<u8>>> + Send + Sync>;
+
+/// 主题上收到的消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportMessage {
+    /// 发送方节点标识
+    pub from: String,
+
+    /// 经gossipsub签名验证的发布者PeerID（仅libp2p传输）
+    pub source_peer: Option<PeerId>,
+
+    /// 主题
+    pub topic: String,
+
+    /// 消息体
+    pub data: Vec<u8>,
+}
+
+impl TransportMessage {
+    /// 解析并验证认证消息；有发布者PeerID时同时检查消息声明的PeerID（见 `verify_message_from_peer`）
+    pub async fn verify_with(&self, authenticator: &PubsubAuthenticator) -> Result<(AuthenticatedMessage, MessageVerification)> {
+        let message = PubsubAuthenticator::deserialize_message(&self.data)?;
+        let verification = match &self.source_peer {
+            Some(source) => authenticator.verify_message_from_peer(&message, source).await?,
+            None => authenticator.verify_message(&message).await?,
+        };
+        Ok((message, verification))
+    }
+}
+
+/// 已知的对等节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportPeer {
+    /// 节点标识（PeerID、Iroh节点ID或HTTP地址）
+    pub id: String,
+
+    /// 可达地址
+    pub addresses: Vec<String>,
+}
+
+/// 节点的可达性状态
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Reachability {
+    /// 尚未探测出结果（或传输层不做探测）
+    #[default]
+    Unknown,
+
+    /// 公网可达：其他节点能直接拨入
+    Public { address: String },
+
+    /// 处于NAT后，且没有可用的中继预约
+    Private,
+
+    /// 处于NAT后，经中继电路可达（对方拨入后尝试打洞）
+    Relayed { relay: String, address: String },
+}
+
+/// 统一传输接口
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// 传输类型
+    fn kind(&self) -> TransportKind;
+
+    /// 本节点标识（其他节点用它调用send_request）
+    fn local_id(&self) -> String;
+
+    /// 设置入站请求处理器（未设置时入站请求返回错误）
+    fn set_request_handler(&self, handler: RequestHandler);
+
+    /// 本节点的可达性（只有libp2p传输做探测，其余返回Unknown）
+    fn reachability(&self) -> Reachability {
+        Reachability::Unknown
+    }
+
+    /// 向节点发送请求并等待响应
+    async fn send_request(&self, peer: &str, data: Vec<u8>) -> Result<Vec<u8>>;
+
+    /// 订阅主题
+    async fn subscribe(&self, topic: &str) -> Result<broadcast::Receiver<TransportMessage>>;
+
+    /// 向主题发布消息
+    async fn publish(&self, topic: &str, data: Vec<u8>) -> Result<()>;
+
+    /// 当前已知的对等节点
+    async fn discover_peers(&self) -> Result<Vec<TransportPeer>>;
+}
+
+/// 按配置启动传输层
+pub async fn start_transport(config: &TransportConfig, identity: &LibP2PIdentity) -> Result<Arc<dyn Transport>> {
+    match config.kind {
+        TransportKind::Libp2p => Ok(Arc::new(Libp2pTransport::start(identity, config).await?)),
+        #[cfg(feature = "iroh")]
+        TransportKind::Iroh => {
+            use crate::iroh_communicator::{IrohCommunicator, IrohConfig};
+
+            let mut iroh_config = IrohConfig::default();
+            if let Some(addr) = config.listen_addr.as_deref() {
+                iroh_config.listen_addr = Some(addr.parse()
+                    .with_context(|| format!("无效的Iroh监听地址: {}", addr))?);
+            }
+            let communicator = IrohCommunicator::new(iroh_config).await?;
+            Ok(Arc::new(IrohTransport::start(communicator, &config.peers).await?))
+        }
+        #[cfg(not(feature = "iroh"))]
+        TransportKind::Iroh => anyhow::bail!("Iroh传输需要启用'iroh' feature"),
+        TransportKind::Http => {
+            let listen_addr = config.listen_addr.as_deref().unwrap_or(DEFAULT_HTTP_TRANSPORT_ADDR);
+            Ok(Arc::new(HttpTransport::start(listen_addr, config.peers.clone()).await?))
+        }
+    }
+}
+
+type HandlerSlot = Arc<RwLock<Option<RequestHandler>>>;
+
+fn call_handler(slot: &HandlerSlot, from: String, data: Vec<u8>) -> BoxFuture<'static, Result<Vec<u8>>> {
+    let handler = slot.read().ok().and_then(|handler| handler.clone());
+    match handler {
+        Some(handler) => handler(from, data),
+        None => Box::pin(async { Err(anyhow::anyhow!("未设置请求处理器")) }),
+    }
+}
+
+fn set_handler(slot: &HandlerSlot, handler: RequestHandler) {
+    if let Ok(mut slot) = slot.write() {
+        *slot = Some(handler);
+    }
+}
+
+/// 处理结果编码为响应帧（libp2p和Iroh共用）
+fn encode_response(result: Result<Vec<u8>>) -> Vec<u8> {
+    match result {
+        Ok(data) => {
+            let mut response = Vec::with_capacity(data.len() + 1);
+            response.push(RESPONSE_OK);
+            response.extend_from_slice(&data);
+            response
+        }
+        Err(e) => {
+            let mut response = vec![RESPONSE_ERROR];
+            response.extend_from_slice(e.to_string().as_bytes());
+            response
+        }
+    }
+}
+
+fn decode_response(response: &[u8]) -> Result<Vec<u8>> {
+    match response.split_first() {
+        Some((&RESPONSE_OK, data)) => Ok(data.to_vec()),
+        Some((&RESPONSE_ERROR, message)) => anyhow::bail!("对方处理请求失败: {}", String::from_utf8_lossy(message)),
+        _ => anyhow::bail!("无效的传输层响应"),
+    }
+}
+
+/// 主题订阅表：每个主题一个广播通道
+#[derive(Clone, Default)]
+struct Subscriptions {
+    topics: Arc<Mutex<HashMap<String, broadcast::Sender<TransportMessage>>>>,
+}
+
+impl Subscriptions {
+    /// 订阅主题，返回接收端和是否为新主题
+    fn subscribe(&self, topic: &str) -> (broadcast::Receiver<TransportMessage>, bool) {
+        let mut topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+        match topics.get(topic) {
+            Some(sender) => (sender.subscribe(), false),
+            None => {
+                let (sender, receiver) = broadcast::channel(SUBSCRIPTION_CAPACITY);
+                topics.insert(topic.to_string(), sender);
+                (receiver, true)
+            }
+        }
+    }
+
+    fn deliver(&self, message: TransportMessage) {
+        let topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = topics.get(&message.topic) {
+            let _ = sender.send(message);
+        }
+    }
+}
+
+// ==================== libp2p ====================
+
+#[derive(NetworkBehaviour)]
+struct TransportBehaviour {
+    request_response: request_response::Behaviour<DIAPCodec>,
+    gossipsub: gossipsub::Behaviour,
+    mdns: Toggle<mdns::tokio::Behaviour>,
+    identify: identify::Behaviour,
+    autonat: Toggle<autonat::Behaviour>,
+    relay_client: relay::client::Behaviour,
+    relay_server: Toggle<relay::Behaviour>,
+    dcutr: Toggle<dcutr::Behaviour>,
+}
+
+/// 已知对端地址；配置了地址簿时同步更新其中已知DID的条目
+struct KnownPeers {
+    addresses: HashMap<PeerId, Vec<Multiaddr>>,
+    address_book: Option<AddressBook>,
+}
+
+impl KnownPeers {
+    fn add(&mut self, peer_id: PeerId, new_addresses: Vec<Multiaddr>, source: AddressSource) {
+        let addresses = self.addresses.entry(peer_id).or_default();
+        for addr in &new_addresses {
+            if !addresses.contains(addr) {
+                addresses.push(addr.clone());
+            }
+        }
+        if let Some(book) = &self.address_book {
+            let new_addresses: Vec<String> = new_addresses.iter().map(ToString::to_string).collect();
+            book.observe_peer(&peer_id.to_base58(), &new_addresses, source);
+        }
+    }
+
+    fn get(&self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        self.addresses.get(peer_id).cloned().unwrap_or_default()
+    }
+}
+
+/// NAT穿透状态：配置的中继和当前可达性
+struct NatState {
+    relays: Vec<(PeerId, Multiaddr)>,
+    reserving: bool,
+    reachability: Arc<RwLock<Reachability>>,
+}
+
+impl NatState {
+    fn current(&self) -> Reachability {
+        self.reachability.read().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    fn set(&self, reachability: Reachability) {
+        log::info!("🌐 可达性: {:?}", reachability);
+        if let Ok(mut current) = self.reachability.write() {
+            *current = reachability;
+        }
+    }
+}
+
+type RequestReply = oneshot::Sender<Result<Vec<u8>>>;
+
+enum Libp2pCommand {
+    Request(PeerId, Vec<Multiaddr>, Vec<u8>, RequestReply),
+    Subscribe(String, oneshot::Sender<Result<()>>),
+    Publish(String, Vec<u8>, oneshot::Sender<Result<()>>),
+    Peers(oneshot::Sender<Vec<TransportPeer>>),
+}
+
+/// libp2p传输：request-response（DIAP请求协议）+ gossipsub + 可选mDNS，以及AutoNAT/中继/DCUtR
+pub struct Libp2pTransport {
+    peer_id: PeerId,
+    listen_addrs: Vec<Multiaddr>,
+    commands: mpsc::UnboundedSender<Libp2pCommand>,
+    handler: HandlerSlot,
+    subscriptions: Subscriptions,
+    reachability: Arc<RwLock<Reachability>>,
+    connections: ConnectionManager,
+    request_timeout: Duration,
+}
+
+impl Libp2pTransport {
+    /// 按配置启动libp2p传输；peers和relays为带/p2p/后缀的多地址，会预先拨号
+    pub async fn start(identity: &LibP2PIdentity, config: &TransportConfig) -> Result<Self> {
+        Self::launch(identity, config, None, ConnectionManager::new()).await
+    }
+
+    /// 启动并由指定的连接管理器跟踪连接（例如带信誉存储的管理器）
+    pub async fn start_with_connection_manager(
+        identity: &LibP2PIdentity,
+        config: &TransportConfig,
+        connections: ConnectionManager,
+    ) -> Result<Self> {
+        Self::launch(identity, config, None, connections).await
+    }
+
+    /// 启动并使用地址簿：重连最近在线的对端，identify/mDNS发现的地址写回地址簿
+    pub async fn start_with_address_book(
+        identity: &LibP2PIdentity,
+        config: &TransportConfig,
+        address_book: AddressBook,
+    ) -> Result<Self> {
+        Self::launch(identity, config, Some(address_book), ConnectionManager::new()).await
+    }
+
+    async fn launch(
+        identity: &LibP2PIdentity,
+        config: &TransportConfig,
+        address_book: Option<AddressBook>,
+        connections: ConnectionManager,
+    ) -> Result<Self> {
+        let mut swarm = build_transport_swarm(identity, config)?;
+
+        // 主监听地址和额外地址（QUIC、WS/WSS），等每个监听器都报告地址
+        let primary = config.listen_addr.as_deref().unwrap_or(DEFAULT_LIBP2P_TRANSPORT_ADDR);
+        let mut pending = HashSet::new();
+        for addr in std::iter::once(primary).chain(config.extra_listen_addrs.iter().map(String::as_str)) {
+            let listen_addr: Multiaddr = addr.parse()
+                .with_context(|| format!("无效的libp2p监听地址: {}", addr))?;
+            pending.insert(swarm.listen_on(listen_addr)
+                .with_context(|| format!("无法监听地址: {}", addr))?);
+        }
+        let mut listen_addrs = Vec::new();
+        while !pending.is_empty() {
+            match swarm.select_next_some().await {
+                SwarmEvent::NewListenAddr { listener_id, address } => {
+                    pending.remove(&listener_id);
+                    listen_addrs.push(address);
+                }
+                SwarmEvent::ListenerClosed { listener_id, reason: Err(e), .. } => {
+                    anyhow::bail!("监听失败 {:?}: {}", listener_id, e);
+                }
+                _ => {}
+            }
+        }
+
+        let mut known = KnownPeers { addresses: HashMap::new(), address_book: address_book.clone() };
+        for peer in &config.peers {
+            match parse_peer_addr(peer) {
+                Ok((peer_id, addr)) => {
+                    if let Err(e) = swarm.dial(addr.clone()) {
+                        log::warn!("拨号配置的节点失败 {}: {}", peer, e);
+                    }
+                    swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
+                    known.add(peer_id, vec![addr], AddressSource::Manual);
+                }
+                Err(e) => log::warn!("忽略无效的节点地址: {}", e),
+            }
+        }
+
+        // 地址簿中的对端都可按PeerID直接请求；最近在线的几个立即重连
+        if let Some(book) = &address_book {
+            for (index, entry) in book.entries().into_iter().enumerate() {
+                let Ok(peer_id) = entry.peer_id.parse::<PeerId>() else {
+                    continue;
+                };
+                let addresses: Vec<Multiaddr> = entry.addresses.iter().filter_map(|addr| addr.parse().ok()).collect();
+                known.addresses.entry(peer_id).or_default().extend(addresses.iter().cloned());
+                if index < MAX_RECONNECT_PEERS && !addresses.is_empty() {
+                    let opts = libp2p::swarm::dial_opts::DialOpts::peer_id(peer_id)
+                        .addresses(prioritize_addresses(addresses, &config.protocols))
+                        .build();
+                    if let Err(e) = swarm.dial(opts) {
+                        log::debug!("重连{}失败: {}", entry.did, e);
+                    }
+                }
+            }
+        }
+
+        // 中继同时作为AutoNAT探测服务器
+        let mut relays = Vec::new();
+        for relay in &config.relays {
+            match parse_peer_addr(relay) {
+                Ok((peer_id, addr)) => {
+                    if let Some(autonat) = swarm.behaviour_mut().autonat.as_mut() {
+                        autonat.add_server(peer_id, Some(addr.clone()));
+                    }
+                    if let Err(e) = swarm.dial(addr.clone()) {
+                        log::warn!("拨号中继失败 {}: {}", relay, e);
+                    }
+                    relays.push((peer_id, addr));
+                }
+                Err(e) => log::warn!("忽略无效的中继地址: {}", e),
+            }
+        }
+
+        let peer_id = *swarm.local_peer_id();
+        let handler: HandlerSlot = Arc::default();
+        let subscriptions = Subscriptions::default();
+        let reachability = Arc::new(RwLock::new(Reachability::Unknown));
+        let nat = NatState { relays, reserving: false, reachability: reachability.clone() };
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run_transport(
+            swarm,
+            known,
+            config.protocols.clone(),
+            nat,
+            handler.clone(),
+            subscriptions.clone(),
+            connections.clone(),
+            receiver,
+        ));
+
+        log::info!("🔌 libp2p传输已启动: {}", peer_id);
+        for address in &listen_addrs {
+            log::info!("   监听: {}", address);
+        }
+        Ok(Self {
+            peer_id,
+            listen_addrs,
+            commands,
+            handler,
+            subscriptions,
+            reachability,
+            connections,
+            request_timeout: DEFAULT_TRANSPORT_TIMEOUT,
+        })
+    }
+
+    /// 设置请求超时
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 本地PeerID
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    /// 监听地址
+    pub fn listen_addrs(&self) -> &[Multiaddr] {
+        &self.listen_addrs
+    }
+
+    /// 连接管理器（由Swarm的连接事件驱动）
+    pub fn connection_manager(&self) -> &ConnectionManager {
+        &self.connections
+    }
+
+    async fn command<T>(&self, build: impl FnOnce(oneshot::Sender<T>) -> Libp2pCommand) -> Result<T> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(build(tx)).map_err(|_| anyhow::anyhow!("libp2p传输已停止"))?;
+        rx.await.context("libp2p传输已停止")
+    }
+}
+
+#[async_trait]
+impl Transport for Libp2pTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::Libp2p
+    }
+
+    fn local_id(&self) -> String {
+        self.peer_id.to_base58()
+    }
+
+    fn set_request_handler(&self, handler: RequestHandler) {
+        set_handler(&self.handler, handler);
+    }
+
+    fn reachability(&self) -> Reachability {
+        self.reachability.read().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// peer可以是PeerID，也可以是带/p2p/后缀的多地址
+    async fn send_request(&self, peer: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+        let (peer_id, addresses) = match peer.parse::<PeerId>() {
+            Ok(peer_id) => (peer_id, Vec::new()),
+            Err(_) => {
+                let (peer_id, addr) = parse_peer_addr(peer)?;
+                (peer_id, vec![addr])
+            }
+        };
+        let reply = self.command(|tx| Libp2pCommand::Request(peer_id, addresses, data, tx));
+        tokio::time::timeout(self.request_timeout, reply).await
+            .map_err(|_| anyhow::anyhow!("请求超时: {}", peer_id))??
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<broadcast::Receiver<TransportMessage>> {
+        let (receiver, is_new) = self.subscriptions.subscribe(topic);
+        if is_new {
+            self.command(|tx| Libp2pCommand::Subscribe(topic.to_string(), tx)).await??;
+        }
+        Ok(receiver)
+    }
+
+    async fn publish(&self, topic: &str, data: Vec<u8>) -> Result<()> {
+        self.command(|tx| Libp2pCommand::Publish(topic.to_string(), data, tx)).await?
+    }
+
+    async fn discover_peers(&self) -> Result<Vec<TransportPeer>> {
+        self.command(Libp2pCommand::Peers).await
+    }
+}
+
+fn build_transport_swarm(identity: &LibP2PIdentity, config: &TransportConfig) -> Result<Swarm<TransportBehaviour>> {
+    let nat_traversal = config.enable_nat_traversal;
+    let quic_enabled = config.protocols.contains(&Libp2pProtocol::Quic);
+    let websocket_enabled = config.protocols.contains(&Libp2pProtocol::Websocket);
+    let wss_tls = wss_tls_config(config)?;
+    Ok(libp2p::SwarmBuilder::with_existing_identity(identity.keypair().clone())
+        .with_tokio()
+        .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)
+        .context("创建TCP传输失败")?
+        .with_other_transport(|key| -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(if quic_enabled {
+                OptionalTransport::some(quic::tokio::Transport::new(quic::Config::new(key)))
+            } else {
+                OptionalTransport::none()
+            })
+        })
+        .context("创建QUIC传输失败")?
+        .with_other_transport(|key| -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+            if !websocket_enabled {
+                return Ok(OptionalTransport::none());
+            }
+            let tcp = dns::tokio::Transport::system(tcp::tokio::Transport::new(tcp::Config::default()))?;
+            let mut ws = websocket::WsConfig::new(tcp);
+            if let Some(tls) = wss_tls {
+                ws.set_tls_config(tls);
+            }
+            Ok(OptionalTransport::some(ws
+                .upgrade(upgrade::Version::V1)
+                .authenticate(noise::Config::new(key)?)
+                .multiplex(yamux::Config::default())))
+        })
+        .context("创建WebSocket传输失败")?
+        .with_relay_client(noise::Config::new, yamux::Config::default)
+        .context("创建中继客户端传输失败")?
+        .with_behaviour(|key, relay_client| -> Result<TransportBehaviour, Box<dyn std::error::Error + Send + Sync>> {
+            let peer_id = key.public().to_peer_id();
+            let gossipsub = gossipsub::Behaviour::new(
+                gossipsub::MessageAuthenticity::Signed(key.clone()),
+                gossipsub::Config::default(),
+            )?;
+            let mdns = if config.enable_mdns {
+                Some(mdns::tokio::Behaviour::new(mdns::Config::default(), peer_id)?)
+            } else {
+                None
+            };
+            Ok(TransportBehaviour {
+                request_response: request_response::Behaviour::with_codec(
+                    DIAPCodec::default(),
+                    [(p2p_codec::request_protocol(), ProtocolSupport::Full)],
+                    request_response::Config::default(),
+                ),
+                gossipsub,
+                mdns: Toggle::from(mdns),
+                identify: identify::Behaviour::new(identify::Config::new(network_params().protocol(), key.public())),
+                autonat: Toggle::from(nat_traversal.then(|| autonat::Behaviour::new(peer_id, autonat::Config::default()))),
+                relay_client,
+                relay_server: Toggle::from(config.relay_server.then(|| relay::Behaviour::new(peer_id, relay::Config::default()))),
+                dcutr: Toggle::from(nat_traversal.then(|| dcutr::Behaviour::new(peer_id))),
+            })
+        })
+        .context("创建传输层行为失败")?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+        .build())
+}
+
+/// WSS监听的TLS配置（未配置wss_tls时只支持/ws监听和/wss拨号）
+fn wss_tls_config(config: &TransportConfig) -> Result<Option<websocket::tls::Config>> {
+    let Some(settings) = &config.wss_tls else {
+        return Ok(None);
+    };
+    #[cfg(feature = "tls")]
+    {
+        let identity = crate::tls::TlsIdentity::from_settings(settings)?;
+        let certificates = identity.certificates()?.into_iter()
+            .map(|certificate| websocket::tls::Certificate::new(certificate.as_ref().to_vec()));
+        let key = websocket::tls::PrivateKey::new(identity.private_key_der()?);
+        Ok(Some(websocket::tls::Config::new(key, certificates).context("WSS证书配置失败")?))
+    }
+    #[cfg(not(feature = "tls"))]
+    {
+        let _ = settings;
+        anyhow::bail!("WSS监听需要启用'tls' feature")
+    }
+}
+
+/// 按协议优先级排序并去重地址，丢弃未启用协议的地址
+fn prioritize_addresses(addresses: Vec<Multiaddr>, protocols: &[Libp2pProtocol]) -> Vec<Multiaddr> {
+    let mut ranked: Vec<(usize, Multiaddr)> = Vec::new();
+    for addr in addresses {
+        if ranked.iter().any(|(_, existing)| *existing == addr) {
+            continue;
+        }
+        if let Some(rank) = protocols.iter().position(|protocol| *protocol == transport_hint(&addr)) {
+            ranked.push((rank, addr));
+        }
+    }
+    ranked.sort_by_key(|(rank, _)| *rank);
+    ranked.into_iter().map(|(_, addr)| addr).collect()
+}
+
+/// 传输层事件循环：转发请求/响应、gossipsub消息、mDNS/identify发现和NAT穿透事件；
+/// 命令通道关闭（Libp2pTransport被丢弃）时退出
+#[allow(clippy::too_many_arguments)]
+async fn run_transport(
+    mut swarm: Swarm<TransportBehaviour>,
+    mut known: KnownPeers,
+    protocols: Vec<Libp2pProtocol>,
+    mut nat: NatState,
+    handler: HandlerSlot,
+    subscriptions: Subscriptions,
+    connections: ConnectionManager,
+    mut commands: mpsc::UnboundedReceiver<Libp2pCommand>,
+) {
+    let mut in_flight: HashMap<OutboundRequestId, RequestReply> = HashMap::new();
+    // 等待连接建立后再发出的请求
+    let mut dialing: HashMap<PeerId, Vec<(Vec<u8>, RequestReply)>> = HashMap::new();
+    // 处理器在独立任务中运行，完成后经此通道回到事件循环发送响应
+    let (responses_tx, mut responses) = mpsc::unbounded_channel::<(ResponseChannel<Vec<u8>>, Vec<u8>)>();
+
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(Libp2pCommand::Request(peer_id, mut addresses, data, tx)) => {
+                    if swarm.is_connected(&peer_id) {
+                        let id = swarm.behaviour_mut().request_response.send_request(&peer_id, data);
+                        in_flight.insert(id, tx);
+                        continue;
+                    }
+                    let queued = dialing.entry(peer_id).or_default();
+                    queued.push((data, tx));
+                    if queued.len() > 1 {
+                        continue;
+                    }
+                    addresses.extend(known.get(&peer_id));
+                    let addresses = prioritize_addresses(addresses, &protocols);
+                    let opts = libp2p::swarm::dial_opts::DialOpts::peer_id(peer_id).addresses(addresses).build();
+                    if let Err(e) = swarm.dial(opts) {
+                        for (_, tx) in dialing.remove(&peer_id).unwrap_or_default() {
+                            let _ = tx.send(Err(anyhow::anyhow!("无法拨号{}: {}", peer_id, e)));
+                        }
+                    }
+                }
+                Some(Libp2pCommand::Subscribe(topic, tx)) => {
+                    let result = swarm.behaviour_mut().gossipsub.subscribe(&gossipsub::IdentTopic::new(&topic))
+                        .map(|_| ())
+                        .map_err(|e| anyhow::anyhow!("订阅主题失败 {}: {:?}", topic, e));
+                    let _ = tx.send(result);
+                }
+                Some(Libp2pCommand::Publish(topic, data, tx)) => {
+                    let result = match swarm.behaviour_mut().gossipsub.publish(gossipsub::IdentTopic::new(&topic), data) {
+                        Ok(_) => Ok(()),
+                        // 暂无订阅该主题的节点：消息无人接收，但不是错误
+                        Err(gossipsub::PublishError::InsufficientPeers) => {
+                            log::debug!("主题{}暂无订阅节点", topic);
+                            Ok(())
+                        }
+                        Err(e) => Err(anyhow::anyhow!("发布消息失败 {}: {:?}", topic, e)),
+                    };
+                    let _ = tx.send(result);
+                }
+                Some(Libp2pCommand::Peers(tx)) => {
+                    let mut peers: HashMap<PeerId, Vec<Multiaddr>> = known.addresses.clone();
+                    for peer_id in swarm.connected_peers() {
+                        peers.entry(*peer_id).or_default();
+                    }
+                    let _ = tx.send(peers.into_iter()
+                        .map(|(peer_id, addresses)| TransportPeer {
+                            id: peer_id.to_base58(),
+                            addresses: addresses.iter().map(ToString::to_string).collect(),
+                        })
+                        .collect());
+                }
+                None => break,
+            },
+            Some((channel, response)) = responses.recv() => {
+                if swarm.behaviour_mut().request_response.send_response(channel, response).is_err() {
+                    log::debug!("请求方已断开");
+                }
+            }
+            event = swarm.select_next_some() => match event {
+                SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } => {
+                    // 只记录主动拨出的地址，入站连接的远端端口不可重拨
+                    connections.on_connected(peer_id, endpoint.is_dialer().then(|| endpoint.get_remote_address().clone()));
+                    for (data, tx) in dialing.remove(&peer_id).unwrap_or_default() {
+                        let id = swarm.behaviour_mut().request_response.send_request(&peer_id, data);
+                        in_flight.insert(id, tx);
+                    }
+                }
+                SwarmEvent::ConnectionClosed { peer_id, num_established: 0, .. } => {
+                    connections.on_disconnected(&peer_id);
+                }
+                SwarmEvent::OutgoingConnectionError { peer_id: Some(peer_id), error, .. } => {
+                    connections.on_dial_failed(&peer_id);
+                    for (_, tx) in dialing.remove(&peer_id).unwrap_or_default() {
+                        let _ = tx.send(Err(anyhow::anyhow!("连接{}失败: {}", peer_id, error)));
+                    }
+                }
+                SwarmEvent::Behaviour(TransportBehaviourEvent::RequestResponse(event)) => match event {
+                    request_response::Event::Message { peer, message, .. } => {
+                        connections.on_activity(&peer);
+                        match message {
+                            request_response::Message::Request { request, channel, .. } => {
+                                let reply = call_handler(&handler, peer.to_base58(), request);
+                                let responses_tx = responses_tx.clone();
+                                tokio::spawn(async move {
+                                    let _ = responses_tx.send((channel, encode_response(reply.await)));
+                                });
+                            }
+                            request_response::Message::Response { request_id, response } => {
+                                if let Some(tx) = in_flight.remove(&request_id) {
+                                    let _ = tx.send(decode_response(&response));
+                                }
+                            }
+                        }
+                    }
+                    request_response::Event::OutboundFailure { request_id, error, .. } => {
+                        if let Some(tx) = in_flight.remove(&request_id) {
+                            let _ = tx.send(Err(anyhow::anyhow!("请求失败: {}", error)));
+                        }
+                    }
+                    _ => {}
+                },
+                SwarmEvent::Behaviour(TransportBehaviourEvent::Gossipsub(gossipsub::Event::Message { propagation_source, message, .. })) => {
+                    connections.on_activity(&propagation_source);
+                    subscriptions.deliver(TransportMessage {
+                        from: message.source.unwrap_or(propagation_source).to_base58(),
+                        source_peer: message.source,
+                        topic: message.topic.as_str().to_string(),
+                        data: message.data,
+                    });
+                }
+                SwarmEvent::Behaviour(TransportBehaviourEvent::Mdns(mdns::Event::Discovered(peers))) => {
+                    for (peer_id, addr) in peers {
+                        log::debug!("mDNS发现节点: {} {}", peer_id, addr);
+                        swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
+                        known.add(peer_id, vec![addr], AddressSource::Mdns);
+                    }
+                }
+                SwarmEvent::Behaviour(TransportBehaviourEvent::Identify(identify::Event::Received { peer_id, info, .. })) => {
+                    known.add(peer_id, info.listen_addrs, AddressSource::Identify);
+                }
+                SwarmEvent::Behaviour(TransportBehaviourEvent::Autonat(autonat::Event::StatusChanged { new, .. })) => {
+                    handle_nat_status(&mut swarm, &mut nat, new);
+                }
+                SwarmEvent::Behaviour(TransportBehaviourEvent::RelayClient(
+                    relay::client::Event::ReservationReqAccepted { relay_peer_id, .. },
+                )) => {
+                    if let Some((_, relay_addr)) = nat.relays.iter().find(|(peer_id, _)| *peer_id == relay_peer_id) {
+                        let address = relay_addr.clone()
+                            .with(Protocol::P2pCircuit)
+                            .with(Protocol::P2p(*swarm.local_peer_id()));
+                        nat.set(Reachability::Relayed { relay: relay_peer_id.to_base58(), address: address.to_string() });
+                    }
+                }
+                SwarmEvent::Behaviour(TransportBehaviourEvent::Dcutr(event)) => match event.result {
+                    Ok(_) => log::info!("🕳️ 打洞成功，已与{}直连", event.remote_peer_id),
+                    Err(e) => log::debug!("与{}打洞失败，继续使用中继: {}", event.remote_peer_id, e),
+                },
+                SwarmEvent::ListenerClosed { addresses, .. } if addresses.iter().any(is_relayed) => {
+                    // 中继电路断开：仍处于NAT后则重新预约
+                    nat.reserving = false;
+                    if matches!(nat.current(), Reachability::Relayed { .. }) {
+                        nat.set(Reachability::Private);
+                        handle_nat_status(&mut swarm, &mut nat, autonat::NatStatus::Private);
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    log::info!("libp2p传输已停止: {}", swarm.local_peer_id());
+}
+
+/// 处理AutoNAT结论：处于NAT后时在配置的中继上预约电路
+fn handle_nat_status(swarm: &mut Swarm<TransportBehaviour>, nat: &mut NatState, status: autonat::NatStatus) {
+    match status {
+        autonat::NatStatus::Public(address) => nat.set(Reachability::Public { address: address.to_string() }),
+        autonat::NatStatus::Unknown => nat.set(Reachability::Unknown),
+        autonat::NatStatus::Private => {
+            // 已有中继预约时保持Relayed
+            if !matches!(nat.current(), Reachability::Relayed { .. }) {
+                nat.set(Reachability::Private);
+            }
+            if nat.reserving {
+                return;
+            }
+            for (_, relay_addr) in nat.relays.clone() {
+                let circuit = relay_addr.with(Protocol::P2pCircuit);
+                match swarm.listen_on(circuit.clone()) {
+                    Ok(_) => nat.reserving = true,
+                    Err(e) => log::warn!("中继预约失败 {}: {}", circuit, e),
+                }
+            }
+        }
+    }
+}
+
+/// 解析带/p2p/后缀的多地址（中继地址取最后一个/p2p/，即目标节点）
+fn parse_peer_addr(addr: &str) -> Result<(PeerId, Multiaddr)> {
+    let address: Multiaddr = addr.parse().with_context(|| format!("无效的多地址: {}", addr))?;
+    let peer_id = address.iter()
+        .filter_map(|protocol| match protocol {
+            Protocol::P2p(peer_id) => Some(peer_id),
+            _ => None,
+        })
+        .last()
+        .ok_or_else(|| anyhow::anyhow!("多地址缺少/p2p/后缀: {}", addr))?;
+    Ok((peer_id, address))
+}
+
+// ==================== Iroh ====================
+
+#[cfg(feature = "iroh")]
+pub use iroh_transport::IrohTransport;
+
+#[cfg(feature = "iroh")]
+mod iroh_transport {
+    use super::*;
+    use base64::{Engine as _, engine::general_purpose};
+    use crate::event_channel::{bounded_event_channel, OverflowPolicy, DEFAULT_CHANNEL_CAPACITY};
+    use crate::iroh_communicator::{IrohCommunicator, IrohMessage, IrohMessageType};
+
+    const REQUEST_MESSAGE_TYPE: &str = "transport/request";
+    const RESPONSE_MESSAGE_TYPE: &str = "transport/response";
+    const PUBLISH_MESSAGE_TYPE: &str = "transport/publish";
+
+    /// 发送方节点ID（对方据此回复）
+    const REPLY_NODE_METADATA_KEY: &str = "reply_node";
+    const TOPIC_METADATA_KEY: &str = "topic";
+
+    /// Iroh传输：请求/响应与发布都使用自定义IrohMessage，内容为base64
+    pub struct IrohTransport {
+        communicator: Arc<IrohCommunicator>,
+        node_id: String,
+        handler: HandlerSlot,
+        subscriptions: Subscriptions,
+        tasks: Vec<JoinHandle<()>>,
+    }
+
+    impl IrohTransport {
+        /// 启动Iroh传输：后台接受连接，并连接peers中的节点ID（失败只记录日志）
+        pub async fn start(communicator: IrohCommunicator, peers: &[String]) -> Result<Self> {
+            let communicator = Arc::new(communicator);
+            let node_id = communicator.get_node_addr_object().node_id.to_string();
+            let handler: HandlerSlot = Arc::default();
+            let subscriptions = Subscriptions::default();
+
+            let (sender, receiver) = bounded_event_channel(DEFAULT_CHANNEL_CAPACITY, OverflowPolicy::default());
+            let listener = communicator.spawn_message_listener_to(sender);
+            let dispatcher = tokio::spawn(dispatch(
+                communicator.clone(),
+                node_id.clone(),
+                handler.clone(),
+                subscriptions.clone(),
+                receiver,
+            ));
+
+            for peer in peers {
+                if let Err(e) = communicator.connect_to_node(peer).await {
+                    log::warn!("连接Iroh节点失败 {}: {}", peer, e);
+                }
+            }
+
+            log::info!("🔌 Iroh传输已启动: {}", node_id);
+            Ok(Self { communicator, node_id, handler, subscriptions, tasks: vec![listener, dispatcher] })
+        }
+
+        /// 底层通信器
+        pub fn communicator(&self) -> &IrohCommunicator {
+            &self.communicator
+        }
+
+        fn message(&self, data: &[u8], message_type: &str) -> IrohMessage {
+            let mut message = self.communicator.create_custom_message(
+                &self.node_id,
+                None,
+                &general_purpose::STANDARD.encode(data),
+                message_type,
+            );
+            message.metadata.insert(REPLY_NODE_METADATA_KEY.to_string(), self.node_id.clone());
+            message
+        }
+    }
+
+    impl Drop for IrohTransport {
+        fn drop(&mut self) {
+            for task in &self.tasks {
+                task.abort();
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Transport for IrohTransport {
+        fn kind(&self) -> TransportKind {
+            TransportKind::Iroh
+        }
+
+        fn local_id(&self) -> String {
+            self.node_id.clone()
+        }
+
+        fn set_request_handler(&self, handler: RequestHandler) {
+            set_handler(&self.handler, handler);
+        }
+
+        async fn send_request(&self, peer: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+            if !self.communicator.is_node_connected(peer) {
+                self.communicator.connect_to_node(peer).await?;
+            }
+            let response = self.communicator.request_and_wait(peer, self.message(&data, REQUEST_MESSAGE_TYPE)).await?;
+            let response = general_purpose::STANDARD.decode(&response.content).context("响应解码失败")?;
+            decode_response(&response)
+        }
+
+        async fn subscribe(&self, topic: &str) -> Result<broadcast::Receiver<TransportMessage>> {
+            Ok(self.subscriptions.subscribe(topic).0)
+        }
+
+        /// 发给当前所有已连接节点（Iroh没有gossip层）
+        async fn publish(&self, topic: &str, data: Vec<u8>) -> Result<()> {
+            let mut message = self.message(&data, PUBLISH_MESSAGE_TYPE);
+            message.metadata.insert(TOPIC_METADATA_KEY.to_string(), topic.to_string());
+            for node_id in self.communicator.get_connected_nodes() {
+                if let Err(e) = self.communicator.send_message(&node_id, message.clone()).await {
+                    log::warn!("向{}发布消息失败: {}", node_id, e);
+                }
+            }
+            Ok(())
+        }
+
+        async fn discover_peers(&self) -> Result<Vec<TransportPeer>> {
+            Ok(self.communicator.get_connections().into_iter()
+                .map(|(node_id, connection)| TransportPeer { id: node_id, addresses: vec![connection.remote_addr] })
+                .collect())
+        }
+    }
+
+    /// 分发入站消息：请求交给处理器并回复，发布消息投递给订阅者，其余忽略
+    async fn dispatch(
+        communicator: Arc<IrohCommunicator>,
+        node_id: String,
+        handler: HandlerSlot,
+        subscriptions: Subscriptions,
+        mut receiver: crate::event_channel::EventReceiver<IrohMessage>,
+    ) {
+        while let Some(message) = receiver.recv().await {
+            let kind = match &message.message_type {
+                IrohMessageType::Custom(kind) => kind.clone(),
+                _ => continue,
+            };
+            let Some(reply_node) = message.metadata.get(REPLY_NODE_METADATA_KEY).cloned() else {
+                log::debug!("忽略缺少发送方节点ID的传输层消息: {}", message.message_id);
+                continue;
+            };
+            let data = match general_purpose::STANDARD.decode(&message.content) {
+                Ok(data) => data,
+                Err(e) => {
+                    log::debug!("传输层消息解码失败 {}: {}", message.message_id, e);
+                    continue;
+                }
+            };
+
+            match kind.as_str() {
+                REQUEST_MESSAGE_TYPE => {
+                    let reply = call_handler(&handler, reply_node.clone(), data);
+                    let communicator = communicator.clone();
+                    let node_id = node_id.clone();
+                    tokio::spawn(async move {
+                        let content = general_purpose::STANDARD.encode(encode_response(reply.await));
+                        let response = communicator.create_reply(
+                            &message,
+                            &node_id,
+                            &content,
+                            IrohMessageType::Custom(RESPONSE_MESSAGE_TYPE.to_string()),
+                        );
+                        if let Err(e) = communicator.send_message(&reply_node, response).await {
+                            log::warn!("回复传输层请求失败 {}: {}", reply_node, e);
+                        }
+                    });
+                }
+                PUBLISH_MESSAGE_TYPE => {
+                    if let Some(topic) = message.metadata.get(TOPIC_METADATA_KEY) {
+                        subscriptions.deliver(TransportMessage { from: reply_node, source_peer: None, topic: topic.clone(), data });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// ==================== HTTP ====================
+
+/// HTTP传输：每个节点运行一个小型HTTP服务，请求和发布都是POST；节点标识是其基础URL。
+/// 对等节点只来自配置和 `add_peer`，入站请求不会注册对端
+pub struct HttpTransport {
+    base_url: String,
+    client: reqwest::Client,
+    peers: Arc<RwLock<Vec<String>>>,
+    handler: HandlerSlot,
+    subscriptions: Subscriptions,
+    request_timeout: Duration,
+    server: JoinHandle<()>,
+}
+
+impl HttpTransport {
+    /// 在listen_addr上启动HTTP传输；peers为其他节点的基础URL（如 http://127.0.0.1:8080）
+    pub async fn start(listen_addr: &str, peers: Vec<String>) -> Result<Self> {
+        let listener = TcpListener::bind(listen_addr).await
+            .with_context(|| format!("无法监听地址: {}", listen_addr))?;
+        let base_url = format!("http://{}", listener.local_addr().context("获取监听地址失败")?);
+
+        let handler: HandlerSlot = Arc::default();
+        let subscriptions = Subscriptions::default();
+        let server = tokio::spawn(serve_http(listener, handler.clone(), subscriptions.clone()));
+
+        log::info!("🔌 HTTP传输已启动: {}", base_url);
+        let transport = Self {
+            base_url,
+            client: reqwest::Client::new(),
+            peers: Arc::default(),
+            handler,
+            subscriptions,
+            request_timeout: DEFAULT_TRANSPORT_TIMEOUT,
+            server,
+        };
+        for peer in &peers {
+            transport.add_peer(peer);
+        }
+        Ok(transport)
+    }
+
+    /// 设置请求超时
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 添加对等节点（超过MAX_HTTP_PEERS时忽略），返回是否为新节点
+    pub fn add_peer(&self, base_url: &str) -> bool {
+        let base_url = base_url.trim_end_matches('/');
+        let Ok(mut peers) = self.peers.write() else {
+            return false;
+        };
+        if peers.iter().any(|peer| peer == base_url) {
+            return false;
+        }
+        if peers.len() >= MAX_HTTP_PEERS {
+            log::warn!("HTTP传输对等节点已达上限({})，忽略: {}", MAX_HTTP_PEERS, base_url);
+            return false;
+        }
+        peers.push(base_url.to_string());
+        true
+    }
+
+    async fn post(&self, url: String, headers: &[(&str, &str)], data: Vec<u8>) -> Result<Vec<u8>> {
+        let mut request = self.client.post(&url)
+            .timeout(self.request_timeout)
+            .body(data);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = request.send().await.with_context(|| format!("HTTP请求失败: {}", url))?;
+        let status = response.status();
+        let body = response.bytes().await.context("读取HTTP响应失败")?;
+        if !status.is_success() {
+            anyhow::bail!("对方处理请求失败 ({}): {}", status, String::from_utf8_lossy(&body));
+        }
+        Ok(body.to_vec())
+    }
+}
+
+impl Drop for HttpTransport {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::Http
+    }
+
+    fn local_id(&self) -> String {
+        self.base_url.clone()
+    }
+
+    fn set_request_handler(&self, handler: RequestHandler) {
+        set_handler(&self.handler, handler);
+    }
+
+    async fn send_request(&self, peer: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+        self.post(format!("{}{}", peer.trim_end_matches('/'), network_params().http_request_path()), &[], data).await
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<broadcast::Receiver<TransportMessage>> {
+        Ok(self.subscriptions.subscribe(topic).0)
+    }
+
+    /// 并发发给所有已知节点（失败只记录日志）
+    async fn publish(&self, topic: &str, data: Vec<u8>) -> Result<()> {
+        let peers = self.peers.read().map(|peers| peers.clone()).unwrap_or_default();
+        let publish_path = network_params().http_publish_path();
+        let deliveries = peers.iter().map(|peer| {
+            let url = format!("{}{}", peer, publish_path);
+            let data = data.clone();
+            async move {
+                if let Err(e) = self.post(url, &[(HTTP_TOPIC_HEADER, topic)], data).await {
+                    log::warn!("向{}发布消息失败: {}", peer, e);
+                }
+            }
+        });
+        futures::future::join_all(deliveries).await;
+        Ok(())
+    }
+
+    /// 配置的和通过add_peer添加的节点
+    async fn discover_peers(&self) -> Result<Vec<TransportPeer>> {
+        let peers = self.peers.read().map(|peers| peers.clone()).unwrap_or_default();
+        Ok(peers.into_iter()
+            .map(|peer| TransportPeer { addresses: vec![peer.clone()], id: peer })
+            .collect())
+    }
+}
+
+async fn serve_http(listener: TcpListener, handler: HandlerSlot, subscriptions: Subscriptions) {
+    let limiter = connection_limiter();
+    loop {
+        let Ok(permit) = limiter.clone().acquire_owned().await else {
+            break;
+        };
+        match listener.accept().await {
+            Ok((stream, remote)) => {
+                let handler = handler.clone();
+                let subscriptions = subscriptions.clone();
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    if let Err(e) = serve_http_connection(stream, remote.to_string(), &handler, &subscriptions).await {
+                        log::debug!("HTTP传输连接处理失败: {}", e);
+                    }
+                });
+            }
+            Err(e) => log::warn!("HTTP传输接受连接失败: {}", e),
+        }
+    }
+}
+
+/// from为对端套接字地址（未认证，仅供日志和限流）
+async fn serve_http_connection(
+    mut stream: TcpStream,
+    from: String,
+    handler: &HandlerSlot,
+    subscriptions: &Subscriptions,
+) -> Result<()> {
+    let request = match read_request(&mut stream, DEFAULT_MAX_MESSAGE_SIZE).await {
+        Ok(request) => request,
+        Err(e) => match e.status() {
+            Some(status) => {
+                return write_response(&mut stream, status, "text/plain; charset=utf-8", e.to_string().as_bytes()).await;
+            }
+            None => return Err(e.into()),
+        },
+    };
+
+    let params = network_params();
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", path) if path == params.http_request_path() => match call_handler(handler, from, request.body).await {
+            Ok(body) => write_response(&mut stream, 200, "application/octet-stream", &body).await,
+            Err(e) => write_response(&mut stream, 500, "text/plain; charset=utf-8", e.to_string().as_bytes()).await,
+        },
+        ("POST", path) if path == params.http_publish_path() => {
+            let Some(topic) = request.header(HTTP_TOPIC_HEADER).map(str::to_string) else {
+                return write_response(&mut stream, 400, "text/plain; charset=utf-8", b"missing topic").await;
+            };
+            subscriptions.deliver(TransportMessage { from, source_peer: None, topic, data: request.body });
+            write_response(&mut stream, 200, "text/plain; charset=utf-8", b"ok").await
+        }
+        _ => write_response(&mut stream, 404, "text/plain; charset=utf-8", b"not found").await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_handler(prefix: &'static str) -> RequestHandler {
+        Arc::new(move |from: String, data: Vec<u8>| -> BoxFuture<'static, Result<Vec<u8>>> {
+            Box::pin(async move {
+                let mut response = format!("{}:{}:", prefix, from).into_bytes();
+                response.extend_from_slice(&data);
+                Ok(response)
+            })
+        })
+    }
+
+    #[tokio::test]
+    async fn test_http_transport_request_and_publish() {
+        let alice = HttpTransport::start(DEFAULT_HTTP_TRANSPORT_ADDR, Vec::new()).await.unwrap();
+        let bob = HttpTransport::start(DEFAULT_HTTP_TRANSPORT_ADDR, vec![alice.local_id()]).await.unwrap();
+        alice.set_request_handler(echo_handler("alice"));
+
+        let response = bob.send_request(&alice.local_id(), b"ping".to_vec()).await.unwrap();
+        assert!(String::from_utf8(response).unwrap().starts_with("alice:127.0.0.1:"));
+        // 入站请求不会注册对端
+        assert!(alice.discover_peers().await.unwrap().is_empty());
+        assert!(!bob.add_peer(&format!("{}/", alice.local_id())));
+
+        let mut received = alice.subscribe("diap/test").await.unwrap();
+        bob.publish("diap/test", b"hello".to_vec()).await.unwrap();
+        let message = received.recv().await.unwrap();
+        assert!(message.from.starts_with("127.0.0.1:"));
+        assert_eq!(message.data, b"hello");
+
+        // 未设置处理器的节点返回错误
+        assert!(alice.send_request(&bob.local_id(), b"ping".to_vec()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_libp2p_transport_request() {
+        let config = TransportConfig {
+            listen_addr: Some("/ip4/127.0.0.1/tcp/0".to_string()),
+            enable_mdns: false,
+            ..TransportConfig::default()
+        };
+        let alice = Libp2pTransport::start(&LibP2PIdentity::generate().unwrap(), &config).await.unwrap();
+        let bob = Libp2pTransport::start(&LibP2PIdentity::generate().unwrap(), &config).await.unwrap();
+        alice.set_request_handler(echo_handler("alice"));
+
+        let alice_addr = format!("{}/p2p/{}", alice.listen_addrs()[0], alice.local_id());
+        let response = bob.send_request(&alice_addr, b"ping".to_vec()).await.unwrap();
+        assert_eq!(response, format!("alice:{}:ping", bob.local_id()).into_bytes());
+        assert!(bob.discover_peers().await.unwrap().iter().any(|peer| peer.id == alice.local_id()));
+        assert_eq!(alice.reachability(), Reachability::Unknown);
+
+        // 连接事件驱动连接管理器；拨出方记录可重拨的地址
+        let alice_id: PeerId = alice.local_id().parse().unwrap();
+        assert!(bob.connection_manager().is_connected(&alice_id));
+        assert!(!bob.connection_manager().peer(&alice_id).unwrap().addrs.is_empty());
+        assert!(alice.connection_manager().is_connected(&bob.local_id().parse().unwrap()));
+    }
+
+    #[test]
+    fn test_prioritize_addresses() {
+        let tcp: Multiaddr = "/ip4/203.0.113.7/tcp/4001".parse().unwrap();
+        let quic: Multiaddr = "/ip4/203.0.113.7/udp/4001/quic-v1".parse().unwrap();
+        let wss: Multiaddr = "/dns4/agent.example.com/tcp/443/wss".parse().unwrap();
+        let addresses = vec![wss.clone(), tcp.clone(), quic.clone(), tcp.clone()];
+
+        let ordered = prioritize_addresses(addresses.clone(), &[Libp2pProtocol::Quic, Libp2pProtocol::Tcp, Libp2pProtocol::Websocket]);
+        assert_eq!(ordered, vec![quic, tcp.clone(), wss.clone()]);
+        // 未列出的协议不拨号
+        assert_eq!(prioritize_addresses(addresses, &[Libp2pProtocol::Websocket, Libp2pProtocol::Tcp]), vec![wss, tcp]);
+    }
+
+    #[test]
+    fn test_parse_relayed_peer_addr() {
+        let relay = PeerId::random();
+        let target = PeerId::random();
+        let addr = format!("/ip4/203.0.113.7/tcp/4001/p2p/{}/p2p-circuit/p2p/{}", relay, target);
+        let (peer_id, address) = parse_peer_addr(&addr).unwrap();
+        assert_eq!(peer_id, target);
+        assert!(is_relayed(&address));
+        assert!(parse_peer_addr("/ip4/203.0.113.7/tcp/4001").is_err());
+    }
+}
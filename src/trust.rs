@@ -0,0 +1,349 @@
+// DIAP Rust SDK - 信誉与信任评分
+// 按DID累计验证结果、消息有效性、速率限制违规和在线时长，得出0~100的信任分；
+// 分数随时间向初始值衰减，可持久化到JSON文件，并提供主题策略钩子和对端排序供上层使用
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::clock::{SharedClock, system_clock};
+
+/// 信任分上限
+pub const MAX_TRUST_SCORE: f64 = 100.0;
+
+/// 信誉事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReputationEvent {
+    /// 消息通过验证
+    Verified,
+    /// 消息验证失败（签名、证明、策略等）
+    VerificationFailed,
+    /// 消息内容无效（无法解析、协议错误等，由应用上报）
+    InvalidMessage,
+    /// 超出速率限制
+    RateLimited,
+    /// 在线时长（秒）
+    Uptime { secs: u64 },
+}
+
+/// 评分权重
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustWeights {
+    /// 新DID的初始分（衰减的目标值）
+    pub initial_score: f64,
+
+    /// 每条通过验证的消息
+    pub verified: f64,
+
+    /// 每次验证失败
+    pub verification_failed: f64,
+
+    /// 每条无效消息
+    pub invalid_message: f64,
+
+    /// 每次速率限制违规
+    pub rate_limited: f64,
+
+    /// 每小时在线时长
+    pub uptime_per_hour: f64,
+
+    /// 偏离初始分的部分衰减一半所需时间（秒，0表示不衰减）
+    pub half_life_secs: u64,
+}
+
+impl Default for TrustWeights {
+    fn default() -> Self {
+        Self {
+            initial_score: 50.0,
+            verified: 0.1,
+            verification_failed: -5.0,
+            invalid_message: -3.0,
+            rate_limited: -2.0,
+            uptime_per_hour: 0.5,
+            half_life_secs: 7 * 24 * 3600,
+        }
+    }
+}
+
+/// 单个DID的信誉记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReputationRecord {
+    /// DID
+    pub did: String,
+
+    /// 当前分数（0~100）
+    pub score: f64,
+
+    /// 通过验证的消息数
+    pub verified: u64,
+
+    /// 验证失败次数
+    pub verification_failures: u64,
+
+    /// 无效消息数
+    pub invalid_messages: u64,
+
+    /// 速率限制违规次数
+    pub rate_limit_violations: u64,
+
+    /// 累计在线时长（秒）
+    pub uptime_secs: u64,
+
+    /// 最近更新时间
+    pub updated_at: u64,
+}
+
+/// 主题策略钩子：TopicPolicy::Custom的主题由它决定是否接受发送者（参数为主题和发送者DID）
+pub type TopicPolicyHook = Arc<dyn Fn(&str, &str) -> bool + Send + Sync>;
+
+#[derive(Serialize, Deserialize)]
+struct ReputationSnapshot {
+    weights: TrustWeights,
+    records: Vec<ReputationRecord>,
+}
+
+/// 信誉存储（可克隆，克隆体共享状态）
+#[derive(Clone)]
+pub struct ReputationStore {
+    weights: TrustWeights,
+    records: Arc<Mutex<HashMap<String, ReputationRecord>>>,
+    path: Option<PathBuf>,
+    clock: SharedClock,
+}
+
+impl ReputationStore {
+    /// 创建内存中的信誉存储
+    pub fn new(weights: TrustWeights) -> Self {
+        Self {
+            weights,
+            records: Arc::new(Mutex::new(HashMap::new())),
+            path: None,
+            clock: system_clock(),
+        }
+    }
+
+    /// 打开持久化的信誉存储（文件不存在时新建；已有文件中的权重优先于参数）
+    pub fn open(path: impl AsRef<Path>, weights: TrustWeights) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建信誉存储目录: {:?}", parent))?;
+        }
+
+        let mut store = Self::new(weights);
+        if path.exists() {
+            let content = std::fs::read(&path).with_context(|| format!("无法读取信誉存储: {:?}", path))?;
+            let snapshot: ReputationSnapshot = serde_json::from_slice(&content).context("解析信誉存储失败")?;
+            store.weights = snapshot.weights;
+            *store.records.lock().unwrap() = snapshot.records.into_iter()
+                .map(|record| (record.did.clone(), record))
+                .collect();
+        }
+        log::info!("💾 信誉存储: {:?} ({}个DID)", path, store.len());
+        store.path = Some(path);
+        Ok(store)
+    }
+
+    /// 使用指定时间源
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 评分权重
+    pub fn weights(&self) -> &TrustWeights {
+        &self.weights
+    }
+
+    /// 记录一次信誉事件，返回更新后的分数
+    pub fn record(&self, did: &str, event: ReputationEvent) -> f64 {
+        let now = self.clock.now_secs();
+        let mut records = self.records.lock().unwrap();
+        let record = records.entry(did.to_string()).or_insert_with(|| ReputationRecord {
+            did: did.to_string(),
+            score: self.weights.initial_score,
+            verified: 0,
+            verification_failures: 0,
+            invalid_messages: 0,
+            rate_limit_violations: 0,
+            uptime_secs: 0,
+            updated_at: now,
+        });
+        record.score = self.decayed(record, now);
+        record.updated_at = now;
+
+        let delta = match event {
+            ReputationEvent::Verified => {
+                record.verified += 1;
+                self.weights.verified
+            }
+            ReputationEvent::VerificationFailed => {
+                record.verification_failures += 1;
+                self.weights.verification_failed
+            }
+            ReputationEvent::InvalidMessage => {
+                record.invalid_messages += 1;
+                self.weights.invalid_message
+            }
+            ReputationEvent::RateLimited => {
+                record.rate_limit_violations += 1;
+                self.weights.rate_limited
+            }
+            ReputationEvent::Uptime { secs } => {
+                record.uptime_secs += secs;
+                self.weights.uptime_per_hour * secs as f64 / 3600.0
+            }
+        };
+        record.score = (record.score + delta).clamp(0.0, MAX_TRUST_SCORE);
+        record.score
+    }
+
+    /// 信任分（未记录过的DID为初始分）
+    pub fn trust_score(&self, did: &str) -> f64 {
+        let now = self.clock.now_secs();
+        self.records.lock().unwrap().get(did)
+            .map(|record| self.decayed(record, now))
+            .unwrap_or(self.weights.initial_score)
+    }
+
+    /// 信誉记录（分数已按当前时间衰减）
+    pub fn record_of(&self, did: &str) -> Option<ReputationRecord> {
+        let now = self.clock.now_secs();
+        self.records.lock().unwrap().get(did).map(|record| ReputationRecord {
+            score: self.decayed(record, now),
+            ..record.clone()
+        })
+    }
+
+    /// 所有记录（分数高的在前）
+    pub fn snapshot(&self) -> Vec<ReputationRecord> {
+        let now = self.clock.now_secs();
+        let mut records: Vec<ReputationRecord> = self.records.lock().unwrap().values()
+            .map(|record| ReputationRecord { score: self.decayed(record, now), ..record.clone() })
+            .collect();
+        records.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.did.cmp(&b.did)));
+        records
+    }
+
+    /// 按信任分从高到低排序候选DID，过滤掉低于min_score的（用于对端选择）
+    pub fn rank_peers<'a>(&self, candidates: impl IntoIterator<Item = &'a str>, min_score: f64) -> Vec<(String, f64)> {
+        let mut ranked: Vec<(String, f64)> = candidates.into_iter()
+            .map(|did| (did.to_string(), self.trust_score(did)))
+            .filter(|(_, score)| *score >= min_score)
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+
+    /// 生成“信任分不低于min_score”的主题策略钩子
+    pub fn min_score_policy(&self, min_score: f64) -> TopicPolicyHook {
+        let store = self.clone();
+        Arc::new(move |_topic, did| store.trust_score(did) >= min_score)
+    }
+
+    /// 删除某个DID的记录（例如运维人工复核后恢复初始分）
+    pub fn reset(&self, did: &str) -> bool {
+        self.records.lock().unwrap().remove(did).is_some()
+    }
+
+    /// 记录的DID数
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    /// 是否没有记录
+    pub fn is_empty(&self) -> bool {
+        self.records.lock().unwrap().is_empty()
+    }
+
+    /// 写入持久化文件（内存存储时什么也不做）
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let snapshot = ReputationSnapshot {
+            weights: self.weights.clone(),
+            records: self.records.lock().unwrap().values().cloned().collect(),
+        };
+        let content = serde_json::to_vec_pretty(&snapshot).context("序列化信誉存储失败")?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content).with_context(|| format!("无法写入信誉存储: {:?}", tmp))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("无法替换信誉存储文件: {:?}", path))?;
+        Ok(())
+    }
+
+    /// 定期保存（信誉随每条消息变化，不逐条写盘）
+    pub fn spawn_autosave(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = store.save() {
+                    log::warn!("⚠️ 保存信誉存储失败: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 偏离初始分的部分按半衰期衰减
+    fn decayed(&self, record: &ReputationRecord, now: u64) -> f64 {
+        if self.weights.half_life_secs == 0 {
+            return record.score;
+        }
+        let elapsed = now.saturating_sub(record.updated_at) as f64;
+        let factor = 0.5f64.powf(elapsed / self.weights.half_life_secs as f64);
+        self.weights.initial_score + (record.score - self.weights.initial_score) * factor
+    }
+}
+
+impl Default for ReputationStore {
+    fn default() -> Self {
+        Self::new(TrustWeights::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn test_scores_decay_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reputation.json");
+        let clock = MockClock::new(1_000);
+        let weights = TrustWeights { half_life_secs: 100, ..Default::default() };
+        let store = ReputationStore::open(&path, weights.clone()).unwrap().with_clock(Arc::new(clock.clone()));
+
+        assert_eq!(store.trust_score("did:key:new"), 50.0);
+        for _ in 0..4 {
+            store.record("did:key:spammer", ReputationEvent::RateLimited);
+        }
+        store.record("did:key:spammer", ReputationEvent::VerificationFailed);
+        assert_eq!(store.trust_score("did:key:spammer"), 37.0);
+        assert_eq!(store.record("did:key:good", ReputationEvent::Uptime { secs: 7200 }), 51.0);
+
+        let ranked = store.rank_peers(["did:key:spammer", "did:key:good", "did:key:new"], 40.0);
+        assert_eq!(ranked.iter().map(|(did, _)| did.as_str()).collect::<Vec<_>>(), vec!["did:key:good", "did:key:new"]);
+        let policy = store.min_score_policy(40.0);
+        assert!(!policy("t", "did:key:spammer"));
+        assert!(policy("t", "did:key:new"));
+
+        // 一个半衰期后偏离初始分的部分减半
+        clock.advance(Duration::from_secs(100));
+        assert!((store.trust_score("did:key:spammer") - 43.5).abs() < 1e-9);
+
+        store.save().unwrap();
+        let reopened = ReputationStore::open(&path, TrustWeights::default()).unwrap().with_clock(Arc::new(clock));
+        assert_eq!(reopened.weights(), &weights);
+        let record = reopened.record_of("did:key:spammer").unwrap();
+        assert_eq!((record.rate_limit_violations, record.verification_failures), (4, 1));
+        assert!((record.score - 43.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,133 @@
+// DIAP Rust SDK - 信任图
+// 记录智能体之间的引荐关系（谁邀请了谁），用于追溯新智能体的引荐链
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// 引荐关系
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Introduction {
+    /// 被引荐的智能体DID
+    pub did: String,
+
+    /// 引荐人DID
+    pub introducer_did: String,
+
+    /// 所用邀请ID
+    pub invite_id: String,
+
+    /// 引荐时间
+    pub introduced_at: u64,
+}
+
+/// 信任图
+#[derive(Clone, Default)]
+pub struct TrustGraph {
+    /// 被引荐DID -> 引荐关系（每个DID只记录第一个引荐人）
+    introductions: Arc<DashMap<String, Introduction>>,
+}
+
+impl TrustGraph {
+    /// 创建空的信任图
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录引荐关系，返回是否新增
+    /// 已有引荐人的DID不会被覆盖，自我引荐会被忽略
+    pub fn record_introduction(&self, introduction: Introduction) -> bool {
+        if introduction.did == introduction.introducer_did {
+            return false;
+        }
+        if self.introductions.contains_key(&introduction.did) {
+            return false;
+        }
+
+        log::info!("🤝 {} 由 {} 引荐", introduction.did, introduction.introducer_did);
+        self.introductions.insert(introduction.did.clone(), introduction);
+        true
+    }
+
+    /// 获取DID的引荐人
+    pub fn introducer_of(&self, did: &str) -> Option<String> {
+        self.introductions.get(did).map(|i| i.introducer_did.clone())
+    }
+
+    /// 获取DID的引荐关系
+    pub fn introduction(&self, did: &str) -> Option<Introduction> {
+        self.introductions.get(did).map(|i| i.clone())
+    }
+
+    /// 获取由某DID引荐的全部智能体
+    pub fn introduced_by(&self, introducer_did: &str) -> Vec<String> {
+        let mut dids: Vec<String> = self.introductions
+            .iter()
+            .filter(|i| i.introducer_did == introducer_did)
+            .map(|i| i.did.clone())
+            .collect();
+        dids.sort();
+        dids
+    }
+
+    /// 引荐链（从直接引荐人开始，逐级向上，遇到环时停止）
+    pub fn introduction_chain(&self, did: &str) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::from([did.to_string()]);
+        let mut current = did.to_string();
+
+        while let Some(introducer) = self.introducer_of(&current) {
+            if !seen.insert(introducer.clone()) {
+                break;
+            }
+            chain.push(introducer.clone());
+            current = introducer;
+        }
+        chain
+    }
+
+    /// 记录数量
+    pub fn len(&self) -> usize {
+        self.introductions.len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.introductions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intro(did: &str, introducer: &str) -> Introduction {
+        Introduction {
+            did: did.to_string(),
+            introducer_did: introducer.to_string(),
+            invite_id: format!("invite-{}", did),
+            introduced_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_introduction_chain() {
+        let graph = TrustGraph::new();
+        assert!(graph.record_introduction(intro("did:b", "did:a")));
+        assert!(graph.record_introduction(intro("did:c", "did:b")));
+        assert!(graph.record_introduction(intro("did:d", "did:b")));
+
+        // 不覆盖已有引荐人，忽略自我引荐
+        assert!(!graph.record_introduction(intro("did:c", "did:x")));
+        assert!(!graph.record_introduction(intro("did:e", "did:e")));
+
+        assert_eq!(graph.introducer_of("did:c").as_deref(), Some("did:b"));
+        assert_eq!(graph.introduced_by("did:b"), vec!["did:c".to_string(), "did:d".to_string()]);
+        assert_eq!(graph.introduction_chain("did:c"), vec!["did:b".to_string(), "did:a".to_string()]);
+
+        // 环不会导致死循环
+        graph.record_introduction(intro("did:a", "did:c"));
+        assert_eq!(graph.introduction_chain("did:c").len(), 2);
+    }
+}
@@ -0,0 +1,187 @@
+// DIAP Rust SDK - 验证信任等级
+// 验证结果标注达到的信任等级：只有签名、签名+缓存的DID文档、完整ZKP+新获取的文档；
+// 应用可以按主题和消息类型配置最低等级，低风险操作接受较低等级，敏感操作要求最高等级
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::pubsub_authenticator::PubSubMessageType;
+use crate::topic_pattern::TopicPattern;
+
+/// 验证结果的信任等级（从低到高）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TrustLevel {
+    /// 只验证了签名（公钥来自验证提示或did:key，未获取DID文档）
+    SignatureOnly,
+
+    /// 签名 + 缓存中的DID文档
+    SignaturePlusCachedDoc,
+
+    /// 签名 + 本次新获取的DID文档 + ZKP绑定证明
+    FullZkpFreshDoc,
+}
+
+impl TrustLevel {
+    /// 是否满足要求的等级
+    pub fn satisfies(self, required: TrustLevel) -> bool {
+        self >= required
+    }
+}
+
+impl std::fmt::Display for TrustLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TrustLevel::SignatureOnly => "仅签名",
+            TrustLevel::SignaturePlusCachedDoc => "签名+缓存文档",
+            TrustLevel::FullZkpFreshDoc => "完整ZKP+最新文档",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 按主题和消息类型要求的最低信任等级（同时匹配多条时取最高）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustPolicy {
+    /// 未匹配任何规则时的最低等级（None表示不限制）
+    #[serde(default)]
+    pub default_level: Option<TrustLevel>,
+
+    /// 主题名称或通配模式 -> 最低等级
+    #[serde(default)]
+    pub topics: HashMap<String, TrustLevel>,
+
+    /// 消息类型（见 message_type_key）-> 最低等级
+    #[serde(default)]
+    pub message_types: HashMap<String, TrustLevel>,
+}
+
+impl TrustPolicy {
+    /// 创建空策略（不限制）
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置默认最低等级
+    pub fn with_default(mut self, level: TrustLevel) -> Self {
+        self.default_level = Some(level);
+        self
+    }
+
+    /// 要求主题（或通配模式）达到的最低等级
+    pub fn require_for_topic(mut self, topic: &str, level: TrustLevel) -> Result<Self> {
+        TopicPattern::parse(topic)?;
+        self.topics.insert(topic.to_string(), level);
+        Ok(self)
+    }
+
+    /// 要求消息类型达到的最低等级
+    pub fn require_for_message_type(mut self, message_type: &PubSubMessageType, level: TrustLevel) -> Self {
+        self.message_types.insert(message_type_key(message_type), level);
+        self
+    }
+
+    /// 消息需要的最低等级
+    pub fn required_level(&self, topic: &str, message_type: &PubSubMessageType) -> Option<TrustLevel> {
+        let by_topic = self.topics.iter()
+            .filter(|(pattern, _)| TopicPattern::parse(pattern).is_ok_and(|pattern| pattern.matches(topic)))
+            .map(|(_, level)| *level);
+        let by_type = self.message_types.get(&message_type_key(message_type)).copied();
+
+        by_topic.chain(by_type).max().or(self.default_level)
+    }
+}
+
+/// 消息类型在策略中的键（自定义类型使用其名称）
+pub fn message_type_key(message_type: &PubSubMessageType) -> String {
+    match message_type {
+        PubSubMessageType::Custom(kind) => kind.clone(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_level() {
+        let policy = TrustPolicy::new()
+            .with_default(TrustLevel::SignatureOnly)
+            .require_for_topic("payments/*", TrustLevel::FullZkpFreshDoc).unwrap()
+            .require_for_topic("chat", TrustLevel::SignaturePlusCachedDoc).unwrap()
+            .require_for_message_type(&PubSubMessageType::Custom("lease".to_string()), TrustLevel::SignaturePlusCachedDoc)
+            .require_for_message_type(&PubSubMessageType::ResourceRequest, TrustLevel::FullZkpFreshDoc);
+
+        assert_eq!(policy.required_level("payments/eu", &PubSubMessageType::Heartbeat), Some(TrustLevel::FullZkpFreshDoc));
+        assert_eq!(policy.required_level("chat", &PubSubMessageType::Heartbeat), Some(TrustLevel::SignaturePlusCachedDoc));
+        assert_eq!(policy.required_level("chat", &PubSubMessageType::ResourceRequest), Some(TrustLevel::FullZkpFreshDoc));
+        assert_eq!(
+            policy.required_level("other", &PubSubMessageType::Custom("lease".to_string())),
+            Some(TrustLevel::SignaturePlusCachedDoc)
+        );
+        assert_eq!(policy.required_level("other", &PubSubMessageType::Heartbeat), Some(TrustLevel::SignatureOnly));
+        assert_eq!(TrustPolicy::new().required_level("other", &PubSubMessageType::Heartbeat), None);
+
+        assert!(TrustLevel::FullZkpFreshDoc.satisfies(TrustLevel::SignaturePlusCachedDoc));
+        assert!(!TrustLevel::SignatureOnly.satisfies(TrustLevel::SignaturePlusCachedDoc));
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_enforces_policy() {
+        use crate::did_resolver::DIDResolver;
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::{CallbackSigner, KeyPair};
+        use crate::pubsub_authenticator::PubsubAuthenticator;
+        use crate::verification_hint::{HintSource, VerificationHint, DEFAULT_HINT_TTL};
+        use std::sync::Arc;
+
+        struct StaticHint(VerificationHint);
+
+        #[async_trait::async_trait]
+        impl HintSource for StaticHint {
+            async fn lookup(&self, _did: &str) -> Result<Option<VerificationHint>> {
+                Ok(Some(self.0.clone()))
+            }
+        }
+
+        // 没有网关可用：只能通过验证提示得到“仅签名”等级
+        let keypair = KeyPair::generate().unwrap();
+        let document = DIDResolver::resolve_did_key(&keypair.did).unwrap();
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let hint = VerificationHint::sign(&keypair, &document, "QmCurrent", now, DEFAULT_HINT_TTL).unwrap();
+        let client = IpfsClient::new_public_only(1);
+        for gateway in client.public_gateways() {
+            client.remove_gateway(&gateway);
+        }
+        let receiver = PubsubAuthenticator::new(IdentityManager::new(client), None, None)
+            .with_verification_hints(Arc::new(StaticHint(hint)));
+        receiver.set_trust_policy(
+            TrustPolicy::new().require_for_topic("payments/*", TrustLevel::SignaturePlusCachedDoc).unwrap()
+        ).await;
+
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+        let sender = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None);
+        sender.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmCurrent".to_string()).await.unwrap();
+
+        let message = sender.create_authenticated_message("chat", PubSubMessageType::Heartbeat, b"hi", None).await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(verification.verified, "{:?}", verification.details);
+        assert_eq!(verification.trust_level, Some(TrustLevel::SignatureOnly));
+        assert!(!verification.meets(TrustLevel::SignaturePlusCachedDoc));
+
+        let message = sender.create_authenticated_message("payments/eu", PubSubMessageType::Heartbeat, b"pay", None).await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d.contains("信任等级不足")));
+
+        // 要求最高等级时不使用提示，必须重新获取DID文档
+        receiver.set_trust_policy(TrustPolicy::new().with_default(TrustLevel::FullZkpFreshDoc)).await;
+        let message = sender.create_authenticated_message("chat", PubSubMessageType::Heartbeat, b"hi", None).await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(!verification.verified);
+        assert!(!verification.details.iter().any(|d| d.contains("验证提示")));
+        assert_eq!(verification.trust_level, None);
+    }
+}
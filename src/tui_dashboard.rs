@@ -0,0 +1,196 @@
+// DIAP Rust SDK - 终端仪表盘（diap top）
+// 定时读取本地管理接口，展示对端、主题消息速率、验证失败和资源占用；按 q 或 Esc 退出
+
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+use std::io::Stdout;
+use std::time::Duration;
+
+use crate::admin_api::{fetch_status, AgentStatus};
+use crate::connection_manager::PeerState;
+
+/// 运行仪表盘直到用户退出
+pub async fn run(addr: &str, token: Option<&str>, refresh: Duration) -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = event_loop(&mut terminal, addr, token, refresh).await;
+
+    // 无论成功与否都恢复终端
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+async fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    addr: &str,
+    token: Option<&str>,
+    refresh: Duration,
+) -> Result<()> {
+    loop {
+        let status = fetch_status(addr, token).await.map_err(|e| e.to_string());
+        terminal.draw(|frame| draw(frame, addr, &status))?;
+
+        if event::poll(refresh)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, addr: &str, status: &Result<AgentStatus, String>) {
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => {
+            let message = Paragraph::new(vec![
+                Line::from(format!("无法连接管理接口 {}", addr)),
+                Line::from(e.as_str()),
+                Line::from("按 q 退出"),
+            ])
+            .style(Style::default().fg(Color::Red))
+            .block(Block::default().title(" diap top ").borders(Borders::ALL));
+            frame.render_widget(message, frame.size());
+            return;
+        }
+    };
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(6), Constraint::Length(8)])
+        .split(frame.size());
+    let middle = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(rows[1]);
+
+    draw_header(frame, rows[0], addr, status);
+    draw_peers(frame, middle[0], status);
+    draw_topics(frame, middle[1], status);
+    draw_failures(frame, rows[2], status);
+}
+
+fn draw_header(frame: &mut Frame, area: Rect, addr: &str, status: &AgentStatus) {
+    let resources = &status.resources;
+    let memory = resources.rss_bytes
+        .map(|bytes| format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)))
+        .unwrap_or_else(|| "-".to_string());
+    let cpu = resources.cpu_seconds.map(|s| format!("{:.1}s", s)).unwrap_or_else(|| "-".to_string());
+    let threads = resources.threads.map(|t| t.to_string()).unwrap_or_else(|| "-".to_string());
+
+    let header = Paragraph::new(vec![
+        Line::from(vec![
+            Span::styled("DID ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(status.did.as_deref().unwrap_or("-")),
+        ]),
+        Line::from(format!(
+            "运行 {}  |  内存 {}  CPU {}  线程 {}  |  SDK {}",
+            format_uptime(status.uptime_secs), memory, cpu, threads, status.version
+        )),
+    ])
+    .block(Block::default().title(format!(" diap top — {} ", addr)).borders(Borders::ALL));
+    frame.render_widget(header, area);
+}
+
+fn draw_peers(frame: &mut Frame, area: Rect, status: &AgentStatus) {
+    let rows = status.peers.iter().map(|peer| {
+        let color = match peer.state {
+            PeerState::Connected => Color::Green,
+            PeerState::Dialing => Color::Yellow,
+            PeerState::Disconnected => Color::Gray,
+            PeerState::GaveUp => Color::Red,
+        };
+        Row::new(vec![
+            shorten(&peer.peer_id, 16),
+            peer.did.as_deref().map(|did| shorten(did, 24)).unwrap_or_default(),
+            format!("{:?}", peer.state),
+            peer.latency_ms.map(|ms| format!("{}ms", ms)).unwrap_or_default(),
+        ])
+        .style(Style::default().fg(color))
+    });
+
+    let connected = status.peers.iter().filter(|p| p.state == PeerState::Connected).count();
+    let table = Table::new(rows, [
+        Constraint::Length(17),
+        Constraint::Min(10),
+        Constraint::Length(12),
+        Constraint::Length(8),
+    ])
+    .header(Row::new(vec!["PeerID", "DID", "状态", "延迟"]).style(Style::default().add_modifier(Modifier::BOLD)))
+    .block(Block::default()
+        .title(format!(" 对端 {}/{} ", connected, status.peers.len()))
+        .borders(Borders::ALL));
+    frame.render_widget(table, area);
+}
+
+fn draw_topics(frame: &mut Frame, area: Rect, status: &AgentStatus) {
+    let rows = status.topics.iter().map(|topic| {
+        Row::new(vec![
+            format!("{}{}", if topic.subscribed { "● " } else { "  " }, topic.topic),
+            format!("{:.1}/min", topic.messages_per_minute),
+            topic.total_messages.to_string(),
+        ])
+    });
+
+    let table = Table::new(rows, [Constraint::Min(10), Constraint::Length(12), Constraint::Length(10)])
+        .header(Row::new(vec!["主题", "速率", "累计"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(Block::default().title(" 主题 ").borders(Borders::ALL));
+    frame.render_widget(table, area);
+}
+
+fn draw_failures(frame: &mut Frame, area: Rect, status: &AgentStatus) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = status.recent_failures.iter()
+        .rev()
+        .take(visible)
+        .map(|failure| Line::from(format!(
+            "{}  {}  {}  {}",
+            failure.at,
+            failure.topic,
+            shorten(&failure.from_did, 24),
+            failure.reasons.join("; "),
+        )))
+        .collect();
+
+    let style = if status.verification_failures > 0 {
+        Style::default().fg(Color::Red)
+    } else {
+        Style::default()
+    };
+    let failures = Paragraph::new(lines)
+        .style(style)
+        .block(Block::default()
+            .title(format!(" 验证失败 {} （q 退出） ", status.verification_failures))
+            .borders(Borders::ALL));
+    frame.render_widget(failures, area);
+}
+
+fn shorten(value: &str, max: usize) -> String {
+    if value.chars().count() <= max {
+        return value.to_string();
+    }
+    let tail: String = value.chars().rev().take(max - 1).collect::<Vec<_>>().into_iter().rev().collect();
+    format!("…{}", tail)
+}
+
+fn format_uptime(secs: u64) -> String {
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
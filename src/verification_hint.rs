@@ -0,0 +1,293 @@
+// DIAP Rust SDK - DHT验证提示
+// 智能体把签名的精简验证提示（公钥、文档哈希、当前CID、过期时间）发布到Kademlia DHT，
+// 同一网络中的验证方可以直接用提示验证消息，不必经过IPFS网关获取DID文档；
+// 提示缺失、过期或CID不一致时回退到完整解析
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use libp2p::kad::RecordKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::constants::network_params;
+use crate::did_builder::DIDDocument;
+use crate::key_manager::{KeyPair, SignedEnvelope, Signer};
+
+/// 默认验证提示有效期
+pub const DEFAULT_HINT_TTL: Duration = Duration::from_secs(3600);
+
+/// 签名的验证提示
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationHint {
+    /// 智能体DID
+    pub did: String,
+
+    /// Ed25519公钥（hex）
+    pub public_key: String,
+
+    /// DID文档哈希（上传内容的SHA-256，hex）
+    pub document_hash: String,
+
+    /// 当前DID文档CID
+    pub cid: String,
+
+    /// 签发时间（秒）
+    pub issued_at: u64,
+
+    /// 过期时间（秒）
+    pub expires_at: u64,
+
+    /// DID签名（base64）
+    pub signature: String,
+}
+
+impl VerificationHint {
+    /// 为当前DID文档签发验证提示
+    pub fn sign(signer: &dyn Signer, document: &DIDDocument, cid: &str, issued_at: u64, ttl: Duration) -> Result<Self> {
+        if document.id != signer.did() {
+            anyhow::bail!("DID文档与签名者不一致: {}", document.id);
+        }
+        Self {
+            did: signer.did(),
+            public_key: hex::encode(signer.public_key()),
+            document_hash: document_hash(document)?,
+            cid: cid.to_string(),
+            issued_at,
+            expires_at: issued_at + ttl.as_secs(),
+            signature: String::new(),
+        }
+        .signed_by(signer)
+    }
+
+    /// 验证签名，并检查公钥与did:key一致
+    pub fn verify(&self) -> Result<bool> {
+        let did_key = KeyPair::public_key_from_did_key(&self.did)?;
+        if hex::encode(did_key) != self.public_key {
+            return Ok(false);
+        }
+        SignedEnvelope::verify(self)
+    }
+
+    /// 是否已过期
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// 提示是否与DID文档内容一致
+    pub fn matches_document(&self, document: &DIDDocument) -> Result<bool> {
+        Ok(document.id == self.did && document_hash(document)? == self.document_hash)
+    }
+
+    /// 公钥字节
+    pub fn public_key_bytes(&self) -> Result<[u8; 32]> {
+        let bytes = hex::decode(&self.public_key).context("解码公钥失败")?;
+        bytes.try_into().map_err(|_| anyhow::anyhow!("公钥长度错误"))
+    }
+}
+
+impl SignedEnvelope for VerificationHint {
+    const KIND: &'static str = "验证提示";
+
+    fn signer_did(&self) -> &str {
+        &self.did
+    }
+
+    fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut String {
+        &mut self.signature
+    }
+}
+
+/// DID文档哈希（与上传到IPFS的内容一致）
+pub fn document_hash(document: &DIDDocument) -> Result<String> {
+    Ok(hex::encode(Sha256::digest(crate::cid_compute::document_bytes(document)?)))
+}
+
+/// 验证提示的DHT键
+pub fn verification_hint_key(did: &str) -> RecordKey {
+    let mut hasher = Sha256::new();
+    hasher.update(network_params().verification_hint_key_prefix().as_bytes());
+    hasher.update(did.as_bytes());
+    RecordKey::new(&hasher.finalize().to_vec())
+}
+
+/// 验证提示来源
+#[async_trait]
+pub trait HintSource: Send + Sync {
+    /// 查找DID的验证提示（未找到时返回None，调用方负责检查签名和有效期）
+    async fn lookup(&self, did: &str) -> Result<Option<VerificationHint>>;
+}
+
+/// 基于DHT的验证提示发布与查询
+#[cfg(feature = "node")]
+pub struct DhtHints {
+    dht: std::sync::Arc<dyn crate::agent_discovery::DhtBackend>,
+}
+
+#[cfg(feature = "node")]
+impl DhtHints {
+    /// 创建验证提示服务
+    pub fn new(dht: std::sync::Arc<dyn crate::agent_discovery::DhtBackend>) -> Self {
+        Self { dht }
+    }
+
+    /// 发布验证提示
+    pub async fn publish(&self, hint: &VerificationHint) -> Result<()> {
+        self.dht.put_record(verification_hint_key(&hint.did), hint.to_bytes()?).await?;
+        log::info!("📌 已发布验证提示: {} -> {}", hint.did, hint.cid);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "node")]
+#[async_trait]
+impl HintSource for DhtHints {
+    /// 返回签名有效的最新提示
+    async fn lookup(&self, did: &str) -> Result<Option<VerificationHint>> {
+        let records = self.dht.get_records(verification_hint_key(did)).await?;
+        let latest = records
+            .iter()
+            .filter_map(|data| VerificationHint::from_bytes(data).ok())
+            .filter(|hint| hint.did == did && hint.verify().unwrap_or(false))
+            .max_by_key(|hint| hint.issued_at);
+        Ok(latest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did_resolver::DIDResolver;
+
+    #[test]
+    fn test_hint_signature() {
+        let keypair = KeyPair::generate().unwrap();
+        let document = DIDResolver::resolve_did_key(&keypair.did).unwrap();
+        let hint = VerificationHint::sign(&keypair, &document, "QmCurrent", 1_000, Duration::from_secs(60)).unwrap();
+        assert!(hint.verify().unwrap());
+        assert!(hint.matches_document(&document).unwrap());
+        assert!(!hint.is_expired(1_059));
+        assert!(hint.is_expired(1_060));
+
+        // 替换公钥或CID都会使签名失效
+        let mut forged = hint.clone();
+        forged.public_key = hex::encode(KeyPair::generate().unwrap().public_key);
+        assert!(!forged.verify().unwrap());
+        let mut forged = hint;
+        forged.cid = "QmOther".to_string();
+        assert!(!forged.verify().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_uses_hints() {
+        use crate::clock::MockClock;
+        use crate::identity_manager::IdentityManager;
+        use crate::ipfs_client::IpfsClient;
+        use crate::key_manager::CallbackSigner;
+        use crate::nonce_manager::NonceManager;
+        use crate::pubsub_authenticator::{PubSubMessageType, PubsubAuthenticator};
+        use std::collections::HashMap;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct StaticHints(Mutex<HashMap<String, VerificationHint>>);
+
+        #[async_trait]
+        impl HintSource for StaticHints {
+            async fn lookup(&self, did: &str) -> Result<Option<VerificationHint>> {
+                Ok(self.0.lock().unwrap().get(did).cloned())
+            }
+        }
+
+        let clock = MockClock::new(1_000_000);
+        let keypair = KeyPair::generate().unwrap();
+        let document = DIDResolver::resolve_did_key(&keypair.did).unwrap();
+        let hints = Arc::new(StaticHints::default());
+
+        // 没有网关可用：只有提示能让验证通过
+        let client = IpfsClient::new_public_only(1);
+        for gateway in client.public_gateways() {
+            client.remove_gateway(&gateway);
+        }
+        let nonces = NonceManager::new_with_clock(Some(300), Some(60), Arc::new(clock.clone()));
+        let receiver = PubsubAuthenticator::new(IdentityManager::new(client), Some(nonces), None)
+            .with_clock(Arc::new(clock.clone()))
+            .with_verification_hints(hints.clone());
+
+        let hint = VerificationHint::sign(&keypair, &document, "QmCurrent", 1_000_000, DEFAULT_HINT_TTL).unwrap();
+        let signer = CallbackSigner::new(keypair.public_key, Arc::new(move |data| keypair.sign(data))).unwrap();
+        let sender = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(1)), None, None)
+            .with_clock(Arc::new(clock.clone()));
+        sender.set_local_signer(Arc::new(signer), libp2p::PeerId::random(), "QmCurrent".to_string()).await.unwrap();
+
+        let message = sender.create_authenticated_message("tasks", PubSubMessageType::Heartbeat, b"ping", None).await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(!verification.verified, "没有提示时需要完整解析");
+
+        hints.0.lock().unwrap().insert(hint.did.clone(), hint.clone());
+        let message = sender.create_authenticated_message("tasks", PubSubMessageType::Heartbeat, b"ping", None).await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(verification.verified, "{:?}", verification.details);
+        assert!(verification.details.iter().any(|d| d.contains("验证提示")));
+
+        // 提示过期后回退到完整解析
+        clock.advance(DEFAULT_HINT_TTL);
+        let message = sender.create_authenticated_message("tasks", PubSubMessageType::Heartbeat, b"ping", None).await.unwrap();
+        let verification = receiver.verify_message(&message).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d.contains("已过期")));
+    }
+
+    #[cfg(feature = "node")]
+    #[tokio::test]
+    async fn test_dht_hints_pick_latest_valid() {
+        use crate::agent_discovery::DhtBackend;
+        use libp2p::PeerId;
+        use std::collections::{HashMap, HashSet};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct MemoryDht(Mutex<HashMap<RecordKey, Vec<Vec<u8>>>>);
+
+        #[async_trait]
+        impl DhtBackend for MemoryDht {
+            fn local_peer_id(&self) -> PeerId {
+                PeerId::random()
+            }
+
+            async fn start_providing(&self, _key: RecordKey) -> Result<()> {
+                Ok(())
+            }
+
+            async fn get_providers(&self, _key: RecordKey) -> Result<HashSet<PeerId>> {
+                Ok(HashSet::new())
+            }
+
+            async fn put_record(&self, key: RecordKey, value: Vec<u8>) -> Result<()> {
+                self.0.lock().unwrap().entry(key).or_default().push(value);
+                Ok(())
+            }
+
+            async fn get_records(&self, key: RecordKey) -> Result<Vec<Vec<u8>>> {
+                Ok(self.0.lock().unwrap().get(&key).cloned().unwrap_or_default())
+            }
+        }
+
+        let keypair = KeyPair::generate().unwrap();
+        let document = DIDResolver::resolve_did_key(&keypair.did).unwrap();
+        let hints = DhtHints::new(Arc::new(MemoryDht::default()));
+        assert!(hints.lookup(&keypair.did).await.unwrap().is_none());
+
+        hints.publish(&VerificationHint::sign(&keypair, &document, "QmOld", 100, DEFAULT_HINT_TTL).unwrap()).await.unwrap();
+        hints.publish(&VerificationHint::sign(&keypair, &document, "QmNew", 200, DEFAULT_HINT_TTL).unwrap()).await.unwrap();
+        let mut forged = VerificationHint::sign(&keypair, &document, "QmNew", 300, DEFAULT_HINT_TTL).unwrap();
+        forged.cid = "QmEvil".to_string();
+        hints.publish(&forged).await.unwrap();
+
+        assert_eq!(hints.lookup(&keypair.did).await.unwrap().unwrap().cid, "QmNew");
+    }
+}
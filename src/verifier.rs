@@ -0,0 +1,204 @@
+// DIAP Rust SDK - 只读验证器
+// 网关、索引器等只需要验证DIAP身份和消息的使用方不需要密钥生成、网络服务和证明生成，
+// DiapVerifier把DID解析、签名验证和证明验证收拢到一个类型里；
+// 配合 default-features = false, features = ["verifier"] 构建时不会引入节点相关的依赖
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::did_builder::{get_did_document_from_cid, DIDDocument};
+use crate::did_cache::DIDCache;
+use crate::did_commitment::CidCommitment;
+use crate::did_resolver::DIDSignatureVerifier;
+use crate::did_revocation::RevocationRegistry;
+use crate::error::DiapResult;
+use crate::identity_manager::{IdentityManager, IdentityVerification};
+use crate::ipfs_client::IpfsClient;
+use crate::latency_budget::LatencyBudget;
+use crate::noir_verifier::{NoirVerificationResult, NoirVerifier};
+use crate::pubsub_authenticator::{AuthenticatedMessage, MessageVerification, PubsubAuthenticator};
+use crate::transparency_log::TransparencyPolicy;
+
+/// 只读验证器
+pub struct DiapVerifier {
+    /// 身份验证（CID + 绑定证明）
+    identity_manager: IdentityManager,
+
+    /// CID -> DID文档 缓存（与消息验证共享）
+    documents: DIDCache,
+
+    /// 按DID验证签名（did:key）
+    signatures: DIDSignatureVerifier,
+
+    /// 消息验证（不设置本地身份，不经过熔断、限流和信誉）
+    authenticator: PubsubAuthenticator,
+
+    /// Noir电路目录（None表示只做简化验证）
+    circuits_path: Option<PathBuf>,
+}
+
+impl DiapVerifier {
+    /// 创建验证器
+    pub fn new(ipfs_client: IpfsClient) -> Self {
+        let signatures = DIDSignatureVerifier::new(DIDCache::default());
+        Self::with_parts(IdentityManager::new(ipfs_client), DIDCache::default(), signatures, None)
+    }
+
+    /// 使用吊销注册表（已吊销的DID验证不通过）
+    pub fn with_revocation_registry(mut self, registry: RevocationRegistry) -> Self {
+        self.identity_manager.set_revocation_registry(registry.clone());
+        let signatures = self.signatures.with_revocation_registry(registry);
+        Self::with_parts(self.identity_manager, self.documents, signatures, self.circuits_path)
+    }
+
+    /// 使用轮换信任策略（轮换后的DID文档需满足透明日志要求）
+    pub fn with_transparency_policy(mut self, policy: TransparencyPolicy) -> Self {
+        self.identity_manager.set_transparency_policy(policy);
+        Self::with_parts(self.identity_manager, self.documents, self.signatures, self.circuits_path)
+    }
+
+    /// 使用指定的DID文档缓存
+    pub fn with_cache(self, cache: DIDCache) -> Self {
+        Self::with_parts(self.identity_manager, cache, self.signatures, self.circuits_path)
+    }
+
+    /// 使用Noir电路目录验证证明（需要nargo）
+    pub fn with_circuits_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.circuits_path = Some(path.into());
+        self
+    }
+
+    fn with_parts(
+        identity_manager: IdentityManager,
+        documents: DIDCache,
+        signatures: DIDSignatureVerifier,
+        circuits_path: Option<PathBuf>,
+    ) -> Self {
+        let authenticator = PubsubAuthenticator::new(identity_manager.clone(), None, Some(documents.clone()));
+        Self {
+            identity_manager,
+            signatures,
+            documents,
+            authenticator,
+            circuits_path,
+        }
+    }
+
+    /// 解析did:key
+    pub fn resolve_did(&self, did: &str) -> DiapResult<DIDDocument> {
+        self.signatures.resolve(did)
+    }
+
+    /// 按CID解析DID文档（优先使用缓存）
+    pub async fn resolve_cid(&self, cid: &str) -> DiapResult<DIDDocument> {
+        if let Some(document) = self.documents.get(cid) {
+            return Ok(document);
+        }
+        let document = get_did_document_from_cid(self.identity_manager.ipfs_client(), cid).await?;
+        if let Err(e) = self.documents.put(cid.to_string(), document.clone()) {
+            log::warn!("缓存DID文档失败: {}", e);
+        }
+        Ok(document)
+    }
+
+    /// 验证签名是否由did的认证密钥生成
+    pub fn verify_signature(&self, did: &str, data: &[u8], signature: &[u8]) -> DiapResult<bool> {
+        self.signatures.verify(did, data, signature)
+    }
+
+    /// 验证DID与CID的绑定证明
+    pub async fn verify_identity(&self, cid: &str, proof: &[u8], nonce: &[u8]) -> Result<IdentityVerification> {
+        self.identity_manager.verify_identity_with_zkp(cid, proof, nonce).await
+    }
+
+    /// 在延迟预算内验证DID与CID的绑定证明（超出预算时结果为provisional）
+    pub async fn verify_identity_with_budget(&self, cid: &str, proof: &[u8], nonce: &[u8], budget: &LatencyBudget) -> Result<IdentityVerification> {
+        self.identity_manager.verify_identity_with_budget(cid, proof, nonce, budget).await
+    }
+
+    /// 验证基于预提交CID的身份（文档可获取之前为待定）
+    pub async fn verify_committed_identity(&self, commitment: &CidCommitment, proof: &[u8], nonce: &[u8]) -> Result<IdentityVerification> {
+        self.identity_manager.verify_committed_identity(commitment, proof, nonce).await
+    }
+
+    /// 验证Noir证明；未配置电路目录或nargo不可用时使用简化验证
+    pub async fn verify_proof(&self, proof: &[u8], public_inputs: &[u8], expected_output: &str) -> Result<NoirVerificationResult> {
+        if let Some(path) = &self.circuits_path {
+            let verifier = NoirVerifier::new(path.to_string_lossy().into_owned());
+            if verifier.check_noir_available().await {
+                return verifier.verify_proof(proof, public_inputs, expected_output).await;
+            }
+            log::warn!("⚠️  nargo不可用，使用简化验证");
+        }
+        NoirVerifier::new(String::new()).verify_proof_simplified(proof, public_inputs, expected_output).await
+    }
+
+    /// 验证认证消息（签名、nonce、时间戳、证明、吊销状态）
+    pub async fn verify_message(&self, message: &AuthenticatedMessage) -> Result<MessageVerification> {
+        self.authenticator.verify_message_unscreened(message, None).await
+    }
+
+    /// 在延迟预算内验证认证消息（超出预算时结果为provisional）
+    pub async fn verify_message_with_budget(&self, message: &AuthenticatedMessage, budget: &LatencyBudget) -> Result<MessageVerification> {
+        self.authenticator.verify_message_unscreened(message, Some(budget)).await
+    }
+
+    /// DID文档缓存
+    pub fn cache(&self) -> &DIDCache {
+        &self.documents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did_resolver::DIDResolver;
+    use crate::key_manager::{CallbackSigner, KeyPair};
+    use libp2p::PeerId;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_verify_signature_and_proof() {
+        let verifier = DiapVerifier::new(IpfsClient::new_public_only(5));
+        let keypair = KeyPair::generate().unwrap();
+        let signature = keypair.sign(b"hello").unwrap();
+
+        assert_eq!(verifier.resolve_did(&keypair.did).unwrap().id, keypair.did);
+        assert!(verifier.verify_signature(&keypair.did, b"hello", &signature).unwrap());
+        assert!(!verifier.verify_signature(&keypair.did, b"tampered", &signature).unwrap());
+
+        assert!(verifier.verify_proof(b"proof", b"[]", "output").await.unwrap().is_valid);
+        assert!(!verifier.verify_proof(b"", b"[]", "output").await.unwrap().is_valid);
+    }
+
+    #[tokio::test]
+    async fn test_verify_message_without_admission() {
+        let alice = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+        let cache = DIDCache::new(None, None);
+        cache.put("cid-mallory".to_string(), DIDResolver::resolve_did_key(&mallory.did).unwrap()).unwrap();
+        let verifier = DiapVerifier::new(IpfsClient::new_public_only(5)).with_cache(cache);
+
+        let inner = mallory.clone();
+        let signer = CallbackSigner::new(mallory.public_key, Arc::new(move |data: &[u8]| inner.sign(data))).unwrap();
+        let sender = PubsubAuthenticator::new(IdentityManager::new(IpfsClient::new_public_only(5)), None, None);
+        sender.set_local_signer(Arc::new(signer), PeerId::random(), "cid-mallory".to_string()).await.unwrap();
+
+        // 冒用alice的消息被拒绝，只读验证器不记录熔断和信誉
+        let mut spoofed = sender.create_simple_message("tasks", "spoofed").await.unwrap();
+        spoofed.from_did = alice.did.clone();
+        let verification = verifier.verify_message(&spoofed).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d.contains("与发送者DID不符")));
+        assert!(verifier.authenticator.circuit_breakers().snapshot().is_empty());
+        assert!(verifier.authenticator.reputation().is_empty());
+
+        // 未配置主题时要求ZKP证明；mallory以自己的身份发送、缺少证明时同样只返回结果
+        let genuine = sender.create_simple_message("tasks", "hello").await.unwrap();
+        let verification = verifier.verify_message(&genuine).await.unwrap();
+        assert!(!verification.verified);
+        assert!(verification.details.iter().any(|d| d == "✓ 消息签名验证通过"));
+        assert!(verifier.authenticator.circuit_breakers().snapshot().is_empty());
+        assert!(verifier.authenticator.reputation().is_empty());
+    }
+}
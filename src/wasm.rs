@@ -0,0 +1,224 @@
+// DIAP Rust SDK - WebAssembly绑定（wasm32-unknown-unknown，浏览器智能体）
+// 浏览器中没有tokio运行时、文件系统和libp2p传输，这里只导出纯计算和HTTP部分：
+// 密钥生成、did:key文档构建、签名验证，以及经HTTP网关取回DID文档并按CID校验内容
+//
+// 构建: cargo build --target wasm32-unknown-unknown --no-default-features --features wasm
+// （或 wasm-pack build --target web -- --no-default-features --features wasm）
+
+use base64::{Engine as _, engine::general_purpose};
+use ed25519_dalek::{Signer as _, SigningKey};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::cid_compute;
+use crate::constants;
+use crate::did_key;
+
+/// 默认公共网关（与IpfsClient一致）
+const DEFAULT_GATEWAYS: [&str; 3] = ["https://ipfs.io", "https://dweb.link", "https://cloudflare-ipfs.com"];
+
+/// 新生成的身份
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeneratedIdentity {
+    did: String,
+    public_key: String,
+    private_key: String,
+}
+
+fn js_error(message: impl std::fmt::Display) -> JsError {
+    JsError::new(&message.to_string())
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    let json = serde_json::to_string(value).map_err(js_error)?;
+    js_sys::JSON::parse(&json).map_err(|_| js_error("转换为JS对象失败"))
+}
+
+fn decode_private_key(private_key: &str) -> Result<SigningKey, JsError> {
+    let bytes = general_purpose::STANDARD.decode(private_key).map_err(js_error)?;
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| js_error("私钥必须是32字节"))?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// 生成新的Ed25519身份，返回 { did, publicKey, privateKey }（密钥为base64）
+#[wasm_bindgen(js_name = generateIdentity)]
+pub fn generate_identity() -> Result<JsValue, JsError> {
+    let mut secret = [0u8; 32];
+    getrandom::getrandom(&mut secret).map_err(js_error)?;
+    let signing_key = SigningKey::from_bytes(&secret);
+    let public_key = signing_key.verifying_key().to_bytes();
+    to_js(&GeneratedIdentity {
+        did: did_key::did_key_from_public_key(&public_key),
+        public_key: general_purpose::STANDARD.encode(public_key),
+        private_key: general_purpose::STANDARD.encode(signing_key.to_bytes()),
+    })
+}
+
+/// 由私钥（base64）得到did:key
+#[wasm_bindgen(js_name = didFromPrivateKey)]
+pub fn did_from_private_key(private_key: &str) -> Result<String, JsError> {
+    let signing_key = decode_private_key(private_key)?;
+    Ok(did_key::did_key_from_public_key(&signing_key.verifying_key().to_bytes()))
+}
+
+/// 用私钥（base64）签名，返回base64签名
+#[wasm_bindgen]
+pub fn sign(private_key: &str, data: &[u8]) -> Result<String, JsError> {
+    let signing_key = decode_private_key(private_key)?;
+    Ok(general_purpose::STANDARD.encode(signing_key.sign(data).to_bytes()))
+}
+
+/// 用did:key中的公钥验证base64签名
+#[wasm_bindgen(js_name = verifySignature)]
+pub fn verify_signature(did: &str, data: &[u8], signature: &str) -> Result<bool, JsError> {
+    let signature = general_purpose::STANDARD.decode(signature).map_err(js_error)?;
+    did_key::verify_with_did_key(did, data, &signature).map_err(js_error)
+}
+
+/// 构建did:key的DID文档（与DIDResolver::resolve_did_key的结构一致，不含密钥协商方法）
+#[wasm_bindgen(js_name = buildDidDocument)]
+pub fn build_did_document(did: &str) -> Result<JsValue, JsError> {
+    to_js(&did_key_document(did).map_err(js_error)?)
+}
+
+/// 检查DID文档（JSON字符串）是否属于该did:key：id一致，且authentication引用的验证方法使用该公钥
+#[wasm_bindgen(js_name = verifyDidDocument)]
+pub fn verify_did_document(did: &str, document: &str) -> Result<bool, JsError> {
+    let document: serde_json::Value = serde_json::from_str(document).map_err(js_error)?;
+    document_binds_did(&document, did).map_err(js_error)
+}
+
+/// 计算内容按 `ipfs add` 默认参数上传后的CID
+#[wasm_bindgen(js_name = computeCid)]
+pub fn compute_cid(content: &[u8]) -> Result<String, JsError> {
+    cid_compute::compute_content_cid(content, &cid_compute::CidOptions::default()).map_err(js_error)
+}
+
+/// 基于HTTP网关的只读IPFS客户端（浏览器中用fetch实现）
+#[wasm_bindgen]
+pub struct WasmIpfsClient {
+    gateways: Vec<String>,
+    http: reqwest::Client,
+}
+
+#[wasm_bindgen]
+impl WasmIpfsClient {
+    /// 使用指定网关创建（为空时使用默认公共网关）
+    #[wasm_bindgen(constructor)]
+    pub fn new(gateways: Vec<String>) -> Self {
+        let gateways = if gateways.is_empty() {
+            DEFAULT_GATEWAYS.iter().map(|gateway| gateway.to_string()).collect()
+        } else {
+            gateways.into_iter().map(|gateway| gateway.trim_end_matches('/').to_string()).collect()
+        };
+        Self { gateways, http: reqwest::Client::new() }
+    }
+
+    /// 依次尝试网关取回内容，只接受与CID匹配的字节（网关不可信）
+    pub fn fetch(&self, cid: String) -> js_sys::Promise {
+        let gateways = self.gateways.clone();
+        let http = self.http.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let content = fetch_verified(&http, &gateways, &cid).await.map_err(JsValue::from)?;
+            Ok(js_sys::Uint8Array::from(content.as_slice()).into())
+        })
+    }
+
+    /// 取回并验证DID文档：内容与CID匹配，且文档属于期望的did:key；返回文档对象
+    #[wasm_bindgen(js_name = resolveDidDocument)]
+    pub fn resolve_did_document(&self, cid: String, expected_did: String) -> js_sys::Promise {
+        let gateways = self.gateways.clone();
+        let http = self.http.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let content = fetch_verified(&http, &gateways, &cid).await.map_err(JsValue::from)?;
+            let document: serde_json::Value = serde_json::from_slice(&content)
+                .map_err(|e| JsValue::from(js_error(format!("解析DID文档失败: {}", e))))?;
+            if !document_binds_did(&document, &expected_did).map_err(|e| JsValue::from(js_error(e)))? {
+                return Err(js_error(format!("DID文档不属于 {}", expected_did)).into());
+            }
+            to_js(&document).map_err(JsValue::from)
+        })
+    }
+}
+
+async fn fetch_verified(http: &reqwest::Client, gateways: &[String], cid: &str) -> Result<Vec<u8>, JsError> {
+    let mut last_error = String::from("没有可用的网关");
+    for gateway in gateways {
+        let url = format!("{}/ipfs/{}", gateway, cid);
+        let content = match http.get(&url).send().await.and_then(|response| response.error_for_status()) {
+            Ok(response) => match response.bytes().await {
+                Ok(bytes) => bytes.to_vec(),
+                Err(e) => {
+                    last_error = format!("{}: {}", gateway, e);
+                    continue;
+                }
+            },
+            Err(e) => {
+                last_error = format!("{}: {}", gateway, e);
+                continue;
+            }
+        };
+        if cid_compute::content_matches_cid(&content, cid).map_err(js_error)? {
+            return Ok(content);
+        }
+        last_error = format!("{}: 返回的内容与CID不匹配", gateway);
+    }
+    Err(js_error(format!("获取 {} 失败: {}", cid, last_error)))
+}
+
+fn did_key_document(did: &str) -> anyhow::Result<serde_json::Value> {
+    let public_key = did_key::public_key_from_did_key(did)?;
+    let key_id = format!("{}#key-1", did);
+    Ok(serde_json::json!({
+        "@context": constants::network_params().did_contexts.clone(),
+        "id": did,
+        "verificationMethod": [{
+            "id": key_id,
+            "type": "Ed25519VerificationKey2020",
+            "controller": did,
+            "publicKeyMultibase": did_key::public_key_multibase(&public_key),
+        }],
+        "authentication": [key_id],
+        "created": "",
+    }))
+}
+
+fn document_binds_did(document: &serde_json::Value, did: &str) -> anyhow::Result<bool> {
+    let expected_key = did_key::public_key_multibase(&did_key::public_key_from_did_key(did)?);
+    if document["id"].as_str() != Some(did) {
+        return Ok(false);
+    }
+    let authentication: Vec<&str> = document["authentication"].as_array()
+        .map(|ids| ids.iter().filter_map(|id| id.as_str()).collect())
+        .unwrap_or_default();
+    Ok(document["verificationMethod"].as_array().is_some_and(|methods| {
+        methods.iter().any(|method| {
+            method["id"].as_str().is_some_and(|id| authentication.contains(&id))
+                && method["publicKeyMultibase"].as_str() == Some(expected_key.as_str())
+        })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_did_key_document_binding() {
+        let keypair = crate::key_manager::KeyPair::generate().unwrap();
+        let document = did_key_document(&keypair.did).unwrap();
+        assert!(document_binds_did(&document, &keypair.did).unwrap());
+
+        // 与原生解析出的文档等价
+        let native = serde_json::to_value(crate::did_resolver::DIDResolver::resolve_did_key(&keypair.did).unwrap()).unwrap();
+        assert_eq!(document["verificationMethod"], native["verificationMethod"]);
+        assert!(document_binds_did(&native, &keypair.did).unwrap());
+
+        let other = crate::key_manager::KeyPair::generate().unwrap();
+        assert!(!document_binds_did(&document, &other.did).unwrap());
+        let mut swapped = document.clone();
+        swapped["verificationMethod"][0]["publicKeyMultibase"] = serde_json::json!(did_key::public_key_multibase(&other.public_key));
+        assert!(!document_binds_did(&swapped, &keypair.did).unwrap());
+    }
+}
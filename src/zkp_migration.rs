@@ -0,0 +1,303 @@
+// DIAP Rust SDK - Arkworks证明迁移层
+// 旧版Arkworks证明仍然可以验证，但默认只生成Noir（或UltraHonk）证明；
+// 每次验证到旧版证明都会记录弃用遥测（按发送者统计），
+// 迁移报告汇总仍在发送旧版证明的智能体，供运维逐个升级
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::clock::{SharedClock, system_clock};
+use crate::pubsub_authenticator::AuthenticatedMessage;
+
+/// 旧版Arkworks后端生成的证明前缀
+pub const LEGACY_ARKWORKS_PROOF_PREFIX: &[u8] = b"ARKWORKS_PROOF_";
+
+/// 未知来源（验证时未提供发送者DID）的统计键
+const UNKNOWN_SOURCE: &str = "unknown";
+
+/// 证明格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofFormat {
+    /// 旧版Arkworks证明（已弃用，只验证不生成）
+    LegacyArkworks,
+    /// Noir系列后端的证明
+    Noir,
+}
+
+impl ProofFormat {
+    /// 按证明内容识别格式
+    pub fn detect(proof: &[u8]) -> Self {
+        if proof.starts_with(LEGACY_ARKWORKS_PROOF_PREFIX) {
+            ProofFormat::LegacyArkworks
+        } else {
+            ProofFormat::Noir
+        }
+    }
+}
+
+/// 仍在发送旧版证明的来源
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyProofSource {
+    /// 发送者DID（未知时为"unknown"）
+    pub source: String,
+
+    /// 旧版证明数量
+    pub count: u64,
+
+    /// 首次出现时间
+    pub first_seen: u64,
+
+    /// 最近出现时间
+    pub last_seen: u64,
+}
+
+/// 弃用遥测（可克隆，克隆体共享统计）
+#[derive(Clone)]
+pub struct DeprecationTelemetry {
+    sources: Arc<Mutex<HashMap<String, LegacyProofSource>>>,
+    total: Arc<AtomicU64>,
+    clock: SharedClock,
+}
+
+impl DeprecationTelemetry {
+    /// 创建遥测
+    pub fn new() -> Self {
+        Self::new_with_clock(system_clock())
+    }
+
+    /// 使用指定时间源创建
+    pub fn new_with_clock(clock: SharedClock) -> Self {
+        Self {
+            sources: Arc::new(Mutex::new(HashMap::new())),
+            total: Arc::new(AtomicU64::new(0)),
+            clock,
+        }
+    }
+
+    /// 记录一次旧版证明验证（每个来源首次出现时输出弃用警告）
+    pub fn record(&self, source: Option<&str>) {
+        let now = self.clock.now_secs();
+        let source = source.unwrap_or(UNKNOWN_SOURCE);
+        self.total.fetch_add(1, Ordering::Relaxed);
+
+        let mut sources = self.sources.lock().unwrap();
+        match sources.get_mut(source) {
+            Some(entry) => {
+                entry.count += 1;
+                entry.last_seen = now;
+            }
+            None => {
+                log::warn!("⚠️ 收到已弃用的Arkworks证明（来源: {}），请升级到Noir证明", source);
+                sources.insert(source.to_string(), LegacyProofSource {
+                    source: source.to_string(),
+                    count: 1,
+                    first_seen: now,
+                    last_seen: now,
+                });
+            }
+        }
+    }
+
+    /// 旧版证明总数
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// 各来源统计（数量多的在前）
+    pub fn snapshot(&self) -> Vec<LegacyProofSource> {
+        let mut sources: Vec<LegacyProofSource> = self.sources.lock().unwrap().values().cloned().collect();
+        sources.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.source.cmp(&b.source)));
+        sources
+    }
+}
+
+impl Default for DeprecationTelemetry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 迁移报告
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationReport {
+    /// 生成时间
+    pub generated_at: u64,
+
+    /// 扫描的消息数（由遥测生成时为0）
+    pub messages_scanned: u64,
+
+    /// 旧版证明数
+    pub legacy_proofs: u64,
+
+    /// Noir证明数
+    pub noir_proofs: u64,
+
+    /// 没有证明的消息数
+    pub without_proof: u64,
+
+    /// 仍在发送旧版证明的来源（数量多的在前）
+    pub legacy_sources: Vec<LegacyProofSource>,
+}
+
+impl MigrationReport {
+    /// 扫描消息（例如归档或抓取的流量）生成报告
+    pub fn from_messages<'a>(messages: impl IntoIterator<Item = &'a AuthenticatedMessage>, now: u64) -> Self {
+        let mut report = Self::empty(now);
+        let mut sources: HashMap<String, LegacyProofSource> = HashMap::new();
+        for message in messages {
+            report.messages_scanned += 1;
+            if message.zkp_proof.is_empty() {
+                report.without_proof += 1;
+                continue;
+            }
+            match ProofFormat::detect(&message.zkp_proof) {
+                ProofFormat::Noir => report.noir_proofs += 1,
+                ProofFormat::LegacyArkworks => {
+                    report.legacy_proofs += 1;
+                    let entry = sources.entry(message.from_did.clone()).or_insert_with(|| LegacyProofSource {
+                        source: message.from_did.clone(),
+                        count: 0,
+                        first_seen: message.timestamp,
+                        last_seen: message.timestamp,
+                    });
+                    entry.count += 1;
+                    entry.first_seen = entry.first_seen.min(message.timestamp);
+                    entry.last_seen = entry.last_seen.max(message.timestamp);
+                }
+            }
+        }
+        report.legacy_sources = sources.into_values().collect();
+        report.legacy_sources.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.source.cmp(&b.source)));
+        report
+    }
+
+    /// 由运行中的弃用遥测生成报告
+    pub fn from_telemetry(telemetry: &DeprecationTelemetry, now: u64) -> Self {
+        Self {
+            legacy_proofs: telemetry.total(),
+            legacy_sources: telemetry.snapshot(),
+            ..Self::empty(now)
+        }
+    }
+
+    /// 是否已没有旧版证明
+    pub fn is_fully_migrated(&self) -> bool {
+        self.legacy_proofs == 0
+    }
+
+    /// 可读摘要
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "ZKP迁移报告: 扫描{}条消息，Noir证明{}，旧版Arkworks证明{}，无证明{}",
+            self.messages_scanned, self.noir_proofs, self.legacy_proofs, self.without_proof
+        )];
+        if self.is_fully_migrated() {
+            lines.push("✅ 未发现旧版证明，可以关闭arkworks-zkp特性".to_string());
+        } else {
+            lines.push(format!("⚠️ {}个来源仍在发送旧版证明，需升级后重新签发:", self.legacy_sources.len()));
+            for source in &self.legacy_sources {
+                lines.push(format!(
+                    "  {}  {}条  首次{}  最近{}",
+                    source.source, source.count, source.first_seen, source.last_seen
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn empty(now: u64) -> Self {
+        Self {
+            generated_at: now,
+            messages_scanned: 0,
+            legacy_proofs: 0,
+            noir_proofs: 0,
+            without_proof: 0,
+            legacy_sources: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::noir_universal::{NoirBackend, NoirProverInputs, UniversalNoirManager};
+    use crate::pubsub_authenticator::PubSubMessageType;
+
+    fn message(from: &str, proof: &[u8], timestamp: u64) -> AuthenticatedMessage {
+        AuthenticatedMessage {
+            message_id: format!("{}-{}", from, timestamp),
+            message_type: PubSubMessageType::Heartbeat,
+            from_did: from.to_string(),
+            to_did: None,
+            from_peer_id: String::new(),
+            did_cid: String::new(),
+            topic: "t".to_string(),
+            content: Vec::new(),
+            nonce: String::new(),
+            zkp_proof: proof.to_vec(),
+            signature: Vec::new(),
+            timestamp,
+            not_before: None,
+            capability: None,
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_report_from_messages() {
+        let messages = vec![
+            message("did:key:old", b"ARKWORKS_PROOF_1_2_3_4", 10),
+            message("did:key:old", b"ARKWORKS_PROOF_1_2_3_4", 30),
+            message("did:key:new", b"noir-proof", 20),
+            message("did:key:new", b"", 25),
+        ];
+        let report = MigrationReport::from_messages(&messages, 100);
+        assert_eq!((report.messages_scanned, report.legacy_proofs, report.noir_proofs, report.without_proof), (4, 2, 1, 1));
+        assert_eq!(report.legacy_sources, vec![LegacyProofSource {
+            source: "did:key:old".to_string(),
+            count: 2,
+            first_seen: 10,
+            last_seen: 30,
+        }]);
+        assert!(!report.is_fully_migrated());
+        assert!(report.summary().contains("did:key:old"));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_proofs_verify_but_are_not_generated() {
+        let inputs = NoirProverInputs {
+            expected_did_hash: "1".to_string(),
+            public_key_hash: "2".to_string(),
+            nonce_hash: "3".to_string(),
+            expected_output: "4".to_string(),
+        };
+
+        // Arkworks后端默认拒绝生成证明
+        let mut legacy = UniversalNoirManager::with_backend(NoirBackend::Arkworks).await.unwrap();
+        assert!(legacy.generate_proof(&inputs).await.is_err());
+        legacy.set_legacy_generation(true);
+        let old_proof = legacy.generate_proof(&inputs).await.unwrap();
+        assert_eq!(ProofFormat::detect(&old_proof.proof), ProofFormat::LegacyArkworks);
+
+        // 当前后端仍能验证旧版证明，并记录弃用遥测
+        let clock = MockClock::new(1_000);
+        let mut manager = UniversalNoirManager::with_backend(NoirBackend::Simplified).await.unwrap();
+        manager.set_deprecation_telemetry(DeprecationTelemetry::new_with_clock(Arc::new(clock)));
+        let result = manager.verify_proof_from("did:key:old", &old_proof.proof, &old_proof.public_inputs).await.unwrap();
+        assert!(result.is_valid);
+        manager.verify_proof(&old_proof.proof, &old_proof.public_inputs).await.unwrap();
+
+        let new_proof = manager.generate_proof(&inputs).await.unwrap();
+        assert_eq!(ProofFormat::detect(&new_proof.proof), ProofFormat::Noir);
+        manager.verify_proof_from("did:key:new", &new_proof.proof, &new_proof.public_inputs).await.unwrap();
+
+        let report = MigrationReport::from_telemetry(manager.deprecation_telemetry(), 2_000);
+        assert_eq!(report.legacy_proofs, 2);
+        let sources: Vec<&str> = report.legacy_sources.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(sources, vec!["did:key:old", "unknown"]);
+    }
+}